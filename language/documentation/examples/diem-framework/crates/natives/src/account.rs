@@ -6,11 +6,8 @@ use move_binary_format::errors::PartialVMResult;
 use move_core_types::account_address::AccountAddress;
 use move_vm_runtime::native_functions::NativeContext;
 use move_vm_types::{
-    gas_schedule::NativeCostIndex,
-    loaded_data::runtime_types::Type,
-    natives::function::{native_gas, NativeResult},
-    pop_arg,
-    values::Value,
+    gas_schedule::NativeCostIndex, loaded_data::runtime_types::Type,
+    natives::function::NativeResult, pop_arg, values::Value,
 };
 use smallvec::smallvec;
 use std::collections::VecDeque;
@@ -24,7 +21,7 @@ pub fn native_create_signer(
     debug_assert!(arguments.len() == 1);
 
     let address = pop_arg!(arguments, AccountAddress);
-    let cost = native_gas(context.cost_table(), NativeCostIndex::CREATE_SIGNER, 0);
+    let cost = context.native_gas(NativeCostIndex::CREATE_SIGNER, 0);
     Ok(NativeResult::ok(cost, smallvec![Value::signer(address)]))
 }
 
@@ -38,6 +35,6 @@ pub fn native_destroy_signer(
     debug_assert!(ty_args.is_empty());
     debug_assert!(arguments.len() == 1);
 
-    let cost = native_gas(context.cost_table(), NativeCostIndex::DESTROY_SIGNER, 0);
+    let cost = context.native_gas(NativeCostIndex::DESTROY_SIGNER, 0);
     Ok(NativeResult::ok(cost, smallvec![]))
 }
@@ -5,11 +5,11 @@
 pub mod account;
 pub mod signature;
 
-use move_core_types::{account_address::AccountAddress, identifier::Identifier};
-use move_vm_runtime::native_functions::{NativeFunction, NativeFunctionTable};
+use move_core_types::account_address::AccountAddress;
+use move_vm_runtime::native_functions::{self, NativeFunctionTable, StatelessNativeFunction};
 
 pub fn all_natives(diem_framework_addr: AccountAddress) -> NativeFunctionTable {
-    const NATIVES: &[(&str, &str, NativeFunction)] = &[
+    const NATIVES: &[(&str, &str, StatelessNativeFunction)] = &[
         // TODO: Remove once/if DPN is moved over to use the core framework
         (
             "DiemAccount",
@@ -33,16 +33,5 @@ pub fn all_natives(diem_framework_addr: AccountAddress) -> NativeFunctionTable {
         ),
         ("Account", "create_signer", account::native_create_signer),
     ];
-    NATIVES
-        .iter()
-        .cloned()
-        .map(|(module_name, func_name, func)| {
-            (
-                diem_framework_addr,
-                Identifier::new(module_name).unwrap(),
-                Identifier::new(func_name).unwrap(),
-                func,
-            )
-        })
-        .collect()
+    native_functions::make_table(diem_framework_addr, NATIVES)
 }
@@ -6,11 +6,8 @@ use diem_crypto::{ed25519, traits::*};
 use move_binary_format::errors::PartialVMResult;
 use move_vm_runtime::native_functions::NativeContext;
 use move_vm_types::{
-    gas_schedule::NativeCostIndex,
-    loaded_data::runtime_types::Type,
-    natives::function::{native_gas, NativeResult},
-    pop_arg,
-    values::Value,
+    gas_schedule::NativeCostIndex, loaded_data::runtime_types::Type,
+    natives::function::NativeResult, pop_arg, values::Value,
 };
 use smallvec::smallvec;
 use std::{collections::VecDeque, convert::TryFrom};
@@ -25,11 +22,7 @@ pub fn native_ed25519_publickey_validation(
 
     let key_bytes = pop_arg!(arguments, Vec<u8>);
 
-    let cost = native_gas(
-        context.cost_table(),
-        NativeCostIndex::ED25519_VALIDATE_KEY,
-        key_bytes.len(),
-    );
+    let cost = context.native_gas(NativeCostIndex::ED25519_VALIDATE_KEY, key_bytes.len());
 
     // This deserialization performs point-on-curve and small subgroup checks
     let valid = ed25519::Ed25519PublicKey::try_from(&key_bytes[..]).is_ok();
@@ -48,11 +41,7 @@ pub fn native_ed25519_signature_verification(
     let pubkey = pop_arg!(arguments, Vec<u8>);
     let signature = pop_arg!(arguments, Vec<u8>);
 
-    let cost = native_gas(
-        context.cost_table(),
-        NativeCostIndex::ED25519_VERIFY,
-        msg.len(),
-    );
+    let cost = context.native_gas(NativeCostIndex::ED25519_VERIFY, msg.len());
 
     let sig = match ed25519::Ed25519Signature::try_from(signature.as_slice()) {
         Ok(sig) => sig,
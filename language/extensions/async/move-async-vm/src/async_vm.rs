@@ -11,7 +11,7 @@ use std::{
 use move_binary_format::errors::{Location, PartialVMError, PartialVMResult, VMError, VMResult};
 use move_core_types::{
     account_address::AccountAddress,
-    effects::{ChangeSet, Event},
+    effects::{ChangeSet, Event, Op},
     gas_schedule::GasAlgebra,
     identifier::Identifier,
     language_storage::{ModuleId, StructTag, TypeTag},
@@ -183,7 +183,8 @@ impl<'r, 'l, S: MoveResolver> AsyncSession<'r, 'l, S> {
             .vm_session
             .get_data_store()
             .load_resource(actor_addr, &state_type)
-            .map_err(partial_vm_error_to_async)?;
+            .map_err(partial_vm_error_to_async)?
+            .0;
         if state.exists().map_err(partial_vm_error_to_async)? {
             return Err(async_extension_error(format!(
                 "actor `{}` already exists at `{}`",
@@ -226,7 +227,7 @@ impl<'r, 'l, S: MoveResolver> AsyncSession<'r, 'l, S> {
                         &mut change_set,
                         actor_addr,
                         actor.state_tag.clone(),
-                        return_values.remove(0).0,
+                        Op::New(return_values.remove(0).0),
                     )
                     .map_err(partial_vm_error_to_async)?;
                     let async_ext = native_extensions.remove::<AsyncExtension>();
@@ -276,7 +277,8 @@ impl<'r, 'l, S: MoveResolver> AsyncSession<'r, 'l, S> {
             .vm_session
             .get_data_store()
             .load_resource(actor_addr, &state_type)
-            .map_err(partial_vm_error_to_async)?;
+            .map_err(partial_vm_error_to_async)?
+            .0;
         let actor_state = actor_state_global
             .borrow_global()
             .and_then(|v| v.value_as::<Reference>())
@@ -318,7 +320,7 @@ impl<'r, 'l, S: MoveResolver> AsyncSession<'r, 'l, S> {
                             &mut change_set,
                             actor_addr,
                             actor.state_tag.clone(),
-                            mutable_reference_outputs.remove(0).1,
+                            Op::Modify(mutable_reference_outputs.remove(0).1),
                         )
                         .map_err(partial_vm_error_to_async)?;
                     }
@@ -366,10 +368,10 @@ fn publish_actor_state(
     change_set: &mut ChangeSet,
     actor_addr: AccountAddress,
     state_tag: StructTag,
-    state: Vec<u8>,
+    state: Op<Vec<u8>>,
 ) -> PartialVMResult<()> {
     change_set
-        .publish_resource(actor_addr, state_tag, state)
+        .add_resource_op(actor_addr, state_tag, state)
         .map_err(|err| partial_extension_error(format!("cannot publish actor state: {}", err)))
 }
 
@@ -8,12 +8,12 @@ use move_binary_format::errors::PartialVMResult;
 use move_core_types::{account_address::AccountAddress, identifier::Identifier};
 use move_vm_runtime::{
     native_functions,
-    native_functions::{NativeContext, NativeFunction},
+    native_functions::{NativeContext, NativeFunction, StatelessNativeFunction},
 };
 use move_vm_types::{
     gas_schedule::NativeCostIndex,
     loaded_data::runtime_types::Type,
-    natives::function::{native_gas, NativeResult},
+    natives::function::NativeResult,
     pop_arg,
     values::{Value, Vector},
 };
@@ -39,7 +39,7 @@ pub struct AsyncExtension {
 pub fn actor_natives(
     async_addr: AccountAddress,
 ) -> Vec<(AccountAddress, Identifier, Identifier, NativeFunction)> {
-    const NATIVES: &[(&str, &str, NativeFunction)] = &[
+    const NATIVES: &[(&str, &str, StatelessNativeFunction)] = &[
         ("Actor", "self", native_self),
         ("Actor", "virtual_time", native_virtual_time),
         ("Runtime", "send__0", native_send),
@@ -60,7 +60,7 @@ fn native_self(
     mut _ty_args: Vec<Type>,
     mut _args: VecDeque<Value>,
 ) -> PartialVMResult<NativeResult> {
-    let cost = native_gas(context.cost_table(), SELF_COST_INDEX, 1);
+    let cost = context.native_gas(SELF_COST_INDEX, 1);
     let ext = context.extensions().get::<AsyncExtension>();
     Ok(NativeResult::ok(
         cost,
@@ -82,7 +82,7 @@ fn native_send(
     let message_hash = pop_arg!(args, u64);
     let target = pop_arg!(args, AccountAddress);
     ext.sent.push((target, message_hash, bcs_args));
-    let cost = native_gas(context.cost_table(), SEND_COST_INDEX, args.len());
+    let cost = context.native_gas(SEND_COST_INDEX, args.len());
     Ok(NativeResult::ok(cost, smallvec![]))
 }
 
@@ -91,7 +91,7 @@ fn native_virtual_time(
     mut _ty_args: Vec<Type>,
     mut _args: VecDeque<Value>,
 ) -> PartialVMResult<NativeResult> {
-    let cost = native_gas(context.cost_table(), EPOCH_TIME_INDEX, 1);
+    let cost = context.native_gas(EPOCH_TIME_INDEX, 1);
     let ext = context.extensions().get::<AsyncExtension>();
     Ok(NativeResult::ok(
         cost,
@@ -182,7 +182,8 @@ impl Harness {
 
     fn commit_changeset(&self, changeset: ChangeSet) {
         for (addr, change) in changeset.into_inner() {
-            for (struct_tag, val) in change.into_inner().1 {
+            for (struct_tag, op) in change.into_inner().1 {
+                let val = op.ok();
                 self.log(format!(
                     "  commit 0x{}::{}::{}[0x{}] := {}",
                     struct_tag.address.short_str_lossless(),
@@ -0,0 +1,173 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    mvhashmap::{writes, Incarnation, MVHashMap, StateKey, TxnIndex, Version},
+    view::BlockView,
+};
+use move_core_types::{
+    effects::{ChangeSet, Event},
+    resolver::MoveResolver,
+};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+
+/// The output of executing a transaction
+pub trait TransactionOutput: Send {
+    /// Changes made to the state by the transaction
+    fn change_set(&self) -> &ChangeSet;
+}
+
+impl TransactionOutput for (ChangeSet, Vec<Event>) {
+    fn change_set(&self) -> &ChangeSet {
+        &self.0
+    }
+}
+
+/// Executes a single transaction on a view of the state.
+///
+/// A transaction may be executed several times, on different views of the state, before its
+/// output is committed. Execution must hence not have side effects beyond its output, and must
+/// be deterministic given the values it reads from the view.
+pub trait ExecutorTask: Sync {
+    type Txn: Sync;
+    type Output: TransactionOutput;
+    type Error: Send;
+
+    /// Executes `txn` on `view`. An error aborts the execution of the whole block, and should be
+    /// reserved to failures which are not the transaction's fault (e.g. a storage error): a
+    /// transaction failing in the VM should instead be reported in its output.
+    fn execute_transaction<S: MoveResolver>(
+        &self,
+        view: &S,
+        txn: &Self::Txn,
+    ) -> Result<Self::Output, Self::Error>;
+}
+
+/// The result of an incarnation of a transaction, along with what it read and wrote
+struct Execution<T: ExecutorTask> {
+    incarnation: Incarnation,
+    reads: Vec<(StateKey, Option<Version>)>,
+    written_keys: Vec<StateKey>,
+    publishes_modules: bool,
+    result: Result<T::Output, T::Error>,
+}
+
+impl<T: ExecutorTask> Execution<T> {
+    /// Whether the values read are still the last ones written before the transaction
+    fn is_valid(&self, versioned: &MVHashMap, txn_idx: TxnIndex) -> bool {
+        self.reads.iter().all(|(key, version)| {
+            versioned.read(key, txn_idx).map(|(version, _)| version) == *version
+        })
+    }
+}
+
+/// Executes blocks of transactions, in parallel on `concurrency_level` threads
+pub struct BlockExecutor<T> {
+    task: T,
+    thread_pool: ThreadPool,
+}
+
+impl<T: ExecutorTask> BlockExecutor<T> {
+    pub fn new(task: T, concurrency_level: usize) -> Self {
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(concurrency_level)
+            .build()
+            .expect("failed to create the thread pool of the block executor");
+        Self { task, thread_pool }
+    }
+
+    /// Executes the transactions of the block one after the other on top of `base`, returning
+    /// their outputs, or the first error.
+    pub fn execute_block_sequential<S: MoveResolver>(
+        &self,
+        base: &S,
+        txns: &[T::Txn],
+    ) -> Result<Vec<T::Output>, T::Error> {
+        let versioned = MVHashMap::new();
+        txns.iter()
+            .enumerate()
+            .map(|(txn_idx, txn)| self.execute(base, &versioned, txn_idx, 0, &[], txn).result)
+            .collect()
+    }
+
+    /// Executes the transactions of the block in parallel on top of `base`, returning the same
+    /// outputs (or error) as `execute_block_sequential`.
+    pub fn execute_block<S: MoveResolver + Sync>(
+        &self,
+        base: &S,
+        txns: &[T::Txn],
+    ) -> Result<Vec<T::Output>, T::Error> {
+        let versioned = MVHashMap::new();
+
+        // Execute all transactions speculatively, each one seeing the writes of the transactions
+        // preceding it which happened to be executed already.
+        let executions: Vec<Execution<T>> = self.thread_pool.install(|| {
+            txns.par_iter()
+                .enumerate()
+                .map(|(txn_idx, txn)| self.execute(base, &versioned, txn_idx, 0, &[], txn))
+                .collect()
+        });
+        if executions
+            .iter()
+            .any(|execution| execution.publishes_modules)
+        {
+            return self.execute_block_sequential(base, txns);
+        }
+
+        // Commit the executions in order. All the transactions preceding the one being committed
+        // have their final writes in `versioned`, so an execution whose reads are still valid is
+        // the one of a sequential execution, and executing again an invalid one yields it.
+        let mut outputs = Vec::with_capacity(txns.len());
+        for (txn_idx, (txn, execution)) in txns.iter().zip(executions).enumerate() {
+            let execution = if execution.is_valid(&versioned, txn_idx) {
+                execution
+            } else {
+                let execution = self.execute(
+                    base,
+                    &versioned,
+                    txn_idx,
+                    execution.incarnation + 1,
+                    &execution.written_keys,
+                    txn,
+                );
+                if execution.publishes_modules {
+                    return self.execute_block_sequential(base, txns);
+                }
+                execution
+            };
+            outputs.push(execution.result?);
+        }
+        Ok(outputs)
+    }
+
+    /// Executes an incarnation of a transaction and records its writes, replacing the ones of
+    /// the previous incarnation (to `previous_keys`).
+    fn execute<S: MoveResolver>(
+        &self,
+        base: &S,
+        versioned: &MVHashMap,
+        txn_idx: TxnIndex,
+        incarnation: Incarnation,
+        previous_keys: &[StateKey],
+        txn: &T::Txn,
+    ) -> Execution<T> {
+        let view = BlockView::new(base, versioned, txn_idx);
+        let result = self.task.execute_transaction(&view, txn);
+        let writes = match &result {
+            Ok(output) => writes(output.change_set()),
+            Err(_) => vec![],
+        };
+        let written_keys: Vec<_> = writes.iter().map(|(key, _)| key.clone()).collect();
+        let publishes_modules = written_keys
+            .iter()
+            .any(|key| matches!(key, StateKey::Module(_)));
+        versioned.write(txn_idx, incarnation, previous_keys, writes);
+        Execution {
+            incarnation,
+            reads: view.into_reads(),
+            written_keys,
+            publishes_modules,
+            result,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A crate executing blocks of transactions in parallel on top of the Move VM, following the
+//! optimistic approach of Block-STM.
+//!
+//! The transactions of a block are first all executed speculatively in parallel. Each of them
+//! reads the state through a multi-version data structure holding the writes of the transactions
+//! executed so far, and records the versions of the values it read. The executions are then
+//! validated and committed in the order of the block: a transaction whose reads do not match the
+//! writes of the transactions preceding it conflicted with them, and is executed again. Outputs
+//! are hence the same as the ones of executing the block sequentially.
+//!
+//! Executing a single transaction is left to an [`ExecutorTask`], typically running a session of
+//! a `MoveVM` on the view of the state it is given. As the code cache of a `MoveVM` is shared by
+//! its sessions, a block in which a transaction publishes modules is executed again sequentially.
+
+mod executor;
+mod mvhashmap;
+mod view;
+
+pub use executor::{BlockExecutor, ExecutorTask, TransactionOutput};
+pub use mvhashmap::StateKey;
@@ -0,0 +1,92 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::ChangeSet,
+    language_storage::{ModuleId, StructTag},
+};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+
+/// A location in the state: a module or a resource stored under an account
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StateKey {
+    Module(ModuleId),
+    Resource(AccountAddress, StructTag),
+}
+
+/// Index of a transaction in the block
+pub(crate) type TxnIndex = usize;
+/// Number of times a transaction was executed before
+pub(crate) type Incarnation = usize;
+/// Version of a value written by a transaction of the block
+pub(crate) type Version = (TxnIndex, Incarnation);
+
+/// Returns the values written by a change set, `None` meaning the value is deleted
+pub(crate) fn writes(change_set: &ChangeSet) -> Vec<(StateKey, Option<Vec<u8>>)> {
+    let modules = change_set.modules().map(|(addr, name, op)| {
+        (
+            StateKey::Module(ModuleId::new(addr, name.clone())),
+            op.ok().map(|blob| blob.to_vec()),
+        )
+    });
+    let resources = change_set.resources().map(|(addr, tag, op)| {
+        (
+            StateKey::Resource(addr, tag.clone()),
+            op.ok().map(|blob| blob.to_vec()),
+        )
+    });
+    modules.chain(resources).collect()
+}
+
+/// Multi-version data structure holding, for each location of the state, the values written to
+/// it by each transaction of the block.
+pub(crate) struct MVHashMap {
+    data: RwLock<HashMap<StateKey, BTreeMap<TxnIndex, (Incarnation, Option<Vec<u8>>)>>>,
+}
+
+impl MVHashMap {
+    pub(crate) fn new() -> Self {
+        Self {
+            data: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the value of `key` last written by a transaction preceding `txn_idx` in the block,
+    /// along with its version, or `None` if no such transaction wrote it (in which case the value
+    /// is the one in storage).
+    pub(crate) fn read(
+        &self,
+        key: &StateKey,
+        txn_idx: TxnIndex,
+    ) -> Option<(Version, Option<Vec<u8>>)> {
+        let data = self.data.read();
+        data.get(key)?
+            .range(..txn_idx)
+            .next_back()
+            .map(|(idx, (incarnation, value))| ((*idx, *incarnation), value.clone()))
+    }
+
+    /// Records the writes of an incarnation of a transaction, removing the writes of its previous
+    /// incarnation to the keys in `previous_keys`.
+    pub(crate) fn write(
+        &self,
+        txn_idx: TxnIndex,
+        incarnation: Incarnation,
+        previous_keys: &[StateKey],
+        writes: Vec<(StateKey, Option<Vec<u8>>)>,
+    ) {
+        let mut data = self.data.write();
+        for key in previous_keys {
+            if let Some(versions) = data.get_mut(key) {
+                versions.remove(&txn_idx);
+            }
+        }
+        for (key, value) in writes {
+            data.entry(key)
+                .or_insert_with(BTreeMap::new)
+                .insert(txn_idx, (incarnation, value));
+        }
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::mvhashmap::{MVHashMap, StateKey, TxnIndex, Version};
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, MoveResolver, ResourceResolver},
+};
+use std::cell::RefCell;
+
+/// The state as seen by a transaction of the block: the writes of the transactions preceding it,
+/// on top of the storage. Records the version of every value read, `None` for values read from
+/// the storage.
+pub(crate) struct BlockView<'a, S> {
+    base: &'a S,
+    versioned: &'a MVHashMap,
+    txn_idx: TxnIndex,
+    reads: RefCell<Vec<(StateKey, Option<Version>)>>,
+}
+
+impl<'a, S: MoveResolver> BlockView<'a, S> {
+    pub(crate) fn new(base: &'a S, versioned: &'a MVHashMap, txn_idx: TxnIndex) -> Self {
+        Self {
+            base,
+            versioned,
+            txn_idx,
+            reads: RefCell::new(vec![]),
+        }
+    }
+
+    pub(crate) fn into_reads(self) -> Vec<(StateKey, Option<Version>)> {
+        self.reads.into_inner()
+    }
+
+    fn read(
+        &self,
+        key: StateKey,
+        from_base: impl FnOnce() -> Result<Option<Vec<u8>>, S::Err>,
+    ) -> Result<Option<Vec<u8>>, S::Err> {
+        match self.versioned.read(&key, self.txn_idx) {
+            Some((version, value)) => {
+                self.reads.borrow_mut().push((key, Some(version)));
+                Ok(value)
+            }
+            None => {
+                self.reads.borrow_mut().push((key, None));
+                from_base()
+            }
+        }
+    }
+}
+
+impl<'a, S: MoveResolver> ModuleResolver for BlockView<'a, S> {
+    type Error = S::Err;
+
+    fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.read(StateKey::Module(module_id.clone()), || {
+            self.base.get_module(module_id)
+        })
+    }
+}
+
+impl<'a, S: MoveResolver> ResourceResolver for BlockView<'a, S> {
+    type Error = S::Err;
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.read(StateKey::Resource(*address, tag.clone()), || {
+            self.base.get_resource(address, tag)
+        })
+    }
+
+    fn get_resource_group(&self, tag: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        self.base.get_resource_group(tag)
+    }
+
+    fn prefetch_resources(&self, keys: &[(AccountAddress, StructTag)]) -> Result<(), Self::Error> {
+        self.base.prefetch_resources(keys)
+    }
+}
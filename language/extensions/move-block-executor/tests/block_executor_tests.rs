@@ -0,0 +1,186 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_block_executor::{BlockExecutor, ExecutorTask};
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::{ChangeSet, Event, Op},
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, MoveResolver, ResourceResolver},
+};
+use std::{collections::BTreeMap, convert::TryInto};
+
+const NUM_ACCOUNTS: u8 = 4;
+
+/// Transactions on `u64` counters stored under accounts
+enum Txn {
+    /// Increments the counter of the account
+    Increment(AccountAddress),
+    /// Adds the counter of the first account to the one of the second account (wrapping around, as
+    /// long blocks of additions overflow)
+    Add(AccountAddress, AccountAddress),
+    /// Publishes a (fake) module under the account
+    Publish(AccountAddress),
+    /// Fails the execution of the block
+    Fail,
+}
+
+struct CounterTask;
+
+fn counter_tag() -> StructTag {
+    StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new("Counter").unwrap(),
+        type_params: vec![],
+    }
+}
+
+fn read_counter<S: MoveResolver>(view: &S, addr: AccountAddress) -> u64 {
+    view.get_resource(&addr, &counter_tag())
+        .unwrap()
+        .map(|blob| u64::from_le_bytes(blob.as_slice().try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+// Writes the counter of the account, creating it if it does not exist yet
+fn write_counter<S: MoveResolver>(
+    view: &S,
+    change_set: &mut ChangeSet,
+    addr: AccountAddress,
+    value: u64,
+) {
+    let blob = value.to_le_bytes().to_vec();
+    let op = if view.get_resource(&addr, &counter_tag()).unwrap().is_some() {
+        Op::Modify(blob)
+    } else {
+        Op::New(blob)
+    };
+    change_set.add_resource_op(addr, counter_tag(), op).unwrap();
+}
+
+impl ExecutorTask for CounterTask {
+    type Txn = Txn;
+    type Output = (ChangeSet, Vec<Event>);
+    type Error = String;
+
+    fn execute_transaction<S: MoveResolver>(
+        &self,
+        view: &S,
+        txn: &Txn,
+    ) -> Result<Self::Output, String> {
+        let mut change_set = ChangeSet::new();
+        match txn {
+            Txn::Increment(addr) => {
+                let value = read_counter(view, *addr).wrapping_add(1);
+                write_counter(view, &mut change_set, *addr, value);
+            }
+            Txn::Add(from, to) => {
+                let value = read_counter(view, *from).wrapping_add(read_counter(view, *to));
+                write_counter(view, &mut change_set, *to, value);
+            }
+            Txn::Publish(addr) => {
+                change_set
+                    .add_module_op(
+                        ModuleId::new(*addr, Identifier::new("M").unwrap()),
+                        Op::New(vec![]),
+                    )
+                    .unwrap();
+            }
+            Txn::Fail => return Err("failed".to_string()),
+        }
+        Ok((change_set, vec![]))
+    }
+}
+
+/// Minimal storage with no modules or resources
+struct EmptyStorage;
+
+impl ModuleResolver for EmptyStorage {
+    type Error = ();
+
+    fn get_module(&self, _module_id: &ModuleId) -> Result<Option<Vec<u8>>, ()> {
+        Ok(None)
+    }
+}
+
+impl ResourceResolver for EmptyStorage {
+    type Error = ();
+
+    fn get_resource(
+        &self,
+        _address: &AccountAddress,
+        _tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, ()> {
+        Ok(None)
+    }
+}
+
+fn account(i: usize) -> AccountAddress {
+    let mut addr = [0u8; AccountAddress::LENGTH];
+    addr[AccountAddress::LENGTH - 1] = (i % NUM_ACCOUNTS as usize) as u8;
+    AccountAddress::new(addr)
+}
+
+// A block with many conflicts between transactions
+fn block(len: usize) -> Vec<Txn> {
+    (0..len)
+        .map(|i| {
+            if i % 3 == 0 {
+                Txn::Add(account(i), account(i + 1))
+            } else {
+                Txn::Increment(account(i))
+            }
+        })
+        .collect()
+}
+
+// Final value of each counter after applying the outputs in order
+fn final_counters(outputs: Vec<(ChangeSet, Vec<Event>)>) -> BTreeMap<AccountAddress, Vec<u8>> {
+    let mut counters = BTreeMap::new();
+    for (change_set, _) in outputs {
+        for (addr, _, blob) in change_set.resources() {
+            counters.insert(addr, blob.ok().unwrap().to_vec());
+        }
+    }
+    counters
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    let executor = BlockExecutor::new(CounterTask, 4);
+    let txns = block(1000);
+
+    let sequential = executor
+        .execute_block_sequential(&EmptyStorage, &txns)
+        .unwrap();
+    let parallel = executor.execute_block(&EmptyStorage, &txns).unwrap();
+    assert_eq!(parallel, sequential);
+    assert_eq!(final_counters(parallel).len(), NUM_ACCOUNTS as usize);
+}
+
+#[test]
+fn publishing_modules() {
+    let executor = BlockExecutor::new(CounterTask, 4);
+    let mut txns = block(100);
+    txns.insert(50, Txn::Publish(account(0)));
+
+    let sequential = executor
+        .execute_block_sequential(&EmptyStorage, &txns)
+        .unwrap();
+    let parallel = executor.execute_block(&EmptyStorage, &txns).unwrap();
+    assert_eq!(parallel, sequential);
+}
+
+#[test]
+fn failing_transaction() {
+    let executor = BlockExecutor::new(CounterTask, 4);
+    let mut txns = block(100);
+    txns.insert(50, Txn::Fail);
+
+    assert_eq!(
+        executor.execute_block(&EmptyStorage, &txns).unwrap_err(),
+        "failed"
+    );
+}
@@ -8,26 +8,31 @@
 //! See [`README.md`](../README.md) for integration into an adapter.
 
 use better_any::{Tid, TidAble};
-use move_binary_format::errors::{PartialVMError, PartialVMResult};
+use move_binary_format::errors::{Location, PartialVMError, PartialVMResult, VMResult};
 use move_core_types::{
     account_address::AccountAddress,
+    effects::{ChangeSet, Event, Op},
     gas_schedule::{GasAlgebra, GasCarrier, InternalGasUnits},
+    resolver::MoveResolver,
     value::MoveTypeLayout,
     vm_status::StatusCode,
 };
 use move_vm_runtime::{
+    native_extensions::SessionExtension,
     native_functions,
     native_functions::{NativeContext, NativeFunctionTable},
+    session::Session,
 };
 use move_vm_types::{
     loaded_data::runtime_types::Type,
     natives::function::NativeResult,
     pop_arg,
-    values::{GlobalValue, GlobalValueEffect, Reference, StructRef, Value},
+    values::{GlobalValue, Reference, StructRef, Value},
 };
 use sha3::{Digest, Sha3_256};
 use smallvec::smallvec;
 use std::{
+    any::Any,
     cell::RefCell,
     collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque},
     convert::TryInto,
@@ -164,14 +169,14 @@ impl<'a> NativeTableContext<'a> {
             let mut entries = BTreeMap::new();
             for (key, gv) in content {
                 match gv.into_effect()? {
-                    GlobalValueEffect::Deleted => {
+                    Some(Op::Delete) => {
                         entries.insert(key, None);
                     }
-                    GlobalValueEffect::Changed(new_val) => {
+                    Some(Op::New(new_val) | Op::Modify(new_val)) => {
                         let new_bytes = serialize(&value_layout, &new_val)?;
                         entries.insert(key, Some(new_bytes));
                     }
-                    _ => {}
+                    None => {}
                 }
             }
             if !entries.is_empty() {
@@ -186,7 +191,53 @@ impl<'a> NativeTableContext<'a> {
     }
 }
 
+impl<'a> SessionExtension<'a> for NativeTableContext<'a> {
+    type Output = TableChangeSet;
+
+    fn checkpoint(&self) -> PartialVMResult<Box<dyn Any>> {
+        Ok(Box::new(self.table_data.borrow().copy_value()?))
+    }
+
+    fn rollback_to(&mut self, checkpoint: Box<dyn Any>) {
+        let table_data = checkpoint
+            .downcast::<TableData>()
+            .expect("checkpoint of another extension");
+        *self.table_data.get_mut() = *table_data;
+    }
+
+    fn finish(self) -> PartialVMResult<TableChangeSet> {
+        self.into_change_set()
+    }
+}
+
+/// Finishes a session whose extensions include a `NativeTableContext`, returning the table
+/// change set along with the change set and events of the session. Like `Session::finish`, this
+/// must not be called after an invariant violation occurred in the session.
+pub fn finish_session<S: MoveResolver>(
+    session: Session<'_, '_, S>,
+) -> VMResult<(ChangeSet, Vec<Event>, TableChangeSet)> {
+    let (change_set, events, mut extensions) = session.finish_with_extensions()?;
+    let table_change_set = extensions
+        .finish::<NativeTableContext>()
+        .map_err(|e| e.finish(Location::Undefined))?;
+    Ok((change_set, events, table_change_set))
+}
+
 impl TableData {
+    /// Copies the tables, e.g., to restore them when a session is rolled back.
+    fn copy_value(&self) -> PartialVMResult<Self> {
+        let tables = self
+            .tables
+            .iter()
+            .map(|(handle, table)| Ok((*handle, table.copy_value()?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(Self {
+            new_tables: self.new_tables.clone(),
+            removed_tables: self.removed_tables.clone(),
+            tables,
+        })
+    }
+
     /// Gets or creates a new table in the TableData. This initializes information about
     /// the table, like the type layout for keys and values.
     fn get_or_create_table(
@@ -212,6 +263,20 @@ impl TableData {
 }
 
 impl Table {
+    fn copy_value(&self) -> PartialVMResult<Self> {
+        let content = self
+            .content
+            .iter()
+            .map(|(key, gv)| Ok((key.clone(), gv.copy_value()?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(Self {
+            handle: self.handle,
+            key_layout: self.key_layout.clone(),
+            value_layout: self.value_layout.clone(),
+            content,
+        })
+    }
+
     /// Inserts a value into a table.
     fn insert(
         &mut self,
@@ -35,16 +35,20 @@ export class Context {
      *
      * "Registering" the function means that the VS Code machinery will execute it when the command
      * with the given name is requested by the user. The command names themselves are specified in
-     * this extension's `package.json` file, under the key `"contributes.commands"`.
+     * this extension's `package.json` file, under the key `"contributes.commands"` (except for commands
+     * issued by the language server rather than by the user).
      */
-    registerCommand(
+    registerCommand<Args extends Array<unknown>>(
         name: Readonly<string>,
-        command: (context: Readonly<Context>) => Promise<void>,
+        command: (context: Readonly<Context>, ...args: Args) => Promise<void>,
     ): void {
-        const disposable = vscode.commands.registerCommand(`move-analyzer.${name}`, async () => {
-            const com = await command(this);
-            return com;
-        });
+        const disposable = vscode.commands.registerCommand(
+            `move-analyzer.${name}`,
+            async (...args: Args) => {
+                const com = await command(this, ...args);
+                return com;
+            },
+        );
         this.extensionContext.subscriptions.push(disposable);
     }
 
@@ -81,6 +85,10 @@ export class Context {
         const clientOptions: lc.LanguageClientOptions = {
             documentSelector: [{ scheme: 'file', language: 'move' }],
             traceOutputChannel,
+            // Settings affecting how packages are built are passed to the server on startup and
+            // whenever they change.
+            initializationOptions: vscode.workspace.getConfiguration('move-analyzer'),
+            synchronize: { configurationSection: 'move-analyzer' },
         };
 
         const client = new lc.LanguageClient(
@@ -8,6 +8,7 @@ import { Extension } from './extension';
 import { log } from './log';
 import * as childProcess from 'child_process';
 import * as vscode from 'vscode';
+import type * as lc from 'vscode-languageclient';
 
 /**
  * An extension command that displays the version of the server that this extension
@@ -30,6 +31,28 @@ async function serverVersion(context: Readonly<Context>): Promise<void> {
     }
 }
 
+/**
+ * An extension command that displays references to a definition. It is not issued by the user but
+ * by clicking a code lens provided by the server, whose arguments are in the language server
+ * protocol's format and need to be converted before they can be passed to VS Code.
+ */
+async function showReferences(
+    _context: Readonly<Context>,
+    uri: string,
+    position: lc.Position,
+    locations: Array<lc.Location>,
+): Promise<void> {
+    const toRange = (range: lc.Range): vscode.Range => new vscode.Range(
+        range.start.line, range.start.character, range.end.line, range.end.character,
+    );
+    await vscode.commands.executeCommand(
+        'editor.action.showReferences',
+        vscode.Uri.parse(uri),
+        new vscode.Position(position.line, position.character),
+        locations.map((loc) => new vscode.Location(vscode.Uri.parse(loc.uri), toRange(loc.range))),
+    );
+}
+
 /**
  * The entry point to this VS Code extension.
  *
@@ -61,6 +84,8 @@ export function activate(extensionContext: Readonly<vscode.ExtensionContext>): v
 
     // Register handlers for VS Code commands that the user explicitly issues.
     context.registerCommand('serverVersion', serverVersion);
+    // Register handlers for VS Code commands issued by the language server (e.g., via code lenses).
+    context.registerCommand('showReferences', showReferences);
 
     // All other utilities provided by this extension occur via the language server.
     context.startClient();
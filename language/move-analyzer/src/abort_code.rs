@@ -0,0 +1,163 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Abort codes resolved against constants of integer types, so that the user sees which constant
+//! (e.g., `E_NOT_FOUND`) a numeric abort code stands for.
+//!
+//! Codes are shown alongside constants' names in outputs of unit tests and on hover over numeric
+//! literals used as abort codes: arguments of `abort` expressions, second arguments of `assert!`
+//! calls, and codes expected by `#[expected_failure(abort_code = ...)]` attributes.
+
+use move_core_types::value::MoveValue;
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// Returns the value of a constant if it can be used as an abort code
+pub fn abort_code(value: &MoveValue) -> Option<u64> {
+    match value {
+        MoveValue::U8(n) => Some(*n as u64),
+        MoveValue::U64(n) => Some(*n),
+        MoveValue::U128(n) => u64::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// Returns the abort code given by a numeric literal at the given offset of the buffer along with
+/// the byte range of the literal, or `None` if there is no literal used as an abort code there
+pub fn abort_code_at(buffer: &str, offset: usize) -> Option<(u64, usize, usize)> {
+    let is_literal_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let start = buffer[..offset]
+        .rfind(|c: char| !is_literal_char(c))
+        .map_or(0, |i| i + 1);
+    let end = buffer[offset..]
+        .find(|c: char| !is_literal_char(c))
+        .map_or(buffer.len(), |i| offset + i);
+    let code = parse_literal(&buffer[start..end])?;
+    let before = buffer[..start].trim_end();
+    let is_abort_code = ends_with_word(before, "abort")
+        || before
+            .strip_suffix('=')
+            .map_or(false, |b| ends_with_word(b.trim_end(), "abort_code"))
+        || is_assert_code(before);
+    is_abort_code.then(|| (code, start, end))
+}
+
+/// Returns the value of a numeric literal (possibly hexadecimal or suffixed with its type)
+fn parse_literal(literal: &str) -> Option<u64> {
+    if !literal.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let num = ["u128", "u64", "u8"]
+        .iter()
+        .find_map(|suffix| literal.strip_suffix(suffix))
+        .unwrap_or(literal);
+    match num.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => num.parse().ok(),
+    }
+}
+
+/// Returns true if the given text ends with the given word (rather than with an identifier ending
+/// with it)
+fn ends_with_word(text: &str, word: &str) -> bool {
+    text.strip_suffix(word).map_or(false, |before| {
+        !before.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Returns true if the given text (preceding an expression) ends with the beginning of an
+/// `assert!` call whose second argument is the expression
+fn is_assert_code(before: &str) -> bool {
+    if !before.ends_with(',') {
+        return false;
+    }
+    let mut depth = 0;
+    let mut commas = 0;
+    for (i, c) in before.char_indices().rev() {
+        match c {
+            ')' | ']' | '}' => depth += 1,
+            '(' | '[' | '{' if depth == 0 => {
+                return commas == 1 && ends_with_word(before[..i].trim_end(), "assert!");
+            }
+            '(' | '[' | '{' => depth -= 1,
+            ',' if depth == 0 => commas += 1,
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Returns the given text (such as an output of a unit test) with names of constants added after
+/// abort codes that it mentions (e.g., "aborted with 7 (M::E_NOT_FOUND)"), given the names of
+/// constants with each value
+pub fn annotate_abort_codes(text: &str, names: &BTreeMap<u64, Vec<String>>) -> String {
+    const PATTERNS: &[&str] = &["aborted with ", "abort with "];
+
+    let mut annotated = String::new();
+    let mut rest = text;
+    while let Some((start, pattern)) = PATTERNS
+        .iter()
+        .filter_map(|p| rest.find(p).map(|i| (i, p)))
+        .min()
+    {
+        let code_start = start + pattern.len();
+        let code_end = rest[code_start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |i| code_start + i);
+        annotated.push_str(&rest[..code_end]);
+        let code_names = rest[code_start..code_end]
+            .parse::<u64>()
+            .ok()
+            .and_then(|code| names.get(&code));
+        if let Some(code_names) = code_names {
+            annotated.push_str(&format!(" ({})", code_names.join(", ")));
+        }
+        rest = &rest[code_end..];
+    }
+    annotated.push_str(rest);
+    annotated
+}
+
+#[test]
+fn abort_code_at_test() {
+    let code_at = |buffer: &str, literal: &str| {
+        let offset = buffer.find(literal).unwrap() + 1;
+        abort_code_at(buffer, offset).map(|(code, start, end)| {
+            assert_eq!(&buffer[start..end], literal);
+            code
+        })
+    };
+
+    assert_eq!(code_at("if (x) abort 7;", "7"), Some(7));
+    assert_eq!(code_at("assert!(x > 1, 0x10);", "0x10"), Some(16));
+    assert_eq!(code_at("assert!(f(x, y), 42u64)", "42u64"), Some(42));
+    assert_eq!(
+        code_at("#[expected_failure(abort_code = 12)]", "12"),
+        Some(12)
+    );
+    assert_eq!(code_at("assert!(x > 1, 3)", "1"), None);
+    assert_eq!(code_at("let abort_count = 5;", "5"), None);
+    assert_eq!(code_at("g(x, 5)", "5"), None);
+}
+
+#[test]
+fn annotate_abort_codes_test() {
+    let names = BTreeMap::from([
+        (7, vec!["M::E_NOT_FOUND".to_string()]),
+        (8, vec!["M::E_A".to_string(), "N::E_B".to_string()]),
+    ]);
+
+    assert_eq!(
+        annotate_abort_codes(
+            "Test was not expected to abort but it aborted with 7 here",
+            &names
+        ),
+        "Test was not expected to abort but it aborted with 7 (M::E_NOT_FOUND) here"
+    );
+    assert_eq!(
+        annotate_abort_codes(
+            "Test did not abort with expected code. Expected test to abort with 8 but instead it aborted with 9 here",
+            &names
+        ),
+        "Test did not abort with expected code. Expected test to abort with 8 (M::E_A, N::E_B) but instead it aborted with 9 here"
+    );
+}
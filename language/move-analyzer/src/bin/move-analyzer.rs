@@ -7,42 +7,75 @@ use clap::Parser;
 use crossbeam::channel::{bounded, select};
 use lsp_server::{Connection, Message, Notification, Request, Response};
 use lsp_types::{
-    notification::Notification as _, request::Request as _, CompletionOptions, Diagnostic,
-    HoverProviderCapability, OneOf, SaveOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextDocumentSyncOptions, TypeDefinitionProviderCapability, WorkDoneProgressOptions,
+    notification::Notification as _, request::Request as _, CallHierarchyServerCapability,
+    CodeActionKind, CodeActionOptions, CodeActionProviderCapability, CodeLensOptions,
+    CompletionOptions, Diagnostic, DidChangeWorkspaceFoldersParams, DocumentLinkOptions,
+    DocumentOnTypeFormattingOptions, ExecuteCommandOptions, FoldingRangeProviderCapability,
+    HoverProviderCapability, OneOf, RenameOptions, SaveOptions, SelectionRangeProviderCapability,
+    SemanticTokensFullOptions, SemanticTokensOptions, SignatureHelpOptions,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TypeDefinitionProviderCapability, WorkDoneProgressOptions, WorkspaceFoldersServerCapabilities,
+    WorkspaceServerCapabilities,
 };
 use std::{
-    collections::BTreeMap,
-    path::Path,
+    cell::RefCell,
+    collections::{BTreeMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
+use tracing::{debug, debug_span, error, info, warn};
+use tracing_subscriber::filter::LevelFilter;
 
 use move_analyzer::{
-    completion::on_completion_request,
+    bytecode::{on_bytecode_request, BytecodeRequest},
+    code_lens::{on_code_lens_request, on_code_lens_resolve_request},
+    completion::{on_completion_request, on_completion_resolve_request},
+    config::{on_did_change_configuration_notification, Config},
     context::Context,
+    dependency_graph::{on_dependency_graph_request, DependencyGraphRequest},
+    document_link::on_document_link_request,
+    folding::on_folding_range_request,
+    formatting::{
+        on_formatting_request, on_on_type_formatting_request, on_range_formatting_request,
+        FormatConfig, ON_TYPE_TRIGGER_CHARACTERS,
+    },
+    inlay_hints::InlayHintRequest,
+    logging::Logger,
+    prover::PROVE_COMMAND,
+    selection_range::on_selection_range_request,
     symbols,
+    type_at::TypeAtRequest,
+    unit_test::{on_execute_command_request, RUN_TESTS_COMMAND, RUN_TEST_COMMAND},
     vfs::{on_text_document_sync_notification, VirtualFileSystem},
+    watched_files::{on_did_change_watched_files_notification, register_watchers_request},
 };
 use move_symbol_pool::Symbol;
 use url::Url;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
-struct Options {}
+struct Options {
+    /// File to write the log to (instead of stderr)
+    #[clap(long, parse(from_os_str))]
+    log_file: Option<PathBuf>,
+    /// Maximum level of messages to log (off, error, warn, info, debug or trace)
+    #[clap(long, default_value = "info")]
+    log_level: LevelFilter,
+}
 
 fn main() {
-    // For now, move-analyzer only responds to options built-in to clap,
-    // such as `--help` or `--version`.
-    Options::parse();
+    let options = Options::parse();
 
     // stdio is used to communicate Language Server Protocol requests and responses.
-    // stderr is used for logging (and, when Visual Studio Code is used to communicate with this
-    // server, it captures this output in a dedicated "output channel").
+    // stderr is used for logging unless a log file is given (and, when Visual Studio Code is used
+    // to communicate with this server, it captures this output in a dedicated "output channel").
+    let logger = Logger::init(options.log_file.as_deref(), options.log_level)
+        .expect("could not initialize logging");
     let exe = std::env::current_exe()
         .unwrap()
         .to_string_lossy()
         .to_string();
-    eprintln!(
+    info!(
         "Starting language server '{}' communicating via stdio...",
         exe
     );
@@ -52,20 +85,21 @@ fn main() {
         connection,
         files: VirtualFileSystem::default(),
         symbols: Arc::new(Mutex::new(symbols::Symbolicator::empty_symbols())),
+        pending_messages: RefCell::new(VecDeque::new()),
+        cancelled_requests: RefCell::new(HashSet::new()),
+        snippet_support: false,
+        format_config: FormatConfig::default(),
     };
-    let capabilities = serde_json::to_value(lsp_types::ServerCapabilities {
+    let mut capabilities = serde_json::to_value(lsp_types::ServerCapabilities {
         // The server receives notifications from the client as users open, close,
         // and modify documents.
         text_document_sync: Some(TextDocumentSyncCapability::Options(
             TextDocumentSyncOptions {
                 open_close: Some(true),
-                // TODO: We request that the language server client send us the entire text of any
-                // files that are modified. We ought to use the "incremental" sync kind, which would
-                // have clients only send us what has changed and where, thereby requiring far less
-                // data be sent "over the wire." However, to do so, our language server would need
-                // to be capable of applying deltas to its view of the client's open files. See the
-                // 'move_analyzer::vfs' module for details.
-                change: Some(TextDocumentSyncKind::Full),
+                // The client only sends us what has changed and where, which the language server
+                // applies to its view of the client's open files (see the 'move_analyzer::vfs'
+                // module for details).
+                change: Some(TextDocumentSyncKind::Incremental),
                 will_save: None,
                 will_save_wait_until: None,
                 save: Some(
@@ -76,11 +110,13 @@ fn main() {
                 ),
             },
         )),
-        selection_range_provider: None,
+        // Selection ranges are computed from the parsed buffer rather than symbolication results.
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
         hover_provider: Some(HoverProviderCapability::Simple(true)),
         // The server provides completions as a user is typing.
         completion_provider: Some(CompletionOptions {
-            resolve_provider: None,
+            // documentation and `use` declarations are only computed for resolved items
+            resolve_provider: Some(true),
             // In Move, `foo::` and `foo.` should trigger completion suggestions for after
             // the `:` or `.`
             // (Trigger characters are just that: characters, such as `:`, and not sequences of
@@ -98,9 +134,105 @@ fn main() {
             symbols::DEFS_AND_REFS_SUPPORT,
         )),
         references_provider: Some(OneOf::Left(symbols::DEFS_AND_REFS_SUPPORT)),
+        document_highlight_provider: Some(OneOf::Left(symbols::DEFS_AND_REFS_SUPPORT)),
+        call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(
+            symbols::DEFS_AND_REFS_SUPPORT,
+        )),
+        document_symbol_provider: Some(OneOf::Left(symbols::DEFS_AND_REFS_SUPPORT)),
+        workspace_symbol_provider: Some(OneOf::Left(symbols::DEFS_AND_REFS_SUPPORT)),
+        // Renaming is preceded by a check of whether the identifier at the cursor can be renamed.
+        rename_provider: Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(symbols::DEFS_AND_REFS_SUPPORT),
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        })),
+        // Document links are computed from the parsed buffer and from packages on disk rather than
+        // symbolication results.
+        document_link_provider: Some(DocumentLinkOptions {
+            resolve_provider: None,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        }),
+        // Folding ranges are computed from the parsed buffer rather than symbolication results.
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        // Documents are formatted by the server itself (see the 'move_analyzer::formatting' module
+        // for details).
+        document_formatting_provider: Some(OneOf::Left(true)),
+        // Ranges are formatted on request as well as after a block or a statement is completed.
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+            first_trigger_character: ON_TYPE_TRIGGER_CHARACTERS[0].to_string(),
+            more_trigger_character: Some(
+                ON_TYPE_TRIGGER_CHARACTERS[1..]
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+            ),
+        }),
+        // Quick fixes are offered for compiler diagnostics, along with a source action
+        // organizing imports and a refactoring extracting selected statements into a function.
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![
+                CodeActionKind::QUICKFIX,
+                CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                CodeActionKind::REFACTOR_EXTRACT,
+            ]),
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+            resolve_provider: None,
+        })),
+        // Code lenses run individual unit tests through a command executed by the server, and show
+        // references to definitions (counted when a lens is resolved). Multiple tests can also be
+        // run at once (with results reported in custom notifications), and modules are verified by
+        // the prover through other commands executed by the server.
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(true),
+        }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![
+                RUN_TEST_COMMAND.to_string(),
+                RUN_TESTS_COMMAND.to_string(),
+                PROVE_COMMAND.to_string(),
+            ],
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        }),
+        // Signature help is requested when a call's argument list is opened and when moving on to
+        // the next argument.
+        signature_help_provider: Some(SignatureHelpOptions {
+            trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+            retrigger_characters: None,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        }),
+        semantic_tokens_provider: Some(
+            SemanticTokensOptions {
+                work_done_progress_options: WorkDoneProgressOptions {
+                    work_done_progress: None,
+                },
+                legend: symbols::semantic_tokens_legend(),
+                range: Some(true),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+            }
+            .into(),
+        ),
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                supported: Some(true),
+                change_notifications: Some(OneOf::Left(true)),
+            }),
+            file_operations: None,
+        }),
         ..Default::default()
     })
     .expect("could not serialize server capabilities");
+    // Inlay hints are not (yet) part of the server capabilities defined by lsp-types.
+    capabilities["inlayHintProvider"] = serde_json::Value::Bool(true);
 
     let client_response: serde_json::Value = context
         .connection
@@ -109,19 +241,78 @@ fn main() {
 
     let initialize_params: lsp_types::InitializeParams =
         serde_json::from_value(client_response).expect("could not deserialize client capabilities");
+    context.snippet_support = initialize_params
+        .capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.completion.as_ref())
+        .and_then(|completion| completion.completion_item.as_ref())
+        .and_then(|completion_item| completion_item.snippet_support)
+        .unwrap_or(false);
+    // The client's initial trace setting may raise the verbosity of logging.
+    if let Some(trace) = initialize_params.trace {
+        logger.set_trace(trace);
+    }
 
     let (diag_sender, diag_receiver) = bounded::<Result<BTreeMap<Symbol, Vec<Diagnostic>>>>(0);
     let mut symbolicator_runner = symbols::SymbolicatorRunner::idle();
     if symbols::DEFS_AND_REFS_SUPPORT {
-        if let Some(uri) = initialize_params.root_uri {
-            symbolicator_runner =
-                symbols::SymbolicatorRunner::new(&uri, context.symbols.clone(), diag_sender);
-            symbolicator_runner.run();
+        // Each workspace folder is treated as a separate package (if the client does not support
+        // workspace folders, the root of the workspace is used instead).
+        let uris = match initialize_params.workspace_folders {
+            Some(folders) if !folders.is_empty() => folders.into_iter().map(|f| f.uri).collect(),
+            _ => initialize_params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        symbolicator_runner =
+            symbols::SymbolicatorRunner::new(&uris, context.symbols.clone(), diag_sender);
+        // The client may pass the user's settings on initialization (so that the first symbolication
+        // does not happen with the default settings), otherwise they are received via
+        // `workspace/didChangeConfiguration`.
+        if let Some(options) = initialize_params.initialization_options {
+            match Config::from_settings(options)
+                .and_then(|config| Ok((config.build_config()?, config.formatting)))
+            {
+                Ok((build_config, format_config)) => {
+                    symbolicator_runner.set_build_config(build_config);
+                    context.format_config = format_config;
+                }
+                Err(err) => warn!("ignoring initialization options: {:?}", err),
+            }
+        }
+        symbolicator_runner.run();
+        // Changes of manifests and of sources of (downloaded) dependencies are only noticed if the
+        // client watches files for the server.
+        let dynamic_watchers = initialize_params
+            .capabilities
+            .workspace
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .and_then(|watched_files| watched_files.dynamic_registration)
+            .unwrap_or(false);
+        if dynamic_watchers {
+            if let Err(err) = context
+                .connection
+                .sender
+                .send(Message::Request(register_watchers_request()))
+            {
+                error!("could not send file watchers registration: {:?}", err);
+            }
         }
     };
 
     let mut missing_manifest_reported = false;
     loop {
+        // Messages received by request handlers while checking for cancellation are processed
+        // before any new ones.
+        let pending_message = context.pending_messages.borrow_mut().pop_front();
+        if let Some(message) = pending_message {
+            if !on_message(&mut context, &symbolicator_runner, &logger, message) {
+                break;
+            }
+            continue;
+        }
+        // All requests whose cancellation has been recorded so far have already been processed.
+        context.cancelled_requests.borrow_mut().clear();
+
         select! {
             recv(diag_receiver) -> message => {
                 match message {
@@ -136,7 +327,7 @@ fn main() {
                                         .connection
                                         .sender
                                         .send(lsp_server::Message::Notification(notification)) {
-                                            eprintln!("could not send diagnostics response: {:?}", err);
+                                            error!("could not send diagnostics response: {:?}", err);
                                         };
                                 }
                             },
@@ -154,7 +345,7 @@ fn main() {
                                         .connection
                                         .sender
                                         .send(lsp_server::Message::Notification(notification)) {
-                                            eprintln!("could not send compiler error response: {:?}", err);
+                                            error!("could not send compiler error response: {:?}", err);
                                         };
                                 }
                                 if missing_manifest {
@@ -163,25 +354,17 @@ fn main() {
                             },
                         }
                     },
-                    Err(error) => eprintln!("symbolicator message error: {:?}", error),
+                    Err(error) => error!("symbolicator message error: {:?}", error),
                 }
             },
             recv(context.connection.receiver) -> message => {
                 match message {
-                    Ok(Message::Request(request)) => on_request(&context, &request),
-                    Ok(Message::Response(response)) => on_response(&context, &response),
-                    Ok(Message::Notification(notification)) => {
-                        match notification.method.as_str() {
-                            lsp_types::notification::Exit::METHOD => break,
-                            lsp_types::notification::Cancel::METHOD => {
-                                // TODO: Currently the server does not implement request cancellation.
-                                // It ought to, especially once it begins processing requests that may
-                                // take a long time to respond to.
-                            }
-                            _ => on_notification(&mut context, &symbolicator_runner, &notification),
+                    Ok(message) => {
+                        if !on_message(&mut context, &symbolicator_runner, &logger, message) {
+                            break;
                         }
                     }
-                    Err(error) => eprintln!("IDE message error: {:?}", error),
+                    Err(error) => error!("IDE message error: {:?}", error),
                 }
             }
         };
@@ -189,30 +372,152 @@ fn main() {
 
     io_threads.join().expect("I/O threads could not finish");
     symbolicator_runner.quit();
-    eprintln!("Shut down language server '{}'.", exe);
+    info!("Shut down language server '{}'.", exe);
+}
+
+/// Processes a message from the client, returning `false` if the language server should exit.
+fn on_message(
+    context: &mut Context,
+    symbolicator_runner: &symbols::SymbolicatorRunner,
+    logger: &Logger,
+    message: Message,
+) -> bool {
+    match message {
+        Message::Request(request) => on_request(context, &request),
+        Message::Response(response) => on_response(context, &response),
+        Message::Notification(notification) => match notification.method.as_str() {
+            lsp_types::notification::Exit::METHOD => return false,
+            lsp_types::notification::Cancel::METHOD => {
+                context.on_cancel_notification(notification.params)
+            }
+            lsp_types::notification::SetTrace::METHOD => {
+                logger.on_set_trace_notification(&notification)
+            }
+            _ => on_notification(context, symbolicator_runner, &notification),
+        },
+    }
+    true
 }
 
 fn on_request(context: &Context, request: &Request) {
+    let _span = debug_span!("request", method = %request.method, id = ?request.id).entered();
+    if context.is_cancelled(&request.id) {
+        context.send_cancelled_response(request.id.clone());
+        return;
+    }
     match request.method.as_str() {
-        lsp_types::request::Completion::METHOD => on_completion_request(context, request),
+        lsp_types::request::Completion::METHOD => {
+            with_symbols(context, request, on_completion_request);
+        }
+        lsp_types::request::ResolveCompletionItem::METHOD => {
+            with_symbols(context, request, on_completion_resolve_request);
+        }
         lsp_types::request::GotoDefinition::METHOD => {
-            symbols::on_go_to_def_request(context, request, &context.symbols.lock().unwrap());
+            with_symbols(context, request, symbols::on_go_to_def_request);
         }
         lsp_types::request::GotoTypeDefinition::METHOD => {
-            symbols::on_go_to_type_def_request(context, request, &context.symbols.lock().unwrap());
+            with_symbols(context, request, symbols::on_go_to_type_def_request);
         }
         lsp_types::request::References::METHOD => {
-            symbols::on_references_request(context, request, &context.symbols.lock().unwrap());
+            with_symbols(context, request, symbols::on_references_request);
+        }
+        lsp_types::request::DocumentHighlightRequest::METHOD => {
+            with_symbols(context, request, symbols::on_document_highlight_request);
+        }
+        lsp_types::request::CallHierarchyPrepare::METHOD => {
+            with_symbols(context, request, symbols::on_prepare_call_hierarchy_request);
+        }
+        lsp_types::request::CallHierarchyIncomingCalls::METHOD => {
+            with_symbols(context, request, symbols::on_incoming_calls_request);
+        }
+        lsp_types::request::CallHierarchyOutgoingCalls::METHOD => {
+            with_symbols(context, request, symbols::on_outgoing_calls_request);
         }
         lsp_types::request::HoverRequest::METHOD => {
-            symbols::on_hover_request(context, request, &context.symbols.lock().unwrap());
+            with_symbols(context, request, symbols::on_hover_request);
+        }
+        lsp_types::request::DocumentSymbolRequest::METHOD => {
+            with_symbols(context, request, symbols::on_document_symbol_request);
+        }
+        lsp_types::request::Rename::METHOD => {
+            with_symbols(context, request, symbols::on_rename_request);
+        }
+        lsp_types::request::PrepareRenameRequest::METHOD => {
+            with_symbols(context, request, symbols::on_prepare_rename_request);
+        }
+        lsp_types::request::SemanticTokensFullRequest::METHOD => {
+            with_symbols(context, request, symbols::on_semantic_tokens_full_request);
+        }
+        lsp_types::request::SemanticTokensRangeRequest::METHOD => {
+            with_symbols(context, request, symbols::on_semantic_tokens_range_request);
+        }
+        lsp_types::request::WorkspaceSymbol::METHOD => {
+            with_symbols(context, request, symbols::on_workspace_symbol_request);
+        }
+        lsp_types::request::CodeActionRequest::METHOD => {
+            with_symbols(context, request, symbols::on_code_action_request);
+        }
+        lsp_types::request::SignatureHelpRequest::METHOD => {
+            with_symbols(context, request, symbols::on_signature_help_request);
+        }
+        lsp_types::request::CodeLensRequest::METHOD => {
+            with_symbols(context, request, on_code_lens_request);
+        }
+        lsp_types::request::CodeLensResolve::METHOD => {
+            with_symbols(context, request, on_code_lens_resolve_request);
+        }
+        lsp_types::request::ExecuteCommand::METHOD => {
+            on_execute_command_request(context, request);
         }
-        _ => eprintln!("handle request '{}' from client", request.method),
+        lsp_types::request::DocumentLinkRequest::METHOD => {
+            on_document_link_request(context, request);
+        }
+        lsp_types::request::Formatting::METHOD => {
+            on_formatting_request(context, request);
+        }
+        lsp_types::request::RangeFormatting::METHOD => {
+            on_range_formatting_request(context, request);
+        }
+        lsp_types::request::OnTypeFormatting::METHOD => {
+            on_on_type_formatting_request(context, request);
+        }
+        lsp_types::request::FoldingRangeRequest::METHOD => {
+            on_folding_range_request(context, request);
+        }
+        lsp_types::request::SelectionRangeRequest::METHOD => {
+            on_selection_range_request(context, request);
+        }
+        BytecodeRequest::METHOD => {
+            on_bytecode_request(context, request);
+        }
+        DependencyGraphRequest::METHOD => {
+            on_dependency_graph_request(context, request);
+        }
+        InlayHintRequest::METHOD => {
+            with_symbols(context, request, symbols::on_inlay_hint_request);
+        }
+        TypeAtRequest::METHOD => {
+            with_symbols(context, request, symbols::on_type_at_request);
+        }
+        _ => debug!("handle request '{}' from client", request.method),
+    }
+}
+
+/// Handles a request that requires symbolication information, unless the request gets cancelled
+/// while waiting for this information to become available.
+fn with_symbols(
+    context: &Context,
+    request: &Request,
+    handler: fn(&Context, &Request, &symbols::Symbols),
+) {
+    match context.lock_symbols(&request.id) {
+        Some(symbols) => handler(context, request, &symbols),
+        None => context.send_cancelled_response(request.id.clone()),
     }
 }
 
 fn on_response(_context: &Context, _response: &Response) {
-    eprintln!("handle response from client");
+    debug!("handle response from client");
 }
 
 fn on_notification(
@@ -231,6 +536,24 @@ fn on_notification(
                 notification,
             )
         }
-        _ => eprintln!("handle notification '{}' from client", notification.method),
+        lsp_types::notification::DidChangeConfiguration::METHOD => {
+            on_did_change_configuration_notification(context, symbolicator_runner, notification)
+        }
+        lsp_types::notification::DidChangeWatchedFiles::METHOD => {
+            on_did_change_watched_files_notification(context, symbolicator_runner, notification)
+        }
+        lsp_types::notification::DidChangeWorkspaceFolders::METHOD => {
+            let parameters = serde_json::from_value::<DidChangeWorkspaceFoldersParams>(
+                notification.params.clone(),
+            )
+            .expect("could not deserialize notification");
+            for folder in parameters.event.removed {
+                symbolicator_runner.remove_package(&folder.uri);
+            }
+            for folder in parameters.event.added {
+                symbolicator_runner.add_package(&folder.uri);
+            }
+        }
+        _ => debug!("handle notification '{}' from client", notification.method),
     }
 }
@@ -0,0 +1,228 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Custom `move-analyzer/bytecode` request showing the disassembled bytecode of the function
+//! enclosing the user's cursor.
+//!
+//! The enclosing function is found in the parsed AST of the current contents of the buffer, while
+//! the bytecode comes from compiling the package containing the file as it is on disk. Compilation
+//! may take a long time, so it happens on a separate thread and the request is answered once it
+//! finishes.
+
+use crate::{code_lens::package_path, context::Context, vfs::byte_offset};
+use anyhow::{anyhow, bail, Result};
+use lsp_server::{Message, Request, Response};
+use lsp_types::TextDocumentPositionParams;
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{FunctionDefinitionIndex, TableIndex},
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    compiled_unit::{CompiledUnit, NamedCompiledModule},
+    diagnostics::report_diagnostics_to_buffer,
+    parser::{ast as P, syntax::parse_file_string},
+    shared::{CompilationEnv, Flags},
+};
+use move_disassembler::disassembler::Disassembler;
+use move_ir_types::location::Loc;
+use move_package::compilation::{build_plan::BuildPlan, compiled_package::CompiledPackage};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, thread};
+use tempfile::tempdir;
+use tracing::{debug, error};
+
+/// The `move-analyzer/bytecode` request
+pub enum BytecodeRequest {}
+
+impl lsp_types::request::Request for BytecodeRequest {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<FunctionBytecode>;
+    const METHOD: &'static str = "move-analyzer/bytecode";
+}
+
+/// Disassembled bytecode of a function
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionBytecode {
+    /// Name of the module containing the function
+    pub module_name: String,
+    /// Name of the function
+    pub function_name: String,
+    /// Disassembly of the function
+    pub bytecode: String,
+}
+
+/// Returns the names of the module and of the function enclosing the given offset, or None if
+/// the buffer cannot be parsed or the offset is not within a function of a module.
+pub fn enclosing_function(buffer: &str, offset: usize) -> Option<(String, String)> {
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+    let contains = |loc: &Loc| loc.start() as usize <= offset && offset <= loc.end() as usize;
+    defs.iter()
+        .flat_map(|def| match def {
+            P::Definition::Module(mod_def) => vec![mod_def],
+            P::Definition::Address(addr_def) => addr_def.modules.iter().collect(),
+            P::Definition::Script(_) => vec![],
+        })
+        .filter(|mod_def| contains(&mod_def.loc))
+        .find_map(|mod_def| {
+            mod_def.members.iter().find_map(|member| match member {
+                P::ModuleMember::Function(fun) if contains(&fun.loc) => Some((
+                    mod_def.name.0.value.to_string(),
+                    fun.name.0.value.to_string(),
+                )),
+                _ => None,
+            })
+        })
+}
+
+/// Compiles the package rooted at the given path (in dev mode), failing if it does not compile.
+pub fn compile_package(package_path: &Path) -> Result<CompiledPackage> {
+    let install_dir = tempdir()?;
+    let build_config = move_package::BuildConfig {
+        dev_mode: true,
+        install_dir: Some(install_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let resolution_graph = build_config.resolution_graph_for_package(package_path)?;
+    let build_plan = BuildPlan::create(resolution_graph)?;
+    // compiler diagnostics must not be written to stdout, which is used to communicate with the
+    // client
+    let mut diagnostics = None;
+    let package = build_plan.compile_with_driver(&mut std::io::sink(), |compiler| {
+        let (files, compilation_result) = compiler.build()?;
+        match compilation_result {
+            Ok((units, _)) => Ok((files, units)),
+            Err(diags) => {
+                diagnostics = Some(report_diagnostics_to_buffer(&files, diags));
+                Ok((files, vec![]))
+            }
+        }
+    })?;
+    if let Some(diagnostics) = diagnostics {
+        bail!(
+            "package does not compile:\n{}",
+            String::from_utf8_lossy(&diagnostics)
+        );
+    }
+    Ok(package)
+}
+
+/// Compiles the package rooted at the given path and returns the disassembly of the given function.
+pub fn function_bytecode(
+    package_path: &Path,
+    module_name: &str,
+    function_name: &str,
+) -> Result<String> {
+    let package = compile_package(package_path)?;
+    let unit = &package.get_module_by_name_from_root(module_name)?.unit;
+    let (module, source_map) = match unit {
+        CompiledUnit::Module(NamedCompiledModule {
+            module, source_map, ..
+        }) => (module, source_map),
+        CompiledUnit::Script(_) => bail!("{} is not a module", module_name),
+    };
+    let (index, def) = module
+        .function_defs()
+        .iter()
+        .enumerate()
+        .find(|(_, def)| {
+            let handle = module.function_handle_at(def.function);
+            module.identifier_at(handle.name).as_str() == function_name
+        })
+        .ok_or_else(|| anyhow!("function {}::{} not found", module_name, function_name))?;
+    let handle = module.function_handle_at(def.function);
+    Disassembler::from_unit(unit).disassemble_function_def(
+        source_map.get_function_source_map(FunctionDefinitionIndex(index as TableIndex))?,
+        Some((def, handle)),
+        module.identifier_at(handle.name),
+        &handle.type_parameters,
+        handle.parameters,
+        def.code.as_ref(),
+    )
+}
+
+/// Handles bytecode request of the language server
+pub fn on_bytecode_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<TextDocumentPositionParams>(request.params.clone())
+        .expect("could not deserialize bytecode request");
+
+    let fpath = parameters.text_document.uri.path();
+    let target = context.files.get(fpath).and_then(|buffer| {
+        let offset = byte_offset(buffer, &parameters.position);
+        let (module_name, function_name) = enclosing_function(buffer, offset)?;
+        Some((package_path(Path::new(fpath))?, module_name, function_name))
+    });
+
+    let sender = context.connection.sender.clone();
+    let id = request.id.clone();
+    thread::spawn(move || {
+        let response = match target {
+            Some((package_path, module_name, function_name)) => {
+                match function_bytecode(&package_path, &module_name, &function_name) {
+                    Ok(bytecode) => Response::new_ok(
+                        id,
+                        Some(FunctionBytecode {
+                            module_name,
+                            function_name,
+                            bytecode,
+                        }),
+                    ),
+                    Err(err) => Response::new_err(
+                        id,
+                        lsp_server::ErrorCode::InternalError as i32,
+                        format!("could not disassemble function: {}", err),
+                    ),
+                }
+            }
+            None => Response::new_ok(id, None::<FunctionBytecode>),
+        };
+        debug!("about to send bytecode response");
+        if let Err(err) = sender.send(Message::Response(response)) {
+            error!("could not send bytecode response: {:?}", err);
+        }
+    });
+}
+
+#[test]
+fn enclosing_function_test() {
+    let buffer = r#"address 0x1 {
+module M {
+    fun f() {
+        let x = 1;
+    }
+    struct S {}
+}
+}
+module 0x2::N {
+    public fun g(): u64 { 42 }
+}
+"#;
+    let offset = |s: &str| buffer.find(s).unwrap();
+    assert_eq!(
+        enclosing_function(buffer, offset("let x")),
+        Some(("M".to_string(), "f".to_string()))
+    );
+    assert_eq!(
+        enclosing_function(buffer, offset("42")),
+        Some(("N".to_string(), "g".to_string()))
+    );
+    assert_eq!(enclosing_function(buffer, offset("struct S")), None);
+}
+
+#[test]
+/// Tests if the bytecode of a single function is disassembled.
+fn function_bytecode_test() {
+    let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let bytecode = function_bytecode(&path, "M6", "documented_fun").unwrap();
+    assert!(bytecode.starts_with("public documented_fun("));
+    assert!(bytecode.contains("Ret"));
+    assert!(!bytecode.contains("field_chain"));
+
+    assert!(function_bytecode(&path, "M6", "no_such_fun").is_err());
+    assert!(function_bytecode(&path, "NoSuchModule", "f").is_err());
+}
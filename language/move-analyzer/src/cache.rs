@@ -0,0 +1,84 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! On-disk cache of symbolication results, so that packages whose sources did not change since
+//! they were last symbolicated (e.g., when the editor is restarted) do not have to be recompiled.
+//!
+//! A cache file starts with a line containing the key the cached contents were computed for
+//! (derived from the contents of all source files), followed by the contents serialized as JSON.
+//! A cache file whose key does not match or whose contents cannot be deserialized (e.g., because
+//! it was written by a different version of the server) is ignored.
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, path::Path};
+use tracing::warn;
+
+/// Returns contents of the cache file at the given path if it was written for the given key
+pub fn load<T: DeserializeOwned>(path: &Path, key: &str) -> Option<T> {
+    let cached = fs::read_to_string(path).ok()?;
+    let (cached_key, contents) = cached.split_once('\n')?;
+    if cached_key != key {
+        return None;
+    }
+    match serde_json::from_str(contents) {
+        Ok(contents) => Some(contents),
+        Err(err) => {
+            warn!("could not deserialize cache file {:?}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Writes contents of the cache file at the given path (replacing any previous contents)
+pub fn store<T: Serialize>(path: &Path, key: &str, contents: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut cached = format!("{}\n", key);
+    cached.push_str(&serde_json::to_string(contents)?);
+    // write to a temporary file first so that a partially written cache file is never read
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, cached)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Serializes maps as sequences of key-value pairs (JSON only supports maps with string keys)
+pub mod pairs {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let pairs = Vec::<(K, V)>::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+#[test]
+fn cache_test() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cache").join("contents.json");
+
+    assert_eq!(load::<Vec<u64>>(&path, "key"), None);
+
+    store(&path, "key", &vec![4u64, 2]).unwrap();
+    assert_eq!(load::<Vec<u64>>(&path, "key"), Some(vec![4, 2]));
+    assert_eq!(load::<Vec<u64>>(&path, "other key"), None);
+    // contents of a different type are ignored
+    assert_eq!(load::<String>(&path, "key"), None);
+}
@@ -0,0 +1,857 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Code actions (quick fixes) offered for compiler diagnostics.
+//!
+//! Quick fixes are computed on request from the diagnostic reported by the compiler and from the
+//! current contents of the buffer, as the code being fixed usually does not compile.
+
+use crate::vfs::{buffer_position, byte_offset};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, Position, Range, TextEdit, Url,
+    WorkspaceEdit,
+};
+use move_compiler::{
+    naming::ast::{BuiltinTypeName_, Type, TypeName_, Type_},
+    parser::keywords::KEYWORDS,
+};
+use move_symbol_pool::Symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Modules and module members that can be imported with a `use` declaration, indexed by their
+/// (unqualified) names
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportIndex {
+    /// Fully qualified module names (e.g., `std::vector`)
+    modules: BTreeMap<String, BTreeSet<String>>,
+    /// Fully qualified member names (e.g., `std::vector::empty`)
+    members: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ImportIndex {
+    pub fn insert_module(&mut self, name: &str, qualified_name: String) {
+        self.modules
+            .entry(name.to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(qualified_name);
+    }
+
+    pub fn insert_member(&mut self, name: &str, qualified_name: String) {
+        self.members
+            .entry(name.to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(qualified_name);
+    }
+
+    /// Returns names of modules along with their fully qualified names
+    pub fn modules(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.modules
+            .iter()
+            .flat_map(|(name, qualified_names)| qualified_names.iter().map(move |q| (name, q)))
+    }
+
+    /// Adds all entries of another index to this one
+    pub fn extend(&mut self, other: ImportIndex) {
+        for (name, qualified_names) in other.modules {
+            self.modules
+                .entry(name)
+                .or_default()
+                .extend(qualified_names);
+        }
+        for (name, qualified_names) in other.members {
+            self.members
+                .entry(name)
+                .or_default()
+                .extend(qualified_names);
+        }
+    }
+}
+
+/// Returns the name from a compiler diagnostic about an unresolved name (e.g., `vector` from
+/// "Unbound module alias 'vector'") along with an indication of whether it is a module name
+fn unbound_name(diagnostic: &Diagnostic) -> Option<(&str, bool)> {
+    const MODULE_PREFIXES: &[&str] = &["Unbound module alias '"];
+    const MEMBER_PREFIXES: &[&str] = &["Unbound function '", "Unbound type '"];
+
+    let msg = diagnostic.message.as_str();
+    let (rest, is_module) = if let Some(p) = MODULE_PREFIXES.iter().find(|p| msg.starts_with(*p)) {
+        (&msg[p.len()..], true)
+    } else if let Some(p) = MEMBER_PREFIXES.iter().find(|p| msg.starts_with(*p)) {
+        (&msg[p.len()..], false)
+    } else {
+        return None;
+    };
+    rest.find('\'').map(|end| (&rest[..end], is_module))
+}
+
+/// Returns quick fixes adding a `use` declaration for each module or module member that the
+/// diagnostic may refer to
+pub fn import_actions(
+    index: &ImportIndex,
+    uri: &Url,
+    buffer: &str,
+    diagnostic: &Diagnostic,
+) -> Vec<CodeActionOrCommand> {
+    let (name, is_module) = match unbound_name(diagnostic) {
+        Some(v) => v,
+        None => return vec![],
+    };
+    let candidates = if is_module {
+        index.modules.get(name)
+    } else {
+        index.members.get(name)
+    };
+    let (position, indent) = match use_insertion_point(buffer, diagnostic.range.start.line) {
+        Some(v) => v,
+        None => return vec![],
+    };
+
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|qualified_name| {
+            let edit = TextEdit {
+                range: Range::new(position, position),
+                new_text: format!("{}use {};\n", indent, qualified_name),
+            };
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Import `{}`", qualified_name),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Returns the name from a compiler warning about an unused item (e.g., `x` from "Unused local
+/// variable 'x'") along with an indication of whether it is an alias introduced by a `use`
+/// declaration (as opposed to a variable or a parameter)
+fn unused_name(diagnostic: &Diagnostic) -> Option<(&str, bool)> {
+    const ALIAS_PREFIXES: &[&str] = &["Unused 'use' of alias '"];
+    const VARIABLE_PREFIXES: &[&str] = &[
+        "Unused local variable '",
+        "Unused parameter '",
+        "Unused assignment or binding for local '",
+    ];
+
+    let msg = diagnostic.message.as_str();
+    let (rest, is_alias) = if let Some(p) = ALIAS_PREFIXES.iter().find(|p| msg.starts_with(*p)) {
+        (&msg[p.len()..], true)
+    } else if let Some(p) = VARIABLE_PREFIXES.iter().find(|p| msg.starts_with(*p)) {
+        (&msg[p.len()..], false)
+    } else {
+        return None;
+    };
+    rest.find('\'').map(|end| (&rest[..end], is_alias))
+}
+
+/// Returns a quick fix for a compiler warning about an unused item: an unused alias is removed
+/// from its `use` declaration (along with the whole declaration if it introduces no other
+/// aliases), while an unused variable or parameter is prefixed with `_`
+pub fn unused_action(
+    uri: &Url,
+    buffer: &str,
+    diagnostic: &Diagnostic,
+) -> Option<CodeActionOrCommand> {
+    let (name, is_alias) = unused_name(diagnostic)?;
+    let offset = byte_offset(buffer, &diagnostic.range.start);
+    let (title, edit) = if is_alias {
+        let (start, end) = unused_alias_range(buffer, offset)?;
+        (
+            format!("Remove unused alias `{}`", name),
+            TextEdit {
+                range: Range::new(buffer_position(buffer, start), buffer_position(buffer, end)),
+                new_text: String::new(),
+            },
+        )
+    } else {
+        if !buffer[offset..].starts_with(name) || ident_len(&buffer[offset..]) != name.len() {
+            return None;
+        }
+        // an assignment to an existing variable (as opposed to a `let` binding) cannot be fixed by
+        // renaming the variable
+        if diagnostic.message.starts_with("Unused assignment")
+            && !buffer[..offset].trim_end().ends_with("let")
+        {
+            return None;
+        }
+        (
+            format!("Prefix `{}` with an underscore", name),
+            TextEdit {
+                range: Range::new(diagnostic.range.start, diagnostic.range.start),
+                new_text: "_".to_string(),
+            },
+        )
+    };
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+            ..Default::default()
+        }),
+        is_preferred: Some(true),
+        ..Default::default()
+    }))
+}
+
+/// Returns the byte range to be removed to remove an alias at the given offset from a `use`
+/// declaration spanning a single line, which is the whole line unless the alias is one of multiple
+/// aliases introduced by the declaration
+fn unused_alias_range(buffer: &str, offset: usize) -> Option<(usize, usize)> {
+    let line_start = buffer[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = buffer[offset..]
+        .find('\n')
+        .map_or(buffer.len(), |i| offset + i + 1);
+    let line = &buffer[line_start..line_end];
+    if !line.trim_start().starts_with("use ") || !line.contains(';') {
+        return None;
+    }
+    let whole_line = Some((line_start, line_end));
+    let (open, close) = match (line.find('{'), line.rfind('}')) {
+        (Some(open), Some(close)) if open < close => (line_start + open, line_start + close),
+        _ => return whole_line,
+    };
+    if offset <= open || offset >= close {
+        return None;
+    }
+    // boundaries of items (at their separating commas)
+    let mut bounds = vec![open];
+    bounds.extend(
+        buffer[open..close]
+            .match_indices(',')
+            .map(|(i, _)| open + i)
+            .filter(|i| !buffer[i + 1..close].trim().is_empty()),
+    );
+    bounds.push(close);
+    if bounds.len() == 2 {
+        return whole_line;
+    }
+    let idx = bounds
+        .windows(2)
+        .position(|w| w[0] < offset && offset < w[1])?;
+    let start = bounds[idx] + 1;
+    let end = bounds[idx + 1];
+    Some(if idx + 2 == bounds.len() {
+        // the last item is removed along with the preceding comma
+        (bounds[idx], skip_whitespace_back(buffer.as_bytes(), end))
+    } else {
+        // other items are removed along with the following comma and whitespace
+        let next = end + 1;
+        let next = next + (buffer[next..].len() - buffer[next..].trim_start().len());
+        (
+            start + (buffer[start..].len() - buffer[start..].trim_start().len()),
+            next,
+        )
+    })
+}
+
+/// Returns the ability from a label of a compiler diagnostic pointing to a declaration of a type
+/// lacking it (e.g., `copy` from "To satisfy the constraint, the 'copy' ability would need to be
+/// added here")
+fn missing_ability(label: &str) -> Option<&str> {
+    const PREFIX: &str = "To satisfy the constraint, the '";
+    const SUFFIX: &str = "' ability would need to be added here";
+
+    label.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)
+}
+
+/// Returns quick fixes adding an ability missing from a struct to the struct's declaration, which
+/// is found in the source of its file (as returned by `source`, which returns `None` for files
+/// whose structs cannot be changed, such as those of dependencies)
+pub fn ability_actions(
+    diagnostic: &Diagnostic,
+    source: &dyn Fn(&Url) -> Option<String>,
+) -> Vec<CodeActionOrCommand> {
+    diagnostic
+        .related_information
+        .iter()
+        .flatten()
+        .filter_map(|info| {
+            let ability = missing_ability(&info.message)?;
+            let uri = &info.location.uri;
+            let source = source(uri)?;
+            let name_start = byte_offset(&source, &info.location.range.start);
+            let struct_name = &source[name_start..name_start + ident_len(&source[name_start..])];
+            let (start, end, new_text) = add_ability_edit(&source, name_start, ability)?;
+            let edit = TextEdit {
+                range: Range::new(
+                    buffer_position(&source, start),
+                    buffer_position(&source, end),
+                ),
+                new_text,
+            };
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Add '{}' ability to struct `{}`", ability, struct_name),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+/// Returns the edit (as a byte range to be replaced and its replacement) adding an ability to the
+/// declaration of a struct whose name starts at the given offset, or `None` if there is no struct
+/// declaration there (e.g., if the name is that of a type parameter)
+fn add_ability_edit(
+    source: &str,
+    name_start: usize,
+    ability: &str,
+) -> Option<(usize, usize, String)> {
+    let before = source[..name_start].trim_end();
+    let keyword_start = before.strip_suffix("struct")?.len();
+    if before[..keyword_start].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let bytes = source.as_bytes();
+    let mut end = name_start + ident_len(&source[name_start..]);
+    // type parameters (possibly with abilities of their own)
+    let after_name = skip_whitespace(bytes, end);
+    if bytes.get(after_name) == Some(&b'<') {
+        let mut depth = 0;
+        for (i, c) in source[after_name..].char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                end = after_name + i + 1;
+                break;
+            }
+        }
+    }
+    let clause_start = skip_whitespace(bytes, end);
+    let rest = &source[clause_start..];
+    if rest.starts_with("has") && ident_len(rest) == 3 {
+        // abilities end where the struct's fields start (or where the declaration of a native
+        // struct ends)
+        let clause_end = clause_start + rest.find(|c| c == '{' || c == ';')?;
+        let abilities = &source[clause_start + 3..clause_end];
+        if abilities.split(',').any(|a| a.trim() == ability) {
+            return None;
+        }
+        let insert_at = skip_whitespace_back(bytes, clause_end);
+        Some((insert_at, insert_at, format!(", {}", ability)))
+    } else {
+        Some((end, end, format!(" has {}", ability)))
+    }
+}
+
+/// Finds the position where a new `use` declaration should be inserted for code on a given line,
+/// that is after the last `use` declaration of the enclosing module (or script), or at the top of
+/// the module if there are none. Also returns the indentation of the new declaration.
+pub fn use_insertion_point(buffer: &str, line: u32) -> Option<(Position, String)> {
+    let lines = buffer.lines().take(line as usize + 1).collect::<Vec<_>>();
+    let (decl_line, decl) = lines.iter().enumerate().rev().find(|(_, l)| {
+        let l = l.trim_start();
+        l.starts_with("module ") || l.starts_with("script ") || l.starts_with("script{")
+    })?;
+    let decl_indent = &decl[..decl.len() - decl.trim_start().len()];
+
+    let last_use = lines
+        .iter()
+        .enumerate()
+        .skip(decl_line + 1)
+        .rev()
+        .find(|(_, l)| l.trim_start().starts_with("use "));
+    Some(match last_use {
+        Some((use_line, l)) => (
+            Position::new(use_line as u32 + 1, 0),
+            l[..l.len() - l.trim_start().len()].to_string(),
+        ),
+        None => (
+            Position::new(decl_line as u32 + 1, 0),
+            format!("{}    ", decl_indent),
+        ),
+    })
+}
+
+/// Struct literal (pack expression) surrounding the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLiteral {
+    /// Name of the struct, possibly qualified (e.g., `["M", "S"]` for `M::S`)
+    pub path: Vec<String>,
+    /// Names of the fields already present in the literal
+    pub fields: Vec<String>,
+    /// Byte offset of the literal's opening brace
+    open: usize,
+    /// Byte offset of the literal's closing brace
+    close: usize,
+}
+
+/// Finds the innermost struct literal whose braces contain the given position. Similarly to
+/// signature help, this is a purely textual approximation that ignores comments and string
+/// literals.
+pub fn struct_literal(buffer: &str, position: &Position) -> Option<StructLiteral> {
+    let offset = byte_offset(buffer, position);
+    let bytes = buffer.as_bytes();
+
+    // find the opening brace
+    let mut depth = 0;
+    let mut open = offset;
+    loop {
+        if open == 0 {
+            return None;
+        }
+        open -= 1;
+        match bytes[open] {
+            b')' | b']' | b'}' => depth += 1,
+            b'{' if depth == 0 => break,
+            b'(' | b'[' | b'{' if depth > 0 => depth -= 1,
+            b'(' | b'[' | b';' => return None,
+            _ => (),
+        }
+    }
+
+    // find the closing brace and the fields already present
+    let mut depth = 0;
+    let mut close = open + 1;
+    let mut field_starts = vec![open + 1];
+    loop {
+        match bytes.get(close)? {
+            b'(' | b'[' | b'{' => depth += 1,
+            b'}' if depth == 0 => break,
+            b')' | b']' | b'}' => depth -= 1,
+            b';' => return None,
+            b',' if depth == 0 => field_starts.push(close + 1),
+            _ => (),
+        }
+        close += 1;
+    }
+    let fields = field_starts
+        .into_iter()
+        .filter_map(|start| {
+            let field = buffer[start..close].trim_start();
+            let len = ident_len(field);
+            if len > 0 {
+                Some(field[..len].to_string())
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // skip type arguments, if any
+    let mut end = skip_whitespace_back(bytes, open);
+    if end > 0 && bytes[end - 1] == b'>' {
+        let mut depth = 0;
+        loop {
+            if end == 0 {
+                return None;
+            }
+            end -= 1;
+            match bytes[end] {
+                b'>' => depth += 1,
+                b'<' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => (),
+            }
+        }
+        end = skip_whitespace_back(bytes, end);
+    }
+
+    // collect (possibly qualified) struct name
+    let mut path = vec![];
+    let mut start;
+    loop {
+        start = end;
+        while start > 0 && (bytes[start - 1].is_ascii_alphanumeric() || bytes[start - 1] == b'_') {
+            start -= 1;
+        }
+        if start == end {
+            return None;
+        }
+        path.push(buffer[start..end].to_string());
+        let sep = skip_whitespace_back(bytes, start);
+        if sep < 2 || &bytes[sep - 2..sep] != b"::" {
+            break;
+        }
+        end = skip_whitespace_back(bytes, sep - 2);
+    }
+    path.reverse();
+
+    // braces following a keyword (e.g., `else {`) or a module, struct or spec name do not start a
+    // struct literal
+    let prev_end = skip_whitespace_back(bytes, start);
+    let prev_word = buffer[..prev_end]
+        .rsplit(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    if KEYWORDS.contains(&path[path.len() - 1].as_str())
+        || matches!(prev_word, "module" | "struct" | "spec" | "schema" | "has")
+        || (prev_end > 0 && bytes[prev_end - 1] == b',')
+    {
+        return None;
+    }
+
+    Some(StructLiteral {
+        path,
+        fields,
+        open,
+        close,
+    })
+}
+
+fn skip_whitespace(bytes: &[u8], mut start: usize) -> usize {
+    while start < bytes.len() && bytes[start].is_ascii_whitespace() {
+        start += 1;
+    }
+    start
+}
+
+fn skip_whitespace_back(bytes: &[u8], mut end: usize) -> usize {
+    while end > 0 && bytes[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+    end
+}
+
+/// Returns the length of the identifier at the beginning of the given string
+pub fn ident_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(s.len())
+}
+
+/// Returns a quick fix adding all fields of a given struct that are missing in the struct literal
+/// (in declaration order) initialized with placeholder values, or `None` if no fields are missing
+pub fn fill_fields_action(
+    uri: &Url,
+    buffer: &str,
+    literal: &StructLiteral,
+    struct_name: &str,
+    struct_fields: &[(Symbol, Type)],
+) -> Option<CodeActionOrCommand> {
+    let missing = struct_fields
+        .iter()
+        .filter(|(name, _)| !literal.fields.iter().any(|f| f == name.as_str()))
+        .map(|(name, ty)| format!("{}: {}", name, placeholder(ty)))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return None;
+    }
+
+    let content = &buffer[literal.open + 1..literal.close];
+    let content_end = literal.open + 1 + content.trim_end().len();
+    let (sep, new_fields, end) = if content.contains('\n') {
+        // one field per line, indented like the first field (or the line of the closing brace)
+        let close_line_start = buffer[..literal.close].rfind('\n').map_or(0, |i| i + 1);
+        let close_indent = indentation(&buffer[close_line_start..]);
+        let field_indent = if content.trim_start().is_empty() {
+            format!("{}    ", close_indent)
+        } else {
+            let first_field = literal.open + 1 + (content.len() - content.trim_start().len());
+            let line_start = buffer[..first_field].rfind('\n').map_or(0, |i| i + 1);
+            indentation(&buffer[line_start..]).to_string()
+        };
+        let new_fields = missing
+            .iter()
+            .map(|f| format!("\n{}{},", field_indent, f))
+            .collect::<String>();
+        (
+            if content.trim().is_empty() || content.trim_end().ends_with(',') {
+                ""
+            } else {
+                ","
+            },
+            new_fields,
+            format!("\n{}", close_indent),
+        )
+    } else {
+        (
+            if content.trim().is_empty() {
+                ""
+            } else if content.trim_end().ends_with(',') {
+                " "
+            } else {
+                ", "
+            },
+            format!(
+                "{}{}",
+                if content.trim().is_empty() { " " } else { "" },
+                missing.join(", ")
+            ),
+            " ".to_string(),
+        )
+    };
+
+    let edit = TextEdit {
+        range: Range::new(
+            buffer_position(buffer, content_end),
+            buffer_position(buffer, literal.close),
+        ),
+        new_text: format!("{}{}{}", sep, new_fields, end),
+    };
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: format!("Fill in missing fields of `{}`", struct_name),
+        kind: Some(CodeActionKind::QUICKFIX),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()]
+}
+
+/// Returns a placeholder value of a given type. Values of types that cannot be trivially
+/// constructed are represented by an `abort` expression (which has any type).
+fn placeholder(sp!(_, t): &Type) -> &'static str {
+    use BuiltinTypeName_ as B;
+    match t {
+        Type_::Apply(_, sp!(_, TypeName_::Builtin(sp!(_, b))), _) => match b {
+            B::U8 | B::U64 | B::U128 => "0",
+            B::Bool => "false",
+            B::Address => "@0x0",
+            B::Vector => "vector[]",
+            B::Signer => "abort 0",
+        },
+        _ => "abort 0",
+    }
+}
+
+#[test]
+fn import_actions_test() {
+    let mut index = ImportIndex::default();
+    index.insert_module("vector", "std::vector".to_string());
+    index.insert_member("empty", "std::vector::empty".to_string());
+
+    let uri = Url::parse("file:///M.move").unwrap();
+    let buffer = "module 0x1::M {\n    use std::option;\n\n    fun f() { vector::empty(); }\n}\n";
+    let diagnostic = |msg: &str| {
+        Diagnostic::new_simple(
+            Range::new(Position::new(3, 14), Position::new(3, 20)),
+            msg.to_string(),
+        )
+    };
+    let edits = |actions: Vec<CodeActionOrCommand>| {
+        actions
+            .into_iter()
+            .map(|a| match a {
+                CodeActionOrCommand::CodeAction(a) => {
+                    let mut changes = a.edit.unwrap().changes.unwrap();
+                    changes.remove(&uri).unwrap().remove(0)
+                }
+                CodeActionOrCommand::Command(_) => panic!("unexpected command"),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let actions = import_actions(
+        &index,
+        &uri,
+        buffer,
+        &diagnostic("Unbound module alias 'vector'"),
+    );
+    assert_eq!(
+        edits(actions),
+        vec![TextEdit {
+            range: Range::new(Position::new(2, 0), Position::new(2, 0)),
+            new_text: "    use std::vector;\n".to_string(),
+        }]
+    );
+
+    // no `use` declarations in the module
+    let buffer = "module 0x1::M {\n    fun f() { empty(); }\n}\n";
+    let actions = import_actions(
+        &index,
+        &uri,
+        buffer,
+        &diagnostic("Unbound function 'empty' in current scope"),
+    );
+    assert_eq!(
+        edits(actions),
+        vec![TextEdit {
+            range: Range::new(Position::new(1, 0), Position::new(1, 0)),
+            new_text: "    use std::vector::empty;\n".to_string(),
+        }]
+    );
+
+    // unrelated diagnostic
+    assert!(import_actions(&index, &uri, buffer, &diagnostic("Invalid call")).is_empty());
+}
+
+#[test]
+fn unused_action_test() {
+    let uri = Url::parse("file:///M.move").unwrap();
+    let buffer = "module 0x1::M {\n    use std::vector;\n    use std::option::{Self, some, none};\n    use 0x1::N::{f};\n\n    fun g(x: u64) {\n        let y = 1;\n        y = 2;\n    }\n}\n";
+    let action = |line, character, msg: &str| {
+        let diagnostic = Diagnostic::new_simple(
+            Range::new(
+                Position::new(line, character),
+                Position::new(line, character + 1),
+            ),
+            msg.to_string(),
+        );
+        unused_action(&uri, buffer, &diagnostic).map(|a| match a {
+            CodeActionOrCommand::CodeAction(a) => {
+                let mut changes = a.edit.unwrap().changes.unwrap();
+                let edit = changes.remove(&uri).unwrap().remove(0);
+                let start = byte_offset(buffer, &edit.range.start);
+                let end = byte_offset(buffer, &edit.range.end);
+                format!("{}{}{}", &buffer[..start], edit.new_text, &buffer[end..])
+            }
+            CodeActionOrCommand::Command(_) => panic!("unexpected command"),
+        })
+    };
+    let alias_msg = |name: &str| format!("Unused 'use' of alias '{}'. Consider removing it", name);
+
+    // the whole declaration
+    assert!(action(1, 13, &alias_msg("vector"))
+        .unwrap()
+        .contains("{\n    use std::option"));
+    assert!(!action(3, 17, &alias_msg("f")).unwrap().contains("0x1::N"));
+    // a single item of a group
+    assert!(action(2, 28, &alias_msg("some"))
+        .unwrap()
+        .contains("use std::option::{Self, none};"));
+    assert!(action(2, 34, &alias_msg("none"))
+        .unwrap()
+        .contains("use std::option::{Self, some};"));
+    // variables and parameters
+    assert!(action(
+        5,
+        10,
+        "Unused parameter 'x'. Consider removing or prefixing with an underscore: '_x'"
+    )
+    .unwrap()
+    .contains("fun g(_x: u64)"));
+    assert!(action(6, 12, "Unused assignment or binding for local 'y'. Consider removing, replacing with '_', or prefixing with '_' (e.g., '_y')")
+        .unwrap()
+        .contains("let _y = 1;"));
+    // an assignment
+    assert!(action(7, 8, "Unused assignment or binding for local 'y'. Consider removing, replacing with '_', or prefixing with '_' (e.g., '_y')").is_none());
+    // unrelated diagnostic
+    assert!(action(5, 10, "Invalid call").is_none());
+}
+
+#[test]
+fn ability_actions_test() {
+    use lsp_types::{DiagnosticRelatedInformation, Location};
+
+    let uri = Url::parse("file:///S.move").unwrap();
+    let edit = |source: &str, name: &str, ability: &str| {
+        let start = buffer_position(source, source.find(name).unwrap());
+        let diagnostic = Diagnostic {
+            related_information: Some(vec![DiagnosticRelatedInformation {
+                location: Location::new(uri.clone(), Range::new(start, start)),
+                message: format!(
+                    "To satisfy the constraint, the '{}' ability would need to be added here",
+                    ability
+                ),
+            }]),
+            ..Diagnostic::new_simple(
+                Range::default(),
+                "'copy' constraint not satisifed".to_string(),
+            )
+        };
+        let source = source.to_string();
+        let actions = ability_actions(&diagnostic, &|u| (u == &uri).then(|| source.clone()));
+        actions.into_iter().next().map(|a| match a {
+            CodeActionOrCommand::CodeAction(a) => {
+                let mut changes = a.edit.unwrap().changes.unwrap();
+                let edit = changes.remove(&uri).unwrap().remove(0);
+                let start = byte_offset(&source, &edit.range.start);
+                let end = byte_offset(&source, &edit.range.end);
+                format!("{}{}{}", &source[..start], edit.new_text, &source[end..])
+            }
+            CodeActionOrCommand::Command(_) => panic!("unexpected command"),
+        })
+    };
+
+    assert_eq!(
+        edit("struct S { f: u64 }", "S ", "copy").unwrap(),
+        "struct S has copy { f: u64 }"
+    );
+    assert_eq!(
+        edit("struct S has drop, store {\n    f: u64\n}", "S ", "copy").unwrap(),
+        "struct S has drop, store, copy {\n    f: u64\n}"
+    );
+    assert_eq!(
+        edit("struct S<T: copy + drop> has key { f: T }", "S<", "store").unwrap(),
+        "struct S<T: copy + drop> has key, store { f: T }"
+    );
+    assert_eq!(
+        edit("native struct S has drop;", "S ", "copy").unwrap(),
+        "native struct S has drop, copy;"
+    );
+    // a type parameter
+    assert!(edit("fun f<T>(x: T): T { copy x }", "T>", "copy").is_none());
+    // the ability is already declared
+    assert!(edit("struct S has copy { f: u64 }", "S ", "copy").is_none());
+}
+
+#[test]
+fn fill_fields_action_test() {
+    use move_command_line_common::files::FileHash;
+    use move_ir_types::location::{sp, Loc};
+
+    let loc = Loc::new(FileHash::new(""), 0, 0);
+    let struct_fields = vec![
+        (Symbol::from("a"), Type_::u64(loc)),
+        (Symbol::from("b"), Type_::bool(loc)),
+        (
+            Symbol::from("c"),
+            Type_::builtin(loc, sp(loc, BuiltinTypeName_::Signer), vec![]),
+        ),
+    ];
+    let uri = Url::parse("file:///M.move").unwrap();
+    let edit = |buffer: &str, line, character| {
+        let literal = struct_literal(buffer, &Position { line, character }).unwrap();
+        match fill_fields_action(&uri, buffer, &literal, "M::S", &struct_fields) {
+            Some(CodeActionOrCommand::CodeAction(a)) => {
+                let mut changes = a.edit.unwrap().changes.unwrap();
+                changes.remove(&uri).unwrap().remove(0)
+            }
+            _ => panic!("expected a code action"),
+        }
+    };
+
+    let buffer = "fun f(): S { S { b: true } }";
+    let literal = struct_literal(buffer, &Position::new(0, 17)).unwrap();
+    assert_eq!(literal.path, vec!["S".to_string()]);
+    assert_eq!(literal.fields, vec!["b".to_string()]);
+    assert_eq!(
+        edit(buffer, 0, 17),
+        TextEdit {
+            range: Range::new(Position::new(0, 24), Position::new(0, 25)),
+            new_text: ", a: 0, c: abort 0 ".to_string(),
+        }
+    );
+
+    let buffer = "fun f(): S {\n    M::S<vector<u64>> {\n        a: 1,\n    }\n}";
+    let literal = struct_literal(buffer, &Position::new(2, 8)).unwrap();
+    assert_eq!(literal.path, vec!["M".to_string(), "S".to_string()]);
+    assert_eq!(
+        edit(buffer, 2, 8),
+        TextEdit {
+            range: Range::new(Position::new(2, 13), Position::new(3, 4)),
+            new_text: "\n        b: false,\n        c: abort 0,\n    ".to_string(),
+        }
+    );
+
+    // not a struct literal
+    assert!(struct_literal("if (x) { y }", &Position::new(0, 10)).is_none());
+    assert!(struct_literal("module M { }", &Position::new(0, 10)).is_none());
+    assert!(struct_literal("struct S has drop { }", &Position::new(0, 20)).is_none());
+}
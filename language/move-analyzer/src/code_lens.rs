@@ -0,0 +1,286 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Code lenses displayed above definitions. A lens is displayed above each unit test function to
+//! run this test, and above each function and struct definition to show references to it.
+//!
+//! Reference counts are only computed when the client resolves a lens (i.e., when it becomes
+//! visible). Test functions are found by scanning the current buffer contents rather than by looking them up
+//! in the compiled program, so that lenses are available even if the code being edited does not
+//! compile.
+
+use crate::{
+    context::Context,
+    symbols::Symbols,
+    unit_test::{RunTestArgs, RUN_TEST_COMMAND},
+};
+use lsp_server::Request;
+use lsp_types::{CodeLens, CodeLensParams, Command, Position, Range, SymbolKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, error};
+use url::Url;
+
+/// Client-side command showing a list of locations (arguments are the URI and position of the
+/// definition and the locations of its references)
+pub const SHOW_REFERENCES_COMMAND: &str = "move-analyzer.showReferences";
+
+/// Data attached to an unresolved lens showing references to a definition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct ReferencesLensData {
+    /// File containing the definition
+    uri: Url,
+    /// Position of the definition's identifier
+    position: Position,
+}
+
+/// Unit test function found in a buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFunction {
+    /// Name of the module containing the test
+    pub module_name: String,
+    /// Name of the test function
+    pub name: String,
+    /// Line of the `#[test]` attribute
+    pub line: u32,
+}
+
+/// Finds functions annotated with the `#[test]` attribute. This is a purely textual approximation
+/// that assumes an attribute and the function name to be on separate lines.
+pub fn test_functions(buffer: &str) -> Vec<TestFunction> {
+    let mut tests = vec![];
+    let mut module_name = None;
+    // line of the `#[test]` attribute applying to the next function
+    let mut test_attribute = None;
+    for (line, text) in buffer.lines().enumerate() {
+        let text = text.trim();
+        if let Some(decl) = text.strip_prefix("module ") {
+            module_name = decl
+                .split(|c: char| c.is_whitespace() || c == '{')
+                .find(|s| !s.is_empty())
+                .and_then(|name| name.rsplit("::").next())
+                .map(|name| name.to_string());
+            test_attribute = None;
+        } else if let Some(attributes) = text.strip_prefix("#[") {
+            if has_test_attribute(attributes) {
+                test_attribute.get_or_insert(line as u32);
+            }
+        } else if let Some(test_line) = test_attribute.take() {
+            let name = text.split_once("fun ").map(|(_, rest)| {
+                rest.split(|c: char| c == '(' || c == '<' || c.is_whitespace())
+                    .next()
+                    .unwrap_or_default()
+            });
+            if let (Some(module_name), Some(name)) = (&module_name, name) {
+                if !name.is_empty() {
+                    tests.push(TestFunction {
+                        module_name: module_name.clone(),
+                        name: name.to_string(),
+                        line: test_line,
+                    });
+                }
+            }
+        }
+    }
+    tests
+}
+
+/// Returns true if the list of attributes (e.g., `test, expected_failure]`) contains the `test`
+/// attribute (possibly with arguments)
+fn has_test_attribute(attributes: &str) -> bool {
+    // drop attribute arguments, keeping only (top-level) attribute names
+    let mut depth = 0;
+    let mut names = String::new();
+    for c in attributes.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ']' if depth == 0 => break,
+            c if depth == 0 => names.push(c),
+            _ => (),
+        }
+    }
+    names.split(',').any(|name| name.trim() == "test")
+}
+
+/// Returns lenses running unit tests defined in the given buffer
+fn test_lenses(buffer: &str, package_path: &Path) -> Vec<CodeLens> {
+    test_functions(buffer)
+        .into_iter()
+        .map(|test| {
+            let args = RunTestArgs {
+                package_path: package_path.to_path_buf(),
+                module_name: test.module_name,
+                test_name: test.name,
+            };
+            let position = Position::new(test.line, 0);
+            CodeLens {
+                range: Range::new(position, position),
+                command: Some(Command {
+                    title: "▶ Run test".to_string(),
+                    command: RUN_TEST_COMMAND.to_string(),
+                    arguments: Some(vec![serde_json::to_value(args).unwrap()]),
+                }),
+                data: None,
+            }
+        })
+        .collect()
+}
+
+/// Returns unresolved lenses showing references to functions and structs defined in a given file
+fn references_lenses(symbols: &Symbols, uri: &Url) -> Vec<CodeLens> {
+    let mut lenses = vec![];
+    for outline in symbols.file_outline(Path::new(uri.path())) {
+        for def in outline.children.iter().flatten() {
+            if def.kind != SymbolKind::Function && def.kind != SymbolKind::Struct {
+                continue;
+            }
+            let data = ReferencesLensData {
+                uri: uri.clone(),
+                position: def.selection_range.start,
+            };
+            lenses.push(CodeLens {
+                range: def.selection_range,
+                command: None,
+                data: Some(serde_json::to_value(data).unwrap()),
+            });
+        }
+    }
+    lenses
+}
+
+/// Computes the command of a lens showing references to a definition (other lenses are returned
+/// unchanged)
+fn resolve_lens(symbols: &Symbols, mut lens: CodeLens) -> CodeLens {
+    let data = match lens
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<ReferencesLensData>(data).ok())
+    {
+        Some(data) => data,
+        None => return lens,
+    };
+    let locations = symbols
+        .def_references(Path::new(data.uri.path()), &data.position)
+        .unwrap_or_default();
+    let title = match locations.len() {
+        1 => "1 reference".to_string(),
+        n => format!("{} references", n),
+    };
+    lens.command = Some(Command {
+        title,
+        command: SHOW_REFERENCES_COMMAND.to_string(),
+        arguments: Some(vec![
+            serde_json::to_value(&data.uri).unwrap(),
+            serde_json::to_value(data.position).unwrap(),
+            serde_json::to_value(locations).unwrap(),
+        ]),
+    });
+    lens
+}
+
+/// Returns the root directory of the package containing the given file (if any)
+pub fn package_path(fpath: &Path) -> Option<PathBuf> {
+    fpath
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Move.toml").is_file())
+        .map(|dir| dir.to_path_buf())
+}
+
+/// Handles code lens request of the language server
+pub fn on_code_lens_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<CodeLensParams>(request.params.clone())
+        .expect("could not deserialize code lens request");
+
+    let uri = parameters.text_document.uri;
+    let fpath = uri.path();
+    let mut lenses = match (context.files.get(fpath), package_path(Path::new(fpath))) {
+        (Some(buffer), Some(package_path)) => test_lenses(buffer, &package_path),
+        _ => vec![],
+    };
+    lenses.extend(references_lenses(symbols, &uri));
+
+    debug!("about to send code lens response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), lenses);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send code lens response: {:?}", err);
+    }
+}
+
+/// Handles code lens resolve request of the language server
+pub fn on_code_lens_resolve_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let lens = serde_json::from_value::<CodeLens>(request.params.clone())
+        .expect("could not deserialize code lens resolve request");
+
+    let lens = resolve_lens(symbols, lens);
+
+    debug!("about to send code lens resolve response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), lens);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send code lens resolve response: {:?}", err);
+    }
+}
+
+#[test]
+fn test_functions_test() {
+    let buffer = "module 0x1::M {\n    #[test]\n    fun t1() {}\n\n    #[test_only]\n    fun helper() {}\n\n    #[test(a = @0x1), expected_failure(abort_code = 1)]\n    #[allow]\n    public fun t2<T>(a: signer) {}\n}\n";
+    let tests = test_functions(buffer);
+    assert_eq!(
+        tests,
+        vec![
+            TestFunction {
+                module_name: "M".to_string(),
+                name: "t1".to_string(),
+                line: 1,
+            },
+            TestFunction {
+                module_name: "M".to_string(),
+                name: "t2".to_string(),
+                line: 7,
+            },
+        ]
+    );
+}
+
+#[test]
+/// Tests if references to functions and structs are counted in resolved lenses.
+fn references_lenses_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = crate::symbols::Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut mod_path = path.canonicalize().unwrap();
+    mod_path.push("sources/M6.move");
+    let uri = Url::from_file_path(&mod_path).unwrap();
+
+    let titles: Vec<_> = references_lenses(&symbols, &uri)
+        .into_iter()
+        .map(|lens| {
+            assert!(lens.command.is_none());
+            let lens = resolve_lens(&symbols, lens);
+            let command = lens.command.unwrap();
+            assert_eq!(command.command, SHOW_REFERENCES_COMMAND);
+            (lens.range.start.line, command.title)
+        })
+        .collect();
+    // DocumentedStruct
+    assert!(titles.contains(&(13, "3 references".to_string())));
+    // documented_fun
+    assert!(titles.contains(&(20, "1 reference".to_string())));
+    // failing_test
+    assert!(titles
+        .iter()
+        .any(|(_, title)| title.as_str() == "0 references"));
+}
@@ -2,15 +2,27 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::context::Context;
+use crate::{
+    code_action::{use_insertion_point, ImportIndex},
+    context::Context,
+    signature_help::{ident_start, skip_whitespace_back},
+    symbols::{self, Symbols},
+    vfs::{buffer_position, byte_offset},
+};
 use lsp_server::Request;
-use lsp_types::{CompletionItem, CompletionItemKind, CompletionParams, Position};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionParams, Documentation, InsertTextFormat,
+    MarkupContent, MarkupKind, Position, Range, TextEdit,
+};
 use move_command_line_common::files::FileHash;
 use move_compiler::parser::{
     keywords::{BUILTINS, CONTEXTUAL_KEYWORDS, KEYWORDS},
     lexer::{Lexer, Tok},
 };
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use tracing::{debug, error};
+use url::Url;
 
 /// Constructs an `lsp_types::CompletionItem` with the given `label` and `kind`.
 fn completion_item(label: &str, kind: CompletionItemKind) -> CompletionItem {
@@ -50,6 +62,47 @@ fn builtins() -> Vec<CompletionItem> {
         .collect()
 }
 
+/// Snippets for common Move constructs, as labels, descriptions, and snippet bodies (in the
+/// client's snippet syntax, where `$1`, `$2`, ... are tab stops, `${1:name}` is a tab stop with a
+/// placeholder, and `$0` is the final cursor position).
+const SNIPPETS: &[(&str, &str, &str)] = &[
+    (
+        "module",
+        "module declaration",
+        "module ${1:address}::${2:name} {\n\t$0\n}",
+    ),
+    (
+        "public fun",
+        "public function",
+        "public fun ${1:name}($2) {\n\t$0\n}",
+    ),
+    (
+        "struct has key",
+        "struct stored in global storage",
+        "struct ${1:Name} has key {\n\t${2:field}: ${3:u64},\n}",
+    ),
+    (
+        "#[test] fun",
+        "unit test",
+        "#[test]\nfun ${1:test_name}() {\n\t$0\n}",
+    ),
+    ("spec", "specification block", "spec ${1:name} {\n\t$0\n}"),
+];
+
+/// Return a list of completion items corresponding to snippets for common Move constructs (only
+/// offered to clients supporting snippets).
+fn snippets() -> Vec<CompletionItem> {
+    SNIPPETS
+        .iter()
+        .map(|(label, detail, body)| CompletionItem {
+            detail: Some(detail.to_string()),
+            insert_text: Some(body.to_string()),
+            insert_text_format: Some(InsertTextFormat::Snippet),
+            ..completion_item(label, CompletionItemKind::Snippet)
+        })
+        .collect()
+}
+
 /// Lexes the Move source file at the given path and returns a list of completion items
 /// corresponding to the non-keyword identifiers therein.
 ///
@@ -119,18 +172,464 @@ fn get_cursor_token(buffer: &str, position: &Position) -> Option<Tok> {
     }
 }
 
+/// Returns the position of the variable and the names of the fields accessed in the chain of field
+/// accesses preceding the `.` at the user's cursor (e.g., position of `s` and `["f"]` for `s.f.`).
+fn field_access(buffer: &str, position: &Position) -> Option<(Position, Vec<String>)> {
+    let bytes = buffer.as_bytes();
+    let offset = byte_offset(buffer, position);
+    if offset == 0 || bytes[offset - 1] != b'.' {
+        return None;
+    }
+    let mut names = vec![];
+    let mut end = offset - 1;
+    let root_start = loop {
+        end = skip_whitespace_back(bytes, end);
+        let start = ident_start(bytes, end);
+        if start == end || bytes[start].is_ascii_digit() {
+            return None;
+        }
+        names.push(buffer[start..end].to_string());
+        let sep = skip_whitespace_back(bytes, start);
+        if sep == 0 || bytes[sep - 1] != b'.' {
+            break start;
+        }
+        end = sep - 1;
+    };
+    // the root is a variable rather than a field
+    names.pop();
+    names.reverse();
+    Some((buffer_position(buffer, root_start), names))
+}
+
+/// Module declaration enclosing the user's cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EnclosingModule {
+    /// Line where the module declaration starts
+    line: usize,
+    /// Address of the module (if declared as part of the module name)
+    address: Option<String>,
+    /// Name of the module
+    name: String,
+}
+
+/// Returns the module declaration enclosing the given position (if any).
+fn enclosing_module(buffer: &str, position: &Position) -> Option<EnclosingModule> {
+    let (line, module_line) = buffer
+        .lines()
+        .take(position.line as usize + 1)
+        .map(str::trim_start)
+        .enumerate()
+        .filter(|(_, l)| l.starts_with("module "))
+        .last()?;
+    let name = module_line["module ".len()..]
+        .split(|c: char| c.is_whitespace() || c == '{')
+        .find(|s| !s.is_empty())?;
+    let (address, name) = match name.rsplit_once("::") {
+        Some((address, name)) => (Some(normalize_address(address)), name),
+        None => (None, name),
+    };
+    Some(EnclosingModule {
+        line,
+        address,
+        name: name.to_string(),
+    })
+}
+
+/// Normalizes the textual representation of an address so that numerical addresses written
+/// differently (e.g., `0x01` and `0x1`) compare equal.
+fn normalize_address(address: &str) -> String {
+    match address.strip_prefix("0x") {
+        Some(digits) => {
+            let digits = digits.trim_start_matches('0').to_ascii_lowercase();
+            format!("0x{}", if digits.is_empty() { "0" } else { &digits })
+        }
+        None => address.to_string(),
+    }
+}
+
+/// Kind of a module member
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MemberKind {
+    Function,
+    Struct,
+    Constant,
+}
+
+/// A module member offered as a completion after a module access path
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ModuleMember {
+    pub name: String,
+    pub kind: MemberKind,
+    /// Whether the member is accessible outside of its module
+    pub public: bool,
+}
+
+/// Modules and their members, keyed by module address and name, along with named addresses of
+/// the package graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModuleIndex {
+    modules: BTreeMap<String, BTreeMap<String, BTreeSet<ModuleMember>>>,
+    /// Named addresses mapped to their numerical values
+    named_addresses: BTreeMap<String, String>,
+    /// Documentation comments of module members, keyed by their fully qualified names
+    docs: BTreeMap<String, String>,
+}
+
+impl ModuleIndex {
+    /// Adds a module declared under the given addresses (a named address and its numerical value
+    /// both refer to the same module)
+    pub fn insert_module(&mut self, addresses: &[String], name: &str, members: Vec<ModuleMember>) {
+        for address in addresses {
+            self.modules
+                .entry(normalize_address(address))
+                .or_insert_with(BTreeMap::new)
+                .entry(name.to_string())
+                .or_insert_with(BTreeSet::new)
+                .extend(members.iter().cloned());
+        }
+    }
+
+    /// Returns members of the module with the given address and name
+    pub fn members(&self, address: &str, name: &str) -> Option<&BTreeSet<ModuleMember>> {
+        self.modules.get(&normalize_address(address))?.get(name)
+    }
+
+    /// Adds a named address with the given numerical value
+    pub fn insert_named_address(&mut self, name: &str, value: &str) {
+        self.named_addresses
+            .insert(name.to_string(), normalize_address(value));
+    }
+
+    /// Returns named addresses along with their numerical values
+    pub fn named_addresses(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.named_addresses.iter()
+    }
+
+    /// Adds the documentation comment of a member of a module declared under the given addresses
+    pub fn insert_doc(&mut self, addresses: &[String], module: &str, member: &str, doc: &str) {
+        for address in addresses {
+            self.docs
+                .insert(member_key(address, module, member), doc.to_string());
+        }
+    }
+
+    /// Returns the documentation comment of a member of the module with the given address and name
+    pub fn doc(&self, address: &str, module: &str, member: &str) -> Option<&String> {
+        self.docs.get(&member_key(address, module, member))
+    }
+
+    pub fn extend(&mut self, other: ModuleIndex) {
+        self.named_addresses.extend(other.named_addresses);
+        self.docs.extend(other.docs);
+        for (address, modules) in other.modules {
+            let address_modules = self.modules.entry(address).or_insert_with(BTreeMap::new);
+            for (name, members) in modules {
+                address_modules
+                    .entry(name)
+                    .or_insert_with(BTreeSet::new)
+                    .extend(members);
+            }
+        }
+    }
+}
+
+/// Returns the key of a module member in the index of documentation comments
+fn member_key(address: &str, module: &str, member: &str) -> String {
+    format!("{}::{}::{}", normalize_address(address), module, member)
+}
+
+/// Data attached to completion items whose documentation and edits are only computed once an item
+/// is resolved (computing them for all items would slow down completion for big dependency sets)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionData {
+    /// Address of the module that is (or that contains) the item
+    address: String,
+    /// Name of the module that is (or that contains) the item
+    module: String,
+    /// Document and line of the code using the module if the module has to be imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    import: Option<(Url, u32)>,
+}
+
+/// Returns the module access path preceding the `::` at the user's cursor (e.g., `["std",
+/// "vector"]` for `std::vector::`).
+fn access_path(buffer: &str, position: &Position) -> Option<Vec<String>> {
+    let bytes = buffer.as_bytes();
+    let offset = byte_offset(buffer, position);
+    if offset < 2 || &bytes[offset - 2..offset] != b"::" {
+        return None;
+    }
+    let mut path = vec![];
+    let mut end = offset - 2;
+    loop {
+        end = skip_whitespace_back(bytes, end);
+        let start = ident_start(bytes, end);
+        if start == end {
+            return None;
+        }
+        path.push(buffer[start..end].to_string());
+        let sep = skip_whitespace_back(bytes, start);
+        if sep < 2 || &bytes[sep - 2..sep] != b"::" {
+            break;
+        }
+        end = sep - 2;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Returns aliases of modules introduced by `use` declarations following the given line (e.g.,
+/// `vector` for `use std::vector;` and `V` for `use std::vector as V;`), mapped to module
+/// addresses and names.
+fn module_aliases(buffer: &str, line: usize) -> BTreeMap<String, (String, String)> {
+    let text = buffer.lines().skip(line).collect::<Vec<_>>().join("\n");
+    let mut aliases = BTreeMap::new();
+    for stmt in text.split(';') {
+        let decl = match stmt.rfind("use ") {
+            Some(idx)
+                if idx == 0 || stmt[..idx].ends_with(|c: char| c.is_whitespace() || c == '{') =>
+            {
+                &stmt[idx + "use ".len()..]
+            }
+            _ => continue,
+        };
+        let (path, items) = match decl.split_once("::{") {
+            Some((path, items)) => (path, Some(items.trim_end().trim_end_matches('}'))),
+            None => (decl, None),
+        };
+        let mut words = path.split_whitespace();
+        let segments = match words.next() {
+            Some(p) => p.split("::").collect::<Vec<_>>(),
+            None => continue,
+        };
+        let (address, module) = match segments[..] {
+            [address, module] => (normalize_address(address), module.to_string()),
+            _ => continue,
+        };
+        let mut add_alias = |alias: &str| {
+            aliases.insert(alias.to_string(), (address.clone(), module.clone()));
+        };
+        match items {
+            Some(items) => {
+                for item in items.split(',') {
+                    let mut words = item.split_whitespace();
+                    if words.next() == Some("Self") {
+                        match (words.next(), words.next()) {
+                            (Some("as"), Some(alias)) => add_alias(alias),
+                            _ => add_alias(&module),
+                        }
+                    }
+                }
+            }
+            None => match (words.next(), words.next()) {
+                (Some("as"), Some(alias)) => add_alias(alias),
+                _ => add_alias(&module),
+            },
+        }
+    }
+    aliases
+}
+
+/// Returns completion items for modules or module members that can follow the module access path
+/// preceding the `::` at the user's cursor, or `None` if the path cannot be resolved.
+fn module_members(
+    buffer: &str,
+    position: &Position,
+    index: &ModuleIndex,
+) -> Option<Vec<CompletionItem>> {
+    let path = access_path(buffer, position)?;
+    let enclosing = enclosing_module(buffer, position);
+    let (address, module) = match &path[..] {
+        [name] if name == "Self" => {
+            let enclosing = enclosing.as_ref()?;
+            (enclosing.address.clone()?, enclosing.name.clone())
+        }
+        [name] => {
+            let aliases = module_aliases(buffer, enclosing.as_ref().map_or(0, |m| m.line));
+            match aliases.get(name) {
+                Some((address, module)) => (address.clone(), module.clone()),
+                None => {
+                    // modules declared under an address
+                    let modules = index.modules.get(&normalize_address(name))?;
+                    return Some(
+                        modules
+                            .keys()
+                            .map(|m| completion_item(m, CompletionItemKind::Module))
+                            .collect(),
+                    );
+                }
+            }
+        }
+        [address, module] => (normalize_address(address), module.clone()),
+        _ => return None,
+    };
+    let members = index.members(&address, &module)?;
+    // private members are only accessible in their own module
+    let is_enclosing = enclosing.map_or(false, |m| {
+        m.address.as_ref() == Some(&address) && m.name == module
+    });
+    Some(
+        members
+            .iter()
+            .filter(|m| m.public || is_enclosing)
+            .map(|m| {
+                let kind = match m.kind {
+                    MemberKind::Function => CompletionItemKind::Function,
+                    MemberKind::Struct => CompletionItemKind::Struct,
+                    MemberKind::Constant => CompletionItemKind::Constant,
+                };
+                CompletionItem {
+                    data: serde_json::to_value(CompletionData {
+                        address: address.clone(),
+                        module: module.clone(),
+                        import: None,
+                    })
+                    .ok(),
+                    ..completion_item(&m.name, kind)
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Returns completion items for modules that can be imported into the module (or script) enclosing
+/// the user's cursor, that is modules not yet imported there under their own names.
+fn importable_modules(
+    buffer: &str,
+    uri: &Url,
+    position: &Position,
+    index: &ImportIndex,
+) -> Vec<CompletionItem> {
+    let enclosing = enclosing_module(buffer, position);
+    let aliases = module_aliases(buffer, enclosing.as_ref().map_or(0, |m| m.line));
+    index
+        .modules()
+        .filter(|(name, _)| !aliases.contains_key(*name))
+        .filter_map(|(name, qualified_name)| {
+            let (address, module) = qualified_name.rsplit_once("::")?;
+            let address = normalize_address(address);
+            // a module cannot import itself
+            if enclosing.as_ref().map_or(false, |m| {
+                m.address.as_ref() == Some(&address) && m.name == module
+            }) {
+                return None;
+            }
+            Some(CompletionItem {
+                detail: Some(qualified_name.clone()),
+                data: serde_json::to_value(CompletionData {
+                    address,
+                    module: module.to_string(),
+                    import: Some((uri.clone(), position.line)),
+                })
+                .ok(),
+                ..completion_item(name, CompletionItemKind::Module)
+            })
+        })
+        .collect()
+}
+
+/// Computes documentation and edits of a completion item, using the current contents of the buffer
+/// in which the completion was requested
+fn resolve_completion_item(
+    mut item: CompletionItem,
+    buffer: Option<&str>,
+    index: &ModuleIndex,
+) -> CompletionItem {
+    let data = match item
+        .data
+        .take()
+        .and_then(|data| serde_json::from_value::<CompletionData>(data).ok())
+    {
+        Some(data) => data,
+        None => return item,
+    };
+    match &data.import {
+        Some((_, line)) => {
+            if let Some((position, indent)) = buffer.and_then(|b| use_insertion_point(b, *line)) {
+                item.additional_text_edits = Some(vec![TextEdit {
+                    range: Range::new(position, position),
+                    new_text: format!("{}use {}::{};\n", indent, data.address, data.module),
+                }]);
+            }
+        }
+        None => {
+            if let Some(doc) = index.doc(&data.address, &data.module, &item.label) {
+                item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: doc.clone(),
+                }));
+            }
+        }
+    }
+    item
+}
+
+/// Returns completion items for named addresses if the user's cursor is positioned at the address
+/// of a `use` declaration (e.g., `use ` or `use st`), or `None` otherwise.
+fn use_addresses(
+    buffer: &str,
+    position: &Position,
+    index: &ModuleIndex,
+) -> Option<Vec<CompletionItem>> {
+    let bytes = buffer.as_bytes();
+    let offset = byte_offset(buffer, position);
+    let start = ident_start(bytes, offset);
+    let end = skip_whitespace_back(bytes, start);
+    // `use` must be a keyword followed by whitespace rather than a part of a longer identifier
+    if end == start || &buffer[ident_start(bytes, end)..end] != "use" {
+        return None;
+    }
+    Some(
+        index
+            .named_addresses
+            .iter()
+            .map(|(name, value)| CompletionItem {
+                detail: Some(value.clone()),
+                ..completion_item(name, CompletionItemKind::Module)
+            })
+            .collect(),
+    )
+}
+
+/// Returns completion items for fields of the struct value preceding the `.` at the user's cursor,
+/// or `None` if the type of this value is not known.
+fn fields(
+    buffer: &str,
+    position: &Position,
+    path: &str,
+    symbols: &Symbols,
+) -> Option<Vec<CompletionItem>> {
+    let (root_start, fields) = field_access(buffer, position)?;
+    let mod_name = enclosing_module(buffer, position).map(|m| m.name);
+    let fields =
+        symbols::field_completions(symbols, path, &root_start, &fields, mod_name.as_deref());
+    if fields.is_empty() {
+        return None;
+    }
+    Some(
+        fields
+            .into_iter()
+            .map(|(name, ty)| CompletionItem {
+                detail: Some(ty),
+                ..completion_item(&name, CompletionItemKind::Field)
+            })
+            .collect(),
+    )
+}
+
 /// Sends the given connection a response to a completion request.
 ///
 /// The completions returned depend upon where the user's cursor is positioned.
-pub fn on_completion_request(context: &Context, request: &Request) {
-    eprintln!("handling completion request");
+pub fn on_completion_request(context: &Context, request: &Request, symbols: &Symbols) {
+    debug!("handling completion request");
     let parameters = serde_json::from_value::<CompletionParams>(request.params.clone())
         .expect("could not deserialize completion request");
 
     let path = parameters.text_document_position.text_document.uri.path();
     let buffer = context.files.get(path);
     if buffer.is_none() {
-        eprintln!("Could not read '{}' when handling completion request", path);
+        error!("Could not read '{}' when handling completion request", path);
     }
 
     // The completion items we provide depend upon where the user's cursor is positioned.
@@ -138,6 +637,28 @@ pub fn on_completion_request(context: &Context, request: &Request) {
         buffer.and_then(|buf| get_cursor_token(buf, &parameters.text_document_position.position));
 
     let mut items = vec![];
+    // fields of a struct value are the only valid completions following `.`, modules or module
+    // members are the only valid completions following `::`, and named addresses are the only
+    // valid completions following `use`
+    let semantic_items = match (cursor, buffer) {
+        (Some(Tok::Period), Some(buffer)) => fields(
+            buffer,
+            &parameters.text_document_position.position,
+            path,
+            symbols,
+        ),
+        (Some(Tok::ColonColon), Some(buffer)) => module_members(
+            buffer,
+            &parameters.text_document_position.position,
+            symbols.module_index(),
+        ),
+        (None, Some(buffer)) => use_addresses(
+            buffer,
+            &parameters.text_document_position.position,
+            symbols.module_index(),
+        ),
+        _ => None,
+    };
     match cursor {
         Some(Tok::Colon) => {
             // If the user's cursor is positioned after a single `:`, do not provide any completion
@@ -148,27 +669,278 @@ pub fn on_completion_request(context: &Context, request: &Request) {
             // `.` or `::` must be followed by identifiers, which are added to the completion items
             // below.
         }
+        None if semantic_items.is_some() => {
+            // only named addresses can follow `use`
+        }
         _ => {
             // If the user's cursor is positioned anywhere other than following a `.`, `:`, or `::`,
-            // offer them Move's keywords, operators, and builtins as completion items.
+            // offer them Move's keywords, operators, and builtins (and snippets, if the client
+            // supports them) as completion items.
             items.extend_from_slice(&keywords());
             items.extend_from_slice(&builtins());
+            if context.snippet_support {
+                items.extend_from_slice(&snippets());
+            }
         }
     }
 
-    if let Some(buffer) = &buffer {
+    if let Some(semantic_items) = semantic_items {
+        items.extend(semantic_items);
+    } else if let Some(buffer) = &buffer {
         let identifiers = identifiers(buffer);
         items.extend_from_slice(&identifiers);
+        if cursor.is_none() {
+            items.extend(importable_modules(
+                buffer,
+                &parameters.text_document_position.text_document.uri,
+                &parameters.text_document_position.position,
+                symbols.import_index(),
+            ));
+        }
+    }
+
+    if context.is_cancelled(&request.id) {
+        context.send_cancelled_response(request.id.clone());
+        return;
     }
 
     let result = serde_json::to_value(items).expect("could not serialize completion response");
-    eprintln!("about to send completion response");
+    debug!("about to send completion response");
     let response = lsp_server::Response::new_ok(request.id.clone(), result);
     if let Err(err) = context
         .connection
         .sender
         .send(lsp_server::Message::Response(response))
     {
-        eprintln!("could not send completion response: {:?}", err);
+        error!("could not send completion response: {:?}", err);
+    }
+}
+
+/// Sends the given connection a response to a completion item resolve request.
+pub fn on_completion_resolve_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let item = serde_json::from_value::<CompletionItem>(request.params.clone())
+        .expect("could not deserialize completion resolve request");
+
+    let uri = item
+        .data
+        .as_ref()
+        .and_then(|data| serde_json::from_value::<CompletionData>(data.clone()).ok())
+        .and_then(|data| data.import.map(|(uri, _)| uri));
+    let buffer = uri.as_ref().and_then(|uri| context.files.get(uri.path()));
+    let item = resolve_completion_item(item, buffer, symbols.module_index());
+
+    debug!("about to send completion resolve response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), item);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send completion resolve response: {:?}", err);
+    }
+}
+
+#[test]
+fn field_access_test() {
+    let buffer = "module 0x1::M {\n    fun f() {\n        s.inner . \n        1.\n    }\n}\n";
+    assert_eq!(
+        field_access(buffer, &Position::new(2, 17)),
+        Some((Position::new(2, 8), vec!["inner".to_string()]))
+    );
+    assert_eq!(
+        field_access(buffer, &Position::new(2, 10)),
+        Some((Position::new(2, 8), vec![]))
+    );
+    // not preceded by a `.`
+    assert_eq!(field_access(buffer, &Position::new(2, 9)), None);
+    // not a field access
+    assert_eq!(field_access(buffer, &Position::new(3, 10)), None);
+    assert_eq!(
+        enclosing_module(buffer, &Position::new(2, 17)),
+        Some(EnclosingModule {
+            line: 0,
+            address: Some("0x1".to_string()),
+            name: "M".to_string(),
+        })
+    );
+}
+
+#[test]
+fn module_members_test() {
+    let member = |name: &str, kind, public| ModuleMember {
+        name: name.to_string(),
+        kind,
+        public,
+    };
+    let mut index = ModuleIndex::default();
+    index.insert_module(
+        &["std".to_string(), "0x1".to_string()],
+        "vector",
+        vec![
+            member("push_back", MemberKind::Function, true),
+            member("E_INDEX", MemberKind::Constant, false),
+        ],
+    );
+    index.insert_module(
+        &["0xCAFE".to_string()],
+        "M",
+        vec![
+            member("S", MemberKind::Struct, true),
+            member("helper", MemberKind::Function, false),
+        ],
+    );
+
+    let labels = |buffer: &str, line, character| {
+        module_members(buffer, &Position::new(line, character), &index)
+            .map(|items| items.into_iter().map(|i| i.label).collect::<Vec<_>>())
+    };
+
+    let buffer = "module 0xcafe::M {\n    use std::vector as V;\n    fun f() {\n        V::;\n        std::;\n        0x01::vector::;\n        Self::;\n        foo::;\n    }\n}\n";
+    // module alias
+    assert_eq!(labels(buffer, 3, 11), Some(vec!["push_back".to_string()]));
+    // modules under an address
+    assert_eq!(labels(buffer, 4, 13), Some(vec!["vector".to_string()]));
+    // fully qualified module
+    assert_eq!(labels(buffer, 5, 22), Some(vec!["push_back".to_string()]));
+    // private members of the enclosing module are accessible
+    assert_eq!(
+        labels(buffer, 6, 14),
+        Some(vec!["S".to_string(), "helper".to_string()])
+    );
+    // unknown module
+    assert_eq!(labels(buffer, 7, 13), None);
+
+    let aliases = module_aliases(
+        "module 0x1::N {\n    use std::vector::{Self as Vec, push_back};\n    use 0x1::M;\n}\n",
+        0,
+    );
+    assert_eq!(
+        aliases.get("Vec"),
+        Some(&("std".to_string(), "vector".to_string()))
+    );
+    assert_eq!(
+        aliases.get("M"),
+        Some(&("0x1".to_string(), "M".to_string()))
+    );
+    assert_eq!(aliases.get("push_back"), None);
+}
+
+#[test]
+fn use_addresses_test() {
+    let mut index = ModuleIndex::default();
+    index.insert_named_address("std", "0x1");
+    index.insert_named_address("Symbols", "0xCAFE");
+    index.insert_module(&["std".to_string(), "0x1".to_string()], "vector", vec![]);
+
+    let items = |buffer: &str, line, character| {
+        use_addresses(buffer, &Position::new(line, character), &index).map(|items| {
+            items
+                .into_iter()
+                .map(|i| (i.label, i.detail.unwrap()))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let buffer = "module 0x1::M {
+    use 
+    use st
+    reuse 
+    use std::
+}
+";
+    let addresses = Some(vec![
+        ("Symbols".to_string(), "0xcafe".to_string()),
+        ("std".to_string(), "0x1".to_string()),
+    ]);
+    assert_eq!(items(buffer, 1, 8), addresses);
+    assert_eq!(items(buffer, 2, 10), addresses);
+    // not a `use` keyword
+    assert_eq!(items(buffer, 3, 10), None);
+    // `use` not followed by whitespace
+    assert_eq!(items(buffer, 1, 7), None);
+    // modules follow the address
+    assert_eq!(
+        module_members(buffer, &Position::new(4, 13), &index)
+            .map(|items| items.into_iter().map(|i| i.label).collect::<Vec<_>>()),
+        Some(vec!["vector".to_string()])
+    );
+}
+
+#[test]
+fn resolve_completion_item_test() {
+    let mut index = ModuleIndex::default();
+    index.insert_module(
+        &["std".to_string(), "0x1".to_string()],
+        "vector",
+        vec![ModuleMember {
+            name: "empty".to_string(),
+            kind: MemberKind::Function,
+            public: true,
+        }],
+    );
+    index.insert_doc(
+        &["std".to_string(), "0x1".to_string()],
+        "vector",
+        "empty",
+        "Create an empty vector.",
+    );
+    let mut import_index = ImportIndex::default();
+    import_index.insert_module("vector", "std::vector".to_string());
+    import_index.insert_module("option", "std::option".to_string());
+
+    let uri = Url::parse("file:///M.move").unwrap();
+    let buffer = "module 0x1::M {\n    use std::option;\n\n    fun f() { vec }\n    fun g() { std::vector:: }\n}\n";
+
+    // members get documentation once resolved
+    let members = module_members(buffer, &Position::new(4, 27), &index).unwrap();
+    assert!(members.iter().all(|i| i.documentation.is_none()));
+    let resolved = resolve_completion_item(members[0].clone(), Some(buffer), &index);
+    assert_eq!(
+        resolved.documentation,
+        Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: "Create an empty vector.".to_string(),
+        }))
+    );
+    assert!(resolved.data.is_none());
+
+    // modules that are not imported yet get a `use` declaration once resolved
+    let modules = importable_modules(buffer, &uri, &Position::new(3, 17), &import_index);
+    assert_eq!(
+        modules.iter().map(|i| i.label.as_str()).collect::<Vec<_>>(),
+        vec!["vector"]
+    );
+    assert!(modules[0].additional_text_edits.is_none());
+    let resolved = resolve_completion_item(modules[0].clone(), Some(buffer), &index);
+    assert_eq!(
+        resolved.additional_text_edits,
+        Some(vec![TextEdit {
+            range: Range::new(Position::new(2, 0), Position::new(2, 0)),
+            new_text: "    use std::vector;\n".to_string(),
+        }])
+    );
+}
+
+#[test]
+fn snippets_test() {
+    let snippets = snippets();
+    assert_eq!(
+        snippets
+            .iter()
+            .map(|s| s.label.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "module",
+            "public fun",
+            "struct has key",
+            "#[test] fun",
+            "spec"
+        ]
+    );
+    for snippet in &snippets {
+        assert_eq!(snippet.kind, Some(CompletionItemKind::Snippet));
+        assert_eq!(snippet.insert_text_format, Some(InsertTextFormat::Snippet));
+        // every snippet has at least one tab stop
+        assert!(snippet.insert_text.as_ref().unwrap().contains("${1:"));
     }
 }
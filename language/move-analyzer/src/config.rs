@@ -0,0 +1,151 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! User settings affecting how packages are built and how files are formatted, received from the
+//! client either as initialization options or via the `workspace/didChangeConfiguration`
+//! notification (as the `move-analyzer` section of the settings). A change of settings triggers
+//! re-symbolication.
+
+use crate::{context::Context, formatting::FormatConfig, symbols::SymbolicatorRunner};
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use lsp_server::{Message, Notification};
+use lsp_types::{
+    notification::Notification as _, DidChangeConfigurationParams, MessageType, ShowMessageParams,
+};
+use move_command_line_common::address::NumericalAddress;
+use move_package::BuildConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tracing::error;
+
+/// Section of the client's settings containing settings of the language server
+pub const CONFIG_SECTION: &str = "move-analyzer";
+
+/// Settings of the language server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    /// Values of named addresses (in addition to those assigned in package manifests)
+    pub named_addresses: BTreeMap<String, String>,
+    /// Whether packages are built in dev mode (using their dev addresses and dependencies)
+    pub dev_mode: bool,
+    /// Additional flags passed to the package build, as they would be passed on the command line
+    /// (e.g., `["--arch", "move"]`)
+    pub compiler_flags: Vec<String>,
+    /// Settings of the formatter
+    pub formatting: FormatConfig,
+}
+
+impl Config {
+    /// Reads settings from the language server's section of the client's settings
+    pub fn from_settings(settings: serde_json::Value) -> Result<Self> {
+        serde_json::from_value(settings).map_err(|err| anyhow!("invalid settings: {}", err))
+    }
+
+    /// Returns the configuration of package builds corresponding to these settings
+    pub fn build_config(&self) -> Result<BuildConfig> {
+        let mut build_config = BuildConfig::try_parse_from(
+            std::iter::once("move-analyzer").chain(self.compiler_flags.iter().map(|f| f.as_str())),
+        )
+        .map_err(|err| anyhow!("invalid compiler flags: {}", err))?;
+        build_config.dev_mode |= self.dev_mode;
+        for (name, value) in &self.named_addresses {
+            let address = NumericalAddress::parse_str(value)
+                .map_err(|err| anyhow!("invalid value of named address '{}': {}", name, err))?;
+            build_config
+                .additional_named_addresses
+                .insert(name.clone(), address.into_inner());
+        }
+        Ok(build_config)
+    }
+}
+
+/// Handles a change of the client's settings
+pub fn on_did_change_configuration_notification(
+    context: &mut Context,
+    symbolicator_runner: &SymbolicatorRunner,
+    notification: &Notification,
+) {
+    let parameters =
+        serde_json::from_value::<DidChangeConfigurationParams>(notification.params.clone())
+            .expect("could not deserialize did change configuration notification");
+
+    // clients that expect servers to pull settings send no settings at all
+    let settings = match parameters.settings.get(CONFIG_SECTION) {
+        Some(settings) => settings.clone(),
+        None => return,
+    };
+    match Config::from_settings(settings)
+        .and_then(|config| Ok((config.build_config()?, config.formatting)))
+    {
+        Ok((build_config, format_config)) => {
+            symbolicator_runner.set_build_config(build_config);
+            context.format_config = format_config;
+        }
+        Err(err) => {
+            // keep the previous settings
+            let params = ShowMessageParams {
+                typ: MessageType::Error,
+                message: format!("{}", err),
+            };
+            let notification = Notification::new(
+                lsp_types::notification::ShowMessage::METHOD.to_string(),
+                params,
+            );
+            if let Err(err) = context
+                .connection
+                .sender
+                .send(Message::Notification(notification))
+            {
+                error!("could not send settings error: {:?}", err);
+            }
+        }
+    }
+}
+
+#[test]
+fn build_config_test() {
+    let settings = serde_json::json!({
+        "namedAddresses": { "Std": "0x1", "Symbols": "42" },
+        "compilerFlags": ["--arch", "move"],
+        // settings used by the client only are ignored
+        "server": { "path": "move-analyzer" },
+    });
+    let config = Config::from_settings(settings).unwrap();
+    assert!(!config.dev_mode);
+    let build_config = config.build_config().unwrap();
+    assert!(!build_config.dev_mode);
+    assert!(build_config.architecture.is_some());
+    assert_eq!(
+        build_config.additional_named_addresses["Std"],
+        NumericalAddress::parse_str("0x1").unwrap().into_inner()
+    );
+    assert_eq!(build_config.additional_named_addresses.len(), 2);
+
+    assert_eq!(config.formatting, FormatConfig::default());
+
+    let config = Config::from_settings(serde_json::json!({ "devMode": true })).unwrap();
+    assert!(config.build_config().unwrap().dev_mode);
+
+    let config = Config::from_settings(serde_json::json!({
+        "formatting": { "maxLineLength": 80 },
+    }))
+    .unwrap();
+    assert_eq!(config.formatting.max_line_length, 80);
+    assert!(config.formatting.sort_uses);
+
+    let config = Config::from_settings(serde_json::json!({
+        "namedAddresses": { "Std": "not an address" },
+    }))
+    .unwrap();
+    assert!(config.build_config().is_err());
+
+    let config = Config::from_settings(serde_json::json!({
+        "compilerFlags": ["--no-such-flag"],
+    }))
+    .unwrap();
+    assert!(config.build_config().is_err());
+
+    assert!(Config::from_settings(serde_json::json!({ "devMode": "yes" })).is_err());
+}
@@ -2,9 +2,20 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{symbols::Symbols, vfs::VirtualFileSystem};
-use lsp_server::Connection;
-use std::sync::{Arc, Mutex};
+use crate::{formatting::FormatConfig, symbols::Symbols, vfs::VirtualFileSystem};
+use lsp_server::{Connection, Message, RequestId};
+use lsp_types::{notification::Notification as _, CancelParams, NumberOrString};
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    sync::{Arc, Mutex, MutexGuard, TryLockError},
+    thread,
+    time::Duration,
+};
+use tracing::{debug, error};
+
+/// Interval between consecutive attempts to acquire the symbols lock by a request handler.
+const SYMBOLS_LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
 
 /// The context within which the language server is running.
 pub struct Context {
@@ -14,4 +25,116 @@ pub struct Context {
     pub files: VirtualFileSystem,
     /// Symbolication information
     pub symbols: Arc<Mutex<Symbols>>,
+    /// Messages received from the client while checking for request cancellation, which are yet
+    /// to be processed by the main loop.
+    pub pending_messages: RefCell<VecDeque<Message>>,
+    /// Requests that the client has cancelled.
+    pub cancelled_requests: RefCell<HashSet<RequestId>>,
+    /// Whether the client supports snippets (with tab stops) in completion items.
+    pub snippet_support: bool,
+    /// Settings of the formatter
+    pub format_config: FormatConfig,
+}
+
+impl Context {
+    /// Returns true if the client has cancelled the request with the given ID.
+    ///
+    /// Request handlers run on the same thread as the main loop, so a cancellation notification
+    /// can only be seen by a handler if it checks for one itself. All other messages received
+    /// while checking are retained (in order) for the main loop.
+    pub fn is_cancelled(&self, id: &RequestId) -> bool {
+        while let Ok(message) = self.connection.receiver.try_recv() {
+            match message {
+                Message::Notification(notification)
+                    if notification.method == lsp_types::notification::Cancel::METHOD =>
+                {
+                    self.on_cancel_notification(notification.params)
+                }
+                message => self.pending_messages.borrow_mut().push_back(message),
+            }
+        }
+        self.cancelled_requests.borrow().contains(id)
+    }
+
+    /// Records the cancellation of a request.
+    pub fn on_cancel_notification(&self, params: serde_json::Value) {
+        match serde_json::from_value::<CancelParams>(params) {
+            Ok(params) => {
+                let id = match params.id {
+                    NumberOrString::Number(n) => RequestId::from(n),
+                    NumberOrString::String(s) => RequestId::from(s),
+                };
+                self.cancelled_requests.borrow_mut().insert(id);
+            }
+            Err(err) => error!("could not deserialize cancel notification: {:?}", err),
+        }
+    }
+
+    /// Acquires the lock protecting symbolication information on behalf of the request with the
+    /// given ID. Returns `None` if the request gets cancelled while waiting for the lock (e.g.,
+    /// when the symbolicator is updating symbols).
+    pub fn lock_symbols(&self, id: &RequestId) -> Option<MutexGuard<'_, Symbols>> {
+        loop {
+            match self.symbols.try_lock() {
+                Ok(symbols) => return Some(symbols),
+                Err(TryLockError::WouldBlock) => {
+                    if self.is_cancelled(id) {
+                        return None;
+                    }
+                    thread::sleep(SYMBOLS_LOCK_RETRY_INTERVAL);
+                }
+                Err(TryLockError::Poisoned(err)) => panic!("symbols lock poisoned: {:?}", err),
+            }
+        }
+    }
+
+    /// Responds to a cancelled request.
+    pub fn send_cancelled_response(&self, id: RequestId) {
+        debug!("request {:?} cancelled", id);
+        let response = lsp_server::Response::new_err(
+            id,
+            lsp_server::ErrorCode::RequestCanceled as i32,
+            "request cancelled".to_string(),
+        );
+        if let Err(err) = self
+            .connection
+            .sender
+            .send(lsp_server::Message::Response(response))
+        {
+            error!("could not send cancelled response: {:?}", err);
+        }
+    }
+}
+
+#[test]
+fn is_cancelled_test() {
+    use lsp_server::{Notification, Request};
+
+    let (connection, client) = Connection::memory();
+    let context = Context {
+        connection,
+        files: VirtualFileSystem::default(),
+        symbols: Arc::new(Mutex::new(crate::symbols::Symbolicator::empty_symbols())),
+        pending_messages: RefCell::new(VecDeque::new()),
+        cancelled_requests: RefCell::new(HashSet::new()),
+        snippet_support: false,
+        format_config: FormatConfig::default(),
+    };
+    let send = |message: Message| client.sender.send(message).unwrap();
+
+    let request = Request::new(RequestId::from(1), "textDocument/hover".to_string(), ());
+    send(Message::Request(request));
+    send(Message::Notification(Notification::new(
+        lsp_types::notification::Cancel::METHOD.to_string(),
+        CancelParams {
+            id: NumberOrString::Number(0),
+        },
+    )));
+
+    assert!(context.is_cancelled(&RequestId::from(0)));
+    assert!(!context.is_cancelled(&RequestId::from(1)));
+    // messages other than cancellations are retained
+    let pending = context.pending_messages.borrow();
+    assert_eq!(pending.len(), 1);
+    assert!(matches!(&pending[0], Message::Request(r) if r.id == RequestId::from(1)));
 }
@@ -0,0 +1,188 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Custom `move-analyzer/dependencyGraph` request returning the graph of dependencies between
+//! modules of the package containing a file (and of the packages it depends on), e.g., to be
+//! rendered by an editor extension.
+//!
+//! Dependencies are read from the compiled modules, so the graph reflects the package as it is
+//! on disk. Compilation may take a long time, so it happens on a separate thread and the request is
+//! answered once it finishes.
+
+use crate::{bytecode::compile_package, code_lens::package_path, context::Context};
+use anyhow::Result;
+use lsp_server::{Message, Request, Response};
+use lsp_types::TextDocumentIdentifier;
+use move_binary_format::access::ModuleAccess;
+use move_compiler::compiled_unit::CompiledUnit;
+use move_core_types::language_storage::ModuleId;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path, thread};
+use tracing::{debug, error};
+use url::Url;
+
+/// The `move-analyzer/dependencyGraph` request
+pub enum DependencyGraphRequest {}
+
+impl lsp_types::request::Request for DependencyGraphRequest {
+    type Params = TextDocumentIdentifier;
+    type Result = Option<DependencyGraph>;
+    const METHOD: &'static str = "move-analyzer/dependencyGraph";
+}
+
+/// Graph of dependencies between modules
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyGraph {
+    /// Modules of the package and of its dependencies
+    pub nodes: Vec<ModuleNode>,
+    /// Dependencies between modules
+    pub edges: Vec<DependencyEdge>,
+    /// Named addresses of the package along with their values
+    pub addresses: BTreeMap<String, String>,
+}
+
+/// A module in the dependency graph
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleNode {
+    /// Identifier of the module (e.g., `0x1::vector`), which edges refer to
+    pub id: String,
+    /// Address of the module
+    pub address: String,
+    /// Name of the module
+    pub name: String,
+    /// Name of the package containing the module
+    pub package: String,
+    /// Whether the module belongs to a dependency rather than to the package itself
+    pub is_dependency: bool,
+    /// Source file of the module
+    pub uri: Option<Url>,
+}
+
+/// A dependency of a module on another module
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyEdge {
+    /// Identifier of the module having the dependency
+    pub from: String,
+    /// Identifier of the module it depends on
+    pub to: String,
+    /// Whether the dependency is a friend declaration (of `to` by `from`) rather than a use
+    pub friend: bool,
+}
+
+/// Compiles the package rooted at the given path and returns the graph of dependencies between its
+/// modules (and modules of its dependencies).
+pub fn dependency_graph(package_path: &Path) -> Result<DependencyGraph> {
+    let package = compile_package(package_path)?;
+    let root_name = package.compiled_package_info.package_name;
+    let units = package
+        .root_compiled_units
+        .iter()
+        .map(|unit| (root_name, unit))
+        .chain(
+            package
+                .deps_compiled_units
+                .iter()
+                .map(|(name, unit)| (*name, unit)),
+        );
+
+    let id = |module_id: &ModuleId| module_id.short_str_lossless();
+    let mut graph = DependencyGraph {
+        nodes: vec![],
+        edges: vec![],
+        addresses: package
+            .compiled_package_info
+            .address_alias_instantiation
+            .iter()
+            .map(|(name, addr)| (name.to_string(), format!("0x{}", addr.short_str_lossless())))
+            .collect(),
+    };
+    for (package_name, unit_with_source) in units {
+        let module = match &unit_with_source.unit {
+            CompiledUnit::Module(named_module) => &named_module.module,
+            CompiledUnit::Script(_) => continue,
+        };
+        let self_id = module.self_id();
+        graph.nodes.push(ModuleNode {
+            id: id(&self_id),
+            address: format!("0x{}", self_id.address().short_str_lossless()),
+            name: self_id.name().to_string(),
+            package: package_name.to_string(),
+            is_dependency: package_name != root_name,
+            uri: Url::from_file_path(&unit_with_source.source_path).ok(),
+        });
+        let uses = module
+            .immediate_dependencies()
+            .into_iter()
+            .map(|dep| (dep, false));
+        let friends = module
+            .immediate_friends()
+            .into_iter()
+            .map(|friend| (friend, true));
+        graph
+            .edges
+            .extend(uses.chain(friends).map(|(to, friend)| DependencyEdge {
+                from: id(&self_id),
+                to: id(&to),
+                friend,
+            }));
+    }
+    Ok(graph)
+}
+
+/// Handles dependency graph request of the language server
+pub fn on_dependency_graph_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<TextDocumentIdentifier>(request.params.clone())
+        .expect("could not deserialize dependency graph request");
+    let package_path = package_path(Path::new(parameters.uri.path()));
+
+    let sender = context.connection.sender.clone();
+    let id = request.id.clone();
+    thread::spawn(move || {
+        let response = match package_path {
+            Some(package_path) => match dependency_graph(&package_path) {
+                Ok(graph) => Response::new_ok(id, Some(graph)),
+                Err(err) => Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::InternalError as i32,
+                    format!("could not compute dependency graph: {}", err),
+                ),
+            },
+            None => Response::new_ok(id, None::<DependencyGraph>),
+        };
+        debug!("about to send dependency graph response");
+        if let Err(err) = sender.send(Message::Response(response)) {
+            error!("could not send dependency graph response: {:?}", err);
+        }
+    });
+}
+
+#[test]
+/// Tests if dependencies between modules of a package and of its dependencies are found.
+fn dependency_graph_test() {
+    let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let graph = dependency_graph(&path).unwrap();
+    let node = |id: &str| graph.nodes.iter().find(|node| node.id == id);
+
+    let m1 = node("0xcafe::M1").unwrap();
+    assert_eq!(m1.package, "Symbols");
+    assert!(!m1.is_dependency);
+    assert!(m1.uri.as_ref().unwrap().path().ends_with("M1.move"));
+    assert!(graph.edges.contains(&DependencyEdge {
+        from: "0xcafe::M1".to_string(),
+        to: "0xcafe::M2".to_string(),
+        friend: false,
+    }));
+
+    let vector = node("0x1::vector").unwrap();
+    assert_eq!(vector.package, "MoveStdlib");
+    assert!(vector.is_dependency);
+
+    assert_eq!(graph.addresses.get("Symbols").unwrap(), "0xcafe");
+    assert_eq!(graph.addresses.get("std").unwrap(), "0x1");
+}
@@ -3,44 +3,77 @@
 
 use crate::utils::get_loc;
 use codespan_reporting::{diagnostic::Severity, files::SimpleFiles};
-use lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location, Range,
+};
 use move_command_line_common::files::FileHash;
 use move_ir_types::location::Loc;
 use move_symbol_pool::Symbol;
 use std::collections::{BTreeMap, HashMap};
+use url::Url;
+
+/// Compiler diagnostic in the codespan format: severity, category, primary label, secondary
+/// labels and notes
+pub type CodespanDiagnostic = (
+    Severity,
+    &'static str,
+    (Loc, String),
+    Vec<(Loc, String)>,
+    Vec<String>,
+);
 
 /// Converts diagnostics from the codespan format to the format understood by the language server.
 pub fn lsp_diagnostics(
-    diagnostics: &Vec<(
-        codespan_reporting::diagnostic::Severity,
-        &'static str,
-        (Loc, String),
-        Vec<(Loc, String)>,
-        Vec<String>,
-    )>,
+    diagnostics: &[CodespanDiagnostic],
     files: &SimpleFiles<Symbol, String>,
     file_id_mapping: &HashMap<FileHash, usize>,
     file_name_mapping: &BTreeMap<FileHash, Symbol>,
 ) -> BTreeMap<Symbol, Vec<Diagnostic>> {
+    let lsp_range = |loc: &Loc| {
+        let start = get_loc(&loc.file_hash(), loc.start(), files, file_id_mapping)?;
+        let end = get_loc(&loc.file_hash(), loc.end(), files, file_id_mapping)?;
+        Some(Range::new(start, end))
+    };
     let mut lsp_diagnostics = BTreeMap::new();
-    for (s, _, (loc, msg), _, _) in diagnostics {
+    for (s, _, (loc, msg), labels, _) in diagnostics {
         let fpath = file_name_mapping.get(&loc.file_hash()).unwrap();
-        if let Some(start) = get_loc(&loc.file_hash(), loc.start(), files, file_id_mapping) {
-            if let Some(end) = get_loc(&loc.file_hash(), loc.end(), files, file_id_mapping) {
-                let range = Range::new(start, end);
-                lsp_diagnostics
-                    .entry(*fpath)
-                    .or_insert_with(Vec::new)
-                    .push(Diagnostic::new(
-                        range,
-                        Some(severity(*s)),
-                        None,
-                        None,
-                        msg.to_string(),
-                        None,
-                        None,
-                    ));
-            }
+        // secondary labels (e.g., pointing to where a missing ability would need to be declared)
+        // may refer to other files
+        let related = labels
+            .iter()
+            .filter_map(|(label_loc, label_msg)| {
+                let label_fpath = file_name_mapping.get(&label_loc.file_hash())?;
+                Some(DiagnosticRelatedInformation {
+                    location: Location::new(
+                        Url::from_file_path(label_fpath.as_str()).ok()?,
+                        lsp_range(label_loc)?,
+                    ),
+                    message: label_msg.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        if let Some(range) = lsp_range(loc) {
+            lsp_diagnostics
+                .entry(*fpath)
+                .or_insert_with(Vec::new)
+                .push(Diagnostic::new(
+                    range,
+                    Some(severity(*s)),
+                    None,
+                    None,
+                    msg.to_string(),
+                    if related.is_empty() {
+                        None
+                    } else {
+                        Some(related)
+                    },
+                    // unused code is displayed faded out by clients
+                    if *s == Severity::Warning && msg.starts_with("Unused") {
+                        Some(vec![DiagnosticTag::Unnecessary])
+                    } else {
+                        None
+                    },
+                ));
         }
     }
     lsp_diagnostics
@@ -60,7 +93,7 @@ pub fn lsp_empty_diagnostics(
 
 /// Converts diagnostic severity level from the codespan format to the format understood by the
 /// language server.
-fn severity(s: Severity) -> DiagnosticSeverity {
+pub fn severity(s: Severity) -> DiagnosticSeverity {
     match s {
         Severity::Bug => DiagnosticSeverity::Error,
         Severity::Error => DiagnosticSeverity::Error,
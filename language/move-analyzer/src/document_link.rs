@@ -0,0 +1,262 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Document links of modules in use declarations (leading to files defining these modules) and of
+//! dependency paths in package manifests (leading to manifests of these dependencies).
+//!
+//! Links are computed from the current contents of a buffer and from manifests and sources on disk
+//! (rather than from symbolication results) so that they are available before a package is
+//! symbolicated for the first time, and even if it cannot be compiled. Dependencies are located
+//! the same way as during package resolution, but git dependencies are not downloaded.
+
+use crate::{context::Context, vfs::buffer_position};
+use lsp_server::Request;
+use lsp_types::{DocumentLink, DocumentLinkParams, Range, Url};
+use move_command_line_common::files::{find_move_filenames, FileHash};
+use move_compiler::{
+    parser::{ast as P, syntax::parse_file_string},
+    shared::{CompilationEnv, Flags},
+};
+use move_ir_types::location::Loc;
+use move_package::source_package::{
+    layout::SourcePackageLayout,
+    manifest_parser::{parse_dependency_spans, parse_move_manifest_from_file},
+};
+use move_symbol_pool::Symbol;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, error};
+
+/// Files defining modules, indexed by module names and (textual representations of) module
+/// addresses
+type ModuleFiles = BTreeMap<(String, Symbol), PathBuf>;
+
+fn range(buffer: &str, loc: &Loc) -> Range {
+    Range {
+        start: buffer_position(buffer, loc.start() as usize),
+        end: buffer_position(buffer, loc.end() as usize),
+    }
+}
+
+fn link(range: Range, target: &Path) -> Option<DocumentLink> {
+    Some(DocumentLink {
+        range,
+        target: Some(Url::from_file_path(target).ok()?),
+        tooltip: None,
+        data: None,
+    })
+}
+
+/// Returns directories (of the package with the given root and of all its dependencies) where
+/// source files are located
+fn source_dirs(pkg_root: &Path) -> Vec<PathBuf> {
+    let mut visited = BTreeSet::new();
+    let mut pkg_roots = vec![pkg_root.to_path_buf()];
+    let mut dirs = vec![];
+    while let Some(pkg_root) = pkg_roots.pop() {
+        let pkg_root = match fs::canonicalize(&pkg_root) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+        if !visited.insert(pkg_root.clone()) {
+            continue;
+        }
+        let manifest = match parse_move_manifest_from_file(&pkg_root) {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        for dep in manifest
+            .dependencies
+            .values()
+            .chain(manifest.dev_dependencies.values())
+        {
+            pkg_roots.push(pkg_root.join(&dep.local));
+        }
+        dirs.push(pkg_root.join(SourcePackageLayout::Sources.path()));
+    }
+    dirs
+}
+
+/// Returns files defining modules in the given directories
+fn module_files(dirs: &[PathBuf]) -> ModuleFiles {
+    let dirs = dirs.iter().filter(|d| d.exists()).collect::<Vec<_>>();
+    let mut modules = BTreeMap::new();
+    for fname in find_move_filenames(&dirs, false).unwrap_or_default() {
+        let source = match fs::read_to_string(&fname) {
+            Ok(s) => s,
+            Err(_) => continue,
+        };
+        let mut env = CompilationEnv::new(Flags::empty());
+        let defs = match parse_file_string(&mut env, FileHash::new(&source), &source) {
+            Ok((defs, _)) => defs,
+            Err(_) => continue,
+        };
+        let mut add = |address: Option<&P::LeadingNameAccess>, mod_def: &P::ModuleDefinition| {
+            if let Some(address) = mod_def.address.as_ref().or(address) {
+                let key = (address.value.to_string(), mod_def.name.0.value);
+                modules.entry(key).or_insert_with(|| PathBuf::from(&fname));
+            }
+        };
+        for def in &defs {
+            match def {
+                P::Definition::Module(mod_def) => add(None, mod_def),
+                P::Definition::Address(addr_def) => {
+                    for mod_def in &addr_def.modules {
+                        add(Some(&addr_def.addr), mod_def)
+                    }
+                }
+                P::Definition::Script(_) => (),
+            }
+        }
+    }
+    modules
+}
+
+fn module_uses<'a>(mod_def: &'a P::ModuleDefinition, uses: &mut Vec<&'a P::UseDecl>) {
+    for member in &mod_def.members {
+        if let P::ModuleMember::Use(use_decl) = member {
+            uses.push(use_decl);
+        }
+    }
+}
+
+/// Returns links of modules used in the given Move source file, or None if it cannot be parsed
+fn use_links(fpath: &Path, buffer: &str) -> Option<Vec<DocumentLink>> {
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+    let mut uses = vec![];
+    for def in &defs {
+        match def {
+            P::Definition::Module(mod_def) => module_uses(mod_def, &mut uses),
+            P::Definition::Address(addr_def) => {
+                for mod_def in &addr_def.modules {
+                    module_uses(mod_def, &mut uses);
+                }
+            }
+            P::Definition::Script(script) => uses.extend(&script.uses),
+        }
+    }
+    let used_modules = uses
+        .into_iter()
+        .map(|use_decl| match &use_decl.use_ {
+            P::Use::Module(mident, _) | P::Use::Members(mident, _) => *mident,
+        })
+        .collect::<Vec<_>>();
+    if used_modules.is_empty() {
+        return Some(vec![]);
+    }
+
+    let pkg_root = SourcePackageLayout::try_find_root(fpath.parent()?).ok()?;
+    let modules = module_files(&source_dirs(&pkg_root));
+    Some(
+        used_modules
+            .into_iter()
+            .filter_map(|mident| {
+                let key = (
+                    mident.value.address.value.to_string(),
+                    mident.value.module.0.value,
+                );
+                link(range(buffer, &mident.loc), modules.get(&key)?)
+            })
+            .collect(),
+    )
+}
+
+/// Returns links of dependencies of the package with the given manifest, or None if it cannot be
+/// parsed
+fn dependency_links(manifest_path: &Path, buffer: &str) -> Option<Vec<DocumentLink>> {
+    let pkg_root = manifest_path.parent()?;
+    // spans are taken from the buffer (which may not be saved yet) but dependencies are only
+    // located if they are declared on disk as well
+    let manifest = parse_move_manifest_from_file(pkg_root).ok()?;
+    let spans = parse_dependency_spans(buffer).ok()?;
+    Some(
+        spans
+            .into_iter()
+            .filter_map(|(dep_name, span)| {
+                let dep = manifest
+                    .dependencies
+                    .get(&dep_name)
+                    .or_else(|| manifest.dev_dependencies.get(&dep_name))?;
+                let dep_manifest = pkg_root
+                    .join(&dep.local)
+                    .join(SourcePackageLayout::Manifest.path());
+                let range = Range {
+                    start: buffer_position(buffer, span.start),
+                    end: buffer_position(buffer, span.end),
+                };
+                link(range, &fs::canonicalize(dep_manifest).ok()?)
+            })
+            .collect(),
+    )
+}
+
+/// Returns document links of the given file (either a Move source file or a package manifest)
+pub fn document_links(fpath: &Path, buffer: &str) -> Option<Vec<DocumentLink>> {
+    if fpath.file_name() == Some(SourcePackageLayout::Manifest.path().as_os_str()) {
+        dependency_links(fpath, buffer)
+    } else {
+        use_links(fpath, buffer)
+    }
+}
+
+/// Handles document link request of the language server
+pub fn on_document_link_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<DocumentLinkParams>(request.params.clone())
+        .expect("could not deserialize document link request");
+
+    let fpath = parameters.text_document.uri.path();
+    // manifests are not necessarily synchronized with the server
+    let buffer = match context.files.get(fpath) {
+        Some(buffer) => buffer.to_string(),
+        None => fs::read_to_string(fpath).unwrap_or_default(),
+    };
+    let links = document_links(Path::new(fpath), &buffer);
+
+    debug!("about to send document link response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), links);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send document link response: {:?}", err);
+    }
+}
+
+#[test]
+fn document_links_test() {
+    let pkg_path =
+        fs::canonicalize(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/symbols")).unwrap();
+    let stdlib_path = fs::canonicalize(pkg_path.join("../../../move-stdlib")).unwrap();
+    let targets = |fpath: &Path| {
+        let buffer = fs::read_to_string(fpath).unwrap();
+        document_links(fpath, &buffer)
+            .unwrap()
+            .into_iter()
+            .map(|l| {
+                let r = l.range;
+                let target = l.target.unwrap().to_file_path().unwrap();
+                (r.start.line, r.start.character, r.end.character, target)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // modules of the package itself and of its dependencies
+    assert_eq!(
+        targets(&pkg_path.join("sources/M1.move")),
+        vec![(28, 8, 19, pkg_path.join("sources/M2.move"))]
+    );
+    assert_eq!(
+        targets(&pkg_path.join("sources/M6.move")),
+        vec![(1, 8, 19, stdlib_path.join("sources/vector.move"))]
+    );
+    // dependencies in the manifest
+    assert_eq!(
+        targets(&pkg_path.join("Move.toml")),
+        vec![(5, 23, 46, stdlib_path.join("Move.toml"))]
+    );
+}
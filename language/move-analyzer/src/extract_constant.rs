@@ -0,0 +1,362 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Refactoring extracting a literal (such as a magic abort code) into a new constant of the
+//! enclosing module, replacing all identical literals of the same type in the module's functions.
+//!
+//! Literals are found by lexing the current contents of the buffer, while their types (unless
+//! given by a suffix or implied by the kind of the literal) come from the symbols of the file.
+
+use crate::{
+    extract_function::{identifiers, line_indent},
+    vfs::{buffer_position, byte_offset},
+};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Range, TextEdit, Url, WorkspaceEdit,
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::parser::lexer::{Lexer, Tok};
+use std::collections::HashMap;
+
+/// Name of the extracted constant (suffixed with a number if the module already uses it)
+const EXTRACTED_CONSTANT_NAME: &str = "EXTRACTED";
+
+/// Token of the buffer along with its content and byte offset
+type Token<'a> = (Tok, &'a str, usize);
+
+/// Returns the refactoring extracting the literal at the given range (or at the cursor if the
+/// range is empty) into a constant, or `None` if there is no literal of a known type there. Types
+/// of expressions at given ranges are provided by `exp_type`.
+pub fn extract_constant_action(
+    uri: &Url,
+    buffer: &str,
+    range: &Range,
+    exp_type: &dyn Fn(&Range) -> Option<String>,
+) -> Option<CodeActionOrCommand> {
+    let edits = extract_constant(buffer, range, exp_type)?
+        .into_iter()
+        .map(|(start, end, new_text)| TextEdit {
+            range: Range::new(buffer_position(buffer, start), buffer_position(buffer, end)),
+            new_text,
+        })
+        .collect();
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: "Extract into constant".to_string(),
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+/// Returns edits (as byte ranges to be replaced and their replacements) declaring the constant
+/// and replacing occurrences of the literal with it
+fn extract_constant(
+    buffer: &str,
+    range: &Range,
+    exp_type: &dyn Fn(&Range) -> Option<String>,
+) -> Option<Vec<(usize, usize, String)>> {
+    let mut tokens = vec![];
+    let mut lexer = Lexer::new(buffer, FileHash::new(buffer));
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        tokens.push((lexer.peek(), lexer.content(), lexer.start_loc()));
+    }
+    if lexer.peek() != Tok::EOF {
+        return None;
+    }
+    let token_end = |i: usize| tokens[i].2 + tokens[i].1.len();
+    // type of the literal spanning the given tokens
+    let literal_type = |first: usize, last: usize| match &tokens[first..last] {
+        [(Tok::NumTypedValue, content, _)] => Some(
+            content
+                .trim_start_matches(|c: char| c.is_ascii_hexdigit() || c == 'x' || c == '_')
+                .to_string(),
+        ),
+        [(Tok::ByteStringValue, _, _)] => Some("vector<u8>".to_string()),
+        [(Tok::AtSign, _, _), _] => Some("address".to_string()),
+        _ => exp_type(&Range::new(
+            buffer_position(buffer, tokens[first].2),
+            buffer_position(buffer, token_end(last - 1)),
+        )),
+    };
+
+    let (first, last) = selected_literal(buffer, &tokens, range)?;
+    let ty = literal_type(first, last)?;
+    let (body_depth, body_start, body_end) = module_body(&tokens, first)?;
+    let name = {
+        let used = identifiers(buffer);
+        (0..)
+            .map(|i| match i {
+                0 => EXTRACTED_CONSTANT_NAME.to_string(),
+                _ => format!("{}_{}", EXTRACTED_CONSTANT_NAME, i),
+            })
+            .find(|name| !used.contains(name.as_str()))
+            .unwrap()
+    };
+
+    // occurrences in functions (constants cannot refer to other constants, while attributes can
+    // only contain literals)
+    let mut edits = vec![];
+    let mut depth = body_depth;
+    let mut i = body_start;
+    while i < body_end {
+        match tokens[i].0 {
+            Tok::LBrace => depth += 1,
+            Tok::RBrace => depth -= 1,
+            _ => (),
+        }
+        let occurrence = (depth > body_depth && tokens[i - 1].0 != Tok::AtSign)
+            .then(|| literal_end(&tokens, i))
+            .flatten()
+            .filter(|j| {
+                tokens[i..*j]
+                    .iter()
+                    .map(|(_, content, _)| content)
+                    .eq(tokens[first..last].iter().map(|(_, content, _)| content))
+            })
+            .filter(|j| i == first || literal_type(i, *j).as_ref() == Some(&ty));
+        match occurrence {
+            Some(j) => {
+                edits.push((tokens[i].2, token_end(j - 1), name.clone()));
+                i = j;
+            }
+            None => i += 1,
+        }
+    }
+    if !edits.iter().any(|(start, _, _)| *start == tokens[first].2) {
+        return None;
+    }
+
+    // the constant is declared after other constants, or after `use` declarations if there are
+    // none
+    let mut last_const = None;
+    let mut last_use = None;
+    let mut depth = body_depth;
+    for (i, (tok, _, _)) in tokens.iter().enumerate().take(body_end).skip(body_start) {
+        match tok {
+            Tok::LBrace => depth += 1,
+            Tok::RBrace => depth -= 1,
+            Tok::Const | Tok::Use if depth == body_depth => {
+                let semicolon = i + tokens[i..]
+                    .iter()
+                    .position(|(tok, _, _)| *tok == Tok::Semicolon)?;
+                if *tok == Tok::Const {
+                    last_const = Some(token_end(semicolon));
+                } else {
+                    last_use = Some(token_end(semicolon));
+                }
+            }
+            _ => (),
+        }
+    }
+    let next_line = |offset: usize| {
+        buffer[offset..]
+            .find('\n')
+            .map_or(buffer.len(), |i| offset + i + 1)
+    };
+    let decl = format!(
+        "const {}: {} = {};",
+        name,
+        ty,
+        &buffer[tokens[first].2..token_end(last - 1)]
+    );
+    edits.push(match (last_const, last_use) {
+        (Some(end), _) => (
+            next_line(end),
+            next_line(end),
+            format!("{}{}\n", line_indent(buffer, end), decl),
+        ),
+        (None, Some(end)) => (
+            next_line(end),
+            next_line(end),
+            format!("\n{}{}\n", line_indent(buffer, end), decl),
+        ),
+        (None, None) => {
+            let brace = tokens[body_start - 1].2;
+            (
+                next_line(brace),
+                next_line(brace),
+                format!("{}    {}\n\n", line_indent(buffer, brace), decl),
+            )
+        }
+    });
+    edits.sort_by_key(|(start, _, _)| *start);
+    Some(edits)
+}
+
+/// Returns indices of the first token of the literal at the given range (or at the cursor if the
+/// range is empty) and of the token following the literal
+fn selected_literal(buffer: &str, tokens: &[Token], range: &Range) -> Option<(usize, usize)> {
+    let start = byte_offset(buffer, &range.start);
+    let end = byte_offset(buffer, &range.end);
+    if start == end {
+        let i = tokens
+            .iter()
+            .position(|(_, content, s)| *s <= start && start <= s + content.len())?;
+        // an address is extracted along with the preceding `@`
+        let i = if i > 0 && tokens[i - 1].0 == Tok::AtSign {
+            i - 1
+        } else {
+            i
+        };
+        Some((i, literal_end(tokens, i)?))
+    } else {
+        let i = tokens.iter().position(|(_, _, s)| *s >= start)?;
+        let j = literal_end(tokens, i)?;
+        let (_, content, last_start) = tokens[j - 1];
+        (buffer[start..tokens[i].2].trim().is_empty()
+            && last_start + content.len() <= end
+            && buffer[last_start + content.len()..end].trim().is_empty())
+        .then(|| (i, j))
+    }
+}
+
+/// Returns the index of the token following the literal (a number, a byte string, an address, or
+/// a vector of literals) starting at the given token, if there is one
+fn literal_end(tokens: &[Token], i: usize) -> Option<usize> {
+    match tokens.get(i)? {
+        (Tok::NumValue, _, _) | (Tok::NumTypedValue, _, _) | (Tok::ByteStringValue, _, _) => {
+            Some(i + 1)
+        }
+        (Tok::AtSign, _, _) => {
+            matches!(tokens.get(i + 1)?.0, Tok::NumValue | Tok::Identifier).then(|| i + 2)
+        }
+        (Tok::Identifier, "vector", _) => {
+            let mut j = i + 1;
+            // type arguments
+            if tokens.get(j)?.0 == Tok::Less {
+                let mut depth = 0;
+                loop {
+                    match tokens.get(j)?.0 {
+                        Tok::Less => depth += 1,
+                        Tok::Greater => depth -= 1,
+                        Tok::GreaterGreater => depth -= 2,
+                        _ => (),
+                    }
+                    j += 1;
+                    if depth <= 0 {
+                        break;
+                    }
+                }
+                if depth < 0 {
+                    return None;
+                }
+            }
+            if tokens.get(j)?.0 != Tok::LBracket {
+                return None;
+            }
+            j += 1;
+            while tokens.get(j)?.0 != Tok::RBracket {
+                j = literal_end(tokens, j)?;
+                match tokens.get(j)?.0 {
+                    Tok::Comma => j += 1,
+                    Tok::RBracket => (),
+                    _ => return None,
+                }
+            }
+            Some(j + 1)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the depth (in terms of nested braces) of the body of the module containing the given
+/// token, along with indices of the first token of the body and of its closing brace
+fn module_body(tokens: &[Token], token: usize) -> Option<(usize, usize, usize)> {
+    let mut depth = 0;
+    let mut in_declaration = false;
+    let mut body = None;
+    for (i, (tok, _, _)) in tokens.iter().enumerate() {
+        match tok {
+            Tok::Module => in_declaration = true,
+            Tok::LBrace => {
+                depth += 1;
+                if in_declaration && body.is_none() {
+                    body = Some((depth, i + 1));
+                }
+                in_declaration = false;
+            }
+            Tok::RBrace => {
+                if let Some((body_depth, body_start)) = body {
+                    if depth == body_depth {
+                        if body_start <= token && token < i {
+                            return Some((body_depth, body_start, i));
+                        }
+                        body = None;
+                    }
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[test]
+fn extract_constant_test() {
+    let buffer = r#"module 0x1::M {
+    use std::vector;
+
+    fun f(x: u64): vector<u8> {
+        if (x > 7) abort 7;
+        let y: u8 = 7;
+        if (x == 7u64) abort 8;
+        vector[y, 7]
+    }
+}
+"#;
+    let offset = |s: &str| buffer.find(s).unwrap();
+    let types = vec![
+        (offset("7)"), "u64"),
+        (offset("7;"), "u64"),
+        (offset("= 7") + 2, "u8"),
+        (offset("7]"), "u8"),
+    ];
+    let exp_type = |range: &Range| {
+        let start = byte_offset(buffer, &range.start);
+        let end = byte_offset(buffer, &range.end);
+        types
+            .iter()
+            .find(|(offset, _)| *offset == start && start + 1 == end)
+            .map(|(_, ty)| ty.to_string())
+    };
+    let extract = |start: usize, end: usize| {
+        let range = Range::new(buffer_position(buffer, start), buffer_position(buffer, end));
+        extract_constant(buffer, &range, &exp_type).map(|edits| {
+            let mut extracted = buffer.to_string();
+            for (start, end, new_text) in edits.into_iter().rev() {
+                extracted.replace_range(start..end, &new_text);
+            }
+            extracted
+        })
+    };
+
+    // occurrences of the same type are replaced
+    assert_eq!(
+        extract(offset("7;"), offset("7;")).unwrap(),
+        r#"module 0x1::M {
+    use std::vector;
+
+    const EXTRACTED: u64 = 7;
+
+    fun f(x: u64): vector<u8> {
+        if (x > EXTRACTED) abort EXTRACTED;
+        let y: u8 = 7;
+        if (x == 7u64) abort 8;
+        vector[y, 7]
+    }
+}
+"#
+    );
+    // typed literal
+    let extracted = extract(offset("7u64"), offset("7u64") + 4).unwrap();
+    assert!(extracted.contains("    const EXTRACTED: u64 = 7u64;\n"));
+    assert!(extracted.contains("if (x == EXTRACTED) abort 8;"));
+    // not a literal of a known type
+    assert!(extract(offset("8;"), offset("8;")).is_none());
+    assert!(extract(offset("vector[y"), offset("vector[y")).is_none());
+    assert!(extract(offset("vector<u8>"), offset("vector<u8>")).is_none());
+}
@@ -0,0 +1,469 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Refactoring extracting selected statements of a function into a new (private) function of the
+//! same module.
+//!
+//! Locals of the enclosing function used by the statements become parameters of the new function,
+//! while locals defined by the statements and used after them are returned from it. Locals that
+//! the statements modify (or move, unless their values can be copied) and that are used after
+//! them are both passed to the new function and returned from it, so that the statements are
+//! replaced by a call whose results are assigned back to them.
+//!
+//! Which locals are used where comes from the symbols of the file, while the statements and the
+//! enclosing function are found in the current contents of the buffer.
+
+use crate::{
+    code_action::ident_len,
+    vfs::{buffer_position, byte_offset},
+};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    parser::{
+        ast as P,
+        lexer::{Lexer, Tok},
+        syntax::parse_file_string,
+    },
+    shared::{CompilationEnv, Flags},
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Name of the extracted function (suffixed with a number if the module already uses it)
+const EXTRACTED_FUNCTION_NAME: &str = "extracted";
+
+/// Use (or definition) of a local variable or a parameter
+#[derive(Debug, Clone)]
+pub struct LocalUse {
+    /// Start of the identifier defining the local (which identifies it)
+    pub def_start: Position,
+    /// Start of the use
+    pub start: Position,
+    /// Type of the local
+    pub ty: String,
+    /// Whether the use writes to the local, either by assigning it or by taking a mutable
+    /// reference to it
+    pub write: bool,
+}
+
+/// Local passed to or returned from the extracted function
+#[derive(Debug, Clone)]
+struct Local {
+    name: String,
+    ty: String,
+}
+
+/// Returns the refactoring extracting the selected statements into a new function, or `None` if
+/// the selection does not consist of whole statements of a function body that can be extracted
+pub fn extract_function_action(
+    uri: &Url,
+    buffer: &str,
+    range: &Range,
+    locals: &[LocalUse],
+) -> Option<CodeActionOrCommand> {
+    let edits = extract_function(buffer, range, locals)?
+        .into_iter()
+        .map(|(start, end, new_text)| TextEdit {
+            range: Range::new(buffer_position(buffer, start), buffer_position(buffer, end)),
+            new_text,
+        })
+        .collect();
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: "Extract into function".to_string(),
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+/// Returns edits (as byte ranges to be replaced and their replacements) extracting the selected
+/// statements into a new function
+fn extract_function(
+    buffer: &str,
+    range: &Range,
+    locals: &[LocalUse],
+) -> Option<Vec<(usize, usize, String)>> {
+    let (start, end) = selected_statements(
+        buffer,
+        byte_offset(buffer, &range.start),
+        byte_offset(buffer, &range.end),
+    )?;
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+    let fun = enclosing_function(&defs, start, end)?;
+    let fun_start = fun.loc.start() as usize;
+    let fun_end = fun.loc.end() as usize;
+
+    // locals of the enclosing function (keyed by offsets of their definitions) along with offsets
+    // of their uses
+    let mut fun_locals = BTreeMap::<usize, (Local, Vec<(usize, bool)>)>::new();
+    for u in locals {
+        let def = byte_offset(buffer, &u.def_start);
+        let offset = byte_offset(buffer, &u.start);
+        if def < fun_start || def >= fun_end || offset < fun_start || offset >= fun_end {
+            continue;
+        }
+        let name = &buffer[def..def + ident_len(&buffer[def..])];
+        fun_locals
+            .entry(def)
+            .or_insert_with(|| {
+                let local = Local {
+                    name: name.to_string(),
+                    ty: u.ty.replace("& ", "&"),
+                };
+                (local, vec![])
+            })
+            .1
+            .push((offset, u.write));
+    }
+    let selected = |offset: &usize| start <= *offset && *offset < end;
+    let mut params = vec![];
+    let mut defined = vec![];
+    let mut modified = vec![];
+    for (def, (local, uses)) in &fun_locals {
+        let inside = uses.iter().filter(|(o, _)| selected(o) && o != def);
+        let used_inside = inside.clone().next().is_some();
+        let written_inside = inside.clone().any(|(_, write)| *write);
+        let used_after = uses.iter().any(|(o, _)| *o >= end);
+        if selected(def) {
+            if used_after {
+                defined.push(local.clone());
+            }
+        } else if used_inside {
+            params.push((uses.iter().find(|(o, _)| selected(o))?.0, local.clone()));
+            if used_after && (written_inside || !is_copyable(&local.ty)) {
+                modified.push(local.clone());
+            }
+        }
+    }
+    // locals cannot be defined and assigned by the same statement
+    if !defined.is_empty() && !modified.is_empty() {
+        return None;
+    }
+    params.sort_by_key(|(first_use, _)| *first_use);
+    let params = params
+        .into_iter()
+        .map(|(_, local)| local)
+        .collect::<Vec<_>>();
+    let results = if defined.is_empty() {
+        modified
+    } else {
+        defined.clone()
+    };
+
+    let name = function_name(buffer);
+    let fun_indent = line_indent(buffer, fun_start);
+    let body_indent = format!("{}    ", fun_indent);
+    let type_params = used_type_params(
+        fun,
+        params.iter().chain(results.iter()).map(|l| l.ty.as_str()),
+    );
+    let statements = &buffer[start..end];
+    let selected_identifiers = identifiers(statements);
+    let acquires = fun
+        .acquires
+        .iter()
+        .map(|chain| &buffer[chain.loc.start() as usize..chain.loc.end() as usize])
+        .filter(|acquired| {
+            acquired
+                .rsplit("::")
+                .next()
+                .map_or(false, |name| selected_identifiers.contains(name.trim()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut new_fun = format!("\n\n{}fun {}", fun_indent, name);
+    if !type_params.is_empty() {
+        new_fun.push_str(&format!("<{}>", type_params.join(", ")));
+    }
+    let param_list = params
+        .iter()
+        .map(|p| format!("{}: {}", p.name, p.ty))
+        .collect::<Vec<_>>();
+    new_fun.push_str(&format!("({})", param_list.join(", ")));
+    if !results.is_empty() {
+        new_fun.push_str(&format!(": {}", tuple(results.iter().map(|r| &r.ty))));
+    }
+    if !acquires.is_empty() {
+        new_fun.push_str(&format!(" acquires {}", acquires.join(", ")));
+    }
+    new_fun.push_str(" {\n");
+    let statements_indent = line_indent(buffer, start);
+    for (i, line) in statements.lines().enumerate() {
+        let line = if i == 0 {
+            line
+        } else {
+            line.strip_prefix(statements_indent)
+                .unwrap_or_else(|| line.trim_start())
+        };
+        if !line.is_empty() {
+            new_fun.push_str(&body_indent);
+        }
+        new_fun.push_str(line);
+        new_fun.push('\n');
+    }
+    if !results.is_empty() {
+        new_fun.push_str(&format!(
+            "{}{}\n",
+            body_indent,
+            tuple(results.iter().map(|r| &r.name))
+        ));
+    }
+    new_fun.push_str(&format!("{}}}", fun_indent));
+
+    let args = params
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let call = format!("{}({})", name, args);
+    let call = if results.is_empty() {
+        format!("{};", call)
+    } else if !defined.is_empty() {
+        format!("let {} = {};", tuple(results.iter().map(|r| &r.name)), call)
+    } else {
+        format!("{} = {};", tuple(results.iter().map(|r| &r.name)), call)
+    };
+    Some(vec![(start, end, call), (fun_end, fun_end, new_fun)])
+}
+
+/// Returns the byte range of the selected statements (without surrounding whitespace), or `None`
+/// if the selection does not start and end at statement boundaries or if it contains statements
+/// leaving the enclosing function (or loop) early
+fn selected_statements(buffer: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let selection = buffer.get(start..end)?;
+    let start = start + (selection.len() - selection.trim_start().len());
+    let end = start + selection.trim().len();
+    let selection = &buffer[start..end];
+    if !selection.ends_with(';') && !selection.ends_with('}') {
+        return None;
+    }
+    if !matches!(
+        buffer[..start].trim_end().chars().last(),
+        Some('{' | ';' | '}')
+    ) {
+        return None;
+    }
+
+    let mut lexer = Lexer::new(selection, FileHash::new(selection));
+    let mut depth = 0;
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        match lexer.peek() {
+            Tok::LBrace | Tok::LParen => depth += 1,
+            Tok::RBrace | Tok::RParen => {
+                if depth == 0 {
+                    return None;
+                }
+                depth -= 1;
+            }
+            Tok::Return | Tok::Break | Tok::Continue => return None,
+            _ => (),
+        }
+    }
+    (lexer.peek() == Tok::EOF && depth == 0).then(|| (start, end))
+}
+
+/// Returns the function (defined in a module) whose body contains the given byte range
+pub fn enclosing_function(
+    defs: &[P::Definition],
+    start: usize,
+    end: usize,
+) -> Option<&P::Function> {
+    defs.iter()
+        .flat_map(|def| match def {
+            P::Definition::Module(mod_def) => vec![mod_def],
+            P::Definition::Address(addr_def) => addr_def.modules.iter().collect(),
+            P::Definition::Script(_) => vec![],
+        })
+        .flat_map(|mod_def| mod_def.members.iter())
+        .find_map(|member| match member {
+            P::ModuleMember::Function(fun)
+                if matches!(fun.body.value, P::FunctionBody_::Defined(_))
+                    && fun.body.loc.start() as usize <= start
+                    && end <= fun.body.loc.end() as usize =>
+            {
+                Some(fun)
+            }
+            _ => None,
+        })
+}
+
+/// Returns type parameters (along with their constraints) of a function that the given types refer
+/// to
+pub fn used_type_params<'a>(
+    fun: &P::Function,
+    types: impl Iterator<Item = &'a str> + Clone,
+) -> Vec<String> {
+    fun.signature
+        .type_parameters
+        .iter()
+        .filter(|(tp, _)| {
+            types
+                .clone()
+                .any(|ty| identifiers(ty).contains(tp.value.as_str()))
+        })
+        .map(|(tp, abilities)| {
+            if abilities.is_empty() {
+                tp.value.to_string()
+            } else {
+                let abilities = abilities
+                    .iter()
+                    .map(|a| a.value.to_string())
+                    .collect::<Vec<_>>();
+                format!("{}: {}", tp.value, abilities.join(" + "))
+            }
+        })
+        .collect()
+}
+
+/// Returns a name for the extracted function that is not used in the buffer yet
+fn function_name(buffer: &str) -> String {
+    let used = identifiers(buffer);
+    (0..)
+        .map(|i| match i {
+            0 => EXTRACTED_FUNCTION_NAME.to_string(),
+            _ => format!("{}_{}", EXTRACTED_FUNCTION_NAME, i),
+        })
+        .find(|name| !used.contains(name.as_str()))
+        .unwrap()
+}
+
+/// Returns all identifiers in the given text
+pub fn identifiers(text: &str) -> BTreeSet<&str> {
+    let mut identifiers = BTreeSet::new();
+    let mut lexer = Lexer::new(text, FileHash::new(text));
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        if lexer.peek() == Tok::Identifier {
+            identifiers.insert(lexer.content());
+        }
+    }
+    identifiers
+}
+
+/// Returns the indentation of the line containing the given offset
+pub fn line_indent(buffer: &str, offset: usize) -> &str {
+    let line_start = buffer[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &buffer[line_start..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Returns the given items as a tuple (or as the item itself if there is only one)
+fn tuple<'a>(items: impl Iterator<Item = &'a String>) -> String {
+    let items = items.map(String::as_str).collect::<Vec<_>>();
+    match items.as_slice() {
+        [item] => item.to_string(),
+        _ => format!("({})", items.join(", ")),
+    }
+}
+
+/// Returns true if values of the given type can certainly be copied (values of other types are
+/// moved when passed to a function)
+fn is_copyable(ty: &str) -> bool {
+    match ty.strip_prefix("vector<") {
+        Some(elem) => elem.strip_suffix('>').map_or(false, is_copyable),
+        None => ty.starts_with('&') || matches!(ty, "bool" | "u8" | "u64" | "u128" | "address"),
+    }
+}
+
+#[test]
+fn extract_function_test() {
+    let buffer = r#"module 0x1::M {
+    struct R has key { v: u64 }
+
+    fun f(x: u64, v: vector<u64>): u64 acquires R {
+        let y = x + 1;
+        let r = borrow_global_mut<R>(@0x1);
+        r.v = y;
+        x = x * 2;
+        x + y
+    }
+}
+"#;
+    let position = |s: &str| buffer_position(buffer, buffer.find(s).unwrap());
+    let local = |def: &str, u: &str, ty: &str, write| LocalUse {
+        def_start: position(def),
+        start: position(u),
+        ty: ty.to_string(),
+        write,
+    };
+    let locals = vec![
+        local("x: u64", "x: u64", "u64", true),
+        local("v: vector", "v: vector", "vector<u64>", true),
+        local("y = x", "y = x", "u64", true),
+        local("x: u64", "x + 1", "u64", false),
+        local("r = ", "r = ", "&mut 0x1::M::R", true),
+        local("r = ", "r.v", "&mut 0x1::M::R", false),
+        local("y = x", "y;", "u64", false),
+        local("x: u64", "x = x", "u64", true),
+        local("x: u64", "x * 2", "u64", false),
+        local("x: u64", "x + y", "u64", false),
+        local("y = x", "y\n", "u64", false),
+    ];
+    let extract = |from: &str, to: &str| {
+        let start = buffer.find(from).unwrap();
+        let end = buffer.find(to).unwrap() + to.len();
+        let range = Range::new(buffer_position(buffer, start), buffer_position(buffer, end));
+        extract_function(buffer, &range, &locals).map(|edits| {
+            let mut extracted = buffer.to_string();
+            for (start, end, new_text) in edits.into_iter().rev() {
+                extracted.replace_range(start..end, &new_text);
+            }
+            extracted
+        })
+    };
+
+    // a local defined by the statements is returned, a resource is acquired
+    assert_eq!(
+        extract("let y", "r.v = y;").unwrap(),
+        r#"module 0x1::M {
+    struct R has key { v: u64 }
+
+    fun f(x: u64, v: vector<u64>): u64 acquires R {
+        let y = extracted(x);
+        x = x * 2;
+        x + y
+    }
+
+    fun extracted(x: u64): u64 acquires R {
+        let y = x + 1;
+        let r = borrow_global_mut<R>(@0x1);
+        r.v = y;
+        y
+    }
+}
+"#
+    );
+    // a modified local is assigned back
+    assert_eq!(
+        extract("x = x * 2;", "x = x * 2;").unwrap(),
+        r#"module 0x1::M {
+    struct R has key { v: u64 }
+
+    fun f(x: u64, v: vector<u64>): u64 acquires R {
+        let y = x + 1;
+        let r = borrow_global_mut<R>(@0x1);
+        r.v = y;
+        x = extracted(x);
+        x + y
+    }
+
+    fun extracted(x: u64): u64 {
+        x = x * 2;
+        x
+    }
+}
+"#
+    );
+    // not whole statements
+    assert!(extract("x + 1", "x + 1").is_none());
+    assert!(extract("r = ", "r.v = y;").is_none());
+    // a local both defined and modified
+    assert!(extract("let y", "x = x * 2;").is_none());
+    // outside of a function body
+    assert!(extract("struct R", "}").is_none());
+}
@@ -0,0 +1,236 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Folding ranges of address blocks, modules, scripts, functions, structs, spec blocks, and groups
+//! of consecutive use declarations.
+//!
+//! Folding ranges are computed from the parsed AST of the current contents of a buffer (rather than
+//! from symbolication results) so that they are up-to-date even while the code being edited does
+//! not type check. If the buffer cannot be parsed, no folding ranges are returned, and the client
+//! keeps the ones it has or falls back to its own heuristics.
+
+use crate::{context::Context, vfs::buffer_position};
+use lsp_server::Request;
+use lsp_types::{FoldingRange, FoldingRangeKind, FoldingRangeParams};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    parser::{
+        ast as P,
+        lexer::{Lexer, Tok},
+        syntax::parse_file_string,
+    },
+    shared::{CompilationEnv, Flags},
+};
+use move_ir_types::location::Loc;
+use tracing::{debug, error};
+
+/// Collects folding ranges of a single buffer
+struct FoldingRangeCollector<'a> {
+    buffer: &'a str,
+    ranges: Vec<FoldingRange>,
+}
+
+impl<'a> FoldingRangeCollector<'a> {
+    fn line(&self, offset: u32) -> u32 {
+        buffer_position(self.buffer, offset as usize).line
+    }
+
+    /// Returns the offset of the brace closing the first block starting after the given offset
+    fn closing_brace(&self, offset: u32) -> Option<u32> {
+        let text = &self.buffer[offset as usize..];
+        let mut tokens = Lexer::new(text, FileHash::new(text));
+        let mut depth = 0;
+        loop {
+            tokens.advance().ok()?;
+            match tokens.peek() {
+                Tok::LBrace => depth += 1,
+                Tok::RBrace if depth == 1 => return Some(offset + tokens.start_loc() as u32),
+                Tok::RBrace => depth -= 1,
+                Tok::EOF => return None,
+                _ => (),
+            }
+        }
+    }
+
+    /// Adds a range folding a block delimited by braces (the line containing the closing brace
+    /// remains visible)
+    fn add_block(&mut self, loc: &Loc) {
+        self.add_block_lines(loc.start(), loc.end());
+    }
+
+    fn add_block_lines(&mut self, start: u32, end: u32) {
+        let start_line = self.line(start);
+        let end_line = self.line(end);
+        self.add(start_line, end_line.saturating_sub(1), None);
+    }
+
+    fn add(&mut self, start_line: u32, end_line: u32, kind: Option<FoldingRangeKind>) {
+        // single-line ranges cannot be folded
+        if end_line > start_line {
+            self.ranges.push(FoldingRange {
+                start_line,
+                start_character: None,
+                end_line,
+                end_character: None,
+                kind,
+            });
+        }
+    }
+
+    fn definition(&mut self, def: &P::Definition) {
+        match def {
+            P::Definition::Module(mod_def) => self.module(mod_def),
+            P::Definition::Address(addr_def) => {
+                // the location of an address block only covers the address, so the end of the
+                // block is found by lexing the rest of the buffer
+                if let Some(end) = self.closing_brace(addr_def.loc.end()) {
+                    self.add_block_lines(addr_def.loc.start(), end);
+                }
+                for mod_def in &addr_def.modules {
+                    self.module(mod_def);
+                }
+            }
+            P::Definition::Script(script) => {
+                self.add_block(&script.loc);
+                self.uses(script.uses.iter());
+                self.add_block(&script.function.loc);
+                for spec in &script.specs {
+                    self.add_block(&spec.loc);
+                }
+            }
+        }
+    }
+
+    fn module(&mut self, mod_def: &P::ModuleDefinition) {
+        self.add_block(&mod_def.loc);
+        let mut members = mod_def.members.iter().peekable();
+        while let Some(member) = members.next() {
+            match member {
+                P::ModuleMember::Function(fun) => self.add_block(&fun.loc),
+                P::ModuleMember::Struct(struct_def) => self.add_block(&struct_def.loc),
+                P::ModuleMember::Spec(spec) => self.add_block(&spec.loc),
+                P::ModuleMember::Use(use_decl) => {
+                    let mut group = vec![use_decl];
+                    while let Some(P::ModuleMember::Use(use_decl)) = members.peek() {
+                        group.push(use_decl);
+                        members.next();
+                    }
+                    self.uses(group.into_iter());
+                }
+                P::ModuleMember::Friend(_) | P::ModuleMember::Constant(_) => (),
+            }
+        }
+    }
+
+    /// Adds a range folding a group of consecutive use declarations
+    fn uses<'b>(&mut self, uses: impl Iterator<Item = &'b P::UseDecl>) {
+        // use declarations do not carry their own locations, so locations of the identifiers they
+        // consist of are used instead
+        let locs = uses
+            .flat_map(|use_decl| match &use_decl.use_ {
+                P::Use::Module(mident, alias) => {
+                    let mut locs = vec![mident.loc];
+                    locs.extend(alias.iter().map(|alias| alias.0.loc));
+                    locs
+                }
+                P::Use::Members(mident, members) => {
+                    let mut locs = vec![mident.loc];
+                    for (name, alias) in members {
+                        locs.push(name.loc);
+                        locs.extend(alias.iter().map(|alias| alias.loc));
+                    }
+                    locs
+                }
+            })
+            .collect::<Vec<_>>();
+        if let (Some(start), Some(end)) = (
+            locs.iter().map(|loc| loc.start()).min(),
+            locs.iter().map(|loc| loc.end()).max(),
+        ) {
+            let start_line = self.line(start);
+            let end_line = self.line(end);
+            self.add(start_line, end_line, Some(FoldingRangeKind::Imports));
+        }
+    }
+}
+
+/// Returns folding ranges of the given buffer, or None if it cannot be parsed
+pub fn folding_ranges(buffer: &str) -> Option<Vec<FoldingRange>> {
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+    let mut collector = FoldingRangeCollector {
+        buffer,
+        ranges: vec![],
+    };
+    for def in &defs {
+        collector.definition(def);
+    }
+    Some(collector.ranges)
+}
+
+/// Handles folding range request of the language server
+pub fn on_folding_range_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<FoldingRangeParams>(request.params.clone())
+        .expect("could not deserialize folding range request");
+
+    let ranges = context
+        .files
+        .get(parameters.text_document.uri.path())
+        .and_then(folding_ranges);
+
+    debug!("about to send folding range response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), ranges);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send folding range response: {:?}", err);
+    }
+}
+
+#[test]
+fn folding_ranges_test() {
+    let buffer = r#"address 0x1 {
+module M {
+    use 0x1::A;
+    use 0x1::B::{
+        f,
+        g as h
+    };
+
+    struct S {
+        f: u64,
+    }
+    struct T { f: u64 }
+
+    fun f(): u64 {
+        0
+    }
+
+    spec f {
+        ensures result == 0;
+    }
+}
+}
+"#;
+    let ranges = folding_ranges(buffer)
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.start_line, r.end_line, r.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranges,
+        vec![
+            (0, 20, None),
+            (1, 19, None),
+            (2, 5, Some(FoldingRangeKind::Imports)),
+            (8, 9, None),
+            (13, 14, None),
+            (17, 18, None),
+        ]
+    );
+
+    // nothing is returned for unparsable buffers
+    assert!(folding_ranges("module M {").is_none());
+}
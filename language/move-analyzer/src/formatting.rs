@@ -0,0 +1,819 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Formatting of Move source files.
+//!
+//! Only buffers that can be parsed are formatted. The layout is computed from the tokens of the
+//! buffer (rather than printed from the parsed AST, which does not retain comments) as follows:
+//! - lines are indented according to nesting of braces, parentheses and brackets, with at most one
+//!   level of indentation added per line
+//! - spacing between tokens on the same line is normalized (spacing around tokens whose role
+//!   depends on the context, such as `<` or `&`, is kept as it is)
+//! - trailing whitespace is removed, and consecutive blank lines are collapsed into one
+//! - groups of consecutive use declarations, as well as members imported by a single declaration,
+//!   are sorted
+//! - lines exceeding the maximum line length are broken at commas of bracketed lists
+//!
+//! Line breaks are otherwise kept where they are.
+//!
+//! Ranges of a buffer (such as the block closed by a `}` typed by the user) can also be formatted on
+//! their own. The whole buffer is still formatted to compute indentation, but only lines formatted
+//! from tokens inside of the range are replaced, and use declarations are not sorted.
+
+use crate::{
+    context::Context,
+    vfs::{buffer_position, byte_offset},
+};
+use lsp_server::Request;
+use lsp_types::{
+    DocumentFormattingParams, DocumentOnTypeFormattingParams, DocumentRangeFormattingParams,
+    FormattingOptions, Position, Range, TextEdit,
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    parser::{
+        lexer::{Lexer, Tok},
+        syntax::parse_file_string,
+    },
+    shared::{CompilationEnv, Flags},
+};
+use serde::Deserialize;
+use std::ops;
+use tracing::{debug, error};
+
+/// Settings of the formatter (the indentation width is taken from formatting requests)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormatConfig {
+    /// Maximum length of a line (longer lines are broken where possible)
+    pub max_line_length: usize,
+    /// Whether use declarations are sorted
+    pub sort_uses: bool,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            max_line_length: 100,
+            sort_uses: true,
+        }
+    }
+}
+
+/// Characters typed by the user after which the surrounding code is formatted
+pub const ON_TYPE_TRIGGER_CHARACTERS: &[&str] = &["}", ";"];
+
+/// Spec block keywords (lexed as identifiers) that may be followed by a parenthesized expression
+const SPEC_KEYWORDS: &[&str] = &[
+    "aborts_if",
+    "aborts_with",
+    "apply",
+    "assert",
+    "assume",
+    "decreases",
+    "emits",
+    "ensures",
+    "include",
+    "modifies",
+    "requires",
+    "succeeds_if",
+];
+
+/// Binary operators that are always surrounded by spaces
+const BINARY_OPERATORS: &[Tok] = &[
+    Tok::Equal,
+    Tok::EqualEqual,
+    Tok::ExclaimEqual,
+    Tok::LessEqual,
+    Tok::GreaterEqual,
+    Tok::LessLess,
+    Tok::Plus,
+    Tok::Minus,
+    Tok::Slash,
+    Tok::Percent,
+    Tok::Caret,
+    Tok::AmpAmp,
+    Tok::PipePipe,
+    Tok::EqualEqualGreater,
+    Tok::LessEqualEqualGreater,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ElementKind {
+    Token(Tok),
+    LineComment,
+    BlockComment,
+}
+
+/// A token or a comment
+#[derive(Debug, Clone, Copy)]
+struct Element<'a> {
+    kind: ElementKind,
+    text: &'a str,
+    /// Byte offset of the element in the buffer
+    offset: usize,
+    /// Number of line breaks preceding the element
+    newlines: usize,
+    /// Whether the element is preceded by whitespace (on the same line)
+    spaced: bool,
+}
+
+impl<'a> Element<'a> {
+    fn is(&self, tok: Tok) -> bool {
+        self.kind == ElementKind::Token(tok)
+    }
+
+    fn is_comment(&self) -> bool {
+        !matches!(self.kind, ElementKind::Token(_))
+    }
+
+    fn tok(&self) -> Tok {
+        match self.kind {
+            ElementKind::Token(tok) => tok,
+            // comments are never matched against tokens
+            _ => Tok::EOF,
+        }
+    }
+
+    fn is_opener(&self) -> bool {
+        matches!(self.tok(), Tok::LBrace | Tok::LParen | Tok::LBracket)
+    }
+
+    fn is_closer(&self) -> bool {
+        matches!(self.tok(), Tok::RBrace | Tok::RParen | Tok::RBracket)
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.text.len()
+    }
+}
+
+/// A line of formatted output
+#[derive(Debug, Clone)]
+struct Line<'a> {
+    indent: usize,
+    elements: Vec<Element<'a>>,
+}
+
+impl<'a> Line<'a> {
+    fn render(&self, indent: &str) -> String {
+        let mut line = indent.repeat(self.indent);
+        for (i, e) in self.elements.iter().enumerate() {
+            if i > 0 && e.spaced {
+                line.push(' ');
+            }
+            line.push_str(e.text);
+        }
+        line
+    }
+
+    /// Returns the range of the buffer the line was formatted from (None for blank lines)
+    fn source_range(&self) -> Option<ops::Range<usize>> {
+        let start = self.elements.iter().map(|e| e.offset).min()?;
+        let end = self.elements.iter().map(Element::end).max()?;
+        Some(start..end)
+    }
+}
+
+/// Splits the gap between two tokens (starting at the given offset) into comments, returning the
+/// line breaks and whitespace preceding the next token
+fn gap_elements<'a>(
+    gap: &'a str,
+    gap_offset: usize,
+    elements: &mut Vec<Element<'a>>,
+) -> (usize, bool) {
+    let mut newlines = 0;
+    let mut spaced = false;
+    let mut rest = gap;
+    while let Some(c) = rest.chars().next() {
+        let (kind, len) = if rest.starts_with("//") {
+            (
+                ElementKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if rest.starts_with("/*") {
+            // block comments can be nested
+            let mut depth = 0;
+            let mut len = 0;
+            while len < rest.len() {
+                if rest[len..].starts_with("/*") {
+                    depth += 1;
+                    len += 2;
+                } else if rest[len..].starts_with("*/") {
+                    depth -= 1;
+                    len += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    len += rest[len..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+            (ElementKind::BlockComment, len)
+        } else {
+            if c == '\n' {
+                newlines += 1;
+            }
+            spaced = true;
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        elements.push(Element {
+            kind,
+            text: rest[..len].trim_end(),
+            offset: gap_offset + gap.len() - rest.len(),
+            newlines,
+            spaced,
+        });
+        newlines = 0;
+        spaced = false;
+        rest = &rest[len..];
+    }
+    (newlines, spaced)
+}
+
+/// Returns tokens and comments of the buffer, or None if it cannot be lexed
+fn elements(buffer: &str) -> Option<Vec<Element>> {
+    let mut lexer = Lexer::new(buffer, FileHash::new(buffer));
+    let mut elements = vec![];
+    let mut prev_end = 0;
+    loop {
+        lexer.advance().ok()?;
+        let start = lexer.start_loc();
+        let (newlines, spaced) = gap_elements(&buffer[prev_end..start], prev_end, &mut elements);
+        if lexer.peek() == Tok::EOF {
+            return Some(elements);
+        }
+        elements.push(Element {
+            kind: ElementKind::Token(lexer.peek()),
+            // `&mut` is lexed together with the following whitespace
+            text: lexer.content().trim_end(),
+            offset: start,
+            newlines,
+            spaced,
+        });
+        prev_end = start + lexer.content().len();
+    }
+}
+
+/// Returns the index of the end (exclusive) of the use declaration starting at the given index,
+/// if there is one which can be moved around (i.e., starts on its own line, is not annotated
+/// with attributes and contains no comments)
+fn use_decl_end(elements: &[Element], start: usize) -> Option<usize> {
+    let e = elements.get(start)?;
+    if !e.is(Tok::Use) || (start > 0 && (e.newlines == 0 || elements[start - 1].is(Tok::RBracket)))
+    {
+        return None;
+    }
+    let len = elements[start..]
+        .iter()
+        .position(|e| e.is(Tok::Semicolon))?;
+    let end = start + len + 1;
+    if elements[start..end].iter().any(|e| e.is_comment()) {
+        return None;
+    }
+    Some(end)
+}
+
+/// Sorts the given sequences of elements by their text, keeping line breaks and whitespace
+/// preceding each sequence in place
+fn sort<'a>(mut seqs: Vec<Vec<Element<'a>>>) -> Vec<Vec<Element<'a>>> {
+    let leading = seqs
+        .iter()
+        .map(|seq| (seq[0].newlines, seq[0].spaced))
+        .collect::<Vec<_>>();
+    seqs.sort_by_key(|seq| sort_key(seq));
+    for (seq, (newlines, spaced)) in seqs.iter_mut().zip(leading) {
+        seq[0].newlines = newlines;
+        seq[0].spaced = spaced;
+    }
+    seqs
+}
+
+fn sort_key(seq: &[Element]) -> (bool, String) {
+    let text = seq.iter().map(|e| e.text).collect::<Vec<_>>().join(" ");
+    // `Self` goes first among imported members
+    (text != "Self" && !text.starts_with("Self "), text)
+}
+
+/// Sorts members imported by a single use declaration
+fn sort_use_members(use_decl: &mut Vec<Element>) {
+    let open = match use_decl.iter().position(|e| e.is(Tok::LBrace)) {
+        Some(i) => i,
+        None => return,
+    };
+    let close = match use_decl.iter().position(|e| e.is(Tok::RBrace)) {
+        Some(i) if i > open + 1 => i,
+        _ => return,
+    };
+    let mut members = vec![];
+    let mut separators = vec![];
+    for e in &use_decl[open + 1..close] {
+        if e.is(Tok::Comma) {
+            separators.push(*e);
+        } else if separators.len() == members.len() {
+            members.push(vec![*e]);
+        } else {
+            members.last_mut().unwrap().push(*e);
+        }
+    }
+    let members = sort(members);
+    let mut separators = separators.into_iter();
+    let mut sorted = use_decl[..=open].to_vec();
+    for member in members {
+        sorted.extend(member);
+        sorted.extend(separators.next());
+    }
+    sorted.extend_from_slice(&use_decl[close..]);
+    *use_decl = sorted;
+}
+
+/// Sorts groups of consecutive use declarations (separated by single line breaks), as well as
+/// members imported by each declaration
+fn sort_uses(elements: &mut Vec<Element>) {
+    let mut i = 0;
+    while i < elements.len() {
+        let mut group = vec![];
+        let mut end = i;
+        while let Some(decl_end) = use_decl_end(elements, end) {
+            if !group.is_empty() && elements[end].newlines != 1 {
+                break;
+            }
+            let mut use_decl = elements[end..decl_end].to_vec();
+            sort_use_members(&mut use_decl);
+            group.push(use_decl);
+            end = decl_end;
+        }
+        if group.is_empty() {
+            i += 1;
+            continue;
+        }
+        let sorted = sort(group).concat();
+        elements.splice(i..end, sorted);
+        i = end;
+    }
+}
+
+/// Returns true if the two elements (on the same line) should be separated by a space
+fn space_between(prev: &Element, next: &Element) -> bool {
+    use Tok::*;
+    if prev.is_comment() || next.is_comment() {
+        return next.kind == ElementKind::LineComment || next.spaced;
+    }
+    match (prev.tok(), next.tok()) {
+        (_, Comma | Semicolon | Colon | ColonColon | Period | RParen | RBracket) => false,
+        (LParen | LBracket | ColonColon | Period | NumSign | AtSign | Exclaim, _) => false,
+        // calls and function declarations (including built-in macros such as `assert!`)
+        (Identifier, Exclaim) => false,
+        (Identifier, LParen) => SPEC_KEYWORDS.contains(&prev.text),
+        (If | While | Return | Abort, LParen) => true,
+        (Comma | Colon | Semicolon | AmpMut, _) => true,
+        (p, n) if BINARY_OPERATORS.contains(&p) || BINARY_OPERATORS.contains(&n) => true,
+        (LBrace, RBrace) => false,
+        (_, LBrace) => true,
+        // the role of these tokens depends on the context (e.g., `<` may open a list of type
+        // arguments or compare values)
+        (LBrace | Less | Greater | GreaterGreater | Amp | Star | Pipe | PeriodPeriod, _)
+        | (_, RBrace | Less | Greater | GreaterGreater | Amp | Star | Pipe | PeriodPeriod)
+        | (_, LBracket | LParen) => next.spaced,
+        _ => true,
+    }
+}
+
+/// Lays out elements in lines
+fn lines(elements: Vec<Element>) -> Vec<Line> {
+    // indentation of lines where enclosing blocks are opened
+    let mut frames: Vec<usize> = vec![];
+    let mut lines: Vec<Line> = vec![];
+    for mut e in elements {
+        let prev = lines.last().and_then(|l| l.elements.last());
+        let mut newlines = if prev.is_none() { 0 } else { e.newlines.min(2) };
+        if prev.map_or(false, |p| p.is(Tok::LBrace)) || e.is(Tok::RBrace) {
+            // no blank lines at the beginning and at the end of a block
+            newlines = newlines.min(1);
+        }
+        match prev {
+            Some(prev) if newlines == 0 => {
+                e.spaced = space_between(prev, &e);
+                lines.last_mut().unwrap().elements.push(e);
+            }
+            _ => {
+                if newlines == 2 {
+                    lines.push(Line {
+                        indent: 0,
+                        elements: vec![],
+                    });
+                }
+                let indent = match frames.last() {
+                    Some(indent) if e.is_closer() => *indent,
+                    Some(indent) => indent + 1,
+                    None => 0,
+                };
+                e.spaced = false;
+                lines.push(Line {
+                    indent,
+                    elements: vec![e],
+                });
+            }
+        }
+        if e.is_opener() {
+            // contents of all blocks opened on the same line are indented by a single level
+            frames.push(lines.last().unwrap().indent);
+        } else if e.is_closer() {
+            frames.pop();
+        }
+    }
+    lines
+}
+
+/// Returns the range of a bracketed list of elements of a line which can be broken (and the
+/// indices of commas separating its items), if there is one
+fn breakable_list(line: &Line) -> Option<(usize, usize, Vec<usize>)> {
+    for (open, e) in line.elements.iter().enumerate() {
+        if !e.is_opener() {
+            continue;
+        }
+        let mut depth = 0;
+        let mut angle_depth = 0usize;
+        let mut commas = vec![];
+        for (i, e) in line.elements.iter().enumerate().skip(open) {
+            if e.is_opener() {
+                depth += 1;
+            } else if e.is_closer() {
+                depth -= 1;
+                if depth == 0 {
+                    if i > open + 1 {
+                        return Some((open, i, commas));
+                    }
+                    break;
+                }
+            } else if e.is(Tok::Less) && !e.spaced {
+                // commas in lists of type arguments do not separate items of the list
+                angle_depth += 1;
+            } else if e.is(Tok::Greater) {
+                angle_depth = angle_depth.saturating_sub(1);
+            } else if e.is(Tok::GreaterGreater) {
+                angle_depth = angle_depth.saturating_sub(2);
+            } else if e.is(Tok::Comma) && depth == 1 && angle_depth == 0 {
+                commas.push(i);
+            }
+        }
+    }
+    None
+}
+
+/// Breaks a line exceeding the maximum line length, putting items of a bracketed list on separate
+/// lines
+fn break_line<'a>(line: Line<'a>, indent: &str, max_line_length: usize) -> Vec<Line<'a>> {
+    if line.render(indent).chars().count() <= max_line_length
+        || line
+            .elements
+            .iter()
+            .any(|e| e.kind == ElementKind::BlockComment)
+    {
+        return vec![line];
+    }
+    let (open, close, commas) = match breakable_list(&line) {
+        Some(list) => list,
+        None => return vec![line],
+    };
+    let new_line = |elements: &[Element<'a>], indent: usize| {
+        let mut elements = elements.to_vec();
+        elements[0].spaced = false;
+        Line { indent, elements }
+    };
+    let mut lines = vec![new_line(&line.elements[..=open], line.indent)];
+    let mut start = open + 1;
+    for end in commas.into_iter().map(|i| i + 1).chain(Some(close)) {
+        if end > start {
+            lines.push(new_line(&line.elements[start..end], line.indent + 1));
+        }
+        start = end;
+    }
+    lines.push(new_line(&line.elements[close..], line.indent));
+    lines
+        .into_iter()
+        .flat_map(|l| break_line(l, indent, max_line_length))
+        .collect()
+}
+
+/// Returns the formatted lines of the buffer (along with the string used for a single level of
+/// indentation), or None if it cannot be parsed
+fn formatted_lines<'a>(
+    buffer: &'a str,
+    options: &FormattingOptions,
+    config: &FormatConfig,
+) -> Option<(String, Vec<Line<'a>>)> {
+    let mut env = CompilationEnv::new(Flags::empty());
+    parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+
+    let indent = if options.insert_spaces {
+        " ".repeat(options.tab_size as usize)
+    } else {
+        "\t".to_string()
+    };
+    let mut elements = elements(buffer)?;
+    if config.sort_uses {
+        sort_uses(&mut elements);
+    }
+    let lines = lines(elements)
+        .into_iter()
+        .flat_map(|line| break_line(line, &indent, config.max_line_length))
+        .collect();
+    Some((indent, lines))
+}
+
+/// Returns the formatted contents of the buffer, or None if it cannot be parsed
+pub fn format(buffer: &str, options: &FormattingOptions, config: &FormatConfig) -> Option<String> {
+    let (indent, lines) = formatted_lines(buffer, options, config)?;
+    let mut formatted = String::new();
+    for line in lines {
+        formatted.push_str(line.render(&indent).trim_end());
+        formatted.push('\n');
+    }
+    Some(formatted)
+}
+
+/// Formats the lines of the buffer overlapping with the given range, returning the range of the
+/// buffer to be replaced (extended to whole lines) along with its formatted contents, or None if
+/// the buffer cannot be parsed or there is nothing to format in the range
+pub fn format_range(
+    buffer: &str,
+    range: ops::Range<usize>,
+    options: &FormattingOptions,
+    config: &FormatConfig,
+) -> Option<(ops::Range<usize>, String)> {
+    // sorting use declarations moves them across lines
+    let config = FormatConfig {
+        sort_uses: false,
+        ..config.clone()
+    };
+    let (indent, lines) = formatted_lines(buffer, options, &config)?;
+    let line_start = |offset: usize| buffer[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = |offset: usize| {
+        buffer[offset..]
+            .find('\n')
+            .map_or(buffer.len(), |i| offset + i)
+    };
+    let start = line_start(range.start);
+    let end = line_end(range.end.max(range.start));
+    let inside = |line: &Line| {
+        line.source_range()
+            .map_or(false, |r| start <= r.start && r.end <= end)
+    };
+    let first = lines.iter().position(inside)?;
+    let last = lines.iter().rposition(inside)?;
+    let replaced =
+        line_start(lines[first].source_range()?.start)..line_end(lines[last].source_range()?.end);
+    let formatted = lines[first..=last]
+        .iter()
+        .map(|line| line.render(&indent).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some((replaced, formatted))
+}
+
+/// Returns the range of the buffer to be formatted after the given character was typed (just
+/// before the given offset): the block closed by a `}`, or the statement ended by a `;`
+fn on_type_range(buffer: &str, offset: usize, ch: &str) -> Option<ops::Range<usize>> {
+    let elements = elements(buffer)?;
+    let typed = elements
+        .iter()
+        .rposition(|e| !e.is_comment() && e.text == ch && e.end() == offset)?;
+    if !elements[typed].is(Tok::RBrace) {
+        return Some(elements[typed].offset..offset);
+    }
+    let mut depth = 0;
+    for e in elements[..=typed].iter().rev() {
+        if e.is(Tok::RBrace) {
+            depth += 1;
+        } else if e.is(Tok::LBrace) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(e.offset..offset);
+            }
+        }
+    }
+    None
+}
+
+/// Returns edits formatting the lines of the buffer overlapping with the given range
+fn range_edits(
+    buffer: &str,
+    range: ops::Range<usize>,
+    options: &FormattingOptions,
+    config: &FormatConfig,
+) -> Option<Vec<TextEdit>> {
+    let (range, formatted) = format_range(buffer, range, options, config)?;
+    if formatted == buffer[range.clone()] {
+        return Some(vec![]);
+    }
+    let range = Range {
+        start: buffer_position(buffer, range.start),
+        end: buffer_position(buffer, range.end),
+    };
+    Some(vec![TextEdit::new(range, formatted)])
+}
+
+fn send_edits(context: &Context, request: &Request, edits: Option<Vec<TextEdit>>) {
+    debug!("about to send formatting response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), edits);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send formatting response: {:?}", err);
+    }
+}
+
+/// Handles formatting request of the language server
+pub fn on_formatting_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<DocumentFormattingParams>(request.params.clone())
+        .expect("could not deserialize formatting request");
+
+    let edits = context
+        .files
+        .get(parameters.text_document.uri.path())
+        .and_then(|buffer| {
+            let formatted = format(buffer, &parameters.options, &context.format_config)?;
+            if formatted == buffer {
+                return Some(vec![]);
+            }
+            let range = Range {
+                start: Position::new(0, 0),
+                end: buffer_position(buffer, buffer.len()),
+            };
+            Some(vec![TextEdit::new(range, formatted)])
+        });
+    send_edits(context, request, edits);
+}
+
+/// Handles range formatting request of the language server
+pub fn on_range_formatting_request(context: &Context, request: &Request) {
+    let parameters =
+        serde_json::from_value::<DocumentRangeFormattingParams>(request.params.clone())
+            .expect("could not deserialize range formatting request");
+
+    let edits = context
+        .files
+        .get(parameters.text_document.uri.path())
+        .and_then(|buffer| {
+            let range = byte_offset(buffer, &parameters.range.start)
+                ..byte_offset(buffer, &parameters.range.end);
+            range_edits(buffer, range, &parameters.options, &context.format_config)
+        });
+    send_edits(context, request, edits);
+}
+
+/// Handles on-type formatting request of the language server
+pub fn on_on_type_formatting_request(context: &Context, request: &Request) {
+    let parameters =
+        serde_json::from_value::<DocumentOnTypeFormattingParams>(request.params.clone())
+            .expect("could not deserialize on-type formatting request");
+
+    let position = &parameters.text_document_position;
+    let edits = context
+        .files
+        .get(position.text_document.uri.path())
+        .and_then(|buffer| {
+            let offset = byte_offset(buffer, &position.position);
+            let range = on_type_range(buffer, offset, &parameters.ch)?;
+            range_edits(buffer, range, &parameters.options, &context.format_config)
+        });
+    send_edits(context, request, edits);
+}
+
+#[test]
+fn format_test() {
+    let buffer = r#"module   0x1::M{
+use 0x1::C;
+  use 0x1::A::{Self,g,f};
+// B goes after A
+use 0x1::B;
+
+
+    /// A struct
+    struct S<T: copy+drop> has   key { f: vector<T> }
+        fun f(x:&mut u64,y : u64):u64 {
+    *x=*x+y;     // add
+    if(x > y) { abort 1 };
+        assert!(vector::length<u64>(&vector::empty<u64>()) == 0, 0);
+
+
+        /* block
+           comment */
+        y
+    }
+
+}
+"#;
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..Default::default()
+    };
+    let config = FormatConfig {
+        max_line_length: 60,
+        ..Default::default()
+    };
+    let expected = r#"module 0x1::M {
+    use 0x1::A::{Self, f, g};
+    use 0x1::C;
+    // B goes after A
+    use 0x1::B;
+
+    /// A struct
+    struct S<T: copy + drop> has key { f: vector<T> }
+    fun f(x: &mut u64, y: u64): u64 {
+        *x = *x + y; // add
+        if (x > y) { abort 1 };
+        assert!(
+            vector::length<u64>(&vector::empty<u64>()) == 0,
+            0
+        );
+
+        /* block
+           comment */
+        y
+    }
+}
+"#;
+    let formatted = format(buffer, &options, &config).unwrap();
+    assert_eq!(formatted, expected);
+    // formatting is idempotent
+    assert_eq!(format(&formatted, &options, &config).unwrap(), expected);
+
+    // uses are kept in place if sorting is disabled
+    let config = FormatConfig {
+        sort_uses: false,
+        ..config
+    };
+    assert!(format(buffer, &options, &config)
+        .unwrap()
+        .contains("use 0x1::C;\n    use 0x1::A::{Self, g, f};"));
+
+    // nothing is returned for unparsable buffers
+    assert!(format("module M {", &options, &config).is_none());
+}
+
+#[test]
+fn format_range_test() {
+    let buffer = r#"module 0x1::M {
+use 0x1::B;
+use 0x1::A;
+fun f(x:u64):u64 {
+let y=x+1;
+        y
+    }
+  fun g() {}
+}
+"#;
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..Default::default()
+    };
+    let config = FormatConfig::default();
+    let line = |n: usize| {
+        let start = buffer
+            .split('\n')
+            .take(n)
+            .map(|l| l.len() + 1)
+            .sum::<usize>();
+        start..start + buffer.split('\n').nth(n).unwrap().len()
+    };
+
+    // only lines overlapping with the range are formatted (and uses are not sorted)
+    let (range, formatted) = format_range(
+        buffer,
+        line(4).start + 3..line(4).start + 4,
+        &options,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(range, line(4));
+    assert_eq!(formatted, "        let y = x + 1;");
+    let (range, formatted) =
+        format_range(buffer, line(1).start..line(2).end, &options, &config).unwrap();
+    assert_eq!(range, line(1).start..line(2).end);
+    assert_eq!(formatted, "    use 0x1::B;\n    use 0x1::A;");
+
+    // typing `;` formats the statement it ends
+    let range = on_type_range(buffer, line(4).end, ";").unwrap();
+    assert_eq!(
+        format_range(buffer, range, &options, &config).unwrap(),
+        (line(4), "        let y = x + 1;".to_string())
+    );
+
+    // typing `}` formats the block it closes
+    let range = on_type_range(buffer, line(6).end, "}").unwrap();
+    assert_eq!(
+        format_range(buffer, range, &options, &config).unwrap(),
+        (
+            line(3).start..line(6).end,
+            "    fun f(x: u64): u64 {\n        let y = x + 1;\n        y\n    }".to_string()
+        )
+    );
+    assert!(on_type_range(buffer, line(5).end, "}").is_none());
+}
@@ -0,0 +1,475 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Quick fix generating a stub of a function that is called but not defined in the current module.
+//!
+//! The stub aborts when called and is added after the function containing the call. Its
+//! parameters are named after locals passed as arguments of the call and typed after the
+//! arguments whose types can be inferred from the buffer (literals, borrows, struct values,
+//! simple operations and locals whose types are known from the symbols of the file). Arguments of
+//! other types, as well as the result of the call if it is used but its type cannot be inferred
+//! from the context, are typed with type parameters of the stub, leaving their instantiation to
+//! the compiler.
+
+use crate::{
+    code_action::ident_len,
+    extract_function::{enclosing_function, identifiers, line_indent, used_type_params, LocalUse},
+    vfs::{buffer_position, byte_offset},
+};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, Range, TextEdit, Url,
+    WorkspaceEdit,
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    parser::{
+        lexer::{Lexer, Tok},
+        syntax::parse_file_string,
+    },
+    shared::{CompilationEnv, Flags},
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// Token of a text along with its content and byte offset
+type Token<'a> = (Tok, &'a str, usize);
+
+/// Type of the result of a call
+enum ResultType {
+    /// The result is not used
+    Unused,
+    /// The result is used where a value of the given type is expected
+    Known(String),
+    /// The result is used but its type cannot be inferred
+    Unknown,
+}
+
+/// Returns a quick fix generating a stub of the function that the diagnostic reports as unbound,
+/// or `None` if the diagnostic is not about a call of an unbound function of the current module
+pub fn generate_function_action(
+    uri: &Url,
+    buffer: &str,
+    diagnostic: &Diagnostic,
+    locals: &[LocalUse],
+) -> Option<CodeActionOrCommand> {
+    let (name, (start, end, new_text)) = generate_function(buffer, diagnostic, locals)?;
+    let edit = TextEdit {
+        range: Range::new(buffer_position(buffer, start), buffer_position(buffer, end)),
+        new_text,
+    };
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: format!("Generate function `{}`", name),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+/// Returns the name of the called function along with the edit (as a byte range to be replaced
+/// and its replacement) adding its stub
+fn generate_function<'a>(
+    buffer: &'a str,
+    diagnostic: &Diagnostic,
+    locals: &[LocalUse],
+) -> Option<(&'a str, (usize, usize, String))> {
+    let name_start = byte_offset(buffer, &diagnostic.range.start);
+    let name = &buffer[name_start..name_start + ident_len(&buffer[name_start..])];
+    if name.is_empty()
+        || diagnostic.message != format!("Unbound function '{}' in current scope", name)
+    {
+        return None;
+    }
+    // calls with explicit type arguments are not supported, as the number of type parameters of
+    // the stub would need to match them
+    let after_name = &buffer[name_start + name.len()..];
+    let open = buffer.len() - after_name.trim_start().len();
+    if !after_name.trim_start().starts_with('(') {
+        return None;
+    }
+    let (args, call_end) = delimited_items(buffer, open)?;
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+    let fun = enclosing_function(&defs, name_start, call_end)?;
+    let fun_start = fun.loc.start() as usize;
+    let fun_end = fun.loc.end() as usize;
+
+    // types of locals of the enclosing function defined before the call (later definitions
+    // shadowing earlier ones)
+    let mut local_defs = locals
+        .iter()
+        .filter(|u| u.start == u.def_start)
+        .map(|u| (byte_offset(buffer, &u.def_start), u.ty.replace("& ", "&")))
+        .filter(|(def, _)| fun_start <= *def && *def < name_start)
+        .collect::<Vec<_>>();
+    local_defs.sort();
+    let fun_locals = local_defs
+        .into_iter()
+        .map(|(def, ty)| (&buffer[def..def + ident_len(&buffer[def..])], ty))
+        .filter(|(local, _)| !local.is_empty())
+        .collect::<BTreeMap<_, _>>();
+
+    let used = identifiers(&buffer[fun_start..fun_end]);
+    let mut new_type_params = vec![];
+    let mut fresh_type_param = || {
+        let tp = (0..)
+            .map(|i| format!("T{}", i))
+            .find(|tp| !used.contains(tp.as_str()) && !new_type_params.contains(tp))
+            .unwrap();
+        new_type_params.push(tp.clone());
+        tp
+    };
+    let mut params = Vec::<(String, String)>::new();
+    for (i, (start, end)) in args.into_iter().enumerate() {
+        let arg = &buffer[start..end];
+        let ty = param_type(arg, &fun_locals, &mut fresh_type_param);
+        let param_name = arg_local(arg)
+            .filter(|local| !params.iter().any(|(p, _)| p == *local))
+            .map_or_else(|| format!("arg{}", i), str::to_string);
+        params.push((param_name, ty));
+    }
+    let result = match result_type(buffer, name_start, call_end, &fun_locals) {
+        ResultType::Unused => None,
+        ResultType::Known(ty) => Some(ty),
+        ResultType::Unknown => Some(fresh_type_param()),
+    };
+
+    let mut type_params = used_type_params(
+        fun,
+        params
+            .iter()
+            .map(|(_, ty)| ty.as_str())
+            .chain(result.as_deref()),
+    );
+    type_params.extend(new_type_params);
+    let indent = line_indent(buffer, fun_start);
+    let mut stub = format!("\n\n{}fun {}", indent, name);
+    if !type_params.is_empty() {
+        stub.push_str(&format!("<{}>", type_params.join(", ")));
+    }
+    let params = params
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", name, ty))
+        .collect::<Vec<_>>();
+    stub.push_str(&format!("({})", params.join(", ")));
+    if let Some(ty) = result {
+        stub.push_str(&format!(": {}", ty));
+    }
+    stub.push_str(&format!(" {{\n{}    abort 0\n{}}}", indent, indent));
+    Some((name, (fun_end, fun_end, stub)))
+}
+
+/// Returns byte ranges of comma-separated items of a parenthesized (or bracketed) list starting at
+/// the given offset, along with the offset of the end of the list
+fn delimited_items(text: &str, open: usize) -> Option<(Vec<(usize, usize)>, usize)> {
+    let mut lexer = Lexer::new(&text[open..], FileHash::new(&text[open..]));
+    let mut items = vec![];
+    let mut item: Option<(usize, usize)> = None;
+    let mut depth = 0;
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        let start = open + lexer.start_loc();
+        let end = start + lexer.content().len();
+        match lexer.peek() {
+            Tok::LParen | Tok::LBracket | Tok::LBrace => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            Tok::RParen | Tok::RBracket | Tok::RBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    items.extend(item);
+                    return Some((items, end));
+                }
+            }
+            Tok::Comma if depth == 1 => {
+                items.extend(item.take());
+                continue;
+            }
+            _ => (),
+        }
+        if depth == 0 {
+            return None;
+        }
+        item = Some((item.map_or(start, |(s, _)| s), end));
+    }
+    None
+}
+
+/// Returns tokens of the given text, or `None` if it cannot be lexed
+fn tokens(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut lexer = Lexer::new(text, FileHash::new(text));
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        tokens.push((lexer.peek(), lexer.content(), lexer.start_loc()));
+    }
+    (lexer.peek() == Tok::EOF).then(|| tokens)
+}
+
+/// Returns the local passed as the given argument (possibly borrowed, copied or moved), if any
+fn arg_local(arg: &str) -> Option<&str> {
+    match tokens(arg)?.as_slice() {
+        [(Tok::Identifier, local, _)]
+        | [(Tok::Amp | Tok::AmpMut | Tok::Copy | Tok::Move, _, _), (Tok::Identifier, local, _)] => {
+            Some(*local)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the type of a parameter to which the given argument is passed, typing the argument (or
+/// the borrowed value) with a fresh type parameter if its type cannot be inferred
+fn param_type(
+    arg: &str,
+    locals: &BTreeMap<&str, String>,
+    fresh_type_param: &mut dyn FnMut() -> String,
+) -> String {
+    let (prefix, value) = if let Some(value) = arg.strip_prefix("&mut ") {
+        ("&mut ", value)
+    } else if let Some(value) = arg.strip_prefix('&') {
+        ("&", value)
+    } else {
+        ("", arg)
+    };
+    let ty = arg_type(value.trim(), locals)
+        // references cannot be borrowed
+        .filter(|ty| prefix.is_empty() || !ty.starts_with('&'))
+        .unwrap_or_else(fresh_type_param);
+    format!("{}{}", prefix, ty)
+}
+
+/// Returns the type of the given argument, or `None` if it cannot be inferred
+fn arg_type(arg: &str, locals: &BTreeMap<&str, String>) -> Option<String> {
+    let tokens = tokens(arg)?;
+    let first = tokens.first()?.0;
+    match tokens.as_slice() {
+        [(Tok::NumValue, _, _)] => return Some("u64".to_string()),
+        [(Tok::NumTypedValue, num, _)] => return num.rfind('u').map(|i| num[i..].to_string()),
+        [(Tok::True | Tok::False, _, _)] => return Some("bool".to_string()),
+        [(Tok::ByteStringValue, _, _)] => return Some("vector<u8>".to_string()),
+        [(Tok::AtSign, _, _), ..] => return Some("address".to_string()),
+        [(Tok::Identifier, local, _)]
+        | [(Tok::Copy | Tok::Move, _, _), (Tok::Identifier, local, _)] => {
+            return locals.get(local).cloned()
+        }
+        [(Tok::Identifier, "vector", _), (Tok::LBracket, _, open), ..] => {
+            let (elems, _) = delimited_items(arg, *open)?;
+            let (start, end) = elems.first()?;
+            return arg_type(&arg[*start..*end], locals).map(|ty| format!("vector<{}>", ty));
+        }
+        [(Tok::Identifier, "vector", _), (Tok::Less, _, _), ..] => {
+            let (_, _, open) = tokens.iter().find(|(tok, _, _)| *tok == Tok::LBracket)?;
+            return Some(arg[..*open].trim().to_string());
+        }
+        [.., (Tok::As, _, _), (Tok::Identifier, ty, _), (Tok::RParen, _, _)] => {
+            return Some(ty.to_string())
+        }
+        _ => (),
+    }
+    // struct values and calls (whose results are of unknown types), possibly with type arguments
+    let open = tokens
+        .iter()
+        .find(|(tok, _, _)| matches!(tok, Tok::LBrace | Tok::LParen));
+    if let Some((open_tok, _, open)) = open {
+        let is_name = tokens
+            .iter()
+            .take_while(|(_, _, offset)| offset < open)
+            .all(|(tok, _, _)| {
+                matches!(
+                    tok,
+                    Tok::Identifier
+                        | Tok::NumValue
+                        | Tok::ColonColon
+                        | Tok::Less
+                        | Tok::Greater
+                        | Tok::Comma
+                )
+            });
+        if is_name && first == Tok::Identifier {
+            return match open_tok {
+                Tok::LBrace => Some(arg[..*open].trim().to_string()),
+                _ => None,
+            };
+        }
+    }
+    if first == Tok::Exclaim {
+        return Some("bool".to_string());
+    }
+
+    // operations (whose operands are split at operators outside of parentheses)
+    let mut operands = vec![];
+    let mut operand_start = 0;
+    let mut depth = 0;
+    for (tok, content, offset) in &tokens {
+        match tok {
+            Tok::LParen | Tok::LBracket | Tok::LBrace => depth += 1,
+            Tok::RParen | Tok::RBracket | Tok::RBrace => depth -= 1,
+            Tok::EqualEqual
+            | Tok::ExclaimEqual
+            | Tok::Less
+            | Tok::LessEqual
+            | Tok::Greater
+            | Tok::GreaterEqual
+            | Tok::AmpAmp
+            | Tok::PipePipe
+                if depth == 0 =>
+            {
+                return Some("bool".to_string())
+            }
+            Tok::Plus
+            | Tok::Minus
+            | Tok::Star
+            | Tok::Slash
+            | Tok::Percent
+            | Tok::Amp
+            | Tok::Pipe
+            | Tok::Caret
+                if depth == 0 =>
+            {
+                operands.push(&arg[operand_start..*offset]);
+                operand_start = offset + content.len();
+            }
+            // the type of a shift is that of its left operand
+            Tok::LessLess | Tok::GreaterGreater if depth == 0 => {
+                return arg_type(arg[..*offset].trim(), locals);
+            }
+            _ => (),
+        }
+    }
+    if operands.is_empty() {
+        return None;
+    }
+    operands.push(&arg[operand_start..]);
+    let types = operands
+        .iter()
+        .filter_map(|operand| arg_type(operand.trim(), locals))
+        .collect::<Vec<_>>();
+    // untyped number literals are only assumed to be `u64` if no other operand is typed
+    types
+        .iter()
+        .find(|ty| *ty != "u64")
+        .or_else(|| types.first())
+        .cloned()
+}
+
+/// Returns the type of the result of a call spanning the given byte range, as expected by the
+/// context of the call
+fn result_type(
+    buffer: &str,
+    start: usize,
+    end: usize,
+    locals: &BTreeMap<&str, String>,
+) -> ResultType {
+    let before = buffer[..start].trim_end();
+    let after = buffer[end..].trim_start();
+    if before.ends_with(|c: char| matches!(c, '{' | ';' | '}')) && after.starts_with(';') {
+        return ResultType::Unused;
+    }
+    // conditions
+    if let Some(before_paren) = before.strip_suffix('(') {
+        let before_paren = before_paren.trim_end();
+        let keyword_start = before_paren
+            .rfind(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '!')
+            .map_or(0, |i| i + 1);
+        if matches!(&before_paren[keyword_start..], "if" | "while" | "assert!") {
+            return ResultType::Known("bool".to_string());
+        }
+    }
+    // bound or assigned values
+    let before_value = match before.strip_suffix('=') {
+        Some(before_value)
+            if !before_value.ends_with(|c: char| matches!(c, '=' | '<' | '>' | '!')) =>
+        {
+            before_value
+        }
+        _ => return ResultType::Unknown,
+    };
+    let statement_start = before_value
+        .rfind(|c: char| matches!(c, '{' | ';' | '}'))
+        .map_or(0, |i| i + 1);
+    let statement = before_value[statement_start..].trim();
+    if let Some(bind) = statement.strip_prefix("let ") {
+        return match bind.split_once(':') {
+            Some((_, ty)) => ResultType::Known(ty.trim().to_string()),
+            None => ResultType::Unknown,
+        };
+    }
+    match locals.get(statement) {
+        Some(ty) => ResultType::Known(ty.clone()),
+        None => ResultType::Unknown,
+    }
+}
+
+#[test]
+fn generate_function_test() {
+    use lsp_types::Position;
+
+    let buffer = r#"module 0x1::M {
+    struct S<T> has drop { f: T }
+
+    fun f<T: drop>(x: u64, t: T, v: vector<u8>): u64 {
+        let s = S { f: t };
+        g(x, &mut v, 1u8, true, b"a", @0x1, S<u64> { f: 1 }, x + 1, vector[x], (x as u128), &s.f);
+        let b: bool = h(&s, x);
+        if (k(copy x, 0, 2 > 1)) {
+            x = m(s);
+        };
+        n();
+        x + p()
+    }
+}
+"#;
+    let position = |s: &str| buffer_position(buffer, buffer.find(s).unwrap());
+    let local = |def: &str, ty: &str| LocalUse {
+        def_start: position(def),
+        start: position(def),
+        ty: ty.to_string(),
+        write: true,
+    };
+    let locals = vec![
+        local("x: u64", "u64"),
+        local("t: T", "T"),
+        local("v: vector", "vector<u8>"),
+        local("s = S", "0x1::M::S<T>"),
+    ];
+    let generate = |name: &str| {
+        let start = position(&format!("{}(", name));
+        let diagnostic = Diagnostic::new_simple(
+            Range::new(start, Position::new(start.line, start.character + 1)),
+            format!("Unbound function '{}' in current scope", name),
+        );
+        generate_function(buffer, &diagnostic, &locals).map(|(_, (start, end, new_text))| {
+            assert_eq!(start, end);
+            assert_eq!(&buffer[start - 1..start], "}");
+            new_text
+        })
+    };
+
+    assert_eq!(
+        generate("g").unwrap(),
+        "\n\n    fun g<T0>(x: u64, v: &mut vector<u8>, arg2: u8, arg3: bool, arg4: vector<u8>, arg5: address, arg6: S<u64>, arg7: u64, arg8: vector<u64>, arg9: u128, arg10: &T0) {\n        abort 0\n    }"
+    );
+    assert_eq!(
+        generate("h").unwrap(),
+        "\n\n    fun h<T: drop>(s: &0x1::M::S<T>, x: u64): bool {\n        abort 0\n    }"
+    );
+    assert_eq!(
+        generate("k").unwrap(),
+        "\n\n    fun k(x: u64, arg1: u64, arg2: bool): bool {\n        abort 0\n    }"
+    );
+    assert_eq!(
+        generate("m").unwrap(),
+        "\n\n    fun m<T: drop>(s: 0x1::M::S<T>): u64 {\n        abort 0\n    }"
+    );
+    assert_eq!(
+        generate("n").unwrap(),
+        "\n\n    fun n() {\n        abort 0\n    }"
+    );
+    assert_eq!(
+        generate("p").unwrap(),
+        "\n\n    fun p<T0>(): T0 {\n        abort 0\n    }"
+    );
+}
@@ -0,0 +1,252 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inlay hints showing information inferred by the compiler that is not present in the source
+//! code: types of `let`-bound variables and type arguments elided at function call sites.
+//!
+//! The version of `lsp_types` used by the language server predates inlay hints becoming part of the
+//! protocol, so the request and its (minimal) data types are defined here.
+
+use crate::utils::get_loc;
+use codespan_reporting::files::{Files, SimpleFiles};
+use lsp_types::{Position, Range, TextDocumentIdentifier};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    naming::ast::{Type, Type_},
+    shared::Identifier,
+    typing::ast::{
+        Exp, ExpListItem, FunctionBody_, LValue, LValueList, LValue_, ModuleCall, ModuleDefinition,
+        SequenceItem, SequenceItem_, UnannotatedExp_,
+    },
+};
+use move_ir_types::location::*;
+use move_symbol_pool::Symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// The `textDocument/inlayHint` request
+pub enum InlayHintRequest {}
+
+impl lsp_types::request::Request for InlayHintRequest {
+    type Params = InlayHintParams;
+    type Result = Option<Vec<InlayHint>>;
+    const METHOD: &'static str = "textDocument/inlayHint";
+}
+
+/// Parameters of the inlay hint request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHintParams {
+    pub text_document: TextDocumentIdentifier,
+    /// The visible document range for which inlay hints should be computed
+    pub range: Range,
+}
+
+/// Kind of an inlay hint (serialized as a number, as required by the protocol)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InlayHintKind(u32);
+
+impl InlayHintKind {
+    pub const TYPE: InlayHintKind = InlayHintKind(1);
+}
+
+/// A single inlay hint
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHint {
+    /// Position of the hint (the hint is displayed before the character at this position)
+    pub position: Position,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<InlayHintKind>,
+}
+
+/// Collects inlay hints for a module, keyed by file hash
+pub struct InlayHintCollector<'a> {
+    files: &'a SimpleFiles<Symbol, String>,
+    file_id_mapping: &'a HashMap<FileHash, usize>,
+    hints: BTreeMap<FileHash, Vec<InlayHint>>,
+}
+
+impl<'a> InlayHintCollector<'a> {
+    pub fn new(
+        files: &'a SimpleFiles<Symbol, String>,
+        file_id_mapping: &'a HashMap<FileHash, usize>,
+    ) -> Self {
+        Self {
+            files,
+            file_id_mapping,
+            hints: BTreeMap::new(),
+        }
+    }
+
+    /// Returns collected hints (sorted by position)
+    pub fn finish(mut self) -> BTreeMap<FileHash, Vec<InlayHint>> {
+        for hints in self.hints.values_mut() {
+            hints.sort_by_key(|h| h.position);
+        }
+        self.hints
+    }
+
+    /// Collects hints for all function bodies in a module
+    pub fn mod_hints(&mut self, mod_def: &ModuleDefinition) {
+        for (_, _, fun) in &mod_def.functions {
+            if let FunctionBody_::Defined(sequence) = &fun.body.value {
+                for seq_item in sequence {
+                    self.seq_item_hints(seq_item);
+                }
+            }
+        }
+    }
+
+    fn seq_item_hints(&mut self, seq_item: &SequenceItem) {
+        use SequenceItem_ as I;
+        match &seq_item.value {
+            I::Seq(e) => self.exp_hints(e),
+            I::Declare(_) => (),
+            I::Bind(lvalues, _, e) => {
+                // an annotated binding is represented as an annotated RHS expression, in which case
+                // types of the variables are already visible
+                if !matches!(e.exp.value, UnannotatedExp_::Annotate(..)) {
+                    self.lvalue_list_hints(lvalues);
+                }
+                self.exp_hints(e);
+            }
+        }
+    }
+
+    fn lvalue_list_hints(&mut self, lvalues: &LValueList) {
+        for lval in &lvalues.value {
+            self.lvalue_hints(lval);
+        }
+    }
+
+    fn lvalue_hints(&mut self, lval: &LValue) {
+        match &lval.value {
+            LValue_::Var(var, t) => {
+                if let Some(ty) = type_hint_string(t) {
+                    self.add_hint(&var.loc(), format!(": {}", ty));
+                }
+            }
+            LValue_::Unpack(_, _, _, fields) | LValue_::BorrowUnpack(_, _, _, _, fields) => {
+                for (_, _, (_, (_, lval))) in fields {
+                    self.lvalue_hints(lval);
+                }
+            }
+            LValue_::Ignore => (),
+        }
+    }
+
+    fn exp_hints(&mut self, exp: &Exp) {
+        use UnannotatedExp_ as E;
+        match &exp.exp.value {
+            E::ModuleCall(mod_call) => self.mod_call_hints(mod_call),
+            E::Builtin(_, e) => self.exp_hints(e),
+            E::Vector(_, _, _, e) => self.exp_hints(e),
+            E::IfElse(cond, t, f) => {
+                self.exp_hints(cond);
+                self.exp_hints(t);
+                self.exp_hints(f);
+            }
+            E::While(cond, body) => {
+                self.exp_hints(cond);
+                self.exp_hints(body);
+            }
+            E::Loop { body, .. } => self.exp_hints(body),
+            E::Block(sequence) => {
+                for seq_item in sequence {
+                    self.seq_item_hints(seq_item);
+                }
+            }
+            E::Assign(_, _, e) => self.exp_hints(e),
+            E::Mutate(lhs, rhs) => {
+                self.exp_hints(lhs);
+                self.exp_hints(rhs);
+            }
+            E::Return(e) => self.exp_hints(e),
+            E::Abort(e) => self.exp_hints(e),
+            E::Dereference(e) => self.exp_hints(e),
+            E::UnaryExp(_, e) => self.exp_hints(e),
+            E::BinopExp(lhs, _, _, rhs) => {
+                self.exp_hints(lhs);
+                self.exp_hints(rhs);
+            }
+            E::Pack(_, _, _, fields) => {
+                for (_, _, (_, (_, e))) in fields {
+                    self.exp_hints(e);
+                }
+            }
+            E::ExpList(list_items) => {
+                for item in list_items {
+                    match item {
+                        ExpListItem::Single(e, _) => self.exp_hints(e),
+                        ExpListItem::Splat(_, e, _) => self.exp_hints(e),
+                    }
+                }
+            }
+            E::Borrow(_, e, _) => self.exp_hints(e),
+            E::TempBorrow(_, e) => self.exp_hints(e),
+            E::Cast(e, _) => self.exp_hints(e),
+            E::Annotate(e, _) => self.exp_hints(e),
+            _ => (),
+        }
+    }
+
+    fn mod_call_hints(&mut self, mod_call: &ModuleCall) {
+        let name_loc = mod_call.name.loc();
+        // type arguments are elided if the function name is not immediately followed by them
+        if !mod_call.type_arguments.is_empty() && self.char_at(&name_loc) != Some('<') {
+            let ty_args = mod_call
+                .type_arguments
+                .iter()
+                .map(type_hint_string)
+                .collect::<Option<Vec<_>>>();
+            if let Some(ty_args) = ty_args {
+                self.add_hint(&name_loc, format!("<{}>", ty_args.join(", ")));
+            }
+        }
+        self.exp_hints(&mod_call.arguments);
+    }
+
+    /// Returns the source character immediately following the given location
+    fn char_at(&self, loc: &Loc) -> Option<char> {
+        let id = self.file_id_mapping.get(&loc.file_hash())?;
+        let source = self.files.source(*id).ok()?;
+        source.get(loc.end() as usize..)?.chars().next()
+    }
+
+    /// Adds a hint displayed right after the given location
+    fn add_hint(&mut self, loc: &Loc, label: String) {
+        let fhash = loc.file_hash();
+        if let Some(position) = get_loc(&fhash, loc.end(), self.files, self.file_id_mapping) {
+            self.hints
+                .entry(fhash)
+                .or_insert_with(Vec::new)
+                .push(InlayHint {
+                    position,
+                    label,
+                    kind: Some(InlayHintKind::TYPE),
+                });
+        }
+    }
+}
+
+/// Returns a string representation of a type to be displayed in a hint, or `None` if the type has
+/// not been fully inferred (in which case a hint would be more confusing than helpful)
+pub fn type_hint_string(t: &Type) -> Option<String> {
+    if is_fully_inferred(t) {
+        Some(crate::symbols::type_to_ide_string(t))
+    } else {
+        None
+    }
+}
+
+fn is_fully_inferred(sp!(_, t): &Type) -> bool {
+    match t {
+        Type_::Unit | Type_::Param(_) => true,
+        Type_::Ref(_, t) => is_fully_inferred(t),
+        Type_::Apply(_, _, ss) => ss.iter().all(is_fully_inferred),
+        Type_::Var(_) | Type_::Anything | Type_::UnresolvedError => false,
+    }
+}
@@ -5,9 +5,32 @@
 #[macro_use(sp)]
 extern crate move_ir_types;
 
+pub mod abort_code;
+pub mod bytecode;
+pub mod cache;
+pub mod code_action;
+pub mod code_lens;
 pub mod completion;
+pub mod config;
 pub mod context;
+pub mod dependency_graph;
 pub mod diagnostics;
+pub mod document_link;
+pub mod extract_constant;
+pub mod extract_function;
+pub mod folding;
+pub mod formatting;
+pub mod generate_function;
+pub mod inlay_hints;
+pub mod logging;
+pub mod organize_imports;
+pub mod prover;
+pub mod selection_range;
+pub mod signature_help;
+pub mod standalone;
 pub mod symbols;
+pub mod type_at;
+pub mod unit_test;
 pub mod utils;
 pub mod vfs;
+pub mod watched_files;
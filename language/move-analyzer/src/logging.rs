@@ -0,0 +1,101 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Logging of the language server's activity, to help diagnose slow or stuck servers.
+//!
+//! Messages are written to stderr (which clients such as Visual Studio Code display in an output
+//! channel) unless a log file is given on the command line. Symbolication runs and request
+//! handlers are recorded as spans, whose durations are logged when they finish. The verbosity set
+//! on the command line can be raised by the client via the `$/setTrace` notification (e.g., when
+//! the user enables tracing of the server in the editor's settings): `messages` enables debug
+//! messages (including timing of request handlers) and `verbose` enables all of them.
+
+use anyhow::Result;
+use lsp_server::Notification;
+use lsp_types::{SetTraceParams, TraceValue};
+use std::{cmp, fs::OpenOptions, io, path::Path, sync::Mutex};
+use tracing::error;
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{format::FmtSpan, writer::BoxMakeWriter},
+    prelude::*,
+    reload, Registry,
+};
+
+/// Handle to the logger, through which the verbosity of logging can be changed
+pub struct Logger {
+    /// Verbosity given on the command line
+    level: LevelFilter,
+    handle: reload::Handle<LevelFilter, Registry>,
+}
+
+impl Logger {
+    /// Starts logging messages up to the given verbosity to the given file (appending to it) or,
+    /// if no file is given, to stderr. Must be called at most once.
+    pub fn init(log_file: Option<&Path>, level: LevelFilter) -> Result<Self> {
+        let writer = match log_file {
+            Some(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                BoxMakeWriter::new(Mutex::new(file))
+            }
+            None => BoxMakeWriter::new(io::stderr),
+        };
+        let (filter, handle) = reload::Layer::new(level);
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(writer)
+                    .with_ansi(false)
+                    .with_span_events(FmtSpan::CLOSE),
+            )
+            .try_init()?;
+        Ok(Self { level, handle })
+    }
+
+    /// Changes the verbosity of logging according to the client's trace setting
+    pub fn set_trace(&self, trace: TraceValue) {
+        if let Err(err) = self.handle.reload(trace_level(self.level, trace)) {
+            error!("could not change log level: {:?}", err);
+        }
+    }
+
+    /// Handles the `$/setTrace` notification of the client
+    pub fn on_set_trace_notification(&self, notification: &Notification) {
+        match serde_json::from_value::<SetTraceParams>(notification.params.clone()) {
+            Ok(params) => self.set_trace(params.value),
+            Err(err) => error!("could not deserialize set trace notification: {:?}", err),
+        }
+    }
+}
+
+/// Returns the verbosity of logging for a trace setting of the client, which never lowers the
+/// verbosity given on the command line
+fn trace_level(level: LevelFilter, trace: TraceValue) -> LevelFilter {
+    let trace_level = match trace {
+        TraceValue::Off => LevelFilter::OFF,
+        TraceValue::Messages => LevelFilter::DEBUG,
+        TraceValue::Verbose => LevelFilter::TRACE,
+    };
+    cmp::max(level, trace_level)
+}
+
+#[test]
+fn trace_level_test() {
+    assert_eq!(
+        trace_level(LevelFilter::INFO, TraceValue::Off),
+        LevelFilter::INFO
+    );
+    assert_eq!(
+        trace_level(LevelFilter::INFO, TraceValue::Messages),
+        LevelFilter::DEBUG
+    );
+    assert_eq!(
+        trace_level(LevelFilter::WARN, TraceValue::Verbose),
+        LevelFilter::TRACE
+    );
+    assert_eq!(
+        trace_level(LevelFilter::TRACE, TraceValue::Messages),
+        LevelFilter::TRACE
+    );
+}
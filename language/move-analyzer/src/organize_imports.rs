@@ -0,0 +1,382 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Source action organizing `use` declarations of each module (or script) of a file.
+//!
+//! Declarations are sorted by module, declarations importing from the same module are merged into
+//! a single (grouped) declaration, and aliases not referred to anywhere else in the module are
+//! removed. The organized declarations replace the first of the original ones, while the rest of
+//! them are deleted, all in a single edit.
+//!
+//! Similarly to other actions computed from the buffer, declarations are found by lexing rather
+//! than parsing so that files that do not compile can be organized as well. Declarations with
+//! attributes (e.g., `#[test_only]`) are left as they are.
+
+use crate::vfs::buffer_position;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Range, TextEdit, Url, WorkspaceEdit,
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::parser::lexer::{Lexer, Tok};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Token of the buffer along with its content and byte offset
+type Token<'a> = (Tok, &'a str, usize);
+
+/// Aliases imported from a single module by `use` declarations
+#[derive(Debug, Default)]
+struct ModuleImports {
+    /// Name of the module
+    name: String,
+    /// Aliases of the module itself (`None` if it is imported under its own name)
+    module_aliases: BTreeSet<Option<String>>,
+    /// Members of the module along with their aliases
+    members: BTreeSet<(String, Option<String>)>,
+}
+
+/// `use` declaration that can be organized, spanning the given byte range
+struct UseDecl {
+    start: usize,
+    end: usize,
+    /// Fully qualified name of the module (e.g., `std::vector`)
+    module: String,
+    imports: ModuleImports,
+}
+
+/// Returns the source action organizing `use` declarations of the buffer, or `None` if they are
+/// already organized
+pub fn organize_imports_action(uri: &Url, buffer: &str) -> Option<CodeActionOrCommand> {
+    let edits = organized_imports(buffer);
+    if edits.is_empty() {
+        return None;
+    }
+    let edits = edits
+        .into_iter()
+        .map(|(start, end, new_text)| TextEdit {
+            range: Range::new(buffer_position(buffer, start), buffer_position(buffer, end)),
+            new_text,
+        })
+        .collect();
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: "Organize imports".to_string(),
+        kind: Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+/// Returns edits (as byte ranges to be replaced and their replacements) organizing `use`
+/// declarations of all modules and scripts of the buffer
+fn organized_imports(buffer: &str) -> Vec<(usize, usize, String)> {
+    let mut tokens = vec![];
+    let mut lexer = Lexer::new(buffer, FileHash::new(buffer));
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        tokens.push((lexer.peek(), lexer.content(), lexer.start_loc()));
+    }
+    if lexer.peek() != Tok::EOF {
+        return vec![];
+    }
+
+    let mut edits = vec![];
+    let mut depth = 0;
+    let mut in_declaration = false;
+    // depth of the body of the enclosing module (or script) along with the index of its first
+    // token
+    let mut body: Option<(usize, usize)> = None;
+    for (i, (tok, _, _)) in tokens.iter().enumerate() {
+        match tok {
+            Tok::Module | Tok::Script => in_declaration = true,
+            Tok::LBrace => {
+                depth += 1;
+                // spec modules nested in modules are not organized separately
+                if in_declaration && body.is_none() {
+                    body = Some((depth, i + 1));
+                }
+                in_declaration = false;
+            }
+            Tok::RBrace => {
+                if let Some((body_depth, body_start)) = body {
+                    if depth == body_depth {
+                        edits.extend(organized_body(buffer, &tokens[body_start..i]));
+                        body = None;
+                    }
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    edits
+}
+
+/// Returns edits organizing `use` declarations among the given tokens of a module (or script) body
+fn organized_body(buffer: &str, tokens: &[Token]) -> Vec<(usize, usize, String)> {
+    let mut decls = vec![];
+    let mut depth = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].0 {
+            Tok::LBrace => depth += 1,
+            Tok::RBrace => depth -= 1,
+            // declarations with attributes are not organized
+            Tok::Use if depth == 0 && (i == 0 || tokens[i - 1].0 != Tok::RBracket) => {
+                if let Some((decl, len)) = use_decl(&tokens[i..]) {
+                    decls.push(decl);
+                    i += len;
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    if decls.is_empty() {
+        return vec![];
+    }
+
+    // identifiers outside of the declarations refer to the imported aliases
+    let used = tokens
+        .iter()
+        .filter(|(tok, _, start)| {
+            *tok == Tok::Identifier && !decls.iter().any(|d| d.start <= *start && *start < d.end)
+        })
+        .map(|(_, content, _)| *content)
+        .collect::<BTreeSet<_>>();
+    let mut imports = BTreeMap::<&str, ModuleImports>::new();
+    for decl in &decls {
+        let module_imports = imports.entry(&decl.module).or_default();
+        module_imports.name = decl.imports.name.clone();
+        module_imports.module_aliases.extend(
+            decl.imports
+                .module_aliases
+                .iter()
+                .filter(|alias| used.contains(alias.as_deref().unwrap_or(&decl.imports.name)))
+                .cloned(),
+        );
+        module_imports.members.extend(
+            decl.imports
+                .members
+                .iter()
+                .filter(|(member, alias)| used.contains(alias.as_deref().unwrap_or(member)))
+                .cloned(),
+        );
+    }
+    let organized = imports
+        .iter()
+        .flat_map(|(module, module_imports)| use_lines(module, module_imports))
+        .collect::<Vec<_>>();
+    let original = decls
+        .iter()
+        .map(|d| {
+            buffer[d.start..d.end]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    if organized == original {
+        return vec![];
+    }
+
+    let line_start = |offset: usize| buffer[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let mut edits = decls
+        .iter()
+        .map(|d| {
+            let start = line_start(d.start);
+            let end = buffer[d.end..]
+                .find('\n')
+                .map_or(buffer.len(), |i| d.end + i + 1);
+            // declarations are removed along with their lines unless they share them with other
+            // code (or comments)
+            if buffer[start..d.start].trim().is_empty() && buffer[d.end..end].trim().is_empty() {
+                (start, end, String::new())
+            } else {
+                (d.start, d.end, String::new())
+            }
+        })
+        .collect::<Vec<_>>();
+    // organized declarations replace the first one (with its indentation)
+    let first = &mut edits[0];
+    let indent = &buffer[line_start(decls[0].start)..decls[0].start];
+    let indent = &indent[..indent.len() - indent.trim_start().len()];
+    first.2 = if first.0 < decls[0].start {
+        organized
+            .iter()
+            .map(|l| format!("{}{}\n", indent, l))
+            .collect()
+    } else {
+        organized.join(&format!("\n{}", indent))
+    };
+    edits
+}
+
+/// Parses a `use` declaration at the beginning of the given tokens, returning it along with the
+/// number of its tokens
+fn use_decl(tokens: &[Token]) -> Option<(UseDecl, usize)> {
+    let tok = |i: usize| tokens.get(i).map(|(tok, _, _)| *tok);
+    let content = |i: usize| tokens.get(i).map_or("", |(_, content, _)| *content);
+    let alias = |i: usize| -> (Option<String>, usize) {
+        if tok(i) == Some(Tok::As) && tok(i + 1) == Some(Tok::Identifier) {
+            (Some(content(i + 1).to_string()), i + 2)
+        } else {
+            (None, i)
+        }
+    };
+
+    if !matches!(tok(1), Some(Tok::Identifier) | Some(Tok::NumValue))
+        || tok(2) != Some(Tok::ColonColon)
+        || tok(3) != Some(Tok::Identifier)
+    {
+        return None;
+    }
+    let mut imports = ModuleImports {
+        name: content(3).to_string(),
+        ..Default::default()
+    };
+    let mut i = 4;
+    if tok(i) == Some(Tok::ColonColon) {
+        i += 1;
+        let grouped = tok(i) == Some(Tok::LBrace);
+        if grouped {
+            i += 1;
+        }
+        loop {
+            if tok(i) != Some(Tok::Identifier) {
+                return None;
+            }
+            let member = content(i);
+            let (member_alias, next) = alias(i + 1);
+            i = next;
+            if member == "Self" {
+                imports.module_aliases.insert(member_alias);
+            } else {
+                imports.members.insert((member.to_string(), member_alias));
+            }
+            if !grouped {
+                break;
+            }
+            if tok(i) == Some(Tok::Comma) {
+                i += 1;
+            }
+            if tok(i) == Some(Tok::RBrace) {
+                i += 1;
+                break;
+            }
+        }
+    } else {
+        let (module_alias, next) = alias(i);
+        imports.module_aliases.insert(module_alias);
+        i = next;
+    }
+    if tok(i) != Some(Tok::Semicolon) {
+        return None;
+    }
+    let (_, _, start) = tokens[0];
+    let (_, semicolon, semicolon_start) = tokens[i];
+    Some((
+        UseDecl {
+            start,
+            end: semicolon_start + semicolon.len(),
+            module: format!("{}::{}", content(1), content(3)),
+            imports,
+        },
+        i + 1,
+    ))
+}
+
+/// Returns organized `use` declarations importing from the given module: module aliases other than
+/// the module's name are imported by separate declarations, while all other aliases are imported by
+/// a single one
+fn use_lines(module: &str, imports: &ModuleImports) -> Vec<String> {
+    let mut items = vec![];
+    if imports.module_aliases.contains(&None) {
+        items.push("Self".to_string());
+    }
+    items.extend(imports.members.iter().map(|(member, alias)| match alias {
+        Some(alias) => format!("{} as {}", member, alias),
+        None => member.clone(),
+    }));
+    let mut lines = match items.as_slice() {
+        [] => vec![],
+        [item] if item == "Self" => vec![format!("use {};", module)],
+        [item] => vec![format!("use {}::{};", module, item)],
+        _ => vec![format!("use {}::{{{}}};", module, items.join(", "))],
+    };
+    lines.extend(
+        imports
+            .module_aliases
+            .iter()
+            .flatten()
+            .map(|alias| format!("use {} as {};", module, alias)),
+    );
+    lines
+}
+
+#[test]
+fn organized_imports_test() {
+    let organize = |buffer: &str| {
+        let mut organized = buffer.to_string();
+        for (start, end, new_text) in organized_imports(buffer).into_iter().rev() {
+            organized.replace_range(start..end, &new_text);
+        }
+        organized
+    };
+
+    let buffer = r#"module 0x1::M {
+    use std::vector;
+    use std::option::{some, none};
+    use 0x1::N as Other;
+    use std::option;
+    use std::signer;
+    use std::vector::{push_back, empty as new};
+
+    #[test_only]
+    use std::debug;
+
+    fun f(): vector<u64> {
+        let v = new();
+        push_back(&mut v, Other::g());
+        vector::reverse(&mut v);
+        option::destroy_none(none());
+        v
+    }
+}
+
+script {
+    use std::signer;
+    fun main(s: signer) { signer::address_of(&s); }
+}
+"#;
+    assert_eq!(
+        organize(buffer),
+        r#"module 0x1::M {
+    use 0x1::N as Other;
+    use std::option::{Self, none};
+    use std::vector::{Self, empty as new, push_back};
+
+    #[test_only]
+    use std::debug;
+
+    fun f(): vector<u64> {
+        let v = new();
+        push_back(&mut v, Other::g());
+        vector::reverse(&mut v);
+        option::destroy_none(none());
+        v
+    }
+}
+
+script {
+    use std::signer;
+    fun main(s: signer) { signer::address_of(&s); }
+}
+"#
+    );
+
+    // already organized
+    let buffer = "module 0x1::M {\n    use std::vector::{Self, empty};\n    fun f() { vector::destroy_empty(empty<u64>()); }\n}\n";
+    assert!(organized_imports(buffer).is_empty());
+}
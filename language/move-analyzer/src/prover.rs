@@ -0,0 +1,295 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Running the Move Prover on behalf of the client.
+//!
+//! The prover is run on a separate thread as verification may take a long time. Once it finishes,
+//! problems it reported are published as diagnostics of the files of the package (until the
+//! package is compiled again), with the locations related to a failing specification condition
+//! (such as where an unexpected abort happened) attached to the diagnostic of that condition. The
+//! request to verify a module is then answered, and the user is additionally notified about the
+//! outcome.
+
+use crate::{context::Context, diagnostics::severity, vfs::buffer_position};
+use anyhow::{anyhow, Result};
+use codespan_reporting::{diagnostic::LabelStyle, term::termcolor::NoColor};
+use lsp_server::{Message, Notification, Request, Response};
+use lsp_types::{
+    notification::Notification as _, Diagnostic, DiagnosticRelatedInformation, Location,
+    MessageType, PublishDiagnosticsParams, Range, ShowMessageParams,
+};
+use move_model::model::VerificationScope;
+use move_package::ModelConfig;
+use move_prover::{cli::Options, run_move_prover_with_model};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, iter,
+    path::PathBuf,
+    thread,
+};
+use tempfile::tempdir;
+use tracing::{debug, error, info};
+use url::Url;
+
+/// Command verifying a single module
+pub const PROVE_COMMAND: &str = "move-analyzer.prove";
+
+/// Source of diagnostics reported by the prover
+const PROVER_SOURCE: &str = "move-prover";
+
+/// Arguments of the command verifying a single module
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProveArgs {
+    /// Root directory of the package containing the module
+    pub package_path: PathBuf,
+    /// Name of the module to be verified
+    pub module_name: String,
+}
+
+/// Outcome of verifying a single module
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProveResult {
+    pub verified: bool,
+    /// Problems reported by the prover (or compiler diagnostics if the package does not compile)
+    pub output: String,
+}
+
+/// Builds the model of the package rooted at the given path and verifies the given module,
+/// returning the outcome along with diagnostics of each file of the package (files without any
+/// problems are mapped to no diagnostics).
+pub fn prove_module(args: &ProveArgs) -> Result<(ProveResult, BTreeMap<PathBuf, Vec<Diagnostic>>)> {
+    let prover_toml = args.package_path.join("Prover.toml");
+    let mut options = if prover_toml.exists() {
+        Options::create_from_toml_file(&prover_toml.to_string_lossy())?
+    } else {
+        Options::default()
+    };
+    options.prover.verify_scope = VerificationScope::OnlyModule(args.module_name.clone());
+    // the Boogie program is written to a temporary directory so that it does not end up in the
+    // package
+    let output_dir = tempdir()?;
+    options.output_path = output_dir
+        .path()
+        .join("output.bpl")
+        .to_string_lossy()
+        .to_string();
+    let min_severity = options.prover.report_severity;
+
+    let install_dir = tempdir()?;
+    let build_config = move_package::BuildConfig {
+        dev_mode: true,
+        install_dir: Some(install_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let model = build_config.move_model_for_package(
+        &args.package_path,
+        ModelConfig {
+            all_files_as_targets: false,
+            target_filter: None,
+        },
+    )?;
+    if !model.has_errors()
+        && !model
+            .get_target_modules()
+            .iter()
+            .any(|module| module.matches_name(&args.module_name))
+    {
+        return Err(anyhow!("module {} not found", args.module_name));
+    }
+
+    let mut output = NoColor::new(vec![]);
+    if let Err(err) = run_move_prover_with_model(&model, &mut output, options, None) {
+        // errors not reported as diagnostics (e.g., when the prover's tools cannot be found)
+        // prevent verification altogether
+        if !model.has_errors() {
+            return Err(err);
+        }
+    }
+
+    let mut diagnostics = BTreeMap::new();
+    for module in model.get_target_modules() {
+        diagnostics.insert(PathBuf::from(module.get_source_path()), vec![]);
+    }
+    let mut sources = HashMap::new();
+    for diag in model.get_diags(min_severity) {
+        // labels pointing to locations internal to the prover are dropped
+        let labels = diag
+            .labels
+            .iter()
+            .filter_map(|label| {
+                let path = PathBuf::from(model.get_file(label.file_id));
+                let source = sources
+                    .entry(label.file_id)
+                    .or_insert_with(|| fs::read_to_string(&path).ok())
+                    .as_ref()?;
+                let range = Range::new(
+                    buffer_position(source, label.range.start.min(source.len())),
+                    buffer_position(source, label.range.end.min(source.len())),
+                );
+                Some((label, path, range))
+            })
+            .collect::<Vec<_>>();
+        let (_, path, range) = match labels
+            .iter()
+            .find(|(label, _, _)| label.style == LabelStyle::Primary)
+        {
+            Some(primary) => primary.clone(),
+            None => continue,
+        };
+        let related = labels
+            .into_iter()
+            .filter(|(label, _, _)| label.style == LabelStyle::Secondary)
+            .filter_map(|(label, path, range)| {
+                Some(DiagnosticRelatedInformation {
+                    location: Location::new(Url::from_file_path(path).ok()?, range),
+                    message: label.message.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        // notes contain the execution trace leading to a failure
+        let message = iter::once(diag.message.clone())
+            .chain(diag.notes.iter().cloned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        diagnostics
+            .entry(path)
+            .or_insert_with(Vec::new)
+            .push(Diagnostic::new(
+                range,
+                Some(severity(diag.severity)),
+                None,
+                Some(PROVER_SOURCE.to_string()),
+                message,
+                if related.is_empty() {
+                    None
+                } else {
+                    Some(related)
+                },
+                None,
+            ));
+    }
+
+    let result = ProveResult {
+        verified: !model.has_errors(),
+        output: String::from_utf8_lossy(&output.into_inner()).to_string(),
+    };
+    Ok((result, diagnostics))
+}
+
+/// Handles the command verifying a single module, answering the request once verification
+/// finishes.
+pub fn on_prove_command(context: &Context, request: &Request, arguments: Vec<serde_json::Value>) {
+    let args = match arguments
+        .into_iter()
+        .next()
+        .and_then(|arg| serde_json::from_value::<ProveArgs>(arg).ok())
+    {
+        Some(args) => args,
+        None => {
+            let response = Response::new_err(
+                request.id.clone(),
+                lsp_server::ErrorCode::InvalidParams as i32,
+                format!("invalid arguments of command '{}'", PROVE_COMMAND),
+            );
+            if let Err(err) = context.connection.sender.send(Message::Response(response)) {
+                error!("could not send execute command response: {:?}", err);
+            }
+            return;
+        }
+    };
+
+    let sender = context.connection.sender.clone();
+    let id = request.id.clone();
+    thread::spawn(move || {
+        let module = args.module_name.clone();
+        info!("verifying module {}", module);
+        let mut messages = vec![];
+        let message = match prove_module(&args) {
+            Ok((result, diagnostics)) => {
+                let failures = diagnostics.values().map(Vec::len).sum::<usize>();
+                for (path, diagnostics) in diagnostics {
+                    let url = match Url::from_file_path(&path) {
+                        Ok(url) => url,
+                        Err(()) => continue,
+                    };
+                    let params = PublishDiagnosticsParams::new(url, diagnostics, None);
+                    messages.push(Message::Notification(Notification::new(
+                        lsp_types::notification::PublishDiagnostics::METHOD.to_string(),
+                        params,
+                    )));
+                }
+                let message = if result.verified {
+                    ShowMessageParams {
+                        typ: MessageType::Info,
+                        message: format!("Module {} verified", module),
+                    }
+                } else {
+                    ShowMessageParams {
+                        typ: MessageType::Error,
+                        message: format!(
+                            "Verification of module {} failed with {} error(s)",
+                            module, failures
+                        ),
+                    }
+                };
+                messages.push(Message::Response(Response::new_ok(id, result)));
+                message
+            }
+            Err(err) => {
+                messages.push(Message::Response(Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::InternalError as i32,
+                    format!("could not verify module: {}", err),
+                )));
+                ShowMessageParams {
+                    typ: MessageType::Error,
+                    message: format!("Could not verify module {}: {}", module, err),
+                }
+            }
+        };
+        messages.push(Message::Notification(Notification::new(
+            lsp_types::notification::ShowMessage::METHOD.to_string(),
+            message,
+        )));
+        debug!("about to send execute command response");
+        for message in messages {
+            if let Err(err) = sender.send(message) {
+                error!("could not send verification result: {:?}", err);
+            }
+        }
+    });
+}
+
+#[test]
+/// Tests if verification failures are reported as diagnostics (the prover's tools are not
+/// available everywhere, so the test only runs if they are).
+fn prove_module_test() {
+    if std::env::var("BOOGIE_EXE").unwrap_or_default().is_empty() {
+        return;
+    }
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let prove = |module_name: &str| {
+        prove_module(&ProveArgs {
+            package_path: path.clone(),
+            module_name: module_name.to_string(),
+        })
+    };
+
+    // `total_steps` is never updated by `increment`
+    let (result, diagnostics) = prove("M7").unwrap();
+    assert!(!result.verified);
+    let (_, m7_diagnostics) = diagnostics
+        .iter()
+        .find(|(path, _)| path.ends_with("M7.move"))
+        .unwrap();
+    assert!(m7_diagnostics
+        .iter()
+        .any(|d| d.range.start.line == 13 && d.source.as_deref() == Some(PROVER_SOURCE)));
+
+    assert!(prove("NoSuchModule").is_err());
+}
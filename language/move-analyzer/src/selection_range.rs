@@ -0,0 +1,507 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Selection ranges used to expand (or shrink) the selection around the cursor, growing from an
+//! identifier to the enclosing expressions, statement, function (or other module member), and
+//! module.
+//!
+//! Similarly to folding ranges, selection ranges are computed from the parsed AST of the current
+//! contents of a buffer so that they are up-to-date while the code is being edited. If the buffer
+//! cannot be parsed, no selection ranges are returned, and the client falls back to its own
+//! (bracket-based) heuristics.
+
+use crate::{
+    context::Context,
+    vfs::{buffer_position, byte_offset},
+};
+use lsp_server::Request;
+use lsp_types::{Position, Range, SelectionRange, SelectionRangeParams};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    parser::{ast as P, syntax::parse_file_string},
+    shared::{CompilationEnv, Flags, Identifier},
+};
+use move_ir_types::location::Loc;
+use tracing::{debug, error};
+
+/// Collects locations of the AST nodes enclosing a single offset, from the outermost to the
+/// innermost one
+struct SelectionCollector {
+    offset: u32,
+    locs: Vec<Loc>,
+}
+
+impl SelectionCollector {
+    /// Records the location of a node if it encloses the offset (and is nested in the location
+    /// recorded last), and returns true if the node's children should be visited
+    fn enter(&mut self, loc: &Loc) -> bool {
+        if loc.start() > self.offset || loc.end() < self.offset {
+            return false;
+        }
+        match self.locs.last() {
+            Some(last) if last == loc => true,
+            Some(last) if loc.start() < last.start() || loc.end() > last.end() => false,
+            _ => {
+                self.locs.push(*loc);
+                true
+            }
+        }
+    }
+
+    fn definition(&mut self, def: &P::Definition) {
+        match def {
+            P::Definition::Module(mod_def) => self.module(mod_def),
+            P::Definition::Address(addr_def) => {
+                for mod_def in &addr_def.modules {
+                    self.module(mod_def);
+                }
+            }
+            P::Definition::Script(script) => {
+                if !self.enter(&script.loc) {
+                    return;
+                }
+                for constant in &script.constants {
+                    self.constant(constant);
+                }
+                self.function(&script.function);
+                for spec in &script.specs {
+                    self.spec_block(spec);
+                }
+            }
+        }
+    }
+
+    fn module(&mut self, mod_def: &P::ModuleDefinition) {
+        if !self.enter(&mod_def.loc) {
+            return;
+        }
+        self.enter(&mod_def.name.loc());
+        for member in &mod_def.members {
+            match member {
+                P::ModuleMember::Function(fun) => self.function(fun),
+                P::ModuleMember::Struct(struct_def) => self.struct_def(struct_def),
+                P::ModuleMember::Constant(constant) => self.constant(constant),
+                P::ModuleMember::Spec(spec) => self.spec_block(spec),
+                P::ModuleMember::Friend(friend) => {
+                    if self.enter(&friend.loc) {
+                        self.name_access_chain(&friend.friend);
+                    }
+                }
+                P::ModuleMember::Use(_) => (),
+            }
+        }
+    }
+
+    fn function(&mut self, fun: &P::Function) {
+        if !self.enter(&fun.loc) {
+            return;
+        }
+        self.enter(&fun.name.loc());
+        self.signature(&fun.signature);
+        for acquire in &fun.acquires {
+            self.name_access_chain(acquire);
+        }
+        if self.enter(&fun.body.loc) {
+            if let P::FunctionBody_::Defined(seq) = &fun.body.value {
+                self.sequence(seq);
+            }
+        }
+    }
+
+    fn signature(&mut self, signature: &P::FunctionSignature) {
+        for (name, _) in &signature.type_parameters {
+            self.enter(&name.loc);
+        }
+        for (var, t) in &signature.parameters {
+            self.enter(&var.loc());
+            self.type_(t);
+        }
+        self.type_(&signature.return_type);
+    }
+
+    fn struct_def(&mut self, struct_def: &P::StructDefinition) {
+        if !self.enter(&struct_def.loc) {
+            return;
+        }
+        self.enter(&struct_def.name.loc());
+        if let P::StructFields::Defined(fields) = &struct_def.fields {
+            for (field, t) in fields {
+                self.enter(&field.loc());
+                self.type_(t);
+            }
+        }
+    }
+
+    fn constant(&mut self, constant: &P::Constant) {
+        if !self.enter(&constant.loc) {
+            return;
+        }
+        self.enter(&constant.name.loc());
+        self.type_(&constant.signature);
+        self.exp(&constant.value);
+    }
+
+    fn spec_block(&mut self, spec: &P::SpecBlock) {
+        if !self.enter(&spec.loc) {
+            return;
+        }
+        match &spec.value.target.value {
+            P::SpecBlockTarget_::Member(name, _) | P::SpecBlockTarget_::Schema(name, _) => {
+                self.enter(&name.loc);
+            }
+            P::SpecBlockTarget_::Code | P::SpecBlockTarget_::Module => (),
+        }
+        for member in &spec.value.members {
+            if !self.enter(&member.loc) {
+                continue;
+            }
+            match &member.value {
+                P::SpecBlockMember_::Condition {
+                    exp,
+                    additional_exps,
+                    ..
+                } => {
+                    self.exp(exp);
+                    for exp in additional_exps {
+                        self.exp(exp);
+                    }
+                }
+                P::SpecBlockMember_::Function {
+                    name,
+                    signature,
+                    body,
+                    ..
+                } => {
+                    self.enter(&name.loc());
+                    self.signature(signature);
+                    if self.enter(&body.loc) {
+                        if let P::FunctionBody_::Defined(seq) = &body.value {
+                            self.sequence(seq);
+                        }
+                    }
+                }
+                P::SpecBlockMember_::Variable {
+                    name, type_, init, ..
+                } => {
+                    self.enter(&name.loc);
+                    self.type_(type_);
+                    if let Some(init) = init {
+                        self.exp(init);
+                    }
+                }
+                P::SpecBlockMember_::Let { name, def, .. } => {
+                    self.enter(&name.loc);
+                    self.exp(def);
+                }
+                P::SpecBlockMember_::Update { lhs, rhs } => {
+                    self.exp(lhs);
+                    self.exp(rhs);
+                }
+                P::SpecBlockMember_::Include { exp, .. }
+                | P::SpecBlockMember_::Apply { exp, .. } => self.exp(exp),
+                P::SpecBlockMember_::Pragma { .. } => (),
+            }
+        }
+    }
+
+    fn sequence(&mut self, (_, items, _, final_exp): &P::Sequence) {
+        for item in items {
+            if !self.enter(&item.loc) {
+                continue;
+            }
+            match &item.value {
+                P::SequenceItem_::Seq(exp) => self.exp(exp),
+                P::SequenceItem_::Declare(binds, t) => {
+                    self.bind_list(binds);
+                    if let Some(t) = t {
+                        self.type_(t);
+                    }
+                }
+                P::SequenceItem_::Bind(binds, t, exp) => {
+                    self.bind_list(binds);
+                    if let Some(t) = t {
+                        self.type_(t);
+                    }
+                    self.exp(exp);
+                }
+            }
+        }
+        if let Some(exp) = final_exp.as_ref() {
+            self.exp(exp);
+        }
+    }
+
+    fn bind_list(&mut self, binds: &P::BindList) {
+        if !self.enter(&binds.loc) {
+            return;
+        }
+        for bind in &binds.value {
+            self.bind(bind);
+        }
+    }
+
+    fn bind(&mut self, bind: &P::Bind) {
+        if !self.enter(&bind.loc) {
+            return;
+        }
+        match &bind.value {
+            P::Bind_::Var(var) => {
+                self.enter(&var.loc());
+            }
+            P::Bind_::Unpack(chain, tys, fields) => {
+                self.name_access_chain(chain);
+                self.types(tys.iter().flatten());
+                for (field, bind) in fields {
+                    self.enter(&field.loc());
+                    self.bind(bind);
+                }
+            }
+        }
+    }
+
+    fn exp(&mut self, exp: &P::Exp) {
+        use P::Exp_ as E;
+        if !self.enter(&exp.loc) {
+            return;
+        }
+        match &exp.value {
+            E::Move(var) | E::Copy(var) => {
+                self.enter(&var.loc());
+            }
+            E::Name(chain, tys) => {
+                self.name_access_chain(chain);
+                self.types(tys.iter().flatten());
+            }
+            E::Call(chain, _, tys, args) => {
+                self.name_access_chain(chain);
+                self.types(tys.iter().flatten());
+                if self.enter(&args.loc) {
+                    self.exps(&args.value);
+                }
+            }
+            E::Pack(chain, tys, fields) => {
+                self.name_access_chain(chain);
+                self.types(tys.iter().flatten());
+                for (field, exp) in fields {
+                    self.enter(&field.loc());
+                    self.exp(exp);
+                }
+            }
+            E::Vector(_, tys, args) => {
+                self.types(tys.iter().flatten());
+                if self.enter(&args.loc) {
+                    self.exps(&args.value);
+                }
+            }
+            E::IfElse(cond, t, f) => {
+                self.exp(cond);
+                self.exp(t);
+                if let Some(f) = f {
+                    self.exp(f);
+                }
+            }
+            E::While(e1, e2) | E::Assign(e1, e2) | E::BinopExp(e1, _, e2) | E::Index(e1, e2) => {
+                self.exp(e1);
+                self.exp(e2);
+            }
+            E::Loop(e) | E::Abort(e) | E::Dereference(e) | E::UnaryExp(_, e) | E::Borrow(_, e) => {
+                self.exp(e)
+            }
+            E::Return(e) => {
+                if let Some(e) = e {
+                    self.exp(e);
+                }
+            }
+            E::Block(seq) => self.sequence(seq),
+            E::Lambda(binds, body) => {
+                self.bind_list(binds);
+                self.exp(body);
+            }
+            E::Quant(_, ranges, triggers, cond, body) => {
+                if self.enter(&ranges.loc) {
+                    for range in &ranges.value {
+                        if self.enter(&range.loc) {
+                            let (bind, exp) = &range.value;
+                            self.bind(bind);
+                            self.exp(exp);
+                        }
+                    }
+                }
+                for trigger in triggers {
+                    self.exps(trigger);
+                }
+                if let Some(cond) = cond {
+                    self.exp(cond);
+                }
+                self.exp(body);
+            }
+            E::ExpList(exps) => self.exps(exps),
+            E::Dot(e, name) => {
+                self.exp(e);
+                self.enter(&name.loc);
+            }
+            E::Cast(e, t) | E::Annotate(e, t) => {
+                self.exp(e);
+                self.type_(t);
+            }
+            E::Spec(spec) => self.spec_block(spec),
+            E::Value(_) | E::Unit | E::Break | E::Continue | E::UnresolvedError => (),
+        }
+    }
+
+    fn exps(&mut self, exps: &[P::Exp]) {
+        for exp in exps {
+            self.exp(exp);
+        }
+    }
+
+    fn type_(&mut self, t: &P::Type) {
+        if !self.enter(&t.loc) {
+            return;
+        }
+        match &t.value {
+            P::Type_::Apply(chain, tys) => {
+                self.name_access_chain(chain);
+                self.types(tys.iter());
+            }
+            P::Type_::Ref(_, t) => self.type_(t),
+            P::Type_::Fun(tys, t) => {
+                self.types(tys.iter());
+                self.type_(t);
+            }
+            P::Type_::Multiple(tys) => self.types(tys.iter()),
+            P::Type_::Unit => (),
+        }
+    }
+
+    fn types<'a>(&mut self, tys: impl Iterator<Item = &'a P::Type>) {
+        for t in tys {
+            self.type_(t);
+        }
+    }
+
+    fn name_access_chain(&mut self, chain: &P::NameAccessChain) {
+        if !self.enter(&chain.loc) {
+            return;
+        }
+        match &chain.value {
+            P::NameAccessChain_::One(_) => (),
+            P::NameAccessChain_::Two(_, name) => {
+                self.enter(&name.loc);
+            }
+            P::NameAccessChain_::Three(access, name) => {
+                self.enter(&access.loc);
+                self.enter(&name.loc);
+            }
+        }
+    }
+}
+
+/// Returns selection ranges around the given positions in the given buffer, or None if it cannot
+/// be parsed
+pub fn selection_ranges(buffer: &str, positions: &[Position]) -> Option<Vec<SelectionRange>> {
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, FileHash::new(buffer), buffer).ok()?;
+    let ranges = positions
+        .iter()
+        .map(|position| {
+            let mut collector = SelectionCollector {
+                offset: byte_offset(buffer, position) as u32,
+                locs: vec![],
+            };
+            for def in &defs {
+                collector.definition(def);
+            }
+            // a range must be returned for each position even if it is not enclosed by any node
+            let empty = SelectionRange {
+                range: Range::new(*position, *position),
+                parent: None,
+            };
+            collector
+                .locs
+                .iter()
+                .fold(None, |parent, loc| {
+                    Some(SelectionRange {
+                        range: Range::new(
+                            buffer_position(buffer, loc.start() as usize),
+                            buffer_position(buffer, loc.end() as usize),
+                        ),
+                        parent: parent.map(Box::new),
+                    })
+                })
+                .unwrap_or(empty)
+        })
+        .collect();
+    Some(ranges)
+}
+
+/// Handles selection range request of the language server
+pub fn on_selection_range_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<SelectionRangeParams>(request.params.clone())
+        .expect("could not deserialize selection range request");
+
+    let ranges = context
+        .files
+        .get(parameters.text_document.uri.path())
+        .and_then(|buffer| selection_ranges(buffer, &parameters.positions));
+
+    debug!("about to send selection range response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), ranges);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send selection range response: {:?}", err);
+    }
+}
+
+#[test]
+fn selection_ranges_test() {
+    let buffer = r#"module 0x1::M {
+    fun f(a: u64, b: u64): u64 {
+        let x = a + g(b);
+        x
+    }
+
+    fun g(b: u64): u64 { b }
+}
+"#;
+    let ranges = |line, character| {
+        let mut ranges = vec![];
+        let mut range = selection_ranges(buffer, &[Position::new(line, character)])
+            .unwrap()
+            .pop();
+        while let Some(r) = range {
+            let (start, end) = (r.range.start, r.range.end);
+            ranges.push(((start.line, start.character), (end.line, end.character)));
+            range = r.parent.map(|p| *p);
+        }
+        ranges
+    };
+
+    // argument of a call
+    assert_eq!(
+        ranges(2, 22),
+        vec![
+            ((2, 22), (2, 23)), // b
+            ((2, 21), (2, 24)), // (b)
+            ((2, 20), (2, 24)), // g(b)
+            ((2, 16), (2, 24)), // a + g(b)
+            ((2, 8), (2, 24)),  // let x = a + g(b)
+            ((1, 31), (4, 5)),  // function body
+            ((1, 4), (4, 5)),   // function
+            ((0, 0), (7, 1)),   // module
+        ]
+    );
+    // function name
+    assert_eq!(
+        ranges(6, 9),
+        vec![((6, 8), (6, 9)), ((6, 4), (6, 28)), ((0, 0), (7, 1))]
+    );
+    // outside of any module
+    assert_eq!(ranges(8, 0), vec![((8, 0), (8, 0))]);
+
+    // nothing is returned for unparsable buffers
+    assert!(selection_ranges("module M {", &[Position::new(0, 0)]).is_none());
+}
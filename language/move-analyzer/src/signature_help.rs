@@ -0,0 +1,180 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signature help shows the signature of a function whose arguments are being typed, highlighting
+//! the parameter corresponding to the argument under the cursor.
+//!
+//! Since the code being edited usually does not compile, the call surrounding the cursor is found
+//! by scanning the current buffer contents backwards rather than by looking it up in the compiled
+//! program. Resolving the called function to its signature is left to the symbolicator.
+
+use crate::vfs::{buffer_position, byte_offset};
+use lsp_types::{ParameterInformation, ParameterLabel, Position, SignatureInformation};
+
+/// Function call surrounding the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallContext {
+    /// Name of the function, possibly qualified (e.g., `["vector", "push_back"]` for
+    /// `vector::push_back`)
+    pub path: Vec<String>,
+    /// Position where the (unqualified) function name starts
+    pub name_start: Position,
+    /// Index of the argument under the cursor
+    pub active_parameter: u32,
+}
+
+/// Finds the innermost function call whose argument list contains the given position. This is a
+/// purely textual approximation that ignores comments and string literals.
+pub fn call_context(buffer: &str, position: &Position) -> Option<CallContext> {
+    let offset = byte_offset(buffer, position);
+    let bytes = buffer.as_bytes();
+
+    // find the opening parenthesis of the argument list, counting arguments preceding the cursor
+    let mut depth = 0;
+    let mut active_parameter = 0;
+    let mut idx = offset;
+    let open_paren = loop {
+        if idx == 0 {
+            return None;
+        }
+        idx -= 1;
+        match bytes[idx] {
+            b')' | b']' | b'}' => depth += 1,
+            b'(' if depth == 0 => break idx,
+            b'(' | b'[' | b'{' if depth > 0 => depth -= 1,
+            b'[' | b'{' | b';' => return None,
+            b',' if depth == 0 => active_parameter += 1,
+            _ => (),
+        }
+    };
+
+    // skip explicit type arguments, if any
+    let mut end = skip_whitespace_back(bytes, open_paren);
+    if end > 0 && bytes[end - 1] == b'>' {
+        let mut depth = 0;
+        loop {
+            if end == 0 {
+                return None;
+            }
+            end -= 1;
+            match bytes[end] {
+                b'>' => depth += 1,
+                b'<' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                b'(' | b')' | b'{' | b'}' | b';' => return None,
+                _ => (),
+            }
+        }
+        end = skip_whitespace_back(bytes, end);
+    }
+
+    // collect (possibly qualified) function name
+    let mut path = vec![];
+    let mut name_start = None;
+    loop {
+        let start = ident_start(bytes, end);
+        if start == end {
+            return None;
+        }
+        name_start.get_or_insert(start);
+        path.push(buffer[start..end].to_string());
+        let sep = skip_whitespace_back(bytes, start);
+        if sep < 2 || &bytes[sep - 2..sep] != b"::" {
+            break;
+        }
+        end = skip_whitespace_back(bytes, sep - 2);
+    }
+    path.reverse();
+
+    // a parenthesis preceded by a keyword (e.g., in `if (...)`) does not start a call
+    if path.len() == 1 && matches!(path[0].as_str(), "if" | "while" | "return" | "abort") {
+        return None;
+    }
+
+    Some(CallContext {
+        path,
+        name_start: buffer_position(buffer, name_start.unwrap()),
+        active_parameter,
+    })
+}
+
+pub(crate) fn skip_whitespace_back(bytes: &[u8], mut end: usize) -> usize {
+    while end > 0 && bytes[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+    end
+}
+
+pub(crate) fn ident_start(bytes: &[u8], mut end: usize) -> usize {
+    while end > 0 && (bytes[end - 1].is_ascii_alphanumeric() || bytes[end - 1] == b'_') {
+        end -= 1;
+    }
+    end
+}
+
+/// Builds signature information with a label of the form `fun M::f<T>(p1: t1, p2: t2): ret`,
+/// where each parameter is identified by its offsets within the label
+pub fn signature_information(
+    fun_name: &str,
+    type_args: &[String],
+    params: &[(String, String)],
+    ret: Option<String>,
+    active_parameter: u32,
+) -> SignatureInformation {
+    let mut label = format!("fun {}", fun_name);
+    if !type_args.is_empty() {
+        label.push_str(&format!("<{}>", type_args.join(", ")));
+    }
+    label.push('(');
+    let mut parameters = vec![];
+    for (i, (name, ty)) in params.iter().enumerate() {
+        if i > 0 {
+            label.push_str(", ");
+        }
+        let start = label.encode_utf16().count() as u32;
+        label.push_str(&format!("{}: {}", name, ty));
+        let end = label.encode_utf16().count() as u32;
+        parameters.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([start, end]),
+            documentation: None,
+        });
+    }
+    label.push(')');
+    if let Some(ret) = ret {
+        label.push_str(&format!(": {}", ret));
+    }
+    SignatureInformation {
+        label,
+        documentation: None,
+        parameters: Some(parameters),
+        active_parameter: Some(active_parameter),
+    }
+}
+
+#[test]
+fn call_context_test() {
+    let ctx = |buffer: &str, line, character| call_context(buffer, &Position { line, character });
+
+    let buffer = "fun f() {\n    vector::push_back<u64>(&mut v, g(1, 2), \n}\n";
+    assert_eq!(
+        ctx(buffer, 1, 27),
+        Some(CallContext {
+            path: vec!["vector".to_string(), "push_back".to_string()],
+            name_start: Position::new(1, 12),
+            active_parameter: 0,
+        })
+    );
+    // nested calls are skipped
+    assert_eq!(ctx(buffer, 1, 44).unwrap().active_parameter, 2);
+    // innermost call is found
+    let inner = ctx(buffer, 1, 39).unwrap();
+    assert_eq!(inner.path, vec!["g".to_string()]);
+    assert_eq!(inner.active_parameter, 1);
+    // not in a call
+    assert_eq!(ctx(buffer, 1, 4), None);
+    assert_eq!(ctx("if (x", 0, 5), None);
+}
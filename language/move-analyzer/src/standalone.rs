@@ -0,0 +1,99 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for Move source files that do not belong to any package.
+//!
+//! Such a file is symbolicated as the only source file of a package created for it in a temporary
+//! directory, with the standard library as the package's only dependency. The package links to
+//! the file rather than containing its copy so that locations within the package (whose paths are
+//! canonicalized during symbolication) refer to the original file. Apart from `std`, named
+//! addresses cannot be assigned values, so modules of such a file need to be declared under
+//! numerical addresses.
+
+use crate::code_lens::package_path;
+use anyhow::{anyhow, Result};
+use move_package::source_package::layout::SourcePackageLayout;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tempfile::{tempdir, TempDir};
+
+/// Name of the package created for a standalone file
+const STANDALONE_PACKAGE_NAME: &str = "Standalone";
+
+/// Returns true if the given file is a Move source file that does not belong to any package
+pub fn is_standalone(fpath: &Path) -> bool {
+    fpath.extension().map_or(false, |ext| ext == "move") && package_path(fpath).is_none()
+}
+
+/// Creates a package (in a temporary directory, removed once the returned value is dropped) whose
+/// only source file is the given file.
+pub fn standalone_package(fpath: &Path) -> Result<TempDir> {
+    let fname = fpath
+        .file_name()
+        .ok_or_else(|| anyhow!("{:?} is not a file", fpath))?;
+    let dir = tempdir()?;
+    let sources_dir = dir.path().join(SourcePackageLayout::Sources.path());
+    fs::create_dir(&sources_dir)?;
+    link_file(&fs::canonicalize(fpath)?, &sources_dir.join(fname))?;
+    fs::write(
+        dir.path().join(SourcePackageLayout::Manifest.path()),
+        manifest(&move_stdlib::path_in_crate("")),
+    )?;
+    Ok(dir)
+}
+
+/// Returns the manifest of a package created for a standalone file, depending on the standard
+/// library at the given path.
+fn manifest(stdlib_path: &Path) -> String {
+    let mut manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.0.0\"\n\n[addresses]\nstd = \"0x1\"\n",
+        STANDALONE_PACKAGE_NAME
+    );
+    // sources of the standard library are only available where the analyzer was built
+    if stdlib_path
+        .join(SourcePackageLayout::Manifest.path())
+        .is_file()
+    {
+        manifest.push_str(&format!(
+            "\n[dependencies]\nMoveStdlib = {{ local = {:?} }}\n",
+            stdlib_path.to_string_lossy()
+        ));
+    }
+    manifest
+}
+
+#[cfg(unix)]
+fn link_file(original: &Path, link: &PathBuf) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn link_file(original: &Path, link: &PathBuf) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
+#[test]
+/// Tests if a file outside of any package is symbolicated against the standard library.
+fn standalone_package_test() {
+    use crate::symbols::Symbolicator;
+
+    let dir = tempdir().unwrap();
+    let fpath = dir.path().join("M.move");
+    fs::write(
+        &fpath,
+        "module 0x42::M {\n    use std::vector;\n\n    public fun f(): vector<u64> {\n        vector::empty()\n    }\n}\n",
+    )
+    .unwrap();
+    assert!(is_standalone(&fpath));
+    assert!(!is_standalone(&dir.path().join("Move.toml")));
+
+    let pkg_dir = standalone_package(&fpath).unwrap();
+    let (symbols_opt, diagnostics) = Symbolicator::get_symbols(pkg_dir.path()).unwrap();
+    assert!(diagnostics.values().all(|diags| diags.is_empty()));
+    // symbols refer to the original file
+    let symbols = symbols_opt.unwrap();
+    let outline = symbols.file_outline(&fs::canonicalize(&fpath).unwrap());
+    assert!(outline.iter().any(|s| s.name == "0x42::M"));
+}
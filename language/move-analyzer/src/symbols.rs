@@ -45,53 +45,137 @@
 //! processes function bodies and struct definitions to match uses to definitions. For local
 //! definitions, the symbolicator builds a scope stack, entering encountered definitions and
 //! matching uses to a definition in the innermost scope.
+//!
+//! When symbolicating on behalf of the language server, packages are compiled incrementally:
+//! results of the most recent compilation of the whole package serve as a pre-compiled library
+//! against which only files affected by changes (changed files and files using definitions from
+//! them) are compiled again, while ASTs of the remaining modules are taken from this library.
 
 use crate::{
+    abort_code::{abort_code, abort_code_at},
+    cache,
+    code_action::{
+        ability_actions, fill_fields_action, import_actions, struct_literal, unused_action,
+        ImportIndex,
+    },
+    completion::{MemberKind, ModuleIndex, ModuleMember},
     context::Context,
-    diagnostics::{lsp_diagnostics, lsp_empty_diagnostics},
-    utils::get_loc,
+    diagnostics::{lsp_diagnostics, lsp_empty_diagnostics, CodespanDiagnostic},
+    extract_constant::extract_constant_action,
+    extract_function::{extract_function_action, LocalUse},
+    generate_function::generate_function_action,
+    inlay_hints::{InlayHint, InlayHintCollector, InlayHintParams},
+    organize_imports::organize_imports_action,
+    signature_help::{call_context, ident_start, signature_information, CallContext},
+    standalone::standalone_package,
+    type_at::{innermost_exp_type, ExpType, ExpTypeCollector, TypeAtParams},
+    utils::{get_loc, get_range},
+    vfs::{buffer_position, byte_offset},
 };
 use anyhow::Result;
-use codespan_reporting::files::SimpleFiles;
+use codespan_reporting::{diagnostic::Severity, files::SimpleFiles};
 use crossbeam::channel::Sender;
 use im::ordmap::OrdMap;
 use lsp_server::{Request, RequestId};
 use lsp_types::{
-    request::GotoTypeDefinitionParams, Diagnostic, GotoDefinitionParams, Hover, HoverContents,
-    HoverParams, LanguageString, Location, MarkedString, Position, Range, ReferenceParams,
+    request::GotoTypeDefinitionParams, CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams,
+    CallHierarchyItem, CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams,
+    CallHierarchyPrepareParams, CodeActionKind, CodeActionParams, Diagnostic, DocumentHighlight,
+    DocumentHighlightKind, DocumentHighlightParams, DocumentSymbol, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, Hover, HoverContents, HoverParams,
+    LanguageString, Location, MarkedString, MarkupContent, MarkupKind, Position,
+    PrepareRenameResponse, Range, ReferenceParams, RenameParams, SemanticToken,
+    SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensLegend,
+    SemanticTokensParams, SemanticTokensRangeParams, SignatureHelp, SignatureHelpParams,
+    SignatureInformation, SymbolInformation, SymbolKind, TextDocumentPositionParams, TextEdit,
+    WorkspaceEdit, WorkspaceSymbolParams,
 };
 use std::{
     cmp,
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap},
     fmt, fs,
     path::{Path, PathBuf},
     sync::{Arc, Condvar, Mutex},
     thread,
+    time::{Duration, Instant},
 };
-use tempfile::tempdir;
+use tempfile::{tempdir, TempDir};
+use tracing::{debug, error, info, info_span, warn};
 use url::Url;
 
 use move_command_line_common::files::FileHash;
 use move_compiler::{
-    expansion::ast::{Address, Fields, ModuleIdent, ModuleIdent_},
+    cfgir::ast as G,
+    expansion::ast::{self as EA, Address, Fields, ModuleIdent, ModuleIdent_, Visibility},
     naming::ast::{StructDefinition, StructFields, TParam, Type, TypeName_, Type_},
-    parser::ast::StructName,
-    shared::Identifier,
+    parser::{
+        ast::{self as P, StructName},
+        keywords::KEYWORDS,
+        lexer::{Lexer, Tok},
+        syntax::parse_file_string,
+    },
+    shared::{CompilationEnv, Flags, Identifier, Name},
     typing::ast::{
         BuiltinFunction_, Exp, ExpListItem, Function, FunctionBody_, LValue, LValueList, LValue_,
         ModuleCall, ModuleDefinition, SequenceItem, SequenceItem_, UnannotatedExp_,
     },
+    FullyCompiledProgram, PASS_CFGIR, PASS_EXPANSION, PASS_HLIR, PASS_NAMING, PASS_PARSER,
     PASS_TYPING,
 };
+use move_core_types::value::MoveValue;
 use move_ir_types::location::*;
-use move_package::compilation::build_plan::BuildPlan;
+use move_package::{
+    compilation::{build_plan::BuildPlan, package_layout::CompiledPackageLayout},
+    resolution::resolution_graph::ResolvedGraph,
+    BuildConfig,
+};
 use move_symbol_pool::Symbol;
+use serde::{Deserialize, Serialize};
 
 /// Enabling/disabling the language server reporting readiness to support go-to-def and
 /// go-to-references to the IDE.
 pub const DEFS_AND_REFS_SUPPORT: bool = true;
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Copy)]
+/// Maximal number of symbols returned in response to a workspace symbol query.
+const MAX_WORKSPACE_SYMBOLS: usize = 256;
+
+/// Directory (within a package's build directory) where symbolication results are cached
+const SYMBOLS_CACHE_DIR: &str = "move-analyzer";
+const SYMBOLS_CACHE_FILE: &str = "symbols.json";
+/// Version of the cached symbols' format, to be bumped whenever the format changes (cached symbols
+/// whose format changed in an incompatible way are ignored anyway, but compatible changes, such as
+/// a new optional field, would otherwise go unnoticed)
+const SYMBOLS_CACHE_VERSION: u32 = 12;
+
+/// Semantic token types reported to the client (position of a type on this list is used to
+/// identify it in the encoded tokens). Named addresses use a custom token type.
+const SEMANTIC_TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::STRUCT,
+    SemanticTokenType::TYPE_PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::new("namedAddress"),
+];
+const NAMESPACE_TOKEN: u32 = 0;
+const STRUCT_TOKEN: u32 = 1;
+const TYPE_PARAMETER_TOKEN: u32 = 2;
+const VARIABLE_TOKEN: u32 = 3;
+const PROPERTY_TOKEN: u32 = 4;
+const FUNCTION_TOKEN: u32 = 5;
+const NAMED_ADDRESS_TOKEN: u32 = 6;
+
+/// Semantic token modifiers reported to the client (position of a modifier on this list
+/// determines its bit in the encoded tokens' modifier set)
+const SEMANTIC_TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::READONLY,
+];
+const DECLARATION_MODIFIER: u32 = 1 << 0;
+const READONLY_MODIFIER: u32 = 1 << 1;
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Copy, Serialize, Deserialize)]
 /// Location of a definition's identifier
 struct DefLoc {
     /// File where the definition of the identifier starts
@@ -101,7 +185,7 @@ struct DefLoc {
 }
 
 /// Location of a use's identifier
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Copy)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Copy, Serialize, Deserialize)]
 struct UseLoc {
     /// File where this use identifier starts
     fhash: FileHash,
@@ -113,7 +197,7 @@ struct UseLoc {
 
 /// Information about a type of an identifier. The reason we need an additional enum is that there
 /// is not direct representation of a function type in the Type enum.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
 pub enum IdentType {
     RegularType(Type),
@@ -125,11 +209,26 @@ pub enum IdentType {
         Type,         /* ret */
         Vec<Type>,    /* acquires */
     ),
+    /// Type of a specification-only entity (a schema, a specification function, or a
+    /// specification variable), which is only available as a string
+    SpecType(String),
+}
+
+/// Kind of an identifier's definition
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DefKind {
+    Function,
+    Struct,
+    Field,
+    Const,
+    TypeParam,
+    /// Function parameter or local variable (or a specification variable)
+    Local,
 }
 
 /// Information about both the use identifier (source file is specified wherever an instance of this
 /// struct is used) and the definition identifier
-#[derive(Debug, Clone, Eq)]
+#[derive(Debug, Clone, Eq, Serialize, Deserialize)]
 pub struct UseDef {
     /// Column where the (use) identifier location starts on a given line (use this field for
     /// sorting uses on the line)
@@ -142,6 +241,11 @@ pub struct UseDef {
     def_loc: DefLoc,
     /// Location of the type definition
     type_def_loc: Option<DefLoc>,
+    /// Kind of the definition
+    def_kind: DefKind,
+    /// Whether this use (of a local) writes to it, either by defining or assigning it or by taking
+    /// a mutable reference to it
+    write: bool,
 }
 
 /// Definition of a struct field
@@ -158,6 +262,13 @@ struct StructDef {
     field_defs: Vec<FieldDef>,
 }
 
+/// Definition of a specification-only entity
+#[derive(Debug, Clone)]
+struct SpecDef {
+    name_start: Position,
+    ident_type: IdentType,
+}
+
 /// Module-level definitions
 #[derive(Debug)]
 struct ModuleDefs {
@@ -169,8 +280,17 @@ struct ModuleDefs {
     constants: BTreeMap<Symbol, Position>,
     /// Function definitions
     functions: BTreeMap<Symbol, Position>,
+    /// Schema definitions
+    schemas: BTreeMap<Symbol, SpecDef>,
+    /// Specification function definitions
+    spec_funs: BTreeMap<Symbol, SpecDef>,
+    /// Global specification variable definitions
+    spec_vars: BTreeMap<Symbol, SpecDef>,
 }
 
+/// Locals visible in a specification block, with their definitions and types
+type SpecScope = OrdMap<Symbol, (DefLoc, IdentType)>;
+
 /// Data used during symbolication
 pub struct Symbolicator {
     /// Outermost definitions in a module (structs, consts, functions)
@@ -183,33 +303,175 @@ pub struct Symbolicator {
     type_params: BTreeMap<Symbol, DefLoc>,
     /// Current processed module (always set before module processing starts)
     current_mod: Option<ModuleIdent>,
+    /// Signatures of all processed functions
+    fun_signatures: BTreeMap<DefLoc, FunSignature>,
+    /// Expansion ASTs of modules (specifications are not part of the typed AST)
+    expansion_modules: BTreeMap<ModuleIdent_, EA::ModuleDefinition>,
+    /// Currently processed function (set while processing its body)
+    current_fun: Option<Symbol>,
+}
+
+/// Signature of a function definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FunSignature {
+    /// Declared type of the function
+    fun_type: IdentType,
+    /// Names of the function's parameters
+    param_names: Vec<Symbol>,
+}
+
+/// Fields of a struct definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StructFieldsInfo {
+    /// Module where the struct is defined
+    mod_ident: ModuleIdent_,
+    /// File where the struct is defined
+    fhash: FileHash,
+    /// Type parameters of the struct
+    type_params: Vec<TParam>,
+    /// Names and types of the fields (in declaration order)
+    fields: Vec<(Symbol, Type)>,
+}
+
+/// Semantic token whose position is absolute (rather than relative to the previous token)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+struct FileToken {
+    start: Position,
+    length: u32,
+    token_type: u32,
+    modifiers: u32,
+}
+
+/// A symbol (and its location) stored in the symbol index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedSymbol {
+    kind: SymbolKind,
+    location: Location,
+    /// Name of the enclosing definition (e.g., the module where a function is defined)
+    container_name: Option<String>,
 }
 
+/// Workspace-wide index of symbols keyed by their names, used to answer (fuzzy) symbol queries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SymbolIndex(BTreeMap<String, Vec<IndexedSymbol>>);
+
 /// Maps a line number to a list of use-def pairs on a given line (use-def set is sorted by
 /// col_start)
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct UseDefMap(BTreeMap<u32, BTreeSet<UseDef>>);
 
 /// Result of the symbolication process
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Symbols {
     /// A map from def locations to all the references (uses)
+    #[serde(with = "cache::pairs")]
     references: BTreeMap<DefLoc, BTreeSet<UseLoc>>,
     /// A mapping from uses to definitions in a file
     file_use_defs: BTreeMap<PathBuf, UseDefMap>,
     /// A mapping from file hashes to file names
+    #[serde(with = "cache::pairs")]
     file_name_mapping: BTreeMap<FileHash, Symbol>,
+    /// A mapping from files to outlines of their contents
+    file_outlines: BTreeMap<PathBuf, Vec<DocumentSymbol>>,
+    /// Index of all symbols in the package and its dependencies
+    symbol_index: SymbolIndex,
+    /// A mapping from files to semantic tokens in these files (sorted by position)
+    file_tokens: BTreeMap<PathBuf, Vec<FileToken>>,
+    /// A mapping from files to inlay hints in these files (sorted by position)
+    file_inlay_hints: BTreeMap<PathBuf, Vec<InlayHint>>,
+    /// A mapping from files to types of expressions in these files (sorted by range)
+    file_exp_types: BTreeMap<PathBuf, Vec<ExpType>>,
+    /// A mapping from function definitions to their signatures
+    #[serde(with = "cache::pairs")]
+    fun_signatures: BTreeMap<DefLoc, FunSignature>,
+    /// Modules and module members that can be imported
+    import_index: ImportIndex,
+    /// Modules and their members (used for completion of module access paths)
+    module_index: ModuleIndex,
+    /// A mapping from struct names to fields of structs with these names
+    struct_fields: BTreeMap<Symbol, Vec<StructFieldsInfo>>,
+    /// Documentation comments of functions, structs, and constants
+    #[serde(with = "cache::pairs")]
+    def_docs: BTreeMap<DefLoc, String>,
+    /// Declarations of constants along with their values (evaluated by the compiler)
+    #[serde(with = "cache::pairs")]
+    const_values: BTreeMap<DefLoc, String>,
+    /// Constants of integer types (which may be used as abort codes) keyed by their values
+    abort_codes: BTreeMap<u64, BTreeSet<DefLoc>>,
+    /// Files of the symbolicated packages themselves (as opposed to files of their dependencies,
+    /// whose definitions cannot be renamed)
+    package_files: BTreeSet<FileHash>,
+    /// Locations of declarations of named addresses in package manifests
+    address_declarations: BTreeMap<Symbol, Location>,
+    /// A mapping from files to files using definitions from them
+    file_dependents: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+    /// Files whose definitions could not be symbolicated because of errors (in them or in files
+    /// they depend on)
+    broken_files: BTreeSet<PathBuf>,
+    /// Compiler warnings about files of the symbolicated packages (reported again when symbols
+    /// are loaded from the cache)
+    warnings: BTreeMap<Symbol, Vec<Diagnostic>>,
 }
 
+/// Delay between the last edit of a buffer and symbolication of its (unsaved) contents
+const SYMBOLICATION_DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Copy)]
 enum RunnerState {
     Run,
+    /// Run unless another request arrives before the deadline
+    Debounce(Instant),
     Wait,
     Quit,
 }
 
+/// Error signaling that symbolication was abandoned as its results would have been superseded by
+/// a run requested in the meantime
+#[derive(Debug)]
+pub struct SymbolicationCancelled;
+
+impl fmt::Display for SymbolicationCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "symbolication cancelled")
+    }
+}
+
+impl std::error::Error for SymbolicationCancelled {}
+
 /// Data used during symbolication running and symbolication info updating
 pub struct SymbolicatorRunner {
     mtx_cvar: Arc<(Mutex<RunnerState>, Condvar)>,
+    /// Root directories of packages being symbolicated
+    pkg_paths: Arc<Mutex<BTreeSet<PathBuf>>>,
+    /// Contents of unsaved buffers (keyed by canonical file paths) used instead of on-disk contents
+    source_overlay: Arc<Mutex<BTreeMap<PathBuf, String>>>,
+    /// Configuration of package builds (as set by the user)
+    build_config: Arc<Mutex<BuildConfig>>,
+    /// Files (canonical paths) changed since the last symbolication run
+    changed_files: Arc<Mutex<BTreeSet<PathBuf>>>,
+    /// File (canonical path) most recently edited by the user
+    edited_file: Arc<Mutex<Option<PathBuf>>>,
+    /// Open files (canonical paths) not belonging to any package
+    standalone_files: Arc<Mutex<BTreeSet<PathBuf>>>,
+}
+
+/// Results of the most recent compilation of the whole package, against which only files affected
+/// by subsequent changes are recompiled
+pub struct CompilationBase {
+    /// Key of the configuration the package was compiled with (including named addresses)
+    config_key: String,
+    /// Hashes of contents of source files at the time of compilation, keyed by file names
+    file_hashes: BTreeMap<Symbol, FileHash>,
+    /// Names of modules declared in each file
+    file_modules: BTreeMap<FileHash, BTreeSet<Symbol>>,
+    /// A mapping from files to files using definitions from them
+    file_dependents: BTreeMap<FileHash, BTreeSet<FileHash>>,
+    /// Warnings reported when compiling the program (files that are not recompiled do not get
+    /// their warnings reported again)
+    warnings: Vec<CodespanDiagnostic>,
+    /// The compiled program (with all definitions treated as sources, as required of pre-compiled
+    /// programs)
+    program: FullyCompiledProgram,
 }
 
 impl fmt::Display for IdentType {
@@ -258,11 +520,12 @@ impl fmt::Display for IdentType {
                     acquires_str
                 )
             }
+            Self::SpecType(s) => write!(f, "{}", s),
         }
     }
 }
 
-fn type_to_ide_string(sp!(_, t): &Type) -> String {
+pub fn type_to_ide_string(sp!(_, t): &Type) -> String {
     match t {
         Type_::Unit => "()".to_string(),
         Type_::Ref(m, r) => format!("&{} {}", if *m { "mut" } else { "" }, type_to_ide_string(r)),
@@ -309,6 +572,15 @@ fn addr_to_ide_string(addr: &Address) -> String {
     }
 }
 
+/// Returns the addresses a module declared under the given address can be referred to by (a named
+/// address and its numerical value both refer to the same module)
+fn module_addresses(address: &Address) -> Vec<String> {
+    match address {
+        Address::Numerical(Some(name), sp!(_, bytes)) => vec![name.to_string(), bytes.to_string()],
+        address => vec![addr_to_ide_string(address)],
+    }
+}
+
 fn type_list_to_ide_string(items: &[Type]) -> String {
     items
         .iter()
@@ -317,88 +589,973 @@ fn type_list_to_ide_string(items: &[Type]) -> String {
         .join(", ")
 }
 
+fn spec_type_params_to_ide_string(type_params: &[(Name, EA::AbilitySet)]) -> String {
+    if type_params.is_empty() {
+        return "".to_string();
+    }
+    let names = type_params
+        .iter()
+        .map(|(name, _)| name.value.to_string())
+        .collect::<Vec<_>>();
+    format!("<{}>", names.join(", "))
+}
+
+fn spec_fun_to_ide_string(name: &Symbol, signature: &EA::FunctionSignature) -> String {
+    let params = signature
+        .parameters
+        .iter()
+        .map(|(v, t)| format!("{}: {}", v, t.value))
+        .collect::<Vec<_>>();
+    let ret_str = match &signature.return_type.value {
+        EA::Type_::Unit => "".to_string(),
+        t => format!(": {}", t),
+    };
+    format!(
+        "spec fun {}{}({}){}",
+        name,
+        spec_type_params_to_ide_string(&signature.type_parameters),
+        params.join(", "),
+        ret_str
+    )
+}
+
+impl Symbols {
+    /// Returns the index of modules and their members
+    pub fn module_index(&self) -> &ModuleIndex {
+        &self.module_index
+    }
+
+    /// Returns the index of modules and module members that can be imported
+    pub fn import_index(&self) -> &ImportIndex {
+        &self.import_index
+    }
+
+    /// Returns outlines of modules and scripts defined in a given file
+    pub fn file_outline(&self, fpath: &Path) -> &[DocumentSymbol] {
+        self.file_outlines
+            .get(fpath)
+            .map_or(&[], |outline| outline.as_slice())
+    }
+
+    /// Returns locations of all references to a definition whose identifier is at a given position
+    /// in a given file (excluding the definition itself), or None if no identifier is there
+    pub fn def_references(&self, fpath: &Path, position: &Position) -> Option<Vec<Location>> {
+        let uses = self.file_use_defs.get(fpath)?.get(position.line)?;
+        let u = uses
+            .iter()
+            .find(|u| position.character >= u.col_start && position.character <= u.col_end)?;
+        Some(self.reference_locations(&u.def_loc, false))
+    }
+
+    /// Returns all uses (and definitions) of locals and parameters in a given file
+    pub fn local_uses(&self, fpath: &Path) -> Vec<LocalUse> {
+        let use_defs = match self.file_use_defs.get(fpath) {
+            Some(use_defs) => use_defs,
+            None => return vec![],
+        };
+        use_defs
+            .0
+            .iter()
+            .flat_map(|(line, uses)| {
+                uses.iter()
+                    .filter(|u| u.def_kind == DefKind::Local)
+                    .map(move |u| LocalUse {
+                        def_start: u.def_loc.start,
+                        start: Position::new(*line, u.col_start),
+                        ty: u.use_type.to_string(),
+                        write: u.write,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns highlights of all occurrences (within a given file) of an identifier at a given
+    /// position in this file, or None if no identifier is there. Occurrences of locals are
+    /// highlighted as reads or writes.
+    pub fn document_highlights(
+        &self,
+        fpath: &Path,
+        position: &Position,
+    ) -> Option<Vec<DocumentHighlight>> {
+        let use_defs = self.file_use_defs.get(fpath)?;
+        let uses = use_defs.get(position.line)?;
+        let u = uses
+            .iter()
+            .find(|u| position.character >= u.col_start && position.character <= u.col_end)?;
+        let mut highlights = vec![];
+        for (line, uses) in &use_defs.0 {
+            for use_def in uses.iter().filter(|use_def| use_def.def_loc == u.def_loc) {
+                let kind = match (&use_def.def_kind, use_def.write) {
+                    (DefKind::Local, true) => DocumentHighlightKind::Write,
+                    (DefKind::Local, false) => DocumentHighlightKind::Read,
+                    _ => DocumentHighlightKind::Text,
+                };
+                highlights.push(DocumentHighlight {
+                    range: Range {
+                        start: Position::new(*line, use_def.col_start),
+                        end: Position::new(*line, use_def.col_end),
+                    },
+                    kind: Some(kind),
+                });
+            }
+        }
+        Some(highlights)
+    }
+
+    /// Returns the range of an identifier at a given position in a given file (whose contents are
+    /// also given) if this identifier can be renamed, or the reason why it cannot be renamed
+    pub fn prepare_rename(
+        &self,
+        fpath: &Path,
+        buffer: &str,
+        position: &Position,
+    ) -> Result<Range, String> {
+        let bytes = buffer.as_bytes();
+        let offset = byte_offset(buffer, position);
+        let start = ident_start(bytes, offset);
+        let end = offset
+            + bytes[offset..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                .count();
+        let name = &buffer[start..end];
+        if name.is_empty() {
+            return Err("no identifier to rename".to_string());
+        }
+        if KEYWORDS.contains(&name) {
+            return Err(format!("keyword '{}' cannot be renamed", name));
+        }
+        if self.named_address_token(fpath, position).is_some() {
+            return Err(format!("address '{}' cannot be renamed", name));
+        }
+        let u = self
+            .file_use_defs
+            .get(fpath)
+            .and_then(|use_defs| use_defs.get(position.line))
+            .into_iter()
+            .flatten()
+            .find(|u| position.character >= u.col_start && position.character <= u.col_end)
+            .ok_or_else(|| format!("'{}' cannot be renamed", name))?;
+        if !self.package_files.contains(&u.def_loc.fhash) {
+            return Err(format!(
+                "'{}' is defined in a dependency and cannot be renamed",
+                name
+            ));
+        }
+        Ok(Range {
+            start: Position::new(position.line, u.col_start),
+            end: Position::new(position.line, u.col_end),
+        })
+    }
+
+    /// Returns the token of a named address at a given position in a given file (if any)
+    fn named_address_token(&self, fpath: &Path, position: &Position) -> Option<&FileToken> {
+        self.file_tokens.get(fpath).into_iter().flatten().find(|t| {
+            t.token_type == NAMED_ADDRESS_TOKEN
+                && t.start.line == position.line
+                && position.character >= t.start.character
+                && position.character <= t.start.character + t.length
+        })
+    }
+
+    /// Returns the location of the manifest entry declaring the named address at a given position
+    /// in a given file (whose contents are also given), if there is a named address there
+    pub fn address_declaration(
+        &self,
+        fpath: &Path,
+        buffer: &str,
+        position: &Position,
+    ) -> Option<&Location> {
+        let token = self.named_address_token(fpath, position)?;
+        let start = byte_offset(buffer, &token.start);
+        let name = buffer.get(start..start + token.length as usize)?;
+        self.address_declarations.get(&Symbol::from(name))
+    }
+
+    /// Returns locations of all references to a given definition (optionally including the
+    /// definition itself)
+    fn reference_locations(&self, def_loc: &DefLoc, include_decl: bool) -> Vec<Location> {
+        let mut locs = vec![];
+        for ref_loc in self.references.get(def_loc).into_iter().flatten() {
+            if include_decl || !(def_loc.start == ref_loc.start && def_loc.fhash == ref_loc.fhash) {
+                let end_pos = Position {
+                    line: ref_loc.start.line,
+                    character: ref_loc.col_end,
+                };
+                let range = Range {
+                    start: ref_loc.start,
+                    end: end_pos,
+                };
+                let path = self.file_name_mapping.get(&ref_loc.fhash).unwrap();
+                locs.push(Location {
+                    uri: Url::from_file_path(path.as_str()).unwrap(),
+                    range,
+                });
+            }
+        }
+        locs
+    }
+
+    /// Returns the use of a function at a given position in a given file (if any)
+    fn fun_use_def(&self, fpath: &Path, position: &Position) -> Option<UseDef> {
+        self.file_use_defs
+            .get(fpath)?
+            .get(position.line)?
+            .into_iter()
+            .find(|u| {
+                u.def_kind == DefKind::Function
+                    && position.character >= u.col_start
+                    && position.character <= u.col_end
+            })
+    }
+
+    /// Returns outlines of a function whose name starts at a given position in a given file and of
+    /// the enclosing module (or script)
+    fn fun_outline(
+        &self,
+        fpath: &Path,
+        name_start: &Position,
+    ) -> Option<(&DocumentSymbol, &DocumentSymbol)> {
+        self.file_outlines.get(fpath)?.iter().find_map(|parent| {
+            parent
+                .children
+                .iter()
+                .flatten()
+                .find(|fun| {
+                    fun.kind == SymbolKind::Function && fun.selection_range.start == *name_start
+                })
+                .map(|fun| (fun, parent))
+        })
+    }
+
+    /// Returns outlines of a function whose definition contains a given position in a given file
+    /// and of the enclosing module (or script)
+    fn enclosing_fun_outline(
+        &self,
+        fpath: &Path,
+        position: &Position,
+    ) -> Option<(&DocumentSymbol, &DocumentSymbol)> {
+        self.file_outlines.get(fpath)?.iter().find_map(|parent| {
+            parent
+                .children
+                .iter()
+                .flatten()
+                .find(|fun| {
+                    fun.kind == SymbolKind::Function
+                        && fun.range.start <= *position
+                        && *position <= fun.range.end
+                })
+                .map(|fun| (fun, parent))
+        })
+    }
+
+    /// Returns a call hierarchy item representing a function whose name starts at a given position
+    /// in a given file
+    fn call_hierarchy_item(
+        &self,
+        fpath: &Path,
+        name_start: &Position,
+    ) -> Option<CallHierarchyItem> {
+        let (fun, parent) = self.fun_outline(fpath, name_start)?;
+        Some(CallHierarchyItem {
+            name: fun.name.clone(),
+            kind: SymbolKind::Function,
+            tags: None,
+            detail: Some(parent.name.clone()),
+            uri: Url::from_file_path(fpath).ok()?,
+            range: fun.range,
+            selection_range: fun.selection_range,
+            data: None,
+        })
+    }
+
+    /// Returns a call hierarchy item representing a function used (or defined) at a given position
+    /// in a given file
+    pub fn prepare_call_hierarchy(
+        &self,
+        fpath: &Path,
+        position: &Position,
+    ) -> Option<CallHierarchyItem> {
+        let u = self.fun_use_def(fpath, position)?;
+        let def_fpath = self.file_name_mapping.get(&u.def_loc.fhash)?;
+        self.call_hierarchy_item(Path::new(def_fpath.as_str()), &u.def_loc.start)
+    }
+
+    /// Returns calls of the function represented by a given call hierarchy item, grouped by the
+    /// calling functions
+    pub fn incoming_calls(&self, item: &CallHierarchyItem) -> Vec<CallHierarchyIncomingCall> {
+        let u = match self.fun_use_def(Path::new(item.uri.path()), &item.selection_range.start) {
+            Some(u) => u,
+            None => return vec![],
+        };
+        // calls keyed by the location of the caller's name
+        let mut calls: BTreeMap<(PathBuf, Position), CallHierarchyIncomingCall> = BTreeMap::new();
+        for loc in self.reference_locations(&u.def_loc, false) {
+            let fpath = PathBuf::from(loc.uri.path());
+            let caller_start = match self.enclosing_fun_outline(&fpath, &loc.range.start) {
+                Some((caller, _)) => caller.selection_range.start,
+                // e.g., a use in a spec block
+                None => continue,
+            };
+            let call = match calls.entry((fpath, caller_start)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    match self.call_hierarchy_item(&entry.key().0, &caller_start) {
+                        Some(from) => entry.insert(CallHierarchyIncomingCall {
+                            from,
+                            from_ranges: vec![],
+                        }),
+                        None => continue,
+                    }
+                }
+            };
+            call.from_ranges.push(loc.range);
+        }
+        calls.into_values().collect()
+    }
+
+    /// Returns calls made by the function represented by a given call hierarchy item, grouped by
+    /// the called functions
+    pub fn outgoing_calls(&self, item: &CallHierarchyItem) -> Vec<CallHierarchyOutgoingCall> {
+        let fpath = Path::new(item.uri.path());
+        let (fun, use_defs) = match (
+            self.fun_outline(fpath, &item.selection_range.start),
+            self.file_use_defs.get(fpath),
+        ) {
+            (Some((fun, _)), Some(use_defs)) => (fun, use_defs),
+            _ => return vec![],
+        };
+        // calls keyed by the location of the callee's definition
+        let mut calls: BTreeMap<DefLoc, CallHierarchyOutgoingCall> = BTreeMap::new();
+        for line in fun.range.start.line..=fun.range.end.line {
+            for u in use_defs.get(line).into_iter().flatten() {
+                let start = Position::new(line, u.col_start);
+                if u.def_kind != DefKind::Function
+                    || start == fun.selection_range.start
+                    || start < fun.range.start
+                    || start > fun.range.end
+                {
+                    continue;
+                }
+                let call = match calls.entry(u.def_loc) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let to = self.file_name_mapping.get(&u.def_loc.fhash).and_then(|p| {
+                            self.call_hierarchy_item(Path::new(p.as_str()), &u.def_loc.start)
+                        });
+                        match to {
+                            Some(to) => entry.insert(CallHierarchyOutgoingCall {
+                                to,
+                                from_ranges: vec![],
+                            }),
+                            None => continue,
+                        }
+                    }
+                };
+                let end = Position::new(line, u.col_end);
+                call.from_ranges.push(Range::new(start, end));
+            }
+        }
+        calls.into_values().collect()
+    }
+
+    /// Merges symbolication information of another package into this one. Information about
+    /// dependencies shared by both packages is the same in both, so it can simply be overwritten.
+    /// Returns files depending (directly or transitively) on the given files, that is files using
+    /// definitions from them
+    pub fn dependents(&self, files: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+        let mut dependents = BTreeSet::new();
+        let mut worklist = files.iter().collect::<Vec<_>>();
+        while let Some(fpath) = worklist.pop() {
+            for dependent in self.file_dependents.get(fpath).into_iter().flatten() {
+                if dependents.insert(dependent.clone()) {
+                    worklist.push(dependent);
+                }
+            }
+        }
+        dependents
+    }
+
+    pub fn merge(&mut self, other: Symbols) {
+        for (def_loc, uses) in other.references {
+            self.references
+                .entry(def_loc)
+                .or_insert_with(BTreeSet::new)
+                .extend(uses);
+        }
+        self.file_use_defs.extend(other.file_use_defs);
+        self.file_name_mapping.extend(other.file_name_mapping);
+        self.file_outlines.extend(other.file_outlines);
+        self.symbol_index = SymbolIndex::new(&self.file_outlines);
+        self.file_tokens.extend(other.file_tokens);
+        self.file_inlay_hints.extend(other.file_inlay_hints);
+        self.file_exp_types.extend(other.file_exp_types);
+        self.fun_signatures.extend(other.fun_signatures);
+        self.import_index.extend(other.import_index);
+        self.module_index.extend(other.module_index);
+        self.def_docs.extend(other.def_docs);
+        self.const_values.extend(other.const_values);
+        for (code, consts) in other.abort_codes {
+            self.abort_codes
+                .entry(code)
+                .or_insert_with(BTreeSet::new)
+                .extend(consts);
+        }
+        self.package_files.extend(other.package_files);
+        self.address_declarations.extend(other.address_declarations);
+        for (fpath, dependents) in other.file_dependents {
+            self.file_dependents
+                .entry(fpath)
+                .or_insert_with(BTreeSet::new)
+                .extend(dependents);
+        }
+        self.broken_files.extend(other.broken_files);
+        for (fname, warnings) in other.warnings {
+            self.warnings
+                .entry(fname)
+                .or_insert_with(Vec::new)
+                .extend(warnings);
+        }
+        for (name, infos) in other.struct_fields {
+            let struct_infos = self.struct_fields.entry(name).or_insert_with(Vec::new);
+            for info in infos {
+                if !struct_infos
+                    .iter()
+                    .any(|i| i.mod_ident == info.mod_ident && i.fhash == info.fhash)
+                {
+                    struct_infos.push(info);
+                }
+            }
+        }
+    }
+}
+
+impl Symbols {
+    /// Fills in symbols of broken files (those that could not be symbolicated) from symbols
+    /// computed previously, so that these files can still be navigated (based on their last
+    /// contents that could be compiled) until they are fixed.
+    pub fn fill_broken_files(&mut self, previous: &Symbols) {
+        if self.broken_files.is_empty() {
+            return;
+        }
+        let broken_hashes = previous
+            .file_name_mapping
+            .iter()
+            .filter(|(_, fname)| self.broken_files.contains(Path::new(fname.as_str())))
+            .map(|(fhash, _)| *fhash)
+            .collect::<BTreeSet<_>>();
+        fn copy<T: Clone>(to: &mut BTreeMap<PathBuf, T>, from: &BTreeMap<PathBuf, T>, f: &Path) {
+            if let Some(v) = from.get(f) {
+                to.insert(f.to_path_buf(), v.clone());
+            }
+        }
+        for f in &self.broken_files {
+            copy(&mut self.file_use_defs, &previous.file_use_defs, f);
+            copy(&mut self.file_outlines, &previous.file_outlines, f);
+            copy(&mut self.file_tokens, &previous.file_tokens, f);
+            copy(&mut self.file_inlay_hints, &previous.file_inlay_hints, f);
+            copy(&mut self.file_exp_types, &previous.file_exp_types, f);
+            copy(&mut self.file_dependents, &previous.file_dependents, f);
+        }
+        // locations in previously symbolicated contents of broken files are identified by hashes
+        // of these contents
+        for (fhash, fname) in &previous.file_name_mapping {
+            self.file_name_mapping.entry(*fhash).or_insert(*fname);
+        }
+        for (def_loc, uses) in &previous.references {
+            if broken_hashes.contains(&def_loc.fhash) {
+                self.references.insert(*def_loc, uses.clone());
+            }
+        }
+        for (def_loc, signature) in &previous.fun_signatures {
+            if broken_hashes.contains(&def_loc.fhash) {
+                self.fun_signatures.insert(*def_loc, signature.clone());
+            }
+        }
+        for (def_loc, doc) in &previous.def_docs {
+            if broken_hashes.contains(&def_loc.fhash) {
+                self.def_docs.insert(*def_loc, doc.clone());
+            }
+        }
+        for (def_loc, value) in &previous.const_values {
+            if broken_hashes.contains(&def_loc.fhash) {
+                self.const_values.insert(*def_loc, value.clone());
+            }
+        }
+        for (code, consts) in &previous.abort_codes {
+            self.abort_codes
+                .entry(*code)
+                .or_insert_with(BTreeSet::new)
+                .extend(
+                    consts
+                        .iter()
+                        .filter(|def_loc| broken_hashes.contains(&def_loc.fhash)),
+                );
+        }
+        self.package_files.extend(
+            previous
+                .package_files
+                .iter()
+                .filter(|fhash| broken_hashes.contains(fhash)),
+        );
+        self.symbol_index = SymbolIndex::new(&self.file_outlines);
+        // modules of broken files can still be completed and imported
+        let mut import_index = previous.import_index.clone();
+        import_index.extend(std::mem::take(&mut self.import_index));
+        self.import_index = import_index;
+        let mut module_index = previous.module_index.clone();
+        module_index.extend(std::mem::take(&mut self.module_index));
+        self.module_index = module_index;
+    }
+}
+
+impl CompilationBase {
+    fn new(
+        config_key: String,
+        source_files: &BTreeMap<FileHash, (Symbol, String)>,
+        file_dependents: BTreeMap<FileHash, BTreeSet<FileHash>>,
+        warnings: Vec<CodespanDiagnostic>,
+        mut program: FullyCompiledProgram,
+    ) -> Self {
+        program
+            .parser
+            .source_definitions
+            .append(&mut program.parser.lib_definitions);
+        let mut file_modules = BTreeMap::new();
+        for pkg_def in &program.parser.source_definitions {
+            let names = match &pkg_def.def {
+                P::Definition::Module(mod_def) => vec![mod_def.name.0.value],
+                P::Definition::Address(addr_def) => {
+                    addr_def.modules.iter().map(|m| m.name.0.value).collect()
+                }
+                P::Definition::Script(_) => vec![],
+            };
+            file_modules
+                .entry(definition_file(&pkg_def.def))
+                .or_insert_with(BTreeSet::new)
+                .extend(names);
+        }
+        CompilationBase {
+            config_key,
+            file_hashes: source_files
+                .iter()
+                .map(|(fhash, (fname, _))| (*fname, *fhash))
+                .collect(),
+            file_modules,
+            file_dependents,
+            warnings,
+            program,
+        }
+    }
+
+    /// Returns files to be recompiled (as hashes of their current contents), that is new files,
+    /// changed files, and files depending on them, along with files of the base superseded by
+    /// them (as hashes of their contents at the time of compilation). Returns None if the whole
+    /// package has to be compiled again.
+    fn recompiled_files(
+        &self,
+        config_key: &str,
+        source_files: &BTreeMap<FileHash, (Symbol, String)>,
+    ) -> Option<(BTreeSet<FileHash>, BTreeSet<FileHash>)> {
+        if config_key != self.config_key {
+            return None;
+        }
+        let current = source_files
+            .iter()
+            .map(|(fhash, (fname, _))| (*fname, *fhash))
+            .collect::<BTreeMap<_, _>>();
+        // modules of the base remain visible to the compiler, so modules of removed files (or
+        // modules removed from changed files) could still be used without an error
+        if self
+            .file_hashes
+            .keys()
+            .any(|fname| !current.contains_key(fname))
+        {
+            return None;
+        }
+        let mut worklist = vec![];
+        for (fname, fhash) in &current {
+            match self.file_hashes.get(fname) {
+                Some(base_hash) if base_hash != fhash => {
+                    let modules = module_declarations(*fhash, &source_files[fhash].1)?
+                        .into_iter()
+                        .map(|(_, name)| name)
+                        .collect::<BTreeSet<_>>();
+                    if !self
+                        .file_modules
+                        .get(base_hash)
+                        .map_or(true, |base_modules| base_modules.is_subset(&modules))
+                    {
+                        return None;
+                    }
+                    worklist.push(*base_hash);
+                }
+                _ => (),
+            }
+        }
+        let mut superseded = BTreeSet::new();
+        while let Some(fhash) = worklist.pop() {
+            if superseded.insert(fhash) {
+                worklist.extend(self.file_dependents.get(&fhash).into_iter().flatten());
+            }
+        }
+        let recompiled = current
+            .iter()
+            .filter(|(fname, _)| {
+                self.file_hashes
+                    .get(*fname)
+                    .map_or(true, |base_hash| superseded.contains(base_hash))
+            })
+            .map(|(_, fhash)| *fhash)
+            .collect::<BTreeSet<_>>();
+        // recompiling most of the package would not be any faster than compiling all of it
+        if recompiled.len() * 2 > current.len() {
+            return None;
+        }
+        Some((recompiled, superseded))
+    }
+}
+
 impl SymbolicatorRunner {
     /// Create a new idle runner (one that does not actually symbolicate)
     pub fn idle() -> Self {
         let mtx_cvar = Arc::new((Mutex::new(RunnerState::Wait), Condvar::new()));
-        SymbolicatorRunner { mtx_cvar }
+        let pkg_paths = Arc::new(Mutex::new(BTreeSet::new()));
+        let source_overlay = Arc::new(Mutex::new(BTreeMap::new()));
+        let build_config = Arc::new(Mutex::new(BuildConfig::default()));
+        let changed_files = Arc::new(Mutex::new(BTreeSet::new()));
+        let edited_file = Arc::new(Mutex::new(None));
+        let standalone_files = Arc::new(Mutex::new(BTreeSet::new()));
+        SymbolicatorRunner {
+            mtx_cvar,
+            pkg_paths,
+            source_overlay,
+            build_config,
+            changed_files,
+            edited_file,
+            standalone_files,
+        }
     }
 
-    /// Create a new runner
+    /// Create a new runner for packages rooted at the given URIs (symbols of all packages are
+    /// merged together)
     pub fn new(
-        uri: &Url,
+        uris: &[Url],
         symbols: Arc<Mutex<Symbols>>,
         sender: Sender<Result<BTreeMap<Symbol, Vec<Diagnostic>>>>,
     ) -> Self {
         let mtx_cvar = Arc::new((Mutex::new(RunnerState::Wait), Condvar::new()));
         let thread_mtx_cvar = mtx_cvar.clone();
-        let pkg_path = uri.to_file_path().unwrap();
+        let pkg_paths = Arc::new(Mutex::new(
+            uris.iter()
+                .filter_map(|uri| uri.to_file_path().ok())
+                .collect::<BTreeSet<_>>(),
+        ));
+        let thread_pkg_paths = pkg_paths.clone();
+        let source_overlay = Arc::new(Mutex::new(BTreeMap::new()));
+        let thread_source_overlay = source_overlay.clone();
+        let build_config = Arc::new(Mutex::new(BuildConfig::default()));
+        let thread_build_config = build_config.clone();
+        let changed_files = Arc::new(Mutex::new(BTreeSet::new()));
+        let thread_changed_files = changed_files.clone();
+        let edited_file = Arc::new(Mutex::new(None));
+        let thread_edited_file = edited_file.clone();
+        let standalone_files = Arc::new(Mutex::new(BTreeSet::new()));
+        let thread_standalone_files = standalone_files.clone();
 
         thread::spawn(move || {
+            // most recently computed symbols of each package
+            let mut pkg_symbols: BTreeMap<PathBuf, Symbols> = BTreeMap::new();
+            // most recently published diagnostics of each package
+            let mut pkg_diagnostics: BTreeMap<PathBuf, BTreeMap<Symbol, Vec<Diagnostic>>> =
+                BTreeMap::new();
+            // results of the most recent compilation of each whole package
+            let mut pkg_bases: BTreeMap<PathBuf, Option<CompilationBase>> = BTreeMap::new();
+            // packages created for standalone files
+            let mut standalone_pkgs: BTreeMap<PathBuf, TempDir> = BTreeMap::new();
             let (mtx, cvar) = &*thread_mtx_cvar;
+            // a run is superseded by any run requested while it is in progress
+            let is_cancelled = || *mtx.lock().unwrap() != RunnerState::Wait;
             // infinite loop to wait for symbolication requests
             loop {
                 let get_symbols = {
                     // hold the lock only as long as it takes to get the data, rather than through
                     // the whole symbolication process (hence a separate scope here)
                     let mut symbolicate = mtx.lock().unwrap();
-                    match *symbolicate {
+                    let state = *symbolicate;
+                    match state {
                         RunnerState::Quit => break,
                         RunnerState::Run => {
                             *symbolicate = RunnerState::Wait;
                             true
                         }
-                        RunnerState::Wait => {
-                            // wait for next request
-                            symbolicate = cvar.wait(symbolicate).unwrap();
-                            match *symbolicate {
-                                RunnerState::Quit => break,
-                                RunnerState::Run => {
-                                    *symbolicate = RunnerState::Wait;
-                                    true
-                                }
-                                RunnerState::Wait => false,
+                        RunnerState::Debounce(deadline) => {
+                            let now = Instant::now();
+                            if now >= deadline {
+                                *symbolicate = RunnerState::Wait;
+                                true
+                            } else {
+                                // wait for the deadline or for the next request (whichever comes
+                                // first) and re-examine the state
+                                drop(cvar.wait_timeout(symbolicate, deadline - now).unwrap());
+                                false
                             }
                         }
+                        RunnerState::Wait => {
+                            // wait for next request and re-examine the state
+                            drop(cvar.wait(symbolicate).unwrap());
+                            false
+                        }
                     }
                 };
                 if get_symbols {
-                    eprintln!("symbolication started");
-                    match Symbolicator::get_symbols(&pkg_path) {
-                        Ok((symbols_opt, lsp_diagnostics)) => {
-                            eprintln!("symbolication finished");
-                            if let Some(new_symbols) = symbols_opt {
-                                // replace symbols only if they have been actually recomputed,
-                                // otherwise keep the old (possibly out-dated) symbolication info
-                                let mut old_symbols = symbols.lock().unwrap();
-                                *old_symbols = new_symbols;
-                            }
-                            // set/reset (previous) diagnostics
-                            if let Err(err) = sender.send(Ok(lsp_diagnostics)) {
-                                eprintln!("could not pass diagnostics: {:?}", err);
+                    let mut pkg_paths = thread_pkg_paths.lock().unwrap().clone();
+                    // packages of standalone files that are no longer open are removed (along
+                    // with their directories), while packages of newly opened ones are created
+                    let standalone_files = thread_standalone_files.lock().unwrap().clone();
+                    standalone_pkgs.retain(|fpath, _| standalone_files.contains(fpath));
+                    for fpath in standalone_files {
+                        if let Entry::Vacant(entry) = standalone_pkgs.entry(fpath) {
+                            match standalone_package(entry.key()) {
+                                Ok(dir) => {
+                                    entry.insert(dir);
+                                }
+                                Err(err) => error!(
+                                    "could not create package for {:?}: {:?}",
+                                    entry.key(),
+                                    err
+                                ),
                             }
                         }
-                        Err(err) => {
-                            eprintln!("symbolication failed: {:?}", err);
-                            if let Err(err) = sender.send(Err(err)) {
-                                eprintln!("could not compiler error: {:?}", err);
+                    }
+                    let standalone_pkg_paths = standalone_pkgs
+                        .values()
+                        .map(|dir| dir.path().to_path_buf())
+                        .collect::<BTreeSet<_>>();
+                    pkg_paths.extend(standalone_pkg_paths.iter().cloned());
+                    let source_overlay = thread_source_overlay.lock().unwrap().clone();
+                    let build_config = thread_build_config.lock().unwrap().clone();
+                    let changed_files = std::mem::take(&mut *thread_changed_files.lock().unwrap());
+                    // forget about packages removed from the workspace and clear their diagnostics
+                    let removed = pkg_symbols
+                        .keys()
+                        .filter(|p| !pkg_paths.contains(*p))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for pkg_path in removed {
+                        let old_symbols = pkg_symbols.remove(&pkg_path).unwrap();
+                        pkg_diagnostics.remove(&pkg_path);
+                        pkg_bases.remove(&pkg_path);
+                        let lsp_diagnostics = lsp_empty_diagnostics(&old_symbols.file_name_mapping);
+                        if let Err(err) = sender.send(Ok(lsp_diagnostics)) {
+                            error!("could not pass diagnostics: {:?}", err);
+                        }
+                    }
+                    // the package containing the file being edited is symbolicated first so that
+                    // its symbols are updated as soon as possible
+                    let edited_file = thread_edited_file.lock().unwrap().clone();
+                    let mut ordered_pkg_paths = pkg_paths.iter().collect::<Vec<_>>();
+                    if let Some(edited_file) = edited_file {
+                        let standalone_pkg = standalone_pkgs.get(&edited_file).map(|d| d.path());
+                        ordered_pkg_paths.sort_by_key(|pkg_path| {
+                            standalone_pkg != Some(pkg_path.as_path())
+                                && !fs::canonicalize(pkg_path)
+                                    .map_or(false, |p| edited_file.starts_with(p))
+                        });
+                    }
+                    for pkg_path in ordered_pkg_paths {
+                        // the duration of symbolication is logged when the span is closed
+                        let _span = info_span!("symbolication", package = ?pkg_path).entered();
+                        info!("symbolication started");
+                        // packages of standalone files are re-created in a different directory
+                        // each time, so their symbols are not worth caching
+                        let cache_dir = if standalone_pkg_paths.contains(pkg_path) {
+                            None
+                        } else {
+                            Some(symbols_cache_dir(pkg_path))
+                        };
+                        match Symbolicator::get_symbols_incremental(
+                            pkg_path,
+                            &source_overlay,
+                            &build_config,
+                            cache_dir.as_deref(),
+                            pkg_bases.entry(pkg_path.clone()).or_default(),
+                            &is_cancelled,
+                        ) {
+                            Ok((mut symbols_opt, lsp_diagnostics)) => {
+                                info!("symbolication finished");
+                                if let (Some(new_symbols), Some(old_symbols)) =
+                                    (&mut symbols_opt, pkg_symbols.get(pkg_path))
+                                {
+                                    new_symbols.fill_broken_files(old_symbols);
+                                }
+                                // files using definitions from changed files are affected by the
+                                // changes as well (all files are affected on the first run)
+                                let affected = pkg_symbols.get(pkg_path).map(|symbols| {
+                                    let mut affected = symbols.dependents(&changed_files);
+                                    affected.extend(changed_files.iter().cloned());
+                                    affected
+                                });
+                                let lsp_diagnostics = diagnostics_to_publish(
+                                    pkg_diagnostics.entry(pkg_path.clone()).or_default(),
+                                    lsp_diagnostics,
+                                    // diagnostics of files compiled after excluding broken
+                                    // ones are not reported
+                                    symbols_opt
+                                        .as_ref()
+                                        .map_or(false, |s| s.broken_files.is_empty()),
+                                    affected.as_ref(),
+                                );
+                                if let Some(new_symbols) = symbols_opt {
+                                    // replace symbols only if they have been actually recomputed,
+                                    // otherwise keep the old (possibly out-dated) symbolication
+                                    // info
+                                    pkg_symbols.insert(pkg_path.clone(), new_symbols);
+                                    *symbols.lock().unwrap() = merged_symbols(&pkg_symbols);
+                                }
+                                // set/reset (previous) diagnostics
+                                if let Err(err) = sender.send(Ok(lsp_diagnostics)) {
+                                    error!("could not pass diagnostics: {:?}", err);
+                                }
+                            }
+                            Err(err) if err.is::<SymbolicationCancelled>() => {
+                                info!("symbolication cancelled");
+                                // changes are yet to be accounted for by the next run
+                                thread_changed_files
+                                    .lock()
+                                    .unwrap()
+                                    .extend(changed_files.iter().cloned());
+                                break;
+                            }
+                            Err(err) => {
+                                error!("symbolication failed: {:?}", err);
+                                if let Err(err) = sender.send(Err(err)) {
+                                    error!("could not compiler error: {:?}", err);
+                                }
                             }
                         }
                     }
+                    *symbols.lock().unwrap() = merged_symbols(&pkg_symbols);
                 }
             }
         });
 
-        SymbolicatorRunner { mtx_cvar }
+        SymbolicatorRunner {
+            mtx_cvar,
+            pkg_paths,
+            source_overlay,
+            build_config,
+            changed_files,
+            edited_file,
+            standalone_files,
+        }
+    }
+
+    /// Records unsaved contents of a buffer to be symbolicated instead of the on-disk contents of
+    /// the corresponding file
+    pub fn set_buffer(&self, uri: &Url, contents: String) {
+        if let Some(fpath) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| fs::canonicalize(p).ok())
+        {
+            self.changed_files.lock().unwrap().insert(fpath.clone());
+            *self.edited_file.lock().unwrap() = Some(fpath.clone());
+            self.source_overlay.lock().unwrap().insert(fpath, contents);
+        }
+    }
+
+    /// Forgets unsaved contents of a buffer so that the on-disk contents of the corresponding file
+    /// are symbolicated again
+    pub fn remove_buffer(&self, uri: &Url) {
+        if let Some(fpath) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| fs::canonicalize(p).ok())
+        {
+            self.source_overlay.lock().unwrap().remove(&fpath);
+            self.changed_files.lock().unwrap().insert(fpath);
+        }
+    }
+
+    /// Records a change of the on-disk contents of a file
+    pub fn file_changed(&self, uri: &Url) {
+        if let Some(fpath) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| fs::canonicalize(p).ok())
+        {
+            self.changed_files.lock().unwrap().insert(fpath);
+        }
+    }
+
+    /// Adds a package to the set of symbolicated packages and schedules a symbolication run
+    pub fn add_package(&self, uri: &Url) {
+        if let Ok(pkg_path) = uri.to_file_path() {
+            self.pkg_paths.lock().unwrap().insert(pkg_path);
+            self.run();
+        }
+    }
+
+    /// Symbolicates the given file (not belonging to any package) as long as it is open, scheduling
+    /// a symbolication run
+    pub fn add_standalone_file(&self, uri: &Url) {
+        if let Some(fpath) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| fs::canonicalize(p).ok())
+        {
+            self.standalone_files.lock().unwrap().insert(fpath);
+            self.run();
+        }
+    }
+
+    /// Stops symbolicating the given file (not belonging to any package), scheduling a
+    /// symbolication run if it was symbolicated
+    pub fn remove_standalone_file(&self, uri: &Url) {
+        if let Some(fpath) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| fs::canonicalize(p).ok())
+        {
+            if self.standalone_files.lock().unwrap().remove(&fpath) {
+                self.run();
+            }
+        }
+    }
+
+    /// Removes a package from the set of symbolicated packages and schedules a symbolication run
+    pub fn remove_package(&self, uri: &Url) {
+        if let Ok(pkg_path) = uri.to_file_path() {
+            self.pkg_paths.lock().unwrap().remove(&pkg_path);
+            self.run();
+        }
+    }
+
+    /// Sets the configuration of package builds and schedules a symbolication run
+    pub fn set_build_config(&self, build_config: BuildConfig) {
+        *self.build_config.lock().unwrap() = build_config;
+        self.run();
     }
 
     pub fn run(&self) {
-        eprintln!("scheduling run");
+        debug!("scheduling run");
         let (mtx, cvar) = &*self.mtx_cvar;
         let mut symbolicate = mtx.lock().unwrap();
         *symbolicate = RunnerState::Run;
         cvar.notify_one();
-        eprintln!("scheduled run");
+        debug!("scheduled run");
+    }
+
+    /// Schedules a run after a short delay, postponing an already scheduled delayed run, so that
+    /// symbolication does not happen after each keystroke
+    pub fn run_debounced(&self) {
+        let (mtx, cvar) = &*self.mtx_cvar;
+        let mut symbolicate = mtx.lock().unwrap();
+        if matches!(*symbolicate, RunnerState::Wait | RunnerState::Debounce(_)) {
+            *symbolicate = RunnerState::Debounce(Instant::now() + SYMBOLICATION_DEBOUNCE_DELAY);
+            cvar.notify_one();
+        }
     }
 
     pub fn quit(&self) {
@@ -419,6 +1576,7 @@ impl UseDef {
         use_name: &Symbol,
         use_type: IdentType,
         type_def_loc: Option<DefLoc>,
+        def_kind: DefKind,
     ) -> Self {
         let def_loc = DefLoc {
             fhash: def_fhash,
@@ -441,6 +1599,32 @@ impl UseDef {
             use_type,
             def_loc,
             type_def_loc,
+            def_kind,
+            write: false,
+        }
+    }
+
+    /// Get semantic token corresponding to this use
+    fn semantic_token(&self, line: u32, use_fhash: FileHash) -> FileToken {
+        let (token_type, mut modifiers) = match self.def_kind {
+            DefKind::Function => (FUNCTION_TOKEN, 0),
+            DefKind::Struct => (STRUCT_TOKEN, 0),
+            DefKind::Field => (PROPERTY_TOKEN, 0),
+            DefKind::Const => (VARIABLE_TOKEN, READONLY_MODIFIER),
+            DefKind::TypeParam => (TYPE_PARAMETER_TOKEN, 0),
+            DefKind::Local => (VARIABLE_TOKEN, 0),
+        };
+        if self.def_loc.fhash == use_fhash
+            && self.def_loc.start.line == line
+            && self.def_loc.start.character == self.col_start
+        {
+            modifiers |= DECLARATION_MODIFIER;
+        }
+        FileToken {
+            start: Position::new(line, self.col_start),
+            length: self.col_end - self.col_start,
+            token_type,
+            modifiers,
         }
     }
 }
@@ -485,84 +1669,517 @@ impl UseDefMap {
     }
 }
 
-impl Symbolicator {
-    /// Main driver to get symbols for the whole package. Returned symbols is an option as only the
-    /// correctly computed symbols should be a replacement for the old set - if symbols are not
-    /// actually (re)computed and the diagnostics are returned, the old symbolic information should
-    /// be retained even if it's getting out-of-date.
-    pub fn get_symbols(
-        pkg_path: &Path,
-    ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
-        let build_config = move_package::BuildConfig {
-            test_mode: true,
-            install_dir: Some(tempdir().unwrap().path().to_path_buf()),
-            ..Default::default()
-        };
+impl SymbolIndex {
+    /// Create an index of all symbols present in the given file outlines
+    fn new(file_outlines: &BTreeMap<PathBuf, Vec<DocumentSymbol>>) -> Self {
+        let mut index = SymbolIndex::default();
+        for (fpath, outline) in file_outlines {
+            let uri = match Url::from_file_path(fpath) {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+            for symbol in outline {
+                index.insert(&uri, symbol, None);
+            }
+        }
+        index
+    }
 
-        eprintln!("symbolicating {:?}", pkg_path);
+    /// Insert a symbol and all its children into the index
+    fn insert(&mut self, uri: &Url, symbol: &DocumentSymbol, container_name: Option<&str>) {
+        // spec blocks do not introduce new names
+        if symbol.kind != SymbolKind::Namespace {
+            self.0
+                .entry(symbol.name.clone())
+                .or_insert_with(Vec::new)
+                .push(IndexedSymbol {
+                    kind: symbol.kind,
+                    location: Location::new(uri.clone(), symbol.selection_range),
+                    container_name: container_name.map(|n| n.to_string()),
+                });
+        }
+        for child in symbol.children.iter().flatten() {
+            self.insert(uri, child, Some(&symbol.name));
+        }
+    }
+
+    /// Find symbols whose names match the query, with best matches first. The query matches a
+    /// name if all its characters appear in the name in the same order (ignoring case).
+    #[allow(deprecated)]
+    pub fn query(&self, query: &str) -> Vec<SymbolInformation> {
+        let mut matches = self
+            .0
+            .iter()
+            .filter_map(|(name, symbols)| {
+                fuzzy_match_score(query, name).map(|score| (score, name, symbols))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, name, _)| (*score, *name));
+        matches
+            .into_iter()
+            .flat_map(|(_, name, symbols)| {
+                symbols.iter().map(move |s| SymbolInformation {
+                    name: name.clone(),
+                    kind: s.kind,
+                    tags: None,
+                    deprecated: None,
+                    location: s.location.clone(),
+                    container_name: s.container_name.clone(),
+                })
+            })
+            .take(MAX_WORKSPACE_SYMBOLS)
+            .collect()
+    }
+}
+
+/// Computes how well a query matches a name (lower score means a better match), or returns `None`
+/// if the characters of the query do not appear (ignoring case) in the name in the same order.
+/// Exact and prefix matches are preferred, followed by matches with fewer gaps between the matched
+/// characters.
+fn fuzzy_match_score(query: &str, name: &str) -> Option<usize> {
+    let query = query.to_lowercase();
+    let name = name.to_lowercase();
+    if query == name {
+        return Some(0);
+    }
+    if name.starts_with(&query) {
+        return Some(1);
+    }
+    let mut score = 2;
+    let mut name_chars = name.chars();
+    for q in query.chars() {
+        let mut gap = 0;
+        loop {
+            match name_chars.next() {
+                Some(c) if c == q => break,
+                Some(_) => gap += 1,
+                None => return None,
+            }
+        }
+        if gap > 0 {
+            score += 1;
+        }
+    }
+    Some(score)
+}
+
+impl Symbolicator {
+    /// Main driver to get symbols for the whole package. Returned symbols is an option as only the
+    /// correctly computed symbols should be a replacement for the old set - if symbols are not
+    /// actually (re)computed and the diagnostics are returned, the old symbolic information should
+    /// be retained even if it's getting out-of-date.
+    pub fn get_symbols(
+        pkg_path: &Path,
+    ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
+        Self::get_symbols_with_overlay(pkg_path, &BTreeMap::new(), &BuildConfig::default(), None)
+    }
+
+    /// Same as `get_symbols` but uses the given contents (keyed by canonical file paths) in place
+    /// of the on-disk contents of the respective source files and builds the package with the
+    /// given configuration (always in test mode). If a cache directory is given,
+    /// symbols are loaded from there if the package's sources did not change since they were
+    /// cached, and stored there otherwise (unless they were computed for unsaved contents).
+    pub fn get_symbols_with_overlay(
+        pkg_path: &Path,
+        source_overlay: &BTreeMap<PathBuf, String>,
+        build_config: &BuildConfig,
+        cache_dir: Option<&Path>,
+    ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
+        Self::symbolicate(
+            pkg_path,
+            source_overlay,
+            build_config,
+            cache_dir,
+            None,
+            &|| false,
+        )
+    }
+
+    /// Same as `get_symbols_with_overlay` but only recompiles files affected by changes made since
+    /// the given base was compiled (if there is one), symbolicating modules of the remaining files
+    /// as they were compiled in the base. Whenever the whole package is compiled, the base is
+    /// replaced. Symbolication is abandoned with a `SymbolicationCancelled` error as soon as the
+    /// given function reports that it has been cancelled.
+    pub fn get_symbols_incremental(
+        pkg_path: &Path,
+        source_overlay: &BTreeMap<PathBuf, String>,
+        build_config: &BuildConfig,
+        cache_dir: Option<&Path>,
+        base: &mut Option<CompilationBase>,
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
+        Self::symbolicate(
+            pkg_path,
+            source_overlay,
+            build_config,
+            cache_dir,
+            Some(base),
+            is_cancelled,
+        )
+    }
+
+    /// Computes symbols, compiling incrementally if given a place for the compilation base
+    fn symbolicate(
+        pkg_path: &Path,
+        source_overlay: &BTreeMap<PathBuf, String>,
+        build_config: &BuildConfig,
+        cache_dir: Option<&Path>,
+        base: Option<&mut Option<CompilationBase>>,
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
+        let build_config = BuildConfig {
+            test_mode: true,
+            install_dir: Some(tempdir().unwrap().path().to_path_buf()),
+            ..build_config.clone()
+        };
+        // symbols depend on the build configuration as much as on the sources
+        let cache_config = BuildConfig {
+            install_dir: None,
+            ..build_config.clone()
+        };
+
+        info!("symbolicating {:?}", pkg_path);
 
         let resolution_graph = build_config.resolution_graph_for_package(pkg_path)?;
 
+        // collect all named addresses used in the package graph
+        let named_addresses = resolution_graph
+            .package_table
+            .values()
+            .flat_map(|pkg| pkg.resolution_table.keys().copied())
+            .collect::<BTreeSet<_>>();
+
         // get source files to be able to correlate positions (in terms of byte offsets) with actual
         // file locations (in terms of line/column numbers)
-        let source_files = &resolution_graph.file_sources();
+        let source_files = &resolution_graph
+            .file_sources()
+            .into_values()
+            .map(
+                |(fname, source)| match source_overlay.get(&canonical_path(&fname)) {
+                    Some(contents) => (FileHash::new(contents), (fname, contents.clone())),
+                    None => (FileHash::new(&source), (fname, source)),
+                },
+            )
+            .collect::<BTreeMap<_, _>>();
         let mut files = SimpleFiles::new();
         let mut file_id_mapping = HashMap::new();
         let mut file_name_mapping = BTreeMap::new();
         for (fhash, (fname, source)) in source_files {
             let id = files.add(*fname, source.clone());
             file_id_mapping.insert(*fhash, id);
-            file_name_mapping.insert(*fhash, *fname);
+            // dependency paths resolved by the package system (e.g., relative paths of local
+            // dependencies or paths of git dependencies downloaded to the package cache) are not
+            // necessarily canonical - use real file paths so that locations reported to the client
+            // refer to the same files that the client reports back to us
+            let fpath = canonical_path(fname);
+            file_name_mapping.insert(*fhash, Symbol::from(fpath.to_string_lossy().as_ref()));
+        }
+
+        let cache_path = cache_dir.map(|dir| dir.join(SYMBOLS_CACHE_FILE));
+        let cache_key = symbols_cache_key(&cache_config, &resolution_graph, &file_name_mapping);
+        if let Some(cache_path) = &cache_path {
+            if let Some(symbols) = cache::load::<Symbols>(cache_path, &cache_key) {
+                info!("loaded symbols from {:?}", cache_path);
+                let mut lsp_diagnostics = lsp_empty_diagnostics(&symbols.file_name_mapping);
+                lsp_diagnostics.extend(symbols.warnings.clone());
+                return Ok((Some(symbols), lsp_diagnostics));
+            }
         }
 
-        let build_plan = BuildPlan::create(resolution_graph)?;
+        let root_package = resolution_graph.root_package.package.name;
+        let address_declarations = resolution_graph.package_table[&root_package]
+            .address_declarations
+            .iter()
+            .filter_map(|(name, decl)| {
+                let manifest_path = fs::canonicalize(&decl.manifest_path).ok()?;
+                let manifest = fs::read_to_string(&manifest_path).ok()?;
+                let range = Range {
+                    start: buffer_position(&manifest, decl.span.start),
+                    end: buffer_position(&manifest, decl.span.end),
+                };
+                let uri = Url::from_file_path(manifest_path).ok()?;
+                Some((*name, Location { uri, range }))
+            })
+            .collect();
+        // named addresses and modules known to the package resolver complement modules indexed
+        // after compilation below
+        let package_index = package_module_index(&resolution_graph, source_files);
+        let config_key = symbols_cache_key(&cache_config, &resolution_graph, &BTreeMap::new());
+        // the base along with files recompiled against it and files of the base they supersede
+        let mut incremental = base.as_ref().and_then(|b| b.as_ref()).and_then(|base| {
+            let (recompiled, superseded) = base.recompiled_files(&config_key, source_files)?;
+            Some((base, recompiled, superseded))
+        });
+        // results of all compiler passes are needed to compile against them later
+        let mut build_base = base.is_some() && incremental.is_none();
+        if is_cancelled() {
+            return Err(SymbolicationCancelled.into());
+        }
+        let mut parsed_ast = None;
+        let mut expansion_ast = None;
         let mut typed_ast = None;
         let mut diagnostics = None;
-        build_plan.compile_with_driver(&mut std::io::sink(), |compiler| {
-            let (files, compilation_result) = compiler.run::<PASS_TYPING>()?;
-            let (_, compiler) = match compilation_result {
-                Ok(v) => v,
-                Err(diags) => {
-                    diagnostics = Some(diags);
-                    eprintln!("typed AST compilation failed");
-                    return Ok((files, vec![]));
+        let mut base_program = None;
+        // files with errors (canonical paths) are compiled again as if they were empty, so that
+        // symbols of the rest of the package are not held back by them
+        let mut broken_files = BTreeSet::new();
+        // diagnostics of all compilation attempts, except for those likely caused by excluding
+        // broken files in later attempts
+        let mut compiler_diagnostics = vec![];
+        // warnings of the successful compilation attempt
+        let mut warnings = vec![];
+        // constants of the successful compilation attempt
+        let mut constants = vec![];
+        let compiled = loop {
+            let mut attempt_overlay = source_overlay.clone();
+            attempt_overlay.extend(
+                broken_files
+                    .iter()
+                    .map(|f: &PathBuf| (f.clone(), String::new())),
+            );
+            let build_plan = BuildPlan::create(resolution_graph.clone())?;
+            build_plan.compile_with_driver(&mut std::io::sink(), |compiler| {
+                let (files, compilation_result) = compiler
+                    .set_source_overlay(attempt_overlay)
+                    .set_pre_compiled_lib_opt(
+                        incremental.as_ref().map(|(base, _, _)| &base.program),
+                    )
+                    .run::<PASS_PARSER>()?;
+                let (_, compiler) = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("parsed AST compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                debug!("compiled to parsed AST");
+                if is_cancelled() {
+                    return Err(SymbolicationCancelled.into());
                 }
-            };
-            eprintln!("compiled to typed AST");
-            let (compiler, typed_program) = compiler.into_ast();
-            typed_ast = Some(typed_program.clone());
-            eprintln!("compiling to bytecode");
-            let compilation_result = compiler.at_typing(typed_program).build();
-            let (units, _) = match compilation_result {
-                Ok(v) => v,
-                Err(diags) => {
-                    diagnostics = Some(diags);
-                    eprintln!("bytecode compilation failed");
-                    return Ok((files, vec![]));
+                let (compiler, parsed_program) = compiler.into_ast();
+                parsed_ast = Some(parsed_program.clone());
+                // only files affected by changes are compiled any further
+                let parsed_program = match &incremental {
+                    Some((_, recompiled, _)) => retain_definitions(parsed_program, recompiled),
+                    None => parsed_program,
+                };
+                let compilation_result = compiler.at_parser(parsed_program).run::<PASS_EXPANSION>();
+                let compiler = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("expansion AST compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                debug!("compiled to expansion AST");
+                let (compiler, expansion_program) = compiler.into_ast();
+                expansion_ast = Some(expansion_program.clone());
+                let compilation_result = compiler
+                    .at_expansion(expansion_program)
+                    .run::<PASS_NAMING>();
+                let compiler = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("naming AST compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                let (compiler, naming_program) = compiler.into_ast();
+                let naming_ast = build_base.then(|| naming_program.clone());
+                let compilation_result = compiler.at_naming(naming_program).run::<PASS_TYPING>();
+                let compiler = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("typed AST compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                debug!("compiled to typed AST");
+                if is_cancelled() {
+                    return Err(SymbolicationCancelled.into());
+                }
+                let (compiler, typed_program) = compiler.into_ast();
+                typed_ast = Some(typed_program.clone());
+                debug!("compiling to bytecode");
+                let compilation_result = compiler.at_typing(typed_program).run::<PASS_HLIR>();
+                let compiler = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("bytecode compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                let (compiler, hlir_program) = compiler.into_ast();
+                let hlir_ast = build_base.then(|| hlir_program.clone());
+                let compilation_result = compiler.at_hlir(hlir_program).run::<PASS_CFGIR>();
+                let compiler = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("bytecode compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                let (compiler, cfgir_program) = compiler.into_ast();
+                constants = constant_values(&cfgir_program);
+                let cfgir_ast = build_base.then(|| cfgir_program.clone());
+                let compilation_result = compiler.at_cfgir(cfgir_program).build();
+                let units = match compilation_result {
+                    Ok((units, unit_warnings)) => {
+                        warnings = unit_warnings.into_codespan_format();
+                        units
+                    }
+                    Err(diags) => {
+                        diagnostics = Some(diags);
+                        debug!("bytecode compilation failed");
+                        return Ok((files, vec![]));
+                    }
+                };
+                debug!("compiled to bytecode");
+                if let (Some(naming), Some(hlir), Some(cfgir)) = (naming_ast, hlir_ast, cfgir_ast) {
+                    base_program = Some(FullyCompiledProgram {
+                        files: files.clone(),
+                        parser: parsed_ast.clone().unwrap(),
+                        expansion: expansion_ast.clone().unwrap(),
+                        naming,
+                        typing: typed_ast.clone().unwrap(),
+                        hlir,
+                        cfgir,
+                        compiled: units.clone(),
+                    });
                 }
+                Ok((files, units))
+            })?;
+            debug_assert!(typed_ast.is_some() || diagnostics.is_some());
+            let attempt_diagnostics = match diagnostics.take() {
+                Some(diags) => diags.into_codespan_format(),
+                None => break true,
             };
-            eprintln!("compiled to bytecode");
-            Ok((files, units))
-        })?;
-
-        debug_assert!(typed_ast.is_some() || diagnostics.is_some());
-        if let Some(compiler_diagnostics) = diagnostics {
-            let lsp_diagnostics = lsp_diagnostics(
-                &compiler_diagnostics.into_codespan_format(),
-                &files,
-                &file_id_mapping,
-                &file_name_mapping,
+            let newly_broken = attempt_diagnostics
+                .iter()
+                .filter(|(severity, ..)| *severity >= Severity::Error)
+                .filter_map(|(_, _, (loc, _), _, _)| file_name_mapping.get(&loc.file_hash()))
+                .map(|fname| PathBuf::from(fname.as_str()))
+                .filter(|fpath| !broken_files.contains(fpath))
+                .collect::<BTreeSet<_>>();
+            // errors in files referring to modules of files excluded in previous attempts are
+            // most likely caused by the exclusion
+            let excluded_modules = source_files
+                .values()
+                .filter(|(fname, _)| broken_files.contains(&canonical_path(fname)))
+                .flat_map(|(_, source)| module_names(source).0)
+                .collect::<BTreeSet<_>>();
+            compiler_diagnostics.extend(attempt_diagnostics.into_iter().filter(
+                |(severity, _, (loc, _), _, _)| {
+                    // warnings about files that are compiled again are reported by later attempts
+                    if *severity < Severity::Error
+                        && !newly_broken.is_empty()
+                        && file_name_mapping
+                            .get(&loc.file_hash())
+                            .map_or(false, |fname| {
+                                !newly_broken.contains(Path::new(fname.as_str()))
+                            })
+                    {
+                        return false;
+                    }
+                    source_files
+                        .get(&loc.file_hash())
+                        .map_or(true, |(_, source)| {
+                            module_names(source).1.is_disjoint(&excluded_modules)
+                        })
+                },
+            ));
+            // errors persisting without any new files to blame
+            if newly_broken.is_empty() {
+                break false;
+            }
+            broken_files.extend(newly_broken);
+            info!(
+                "compiling again without {} file(s) with errors",
+                broken_files.len()
             );
-            return Ok((None, lsp_diagnostics));
+            // a partial compilation is neither incremental nor suitable as a base
+            incremental = None;
+            build_base = false;
+            parsed_ast = None;
+            expansion_ast = None;
+            typed_ast = None;
+            base_program = None;
+        };
+
+        let compiler_diagnostics = lsp_diagnostics(
+            &compiler_diagnostics,
+            &files,
+            &file_id_mapping,
+            &file_name_mapping,
+        );
+        // files without problems are included so that their stale diagnostics get cleared
+        let mut file_diagnostics = lsp_empty_diagnostics(&file_name_mapping);
+        file_diagnostics.extend(compiler_diagnostics);
+        if !compiled {
+            return Ok((None, file_diagnostics));
         }
 
-        let modules = &typed_ast.unwrap().modules;
+        // symbolication itself may take a while as well
+        if is_cancelled() {
+            return Err(SymbolicationCancelled.into());
+        }
+        let mut typed_program = typed_ast.unwrap();
+        let mut expansion_program = expansion_ast.unwrap();
+        if let Some((base, _, superseded)) = &incremental {
+            // as are their warnings and constants
+            constants.extend(
+                constant_values(&base.program.cfgir)
+                    .into_iter()
+                    .filter(|(name_loc, _, _)| !superseded.contains(&name_loc.file_hash())),
+            );
+            warnings.extend(
+                base.warnings
+                    .iter()
+                    .filter(|(_, _, (loc, _), _, _)| !superseded.contains(&loc.file_hash()))
+                    .cloned(),
+            );
+            // modules of files that were not recompiled are taken from the base
+            for (mident, mdef) in base.program.typing.modules.key_cloned_iter() {
+                if !superseded.contains(&mident.loc.file_hash())
+                    && !typed_program.modules.contains_key(&mident)
+                {
+                    typed_program.modules.add(mident, mdef.clone()).unwrap();
+                }
+            }
+            for (mident, mdef) in base.program.expansion.modules.key_cloned_iter() {
+                if !superseded.contains(&mident.loc.file_hash())
+                    && !expansion_program.modules.contains_key(&mident)
+                {
+                    expansion_program.modules.add(mident, mdef.clone()).unwrap();
+                }
+            }
+            info!(
+                "recompiled {} out of {} files",
+                incremental.as_ref().map_or(0, |(_, r, _)| r.len()),
+                source_files.len()
+            );
+        }
+        let modules = &typed_program.modules;
+        let expansion_modules = expansion_program
+            .modules
+            .into_iter()
+            .map(|(module_ident, module_def)| (module_ident.value, module_def))
+            .collect::<BTreeMap<_, _>>();
 
         let mut mod_outer_defs = BTreeMap::new();
         let mut mod_use_defs = BTreeMap::new();
         for (pos, module_ident, module_def) in modules {
+            let specs = expansion_modules
+                .get(module_ident)
+                .map_or(&[][..], |module_def| module_def.specs.as_slice());
             let (defs, symbols) =
-                Self::get_mod_outer_defs(&pos, module_def, &files, &file_id_mapping);
+                Self::get_mod_outer_defs(&pos, module_def, specs, &files, &file_id_mapping);
             mod_outer_defs.insert(*module_ident, defs);
             mod_use_defs.insert(*module_ident, symbols);
         }
@@ -573,6 +2190,9 @@ impl Symbolicator {
             file_id_mapping,
             type_params: BTreeMap::new(),
             current_mod: None,
+            fun_signatures: BTreeMap::new(),
+            expansion_modules,
+            current_fun: None,
         };
 
         let mut references = BTreeMap::new();
@@ -588,21 +2208,274 @@ impl Symbolicator {
             };
 
             file_use_defs
-                .entry(
-                    fs::canonicalize(fpath.as_str())
-                        .unwrap_or_else(|_| PathBuf::from(fpath.as_str())),
-                )
+                .entry(canonical_path(fpath))
                 .or_insert_with(UseDefMap::new)
                 .extend(use_defs.elements());
         }
 
-        let lsp_diagnostics = lsp_empty_diagnostics(&file_name_mapping);
+        // specifications may refer to functions of any module (whose signatures are only known
+        // once the module has been processed), so they are processed last
+        for (pos, module_ident, module_def) in modules {
+            let fpath = match source_files.get(&pos.file_hash()) {
+                Some((p, _)) => p,
+                None => continue,
+            };
+            symbolicator.current_mod = Some(sp(pos, *module_ident));
+            let use_defs = file_use_defs
+                .entry(canonical_path(fpath))
+                .or_insert_with(UseDefMap::new);
+            symbolicator.mod_spec_symbols(module_def, &mut references, use_defs);
+        }
+
+        let parsed_ast = parsed_ast.unwrap();
+        // dependencies are compiled from sources as well, so files are attributed to packages
+        let package_files = parsed_ast
+            .source_definitions
+            .iter()
+            .filter(|pkg_def| pkg_def.package == Some(root_package))
+            .map(|pkg_def| definition_file(&pkg_def.def))
+            .collect::<BTreeSet<_>>();
+        // warnings about dependencies cannot be acted upon
+        let package_warnings = warnings
+            .iter()
+            .filter(|(_, _, (loc, _), _, _)| package_files.contains(&loc.file_hash()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let package_warnings = lsp_diagnostics(
+            &package_warnings,
+            &symbolicator.files,
+            &symbolicator.file_id_mapping,
+            &file_name_mapping,
+        );
+        for (fname, diags) in &package_warnings {
+            file_diagnostics
+                .entry(*fname)
+                .or_insert_with(Vec::new)
+                .extend(diags.iter().cloned());
+        }
+
+        let file_outlines = Self::get_file_outlines(
+            &parsed_ast,
+            &symbolicator.files,
+            &symbolicator.file_id_mapping,
+            &file_name_mapping,
+        );
+
+        let symbol_index = SymbolIndex::new(&file_outlines);
+
+        let mut file_tokens = BTreeMap::new();
+        for (fhash, (fname, source)) in source_files {
+            let fpath = canonical_path(fname);
+            let tokens = Self::get_file_tokens(
+                *fhash,
+                source,
+                file_use_defs.get(&fpath),
+                &named_addresses,
+                &symbolicator.files,
+                &symbolicator.file_id_mapping,
+            );
+            file_tokens.insert(fpath, tokens);
+        }
+
+        let mut hint_collector =
+            InlayHintCollector::new(&symbolicator.files, &symbolicator.file_id_mapping);
+        for (_, _, module_def) in modules {
+            hint_collector.mod_hints(module_def);
+        }
+        let mut file_inlay_hints = BTreeMap::new();
+        for (fhash, hints) in hint_collector.finish() {
+            if let Some((fname, _)) = source_files.get(&fhash) {
+                file_inlay_hints.insert(canonical_path(fname), hints);
+            }
+        }
+
+        let mut type_collector =
+            ExpTypeCollector::new(&symbolicator.files, &symbolicator.file_id_mapping);
+        for (_, _, module_def) in modules {
+            type_collector.mod_types(module_def);
+        }
+        let mut file_exp_types = BTreeMap::new();
+        for (fhash, types) in type_collector.finish() {
+            if let Some((fname, _)) = source_files.get(&fhash) {
+                file_exp_types.insert(canonical_path(fname), types);
+            }
+        }
+
+        let mut import_index = ImportIndex::default();
+        let mut module_index = ModuleIndex::default();
+        let mut struct_fields = BTreeMap::new();
+        for (_, module_ident, module_def) in modules {
+            let mod_name = module_ident.module.value();
+            let qualified_mod_name = format!(
+                "{}::{}",
+                addr_to_ide_string(&module_ident.address),
+                mod_name
+            );
+            let mut members = vec![];
+            for (_, name, fun) in &module_def.functions {
+                let public = fun.visibility != Visibility::Internal;
+                if public {
+                    import_index.insert_member(name, format!("{}::{}", qualified_mod_name, name));
+                }
+                members.push(ModuleMember {
+                    name: name.to_string(),
+                    kind: MemberKind::Function,
+                    public,
+                });
+            }
+            for (_, name, _) in &module_def.constants {
+                members.push(ModuleMember {
+                    name: name.to_string(),
+                    kind: MemberKind::Constant,
+                    public: false,
+                });
+            }
+            for (pos, name, def) in &module_def.structs {
+                import_index.insert_member(name, format!("{}::{}", qualified_mod_name, name));
+                members.push(ModuleMember {
+                    name: name.to_string(),
+                    kind: MemberKind::Struct,
+                    public: true,
+                });
+                if let StructFields::Defined(fields) = &def.fields {
+                    let mut fields = fields
+                        .iter()
+                        .map(|(_, fname, (idx, t))| (*idx, *fname, t.clone()))
+                        .collect::<Vec<_>>();
+                    fields.sort_by_key(|(idx, _, _)| *idx);
+                    struct_fields
+                        .entry(*name)
+                        .or_insert_with(Vec::new)
+                        .push(StructFieldsInfo {
+                            mod_ident: *module_ident,
+                            fhash: pos.file_hash(),
+                            type_params: def
+                                .type_parameters
+                                .iter()
+                                .map(|tp| tp.param.clone())
+                                .collect(),
+                            fields: fields.into_iter().map(|(_, f, t)| (f, t)).collect(),
+                        });
+                }
+            }
+            import_index.insert_module(&mod_name, qualified_mod_name);
+            let addresses = module_addresses(&module_ident.address);
+            module_index.insert_module(&addresses, &mod_name, members);
+        }
+        module_index.extend(package_index);
+
+        let mut def_docs = BTreeMap::new();
+        for (mod_ident, mod_defs) in &symbolicator.mod_outer_defs {
+            let source = match source_files.get(&mod_defs.fhash) {
+                Some((_, source)) => source,
+                None => continue,
+            };
+            let addresses = module_addresses(&mod_ident.address);
+            let mod_name = mod_ident.module.value();
+            let def_starts = mod_defs
+                .structs
+                .iter()
+                .map(|(name, def)| (name, &def.name_start))
+                .chain(mod_defs.constants.iter())
+                .chain(mod_defs.functions.iter());
+            for (name, start) in def_starts {
+                if let Some(doc) = doc_comment(source, start.line) {
+                    module_index.insert_doc(&addresses, &mod_name, name, &doc);
+                    let def_loc = DefLoc {
+                        fhash: mod_defs.fhash,
+                        start: *start,
+                    };
+                    def_docs.insert(def_loc, doc);
+                }
+            }
+        }
+
+        let mut const_values = BTreeMap::new();
+        let mut abort_codes = BTreeMap::new();
+        for (name_loc, decl_loc, value) in &constants {
+            let fhash = name_loc.file_hash();
+            let decl = source_files.get(&fhash).and_then(|(_, source)| {
+                source.get(decl_loc.start() as usize..decl_loc.end() as usize)
+            });
+            let start =
+                Self::get_start_loc(name_loc, &symbolicator.files, &symbolicator.file_id_mapping);
+            if let (Some(decl), Some(start)) = (decl, start) {
+                let def_loc = DefLoc { fhash, start };
+                const_values.insert(def_loc, constant_declaration(decl, value));
+                if let Some(code) = abort_code(value) {
+                    abort_codes
+                        .entry(code)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(def_loc);
+                }
+            }
+        }
+
+        let mut hash_dependents = BTreeMap::new();
+        for (def_loc, uses) in &references {
+            for use_loc in uses.iter().filter(|u| u.fhash != def_loc.fhash) {
+                hash_dependents
+                    .entry(def_loc.fhash)
+                    .or_insert_with(BTreeSet::new)
+                    .insert(use_loc.fhash);
+            }
+        }
+        let file_path = |fhash: &FileHash| {
+            file_name_mapping
+                .get(fhash)
+                .map(|fname| PathBuf::from(fname.as_str()))
+        };
+        let file_dependents = hash_dependents
+            .iter()
+            .filter_map(|(fhash, dependents)| {
+                Some((
+                    file_path(fhash)?,
+                    dependents.iter().filter_map(file_path).collect(),
+                ))
+            })
+            .collect();
+
         let symbols = Symbols {
             references,
             file_use_defs,
             file_name_mapping,
+            file_outlines,
+            symbol_index,
+            file_tokens,
+            file_inlay_hints,
+            file_exp_types,
+            fun_signatures: symbolicator.fun_signatures,
+            import_index,
+            module_index,
+            struct_fields,
+            def_docs,
+            const_values,
+            abort_codes,
+            package_files,
+            address_declarations,
+            file_dependents,
+            broken_files,
+            warnings: package_warnings,
         };
-        Ok((Some(symbols), lsp_diagnostics))
+        if let (Some(base), Some(program)) = (base, base_program) {
+            *base = Some(CompilationBase::new(
+                config_key,
+                source_files,
+                hash_dependents,
+                warnings,
+                program,
+            ));
+        }
+        if let Some(cache_path) = &cache_path {
+            // symbols computed for unsaved contents would be invalidated by the next edit anyway,
+            // while symbols missing broken files are only meant to be used until they are fixed
+            if source_overlay.is_empty() && symbols.broken_files.is_empty() {
+                if let Err(err) = cache::store(cache_path, &cache_key, &symbols) {
+                    warn!("could not store symbols in {:?}: {:?}", cache_path, err);
+                }
+            }
+        }
+        Ok((Some(symbols), file_diagnostics))
     }
 
     /// Get empty symbols
@@ -611,7 +2484,322 @@ impl Symbolicator {
             file_use_defs: BTreeMap::new(),
             references: BTreeMap::new(),
             file_name_mapping: BTreeMap::new(),
+            file_outlines: BTreeMap::new(),
+            symbol_index: SymbolIndex::default(),
+            file_tokens: BTreeMap::new(),
+            file_inlay_hints: BTreeMap::new(),
+            file_exp_types: BTreeMap::new(),
+            fun_signatures: BTreeMap::new(),
+            import_index: ImportIndex::default(),
+            module_index: ModuleIndex::default(),
+            struct_fields: BTreeMap::new(),
+            def_docs: BTreeMap::new(),
+            const_values: BTreeMap::new(),
+            abort_codes: BTreeMap::new(),
+            package_files: BTreeSet::new(),
+            address_declarations: BTreeMap::new(),
+            file_dependents: BTreeMap::new(),
+            broken_files: BTreeSet::new(),
+            warnings: BTreeMap::new(),
+        }
+    }
+
+    /// Get semantic tokens for a file. Tokens for identifiers whose definitions are known come from
+    /// the use-def map while named addresses (and modules), which are not part of this map, are
+    /// found by lexing the file's source.
+    fn get_file_tokens(
+        fhash: FileHash,
+        source: &str,
+        use_defs: Option<&UseDefMap>,
+        named_addresses: &BTreeSet<Symbol>,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) -> Vec<FileToken> {
+        let mut tokens = vec![];
+        if let Some(use_defs) = use_defs {
+            for (line, uses) in &use_defs.0 {
+                tokens.extend(uses.iter().map(|u| u.semantic_token(*line, fhash)));
+            }
+        }
+
+        let mut lexer = Lexer::new(source, fhash);
+        if lexer.advance().is_ok() {
+            while lexer.peek() != Tok::EOF {
+                // a name followed by `::` is either a named address or a module
+                if lexer.peek() == Tok::Identifier
+                    && matches!(lexer.lookahead(), Ok(Tok::ColonColon))
+                {
+                    let name = lexer.content();
+                    let token_type = if named_addresses.contains(&Symbol::from(name)) {
+                        NAMED_ADDRESS_TOKEN
+                    } else {
+                        NAMESPACE_TOKEN
+                    };
+                    let start = get_loc(
+                        &fhash,
+                        lexer.start_loc() as ByteIndex,
+                        files,
+                        file_id_mapping,
+                    );
+                    if let Some(start) = start {
+                        tokens.push(FileToken {
+                            start,
+                            length: name.len() as u32,
+                            token_type,
+                            modifiers: 0,
+                        });
+                    }
+                }
+                if lexer.advance().is_err() {
+                    break;
+                }
+            }
+        }
+        tokens.sort();
+        tokens
+    }
+
+    /// Get outlines of all files in the package (and its dependencies) based on the parsed AST
+    fn get_file_outlines(
+        parsed_program: &P::Program,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+        file_name_mapping: &BTreeMap<FileHash, Symbol>,
+    ) -> BTreeMap<PathBuf, Vec<DocumentSymbol>> {
+        let mut file_outlines = BTreeMap::new();
+        let mut add_outline = |loc: &Loc, outline: Option<DocumentSymbol>| {
+            if let (Some(fpath), Some(outline)) = (file_name_mapping.get(&loc.file_hash()), outline)
+            {
+                file_outlines
+                    .entry(canonical_path(fpath))
+                    .or_insert_with(Vec::new)
+                    .push(outline);
+            }
+        };
+        for pkg_def in parsed_program
+            .source_definitions
+            .iter()
+            .chain(parsed_program.lib_definitions.iter())
+        {
+            match &pkg_def.def {
+                P::Definition::Module(mod_def) => {
+                    add_outline(
+                        &mod_def.loc,
+                        Self::module_outline(mod_def, None, files, file_id_mapping),
+                    );
+                }
+                P::Definition::Address(addr_def) => {
+                    for mod_def in &addr_def.modules {
+                        add_outline(
+                            &mod_def.loc,
+                            Self::module_outline(
+                                mod_def,
+                                Some(&addr_def.addr),
+                                files,
+                                file_id_mapping,
+                            ),
+                        );
+                    }
+                }
+                P::Definition::Script(script) => {
+                    add_outline(
+                        &script.loc,
+                        Self::script_outline(script, files, file_id_mapping),
+                    );
+                }
+            }
+        }
+        file_outlines
+    }
+
+    /// Get outline of a module
+    fn module_outline(
+        mod_def: &P::ModuleDefinition,
+        enclosing_addr: Option<&P::LeadingNameAccess>,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) -> Option<DocumentSymbol> {
+        let name = match mod_def.address.as_ref().or(enclosing_addr) {
+            Some(sp!(_, addr)) => format!("{}::{}", addr, mod_def.name),
+            None => format!("{}", mod_def.name),
+        };
+        let mut children = vec![];
+        for member in &mod_def.members {
+            let child = match member {
+                P::ModuleMember::Function(fun) => Self::outline_symbol(
+                    fun.name.value().to_string(),
+                    SymbolKind::Function,
+                    &fun.loc,
+                    &fun.name.loc(),
+                    vec![],
+                    files,
+                    file_id_mapping,
+                ),
+                P::ModuleMember::Struct(struct_def) => {
+                    Self::struct_outline(struct_def, files, file_id_mapping)
+                }
+                P::ModuleMember::Constant(c) => Self::outline_symbol(
+                    c.name.value().to_string(),
+                    SymbolKind::Constant,
+                    &c.loc,
+                    &c.name.loc(),
+                    vec![],
+                    files,
+                    file_id_mapping,
+                ),
+                P::ModuleMember::Spec(spec) => Self::spec_outline(spec, files, file_id_mapping),
+                P::ModuleMember::Use(_) | P::ModuleMember::Friend(_) => None,
+            };
+            children.extend(child);
+        }
+        Self::outline_symbol(
+            name,
+            SymbolKind::Module,
+            &mod_def.loc,
+            &mod_def.name.loc(),
+            children,
+            files,
+            file_id_mapping,
+        )
+    }
+
+    /// Get outline of a script
+    fn script_outline(
+        script: &P::Script,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) -> Option<DocumentSymbol> {
+        let mut children = vec![];
+        for c in &script.constants {
+            children.extend(Self::outline_symbol(
+                c.name.value().to_string(),
+                SymbolKind::Constant,
+                &c.loc,
+                &c.name.loc(),
+                vec![],
+                files,
+                file_id_mapping,
+            ));
+        }
+        children.extend(Self::outline_symbol(
+            script.function.name.value().to_string(),
+            SymbolKind::Function,
+            &script.function.loc,
+            &script.function.name.loc(),
+            vec![],
+            files,
+            file_id_mapping,
+        ));
+        for spec in &script.specs {
+            children.extend(Self::spec_outline(spec, files, file_id_mapping));
+        }
+        Self::outline_symbol(
+            "script".to_string(),
+            SymbolKind::Module,
+            &script.loc,
+            &script.function.name.loc(),
+            children,
+            files,
+            file_id_mapping,
+        )
+    }
+
+    /// Get outline of a struct (including its fields)
+    fn struct_outline(
+        struct_def: &P::StructDefinition,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) -> Option<DocumentSymbol> {
+        let mut children = vec![];
+        if let P::StructFields::Defined(fields) = &struct_def.fields {
+            for (field, _) in fields {
+                children.extend(Self::outline_symbol(
+                    field.value().to_string(),
+                    SymbolKind::Field,
+                    &field.loc(),
+                    &field.loc(),
+                    vec![],
+                    files,
+                    file_id_mapping,
+                ));
+            }
+        }
+        Self::outline_symbol(
+            struct_def.name.value().to_string(),
+            SymbolKind::Struct,
+            &struct_def.loc,
+            &struct_def.name.loc(),
+            children,
+            files,
+            file_id_mapping,
+        )
+    }
+
+    /// Get outline of a spec block (including spec functions defined in it)
+    fn spec_outline(
+        sp!(loc, spec): &P::SpecBlock,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) -> Option<DocumentSymbol> {
+        use P::SpecBlockTarget_ as T;
+        let name = match &spec.target.value {
+            T::Code => "spec".to_string(),
+            T::Module => "spec module".to_string(),
+            T::Member(name, _) => format!("spec {}", name),
+            T::Schema(name, _) => format!("spec schema {}", name),
+        };
+        let mut children = vec![];
+        for member in &spec.members {
+            if let P::SpecBlockMember_::Function { name, .. } = &member.value {
+                children.extend(Self::outline_symbol(
+                    name.value().to_string(),
+                    SymbolKind::Function,
+                    &member.loc,
+                    &name.loc(),
+                    vec![],
+                    files,
+                    file_id_mapping,
+                ));
+            }
         }
+        Self::outline_symbol(
+            name,
+            SymbolKind::Namespace,
+            loc,
+            &spec.target.loc,
+            children,
+            files,
+            file_id_mapping,
+        )
+    }
+
+    /// Create a single outline entry (returns `None` if source locations cannot be computed)
+    #[allow(deprecated)]
+    fn outline_symbol(
+        name: String,
+        kind: SymbolKind,
+        loc: &Loc,
+        name_loc: &Loc,
+        children: Vec<DocumentSymbol>,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) -> Option<DocumentSymbol> {
+        let range = get_range(loc, files, file_id_mapping)?;
+        let selection_range = get_range(name_loc, files, file_id_mapping)?;
+        Some(DocumentSymbol {
+            name,
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range,
+            children: if children.is_empty() {
+                None
+            } else {
+                Some(children)
+            },
+        })
     }
 
     /// Main AST traversal functions
@@ -620,6 +2808,7 @@ impl Symbolicator {
     fn get_mod_outer_defs(
         loc: &Loc,
         mod_def: &ModuleDefinition,
+        specs: &[EA::SpecBlock],
         files: &SimpleFiles<Symbol, String>,
         file_id_mapping: &HashMap<FileHash, usize>,
     ) -> (ModuleDefs, UseDefMap) {
@@ -685,12 +2874,68 @@ impl Symbolicator {
             functions.insert(*name, name_start);
         }
 
+        let mut schemas = BTreeMap::new();
+        let mut spec_funs = BTreeMap::new();
+        let mut spec_vars = BTreeMap::new();
+        for spec in specs {
+            if let EA::SpecBlockTarget_::Schema(name, type_params) = &spec.value.target.value {
+                let ident_type = IdentType::SpecType(format!(
+                    "schema {}{}",
+                    name.value,
+                    spec_type_params_to_ide_string(type_params)
+                ));
+                Self::add_spec_def(&mut schemas, name, ident_type, files, file_id_mapping);
+            }
+            for member in &spec.value.members {
+                match &member.value {
+                    EA::SpecBlockMember_::Function {
+                        name, signature, ..
+                    } => {
+                        let ident_type =
+                            IdentType::SpecType(spec_fun_to_ide_string(&name.value(), signature));
+                        Self::add_spec_def(
+                            &mut spec_funs,
+                            &name.0,
+                            ident_type,
+                            files,
+                            file_id_mapping,
+                        );
+                    }
+                    EA::SpecBlockMember_::Variable {
+                        is_global: true,
+                        name,
+                        type_parameters,
+                        type_,
+                        ..
+                    } => {
+                        let ident_type = IdentType::SpecType(format!(
+                            "global {}{}: {}",
+                            name.value,
+                            spec_type_params_to_ide_string(type_parameters),
+                            type_.value
+                        ));
+                        Self::add_spec_def(
+                            &mut spec_vars,
+                            name,
+                            ident_type,
+                            files,
+                            file_id_mapping,
+                        );
+                    }
+                    _ => (),
+                }
+            }
+        }
+
         let fhash = loc.file_hash();
         let module_defs = ModuleDefs {
             fhash,
             structs,
             constants,
             functions,
+            schemas,
+            spec_funs,
+            spec_vars,
         };
 
         let use_def_map = UseDefMap::new();
@@ -729,6 +2974,21 @@ impl Symbolicator {
                     .collect(),
             );
             let ident_type_def = self.ident_type_def_loc(&use_type);
+            self.fun_signatures.insert(
+                DefLoc {
+                    fhash: pos.file_hash(),
+                    start: name_start,
+                },
+                FunSignature {
+                    fun_type: use_type.clone(),
+                    param_names: fun
+                        .signature
+                        .parameters
+                        .iter()
+                        .map(|(v, _)| v.value())
+                        .collect(),
+                },
+            );
             use_defs.insert(
                 name_start.line,
                 UseDef::new(
@@ -740,9 +3000,12 @@ impl Symbolicator {
                     name,
                     use_type,
                     ident_type_def,
+                    DefKind::Function,
                 ),
             );
+            self.current_fun = Some(*name);
             self.fun_symbols(fun, references, use_defs);
+            self.current_fun = None;
         }
 
         for (pos, name, c) in &mod_def.constants {
@@ -761,6 +3024,7 @@ impl Symbolicator {
                     name,
                     ident_type,
                     ident_type_def,
+                    DefKind::Const,
                 ),
             );
         }
@@ -786,6 +3050,7 @@ impl Symbolicator {
                     name,
                     ident_type,
                     ident_type_def,
+                    DefKind::Struct,
                 ),
             );
 
@@ -824,6 +3089,7 @@ impl Symbolicator {
                         fname,
                         ident_type,
                         ident_type_def,
+                        DefKind::Field,
                     ),
                 );
             }
@@ -962,6 +3228,7 @@ impl Symbolicator {
                         *t.clone(),
                     );
                 } else {
+                    // assignment to a previously defined local
                     self.add_local_use_def(
                         &var.value(),
                         &var.loc(),
@@ -969,6 +3236,7 @@ impl Symbolicator {
                         scope,
                         use_defs,
                         *t.clone(),
+                        true,
                     )
                 }
             }
@@ -1048,6 +3316,7 @@ impl Symbolicator {
                 scope,
                 use_defs,
                 exp.ty.clone(),
+                false,
             ),
             E::Copy {
                 from_user: _,
@@ -1059,6 +3328,7 @@ impl Symbolicator {
                 scope,
                 use_defs,
                 exp.ty.clone(),
+                false,
             ),
             E::Use(v) => self.add_local_use_def(
                 &v.value(),
@@ -1067,6 +3337,7 @@ impl Symbolicator {
                 scope,
                 use_defs,
                 exp.ty.clone(),
+                false,
             ),
             E::Constant(mod_ident_opt, name) => self.add_const_use_def(
                 mod_ident_opt,
@@ -1172,13 +3443,14 @@ impl Symbolicator {
             E::TempBorrow(_, exp) => {
                 self.exp_symbols(exp, scope, references, use_defs);
             }
-            E::BorrowLocal(_, var) => self.add_local_use_def(
+            E::BorrowLocal(mut_, var) => self.add_local_use_def(
                 &var.value(),
                 &var.loc(),
                 references,
                 scope,
                 use_defs,
                 exp.ty.clone(),
+                *mut_,
             ),
             E::Cast(exp, t) => {
                 self.exp_symbols(exp, scope, references, use_defs);
@@ -1188,6 +3460,9 @@ impl Symbolicator {
                 self.exp_symbols(exp, scope, references, use_defs);
                 self.add_type_id_use_def(t, references, use_defs);
             }
+            E::Spec(spec_id, used_locals) => {
+                self.inline_spec_symbols(spec_id, used_locals, scope, references, use_defs);
+            }
 
             _ => (),
         }
@@ -1337,6 +3612,7 @@ impl Symbolicator {
                         &tname,
                         ident_type,
                         ident_type_def,
+                        DefKind::TypeParam,
                     ),
                 );
                 let exists = tp_scope.insert(tname, DefLoc { fhash, start });
@@ -1408,6 +3684,7 @@ impl Symbolicator {
                             use_name,
                             ident_type,
                             ident_type_def,
+                            DefKind::Const,
                         ),
                     );
                 }
@@ -1443,6 +3720,7 @@ impl Symbolicator {
                             use_name,
                             use_type.clone(),
                             self.ident_type_def_loc(&use_type),
+                            DefKind::Function,
                         ),
                     );
                 }
@@ -1480,6 +3758,7 @@ impl Symbolicator {
                             use_name,
                             ident_type,
                             ident_type_def,
+                            DefKind::Struct,
                         ),
                     );
                 }
@@ -1520,6 +3799,7 @@ impl Symbolicator {
                                     use_name,
                                     ident_type,
                                     ident_type_def,
+                                    DefKind::Field,
                                 ),
                             );
                         }
@@ -1558,6 +3838,7 @@ impl Symbolicator {
                                     &use_name,
                                     ident_type,
                                     ident_type_def,
+                                    DefKind::TypeParam,
                                 ),
                             );
                         }
@@ -1609,22 +3890,22 @@ impl Symbolicator {
                 // enter self-definition for def name
                 let ident_type = IdentType::RegularType(use_type);
                 let ident_type_def = self.ident_type_def_loc(&ident_type);
-                use_defs.insert(
-                    name_start.line,
-                    UseDef::new(
-                        references,
-                        pos.file_hash(),
-                        name_start,
-                        pos.file_hash(),
-                        name_start,
-                        name,
-                        ident_type,
-                        ident_type_def,
-                    ),
-                );
-            }
-            None => {
-                debug_assert!(false);
+                let mut use_def = UseDef::new(
+                    references,
+                    pos.file_hash(),
+                    name_start,
+                    pos.file_hash(),
+                    name_start,
+                    name,
+                    ident_type,
+                    ident_type_def,
+                    DefKind::Local,
+                );
+                use_def.write = true;
+                use_defs.insert(name_start.line, use_def);
+            }
+            None => {
+                debug_assert!(false);
             }
         }
     }
@@ -1639,6 +3920,7 @@ impl Symbolicator {
         scope: &OrdMap<Symbol, DefLoc>,
         use_defs: &mut UseDefMap,
         use_type: Type,
+        write: bool,
     ) {
         let name_start = match Self::get_start_loc(use_pos, &self.files, &self.file_id_mapping) {
             Some(v) => v,
@@ -1651,24 +3933,516 @@ impl Symbolicator {
         if let Some(def_loc) = scope.get(use_name) {
             let ident_type = IdentType::RegularType(use_type);
             let ident_type_def = self.ident_type_def_loc(&ident_type);
-            use_defs.insert(
-                name_start.line,
-                UseDef::new(
-                    references,
-                    use_pos.file_hash(),
-                    name_start,
-                    def_loc.fhash,
-                    def_loc.start,
-                    use_name,
-                    ident_type,
-                    ident_type_def,
-                ),
+            let mut use_def = UseDef::new(
+                references,
+                use_pos.file_hash(),
+                name_start,
+                def_loc.fhash,
+                def_loc.start,
+                use_name,
+                ident_type,
+                ident_type_def,
+                DefKind::Local,
             );
+            use_def.write = write;
+            use_defs.insert(name_start.line, use_def);
         } else {
             debug_assert!(false);
         }
     }
 
+    /// Records a definition of a specification-only entity
+    fn add_spec_def(
+        defs: &mut BTreeMap<Symbol, SpecDef>,
+        name: &Name,
+        ident_type: IdentType,
+        files: &SimpleFiles<Symbol, String>,
+        file_id_mapping: &HashMap<FileHash, usize>,
+    ) {
+        match Self::get_start_loc(&name.loc, files, file_id_mapping) {
+            Some(name_start) => {
+                defs.insert(
+                    name.value,
+                    SpecDef {
+                        name_start,
+                        ident_type,
+                    },
+                );
+            }
+            None => debug_assert!(false),
+        }
+    }
+
+    /// Get symbols for module-level specification blocks of the current module
+    fn mod_spec_symbols(
+        &self,
+        mod_def: &ModuleDefinition,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        let mod_ident = self.current_mod.unwrap().value;
+        let specs = match self.expansion_modules.get(&mod_ident) {
+            Some(mod_def) => &mod_def.specs,
+            None => return,
+        };
+        for spec in specs {
+            let mut scope = SpecScope::new();
+            if let EA::SpecBlockTarget_::Member(name, _) = &spec.value.target.value {
+                // add use of the specified function (or struct)
+                self.add_spec_access_use_def(
+                    &sp(name.loc, EA::ModuleAccess_::Name(*name)),
+                    &scope,
+                    references,
+                    use_defs,
+                );
+                // specification of a function refers to the function's parameters
+                if let Some(fun) = mod_def.functions.get_(&name.value) {
+                    for (pname, ptype) in &fun.signature.parameters {
+                        if let Some(start) =
+                            Self::get_start_loc(&pname.loc(), &self.files, &self.file_id_mapping)
+                        {
+                            let def_loc = DefLoc {
+                                fhash: pname.loc().file_hash(),
+                                start,
+                            };
+                            let ident_type = IdentType::RegularType(ptype.clone());
+                            scope.insert(pname.value(), (def_loc, ident_type));
+                        }
+                    }
+                }
+            }
+            self.spec_block_symbols(spec, scope, references, use_defs);
+        }
+    }
+
+    /// Get symbols for a specification block inside the body of the current function, which may
+    /// refer to the function's locals
+    fn inline_spec_symbols(
+        &self,
+        spec_id: &EA::SpecId,
+        used_locals: &BTreeMap<P::Var, Type>,
+        scope: &OrdMap<Symbol, DefLoc>,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        let spec = self
+            .current_fun
+            .zip(self.expansion_modules.get(&self.current_mod.unwrap().value))
+            .and_then(|(fun_name, mod_def)| mod_def.functions.get_(&fun_name))
+            .and_then(|fun| fun.specs.get(spec_id));
+        let spec = match spec {
+            Some(spec) => spec,
+            None => return,
+        };
+        let spec_scope = used_locals
+            .iter()
+            .filter_map(|(var, t)| {
+                let def_loc = scope.get(&var.value())?;
+                Some((var.value(), (*def_loc, IdentType::RegularType(t.clone()))))
+            })
+            .collect::<SpecScope>();
+        self.spec_block_symbols(spec, spec_scope, references, use_defs);
+    }
+
+    /// Get symbols for a specification block
+    fn spec_block_symbols(
+        &self,
+        spec: &EA::SpecBlock,
+        mut scope: SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        use EA::SpecBlockMember_ as M;
+
+        if let EA::SpecBlockTarget_::Schema(name, _) = &spec.value.target.value {
+            self.add_spec_outer_use_def(name, references, use_defs);
+        }
+        // variables declared in a block (e.g., variables of a schema) are visible in the whole
+        // block
+        for member in &spec.value.members {
+            if let M::Variable {
+                is_global: false,
+                name,
+                type_,
+                ..
+            } = &member.value
+            {
+                let ident_type = IdentType::SpecType(format!("{}: {}", name.value, type_.value));
+                self.add_spec_local_def(name, ident_type, &mut scope, references, use_defs);
+            }
+        }
+        for member in &spec.value.members {
+            match &member.value {
+                M::Condition {
+                    exp,
+                    additional_exps,
+                    ..
+                } => {
+                    for exp in std::iter::once(exp).chain(additional_exps) {
+                        self.spec_exp_symbols(exp, &scope, references, use_defs);
+                    }
+                }
+                M::Function {
+                    name,
+                    signature,
+                    body,
+                    ..
+                } => {
+                    self.add_spec_outer_use_def(&name.0, references, use_defs);
+                    let mut fun_scope = SpecScope::new();
+                    for (pname, ptype) in &signature.parameters {
+                        let ident_type = IdentType::SpecType(format!("{}: {}", pname, ptype.value));
+                        self.add_spec_local_def(
+                            &pname.0,
+                            ident_type,
+                            &mut fun_scope,
+                            references,
+                            use_defs,
+                        );
+                    }
+                    if let EA::FunctionBody_::Defined(sequence) = &body.value {
+                        self.spec_seq_symbols(sequence, &fun_scope, references, use_defs);
+                    }
+                }
+                M::Variable {
+                    is_global,
+                    name,
+                    init,
+                    ..
+                } => {
+                    if *is_global {
+                        self.add_spec_outer_use_def(name, references, use_defs);
+                    }
+                    if let Some(exp) = init {
+                        self.spec_exp_symbols(exp, &scope, references, use_defs);
+                    }
+                }
+                M::Update { lhs, rhs } => {
+                    self.spec_exp_symbols(lhs, &scope, references, use_defs);
+                    self.spec_exp_symbols(rhs, &scope, references, use_defs);
+                }
+                M::Let { name, def, .. } => {
+                    self.spec_exp_symbols(def, &scope, references, use_defs);
+                    let ident_type = IdentType::SpecType(format!("let {}", name.value));
+                    self.add_spec_local_def(name, ident_type, &mut scope, references, use_defs);
+                }
+                M::Include { exp, .. } | M::Apply { exp, .. } => {
+                    self.spec_exp_symbols(exp, &scope, references, use_defs);
+                }
+                M::Pragma { .. } => (),
+            }
+        }
+    }
+
+    /// Get symbols for a sequence of expressions in a specification
+    fn spec_seq_symbols(
+        &self,
+        sequence: &EA::Sequence,
+        scope: &SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        // a sequence is a new var scope
+        let mut scope = scope.clone();
+        for seq_item in sequence {
+            match &seq_item.value {
+                EA::SequenceItem_::Seq(exp) => {
+                    self.spec_exp_symbols(exp, &scope, references, use_defs)
+                }
+                EA::SequenceItem_::Declare(lvalues, _) => {
+                    for lval in &lvalues.value {
+                        self.spec_lvalue_symbols(lval, &mut scope, references, use_defs);
+                    }
+                }
+                EA::SequenceItem_::Bind(lvalues, exp) => {
+                    self.spec_exp_symbols(exp, &scope, references, use_defs);
+                    for lval in &lvalues.value {
+                        self.spec_lvalue_symbols(lval, &mut scope, references, use_defs);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get symbols for an lvalue (binding new locals) in a specification
+    fn spec_lvalue_symbols(
+        &self,
+        lval: &EA::LValue,
+        scope: &mut SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        match &lval.value {
+            EA::LValue_::Var(sp!(_, EA::ModuleAccess_::Name(name)), _) => {
+                let ident_type = IdentType::SpecType(name.value.to_string());
+                self.add_spec_local_def(name, ident_type, scope, references, use_defs);
+            }
+            EA::LValue_::Var(..) => (),
+            EA::LValue_::Unpack(_, _, fields) => {
+                for (_, _, (_, lval)) in fields {
+                    self.spec_lvalue_symbols(lval, scope, references, use_defs);
+                }
+            }
+        }
+    }
+
+    /// Get symbols for an expression in a specification
+    fn spec_exp_symbols(
+        &self,
+        exp: &EA::Exp,
+        scope: &SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        use EA::Exp_ as E;
+        match &exp.value {
+            E::Move(var) | E::Copy(var) => self.add_spec_access_use_def(
+                &sp(var.loc(), EA::ModuleAccess_::Name(var.0)),
+                scope,
+                references,
+                use_defs,
+            ),
+            E::Name(access, _) => self.add_spec_access_use_def(access, scope, references, use_defs),
+            E::Call(access, _, _, args) => {
+                self.add_spec_access_use_def(access, scope, references, use_defs);
+                for arg in &args.value {
+                    self.spec_exp_symbols(arg, scope, references, use_defs);
+                }
+            }
+            E::Pack(access, _, fields) => {
+                self.add_spec_access_use_def(access, scope, references, use_defs);
+                for (_, _, (_, exp)) in fields {
+                    self.spec_exp_symbols(exp, scope, references, use_defs);
+                }
+            }
+            E::Vector(_, _, exps) => {
+                for exp in &exps.value {
+                    self.spec_exp_symbols(exp, scope, references, use_defs);
+                }
+            }
+            E::ExpList(exps) => {
+                for exp in exps {
+                    self.spec_exp_symbols(exp, scope, references, use_defs);
+                }
+            }
+            E::IfElse(cond, t, f) => {
+                self.spec_exp_symbols(cond, scope, references, use_defs);
+                self.spec_exp_symbols(t, scope, references, use_defs);
+                self.spec_exp_symbols(f, scope, references, use_defs);
+            }
+            E::While(lhs, rhs)
+            | E::Mutate(lhs, rhs)
+            | E::BinopExp(lhs, _, rhs)
+            | E::Index(lhs, rhs) => {
+                self.spec_exp_symbols(lhs, scope, references, use_defs);
+                self.spec_exp_symbols(rhs, scope, references, use_defs);
+            }
+            E::Loop(exp)
+            | E::Return(exp)
+            | E::Abort(exp)
+            | E::Dereference(exp)
+            | E::UnaryExp(_, exp)
+            | E::Borrow(_, exp)
+            | E::Cast(exp, _)
+            | E::Annotate(exp, _) => self.spec_exp_symbols(exp, scope, references, use_defs),
+            E::Block(sequence) => self.spec_seq_symbols(sequence, scope, references, use_defs),
+            E::Lambda(lvalues, body) => {
+                let mut scope = scope.clone();
+                for lval in &lvalues.value {
+                    self.spec_lvalue_symbols(lval, &mut scope, references, use_defs);
+                }
+                self.spec_exp_symbols(body, &scope, references, use_defs);
+            }
+            E::Quant(_, ranges, triggers, cond, body) => {
+                let mut scope = scope.clone();
+                for range in &ranges.value {
+                    let (lval, range_exp) = &range.value;
+                    self.spec_exp_symbols(range_exp, &scope, references, use_defs);
+                    self.spec_lvalue_symbols(lval, &mut scope, references, use_defs);
+                }
+                for exp in triggers.iter().flatten().chain(cond.as_deref()) {
+                    self.spec_exp_symbols(exp, &scope, references, use_defs);
+                }
+                self.spec_exp_symbols(body, &scope, references, use_defs);
+            }
+            E::Assign(lvalues, exp) => {
+                for lval in &lvalues.value {
+                    if let EA::LValue_::Var(access, _) = &lval.value {
+                        self.add_spec_access_use_def(access, scope, references, use_defs);
+                    }
+                }
+                self.spec_exp_symbols(exp, scope, references, use_defs);
+            }
+            E::FieldMutate(dotted, exp) => {
+                self.spec_dotted_symbols(dotted, scope, references, use_defs);
+                self.spec_exp_symbols(exp, scope, references, use_defs);
+            }
+            E::ExpDotted(dotted) => self.spec_dotted_symbols(dotted, scope, references, use_defs),
+            E::Value(_)
+            | E::Break
+            | E::Continue
+            | E::Unit { .. }
+            | E::Spec(..)
+            | E::UnresolvedError => (),
+        }
+    }
+
+    /// Get symbols for a field access expression in a specification
+    fn spec_dotted_symbols(
+        &self,
+        dotted: &EA::ExpDotted,
+        scope: &SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        match &dotted.value {
+            EA::ExpDotted_::Exp(exp) => self.spec_exp_symbols(exp, scope, references, use_defs),
+            EA::ExpDotted_::Dot(dotted, _) => {
+                self.spec_dotted_symbols(dotted, scope, references, use_defs)
+            }
+        }
+    }
+
+    /// Add definition of a local in a specification
+    fn add_spec_local_def(
+        &self,
+        name: &Name,
+        ident_type: IdentType,
+        scope: &mut SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        let name_start = match Self::get_start_loc(&name.loc, &self.files, &self.file_id_mapping) {
+            Some(v) => v,
+            None => {
+                debug_assert!(false);
+                return;
+            }
+        };
+        let def_loc = DefLoc {
+            fhash: name.loc.file_hash(),
+            start: name_start,
+        };
+        scope.insert(name.value, (def_loc, ident_type.clone()));
+        self.add_spec_use_def(
+            name,
+            (def_loc, ident_type, DefKind::Local),
+            true,
+            references,
+            use_defs,
+        );
+    }
+
+    /// Add use of a (possibly module-qualified) name in a specification, which may refer to a
+    /// local, to a specification-only entity, or to a function or a struct (names that cannot be
+    /// resolved, such as names of built-in specification functions, are ignored)
+    fn add_spec_access_use_def(
+        &self,
+        access: &EA::ModuleAccess,
+        scope: &SpecScope,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        let (mod_ident, name) = match &access.value {
+            EA::ModuleAccess_::Name(name) => {
+                if let Some((def_loc, ident_type)) = scope.get(&name.value) {
+                    let def = (*def_loc, ident_type.clone(), DefKind::Local);
+                    self.add_spec_use_def(name, def, false, references, use_defs);
+                    return;
+                }
+                (self.current_mod.unwrap().value, name)
+            }
+            EA::ModuleAccess_::ModuleAccess(mod_ident, name) => (mod_ident.value, name),
+        };
+        if let Some(def) = self.spec_outer_def(&mod_ident, name) {
+            self.add_spec_use_def(name, def, false, references, use_defs);
+        }
+    }
+
+    /// Add use of a specification-only entity of the current module at its definition
+    fn add_spec_outer_use_def(
+        &self,
+        name: &Name,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        if let Some(def) = self.spec_outer_def(&self.current_mod.unwrap().value, name) {
+            self.add_spec_use_def(name, def, false, references, use_defs);
+        }
+    }
+
+    /// Finds a module-level definition of a name used in a specification (specification-only
+    /// entities take precedence over functions and structs)
+    fn spec_outer_def(
+        &self,
+        mod_ident: &ModuleIdent_,
+        name: &Name,
+    ) -> Option<(DefLoc, IdentType, DefKind)> {
+        let mod_defs = self.mod_outer_defs.get(mod_ident)?;
+        let def_loc = |start: &Position| DefLoc {
+            fhash: mod_defs.fhash,
+            start: *start,
+        };
+        let spec_defs = [
+            (&mod_defs.spec_vars, DefKind::Local),
+            (&mod_defs.spec_funs, DefKind::Function),
+            (&mod_defs.schemas, DefKind::Struct),
+        ];
+        for (defs, def_kind) in spec_defs {
+            if let Some(def) = defs.get(&name.value) {
+                return Some((def_loc(&def.name_start), def.ident_type.clone(), def_kind));
+            }
+        }
+        if let Some(start) = mod_defs.functions.get(&name.value) {
+            let def_loc = def_loc(start);
+            let fun_type = self.fun_signatures.get(&def_loc)?.fun_type.clone();
+            return Some((def_loc, fun_type, DefKind::Function));
+        }
+        if let Some(def) = mod_defs.structs.get(&name.value) {
+            let typ = Self::create_struct_type(
+                sp(name.loc, *mod_ident),
+                StructName(*name),
+                name.loc,
+                vec![],
+            );
+            let ident_type = IdentType::RegularType(typ);
+            return Some((def_loc(&def.name_start), ident_type, DefKind::Struct));
+        }
+        None
+    }
+
+    /// Add use of a name in a specification, paired with a given definition
+    fn add_spec_use_def(
+        &self,
+        name: &Name,
+        (def_loc, ident_type, def_kind): (DefLoc, IdentType, DefKind),
+        write: bool,
+        references: &mut BTreeMap<DefLoc, BTreeSet<UseLoc>>,
+        use_defs: &mut UseDefMap,
+    ) {
+        let name_start = match Self::get_start_loc(&name.loc, &self.files, &self.file_id_mapping) {
+            Some(v) => v,
+            None => {
+                debug_assert!(false);
+                return;
+            }
+        };
+        let ident_type_def = self.ident_type_def_loc(&ident_type);
+        let mut use_def = UseDef::new(
+            references,
+            name.loc.file_hash(),
+            name_start,
+            def_loc.fhash,
+            def_loc.start,
+            &name.value,
+            ident_type,
+            ident_type_def,
+            def_kind,
+        );
+        use_def.write = write;
+        use_defs.insert(name_start.line, use_def);
+    }
+
     fn create_struct_type(
         module_ident: ModuleIdent,
         struct_name: StructName,
@@ -1686,6 +4460,7 @@ impl Symbolicator {
         match ident_type {
             IdentType::RegularType(t) => self.type_def_loc(t),
             IdentType::FunctionType(_, _, _, _, ret, _) => self.type_def_loc(ret),
+            IdentType::SpecType(_) => None,
         }
     }
 
@@ -1711,43 +4486,322 @@ impl Symbolicator {
     }
 }
 
-/// Handles go-to-def request of the language server
-pub fn on_go_to_def_request(context: &Context, request: &Request, symbols: &Symbols) {
-    let parameters = serde_json::from_value::<GotoDefinitionParams>(request.params.clone())
-        .expect("could not deserialize go-to-def request");
+/// Returns the documentation comment (consisting of `///` lines) immediately preceding the given
+/// line of the source, if any
+fn doc_comment(source: &str, line: u32) -> Option<String> {
+    let mut doc_lines = source
+        .lines()
+        .take(line as usize)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map(str::trim_start)
+        .take_while(|l| l.starts_with("///") && !l.starts_with("////"))
+        .map(|l| {
+            let l = &l["///".len()..];
+            l.strip_prefix(' ').unwrap_or(l).trim_end()
+        })
+        .collect::<Vec<_>>();
+    if doc_lines.iter().all(|l| l.is_empty()) {
+        return None;
+    }
+    doc_lines.reverse();
+    Some(doc_lines.join("\n"))
+}
 
-    let fpath = parameters
-        .text_document_position_params
-        .text_document
-        .uri
-        .path();
-    let loc = parameters.text_document_position_params.position;
-    let line = loc.line;
-    let col = loc.character;
+/// Returns the directory where symbolication results of a package are cached
+fn symbols_cache_dir(pkg_path: &Path) -> PathBuf {
+    pkg_path
+        .join(CompiledPackageLayout::Root.path())
+        .join(SYMBOLS_CACHE_DIR)
+}
 
-    on_use_request(
-        context,
-        symbols,
-        fpath,
-        line,
-        col,
-        request.id.clone(),
-        |u| {
-            // TODO: Do we need beginning and end of the definition? Does not seem to make a
-            // difference from the IDE perspective as the cursor goes to the beginning anyway (at
-            // least in VSCode).
-            let range = Range {
-                start: u.def_loc.start,
-                end: u.def_loc.start,
+/// Computes the key of cached symbols from the names and contents (hashes) of all source files
+/// they were computed from (and from the configuration of the build they were computed from and
+/// from the values of named addresses assigned in package manifests)
+fn symbols_cache_key(
+    build_config: &BuildConfig,
+    resolution_graph: &ResolvedGraph,
+    file_name_mapping: &BTreeMap<FileHash, Symbol>,
+) -> String {
+    let mut files = format!(
+        "{}\n{}\n",
+        SYMBOLS_CACHE_VERSION,
+        serde_json::to_string(build_config).unwrap()
+    );
+    for (pkg_name, pkg) in &resolution_graph.package_table {
+        for (name, addr) in &pkg.resolution_table {
+            files.push_str(&format!(
+                "{} {}={}\n",
+                pkg_name,
+                name,
+                addr.to_hex_literal()
+            ));
+        }
+    }
+    for (fhash, fname) in file_name_mapping {
+        files.push_str(&format!("{} {}\n", fhash, fname));
+    }
+    FileHash::new(&files).to_string()
+}
+
+/// Indexes named addresses declared in the package graph (along with their values) and modules
+/// declared in source files of all packages in the graph, as discovered by the package resolver
+fn package_module_index(
+    resolution_graph: &ResolvedGraph,
+    source_files: &BTreeMap<FileHash, (Symbol, String)>,
+) -> ModuleIndex {
+    let mut index = ModuleIndex::default();
+    let mut named_addresses = BTreeMap::new();
+    for pkg in resolution_graph.package_table.values() {
+        for (name, addr) in &pkg.resolution_table {
+            let value = addr.to_hex_literal();
+            index.insert_named_address(name.as_str(), &value);
+            named_addresses.insert(name.to_string(), value);
+        }
+    }
+    for (fhash, (_, source)) in source_files {
+        for (address, name) in module_declarations(*fhash, source).unwrap_or_default() {
+            let address = match address {
+                Some(address) => address,
+                None => continue,
             };
-            let path = symbols.file_name_mapping.get(&u.def_loc.fhash).unwrap();
-            let loc = Location {
-                uri: Url::from_file_path(path.as_str()).unwrap(),
-                range,
+            // modules are accessible via both named addresses and their values
+            let addresses = match named_addresses.get(&address) {
+                Some(value) => vec![address, value.clone()],
+                None => vec![address],
             };
-            Some(serde_json::to_value(loc).unwrap())
-        },
-    );
+            index.insert_module(&addresses, &name, vec![]);
+        }
+    }
+    index
+}
+
+/// Parses a source file and returns addresses (if declared) and names of modules declared in it,
+/// or None if the file cannot be parsed
+fn module_declarations(fhash: FileHash, source: &str) -> Option<Vec<(Option<String>, Symbol)>> {
+    let mut env = CompilationEnv::new(Flags::empty());
+    let (defs, _) = parse_file_string(&mut env, fhash, source).ok()?;
+    let mut modules = vec![];
+    let mut add = |address: Option<&P::LeadingNameAccess>, mod_def: &P::ModuleDefinition| {
+        let address = mod_def.address.as_ref().or(address);
+        modules.push((address.map(|a| a.value.to_string()), mod_def.name.0.value));
+    };
+    for def in &defs {
+        match def {
+            P::Definition::Module(mod_def) => add(None, mod_def),
+            P::Definition::Address(addr_def) => {
+                for mod_def in &addr_def.modules {
+                    add(Some(&addr_def.addr), mod_def)
+                }
+            }
+            P::Definition::Script(_) => (),
+        }
+    }
+    Some(modules)
+}
+
+/// Returns locations of names and of declarations of constants defined in modules of a program
+/// along with their values (constants that could not be evaluated are skipped)
+fn constant_values(program: &G::Program) -> Vec<(Loc, Loc, MoveValue)> {
+    program
+        .modules
+        .iter()
+        .flat_map(|(_, _, mod_def)| mod_def.constants.iter())
+        .filter_map(|(name_loc, _, c)| Some((name_loc, c.loc, c.value.clone()?)))
+        .collect()
+}
+
+/// Returns the declaration of a constant (with normalized whitespace) followed by its value, unless
+/// the value is given literally
+fn constant_declaration(decl: &str, value: &MoveValue) -> String {
+    let decl = decl
+        .trim_end_matches(|c: char| c == ';' || c.is_whitespace())
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let value = move_value_string(value);
+    match decl.split_once('=') {
+        Some((_, exp)) if exp.trim() == value => decl,
+        _ => format!("{} ({})", decl, value),
+    }
+}
+
+/// Returns a value in Move syntax
+fn move_value_string(value: &MoveValue) -> String {
+    match value {
+        MoveValue::U8(n) => n.to_string(),
+        MoveValue::U64(n) => n.to_string(),
+        MoveValue::U128(n) => n.to_string(),
+        MoveValue::Bool(b) => b.to_string(),
+        MoveValue::Address(a) | MoveValue::Signer(a) => format!("@{}", a.to_hex_literal()),
+        MoveValue::Vector(values) => format!(
+            "vector[{}]",
+            values
+                .iter()
+                .map(move_value_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        MoveValue::Struct(s) => s.to_string(),
+    }
+}
+
+/// Returns names of modules declared in the given source along with names of modules (possibly)
+/// referred to by it, found by lexing so that sources that do not parse are covered as well
+fn module_names(source: &str) -> (BTreeSet<Symbol>, BTreeSet<Symbol>) {
+    let mut declared = BTreeSet::new();
+    let mut referred = BTreeSet::new();
+    let mut lexer = Lexer::new(source, FileHash::new(source));
+    // the last identifier of a module declaration (preceding its body) is the module's name
+    let mut in_declaration = false;
+    let mut last_ident = None;
+    let mut after_colons = false;
+    while lexer.advance().is_ok() && lexer.peek() != Tok::EOF {
+        match lexer.peek() {
+            Tok::Module => in_declaration = true,
+            Tok::LBrace if in_declaration => {
+                declared.extend(last_ident.take());
+                in_declaration = false;
+            }
+            Tok::Identifier => {
+                let name = Symbol::from(lexer.content());
+                // identifiers qualified by or qualifying other identifiers may name modules
+                if after_colons || matches!(lexer.lookahead(), Ok(Tok::ColonColon)) {
+                    referred.insert(name);
+                }
+                last_ident = Some(name);
+            }
+            _ => (),
+        }
+        after_colons = lexer.peek() == Tok::ColonColon;
+    }
+    (declared, referred)
+}
+
+/// Returns the hash of the file containing a top-level definition
+fn definition_file(def: &P::Definition) -> FileHash {
+    match def {
+        P::Definition::Module(mod_def) => mod_def.loc.file_hash(),
+        P::Definition::Address(addr_def) => addr_def.loc.file_hash(),
+        P::Definition::Script(script) => script.loc.file_hash(),
+    }
+}
+
+/// Keeps only top-level definitions from the given files in a parsed program
+fn retain_definitions(mut program: P::Program, files: &BTreeSet<FileHash>) -> P::Program {
+    let in_files = |pkg_def: &P::PackageDefinition| files.contains(&definition_file(&pkg_def.def));
+    program.source_definitions.retain(in_files);
+    program.lib_definitions.retain(in_files);
+    program
+}
+
+/// Merges symbols of all packages
+fn merged_symbols(pkg_symbols: &BTreeMap<PathBuf, Symbols>) -> Symbols {
+    let mut merged_symbols = Symbolicator::empty_symbols();
+    for s in pkg_symbols.values() {
+        merged_symbols.merge(s.clone());
+    }
+    merged_symbols
+}
+
+/// Returns diagnostics to be published after a symbolication run (recording them as published),
+/// given the diagnostics of all files of a package computed by this run and files affected by
+/// changes since the previous run (None if all of them are). Only diagnostics that changed or
+/// belong to affected files are published. If compilation failed, files without problems may
+/// have simply not been checked (e.g., due to a parsing error elsewhere), so their previous
+/// diagnostics are only cleared if they are affected by the changes.
+fn diagnostics_to_publish(
+    published: &mut BTreeMap<Symbol, Vec<Diagnostic>>,
+    diagnostics: BTreeMap<Symbol, Vec<Diagnostic>>,
+    compiled: bool,
+    affected: Option<&BTreeSet<PathBuf>>,
+) -> BTreeMap<Symbol, Vec<Diagnostic>> {
+    let is_affected =
+        |fname: &Symbol| affected.map_or(true, |a| a.contains(Path::new(fname.as_str())));
+    let mut to_publish = BTreeMap::new();
+    // files no longer in the package
+    for fname in published.keys() {
+        if !diagnostics.contains_key(fname) {
+            to_publish.insert(*fname, vec![]);
+        }
+    }
+    published.retain(|fname, _| diagnostics.contains_key(fname));
+    for (fname, diags) in diagnostics {
+        let old = published.get(&fname);
+        let unchecked = !compiled && diags.is_empty() && old.map_or(false, |d| !d.is_empty());
+        if unchecked && !is_affected(&fname) {
+            continue;
+        }
+        if old != Some(&diags) || is_affected(&fname) {
+            published.insert(fname, diags.clone());
+            to_publish.insert(fname, diags);
+        }
+    }
+    to_publish
+}
+
+/// Canonicalizes the path of a source file so that it can be matched against paths of files
+/// reported by the client
+fn canonical_path(fpath: &Symbol) -> PathBuf {
+    fs::canonicalize(fpath.as_str()).unwrap_or_else(|_| PathBuf::from(fpath.as_str()))
+}
+
+/// Handles go-to-def request of the language server
+pub fn on_go_to_def_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<GotoDefinitionParams>(request.params.clone())
+        .expect("could not deserialize go-to-def request");
+
+    let fpath = parameters
+        .text_document_position_params
+        .text_document
+        .uri
+        .path();
+    let loc = parameters.text_document_position_params.position;
+    let line = loc.line;
+    let col = loc.character;
+
+    // named addresses are declared in package manifests rather than in source files
+    let buffer = match context.files.get(fpath) {
+        Some(buffer) => buffer.to_string(),
+        None => fs::read_to_string(fpath).unwrap_or_default(),
+    };
+    if let Some(decl_loc) = symbols.address_declaration(Path::new(fpath), &buffer, &loc) {
+        debug!("about to send go-to-def response");
+        let response = lsp_server::Response::new_ok(request.id.clone(), decl_loc);
+        if let Err(err) = context
+            .connection
+            .sender
+            .send(lsp_server::Message::Response(response))
+        {
+            error!("could not send go-to-def response: {:?}", err);
+        }
+        return;
+    }
+
+    on_use_request(
+        context,
+        symbols,
+        fpath,
+        line,
+        col,
+        request.id.clone(),
+        |u| {
+            // TODO: Do we need beginning and end of the definition? Does not seem to make a
+            // difference from the IDE perspective as the cursor goes to the beginning anyway (at
+            // least in VSCode).
+            let range = Range {
+                start: u.def_loc.start,
+                end: u.def_loc.start,
+            };
+            let path = symbols.file_name_mapping.get(&u.def_loc.fhash).unwrap();
+            let loc = Location {
+                uri: Url::from_file_path(path.as_str()).unwrap(),
+                range,
+            };
+            Some(serde_json::to_value(loc).unwrap())
+        },
+    );
 }
 
 /// Handles go-to-type-def request of the language server
@@ -1807,39 +4861,105 @@ pub fn on_references_request(context: &Context, request: &Request, symbols: &Sym
         line,
         col,
         request.id.clone(),
-        |u| match symbols.references.get(&u.def_loc) {
-            Some(s) => {
-                let mut locs = vec![];
-                for ref_loc in s {
-                    if include_decl
-                        || !(u.def_loc.start == ref_loc.start && u.def_loc.fhash == ref_loc.fhash)
-                    {
-                        let end_pos = Position {
-                            line: ref_loc.start.line,
-                            character: ref_loc.col_end,
-                        };
-                        let range = Range {
-                            start: ref_loc.start,
-                            end: end_pos,
-                        };
-                        let path = symbols.file_name_mapping.get(&ref_loc.fhash).unwrap();
-                        locs.push(Location {
-                            uri: Url::from_file_path(path.as_str()).unwrap(),
-                            range,
-                        });
-                    }
-                }
-                if locs.is_empty() {
-                    Some(serde_json::to_value(Option::<lsp_types::Location>::None).unwrap())
-                } else {
-                    Some(serde_json::to_value(locs).unwrap())
-                }
+        |u| {
+            let locs = symbols.reference_locations(&u.def_loc, include_decl);
+            if locs.is_empty() {
+                Some(serde_json::to_value(Option::<lsp_types::Location>::None).unwrap())
+            } else {
+                Some(serde_json::to_value(locs).unwrap())
             }
-            None => Some(serde_json::to_value(Option::<lsp_types::Location>::None).unwrap()),
         },
     );
 }
 
+/// Handles document highlight request of the language server
+pub fn on_document_highlight_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<DocumentHighlightParams>(request.params.clone())
+        .expect("could not deserialize document highlight request");
+
+    let fpath = parameters
+        .text_document_position_params
+        .text_document
+        .uri
+        .path();
+    let position = parameters.text_document_position_params.position;
+    let highlights = symbols.document_highlights(Path::new(fpath), &position);
+
+    debug!("about to send document highlight response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), highlights);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send document highlight response: {:?}", err);
+    }
+}
+
+/// Handles prepare call hierarchy request of the language server
+pub fn on_prepare_call_hierarchy_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<CallHierarchyPrepareParams>(request.params.clone())
+        .expect("could not deserialize prepare call hierarchy request");
+
+    let fpath = parameters
+        .text_document_position_params
+        .text_document
+        .uri
+        .path();
+    let position = parameters.text_document_position_params.position;
+    let items = symbols
+        .prepare_call_hierarchy(Path::new(fpath), &position)
+        .map(|item| vec![item]);
+
+    debug!("about to send prepare call hierarchy response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), items);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send prepare call hierarchy response: {:?}", err);
+    }
+}
+
+/// Handles incoming calls request of the language server
+pub fn on_incoming_calls_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters =
+        serde_json::from_value::<CallHierarchyIncomingCallsParams>(request.params.clone())
+            .expect("could not deserialize incoming calls request");
+
+    let calls = symbols.incoming_calls(&parameters.item);
+
+    debug!("about to send incoming calls response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), calls);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send incoming calls response: {:?}", err);
+    }
+}
+
+/// Handles outgoing calls request of the language server
+pub fn on_outgoing_calls_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters =
+        serde_json::from_value::<CallHierarchyOutgoingCallsParams>(request.params.clone())
+            .expect("could not deserialize outgoing calls request");
+
+    let calls = symbols.outgoing_calls(&parameters.item);
+
+    debug!("about to send outgoing calls response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), calls);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send outgoing calls response: {:?}", err);
+    }
+}
+
 /// Handles hover request of the language server
 pub fn on_hover_request(context: &Context, request: &Request, symbols: &Symbols) {
     let parameters = serde_json::from_value::<HoverParams>(request.params.clone())
@@ -1854,6 +4974,24 @@ pub fn on_hover_request(context: &Context, request: &Request, symbols: &Symbols)
     let line = loc.line;
     let col = loc.character;
 
+    // numeric literals are not symbolicated, so abort codes are found in the buffer
+    let hover = context
+        .files
+        .get(fpath)
+        .and_then(|buffer| abort_code_hover(symbols, fpath, buffer, &loc));
+    if let Some(hover) = hover {
+        debug!("about to send hover response");
+        let response = lsp_server::Response::new_ok(request.id.clone(), hover);
+        if let Err(err) = context
+            .connection
+            .sender
+            .send(lsp_server::Message::Response(response))
+        {
+            error!("could not send hover response: {:?}", err);
+        }
+        return;
+    }
+
     on_use_request(
         context,
         symbols,
@@ -1862,107 +5000,802 @@ pub fn on_hover_request(context: &Context, request: &Request, symbols: &Symbols)
         col,
         request.id.clone(),
         |u| {
-            let lang_string = LanguageString {
-                language: "".to_string(),
-                value: format!("{}", u.use_type),
+            // constants are shown along with their values
+            let header = match symbols.const_values.get(&u.def_loc) {
+                Some(decl) => decl.clone(),
+                None => format!("{}", u.use_type),
+            };
+            let contents = match symbols.def_docs.get(&u.def_loc) {
+                Some(doc) => HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```\n{}\n```\n---\n{}", header, doc),
+                }),
+                None => {
+                    let lang_string = LanguageString {
+                        language: "".to_string(),
+                        value: header,
+                    };
+                    HoverContents::Scalar(MarkedString::LanguageString(lang_string))
+                }
             };
-            let contents = HoverContents::Scalar(MarkedString::LanguageString(lang_string));
             let range = None;
             Some(serde_json::to_value(Hover { contents, range }).unwrap())
         },
     );
 }
 
-/// Helper function to handle language server queries related to identifier uses
-pub fn on_use_request(
-    context: &Context,
+/// Returns a hover showing declarations of constants whose value is the abort code given by a
+/// numeric literal at the given position, or `None` if there is no such literal. Constants declared
+/// in the same file take precedence over those declared elsewhere in the package.
+fn abort_code_hover(
     symbols: &Symbols,
-    use_fpath: &str,
-    use_line: u32,
-    use_col: u32,
-    id: RequestId,
-    use_def_action: impl Fn(&UseDef) -> Option<serde_json::Value>,
-) {
-    let mut result = None;
-
-    let mut use_def_found = false;
-    if let Some(mod_symbols) = symbols.file_use_defs.get(&PathBuf::from(use_fpath)) {
-        if let Some(uses) = mod_symbols.get(use_line) {
-            for u in uses {
-                if use_col >= u.col_start && use_col <= u.col_end {
-                    result = use_def_action(&u);
-                    use_def_found = true;
-                }
-            }
-        }
+    fpath: &str,
+    buffer: &str,
+    position: &Position,
+) -> Option<Hover> {
+    let (code, start, end) = abort_code_at(buffer, byte_offset(buffer, position))?;
+    let consts = symbols
+        .abort_codes
+        .get(&code)?
+        .iter()
+        .filter(|def_loc| symbols.package_files.contains(&def_loc.fhash))
+        .collect::<Vec<_>>();
+    let in_file = |def_loc: &DefLoc| {
+        symbols
+            .file_name_mapping
+            .get(&def_loc.fhash)
+            .map_or(false, |name| name.as_str() == fpath)
+    };
+    let any_in_file = consts.iter().any(|def_loc| in_file(def_loc));
+    let decls = consts
+        .iter()
+        .filter(|def_loc| !any_in_file || in_file(def_loc))
+        .filter_map(|def_loc| symbols.const_values.get(*def_loc))
+        .cloned()
+        .collect::<Vec<_>>();
+    if decls.is_empty() {
+        return None;
     }
-    if !use_def_found {
-        result = Some(serde_json::to_value(Option::<lsp_types::Location>::None).unwrap());
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("Abort code {}\n```\n{}\n```", code, decls.join("\n")),
+        }),
+        range: Some(Range {
+            start: buffer_position(buffer, start),
+            end: buffer_position(buffer, end),
+        }),
+    })
+}
+
+/// Handles document symbol request of the language server
+pub fn on_document_symbol_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<DocumentSymbolParams>(request.params.clone())
+        .expect("could not deserialize document symbol request");
+
+    let fpath = parameters.text_document.uri.path();
+    let outline = symbols
+        .file_outlines
+        .get(&PathBuf::from(fpath))
+        .cloned()
+        .unwrap_or_default();
+
+    debug!("about to send document symbol response");
+    let response =
+        lsp_server::Response::new_ok(request.id.clone(), DocumentSymbolResponse::Nested(outline));
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send document symbol response: {:?}", err);
     }
+}
 
-    eprintln!("about to send use response");
-    // unwrap will succeed based on the logic above which the compiler is unable to figure out
-    // without using Option
-    let response = lsp_server::Response::new_ok(id, result.unwrap());
+/// Handles workspace symbol request of the language server
+pub fn on_workspace_symbol_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<WorkspaceSymbolParams>(request.params.clone())
+        .expect("could not deserialize workspace symbol request");
+
+    let result = symbols.symbol_index.query(&parameters.query);
+
+    debug!("about to send workspace symbol response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), result);
     if let Err(err) = context
         .connection
         .sender
         .send(lsp_server::Message::Response(response))
     {
-        eprintln!("could not send use response: {:?}", err);
+        error!("could not send workspace symbol response: {:?}", err);
     }
 }
 
-#[cfg(test)]
-fn assert_use_def(
-    mod_symbols: &UseDefMap,
-    file_name_mapping: &BTreeMap<FileHash, Symbol>,
-    use_idx: usize,
-    use_line: u32,
-    use_col: u32,
-    def_line: u32,
-    def_col: u32,
-    def_file: &str,
-    type_str: &str,
-    type_def: Option<(u32, u32, &str)>,
-) {
-    let uses = mod_symbols.get(use_line).unwrap();
-    let use_def = uses.iter().nth(use_idx).unwrap();
-    assert!(use_def.col_start == use_col);
-    assert!(use_def.def_loc.start.line == def_line);
-    assert!(use_def.def_loc.start.character == def_col);
-    assert!(file_name_mapping
-        .get(&use_def.def_loc.fhash)
-        .unwrap()
-        .as_str()
-        .ends_with(def_file));
-    assert!(type_str == format!("{}", use_def.use_type));
-    match use_def.type_def_loc {
-        Some(type_def_loc) => {
-            let tdef_line = type_def.unwrap().0;
-            let tdef_col = type_def.unwrap().1;
-            let tdef_file = type_def.unwrap().2;
-            assert!(type_def_loc.start.line == tdef_line);
-            assert!(type_def_loc.start.character == tdef_col);
-            assert!(file_name_mapping
-                .get(&type_def_loc.fhash)
-                .unwrap()
-                .as_str()
-                .ends_with(tdef_file));
+/// Handles rename request of the language server
+pub fn on_rename_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<RenameParams>(request.params.clone())
+        .expect("could not deserialize rename request");
+
+    let new_name = parameters.new_name;
+    if !is_valid_identifier(&new_name) {
+        let response = lsp_server::Response::new_err(
+            request.id.clone(),
+            lsp_server::ErrorCode::InvalidParams as i32,
+            format!("'{}' is not a valid Move identifier", new_name),
+        );
+        if let Err(err) = context
+            .connection
+            .sender
+            .send(lsp_server::Message::Response(response))
+        {
+            error!("could not send rename response: {:?}", err);
         }
-        None => assert!(type_def.is_none()),
+        return;
     }
-}
 
-#[test]
-/// Tests if symbolication information for specific Move constructs has been constructed correctly.
-fn symbols_test() {
-    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fpath = parameters.text_document_position.text_document.uri.path();
+    let loc = parameters.text_document_position.position;
+    let line = loc.line;
+    let col = loc.character;
 
-    path.push("tests/symbols");
+    on_use_request(
+        context,
+        symbols,
+        fpath,
+        line,
+        col,
+        request.id.clone(),
+        |u| Some(serde_json::to_value(rename_edit(symbols, &u.def_loc, &new_name)).unwrap()),
+    );
+}
 
-    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
-    let symbols = symbols_opt.unwrap();
+/// Handles prepare rename request of the language server
+pub fn on_prepare_rename_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<TextDocumentPositionParams>(request.params.clone())
+        .expect("could not deserialize prepare rename request");
+
+    let fpath = parameters.text_document.uri.path();
+    let buffer = match context.files.get(fpath) {
+        Some(buffer) => buffer.to_string(),
+        None => fs::read_to_string(fpath).unwrap_or_default(),
+    };
+    let response = match symbols.prepare_rename(Path::new(fpath), &buffer, &parameters.position) {
+        Ok(range) => {
+            lsp_server::Response::new_ok(request.id.clone(), PrepareRenameResponse::Range(range))
+        }
+        Err(msg) => lsp_server::Response::new_err(
+            request.id.clone(),
+            lsp_server::ErrorCode::InvalidParams as i32,
+            msg,
+        ),
+    };
+
+    debug!("about to send prepare rename response");
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send prepare rename response: {:?}", err);
+    }
+}
+
+/// Computes an edit renaming all references (including the definition itself) to the identifier
+/// defined at the given location
+fn rename_edit(symbols: &Symbols, def_loc: &DefLoc, new_name: &str) -> WorkspaceEdit {
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for ref_loc in symbols.references.get(def_loc).into_iter().flatten() {
+        let end_pos = Position {
+            line: ref_loc.start.line,
+            character: ref_loc.col_end,
+        };
+        let range = Range {
+            start: ref_loc.start,
+            end: end_pos,
+        };
+        let path = symbols.file_name_mapping.get(&ref_loc.fhash).unwrap();
+        changes
+            .entry(Url::from_file_path(path.as_str()).unwrap())
+            .or_insert_with(Vec::new)
+            .push(TextEdit::new(range, new_name.to_string()));
+    }
+    WorkspaceEdit::new(changes)
+}
+
+/// Checks if the given name can be used as a Move identifier
+fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let valid_chars = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        // identifiers starting with an underscore must contain at least one more character
+        Some('_') => name.len() > 1 && chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    };
+    valid_chars && !KEYWORDS.contains(&name)
+}
+
+/// Returns the legend describing semantic tokens produced by the language server
+pub fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: SEMANTIC_TOKEN_TYPES.to_vec(),
+        token_modifiers: SEMANTIC_TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Handles full semantic tokens request of the language server
+pub fn on_semantic_tokens_full_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<SemanticTokensParams>(request.params.clone())
+        .expect("could not deserialize semantic tokens request");
+
+    let fpath = parameters.text_document.uri.path();
+    let tokens = semantic_tokens(symbols, fpath, None);
+    send_semantic_tokens_response(context, request, tokens);
+}
+
+/// Handles range semantic tokens request of the language server
+pub fn on_semantic_tokens_range_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<SemanticTokensRangeParams>(request.params.clone())
+        .expect("could not deserialize semantic tokens range request");
+
+    let fpath = parameters.text_document.uri.path();
+    let tokens = semantic_tokens(symbols, fpath, Some(parameters.range));
+    send_semantic_tokens_response(context, request, tokens);
+}
+
+fn send_semantic_tokens_response(context: &Context, request: &Request, tokens: SemanticTokens) {
+    debug!("about to send semantic tokens response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), tokens);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send semantic tokens response: {:?}", err);
+    }
+}
+
+/// Encodes semantic tokens of a given file (starting in a given range, if any) in the format
+/// understood by the language server, where each token's position is relative to the previous one
+fn semantic_tokens(symbols: &Symbols, fpath: &str, range: Option<Range>) -> SemanticTokens {
+    let mut data = vec![];
+    let mut prev = Position::new(0, 0);
+    let tokens = symbols.file_tokens.get(&PathBuf::from(fpath));
+    for token in tokens.into_iter().flatten() {
+        if let Some(range) = range {
+            if token.start < range.start || token.start >= range.end {
+                continue;
+            }
+        }
+        let delta_line = token.start.line - prev.line;
+        let delta_start = if delta_line == 0 {
+            token.start.character - prev.character
+        } else {
+            token.start.character
+        };
+        data.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: token.length,
+            token_type: token.token_type,
+            token_modifiers_bitset: token.modifiers,
+        });
+        prev = token.start;
+    }
+    SemanticTokens {
+        result_id: None,
+        data,
+    }
+}
+
+/// Handles inlay hint request of the language server
+pub fn on_inlay_hint_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<InlayHintParams>(request.params.clone())
+        .expect("could not deserialize inlay hint request");
+
+    let fpath = parameters.text_document.uri.path();
+    let hints = inlay_hints(symbols, fpath, parameters.range);
+
+    debug!("about to send inlay hint response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), hints);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send inlay hint response: {:?}", err);
+    }
+}
+
+/// Returns inlay hints of a given file located in a given range
+fn inlay_hints(symbols: &Symbols, fpath: &str, range: Range) -> Vec<InlayHint> {
+    symbols
+        .file_inlay_hints
+        .get(&PathBuf::from(fpath))
+        .into_iter()
+        .flatten()
+        .filter(|h| h.position >= range.start && h.position <= range.end)
+        .cloned()
+        .collect()
+}
+
+/// Handles type-at request of the language server
+pub fn on_type_at_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<TypeAtParams>(request.params.clone())
+        .expect("could not deserialize type-at request");
+
+    let fpath = parameters.text_document.uri.path();
+    let exp_type = type_at(symbols, fpath, &parameters.range);
+
+    debug!("about to send type-at response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), exp_type);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send type-at response: {:?}", err);
+    }
+}
+
+/// Returns the type of the innermost expression of a given file covering a given range
+fn type_at(symbols: &Symbols, fpath: &str, range: &Range) -> Option<ExpType> {
+    let types = symbols.file_exp_types.get(&PathBuf::from(fpath))?;
+    innermost_exp_type(types, range).cloned()
+}
+
+/// Handles code action request of the language server
+pub fn on_code_action_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<CodeActionParams>(request.params.clone())
+        .expect("could not deserialize code action request");
+
+    let uri = &parameters.text_document.uri;
+    let mut actions = vec![];
+    if let Some(buffer) = context.files.get(uri.path()) {
+        // only declarations in the package's own files can be changed by quick fixes
+        let source = |uri: &Url| {
+            let fpath = uri.path();
+            let in_package = symbols.file_name_mapping.iter().any(|(fhash, name)| {
+                name.as_str() == fpath && symbols.package_files.contains(fhash)
+            });
+            if !in_package {
+                return None;
+            }
+            context
+                .files
+                .get(fpath)
+                .map(|s| s.to_string())
+                .or_else(|| fs::read_to_string(fpath).ok())
+        };
+        let locals = symbols.local_uses(Path::new(uri.path()));
+        for diagnostic in &parameters.context.diagnostics {
+            actions.extend(import_actions(
+                &symbols.import_index,
+                uri,
+                buffer,
+                diagnostic,
+            ));
+            actions.extend(unused_action(uri, buffer, diagnostic));
+            actions.extend(ability_actions(diagnostic, &source));
+            actions.extend(generate_function_action(uri, buffer, diagnostic, &locals));
+        }
+        // source actions and refactorings are only offered when requested (possibly along with
+        // other kinds)
+        let requested = |kind: CodeActionKind| {
+            parameters.context.only.as_ref().map_or(true, |only| {
+                only.iter().any(|k| kind.as_str().starts_with(k.as_str()))
+            })
+        };
+        if requested(CodeActionKind::SOURCE_ORGANIZE_IMPORTS) {
+            actions.extend(organize_imports_action(uri, buffer));
+        }
+        if requested(CodeActionKind::REFACTOR_EXTRACT) {
+            // only types of whole literals are of interest
+            let exp_type = |range: &Range| {
+                type_at(symbols, uri.path(), range)
+                    .filter(|t| t.range == *range)
+                    .map(|t| t.ty)
+            };
+            actions.extend(extract_constant_action(
+                uri,
+                buffer,
+                &parameters.range,
+                &exp_type,
+            ));
+            if parameters.range.start != parameters.range.end {
+                actions.extend(extract_function_action(
+                    uri,
+                    buffer,
+                    &parameters.range,
+                    &locals,
+                ));
+            }
+        }
+        if let Some(literal) = struct_literal(buffer, &parameters.range.start) {
+            for (name, info) in literal_structs(symbols, uri.path(), &literal.path) {
+                actions.extend(fill_fields_action(
+                    uri,
+                    buffer,
+                    &literal,
+                    &name,
+                    &info.fields,
+                ));
+            }
+        }
+    }
+
+    debug!("about to send code action response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), actions);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send code action response: {:?}", err);
+    }
+}
+
+/// Returns structs (along with their qualified names) that a struct literal with a given (possibly
+/// qualified) name may refer to. Structs defined in the current file take precedence over others.
+fn literal_structs<'a>(
+    symbols: &'a Symbols,
+    fpath: &str,
+    path: &[String],
+) -> Vec<(String, &'a StructFieldsInfo)> {
+    let struct_name = match path.last() {
+        Some(n) => n,
+        None => return vec![],
+    };
+    let mod_name = path.len().checked_sub(2).map(|i| &path[i]);
+    let candidates = symbols
+        .struct_fields
+        .get(&Symbol::from(struct_name.as_str()))
+        .into_iter()
+        .flatten()
+        .filter(|info| mod_name.map_or(true, |m| info.mod_ident.module.value().as_str() == m))
+        .collect::<Vec<_>>();
+
+    let fpath = PathBuf::from(fpath);
+    let (local, other): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|info| {
+        symbols
+            .file_name_mapping
+            .get(&info.fhash)
+            .map_or(false, |fname| canonical_path(fname) == fpath)
+    });
+    let structs = if local.is_empty() { other } else { local };
+    structs
+        .into_iter()
+        .map(|info| {
+            let name = format!(
+                "{}::{}::{}",
+                addr_to_ide_string(&info.mod_ident.address),
+                info.mod_ident.module.value(),
+                struct_name
+            );
+            (name, info)
+        })
+        .collect()
+}
+
+/// Returns names and types of fields of the struct value obtained by accessing the given chain of
+/// fields in a variable used at the given position. Since fields of a struct can only be accessed
+/// in the struct's defining module, no fields are returned if any struct in the chain is defined
+/// in a module other than the one with the given name (if known).
+pub fn field_completions(
+    symbols: &Symbols,
+    fpath: &str,
+    root_start: &Position,
+    fields: &[String],
+    mod_name: Option<&str>,
+) -> Vec<(Symbol, String)> {
+    let use_def = symbols
+        .file_use_defs
+        .get(&PathBuf::from(fpath))
+        .and_then(|use_defs| use_defs.get(root_start.line))
+        .and_then(|uses| {
+            uses.into_iter()
+                .find(|u| u.col_start == root_start.character)
+        });
+    let mut ty = match use_def {
+        Some(UseDef {
+            use_type: IdentType::RegularType(t),
+            ..
+        }) => t,
+        _ => return vec![],
+    };
+    let mut field_names = fields.iter();
+    loop {
+        let (info, type_args) = match struct_fields_info(symbols, &ty) {
+            Some(v) => v,
+            None => return vec![],
+        };
+        if mod_name.map_or(false, |m| info.mod_ident.module.value().as_str() != m) {
+            return vec![];
+        }
+        let field_name = match field_names.next() {
+            Some(f) => f,
+            None => {
+                return info
+                    .fields
+                    .iter()
+                    .map(|(f, t)| {
+                        let t = subst_type_params(t, &info.type_params, &type_args);
+                        (*f, type_to_ide_string(&t))
+                    })
+                    .collect()
+            }
+        };
+        ty = match info.fields.iter().find(|(f, _)| f.as_str() == field_name) {
+            Some((_, t)) => subst_type_params(t, &info.type_params, &type_args),
+            None => return vec![],
+        };
+    }
+}
+
+/// Returns fields of a struct type (or of a reference to one) along with its type arguments
+fn struct_fields_info<'a>(
+    symbols: &'a Symbols,
+    sp!(_, t): &Type,
+) -> Option<(&'a StructFieldsInfo, Vec<Type>)> {
+    match t {
+        Type_::Ref(_, t) => struct_fields_info(symbols, t),
+        Type_::Apply(_, sp!(_, TypeName_::ModuleType(mod_ident, struct_name)), type_args) => {
+            let info = symbols
+                .struct_fields
+                .get(&struct_name.value())?
+                .iter()
+                .find(|info| info.mod_ident == mod_ident.value)?;
+            Some((info, type_args.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Substitutes type arguments for the respective type parameters in a type
+fn subst_type_params(sp!(loc, t): &Type, type_params: &[TParam], type_args: &[Type]) -> Type {
+    let subst = |t| subst_type_params(t, type_params, type_args);
+    let t = match t {
+        Type_::Param(tp) => {
+            match type_params
+                .iter()
+                .zip(type_args)
+                .find(|(p, _)| p.id == tp.id)
+            {
+                Some((_, arg)) => return arg.clone(),
+                None => Type_::Param(tp.clone()),
+            }
+        }
+        Type_::Ref(mutable, t) => Type_::Ref(*mutable, Box::new(subst(t))),
+        Type_::Apply(abilities, name, ts) => Type_::Apply(
+            abilities.clone(),
+            name.clone(),
+            ts.iter().map(subst).collect(),
+        ),
+        t => t.clone(),
+    };
+    sp(*loc, t)
+}
+
+/// Handles signature help request of the language server
+pub fn on_signature_help_request(context: &Context, request: &Request, symbols: &Symbols) {
+    let parameters = serde_json::from_value::<SignatureHelpParams>(request.params.clone())
+        .expect("could not deserialize signature help request");
+
+    let fpath = parameters
+        .text_document_position_params
+        .text_document
+        .uri
+        .path();
+    let position = parameters.text_document_position_params.position;
+    let result = context
+        .files
+        .get(fpath)
+        .and_then(|buffer| call_context(buffer, &position))
+        .and_then(|call| signature_help(symbols, fpath, &call));
+
+    debug!("about to send signature help response");
+    let response = lsp_server::Response::new_ok(request.id.clone(), result);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send signature help response: {:?}", err);
+    }
+}
+
+/// Computes signature help for a given function call. If the call was present in the last
+/// successfully compiled version of the file, the signature is instantiated with the call's type
+/// arguments. Otherwise, all functions matching the call's (possibly qualified) name are offered,
+/// with those defined in the current file first.
+fn signature_help(symbols: &Symbols, fpath: &str, call: &CallContext) -> Option<SignatureHelp> {
+    let fun_name = call.path.last()?;
+    let mod_name = call.path.len().checked_sub(2).map(|i| &call.path[i]);
+
+    let mut signatures = vec![];
+    let use_def = symbols
+        .file_use_defs
+        .get(&PathBuf::from(fpath))
+        .and_then(|use_defs| use_defs.get(call.name_start.line))
+        .and_then(|use_defs| {
+            use_defs
+                .into_iter()
+                .find(|u| u.col_start == call.name_start.character)
+        });
+    if let Some(u) = use_def {
+        if let Some(sig) = symbols.fun_signatures.get(&u.def_loc) {
+            if matches!(&u.use_type, IdentType::FunctionType(_, name, ..) if name.as_str() == fun_name)
+            {
+                signatures.extend(fun_signature_information(
+                    &u.use_type,
+                    &sig.param_names,
+                    call.active_parameter,
+                ));
+            }
+        }
+    }
+
+    if signatures.is_empty() {
+        let fpath = PathBuf::from(fpath);
+        let mut candidates = symbols
+            .fun_signatures
+            .iter()
+            .filter(|(_, sig)| match &sig.fun_type {
+                IdentType::FunctionType(mod_ident, name, ..) => {
+                    name.as_str() == fun_name
+                        && mod_name.map_or(true, |m| mod_ident.module.value().as_str() == m)
+                }
+                IdentType::RegularType(_) | IdentType::SpecType(_) => false,
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(def_loc, _)| {
+            symbols
+                .file_name_mapping
+                .get(&def_loc.fhash)
+                .map_or(true, |fname| canonical_path(fname) != fpath)
+        });
+        signatures.extend(candidates.into_iter().filter_map(|(_, sig)| {
+            fun_signature_information(&sig.fun_type, &sig.param_names, call.active_parameter)
+        }));
+    }
+
+    if signatures.is_empty() {
+        return None;
+    }
+    Some(SignatureHelp {
+        signatures,
+        active_signature: Some(0),
+        active_parameter: Some(call.active_parameter),
+    })
+}
+
+/// Describes a function of a given type in the format understood by the language server
+fn fun_signature_information(
+    fun_type: &IdentType,
+    param_names: &[Symbol],
+    active_parameter: u32,
+) -> Option<SignatureInformation> {
+    match fun_type {
+        IdentType::FunctionType(mod_ident, name, type_args, args, ret, _) => {
+            let params = param_names
+                .iter()
+                .zip(args)
+                .map(|(n, t)| (n.to_string(), type_to_ide_string(t)))
+                .collect::<Vec<_>>();
+            let ret = match ret {
+                sp!(_, Type_::Unit) => None,
+                _ => Some(type_to_ide_string(ret)),
+            };
+            Some(signature_information(
+                &format!(
+                    "{}::{}::{}",
+                    addr_to_ide_string(&mod_ident.address),
+                    mod_ident.module.value(),
+                    name
+                ),
+                &type_args.iter().map(type_to_ide_string).collect::<Vec<_>>(),
+                &params,
+                ret,
+                active_parameter,
+            ))
+        }
+        IdentType::RegularType(_) | IdentType::SpecType(_) => None,
+    }
+}
+
+/// Helper function to handle language server queries related to identifier uses
+pub fn on_use_request(
+    context: &Context,
+    symbols: &Symbols,
+    use_fpath: &str,
+    use_line: u32,
+    use_col: u32,
+    id: RequestId,
+    use_def_action: impl Fn(&UseDef) -> Option<serde_json::Value>,
+) {
+    let mut result = None;
+
+    let mut use_def_found = false;
+    if let Some(mod_symbols) = symbols.file_use_defs.get(&PathBuf::from(use_fpath)) {
+        if let Some(uses) = mod_symbols.get(use_line) {
+            for u in uses {
+                if use_col >= u.col_start && use_col <= u.col_end {
+                    result = use_def_action(&u);
+                    use_def_found = true;
+                }
+            }
+        }
+    }
+    if !use_def_found {
+        result = Some(serde_json::to_value(Option::<lsp_types::Location>::None).unwrap());
+    }
+
+    if context.is_cancelled(&id) {
+        context.send_cancelled_response(id);
+        return;
+    }
+
+    debug!("about to send use response");
+    // unwrap will succeed based on the logic above which the compiler is unable to figure out
+    // without using Option
+    let response = lsp_server::Response::new_ok(id, result.unwrap());
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        error!("could not send use response: {:?}", err);
+    }
+}
+
+#[cfg(test)]
+fn assert_use_def(
+    mod_symbols: &UseDefMap,
+    file_name_mapping: &BTreeMap<FileHash, Symbol>,
+    use_idx: usize,
+    use_line: u32,
+    use_col: u32,
+    def_line: u32,
+    def_col: u32,
+    def_file: &str,
+    type_str: &str,
+    type_def: Option<(u32, u32, &str)>,
+) {
+    let uses = mod_symbols.get(use_line).unwrap();
+    let use_def = uses.iter().nth(use_idx).unwrap();
+    assert!(use_def.col_start == use_col);
+    assert!(use_def.def_loc.start.line == def_line);
+    assert!(use_def.def_loc.start.character == def_col);
+    assert!(file_name_mapping
+        .get(&use_def.def_loc.fhash)
+        .unwrap()
+        .as_str()
+        .ends_with(def_file));
+    assert!(type_str == format!("{}", use_def.use_type));
+    match use_def.type_def_loc {
+        Some(type_def_loc) => {
+            let tdef_line = type_def.unwrap().0;
+            let tdef_col = type_def.unwrap().1;
+            let tdef_file = type_def.unwrap().2;
+            assert!(type_def_loc.start.line == tdef_line);
+            assert!(type_def_loc.start.character == tdef_col);
+            assert!(file_name_mapping
+                .get(&type_def_loc.fhash)
+                .unwrap()
+                .as_str()
+                .ends_with(tdef_file));
+        }
+        None => assert!(type_def.is_none()),
+    }
+}
+
+#[test]
+/// Tests if symbolication information for specific Move constructs has been constructed correctly.
+fn symbols_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
 
     let mut fpath = path.clone();
     fpath.push("sources/M1.move");
@@ -2947,3 +6780,1349 @@ fn symbols_test() {
         None,
     );
 }
+
+#[test]
+/// Tests if document outlines have been constructed correctly.
+fn document_symbols_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M2.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    let outline = symbols.file_outlines.get(&cpath).unwrap();
+    let names = |symbols: &Vec<DocumentSymbol>| {
+        symbols
+            .iter()
+            .map(|s| (s.name.clone(), s.kind))
+            .collect::<Vec<_>>()
+    };
+
+    // module defined in the file
+    assert!(names(outline) == vec![("Symbols::M2".to_string(), SymbolKind::Module)]);
+    // module members in declaration order
+    let mod_members = outline[0].children.as_ref().unwrap();
+    assert!(
+        names(mod_members)
+            == vec![
+                ("SomeOtherStruct".to_string(), SymbolKind::Struct),
+                ("some_other_struct".to_string(), SymbolKind::Function),
+                ("multi_arg".to_string(), SymbolKind::Function),
+            ]
+    );
+    // module range spans the whole module while selection range covers its name only
+    assert!(outline[0].range.start == Position::new(0, 0));
+    assert!(outline[0].range.end == Position::new(14, 1));
+    assert!(outline[0].selection_range.start == Position::new(0, 16));
+    // struct fields
+    let fields = mod_members[0].children.as_ref().unwrap();
+    assert!(names(fields) == vec![("some_field".to_string(), SymbolKind::Field)]);
+    assert!(fields[0].selection_range.start == Position::new(3, 8));
+}
+
+#[test]
+/// Tests if workspace symbol queries return correct (and correctly ordered) results.
+fn workspace_symbols_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    // exact match comes first, followed by fuzzy matches
+    let results = symbols.symbol_index.query("SomeOtherStruct");
+    assert!(results[0].name == "SomeOtherStruct");
+    assert!(results[0].kind == SymbolKind::Struct);
+    assert!(results[0].container_name.as_deref() == Some("Symbols::M2"));
+    assert!(results[0].location.uri.path().ends_with("M2.move"));
+    assert!(results[0].location.range.start == Position::new(2, 11));
+    assert!(results[1].name == "some_other_struct");
+
+    // symbols from dependencies are indexed as well
+    let results = symbols.symbol_index.query("vctr");
+    assert!(results
+        .iter()
+        .any(|s| s.name == "std::vector" && s.kind == SymbolKind::Module));
+
+    assert!(symbols.symbol_index.query("xyzzy").is_empty());
+}
+
+#[test]
+/// Tests if only identifiers defined in the package itself can be renamed.
+fn prepare_rename_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let prepare_rename = |fname: &str, line: u32, col: u32| {
+        let mut fpath = path.clone();
+        fpath.push(fname);
+        let cpath = fs::canonicalize(&fpath).unwrap();
+        let buffer = fs::read_to_string(&cpath).unwrap();
+        symbols.prepare_rename(&cpath, &buffer, &Position::new(line, col))
+    };
+
+    // function defined in the package
+    assert!(
+        prepare_rename("sources/M2.move", 6, 20)
+            == Ok(Range::new(Position::new(6, 15), Position::new(6, 32)))
+    );
+    // keyword
+    assert!(
+        prepare_rename("sources/M2.move", 6, 6) == Err("keyword 'public' cannot be renamed".into())
+    );
+    // named address
+    assert!(
+        prepare_rename("sources/M6.move", 1, 9) == Err("address 'std' cannot be renamed".into())
+    );
+    // function defined in a dependency
+    assert!(
+        prepare_rename("sources/M6.move", 4, 26)
+            == Err("'empty' is defined in a dependency and cannot be renamed".into())
+    );
+    // no identifier
+    assert!(prepare_rename("sources/M2.move", 1, 0) == Err("no identifier to rename".into()));
+}
+
+#[test]
+/// Tests if named addresses are resolved to their declarations in package manifests.
+fn address_declaration_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let address_declaration = |fname: &str, line: u32, col: u32| {
+        let mut fpath = path.clone();
+        fpath.push(fname);
+        let cpath = fs::canonicalize(&fpath).unwrap();
+        let buffer = fs::read_to_string(&cpath).unwrap();
+        symbols
+            .address_declaration(&cpath, &buffer, &Position::new(line, col))
+            .cloned()
+    };
+    let manifest_location = |manifest_path: PathBuf, line: u32, col_start: u32, col_end: u32| {
+        let cpath = fs::canonicalize(manifest_path).unwrap();
+        Location {
+            uri: Url::from_file_path(cpath).unwrap(),
+            range: Range::new(Position::new(line, col_start), Position::new(line, col_end)),
+        }
+    };
+
+    // address declared in the package itself
+    assert!(
+        address_declaration("sources/M6.move", 0, 8)
+            == Some(manifest_location(path.join("Move.toml"), 8, 0, 7))
+    );
+    // address declared in a dependency
+    assert!(
+        address_declaration("sources/M6.move", 1, 9)
+            == Some(manifest_location(
+                path.join("../../../move-stdlib/Move.toml"),
+                5,
+                0,
+                3
+            ))
+    );
+    // not a named address
+    assert!(address_declaration("sources/M6.move", 0, 20).is_none());
+}
+
+#[test]
+/// Tests if rename produces edits for all references to the renamed identifier.
+fn rename_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M2.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    // function definition in M2 (used in M1)
+    let mod_symbols = symbols.file_use_defs.get(&cpath).unwrap();
+    let uses = mod_symbols.get(6).unwrap();
+    let use_def = uses.iter().next().unwrap();
+    let edit = rename_edit(&symbols, &use_def.def_loc, "new_name");
+    let changes = edit.changes.unwrap();
+    assert!(changes.len() == 2);
+    for (uri, edits) in changes {
+        let expected = if uri.path().ends_with("M1.move") {
+            vec![(25, 21), (31, 12), (127, 22), (131, 16)]
+        } else {
+            vec![(6, 15)]
+        };
+        let mut actual = edits
+            .iter()
+            .map(|e| {
+                assert!(e.new_text == "new_name");
+                assert!(e.range.end.character - e.range.start.character == 17);
+                (e.range.start.line, e.range.start.character)
+            })
+            .collect::<Vec<_>>();
+        actual.sort();
+        assert!(actual == expected);
+    }
+
+    assert!(is_valid_identifier("some_name"));
+    assert!(is_valid_identifier("_x"));
+    assert!(!is_valid_identifier("_"));
+    assert!(!is_valid_identifier("1x"));
+    assert!(!is_valid_identifier("fun"));
+    assert!(!is_valid_identifier("a::b"));
+}
+
+#[test]
+/// Tests if semantic tokens have been computed and encoded correctly.
+fn semantic_tokens_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M1.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    // tokens in `fun other_mod_struct(): Symbols::M2::SomeOtherStruct {` (line 24)
+    let range = Range::new(Position::new(24, 0), Position::new(25, 0));
+    let tokens = semantic_tokens(&symbols, cpath.to_str().unwrap(), Some(range)).data;
+    let decoded = tokens
+        .iter()
+        .map(|t| {
+            (
+                t.delta_line,
+                t.delta_start,
+                t.length,
+                t.token_type,
+                t.token_modifiers_bitset,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        decoded
+            == vec![
+                (24, 8, 16, FUNCTION_TOKEN, DECLARATION_MODIFIER),
+                (0, 20, 7, NAMED_ADDRESS_TOKEN, 0),
+                (0, 9, 2, NAMESPACE_TOKEN, 0),
+                (0, 4, 15, STRUCT_TOKEN, 0),
+            ]
+    );
+
+    // type parameters in `fun type_param_arg<T: copy + drop>(param: T): T {` (M3.move, line 6)
+    let mut fpath = path.clone();
+    fpath.push("sources/M3.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+    let range = Range::new(Position::new(6, 0), Position::new(7, 0));
+    let tokens = semantic_tokens(&symbols, cpath.to_str().unwrap(), Some(range)).data;
+    let types = tokens.iter().map(|t| t.token_type).collect::<Vec<_>>();
+    assert!(
+        types
+            == vec![
+                FUNCTION_TOKEN,
+                TYPE_PARAMETER_TOKEN,
+                VARIABLE_TOKEN,
+                TYPE_PARAMETER_TOKEN,
+                TYPE_PARAMETER_TOKEN,
+            ]
+    );
+}
+
+#[test]
+/// Tests if inlay hints for inferred types have been computed correctly.
+fn inlay_hints_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    let range = Range::new(Position::new(0, 0), Position::new(12, 0));
+    let hints = inlay_hints(&symbols, cpath.to_str().unwrap(), range)
+        .into_iter()
+        .map(|h| (h.position.line, h.position.character, h.label))
+        .collect::<Vec<_>>();
+    assert!(
+        hints
+            == vec![
+                (4, 13, ": vector<u64>".to_string()),
+                (4, 29, "<u64>".to_string()),
+                (5, 25, "<u64>".to_string()),
+                (7, 22, "<u64>".to_string()),
+                (8, 15, ": u64".to_string()),
+                (8, 32, "<u64>".to_string()),
+            ]
+    );
+
+    // only hints within the requested range are returned
+    let range = Range::new(Position::new(8, 0), Position::new(9, 0));
+    let hints = inlay_hints(&symbols, cpath.to_str().unwrap(), range);
+    assert!(hints.len() == 2);
+}
+
+#[test]
+/// Tests if types of expressions covering given ranges have been computed correctly.
+fn type_at_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+    let type_at = |start: (u32, u32), end: (u32, u32)| {
+        let range = Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1));
+        type_at(&symbols, cpath.to_str().unwrap(), &range)
+            .map(|t| (t.range.start.character, t.range.end.character, t.ty))
+    };
+
+    // cursor on a variable passed as an argument
+    assert!(type_at((7, 39), (7, 39)) == Some((39, 40, "vector<u64>".to_string())));
+    // selection within a call covers the whole call
+    assert!(type_at((8, 18), (8, 30)) == Some((18, 44, "u64".to_string())));
+    // no expression outside of function bodies
+    assert!(type_at((3, 4), (3, 4)).is_none());
+}
+
+#[test]
+/// Tests if signature help is computed correctly both for calls present in the compiled program
+/// and for calls that are only being typed.
+fn signature_help_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    // `vector::push_back(&mut v, 42)` call (instantiated signature)
+    let call = CallContext {
+        path: vec!["vector".to_string(), "push_back".to_string()],
+        name_start: Position::new(5, 16),
+        active_parameter: 1,
+    };
+    let help = signature_help(&symbols, cpath.to_str().unwrap(), &call).unwrap();
+    assert!(help.signatures.len() == 1);
+    let sig = &help.signatures[0];
+    assert!(sig.label == "fun std::vector::push_back<u64>(v: &mut vector<u64>, e: u64)");
+    assert!(sig.active_parameter == Some(1));
+    let params = sig.parameters.as_ref().unwrap();
+    assert!(params.len() == 2);
+    assert!(params[1].label == lsp_types::ParameterLabel::LabelOffsets([53, 59]));
+
+    // call not present in the compiled program (generic signature)
+    let call = CallContext {
+        path: vec!["vector".to_string(), "length".to_string()],
+        name_start: Position::new(11, 8),
+        active_parameter: 0,
+    };
+    let help = signature_help(&symbols, cpath.to_str().unwrap(), &call).unwrap();
+    assert!(help.signatures.len() == 1);
+    assert!(
+        help.signatures[0].label == "fun std::vector::length<Element>(v: & vector<Element>): u64"
+    );
+
+    // unknown function
+    let call = CallContext {
+        path: vec!["no_such_function".to_string()],
+        name_start: Position::new(11, 8),
+        active_parameter: 0,
+    };
+    assert!(signature_help(&symbols, cpath.to_str().unwrap(), &call).is_none());
+}
+
+#[test]
+/// Tests if struct literals are resolved to the right struct definitions.
+fn literal_structs_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M1.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    // unqualified struct name
+    let structs = literal_structs(
+        &symbols,
+        cpath.to_str().unwrap(),
+        &["OuterStruct".to_string()],
+    );
+    assert!(structs.len() == 1);
+    assert!(structs[0].0 == "Symbols::M1::OuterStruct");
+    let fields = &structs[0].1.fields;
+    assert!(fields.len() == 1);
+    assert!(fields[0].0.as_str() == "some_struct");
+
+    // qualified struct name (from another module)
+    let structs = literal_structs(
+        &symbols,
+        cpath.to_str().unwrap(),
+        &["M2".to_string(), "SomeOtherStruct".to_string()],
+    );
+    assert!(structs.len() == 1);
+    assert!(structs[0].0 == "Symbols::M2::SomeOtherStruct");
+
+    // wrong module
+    let structs = literal_structs(
+        &symbols,
+        cpath.to_str().unwrap(),
+        &["M2".to_string(), "OuterStruct".to_string()],
+    );
+    assert!(structs.is_empty());
+}
+
+#[test]
+/// Tests if symbols of multiple packages are merged correctly.
+fn merge_symbols_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut merged = Symbolicator::empty_symbols();
+    merged.merge(symbols.clone());
+    // merging the same package again (as if it was a shared dependency) does not duplicate
+    // information
+    merged.merge(symbols.clone());
+
+    assert!(merged.file_use_defs.len() == symbols.file_use_defs.len());
+    assert!(merged.references == symbols.references);
+    assert!(
+        merged
+            .struct_fields
+            .get(&Symbol::from("OuterStruct"))
+            .unwrap()
+            .len()
+            == 1
+    );
+    assert!(
+        merged.symbol_index.query("OuterStruct").len()
+            == symbols.symbol_index.query("OuterStruct").len()
+    );
+}
+
+#[test]
+/// Tests if unsaved buffer contents are symbolicated instead of on-disk file contents.
+fn source_overlay_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    // a type error in the unsaved buffer is reported
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        cpath.clone(),
+        fs::read_to_string(&fpath)
+            .unwrap()
+            .replace("push_back(&mut v, 42)", "push_back(&mut v, true)"),
+    );
+    let (symbols_opt, diagnostics) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+    )
+    .unwrap();
+    assert!(symbols_opt.unwrap().broken_files.contains(&cpath));
+    assert!(diagnostics.values().any(|diags| !diags.is_empty()));
+
+    // symbols reflect the unsaved buffer
+    source_overlay.insert(
+        cpath.clone(),
+        fs::read_to_string(&fpath)
+            .unwrap()
+            .replace("let len = ", "let length = ")
+            .replace("        len\n", "        length\n"),
+    );
+    let (symbols_opt, _) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+    )
+    .unwrap();
+    let symbols = symbols_opt.unwrap();
+    let mod_symbols = symbols.file_use_defs.get(&cpath).unwrap();
+    // renamed local variable definition spans the length of its new name
+    assert!(mod_symbols
+        .get(8)
+        .unwrap()
+        .iter()
+        .any(|u| u.col_start == 12 && u.col_end == 18));
+}
+
+#[test]
+/// Tests if symbols are stored in and loaded from the cache.
+fn symbols_cache_test() {
+    use lsp_types::DiagnosticSeverity;
+
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let cache_dir = tempdir().unwrap();
+    let cache_path = cache_dir.path().join(SYMBOLS_CACHE_FILE);
+
+    // symbols computed for unsaved contents are not cached
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        fs::canonicalize(&fpath).unwrap(),
+        fs::read_to_string(&fpath).unwrap() + "\n",
+    );
+    let (symbols_opt, _) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        Some(cache_dir.path()),
+    )
+    .unwrap();
+    assert!(symbols_opt.is_some());
+    assert!(!cache_path.exists());
+
+    let (symbols_opt, _) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &BTreeMap::new(),
+        &BuildConfig::default(),
+        Some(cache_dir.path()),
+    )
+    .unwrap();
+    let symbols = symbols_opt.unwrap();
+    assert!(cache_path.exists());
+
+    let build_config = BuildConfig {
+        test_mode: true,
+        ..Default::default()
+    };
+    let resolution_graph = build_config
+        .clone()
+        .resolution_graph_for_package(&path)
+        .unwrap();
+    let key = symbols_cache_key(&build_config, &resolution_graph, &symbols.file_name_mapping);
+    let cached_symbols = cache::load::<Symbols>(&cache_path, &key).unwrap();
+    assert!(
+        serde_json::to_value(&symbols).unwrap() == serde_json::to_value(&cached_symbols).unwrap()
+    );
+
+    // cached symbols are returned (rather than recomputed) if sources did not change
+    let mut modified_symbols = cached_symbols;
+    modified_symbols.def_docs.clear();
+    cache::store(&cache_path, &key, &modified_symbols).unwrap();
+    let (symbols_opt, diagnostics) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &BTreeMap::new(),
+        &BuildConfig::default(),
+        Some(cache_dir.path()),
+    )
+    .unwrap();
+    assert!(symbols_opt.unwrap().def_docs.is_empty());
+    assert!(diagnostics
+        .values()
+        .flatten()
+        .all(|d| d.severity != Some(DiagnosticSeverity::Error)));
+
+    // ... and recomputed otherwise
+    let (symbols_opt, _) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        Some(cache_dir.path()),
+    )
+    .unwrap();
+    assert!(!symbols_opt.unwrap().def_docs.is_empty());
+}
+
+#[test]
+/// Tests if documentation comments are associated with definitions.
+fn def_docs_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    let mod_symbols = symbols.file_use_defs.get(&cpath).unwrap();
+    let doc_at = |line, col| {
+        mod_symbols
+            .get(line)
+            .unwrap()
+            .iter()
+            .find(|u| u.col_start <= col && col < u.col_end)
+            .and_then(|u| symbols.def_docs.get(&u.def_loc).cloned())
+    };
+
+    // function defined in a dependency
+    assert!(doc_at(5, 18) == Some("Add element `e` to the end of the vector `v`.".to_string()));
+    // struct
+    assert!(doc_at(20, 33) == Some("A documented struct.".to_string()));
+    // constant
+    assert!(doc_at(21, 18) == Some("A documented constant.".to_string()));
+    // multi-line comment (at definition)
+    assert!(doc_at(20, 15) == Some("A documented function,\nspanning multiple lines.".to_string()));
+    // no comment
+    assert!(doc_at(3, 8).is_none());
+}
+
+#[test]
+/// Tests if definitions in dependencies are located in real (canonical) file paths.
+fn dependency_def_path_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    let mut dep_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    dep_path.push("../move-stdlib/sources/vector.move");
+    let dep_cpath = fs::canonicalize(&dep_path).unwrap();
+
+    // vector::push_back
+    let mod_symbols = symbols.file_use_defs.get(&cpath).unwrap();
+    let use_def = mod_symbols
+        .get(5)
+        .unwrap()
+        .into_iter()
+        .find(|u| u.col_start == 16)
+        .unwrap();
+    let def_path = symbols
+        .file_name_mapping
+        .get(&use_def.def_loc.fhash)
+        .unwrap();
+    assert!(Path::new(def_path.as_str()) == dep_cpath);
+    // dependency file is symbolicated as well
+    assert!(symbols.file_use_defs.contains_key(&dep_cpath));
+}
+
+#[test]
+/// Tests if fields of struct values are resolved for completion.
+fn field_completions_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+    let cpath = cpath.to_str().unwrap();
+
+    // `w` in `w.inner.value` (of type `Wrapper<DocumentedStruct>`)
+    let root_start = Position::new(27, 8);
+    let fields = |chain: &[&str], mod_name| {
+        let chain = chain.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        field_completions(&symbols, cpath, &root_start, &chain, mod_name)
+    };
+
+    let wrapper_fields = fields(&[], Some("M6"));
+    assert!(wrapper_fields.len() == 2);
+    assert!(wrapper_fields[0].0 == Symbol::from("inner"));
+    assert!(wrapper_fields[0].1 == "Symbols::M6::DocumentedStruct");
+    assert!(wrapper_fields[1].0 == Symbol::from("count"));
+    assert!(wrapper_fields[1].1 == "u64");
+    // nested chain (through a type parameter)
+    let inner_fields = fields(&["inner"], Some("M6"));
+    assert!(inner_fields.len() == 1);
+    assert!(inner_fields[0].0 == Symbol::from("value"));
+    // not a struct
+    assert!(fields(&["count"], Some("M6")).is_empty());
+    // fields are not accessible outside of the defining module
+    assert!(fields(&[], Some("M2")).is_empty());
+}
+
+#[test]
+/// Tests if only files affected by changes are recompiled, with symbols of the remaining files
+/// retained from the compilation base.
+fn incremental_symbols_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let mut base = None;
+    let (symbols_opt, _) = Symbolicator::get_symbols_incremental(
+        path.as_path(),
+        &BTreeMap::new(),
+        &BuildConfig::default(),
+        None,
+        &mut base,
+        &|| false,
+    )
+    .unwrap();
+    let full_symbols = symbols_opt.unwrap();
+    assert!(base.is_some());
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M2.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+    let contents = fs::read_to_string(&fpath).unwrap();
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        cpath,
+        contents.replacen("{", "{\n    public fun added() {}\n", 1),
+    );
+    let (symbols_opt, _) = Symbolicator::get_symbols_incremental(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+        &mut base,
+        &|| false,
+    )
+    .unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    // the base was not replaced, so the package was compiled incrementally
+    let (_, base_hash) = base
+        .as_ref()
+        .unwrap()
+        .file_hashes
+        .iter()
+        .find(|(fname, _)| fname.as_str().ends_with("M2.move"))
+        .unwrap();
+    assert!(*base_hash == FileHash::new(&contents));
+    // the change is reflected while symbols of unaffected files are retained
+    let members = symbols.module_index().members("Symbols", "M2").unwrap();
+    assert!(members.iter().any(|m| m.name == "added"));
+    assert!(symbols.file_use_defs.len() == full_symbols.file_use_defs.len());
+    assert!(symbols.module_index().members("std", "vector").is_some());
+
+    // removing a module requires compiling the whole package again
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        fs::canonicalize(&fpath).unwrap(),
+        contents.replace("module Symbols::M2", "module Symbols::Renamed"),
+    );
+    let (symbols_opt, _) = Symbolicator::get_symbols_incremental(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+        &mut base,
+        &|| false,
+    )
+    .unwrap();
+    // M1 uses the removed module
+    let m1_path = fs::canonicalize(path.join("sources/M1.move")).unwrap();
+    assert!(symbols_opt.unwrap().broken_files.contains(&m1_path));
+}
+
+#[test]
+/// Tests if files compiling without errors are symbolicated despite errors in other files, while
+/// symbols of the files with errors are taken from previous symbols.
+fn broken_files_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let full_symbols = symbols_opt.unwrap();
+
+    // a parsing error in M6 and a type error in M2 (used by M1)
+    let m6_path = fs::canonicalize(path.join("sources/M6.move")).unwrap();
+    let m2_path = fs::canonicalize(path.join("sources/M2.move")).unwrap();
+    let m1_path = fs::canonicalize(path.join("sources/M1.move")).unwrap();
+    let m3_path = fs::canonicalize(path.join("sources/M3.move")).unwrap();
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        m6_path.clone(),
+        fs::read_to_string(&m6_path)
+            .unwrap()
+            .replace("let v =", "let v"),
+    );
+    source_overlay.insert(
+        m2_path.clone(),
+        fs::read_to_string(&m2_path).unwrap()
+            + "\nmodule Symbols::Broken { fun f(): u64 { true } }\n",
+    );
+    let (symbols_opt, diagnostics) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+    )
+    .unwrap();
+    let mut symbols = symbols_opt.unwrap();
+    assert!(symbols.broken_files.contains(&m6_path));
+    assert!(symbols.broken_files.contains(&m2_path));
+    assert!(symbols.broken_files.contains(&m1_path));
+    // errors are reported only where they are, rather than in files depending on broken files
+    assert!(diagnostics
+        .iter()
+        .any(|(f, d)| f.as_str() == m6_path.to_str().unwrap() && !d.is_empty()));
+    assert!(diagnostics
+        .iter()
+        .any(|(f, d)| f.as_str() == m2_path.to_str().unwrap() && !d.is_empty()));
+    assert!(diagnostics
+        .iter()
+        .all(|(f, d)| f.as_str() != m1_path.to_str().unwrap() || d.is_empty()));
+    // files without errors are symbolicated
+    assert!(symbols.file_use_defs.contains_key(&m3_path));
+    assert!(!symbols.file_use_defs.contains_key(&m6_path));
+
+    symbols.fill_broken_files(&full_symbols);
+    assert!(symbols.file_use_defs.get(&m6_path).is_some());
+    assert!(symbols.file_use_defs.get(&m1_path).is_some());
+    assert!(!symbols.file_outline(&m6_path).is_empty());
+}
+
+#[test]
+/// Tests if compiler warnings are reported, including those about files not recompiled
+/// incrementally.
+fn warnings_test() {
+    use lsp_types::{DiagnosticSeverity, DiagnosticTag};
+
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let m3_path = fs::canonicalize(path.join("sources/M3.move")).unwrap();
+    let m2_path = fs::canonicalize(path.join("sources/M2.move")).unwrap();
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        m3_path.clone(),
+        fs::read_to_string(&m3_path).unwrap()
+            + "\nmodule Symbols::Unused { fun f() { let unused_local = 1; } }\n",
+    );
+    let m3_warning = |diagnostics: &BTreeMap<Symbol, Vec<Diagnostic>>| {
+        diagnostics
+            .iter()
+            .filter(|(f, _)| f.as_str() == m3_path.to_str().unwrap())
+            .flat_map(|(_, d)| d)
+            .find(|d| d.message.contains("unused_local"))
+            .cloned()
+    };
+
+    let mut base = None;
+    let (symbols_opt, diagnostics) = Symbolicator::get_symbols_incremental(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+        &mut base,
+        &|| false,
+    )
+    .unwrap();
+    let warning = m3_warning(&diagnostics).unwrap();
+    assert!(warning.severity == Some(DiagnosticSeverity::Warning));
+    assert!(warning.tags == Some(vec![DiagnosticTag::Unnecessary]));
+    assert!(!symbols_opt.unwrap().warnings.is_empty());
+
+    source_overlay.insert(
+        m2_path.clone(),
+        fs::read_to_string(&m2_path)
+            .unwrap()
+            .replacen("{", "{\n    public fun added() {}\n", 1),
+    );
+    let (_, diagnostics) = Symbolicator::get_symbols_incremental(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+        &mut base,
+        &|| false,
+    )
+    .unwrap();
+    assert!(m3_warning(&diagnostics).is_some());
+}
+
+#[test]
+/// Tests if declarations of constants are shown along with their values.
+fn const_values_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let m1_path = fs::canonicalize(path.join("sources/M1.move")).unwrap();
+    let m6_path = fs::canonicalize(path.join("sources/M6.move")).unwrap();
+    let mut source_overlay = BTreeMap::new();
+    source_overlay.insert(
+        m1_path.clone(),
+        fs::read_to_string(&m1_path)
+            .unwrap()
+            .replace("SOME_CONST: u64 = 42;", "SOME_CONST: u64 = 1 << 32;"),
+    );
+    let (symbols_opt, _) = Symbolicator::get_symbols_with_overlay(
+        path.as_path(),
+        &source_overlay,
+        &BuildConfig::default(),
+        None,
+    )
+    .unwrap();
+    let symbols = symbols_opt.unwrap();
+    let value_at = |fpath: &PathBuf, line, col| {
+        symbols
+            .file_use_defs
+            .get(fpath)
+            .unwrap()
+            .get(line)
+            .unwrap()
+            .iter()
+            .find(|u| u.col_start <= col && col < u.col_end)
+            .and_then(|u| symbols.const_values.get(&u.def_loc).cloned())
+    };
+
+    // evaluated value (at a use)
+    assert!(
+        value_at(&m1_path, 20, 43)
+            == Some("const SOME_CONST: u64 = 1 << 32 (4294967296)".to_string())
+    );
+    // literal value
+    assert!(value_at(&m6_path, 21, 18) == Some("const DOCUMENTED_CONST: u64 = 42".to_string()));
+
+    // abort code
+    let m6_source = fs::read_to_string(&m6_path).unwrap();
+    let hover = abort_code_hover(
+        &symbols,
+        m6_path.to_str().unwrap(),
+        &m6_source,
+        &Position::new(42, 15),
+    )
+    .unwrap();
+    assert!(hover.range == Some(Range::new(Position::new(42, 14), Position::new(42, 16))));
+    match hover.contents {
+        HoverContents::Markup(markup) => {
+            assert!(markup.value.contains("const DOCUMENTED_CONST: u64 = 42"))
+        }
+        _ => panic!("unexpected hover contents"),
+    }
+}
+
+#[test]
+fn constant_declaration_test() {
+    use move_core_types::account_address::AccountAddress;
+
+    assert_eq!(
+        constant_declaration(
+            "const ADDRS: vector<address> =\n    vector[@0x1, @0x2];",
+            &MoveValue::Vector(vec![
+                MoveValue::Address(AccountAddress::ONE),
+                MoveValue::Address(AccountAddress::from_hex_literal("0x2").unwrap()),
+            ])
+        ),
+        "const ADDRS: vector<address> = vector[@0x1, @0x2]"
+    );
+    assert_eq!(
+        constant_declaration("const B: bool = 1 == 2;", &MoveValue::Bool(false)),
+        "const B: bool = 1 == 2 (false)"
+    );
+}
+
+#[test]
+/// Tests if cancelled symbolication is abandoned without replacing the compilation base.
+fn cancelled_symbolication_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let mut base = None;
+    let err = Symbolicator::get_symbols_incremental(
+        path.as_path(),
+        &BTreeMap::new(),
+        &BuildConfig::default(),
+        None,
+        &mut base,
+        &|| true,
+    )
+    .err()
+    .unwrap();
+    assert!(err.is::<SymbolicationCancelled>());
+    assert!(base.is_none());
+}
+
+#[test]
+/// Tests if files depending on other files are found.
+fn dependents_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let file = |name: &str| {
+        let mut fpath = path.clone();
+        fpath.push("sources");
+        fpath.push(name);
+        fs::canonicalize(&fpath).unwrap()
+    };
+    let dependents = symbols.dependents(&BTreeSet::from([file("M2.move")]));
+    assert!(dependents.contains(&file("M1.move")));
+    assert!(!dependents.contains(&file("M3.move")));
+    assert!(!dependents.contains(&file("M2.move")));
+    // files of dependencies have dependents as well
+    let vector = symbols
+        .file_dependents
+        .keys()
+        .find(|f| f.ends_with("vector.move"))
+        .unwrap();
+    assert!(symbols
+        .dependents(&BTreeSet::from([vector.clone()]))
+        .contains(&file("M6.move")));
+}
+
+#[test]
+/// Tests if only changed diagnostics and diagnostics of affected files are published.
+fn diagnostics_to_publish_test() {
+    let diag = |msg: &str| {
+        Diagnostic::new(
+            Range::default(),
+            None,
+            None,
+            None,
+            msg.to_string(),
+            None,
+            None,
+        )
+    };
+    let a = Symbol::from("/a.move");
+    let b = Symbol::from("/b.move");
+    let c = Symbol::from("/c.move");
+    let mut published = BTreeMap::new();
+
+    // everything is published on the first run
+    let first = BTreeMap::from([(a, vec![diag("a")]), (b, vec![diag("b")]), (c, vec![])]);
+    assert_eq!(
+        diagnostics_to_publish(&mut published, first.clone(), false, None),
+        first
+    );
+
+    // `a` was not checked and is not affected, so its diagnostics are kept, while diagnostics of
+    // `b` are cleared as it is affected
+    let affected = BTreeSet::from([PathBuf::from("/b.move")]);
+    let second = BTreeMap::from([(a, vec![]), (b, vec![]), (c, vec![diag("c")])]);
+    assert_eq!(
+        diagnostics_to_publish(&mut published, second, false, Some(&affected)),
+        BTreeMap::from([(b, vec![]), (c, vec![diag("c")])])
+    );
+    assert_eq!(published[&a], vec![diag("a")]);
+
+    // all diagnostics are cleared once the package compiles, and removed files are cleared too
+    let third = BTreeMap::from([(a, vec![]), (b, vec![])]);
+    assert_eq!(
+        diagnostics_to_publish(&mut published, third, true, Some(&BTreeSet::new())),
+        BTreeMap::from([(a, vec![]), (c, vec![])])
+    );
+}
+
+#[test]
+/// Tests if modules and their members are indexed.
+fn module_index_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let member = |address, module, name: &str| {
+        symbols
+            .module_index()
+            .members(address, module)
+            .unwrap()
+            .iter()
+            .find(|m| m.name == name)
+            .cloned()
+    };
+
+    let documented_fun = member("Symbols", "M6", "documented_fun").unwrap();
+    assert!(documented_fun.kind == MemberKind::Function && documented_fun.public);
+    let inferred_types = member("Symbols", "M6", "inferred_types").unwrap();
+    assert!(!inferred_types.public);
+    let documented_const = member("Symbols", "M6", "DOCUMENTED_CONST").unwrap();
+    assert!(documented_const.kind == MemberKind::Constant && !documented_const.public);
+    // modules are indexed under both named and numerical addresses
+    assert!(member("Symbols", "M6", "Wrapper").unwrap().kind == MemberKind::Struct);
+    assert!(member("0xCAFE", "M6", "Wrapper").is_some());
+    assert!(member("std", "vector", "push_back").unwrap().public);
+
+    // named addresses of the package graph are indexed along with their values
+    let addresses = symbols
+        .module_index()
+        .named_addresses()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect::<Vec<_>>();
+    assert!(addresses.contains(&("std".to_string(), "0x1".to_string())));
+    assert!(addresses.contains(&("Symbols".to_string(), "0xcafe".to_string())));
+}
+
+#[test]
+/// Tests if calls of a function and calls made by a function are found.
+fn call_hierarchy_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M6.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    // the called function is found from its call
+    let item = symbols
+        .prepare_call_hierarchy(&cpath, &Position::new(32, 24))
+        .unwrap();
+    assert!(item.name == "documented_fun");
+    assert!(item.selection_range.start == Position::new(20, 15));
+    assert!(item.detail.as_deref() == Some("Symbols::M6"));
+    // ... as well as from its definition
+    assert!(symbols.prepare_call_hierarchy(&cpath, &Position::new(20, 15)) == Some(item.clone()));
+    // there is no item for a struct
+    assert!(symbols
+        .prepare_call_hierarchy(&cpath, &Position::new(13, 12))
+        .is_none());
+
+    let incoming = symbols.incoming_calls(&item);
+    assert!(incoming.len() == 1);
+    assert!(incoming[0].from.name == "passing_test");
+    assert!(
+        incoming[0].from_ranges == vec![Range::new(Position::new(32, 16), Position::new(32, 30))]
+    );
+
+    let outgoing = symbols.outgoing_calls(&incoming[0].from);
+    assert!(outgoing.len() == 1);
+    assert!(outgoing[0].to == item);
+    assert!(outgoing[0].from_ranges == incoming[0].from_ranges);
+
+    // the function does not call any other function
+    assert!(symbols.outgoing_calls(&item).is_empty());
+}
+
+#[test]
+/// Tests if occurrences of identifiers are highlighted (distinguishing reads and writes of locals).
+fn document_highlights_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let highlights = |fname: &str, line: u32, col: u32| {
+        let mut fpath = path.clone();
+        fpath.push(fname);
+        let cpath = fs::canonicalize(&fpath).unwrap();
+        symbols
+            .document_highlights(&cpath, &Position::new(line, col))
+            .map(|highlights| {
+                highlights
+                    .into_iter()
+                    .map(|h| (h.range.start.line, h.range.start.character, h.kind.unwrap()))
+                    .collect::<Vec<_>>()
+            })
+    };
+    use DocumentHighlightKind as K;
+
+    // a local that is re-assigned (and shadowed in an inner block)
+    assert!(
+        highlights("sources/M4.move", 20, 15)
+            == Some(vec![
+                (18, 12, K::Write),
+                (20, 15, K::Read),
+                (23, 26, K::Read),
+                (26, 12, K::Write),
+                (29, 8, K::Read),
+            ])
+    );
+    // a local that is mutably borrowed
+    assert!(
+        highlights("sources/M1.move", 58, 8)
+            == Some(vec![
+                (55, 12, K::Write),
+                (56, 21, K::Write),
+                (58, 8, K::Read)
+            ])
+    );
+    // a function
+    assert!(
+        highlights("sources/M6.move", 20, 15) == Some(vec![(20, 15, K::Text), (32, 16, K::Text)])
+    );
+    // no identifier
+    assert!(highlights("sources/M6.move", 20, 0).is_none());
+}
+
+#[test]
+/// Tests if symbolication information for identifiers in specifications has been constructed
+/// correctly.
+fn spec_symbols_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let (symbols_opt, _) = Symbolicator::get_symbols(path.as_path()).unwrap();
+    let symbols = symbols_opt.unwrap();
+
+    let mut fpath = path.clone();
+    fpath.push("sources/M7.move");
+    let cpath = fs::canonicalize(&fpath).unwrap();
+
+    let mod_symbols = symbols.file_use_defs.get(&cpath).unwrap();
+
+    // function parameter in an inline spec
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        5,
+        22,
+        4,
+        41,
+        "M7.move",
+        "u64",
+        None,
+    );
+    // specified function
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        9,
+        9,
+        4,
+        8,
+        "M7.move",
+        "fun Symbols::M7::increment(&mut Symbols::M7::Counter, u64)",
+        None,
+    );
+    // schema name in include
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        10,
+        16,
+        16,
+        16,
+        "M7.move",
+        "schema StepPositive",
+        None,
+    );
+    // function parameter in a function spec
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        11,
+        16,
+        4,
+        18,
+        "M7.move",
+        "&mut Symbols::M7::Counter",
+        Some((2, 11, "M7.move")),
+    );
+    // spec function
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        12,
+        16,
+        23,
+        12,
+        "M7.move",
+        "spec fun double(x: u64): u64",
+        None,
+    );
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        1,
+        12,
+        23,
+        4,
+        41,
+        "M7.move",
+        "u64",
+        None,
+    );
+    // global variable
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        13,
+        16,
+        22,
+        15,
+        "M7.move",
+        "global total_steps: u64",
+        None,
+    );
+    // schema variable
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        0,
+        18,
+        17,
+        17,
+        8,
+        "M7.move",
+        "step: u64",
+        None,
+    );
+    // spec function parameter
+    assert_use_def(
+        mod_symbols,
+        &symbols.file_name_mapping,
+        2,
+        23,
+        34,
+        23,
+        19,
+        "M7.move",
+        "x: u64",
+        None,
+    );
+}
@@ -0,0 +1,208 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Custom `move-analyzer/typeAt` request returning the type of the innermost expression covering
+//! a given range, so that clients can show types of arbitrary expressions (rather than only those
+//! of identifiers shown on hover).
+//!
+//! Types of all expressions in function bodies and constant definitions are collected from the
+//! typed AST during symbolication.
+
+use crate::{inlay_hints::type_hint_string, utils::get_range};
+use codespan_reporting::files::SimpleFiles;
+use lsp_types::{Range, TextDocumentIdentifier};
+use move_command_line_common::files::FileHash;
+use move_compiler::typing::ast::{
+    Exp, ExpListItem, FunctionBody_, LValue, LValueList, LValue_, ModuleDefinition, SequenceItem,
+    SequenceItem_, UnannotatedExp_,
+};
+use move_ir_types::location::Loc;
+use move_symbol_pool::Symbol;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+/// The `move-analyzer/typeAt` request
+pub enum TypeAtRequest {}
+
+impl lsp_types::request::Request for TypeAtRequest {
+    type Params = TypeAtParams;
+    type Result = Option<ExpType>;
+    const METHOD: &'static str = "move-analyzer/typeAt";
+}
+
+/// Parameters of the type-at request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeAtParams {
+    pub text_document: TextDocumentIdentifier,
+    /// The selected range (empty if there is no selection, in which case it's the cursor position)
+    pub range: Range,
+}
+
+/// Type of an expression
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExpType {
+    /// Range of the whole expression
+    pub range: Range,
+    /// Fully-instantiated type of the expression
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// Collects types of expressions in a module, keyed by file hash
+pub struct ExpTypeCollector<'a> {
+    files: &'a SimpleFiles<Symbol, String>,
+    file_id_mapping: &'a HashMap<FileHash, usize>,
+    types: BTreeMap<FileHash, Vec<ExpType>>,
+}
+
+impl<'a> ExpTypeCollector<'a> {
+    pub fn new(
+        files: &'a SimpleFiles<Symbol, String>,
+        file_id_mapping: &'a HashMap<FileHash, usize>,
+    ) -> Self {
+        Self {
+            files,
+            file_id_mapping,
+            types: BTreeMap::new(),
+        }
+    }
+
+    /// Returns collected expression types (sorted by range)
+    pub fn finish(mut self) -> BTreeMap<FileHash, Vec<ExpType>> {
+        for types in self.types.values_mut() {
+            types.sort_by_key(|t| (t.range.start, t.range.end));
+            types.dedup();
+        }
+        self.types
+    }
+
+    /// Collects types of expressions in all function bodies and constants of a module
+    pub fn mod_types(&mut self, mod_def: &ModuleDefinition) {
+        for (_, _, fun) in &mod_def.functions {
+            if let FunctionBody_::Defined(sequence) = &fun.body.value {
+                for seq_item in sequence {
+                    self.seq_item_types(seq_item);
+                }
+            }
+        }
+        for (_, _, c) in &mod_def.constants {
+            self.exp_types(&c.value);
+        }
+    }
+
+    fn seq_item_types(&mut self, seq_item: &SequenceItem) {
+        use SequenceItem_ as I;
+        match &seq_item.value {
+            I::Seq(e) => self.exp_types(e),
+            I::Declare(lvalues) => self.lvalue_list_types(lvalues),
+            I::Bind(lvalues, _, e) => {
+                self.lvalue_list_types(lvalues);
+                self.exp_types(e);
+            }
+        }
+    }
+
+    fn lvalue_list_types(&mut self, lvalues: &LValueList) {
+        for lval in &lvalues.value {
+            self.lvalue_types(lval);
+        }
+    }
+
+    fn lvalue_types(&mut self, lval: &LValue) {
+        match &lval.value {
+            LValue_::Var(var, t) => self.add_type(&var.loc(), type_hint_string(t)),
+            LValue_::Unpack(_, _, _, fields) | LValue_::BorrowUnpack(_, _, _, _, fields) => {
+                for (_, _, (_, (_, lval))) in fields {
+                    self.lvalue_types(lval);
+                }
+            }
+            LValue_::Ignore => (),
+        }
+    }
+
+    fn exp_types(&mut self, exp: &Exp) {
+        use UnannotatedExp_ as E;
+        self.add_type(&exp.exp.loc, type_hint_string(&exp.ty));
+        match &exp.exp.value {
+            E::ModuleCall(mod_call) => self.exp_types(&mod_call.arguments),
+            E::Builtin(_, e) => self.exp_types(e),
+            E::Vector(_, _, _, e) => self.exp_types(e),
+            E::IfElse(cond, t, f) => {
+                self.exp_types(cond);
+                self.exp_types(t);
+                self.exp_types(f);
+            }
+            E::While(cond, body) => {
+                self.exp_types(cond);
+                self.exp_types(body);
+            }
+            E::Loop { body, .. } => self.exp_types(body),
+            E::Block(sequence) => {
+                for seq_item in sequence {
+                    self.seq_item_types(seq_item);
+                }
+            }
+            E::Assign(lvalues, _, e) => {
+                self.lvalue_list_types(lvalues);
+                self.exp_types(e);
+            }
+            E::Mutate(lhs, rhs) => {
+                self.exp_types(lhs);
+                self.exp_types(rhs);
+            }
+            E::Return(e) => self.exp_types(e),
+            E::Abort(e) => self.exp_types(e),
+            E::Dereference(e) => self.exp_types(e),
+            E::UnaryExp(_, e) => self.exp_types(e),
+            E::BinopExp(lhs, _, _, rhs) => {
+                self.exp_types(lhs);
+                self.exp_types(rhs);
+            }
+            E::Pack(_, _, _, fields) => {
+                for (_, _, (_, (_, e))) in fields {
+                    self.exp_types(e);
+                }
+            }
+            E::ExpList(list_items) => {
+                for item in list_items {
+                    match item {
+                        ExpListItem::Single(e, _) => self.exp_types(e),
+                        ExpListItem::Splat(_, e, _) => self.exp_types(e),
+                    }
+                }
+            }
+            E::Borrow(_, e, _) => self.exp_types(e),
+            E::TempBorrow(_, e) => self.exp_types(e),
+            E::Cast(e, _) => self.exp_types(e),
+            E::Annotate(e, _) => self.exp_types(e),
+            _ => (),
+        }
+    }
+
+    /// Records the type (if it has been fully inferred) of an expression at the given location
+    fn add_type(&mut self, loc: &Loc, ty: Option<String>) {
+        let ty = match ty {
+            Some(ty) => ty,
+            None => return,
+        };
+        if let Some(range) = get_range(loc, self.files, self.file_id_mapping) {
+            self.types
+                .entry(loc.file_hash())
+                .or_insert_with(Vec::new)
+                .push(ExpType { range, ty });
+        }
+    }
+}
+
+/// Returns the type of the innermost expression (among those whose types are given) covering the
+/// given range
+pub fn innermost_exp_type<'a>(types: &'a [ExpType], range: &Range) -> Option<&'a ExpType> {
+    types
+        .iter()
+        .filter(|t| t.range.start <= range.start && range.end <= t.range.end)
+        .max_by_key(|t| (t.range.start, Reverse(t.range.end)))
+}
@@ -0,0 +1,646 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Running unit tests on behalf of the client, either individually (e.g., when the user clicks a
+//! code lens displayed above a test function) or for a whole package or module (e.g., from a test
+//! explorer of the editor).
+//!
+//! Tests are run on a separate thread as running them may take a long time. When running multiple
+//! tests, the outcome of each test is sent to the client in a custom notification as soon as the
+//! test finishes. The request to run tests is answered once all of them finish, and the user is
+//! additionally notified about the outcome of an individual test. Either way, the status of each
+//! test (along with the gas it used) is also sent in a custom notification keyed by the location of
+//! the test function, so that editor extensions can decorate test functions with their outcomes.
+
+use crate::{
+    abort_code::{abort_code, annotate_abort_codes},
+    context::Context,
+    prover::{on_prove_command, PROVE_COMMAND},
+    vfs::buffer_position,
+};
+use anyhow::{anyhow, Result};
+use crossbeam::channel::Sender;
+use lsp_server::{Message, Notification, Request, Response};
+use lsp_types::{
+    notification::Notification as _, ExecuteCommandParams, Location, MessageType, Range,
+    ShowMessageParams,
+};
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{FunctionDefinitionIndex, TableIndex},
+};
+use move_command_line_common::files::FileHash;
+use move_compiler::{
+    compiled_unit::NamedCompiledModule,
+    diagnostics::report_diagnostics_to_buffer,
+    shared::{NumberFormat, NumericalAddress},
+    unit_test::{plan_builder::construct_test_plan, ModuleTestPlan, TestPlan},
+    PASS_CFGIR,
+};
+use move_core_types::{account_address::AccountAddress, language_storage::ModuleId};
+use move_package::compilation::build_plan::BuildPlan;
+use move_unit_test::UnitTestingConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+use tempfile::tempdir;
+use tracing::{debug, error, info};
+use url::Url;
+
+/// Command running a single unit test
+pub const RUN_TEST_COMMAND: &str = "move-analyzer.runTest";
+
+/// Command running multiple unit tests (whose outcomes are reported in `TEST_RESULT_NOTIFICATION`)
+pub const RUN_TESTS_COMMAND: &str = "move-analyzer.runTests";
+
+/// Custom notification reporting the outcome of a single test run by `RUN_TESTS_COMMAND`
+pub const TEST_RESULT_NOTIFICATION: &str = "$/move/testResult";
+
+/// Custom notification reporting the status of a single test run by `RUN_TEST_COMMAND` or
+/// `RUN_TESTS_COMMAND` at the definition of the test function
+pub const TEST_STATUS_NOTIFICATION: &str = "$/move/testStatus";
+
+/// Arguments of the command running a single unit test
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunTestArgs {
+    /// Root directory of the package containing the test
+    pub package_path: PathBuf,
+    /// Name of the module containing the test
+    pub module_name: String,
+    /// Name of the test function
+    pub test_name: String,
+}
+
+/// Outcome of running a single unit test
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunTestResult {
+    pub passed: bool,
+    /// Output of the test runner (or compiler diagnostics if the package does not compile)
+    pub output: String,
+    /// Status of the test (sent in `TEST_STATUS_NOTIFICATION` rather than with the result)
+    #[serde(skip)]
+    pub status: Option<TestStatusParams>,
+}
+
+/// Arguments of the command running multiple unit tests (all tests of the package unless a module,
+/// or a test function, is given)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunTestsArgs {
+    /// Root directory of the package containing the tests
+    pub package_path: PathBuf,
+    /// Name of the module containing the tests
+    pub module_name: Option<String>,
+    /// Name of the test function
+    pub test_name: Option<String>,
+}
+
+/// Parameters of the notification reporting the outcome of a single test
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResultParams {
+    /// Root directory of the package containing the test
+    pub package_path: PathBuf,
+    /// Name of the module containing the test
+    pub module_name: String,
+    /// Name of the test function
+    pub test_name: String,
+    pub passed: bool,
+    /// Output of the test runner
+    pub output: String,
+    /// Status of the test (sent in `TEST_STATUS_NOTIFICATION` rather than with the result)
+    #[serde(skip)]
+    pub status: Option<TestStatusParams>,
+}
+
+/// Parameters of the notification reporting the status of a single test at its definition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestStatusParams {
+    /// Location of the name of the test function
+    pub location: Location,
+    pub passed: bool,
+    /// Gas used by the test (which is charged one unit per executed instruction)
+    pub gas_used: u64,
+}
+
+/// Outcome of running multiple unit tests
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunTestsResult {
+    /// Number of tests that passed
+    pub passed: usize,
+    /// Number of tests that failed
+    pub failed: usize,
+    /// Compiler diagnostics if the package does not compile (in which case no tests are run)
+    pub diagnostics: Option<String>,
+}
+
+/// Builds the package rooted at the given path in test mode, returning the plan of all of its tests
+/// along with the configuration to run them, or compiler diagnostics if the package does not
+/// compile.
+fn build_test_plan(package_path: &Path) -> Result<Result<(TestPlan, UnitTestingConfig), String>> {
+    let build_config = move_package::BuildConfig {
+        test_mode: true,
+        dev_mode: true,
+        install_dir: Some(tempdir().unwrap().path().to_path_buf()),
+        ..Default::default()
+    };
+    let resolution_graph = build_config.resolution_graph_for_package(package_path)?;
+
+    let mut unit_test_config = UnitTestingConfig::default_with_bound(None);
+    unit_test_config.named_address_values = resolution_graph
+        .extract_named_address_mapping()
+        .map(|(name, addr)| {
+            (
+                name.to_string(),
+                NumericalAddress::new(addr.into_bytes(), NumberFormat::Hex),
+            )
+        })
+        .collect();
+
+    // source files of all packages are needed to report source-mapped errors
+    let dep_file_map: HashMap<_, _> = resolution_graph
+        .package_table
+        .values()
+        .flat_map(|rpkg| {
+            rpkg.get_sources(&resolution_graph.build_options)
+                .unwrap()
+                .into_iter()
+                .filter_map(|fname| {
+                    let contents = fs::read_to_string(Path::new(fname.as_str())).ok()?;
+                    Some((FileHash::new(&contents), (fname, contents)))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let root_package = resolution_graph.root_package.package.name;
+
+    let build_plan = BuildPlan::create(resolution_graph)?;
+    let mut test_plan = None;
+    let mut diagnostics = None;
+    build_plan.compile_with_driver(&mut std::io::sink(), |compiler| {
+        let (files, compilation_result) = compiler.run::<PASS_CFGIR>()?;
+        let (_, compiler) = match compilation_result {
+            Ok(v) => v,
+            Err(diags) => {
+                diagnostics = Some(report_diagnostics_to_buffer(&files, diags));
+                return Ok((files, vec![]));
+            }
+        };
+        let (mut compiler, cfgir) = compiler.into_ast();
+        let compilation_env = compiler.compilation_env();
+        let built_test_plan = construct_test_plan(compilation_env, Some(root_package), &cfgir);
+        let units = match compiler.at_cfgir(cfgir).build() {
+            Ok((units, _)) => units,
+            Err(diags) => {
+                diagnostics = Some(report_diagnostics_to_buffer(&files, diags));
+                return Ok((files, vec![]));
+            }
+        };
+        test_plan = Some((built_test_plan, files.clone(), units.clone()));
+        Ok((files, units))
+    })?;
+
+    if let Some(diagnostics) = diagnostics {
+        return Ok(Err(String::from_utf8_lossy(&diagnostics).to_string()));
+    }
+    let (module_plans, mut files, units) = test_plan.unwrap();
+    files.extend(dep_file_map);
+    let test_plan = TestPlan::new(module_plans.unwrap_or_default(), files, units);
+    Ok(Ok((test_plan, unit_test_config)))
+}
+
+/// Runs the tests of the given module test plan (which must be a part of the given test plan). The
+/// status of the test is only returned if the module test plan consists of a single test.
+fn run_module_tests(
+    test_plan: &TestPlan,
+    module_plan: ModuleTestPlan,
+    unit_test_config: &UnitTestingConfig,
+) -> Result<RunTestResult> {
+    // the test runner's filter matches test names by substring, so the test plan is instead
+    // narrowed down to the exact tests to be run
+    let abort_code_names = abort_code_names(test_plan, &module_plan.module_id);
+    let module_id = module_plan.module_id.clone();
+    let single_test = module_plan.tests.len() == 1;
+    let module_test_plan = TestPlan {
+        files: test_plan.files.clone(),
+        module_tests: BTreeMap::from([(module_plan.module_id.clone(), module_plan)]),
+        module_info: test_plan.module_info.clone(),
+    };
+    let natives = move_stdlib::natives::all_natives(AccountAddress::from_hex_literal("0x1")?);
+    let (output, passed, statistics) = unit_test_config.run_and_report_unit_tests_with_statistics(
+        module_test_plan,
+        Some(natives),
+        vec![],
+    )?;
+    let run_info = statistics
+        .passed()
+        .values()
+        .flatten()
+        .chain(
+            statistics
+                .failed()
+                .values()
+                .flatten()
+                .map(|failure| &failure.test_run_info),
+        )
+        .next();
+    let status = run_info.filter(|_| single_test).and_then(|info| {
+        Some(TestStatusParams {
+            location: test_location(test_plan, &module_id, &info.function_ident)?,
+            passed,
+            gas_used: info.instructions_executed,
+        })
+    });
+    Ok(RunTestResult {
+        passed,
+        output: annotate_abort_codes(&String::from_utf8_lossy(&output), &abort_code_names),
+        status,
+    })
+}
+
+/// Returns the location of the name of the given test function of the given module
+fn test_location(test_plan: &TestPlan, module_id: &ModuleId, test_name: &str) -> Option<Location> {
+    let NamedCompiledModule {
+        module, source_map, ..
+    } = test_plan.module_info.get(module_id)?;
+    let index = module.function_defs().iter().position(|def| {
+        let handle = module.function_handle_at(def.function);
+        module.identifier_at(handle.name).as_str() == test_name
+    })?;
+    let loc = source_map
+        .get_function_source_map(FunctionDefinitionIndex(index as TableIndex))
+        .ok()?
+        .definition_location;
+    let (fname, contents) = test_plan.files.get(&loc.file_hash())?;
+    let uri = Url::from_file_path(fname.as_str()).ok()?;
+    Some(Location::new(
+        uri,
+        Range::new(
+            buffer_position(contents, loc.start() as usize),
+            buffer_position(contents, loc.end() as usize),
+        ),
+    ))
+}
+
+/// Returns qualified names of constants (of the package containing the given module) that abort
+/// codes may stand for, keyed by their values. Constants of the given module take precedence over
+/// those of other modules with the same value.
+fn abort_code_names(test_plan: &TestPlan, module_id: &ModuleId) -> BTreeMap<u64, Vec<String>> {
+    let package_name = test_plan
+        .module_info
+        .get(module_id)
+        .and_then(|m| m.package_name);
+    let mut names = BTreeMap::<u64, (bool, Vec<String>)>::new();
+    for (id, named_module) in &test_plan.module_info {
+        if named_module.package_name != package_name {
+            continue;
+        }
+        let in_module = id == module_id;
+        for (name, idx) in &named_module.source_map.constant_map {
+            let code = named_module
+                .module
+                .constant_pool
+                .get(*idx as usize)
+                .and_then(|c| c.deserialize_constant())
+                .and_then(|value| abort_code(&value));
+            let code = match code {
+                Some(code) => code,
+                None => continue,
+            };
+            let qualified_name = format!("{}::{}", id.name(), name.0);
+            let (in_module_names, code_names) = names.entry(code).or_default();
+            if in_module && !*in_module_names {
+                *in_module_names = true;
+                code_names.clear();
+            }
+            if in_module || !*in_module_names {
+                code_names.push(qualified_name);
+            }
+        }
+    }
+    names
+        .into_iter()
+        .map(|(code, (_, code_names))| (code, code_names))
+        .collect()
+}
+
+/// Returns plans running single tests of the given test plan, optionally restricted to a module
+/// (and to a test function of that module).
+fn single_test_plans(
+    test_plan: &TestPlan,
+    module_name: Option<&str>,
+    test_name: Option<&str>,
+) -> Vec<ModuleTestPlan> {
+    test_plan
+        .module_tests
+        .values()
+        .filter(|plan| module_name.map_or(true, |name| plan.module_id.name().as_str() == name))
+        .flat_map(|plan| {
+            plan.tests
+                .iter()
+                .filter(|(name, _)| test_name.map_or(true, |test_name| *name == test_name))
+                .map(move |(name, test)| ModuleTestPlan {
+                    module_id: plan.module_id.clone(),
+                    tests: BTreeMap::from([(name.clone(), test.clone())]),
+                })
+        })
+        .collect()
+}
+
+/// Builds the package rooted at the given path in test mode and runs the given test function.
+pub fn run_unit_test(args: &RunTestArgs) -> Result<RunTestResult> {
+    let (test_plan, unit_test_config) = match build_test_plan(&args.package_path)? {
+        Ok(plan) => plan,
+        Err(diagnostics) => {
+            return Ok(RunTestResult {
+                passed: false,
+                output: diagnostics,
+                status: None,
+            })
+        }
+    };
+    let module_plan = single_test_plans(&test_plan, Some(&args.module_name), Some(&args.test_name))
+        .pop()
+        .ok_or_else(|| anyhow!("test {}::{} not found", args.module_name, args.test_name))?;
+    run_module_tests(&test_plan, module_plan, &unit_test_config)
+}
+
+/// Builds the package rooted at the given path in test mode and runs the selected tests one by
+/// one, passing the outcome of each test to the given callback as soon as the test finishes.
+pub fn run_unit_tests(
+    args: &RunTestsArgs,
+    mut on_test_result: impl FnMut(TestResultParams),
+) -> Result<RunTestsResult> {
+    let (test_plan, unit_test_config) = match build_test_plan(&args.package_path)? {
+        Ok(plan) => plan,
+        Err(diagnostics) => {
+            return Ok(RunTestsResult {
+                passed: 0,
+                failed: 0,
+                diagnostics: Some(diagnostics),
+            })
+        }
+    };
+    let module_plans = single_test_plans(
+        &test_plan,
+        args.module_name.as_deref(),
+        args.test_name.as_deref(),
+    );
+    if module_plans.is_empty() && (args.module_name.is_some() || args.test_name.is_some()) {
+        return Err(anyhow!(
+            "no tests found in {}",
+            args.module_name.as_deref().unwrap_or("package")
+        ));
+    }
+    let mut result = RunTestsResult {
+        passed: 0,
+        failed: 0,
+        diagnostics: None,
+    };
+    for module_plan in module_plans {
+        let module_name = module_plan.module_id.name().to_string();
+        let test_name = module_plan.tests.keys().next().unwrap().clone();
+        let outcome = run_module_tests(&test_plan, module_plan, &unit_test_config)?;
+        if outcome.passed {
+            result.passed += 1;
+        } else {
+            result.failed += 1;
+        }
+        on_test_result(TestResultParams {
+            package_path: args.package_path.clone(),
+            module_name,
+            test_name,
+            passed: outcome.passed,
+            output: outcome.output,
+            status: outcome.status,
+        });
+    }
+    Ok(result)
+}
+
+/// Handles execute command request of the language server
+pub fn on_execute_command_request(context: &Context, request: &Request) {
+    let parameters = serde_json::from_value::<ExecuteCommandParams>(request.params.clone())
+        .expect("could not deserialize execute command request");
+
+    let args = match parameters.command.as_str() {
+        RUN_TEST_COMMAND => parameters
+            .arguments
+            .into_iter()
+            .next()
+            .and_then(|arg| serde_json::from_value::<RunTestArgs>(arg).ok()),
+        RUN_TESTS_COMMAND => {
+            return on_run_tests_command(context, request, parameters.arguments);
+        }
+        PROVE_COMMAND => return on_prove_command(context, request, parameters.arguments),
+        _ => None,
+    };
+    let args = match args {
+        Some(args) => args,
+        None => {
+            let response = Response::new_err(
+                request.id.clone(),
+                lsp_server::ErrorCode::InvalidParams as i32,
+                format!("invalid command '{}'", parameters.command),
+            );
+            if let Err(err) = context.connection.sender.send(Message::Response(response)) {
+                error!("could not send execute command response: {:?}", err);
+            }
+            return;
+        }
+    };
+
+    let sender = context.connection.sender.clone();
+    let id = request.id.clone();
+    thread::spawn(move || {
+        let test = format!("{}::{}", args.module_name, args.test_name);
+        info!("running test {}", test);
+        let (response, message) = match run_unit_test(&args) {
+            Ok(result) => {
+                send_test_status(&sender, result.status.clone());
+                let message = if result.passed {
+                    ShowMessageParams {
+                        typ: MessageType::Info,
+                        message: format!("Test {} passed", test),
+                    }
+                } else {
+                    ShowMessageParams {
+                        typ: MessageType::Error,
+                        message: format!("Test {} failed:\n{}", test, result.output),
+                    }
+                };
+                (Response::new_ok(id, result), message)
+            }
+            Err(err) => {
+                let message = ShowMessageParams {
+                    typ: MessageType::Error,
+                    message: format!("Could not run test {}: {}", test, err),
+                };
+                let response = Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::InternalError as i32,
+                    format!("could not run test: {}", err),
+                );
+                (response, message)
+            }
+        };
+        let notification = Notification::new(
+            lsp_types::notification::ShowMessage::METHOD.to_string(),
+            message,
+        );
+        debug!("about to send execute command response");
+        for message in [
+            Message::Response(response),
+            Message::Notification(notification),
+        ] {
+            if let Err(err) = sender.send(message) {
+                error!("could not send test result: {:?}", err);
+            }
+        }
+    });
+}
+
+/// Handles the command running multiple unit tests, answering the request once all of them finish.
+fn on_run_tests_command(context: &Context, request: &Request, arguments: Vec<serde_json::Value>) {
+    let args = match arguments
+        .into_iter()
+        .next()
+        .and_then(|arg| serde_json::from_value::<RunTestsArgs>(arg).ok())
+    {
+        Some(args) => args,
+        None => {
+            let response = Response::new_err(
+                request.id.clone(),
+                lsp_server::ErrorCode::InvalidParams as i32,
+                format!("invalid arguments of command '{}'", RUN_TESTS_COMMAND),
+            );
+            if let Err(err) = context.connection.sender.send(Message::Response(response)) {
+                error!("could not send execute command response: {:?}", err);
+            }
+            return;
+        }
+    };
+
+    let sender = context.connection.sender.clone();
+    let id = request.id.clone();
+    thread::spawn(move || {
+        info!("running tests in {}", args.package_path.display());
+        let result = run_unit_tests(&args, |params| {
+            let status = params.status.clone();
+            let notification = Notification::new(TEST_RESULT_NOTIFICATION.to_string(), params);
+            if let Err(err) = sender.send(Message::Notification(notification)) {
+                error!("could not send test result: {:?}", err);
+            }
+            send_test_status(&sender, status);
+        });
+        let response = match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(err) => Response::new_err(
+                id,
+                lsp_server::ErrorCode::InternalError as i32,
+                format!("could not run tests: {}", err),
+            ),
+        };
+        debug!("about to send execute command response");
+        if let Err(err) = sender.send(Message::Response(response)) {
+            error!("could not send test results: {:?}", err);
+        }
+    });
+}
+
+/// Sends the status of a test to the client (if the location of the test is known)
+fn send_test_status(sender: &Sender<Message>, status: Option<TestStatusParams>) {
+    if let Some(status) = status {
+        let notification = Notification::new(TEST_STATUS_NOTIFICATION.to_string(), status);
+        if let Err(err) = sender.send(Message::Notification(notification)) {
+            error!("could not send test status: {:?}", err);
+        }
+    }
+}
+
+#[test]
+/// Tests if a single unit test is run.
+fn run_unit_test_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let run = |test_name: &str| {
+        run_unit_test(&RunTestArgs {
+            package_path: path.clone(),
+            module_name: "M6".to_string(),
+            test_name: test_name.to_string(),
+        })
+    };
+
+    let result = run("passing_test").unwrap();
+    assert!(result.passed);
+    assert!(result.output.contains("passing_test"));
+    assert!(!result.output.contains("failing_test"));
+    // the status is reported at the name of the test function
+    let status = result.status.unwrap();
+    assert!(status.passed);
+    assert!(status.gas_used > 0);
+    assert!(status.location.uri.path().ends_with("M6.move"));
+    assert_eq!(
+        status.location.range.end.character - status.location.range.start.character,
+        "passing_test".len() as u32
+    );
+
+    let result = run("failing_test").unwrap();
+    assert!(!result.passed);
+    // the abort code is shown along with the constant of the same value
+    assert!(result
+        .output
+        .contains("aborted with 42 (M6::DOCUMENTED_CONST)"));
+
+    assert!(run("no_such_test").is_err());
+}
+
+#[test]
+/// Tests if the outcome of each unit test is reported when running multiple tests.
+fn run_unit_tests_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    path.push("tests/symbols");
+
+    let run = |module_name: Option<&str>, test_name: Option<&str>| {
+        let mut results = vec![];
+        let args = RunTestsArgs {
+            package_path: path.clone(),
+            module_name: module_name.map(str::to_string),
+            test_name: test_name.map(str::to_string),
+        };
+        run_unit_tests(&args, |params| results.push(params)).map(|result| (result, results))
+    };
+
+    let (result, results) = run(Some("M6"), None).unwrap();
+    assert!(result.diagnostics.is_none());
+    assert_eq!(result.passed + result.failed, results.len());
+    let outcome = |name: &str| {
+        results
+            .iter()
+            .find(|params| params.module_name == "M6" && params.test_name == name)
+            .map(|params| params.passed)
+    };
+    assert_eq!(outcome("passing_test"), Some(true));
+    assert_eq!(outcome("passing_test_expecting_failure"), Some(true));
+    assert_eq!(outcome("failing_test"), Some(false));
+
+    let (result, results) = run(Some("M6"), Some("failing_test")).unwrap();
+    assert_eq!((result.passed, result.failed), (0, 1));
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].passed);
+
+    // all tests of the package are run if no module is given
+    let (_, package_results) = run(None, None).unwrap();
+    assert!(package_results.len() >= 3);
+
+    assert!(run(Some("M6"), Some("no_such_test")).is_err());
+}
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use codespan_reporting::files::{Files, SimpleFiles};
-use lsp_types::Position;
+use lsp_types::{Position, Range};
 use move_command_line_common::files::FileHash;
 use move_ir_types::location::*;
 use move_symbol_pool::Symbol;
@@ -29,3 +29,15 @@ pub fn get_loc(
         Err(_) => None,
     }
 }
+
+/// Converts a location from the byte index format to the line/character (Range) format, where
+/// line/character are 0-based.
+pub fn get_range(
+    loc: &Loc,
+    files: &SimpleFiles<Symbol, String>,
+    file_id_mapping: &HashMap<FileHash, usize>,
+) -> Option<Range> {
+    let start = get_loc(&loc.file_hash(), loc.start(), files, file_id_mapping)?;
+    let end = get_loc(&loc.file_hash(), loc.end(), files, file_id_mapping)?;
+    Some(Range { start, end })
+}
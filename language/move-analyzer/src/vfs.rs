@@ -13,10 +13,12 @@
 use lsp_server::Notification;
 use lsp_types::{
     notification::Notification as _, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
-    DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, Position, TextDocumentContentChangeEvent,
 };
 
-use crate::symbols;
+use crate::{standalone, symbols};
+use std::{cmp, path::Path};
+use tracing::{debug, error};
 
 /// A mapping from identifiers (file names, potentially, but not necessarily) to their contents.
 #[derive(Debug, Default)]
@@ -32,47 +34,108 @@ impl VirtualFileSystem {
     }
 
     /// Inserts or overwrites the buffer corresponding to the given identifier.
-    ///
-    /// TODO: A far more efficient "virtual file system" would update its buffers with changes sent
-    /// from the client, instead of completely replacing them each time. The rust-analyzer has a
-    /// 'vfs' module that is capable of doing just that, but it is not published on crates.io. If
-    /// we could help get it published, we could use it here.
     pub fn update(&mut self, identifier: &str, content: &str) {
         self.files
             .insert(identifier.to_string(), content.to_string());
     }
 
+    /// Applies a sequence of changes sent by the client to the buffer corresponding to the given
+    /// identifier. Changes are applied in order, each one relative to the buffer contents that
+    /// result from applying all the previous ones. A change without a range replaces the entire
+    /// buffer. If the buffer is not present in the system, only full-content changes have any
+    /// effect.
+    pub fn apply_changes(&mut self, identifier: &str, changes: &[TextDocumentContentChangeEvent]) {
+        for change in changes {
+            let range = match change.range {
+                Some(range) => range,
+                None => {
+                    self.update(identifier, &change.text);
+                    continue;
+                }
+            };
+            let buffer = match self.files.get_mut(identifier) {
+                Some(buffer) => buffer,
+                None => {
+                    error!("cannot apply change to unknown buffer '{}'", identifier);
+                    continue;
+                }
+            };
+            let start = byte_offset(buffer, &range.start);
+            // guard against (invalid) ranges whose end precedes their start
+            let end = cmp::max(start, byte_offset(buffer, &range.end));
+            buffer.replace_range(start..end, &change.text);
+        }
+    }
+
     /// Removes the buffer and its identifier from the system.
     pub fn remove(&mut self, identifier: &str) {
         self.files.remove(identifier);
     }
 }
 
+/// Converts a position in the line/character format used by the language server protocol to a
+/// byte offset into the given buffer. As mandated by the protocol, the character offset is
+/// expressed in UTF-16 code units. Positions past the end of a line are clamped to the end of that
+/// line, and positions past the end of the buffer are clamped to the end of the buffer.
+pub fn byte_offset(buffer: &str, position: &Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match buffer[line_start..].find('\n') {
+            Some(idx) => line_start += idx + 1,
+            None => return buffer.len(),
+        }
+    }
+    let line = &buffer[line_start..];
+    let mut utf16_offset = 0;
+    for (idx, c) in line.char_indices() {
+        if c == '\n' || utf16_offset >= position.character as usize {
+            return line_start + idx;
+        }
+        utf16_offset += c.len_utf16();
+    }
+    buffer.len()
+}
+
+/// Converts a byte offset into the given buffer (which must be at a character boundary) to a
+/// position in the line/character format used by the language server protocol.
+pub fn buffer_position(buffer: &str, offset: usize) -> Position {
+    let prefix = &buffer[..offset];
+    let line_start = prefix.rfind('\n').map_or(0, |idx| idx + 1);
+    Position {
+        line: prefix.matches('\n').count() as u32,
+        character: prefix[line_start..].encode_utf16().count() as u32,
+    }
+}
+
 /// Updates the given virtual file system based on the text document sync notification that was sent.
 pub fn on_text_document_sync_notification(
     files: &mut VirtualFileSystem,
     symbolicator_runner: &symbols::SymbolicatorRunner,
     notification: &Notification,
 ) {
-    eprintln!("text document notification");
+    debug!("text document notification");
     match notification.method.as_str() {
         lsp_types::notification::DidOpenTextDocument::METHOD => {
             let parameters =
                 serde_json::from_value::<DidOpenTextDocumentParams>(notification.params.clone())
                     .expect("could not deserialize notification");
-            files.update(
-                parameters.text_document.uri.path(),
-                &parameters.text_document.text,
-            );
+            let uri = &parameters.text_document.uri;
+            files.update(uri.path(), &parameters.text_document.text);
+            // files outside of packages are symbolicated only while they are open
+            if standalone::is_standalone(Path::new(uri.path())) {
+                symbolicator_runner.add_standalone_file(uri);
+            }
         }
         lsp_types::notification::DidChangeTextDocument::METHOD => {
             let parameters =
                 serde_json::from_value::<DidChangeTextDocumentParams>(notification.params.clone())
                     .expect("could not deserialize notification");
-            files.update(
-                parameters.text_document.uri.path(),
-                &parameters.content_changes.last().unwrap().text,
-            );
+            let uri = &parameters.text_document.uri;
+            files.apply_changes(uri.path(), &parameters.content_changes);
+            if let Some(buffer) = files.get(uri.path()) {
+                symbolicator_runner.set_buffer(uri, buffer.to_string());
+                symbolicator_runner.run_debounced();
+            }
         }
         lsp_types::notification::DidSaveTextDocument::METHOD => {
             let parameters =
@@ -82,6 +145,8 @@ pub fn on_text_document_sync_notification(
                 parameters.text_document.uri.path(),
                 &parameters.text.unwrap(),
             );
+            // saved contents are the same as on-disk ones
+            symbolicator_runner.remove_buffer(&parameters.text_document.uri);
             symbolicator_runner.run();
         }
         lsp_types::notification::DidCloseTextDocument::METHOD => {
@@ -89,8 +154,60 @@ pub fn on_text_document_sync_notification(
                 serde_json::from_value::<DidCloseTextDocumentParams>(notification.params.clone())
                     .expect("could not deserialize notification");
             files.remove(parameters.text_document.uri.path());
+            // unsaved changes (if any) are discarded
+            symbolicator_runner.remove_buffer(&parameters.text_document.uri);
+            symbolicator_runner.run_debounced();
+            symbolicator_runner.remove_standalone_file(&parameters.text_document.uri);
         }
-        _ => eprintln!("invalid notification '{}'", notification.method),
+        _ => error!("invalid notification '{}'", notification.method),
     }
-    eprintln!("text document notification handled");
+    debug!("text document notification handled");
+}
+
+#[test]
+/// Tests if incremental changes sent by the client are correctly applied to the buffer.
+fn apply_changes_test() {
+    use lsp_types::Range;
+
+    let change = |start: (u32, u32), end: (u32, u32), text: &str| TextDocumentContentChangeEvent {
+        range: Some(Range::new(
+            Position::new(start.0, start.1),
+            Position::new(end.0, end.1),
+        )),
+        range_length: None,
+        text: text.to_string(),
+    };
+
+    let mut files = VirtualFileSystem::default();
+    files.update("M.move", "module M {\n    fun f() {}\n}\n");
+
+    // insertion, replacement spanning lines, and deletion applied one after the other
+    files.apply_changes(
+        "M.move",
+        &[
+            change((1, 9), (1, 9), "oo"),
+            change((1, 13), (2, 0), ": u64 { 0 }\n"),
+            change((0, 0), (0, 7), ""),
+        ],
+    );
+    assert_eq!(
+        files.get("M.move"),
+        Some("M {\n    fun foo(): u64 { 0 }\n}\n")
+    );
+
+    // characters are counted in UTF-16 code units
+    files.update("M.move", "// \u{1F600} x\n");
+    files.apply_changes("M.move", &[change((0, 6), (0, 7), "y")]);
+    assert_eq!(files.get("M.move"), Some("// \u{1F600} y\n"));
+
+    // a change without a range replaces the whole buffer
+    files.apply_changes(
+        "M.move",
+        &[TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "script {}".to_string(),
+        }],
+    );
+    assert_eq!(files.get("M.move"), Some("script {}"));
 }
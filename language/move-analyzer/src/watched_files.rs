@@ -0,0 +1,151 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Watching of package manifests and source files for changes made outside of the editor (e.g.,
+//! when switching branches or when git dependencies are downloaded), which trigger
+//! re-symbolication (and thus re-resolution of packages and their dependencies).
+//!
+//! Watchers are registered dynamically with clients that support it, and cover manifests and
+//! source files both in the workspace and in the directory where git dependencies are downloaded.
+//! Changes of source files opened in the editor are ignored, as their contents are synchronized
+//! with the server separately.
+
+use crate::{context::Context, symbols::SymbolicatorRunner, vfs::VirtualFileSystem};
+use lsp_server::{Notification, Request, RequestId};
+use lsp_types::{
+    notification::{DidChangeWatchedFiles, Notification as _},
+    request::{RegisterCapability, Request as _},
+    DidChangeWatchedFilesParams, DidChangeWatchedFilesRegistrationOptions, FileEvent,
+    FileSystemWatcher, Registration, RegistrationParams,
+};
+use move_package::{
+    compilation::package_layout::CompiledPackageLayout,
+    source_package::{layout::SourcePackageLayout, manifest_parser::move_home},
+};
+use std::ffi::OsStr;
+
+/// ID of the registration of file watchers (also used as the ID of the registration request)
+const WATCHERS_REGISTRATION_ID: &str = "move-analyzer/watchers";
+
+/// Returns the request registering file watchers with the client
+pub fn register_watchers_request() -> Request {
+    let manifest = SourcePackageLayout::Manifest.path().to_string_lossy();
+    let move_home = move_home();
+    let glob_patterns = vec![
+        format!("**/{}", manifest),
+        "**/*.move".to_string(),
+        format!("{}/**/{}", move_home, manifest),
+        format!("{}/**/*.move", move_home),
+    ];
+    let options = DidChangeWatchedFilesRegistrationOptions {
+        watchers: glob_patterns
+            .into_iter()
+            .map(|glob_pattern| FileSystemWatcher {
+                glob_pattern,
+                kind: None,
+            })
+            .collect(),
+    };
+    let params = RegistrationParams {
+        registrations: vec![Registration {
+            id: WATCHERS_REGISTRATION_ID.to_string(),
+            method: DidChangeWatchedFiles::METHOD.to_string(),
+            register_options: Some(serde_json::to_value(options).unwrap()),
+        }],
+    };
+    Request::new(
+        RequestId::from(WATCHERS_REGISTRATION_ID.to_string()),
+        RegisterCapability::METHOD.to_string(),
+        params,
+    )
+}
+
+/// Returns true if a change of a file on disk may affect symbolication results
+fn affects_symbols(files: &VirtualFileSystem, event: &FileEvent) -> bool {
+    let fpath = match event.uri.to_file_path() {
+        Ok(fpath) => fpath,
+        Err(_) => return false,
+    };
+    // building a package (e.g., from the command line) copies its sources (and sources of its
+    // dependencies) to the build directory
+    let build_dir = CompiledPackageLayout::Root.path().as_os_str();
+    if fpath.components().any(|c| c.as_os_str() == build_dir) {
+        return false;
+    }
+    if fpath.file_name() == Some(SourcePackageLayout::Manifest.path().as_os_str()) {
+        return true;
+    }
+    fpath.extension() == Some(OsStr::new("move")) && files.get(event.uri.path()).is_none()
+}
+
+/// Handles changes of watched files
+pub fn on_did_change_watched_files_notification(
+    context: &Context,
+    symbolicator_runner: &SymbolicatorRunner,
+    notification: &Notification,
+) {
+    let parameters =
+        serde_json::from_value::<DidChangeWatchedFilesParams>(notification.params.clone())
+            .expect("could not deserialize did change watched files notification");
+
+    let mut affected = false;
+    for event in parameters
+        .changes
+        .iter()
+        .filter(|event| affects_symbols(&context.files, event))
+    {
+        symbolicator_runner.file_changed(&event.uri);
+        affected = true;
+    }
+    if affected {
+        // many files may change at once (e.g., when switching branches) but the client does not
+        // necessarily report all of them in a single notification
+        symbolicator_runner.run_debounced();
+    }
+}
+
+#[test]
+fn affects_symbols_test() {
+    use lsp_types::{FileChangeType, Url};
+    use std::path::Path;
+
+    let mut files = VirtualFileSystem::default();
+    let pkg_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/symbols");
+    let event = |fpath: &Path, typ| FileEvent {
+        uri: Url::from_file_path(fpath).unwrap(),
+        typ,
+    };
+
+    let manifest = pkg_path.join("Move.toml");
+    assert!(affects_symbols(
+        &files,
+        &event(&manifest, FileChangeType::Changed)
+    ));
+
+    let source = pkg_path.join("sources/M1.move");
+    assert!(affects_symbols(
+        &files,
+        &event(&source, FileChangeType::Changed)
+    ));
+    assert!(affects_symbols(
+        &files,
+        &event(&source, FileChangeType::Deleted)
+    ));
+    // contents of files opened in the editor are not read from disk
+    files.update(source.to_str().unwrap(), "");
+    assert!(!affects_symbols(
+        &files,
+        &event(&source, FileChangeType::Changed)
+    ));
+
+    let build_source = pkg_path.join("build/Symbols/sources/M1.move");
+    assert!(!affects_symbols(
+        &files,
+        &event(&build_source, FileChangeType::Created)
+    ));
+    let other = pkg_path.join("README.md");
+    assert!(!affects_symbols(
+        &files,
+        &event(&other, FileChangeType::Created)
+    ));
+}
@@ -0,0 +1,46 @@
+module Symbols::M6 {
+    use std::vector;
+
+    fun inferred_types(): u64 {
+        let v = vector::empty();
+        vector::push_back(&mut v, 42);
+        let annotated: vector<u64> = vector::empty<u64>();
+        vector::append(&mut annotated, v);
+        let len = vector::length(&annotated);
+        len
+    }
+
+    /// A documented struct.
+    struct DocumentedStruct has drop { value: u64 }
+
+    /// A documented constant.
+    const DOCUMENTED_CONST: u64 = 42;
+
+    /// A documented function,
+    /// spanning multiple lines.
+    public fun documented_fun(s: DocumentedStruct): u64 {
+        s.value + DOCUMENTED_CONST
+    }
+
+    struct Wrapper<T> has drop { inner: T, count: u64 }
+
+    fun field_chain(w: Wrapper<DocumentedStruct>): u64 {
+        w.inner.value + w.count
+    }
+
+    #[test]
+    fun passing_test() {
+        assert!(documented_fun(DocumentedStruct { value: 0 }) == 42, 0);
+    }
+
+    #[test, expected_failure]
+    fun passing_test_expecting_failure() {
+        abort 42
+    }
+
+    #[test]
+    fun failing_test() {
+        abort 42
+    }
+
+}
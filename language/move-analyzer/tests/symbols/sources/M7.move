@@ -0,0 +1,27 @@
+module Symbols::M7 {
+
+    struct Counter has drop { value: u64 }
+
+    fun increment(counter: &mut Counter, step: u64) {
+        spec { assert step > 0; };
+        counter.value = counter.value + step;
+    }
+
+    spec increment {
+        include StepPositive;
+        ensures counter.value == old(counter.value) + step;
+        ensures double(step) > step;
+        ensures total_steps == old(total_steps) + step;
+    }
+
+    spec schema StepPositive {
+        step: u64;
+        requires step > 0;
+    }
+
+    spec module {
+        global total_steps: u64;
+        fun double(x: u64): u64 { x * 2 }
+    }
+
+}
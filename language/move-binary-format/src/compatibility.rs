@@ -23,6 +23,25 @@ pub struct Compatibility {
     pub struct_layout: bool,
 }
 
+/// The policy deciding which module upgrades are allowed when a module gets republished.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CompatibilityPolicy {
+    /// The new module must be both linking and layout compatible with the old one
+    Full,
+    /// The new module must not change the layout of structs, but dependent modules may not link
+    /// anymore
+    NoStructLayoutChanges,
+    /// Any new module is allowed, even if it breaks dependent modules or previously published
+    /// values
+    Arbitrary,
+}
+
+impl Default for CompatibilityPolicy {
+    fn default() -> Self {
+        CompatibilityPolicy::Full
+    }
+}
+
 impl Compatibility {
     /// Return true if the two module s compared in the compatiblity check are both linking and
     /// layout compatible.
@@ -30,6 +49,16 @@ impl Compatibility {
         self.struct_and_function_linking && self.struct_layout
     }
 
+    /// Return true if an upgrade with this compatibility assessment is allowed by the given
+    /// policy.
+    pub fn is_allowed_by(&self, policy: CompatibilityPolicy) -> bool {
+        match policy {
+            CompatibilityPolicy::Full => self.is_fully_compatible(),
+            CompatibilityPolicy::NoStructLayoutChanges => self.struct_layout,
+            CompatibilityPolicy::Arbitrary => true,
+        }
+    }
+
     /// Return compatibility assessment for `new_module` relative to old module `old_module`.
     pub fn check(old_module: &Module, new_module: &Module) -> Compatibility {
         let mut struct_and_function_linking = true;
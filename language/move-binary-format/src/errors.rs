@@ -41,15 +41,33 @@ impl ExecutionState {
     }
 }
 
+/// A position in the source code of a module, e.g., resolved from its source map
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct SourceLocation {
+    pub file: String,
+    /// Line, starting at 1
+    pub line: u32,
+    /// Column, starting at 1
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct VMError {
     major_status: StatusCode,
     sub_status: Option<u64>,
     message: Option<String>,
+    abort_payload: Option<Vec<u8>>,
     exec_state: Option<ExecutionState>,
     location: Location,
     indices: Vec<(IndexKind, TableIndex)>,
     offsets: Vec<(FunctionDefinitionIndex, CodeOffset)>,
+    source_location: Option<SourceLocation>,
 }
 
 impl VMError {
@@ -134,6 +152,11 @@ impl VMError {
         self.message.as_ref()
     }
 
+    /// The payload an abort carries in addition to its code, e.g., a human-readable reason
+    pub fn abort_payload(&self) -> Option<&[u8]> {
+        self.abort_payload.as_deref()
+    }
+
     pub fn exec_state(&self) -> Option<&ExecutionState> {
         self.exec_state.as_ref()
     }
@@ -154,6 +177,21 @@ impl VMError {
         &self.offsets
     }
 
+    /// Where the error occurred in the source code, if it could be resolved (see
+    /// `with_source_location`)
+    pub fn source_location(&self) -> Option<&SourceLocation> {
+        self.source_location.as_ref()
+    }
+
+    /// Attach the position in the source code the error occurred at, e.g., resolved from the code
+    /// offset of an abort with the source map of the module
+    pub fn with_source_location(self, source_location: SourceLocation) -> Self {
+        Self {
+            source_location: Some(source_location),
+            ..self
+        }
+    }
+
     pub fn status_type(&self) -> StatusType {
         self.major_status.status_type()
     }
@@ -177,6 +215,7 @@ impl VMError {
             location,
             indices,
             offsets,
+            ..
         } = self;
         (
             major_status,
@@ -194,6 +233,7 @@ impl VMError {
             major_status,
             sub_status,
             message,
+            abort_payload,
             exec_state,
             indices,
             offsets,
@@ -203,6 +243,7 @@ impl VMError {
             major_status,
             sub_status,
             message,
+            abort_payload,
             exec_state,
             indices,
             offsets,
@@ -217,6 +258,7 @@ pub struct PartialVMError {
     major_status: StatusCode,
     sub_status: Option<u64>,
     message: Option<String>,
+    abort_payload: Option<Vec<u8>>,
     exec_state: Option<ExecutionState>,
     indices: Vec<(IndexKind, TableIndex)>,
     offsets: Vec<(FunctionDefinitionIndex, CodeOffset)>,
@@ -240,6 +282,7 @@ impl PartialVMError {
             exec_state,
             indices,
             offsets,
+            ..
         } = self;
         (
             major_status,
@@ -256,6 +299,7 @@ impl PartialVMError {
             major_status,
             sub_status,
             message,
+            abort_payload,
             exec_state,
             indices,
             offsets,
@@ -264,10 +308,12 @@ impl PartialVMError {
             major_status,
             sub_status,
             message,
+            abort_payload,
             exec_state,
             location,
             indices,
             offsets,
+            source_location: None,
         }
     }
 
@@ -276,6 +322,7 @@ impl PartialVMError {
             major_status,
             sub_status: None,
             message: None,
+            abort_payload: None,
             exec_state: None,
             indices: vec![],
             offsets: vec![],
@@ -286,6 +333,11 @@ impl PartialVMError {
         self.major_status
     }
 
+    /// The payload an abort carries in addition to its code, e.g., a human-readable reason
+    pub fn abort_payload(&self) -> Option<&[u8]> {
+        self.abort_payload.as_deref()
+    }
+
     pub fn with_sub_status(self, sub_status: u64) -> Self {
         debug_assert!(self.sub_status.is_none());
         Self {
@@ -302,6 +354,16 @@ impl PartialVMError {
         }
     }
 
+    /// Attach a payload to an abort, e.g., a human-readable reason for it
+    pub fn with_abort_payload(self, abort_payload: Vec<u8>) -> Self {
+        debug_assert!(self.major_status == StatusCode::ABORTED);
+        debug_assert!(self.abort_payload.is_none());
+        Self {
+            abort_payload: Some(abort_payload),
+            ..self
+        }
+    }
+
     pub fn with_exec_state(self, exec_state: ExecutionState) -> Self {
         debug_assert!(self.exec_state.is_none());
         Self {
@@ -383,6 +445,14 @@ impl fmt::Display for PartialVMError {
             status = format!("{} and message {}", status, msg);
         }
 
+        if let Some(payload) = &self.abort_payload {
+            status = format!(
+                "{} with abort payload {:?}",
+                status,
+                String::from_utf8_lossy(payload)
+            );
+        }
+
         for (kind, index) in &self.indices {
             status = format!("{} at index {} for {}", status, index, kind);
         }
@@ -407,10 +477,22 @@ impl fmt::Display for VMError {
 
         status = format!("{} at location {}", status, self.location);
 
+        if let Some(source_location) = &self.source_location {
+            status = format!("{} ({})", status, source_location);
+        }
+
         if let Some(msg) = &self.message {
             status = format!("{} and message {}", status, msg);
         }
 
+        if let Some(payload) = &self.abort_payload {
+            status = format!(
+                "{} with abort payload {:?}",
+                status,
+                String::from_utf8_lossy(payload)
+            );
+        }
+
         for (kind, index) in &self.indices {
             status = format!("{} at index {} for {}", status, index, kind);
         }
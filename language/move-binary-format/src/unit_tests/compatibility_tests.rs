@@ -3,7 +3,11 @@
 
 use std::convert::TryFrom;
 
-use crate::{compatibility::Compatibility, file_format::*, normalized};
+use crate::{
+    compatibility::{Compatibility, CompatibilityPolicy},
+    file_format::*,
+    normalized,
+};
 use move_core_types::{account_address::AccountAddress, identifier::Identifier};
 
 fn mk_module(vis: u8) -> normalized::Module {
@@ -132,3 +136,24 @@ fn deprecated_add_script_visibility() {
         NON_COMPATIBLE
     );
 }
+
+#[test]
+fn compatibility_policies() {
+    let layout_incompatible = Compatibility {
+        struct_and_function_linking: true,
+        struct_layout: false,
+    };
+    for policy in [
+        CompatibilityPolicy::Full,
+        CompatibilityPolicy::NoStructLayoutChanges,
+        CompatibilityPolicy::Arbitrary,
+    ] {
+        assert!(COMPATIBLE.is_allowed_by(policy));
+    }
+    assert!(!NON_COMPATIBLE.is_allowed_by(CompatibilityPolicy::Full));
+    assert!(NON_COMPATIBLE.is_allowed_by(CompatibilityPolicy::NoStructLayoutChanges));
+    assert!(NON_COMPATIBLE.is_allowed_by(CompatibilityPolicy::Arbitrary));
+    assert!(!layout_incompatible.is_allowed_by(CompatibilityPolicy::Full));
+    assert!(!layout_incompatible.is_allowed_by(CompatibilityPolicy::NoStructLayoutChanges));
+    assert!(layout_incompatible.is_allowed_by(CompatibilityPolicy::Arbitrary));
+}
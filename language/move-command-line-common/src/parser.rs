@@ -4,6 +4,7 @@
 use anyhow::{anyhow, bail, Result};
 use move_core_types::account_address::AccountAddress;
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, fmt::Display, iter::Peekable, num::ParseIntError};
 
 use crate::{
@@ -307,7 +308,7 @@ pub fn parse_address_impl(tok: ValueToken, contents: &str) -> Result<ParsedAddre
     })
 }
 
-#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy)]
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 #[repr(u32)]
 /// Number format enum, the u32 value represents the base
 pub enum NumberFormat {
@@ -42,6 +42,7 @@ pub struct Compiler<'a> {
     pre_compiled_lib: Option<&'a FullyCompiledProgram>,
     compiled_module_named_address_mapping: BTreeMap<CompiledModuleId, String>,
     flags: Flags,
+    source_overlay: BTreeMap<PathBuf, String>,
 }
 
 pub struct SteppedCompiler<'a, const P: Pass> {
@@ -130,6 +131,7 @@ impl<'a> Compiler<'a> {
             pre_compiled_lib: None,
             compiled_module_named_address_mapping: BTreeMap::new(),
             flags: Flags::empty(),
+            source_overlay: BTreeMap::new(),
         }
     }
 
@@ -193,6 +195,14 @@ impl<'a> Compiler<'a> {
         self
     }
 
+    /// Sets contents to be used in place of the on-disk contents of source files (e.g., unsaved
+    /// editor buffers), keyed by canonical file paths
+    pub fn set_source_overlay(mut self, source_overlay: BTreeMap<PathBuf, String>) -> Self {
+        assert!(self.source_overlay.is_empty());
+        self.source_overlay = source_overlay;
+        self
+    }
+
     pub fn run<const TARGET: Pass>(
         self,
     ) -> anyhow::Result<(
@@ -207,6 +217,7 @@ impl<'a> Compiler<'a> {
             pre_compiled_lib,
             compiled_module_named_address_mapping,
             flags,
+            source_overlay,
         } = self;
         generate_interface_files_for_deps(
             &mut deps,
@@ -215,7 +226,7 @@ impl<'a> Compiler<'a> {
         )?;
         let mut compilation_env = CompilationEnv::new(flags);
         let (source_text, pprog_and_comments_res) =
-            parse_program(&mut compilation_env, maps, targets, deps)?;
+            parse_program(&mut compilation_env, maps, targets, deps, &source_overlay)?;
         let res: Result<_, Diagnostics> = pprog_and_comments_res.and_then(|(pprog, comments)| {
             SteppedCompiler::new_at_parser(compilation_env, pre_compiled_lib, pprog)
                 .run::<TARGET>()
@@ -14,6 +14,7 @@ use crate::{
 };
 use move_ir_types::location::*;
 use move_symbol_pool::Symbol;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::{BTreeMap, BTreeSet, VecDeque},
     fmt,
@@ -91,12 +92,12 @@ pub struct Script {
 // Modules
 //**************************************************************************************************
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum Address {
     Numerical(Option<Name>, Spanned<NumericalAddress>),
     NamedUnassigned(Name),
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ModuleIdent_ {
     pub address: Address,
     pub module: ModuleName,
@@ -671,6 +672,21 @@ impl AbilitySet {
     }
 }
 
+// An ability set is serialized as a sequence of abilities
+impl Serialize for AbilitySet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for AbilitySet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let abilities = Vec::<Ability>::deserialize(deserializer)?;
+        Self::from_abilities(abilities)
+            .map_err(|(a, _, _)| D::Error::custom(format!("duplicate ability '{}'", a)))
+    }
+}
+
 impl Visibility {
     pub const PUBLIC: &'static str = P::Visibility::PUBLIC;
     pub const FRIEND: &'static str = P::Visibility::FRIEND;
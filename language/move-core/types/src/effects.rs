@@ -7,51 +7,101 @@ use crate::{
     identifier::Identifier,
     language_storage::{ModuleId, StructTag, TypeTag},
 };
-use anyhow::{format_err, Error, Result};
-use std::collections::btree_map::{self, BTreeMap};
+use anyhow::{bail, format_err, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{
+        btree_map::{self, BTreeMap},
+        BTreeSet,
+    },
+    fmt::Debug,
+};
 
-/// A collection of changes to modules and resources under a Move account.
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub struct AccountChangeSet {
-    modules: BTreeMap<Identifier, Option<Vec<u8>>>,
-    resources: BTreeMap<StructTag, Option<Vec<u8>>>,
+/// A storage operation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Op<T> {
+    /// Inserts some new data into an empty slot.
+    New(T),
+    /// Modifies some data that currently exists.
+    Modify(T),
+    /// Deletes some data that currently exists.
+    Delete,
 }
 
-fn publish_checked<K, V, F>(map: &mut BTreeMap<K, Option<V>>, k: K, v: V, make_err: F) -> Result<()>
-where
-    K: Ord,
-    F: FnOnce() -> Error,
-{
-    match map.entry(k) {
-        btree_map::Entry::Occupied(entry) => {
-            let r = entry.into_mut();
-            match r {
-                Some(_) => return Err(make_err()),
-                None => *r = Some(v),
-            }
+impl<T> Op<T> {
+    pub fn as_ref(&self) -> Op<&T> {
+        use Op::*;
+
+        match self {
+            New(data) => New(data),
+            Modify(data) => Modify(data),
+            Delete => Delete,
         }
-        btree_map::Entry::Vacant(entry) => {
-            entry.insert(Some(v));
+    }
+
+    pub fn map<F, U>(self, f: F) -> Op<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        use Op::*;
+
+        match self {
+            New(data) => New(f(data)),
+            Modify(data) => Modify(f(data)),
+            Delete => Delete,
         }
     }
-    Ok(())
+
+    /// Returns the data written by the operation, or `None` for a deletion.
+    pub fn ok(self) -> Option<T> {
+        use Op::*;
+
+        match self {
+            New(data) | Modify(data) => Some(data),
+            Delete => None,
+        }
+    }
+}
+
+/// A collection of changes to modules and resources under a Move account.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct AccountChangeSet {
+    modules: BTreeMap<Identifier, Op<Vec<u8>>>,
+    resources: BTreeMap<StructTag, Op<Vec<u8>>>,
 }
 
-fn unpublish_checked<K, V, F>(map: &mut BTreeMap<K, Option<V>>, k: K, make_err: F) -> Result<()>
+/// Merges the operations of `other`, which happen after the ones in `map`, into `map`. Fails if
+/// the operations on a key are inconsistent, e.g., a deletion following a deletion.
+fn squash<K, V>(map: &mut BTreeMap<K, Op<V>>, other: BTreeMap<K, Op<V>>) -> Result<()>
 where
-    K: Ord,
-    F: FnOnce() -> Error,
+    K: Ord + Debug,
 {
-    match map.entry(k) {
-        btree_map::Entry::Occupied(entry) => {
-            let r = entry.into_mut();
-            match r {
-                Some(_) => *r = None,
-                None => return Err(make_err()),
+    use btree_map::Entry::*;
+    use Op::*;
+
+    for (key, op) in other.into_iter() {
+        match map.entry(key) {
+            Occupied(mut entry) => {
+                let r = entry.get_mut();
+                match (r.as_ref(), op) {
+                    (Modify(_) | New(_), New(_)) | (Delete, Delete | Modify(_)) => {
+                        bail!(
+                            "The given change sets cannot be squashed: inconsistent operations on {:?}",
+                            entry.key()
+                        )
+                    }
+                    (Modify(_), Modify(data)) => *r = Modify(data),
+                    (New(_), Modify(data)) => *r = New(data),
+                    (Modify(_), Delete) => *r = Delete,
+                    (Delete, New(data)) => *r = Modify(data),
+                    (New(_), Delete) => {
+                        entry.remove();
+                    }
+                }
+            }
+            Vacant(entry) => {
+                entry.insert(op);
             }
-        }
-        btree_map::Entry::Vacant(entry) => {
-            entry.insert(None);
         }
     }
     Ok(())
@@ -59,8 +109,8 @@ where
 
 impl AccountChangeSet {
     pub fn from_modules_resources(
-        modules: BTreeMap<Identifier, Option<Vec<u8>>>,
-        resources: BTreeMap<StructTag, Option<Vec<u8>>>,
+        modules: BTreeMap<Identifier, Op<Vec<u8>>>,
+        resources: BTreeMap<StructTag, Op<Vec<u8>>>,
     ) -> Self {
         Self { modules, resources }
     }
@@ -75,25 +125,25 @@ impl AccountChangeSet {
     pub fn into_inner(
         self,
     ) -> (
-        BTreeMap<Identifier, Option<Vec<u8>>>,
-        BTreeMap<StructTag, Option<Vec<u8>>>,
+        BTreeMap<Identifier, Op<Vec<u8>>>,
+        BTreeMap<StructTag, Op<Vec<u8>>>,
     ) {
         (self.modules, self.resources)
     }
 
-    pub fn into_resources(self) -> BTreeMap<StructTag, Option<Vec<u8>>> {
+    pub fn into_resources(self) -> BTreeMap<StructTag, Op<Vec<u8>>> {
         self.resources
     }
 
-    pub fn into_modules(self) -> BTreeMap<Identifier, Option<Vec<u8>>> {
+    pub fn into_modules(self) -> BTreeMap<Identifier, Op<Vec<u8>>> {
         self.modules
     }
 
-    pub fn modules(&self) -> &BTreeMap<Identifier, Option<Vec<u8>>> {
+    pub fn modules(&self) -> &BTreeMap<Identifier, Op<Vec<u8>>> {
         &self.modules
     }
 
-    pub fn resources(&self) -> &BTreeMap<StructTag, Option<Vec<u8>>> {
+    pub fn resources(&self) -> &BTreeMap<StructTag, Op<Vec<u8>>> {
         &self.resources
     }
 
@@ -101,58 +151,121 @@ impl AccountChangeSet {
         self.modules.is_empty() && self.resources.is_empty()
     }
 
-    pub fn squash(&mut self, other: Self) -> Result<()> {
-        for (name, blob_opt) in other.modules {
-            match blob_opt {
-                Some(blob) => self.publish_module(name, blob)?,
-                None => self.unpublish_module(name)?,
+    /// Records an operation on a module, failing if there already is one.
+    pub fn add_module_op(&mut self, name: Identifier, op: Op<Vec<u8>>) -> Result<()> {
+        use btree_map::Entry::*;
+
+        match self.modules.entry(name) {
+            Occupied(entry) => bail!("Module {} already has an operation", entry.key()),
+            Vacant(entry) => {
+                entry.insert(op);
             }
         }
-        for (struct_tag, blob_opt) in other.resources {
-            match blob_opt {
-                Some(blob) => self.publish_resource(struct_tag, blob)?,
-                None => self.unpublish_resource(struct_tag)?,
+        Ok(())
+    }
+
+    /// Records an operation on a resource, failing if there already is one.
+    pub fn add_resource_op(&mut self, struct_tag: StructTag, op: Op<Vec<u8>>) -> Result<()> {
+        use btree_map::Entry::*;
+
+        match self.resources.entry(struct_tag) {
+            Occupied(entry) => bail!("Resource {} already has an operation", entry.key()),
+            Vacant(entry) => {
+                entry.insert(op);
             }
         }
         Ok(())
     }
 
-    pub fn publish_or_overwrite_module(&mut self, name: Identifier, blob: Vec<u8>) {
-        self.modules.insert(name, Some(blob));
+    /// Merges the changes of `other`, which happen after the ones of `self`, into `self` (see
+    /// `ChangeSet::squash`).
+    pub fn squash(&mut self, other: Self) -> Result<()> {
+        squash(&mut self.modules, other.modules)?;
+        squash(&mut self.resources, other.resources)
     }
 
-    pub fn publish_or_overwrite_resource(&mut self, struct_tag: StructTag, blob: Vec<u8>) {
-        self.resources.insert(struct_tag, Some(blob));
+    /// Merges changes to members of a resource group into the group as it is stored (`None` if it
+    /// does not exist yet), and records the resulting operation on the storage slot of the group:
+    /// the merged group is written, or the slot is deleted if no member is left.
+    pub fn merge_resource_group(
+        &mut self,
+        group_tag: StructTag,
+        stored: Option<ResourceGroup>,
+        changes: impl IntoIterator<Item = (StructTag, Option<Vec<u8>>)>,
+    ) -> Result<()> {
+        let existed = stored.is_some();
+        let mut group = stored.unwrap_or_default();
+        group.apply(changes)?;
+        let op = match (existed, group.is_empty()) {
+            (false, true) => return Ok(()),
+            (false, false) => Op::New(group.serialize()?),
+            (true, false) => Op::Modify(group.serialize()?),
+            (true, true) => Op::Delete,
+        };
+        self.add_resource_op(group_tag, op)
     }
+}
 
-    pub fn publish_module(&mut self, name: Identifier, blob: Vec<u8>) -> Result<()> {
-        publish_checked(&mut self.modules, name, blob, || {
-            format_err!("module already published")
+/// The members of a resource group, i.e., resources stored together under a single storage slot,
+/// along with their serialized values.
+///
+/// The slot of a group is the one of a resource whose type is the tag of the group, and contains
+/// the BCS serialization of the `ResourceGroup`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ResourceGroup {
+    members: BTreeMap<StructTag, Vec<u8>>,
+}
+
+impl ResourceGroup {
+    pub fn deserialize(blob: &[u8]) -> Result<Self> {
+        Ok(Self {
+            members: bcs::from_bytes(blob)?,
         })
     }
 
-    pub fn unpublish_module(&mut self, name: Identifier) -> Result<()> {
-        unpublish_checked(&mut self.modules, name, || {
-            format_err!("module already unpublished")
-        })
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        Ok(bcs::to_bytes(&self.members)?)
     }
 
-    pub fn publish_resource(&mut self, struct_tag: StructTag, blob: Vec<u8>) -> Result<()> {
-        publish_checked(&mut self.resources, struct_tag, blob, || {
-            format_err!("resource already published")
-        })
+    pub fn members(&self) -> &BTreeMap<StructTag, Vec<u8>> {
+        &self.members
     }
 
-    pub fn unpublish_resource(&mut self, struct_tag: StructTag) -> Result<()> {
-        unpublish_checked(&mut self.resources, struct_tag, || {
-            format_err!("resource already unpublished")
-        })
+    pub fn get(&self, struct_tag: &StructTag) -> Option<&[u8]> {
+        self.members.get(struct_tag).map(|blob| blob.as_slice())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Applies changes to members of the group, failing if a member to be deleted does not exist.
+    pub fn apply(
+        &mut self,
+        changes: impl IntoIterator<Item = (StructTag, Option<Vec<u8>>)>,
+    ) -> Result<()> {
+        for (struct_tag, blob_opt) in changes {
+            match blob_opt {
+                Some(blob) => {
+                    self.members.insert(struct_tag, blob);
+                }
+                None => {
+                    if self.members.remove(&struct_tag).is_none() {
+                        return Err(format_err!(
+                            "resource {} is not a member of the group",
+                            struct_tag
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 /// A collection of changes to a Move state. Each AccountChangeSet in the domain of `accounts`
 /// is guaranteed to be nonempty
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct ChangeSet {
     accounts: BTreeMap<AccountAddress, AccountChangeSet>,
 }
@@ -189,58 +302,38 @@ impl ChangeSet {
         }
     }
 
-    pub fn publish_or_overwrite_module(&mut self, module_id: ModuleId, blob: Vec<u8>) {
-        let (addr, name) = module_id.into();
-        let account_changeset = self.get_or_insert_account_changeset(addr);
-        account_changeset.publish_or_overwrite_module(name, blob)
-    }
-
-    pub fn publish_module(&mut self, module_id: ModuleId, blob: Vec<u8>) -> Result<()> {
-        let (addr, name) = module_id.into();
-        let account_changeset = self.get_or_insert_account_changeset(addr);
-        account_changeset.publish_module(name, blob)
-    }
-
-    pub fn unpublish_module(&mut self, module_id: ModuleId) -> Result<()> {
+    pub fn add_module_op(&mut self, module_id: ModuleId, op: Op<Vec<u8>>) -> Result<()> {
         let (addr, name) = module_id.into();
-        let account_changeset = self.get_or_insert_account_changeset(addr);
-        account_changeset.unpublish_module(name)
-    }
-
-    pub fn publish_or_overwrite_resource(
-        &mut self,
-        addr: AccountAddress,
-        struct_tag: StructTag,
-        blob: Vec<u8>,
-    ) {
         self.get_or_insert_account_changeset(addr)
-            .publish_or_overwrite_resource(struct_tag, blob)
+            .add_module_op(name, op)
     }
 
-    pub fn publish_resource(
+    pub fn add_resource_op(
         &mut self,
         addr: AccountAddress,
         struct_tag: StructTag,
-        blob: Vec<u8>,
+        op: Op<Vec<u8>>,
     ) -> Result<()> {
         self.get_or_insert_account_changeset(addr)
-            .publish_resource(struct_tag, blob)
-    }
-
-    pub fn unpublish_resource(
-        &mut self,
-        addr: AccountAddress,
-        struct_tag: StructTag,
-    ) -> Result<()> {
-        self.get_or_insert_account_changeset(addr)
-            .unpublish_resource(struct_tag)
+            .add_resource_op(struct_tag, op)
     }
 
+    /// Merges the changes of `other`, which happen after the ones of `self`, into `self`, e.g.,
+    /// to combine the outputs of several sessions executed one after the other. Creating then
+    /// modifying a value squashes into creating it, creating then deleting it cancels out, and
+    /// deleting then creating it again squashes into modifying it. Fails if the operations on a
+    /// value are inconsistent, e.g., a value created twice.
+    ///
+    /// Events are not part of a change set: the events of successive sessions are combined by
+    /// appending them in order.
     pub fn squash(&mut self, other: Self) -> Result<()> {
         for (addr, other_account_changeset) in other.accounts {
             match self.accounts.entry(addr) {
                 btree_map::Entry::Occupied(mut entry) => {
                     entry.get_mut().squash(other_account_changeset)?;
+                    if entry.get().is_empty() {
+                        entry.remove();
+                    }
                 }
                 btree_map::Entry::Vacant(entry) => {
                     entry.insert(other_account_changeset);
@@ -250,32 +343,53 @@ impl ChangeSet {
         Ok(())
     }
 
-    pub fn into_modules(self) -> impl Iterator<Item = (ModuleId, Option<Vec<u8>>)> {
+    pub fn into_modules(self) -> impl Iterator<Item = (ModuleId, Op<Vec<u8>>)> {
         self.accounts.into_iter().flat_map(|(addr, account)| {
             account
                 .modules
                 .into_iter()
-                .map(move |(module_name, blob_opt)| (ModuleId::new(addr, module_name), blob_opt))
+                .map(move |(module_name, op)| (ModuleId::new(addr, module_name), op))
         })
     }
 
-    pub fn modules(&self) -> impl Iterator<Item = (AccountAddress, &Identifier, Option<&[u8]>)> {
+    pub fn modules(&self) -> impl Iterator<Item = (AccountAddress, &Identifier, Op<&[u8]>)> {
         self.accounts.iter().flat_map(|(addr, account)| {
             let addr = *addr;
-            account.modules.iter().map(move |(module_name, blob_opt)| {
-                (addr, module_name, blob_opt.as_ref().map(|v| v.as_ref()))
-            })
+            account
+                .modules
+                .iter()
+                .map(move |(module_name, op)| (addr, module_name, op.as_ref().map(|v| v.as_ref())))
         })
     }
 
-    pub fn resources(&self) -> impl Iterator<Item = (AccountAddress, &StructTag, Option<&[u8]>)> {
+    pub fn resources(&self) -> impl Iterator<Item = (AccountAddress, &StructTag, Op<&[u8]>)> {
         self.accounts.iter().flat_map(|(addr, account)| {
             let addr = *addr;
-            account.resources.iter().map(move |(struct_tag, blob_opt)| {
-                (addr, struct_tag, blob_opt.as_ref().map(|v| v.as_ref()))
-            })
+            account
+                .resources
+                .iter()
+                .map(move |(struct_tag, op)| (addr, struct_tag, op.as_ref().map(|v| v.as_ref())))
         })
     }
 }
 
+/// The resources and modules accessed by an execution, e.g., for a scheduler to detect conflicts
+/// between transactions executed in parallel, or for debugging.
+///
+/// Resources are identified by their own struct tag, even when they are stored in a resource
+/// group.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ReadWriteSet {
+    /// Resources read from storage, whether they existed or not
+    pub resource_reads: BTreeSet<(AccountAddress, StructTag)>,
+    /// Resources created or modified
+    pub resource_writes: BTreeSet<(AccountAddress, StructTag)>,
+    /// Resources deleted
+    pub resource_deletes: BTreeSet<(AccountAddress, StructTag)>,
+    /// Modules read from storage, or which the code executed depends on
+    pub module_reads: BTreeSet<ModuleId>,
+    /// Modules published or upgraded
+    pub module_writes: BTreeSet<ModuleId>,
+}
+
 pub type Event = (Vec<u8>, u64, TypeTag, Vec<u8>);
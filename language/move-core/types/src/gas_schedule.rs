@@ -9,6 +9,7 @@
 //! native function itself.
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     ops::{Add, Div, Mul, Sub},
     u64,
 };
@@ -199,6 +200,10 @@ pub struct GasConstants {
 
     pub gas_unit_scaling_factor: GasCarrier,
     pub default_account_size: AbstractMemorySize<GasCarrier>,
+
+    /// The cost per-byte of a module the code executed depends on, charged the first time the
+    /// module is used in a session.
+    pub dependency_per_byte_cost: InternalGasUnits<GasCarrier>,
 }
 
 impl GasConstants {
@@ -225,6 +230,7 @@ impl Default for GasConstants {
             max_transaction_size_in_bytes: MAX_TRANSACTION_SIZE_IN_BYTES,
             gas_unit_scaling_factor: 1000,
             default_account_size: DEFAULT_ACCOUNT_SIZE,
+            dependency_per_byte_cost: InternalGasUnits(4),
         }
     }
 }
@@ -252,6 +258,88 @@ impl CostTable {
     }
 }
 
+/// Gas parameters of the natives of embedders, by name, e.g., `mychain.ed25519.per_byte`, so that
+/// they do not need to reuse the entries of `native_table` of the built-in natives.
+///
+/// They are kept apart from the `CostTable`, whose serialized form, e.g., in on-chain
+/// configuration, does not change, and can be stored and versioned on their own.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Deserialize)]
+pub struct NativeGasParameters {
+    parameters: BTreeMap<String, InternalGasUnits<GasCarrier>>,
+}
+
+impl NativeGasParameters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a named gas parameter, returning the value it replaces, if any. Names are best
+    /// prefixed with the embedder they belong to, e.g., `mychain.ed25519.per_byte`.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        value: InternalGasUnits<GasCarrier>,
+    ) -> Option<InternalGasUnits<GasCarrier>> {
+        self.parameters.insert(name.into(), value)
+    }
+
+    /// Returns the value of a named gas parameter, or `None` if it is not registered.
+    pub fn get(&self, name: &str) -> Option<InternalGasUnits<GasCarrier>> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        bcs::from_bytes(bytes).map_err(Into::into)
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        bcs::to_bytes(self).map_err(Into::into)
+    }
+}
+
+/// The successive versions of the cost table, e.g., as stored in on-chain configuration, so that
+/// gas pricing can evolve through governance without changing the VM. The version pricing a
+/// session is selected when creating its gas meter.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Deserialize)]
+pub struct VersionedCostTables {
+    tables: BTreeMap<u64, CostTable>,
+}
+
+impl VersionedCostTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the cost table of a version, returning the one it replaces, if any.
+    pub fn insert(&mut self, version: u64, cost_table: CostTable) -> Option<CostTable> {
+        self.tables.insert(version, cost_table)
+    }
+
+    pub fn get(&self, version: u64) -> Option<&CostTable> {
+        self.tables.get(&version)
+    }
+
+    /// Returns the latest version, along with its cost table.
+    pub fn latest(&self) -> Option<(u64, &CostTable)> {
+        self.tables
+            .iter()
+            .next_back()
+            .map(|(version, cost_table)| (*version, cost_table))
+    }
+
+    pub fn versions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.tables.keys().copied()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        bcs::from_bytes(bytes).map_err(Into::into)
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        bcs::to_bytes(self).map_err(Into::into)
+    }
+}
+
 /// The  `GasCost` tracks:
 /// - instruction cost: how much time/computational power is needed to perform the instruction
 /// - memory cost: how much memory is required for the instruction, and storage overhead
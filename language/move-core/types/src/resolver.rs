@@ -42,6 +42,20 @@ pub trait ResourceResolver {
         address: &AccountAddress,
         typ: &StructTag,
     ) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Returns the tag of the resource group the given resource type is a member of, if any.
+    /// Members of a group are stored together, in the slot of the resource whose type is the tag
+    /// of the group (see `effects::ResourceGroup`), rather than each in its own slot.
+    fn get_resource_group(&self, _typ: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Hints that the given resources are likely to be read soon, so that a backend can fetch
+    /// them in one batch, e.g., in a single round trip to a database, rather than one by one when
+    /// `get_resource` is called. The resources are still read with `get_resource`.
+    fn prefetch_resources(&self, _keys: &[(AccountAddress, StructTag)]) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 /// A persistent storage implementation that can resolve both resources and modules
@@ -67,6 +81,14 @@ impl<T: ResourceResolver + ?Sized> ResourceResolver for &T {
     ) -> Result<Option<Vec<u8>>, Self::Error> {
         (**self).get_resource(address, tag)
     }
+
+    fn get_resource_group(&self, tag: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        (**self).get_resource_group(tag)
+    }
+
+    fn prefetch_resources(&self, keys: &[(AccountAddress, StructTag)]) -> Result<(), Self::Error> {
+        (**self).prefetch_resources(keys)
+    }
 }
 
 impl<T: ModuleResolver + ?Sized> ModuleResolver for &T {
@@ -0,0 +1,135 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account_address::AccountAddress,
+    effects::{AccountChangeSet, ChangeSet, Op, ResourceGroup},
+    identifier::Identifier,
+    language_storage::StructTag,
+};
+
+fn struct_tag(name: &str) -> StructTag {
+    StructTag {
+        address: AccountAddress::ONE,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    }
+}
+
+#[test]
+fn test_merge_resource_group() {
+    let group_tag = struct_tag("Group");
+
+    // a new group is published with its members
+    let mut changes = AccountChangeSet::new();
+    changes
+        .merge_resource_group(
+            group_tag.clone(),
+            None,
+            vec![
+                (struct_tag("A"), Some(vec![1])),
+                (struct_tag("B"), Some(vec![2])),
+            ],
+        )
+        .unwrap();
+    let blob = match changes.resources()[&group_tag].clone() {
+        Op::New(blob) => blob,
+        op => panic!("expected the group to be created, got {:?}", op),
+    };
+    let group = ResourceGroup::deserialize(&blob).unwrap();
+    assert_eq!(group.get(&struct_tag("A")), Some(&[1][..]));
+    assert_eq!(group.get(&struct_tag("B")), Some(&[2][..]));
+
+    // members which are not changed are kept
+    let mut changes = AccountChangeSet::new();
+    changes
+        .merge_resource_group(
+            group_tag.clone(),
+            Some(group.clone()),
+            vec![(struct_tag("A"), None)],
+        )
+        .unwrap();
+    let blob = match changes.resources()[&group_tag].clone() {
+        Op::Modify(blob) => blob,
+        op => panic!("expected the group to be modified, got {:?}", op),
+    };
+    let merged = ResourceGroup::deserialize(&blob).unwrap();
+    assert_eq!(merged.get(&struct_tag("A")), None);
+    assert_eq!(merged.get(&struct_tag("B")), Some(&[2][..]));
+
+    // a group without members is deleted
+    let mut changes = AccountChangeSet::new();
+    changes
+        .merge_resource_group(
+            group_tag.clone(),
+            Some(merged),
+            vec![(struct_tag("B"), None)],
+        )
+        .unwrap();
+    assert_eq!(changes.resources()[&group_tag], Op::Delete);
+
+    // deleting a member which does not exist fails
+    let mut changes = AccountChangeSet::new();
+    assert!(changes
+        .merge_resource_group(group_tag, None, vec![(struct_tag("A"), None)])
+        .is_err());
+}
+
+fn resource_ops(ops: Vec<Op<Vec<u8>>>) -> Vec<ChangeSet> {
+    ops.into_iter()
+        .map(|op| {
+            let mut change_set = ChangeSet::new();
+            change_set
+                .add_resource_op(AccountAddress::ONE, struct_tag("A"), op)
+                .unwrap();
+            change_set
+        })
+        .collect()
+}
+
+// Squashes change sets each containing one of the given operations on a resource, returning the
+// resulting operation (`None` if it cancelled out)
+fn squash_ops(ops: Vec<Op<Vec<u8>>>) -> anyhow::Result<Option<Op<Vec<u8>>>> {
+    let mut change_set = ChangeSet::new();
+    for other in resource_ops(ops) {
+        change_set.squash(other)?;
+    }
+    let op = change_set
+        .resources()
+        .next()
+        .map(|(_, _, op)| op.map(|blob| blob.to_vec()));
+    Ok(op)
+}
+
+#[test]
+fn test_squash() {
+    use Op::*;
+
+    assert_eq!(
+        squash_ops(vec![New(vec![1]), Modify(vec![2])]).unwrap(),
+        Some(New(vec![2]))
+    );
+    assert_eq!(
+        squash_ops(vec![Modify(vec![1]), Modify(vec![2])]).unwrap(),
+        Some(Modify(vec![2]))
+    );
+    assert_eq!(
+        squash_ops(vec![Modify(vec![1]), Delete]).unwrap(),
+        Some(Delete)
+    );
+    assert_eq!(
+        squash_ops(vec![Delete, New(vec![2])]).unwrap(),
+        Some(Modify(vec![2]))
+    );
+    assert_eq!(squash_ops(vec![New(vec![1]), Delete]).unwrap(), None);
+    assert_eq!(
+        squash_ops(vec![New(vec![1]), Delete, New(vec![2])]).unwrap(),
+        Some(New(vec![2]))
+    );
+
+    assert!(squash_ops(vec![New(vec![1]), New(vec![2])]).is_err());
+    assert!(squash_ops(vec![Modify(vec![1]), New(vec![2])]).is_err());
+    assert!(squash_ops(vec![Delete, Delete]).is_err());
+    assert!(squash_ops(vec![Delete, Modify(vec![2])]).is_err());
+}
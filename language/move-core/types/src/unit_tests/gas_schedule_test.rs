@@ -0,0 +1,65 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::gas_schedule::{
+    CostTable, GasAlgebra, GasConstants, GasCost, InternalGasUnits, NativeGasParameters,
+    VersionedCostTables,
+};
+use std::collections::BTreeMap;
+
+// The layout of cost tables stored before natives could have named gas parameters.
+type OldCostTable = (Vec<GasCost>, Vec<GasCost>, GasConstants);
+
+fn old_cost_table() -> OldCostTable {
+    (
+        vec![GasCost::new(1, 1), GasCost::new(2, 1)],
+        vec![GasCost::new(3, 1)],
+        GasConstants::default(),
+    )
+}
+
+#[test]
+fn decode_old_cost_table() {
+    let (instruction_table, native_table, gas_constants) = old_cost_table();
+    let blob = bcs::to_bytes(&old_cost_table()).unwrap();
+    let cost_table: CostTable = bcs::from_bytes(&blob).unwrap();
+    assert_eq!(
+        cost_table,
+        CostTable {
+            instruction_table,
+            native_table,
+            gas_constants,
+        }
+    );
+    assert_eq!(bcs::to_bytes(&cost_table).unwrap(), blob);
+}
+
+#[test]
+fn decode_old_versioned_cost_tables() {
+    let mut old_tables = BTreeMap::new();
+    old_tables.insert(1u64, old_cost_table());
+    old_tables.insert(3u64, old_cost_table());
+    let blob = bcs::to_bytes(&(old_tables,)).unwrap();
+    let tables = VersionedCostTables::from_bytes(&blob).unwrap();
+    assert_eq!(tables.versions().collect::<Vec<_>>(), vec![1, 3]);
+    assert_eq!(tables.to_bytes().unwrap(), blob);
+}
+
+#[test]
+fn native_gas_parameters_round_trip() {
+    let mut parameters = NativeGasParameters::new();
+    assert_eq!(
+        parameters.register("test.per_byte", InternalGasUnits::new(10)),
+        None
+    );
+    assert_eq!(
+        parameters.register("test.per_byte", InternalGasUnits::new(20)),
+        Some(InternalGasUnits::new(10))
+    );
+    let decoded = NativeGasParameters::from_bytes(&parameters.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        decoded.get("test.per_byte"),
+        Some(InternalGasUnits::new(20))
+    );
+    assert_eq!(decoded.get("test.other"), None);
+}
@@ -2,6 +2,8 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+mod effects_test;
+mod gas_schedule_test;
 mod identifier_test;
 mod language_storage_test;
 mod value_test;
@@ -664,6 +664,28 @@ pub enum StatusCode {
     VM_MAX_TYPE_DEPTH_REACHED = 4024,
     VM_MAX_VALUE_DEPTH_REACHED = 4025,
     VM_EXTENSION_ERROR = 4026,
+    // The type arguments of a generic function or struct instantiated at runtime have more nodes
+    // than allowed.
+    VM_MAX_TYPE_INSTANTIATION_SIZE_REACHED = 4027,
+    // The limit on the number of instructions or native calls executed, independent of gas, was
+    // reached.
+    EXECUTION_LIMIT_REACHED = 4028,
+    // The operation, e.g., publishing modules, was disallowed in the session it was attempted in.
+    OPERATION_DISALLOWED_IN_SESSION = 4029,
+    // A call to a native could not be recorded, or a replayed execution called natives differently
+    // than the execution recorded.
+    NATIVE_REPLAY_ERROR = 4030,
+    // The layout of a type has more nodes than allowed.
+    VM_MAX_TYPE_LAYOUT_NODES_REACHED = 4031,
+    // A resource written is larger, once serialized, than allowed.
+    RESOURCE_SIZE_LIMIT_EXCEEDED = 4032,
+    // The version of the gas schedule selected does not exist.
+    UNKNOWN_GAS_SCHEDULE_VERSION = 4033,
+    // The execution was interrupted by the watchdog of the session, e.g., because it exceeded a
+    // deadline.
+    EXECUTION_INTERRUPTED = 4034,
+    // The layout of a type contains itself, through a cycle of struct instantiations.
+    CYCLIC_TYPE_LAYOUT = 4035,
 
 
     // A reserved status to represent an unknown vm status.
@@ -6,6 +6,7 @@ use anyhow::{format_err, Result};
 use move_binary_format::{
     access::ModuleAccess,
     binary_views::BinaryIndexedView,
+    errors::SourceLocation,
     file_format::{
         AbilitySet, CodeOffset, CodeUnit, ConstantPoolIndex, FunctionDefinitionIndex, LocalIndex,
         MemberCount, ModuleHandleIndex, SignatureIndex, StructDefinition, StructDefinitionIndex,
@@ -363,6 +364,29 @@ impl SourceMap {
             .ok_or_else(|| format_err!("Tried to get code location from undefined function index"))
     }
 
+    /// Returns the line and column, in the file `file_name` with the given contents, of the
+    /// instruction at `offset` in the function definition. Returns `None` if the location of the
+    /// instruction is unknown or if the source map was not built from those contents.
+    pub fn get_source_location(
+        &self,
+        fdef_idx: FunctionDefinitionIndex,
+        offset: CodeOffset,
+        file_name: &str,
+        file_contents: &str,
+    ) -> Option<SourceLocation> {
+        let loc = self.get_code_location(fdef_idx, offset).ok()?;
+        if loc.file_hash() != FileHash::new(file_contents) {
+            return None;
+        }
+        let preceding = file_contents.get(..loc.start() as usize)?;
+        let line_start = preceding.rfind('\n').map_or(0, |idx| idx + 1);
+        Some(SourceLocation {
+            file: file_name.to_string(),
+            line: preceding.matches('\n').count() as u32 + 1,
+            column: preceding[line_start..].chars().count() as u32 + 1,
+        })
+    }
+
     pub fn add_local_mapping(
         &mut self,
         fdef_idx: FunctionDefinitionIndex,
@@ -89,7 +89,7 @@ impl Ord for Loc {
 // Spanned
 //**************************************************************************************************
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Spanned<T> {
     pub loc: Loc,
     pub value: T,
@@ -768,6 +768,16 @@ impl GlobalEnv {
             .any(|(d, _)| d.message.contains(pattern))
     }
 
+    /// Returns accumulated diagnostics of given or higher severity.
+    pub fn get_diags(&self, min_severity: Severity) -> Vec<Diagnostic<FileId>> {
+        self.diags
+            .borrow()
+            .iter()
+            .filter(|(d, _)| d.severity >= min_severity)
+            .map(|(d, _)| d.clone())
+            .collect()
+    }
+
     /// Clear all accumulated diagnosis.
     pub fn clear_diag(&self) {
         self.diags.borrow_mut().clear();
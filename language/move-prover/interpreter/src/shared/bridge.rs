@@ -3,7 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use move_binary_format::errors::{PartialVMError, VMResult};
-use move_core_types::{effects::ChangeSet, language_storage::ModuleId, resolver::MoveResolver};
+use move_core_types::{
+    effects::{ChangeSet, Op},
+    language_storage::ModuleId,
+    resolver::MoveResolver,
+};
 
 /// The result returned by the stackless VM does not contain code offsets and indices. In order to
 /// do cross-vm comparison, we need to adapt the Move VM result by removing these fields.
@@ -38,31 +42,25 @@ fn adapt_move_vm_change_set_internal<S: MoveResolver>(
     let mut adapted = ChangeSet::new();
     for (addr, state) in change_set.into_inner() {
         let (modules, resources) = state.into_inner();
-        for (tag, val) in resources {
-            match val {
-                // deletion
-                None => adapted.unpublish_resource(addr, tag).unwrap(),
-                // addition / modification
-                Some(new_val) => match old_storage.get_resource(&addr, &tag).unwrap() {
-                    // addition
-                    None => adapted.publish_resource(addr, tag, new_val).unwrap(),
-                    // modification is only added to change_set if the values actually change
-                    Some(old_val) => {
-                        if new_val != old_val {
-                            adapted.publish_resource(addr, tag, new_val).unwrap();
-                        }
+        for (tag, op) in resources {
+            match op {
+                // modification is only added to change_set if the values actually change
+                Op::Modify(new_val) => {
+                    let old_val = old_storage.get_resource(&addr, &tag).unwrap();
+                    if old_val.as_ref() != Some(&new_val) {
+                        adapted
+                            .add_resource_op(addr, tag, Op::Modify(new_val))
+                            .unwrap();
                     }
-                },
+                }
+                // addition / deletion
+                op => adapted.add_resource_op(addr, tag, op).unwrap(),
             }
         }
-        for (module_name, blob_opt) in modules {
-            let module_id = ModuleId::new(addr, module_name);
-            match blob_opt {
-                // deletion
-                None => adapted.unpublish_module(module_id).unwrap(),
-                // addition
-                Some(blob) => adapted.publish_module(module_id, blob).unwrap(),
-            }
+        for (module_name, op) in modules {
+            adapted
+                .add_module_op(ModuleId::new(addr, module_name), op)
+                .unwrap();
         }
     }
     adapted
@@ -132,4 +132,13 @@ module std::errors {
         aborts_if false;
         ensures result == CUSTOM;
     }
+
+    /// Aborts with the given code, as `abort code` does, along with a payload, e.g., a
+    /// human-readable reason for the failure, which is reported to the caller of the VM alongside
+    /// the code.
+    native public fun abort_with_payload(code: u64, payload: vector<u8>);
+    spec abort_with_payload {
+        pragma opaque;
+        aborts_if true with code;
+    }
 }
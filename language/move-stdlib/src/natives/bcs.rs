@@ -8,7 +8,7 @@ use move_vm_runtime::native_functions::NativeContext;
 use move_vm_types::{
     gas_schedule::NativeCostIndex,
     loaded_data::runtime_types::Type,
-    natives::function::{native_gas, NativeResult},
+    natives::function::NativeResult,
     pop_arg,
     values::{values_impl::Reference, Value},
 };
@@ -34,18 +34,14 @@ pub fn native_to_bytes(
     };
     let serialized_value = match serialized_value_opt {
         None => {
-            let cost = native_gas(context.cost_table(), NativeCostIndex::BCS_TO_BYTES, 1);
+            let cost = context.native_gas(NativeCostIndex::BCS_TO_BYTES, 1);
             return Ok(NativeResult::err(cost, NFE_BCS_SERIALIZATION_FAILURE));
         }
         Some(serialized_value) => serialized_value,
     };
 
     // cost is proportional to the size of the serialized value
-    let cost = native_gas(
-        context.cost_table(),
-        NativeCostIndex::BCS_TO_BYTES,
-        serialized_value.len(),
-    );
+    let cost = context.native_gas(NativeCostIndex::BCS_TO_BYTES, serialized_value.len());
 
     Ok(NativeResult::ok(
         cost,
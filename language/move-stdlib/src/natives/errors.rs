@@ -0,0 +1,35 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_binary_format::errors::PartialVMResult;
+use move_core_types::gas_schedule::{AbstractMemorySize, GasAlgebra, GasCarrier, InternalGasUnits};
+use move_vm_runtime::native_functions::NativeContext;
+use move_vm_types::{
+    loaded_data::runtime_types::Type, natives::function::NativeResult, pop_arg, values::Value,
+};
+use std::collections::VecDeque;
+
+/// Name of the gas parameter (see `NativeContext::native_gas_parameter`) charged per byte of the
+/// payload by `abort_with_payload`, which is free if the parameter is not registered.
+pub const ABORT_WITH_PAYLOAD_PER_BYTE: &str = "std.errors.abort_with_payload.per_byte";
+
+/// Rust implementation of Move's `native public fun abort_with_payload(code: u64, payload: vector<u8>)`
+pub fn native_abort_with_payload(
+    context: &mut NativeContext,
+    ty_args: Vec<Type>,
+    mut args: VecDeque<Value>,
+) -> PartialVMResult<NativeResult> {
+    debug_assert!(ty_args.is_empty());
+    debug_assert!(args.len() == 2);
+
+    let payload = pop_arg!(args, Vec<u8>);
+    let code = pop_arg!(args, u64);
+
+    // cost is proportional to the size of the payload
+    let cost = context
+        .native_gas_parameter(ABORT_WITH_PAYLOAD_PER_BYTE)
+        .unwrap_or_else(|| InternalGasUnits::new(0))
+        .mul(AbstractMemorySize::new(payload.len() as GasCarrier));
+
+    Ok(NativeResult::err_with_payload(cost, code, payload))
+}
@@ -5,11 +5,8 @@
 use move_binary_format::errors::PartialVMResult;
 use move_vm_runtime::native_functions::NativeContext;
 use move_vm_types::{
-    gas_schedule::NativeCostIndex,
-    loaded_data::runtime_types::Type,
-    natives::function::{native_gas, NativeResult},
-    pop_arg,
-    values::Value,
+    gas_schedule::NativeCostIndex, loaded_data::runtime_types::Type,
+    natives::function::NativeResult, pop_arg, values::Value,
 };
 use sha2::{Digest, Sha256};
 use sha3::Sha3_256;
@@ -26,11 +23,7 @@ pub fn native_sha2_256(
 
     let hash_arg = pop_arg!(arguments, Vec<u8>);
 
-    let cost = native_gas(
-        context.cost_table(),
-        NativeCostIndex::SHA2_256,
-        hash_arg.len(),
-    );
+    let cost = context.native_gas(NativeCostIndex::SHA2_256, hash_arg.len());
 
     let hash_vec = Sha256::digest(hash_arg.as_slice()).to_vec();
     Ok(NativeResult::ok(
@@ -49,11 +42,7 @@ pub fn native_sha3_256(
 
     let hash_arg = pop_arg!(arguments, Vec<u8>);
 
-    let cost = native_gas(
-        context.cost_table(),
-        NativeCostIndex::SHA3_256,
-        hash_arg.len(),
-    );
+    let cost = context.native_gas(NativeCostIndex::SHA3_256, hash_arg.len());
 
     let hash_vec = Sha3_256::digest(hash_arg.as_slice()).to_vec();
     Ok(NativeResult::ok(
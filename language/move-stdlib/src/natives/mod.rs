@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod bcs;
+pub mod errors;
 pub mod event;
 pub mod hash;
 pub mod signer;
@@ -17,12 +18,17 @@ pub mod debug;
 use move_core_types::account_address::AccountAddress;
 use move_vm_runtime::{
     native_functions,
-    native_functions::{NativeFunction, NativeFunctionTable},
+    native_functions::{NativeFunctionTable, StatelessNativeFunction},
 };
 
 pub fn all_natives(move_std_addr: AccountAddress) -> NativeFunctionTable {
-    const NATIVES: &[(&str, &str, NativeFunction)] = &[
+    const NATIVES: &[(&str, &str, StatelessNativeFunction)] = &[
         ("bcs", "to_bytes", bcs::native_to_bytes),
+        (
+            "errors",
+            "abort_with_payload",
+            errors::native_abort_with_payload,
+        ),
         ("event", "write_to_event_store", event::write_to_event_store),
         ("hash", "sha2_256", hash::native_sha2_256),
         ("hash", "sha3_256", hash::native_sha3_256),
@@ -54,7 +60,7 @@ pub fn all_natives(move_std_addr: AccountAddress) -> NativeFunctionTable {
 }
 
 pub fn all_natives_old_names(move_std_addr: AccountAddress) -> NativeFunctionTable {
-    const NATIVES: &[(&str, &str, NativeFunction)] = &[
+    const NATIVES: &[(&str, &str, StatelessNativeFunction)] = &[
         ("BCS", "to_bytes", bcs::native_to_bytes),
         ("Event", "write_to_event_store", event::write_to_event_store),
         ("Hash", "sha2_256", hash::native_sha2_256),
@@ -7,7 +7,7 @@ use move_vm_runtime::native_functions::NativeContext;
 use move_vm_types::{
     gas_schedule::NativeCostIndex,
     loaded_data::runtime_types::Type,
-    natives::function::{native_gas, NativeResult},
+    natives::function::NativeResult,
     pop_arg,
     values::{values_impl::SignerRef, Value},
 };
@@ -23,7 +23,7 @@ pub fn native_borrow_address(
     debug_assert!(arguments.len() == 1);
 
     let signer_reference = pop_arg!(arguments, SignerRef);
-    let cost = native_gas(context.cost_table(), NativeCostIndex::SIGNER_BORROW, 1);
+    let cost = context.native_gas(NativeCostIndex::SIGNER_BORROW, 1);
 
     Ok(NativeResult::ok(
         cost,
@@ -15,4 +15,10 @@ module std::errors_tests {
         assert!(errors::custom(0) == 255, 9);
     }
 
+    #[test]
+    #[expected_failure(abort_code = 7)]
+    fun abort_with_payload() {
+        errors::abort_with_payload(7, b"invalid argument");
+    }
+
 }
@@ -0,0 +1,9 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use move_vm_fuzz::{run, FuzzInput};
+
+fuzz_target!(|input: FuzzInput| run(input));
@@ -0,0 +1,417 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+//! Fuzzing harness of the Move VM interpreter.
+//!
+//! The `interpreter` target builds a module out of arbitrary bytecode, and runs its function with
+//! arbitrary arguments when the bytecode verifier accepts it. The VM is expected to reject
+//! misbehaving code with an execution error: panics and invariant violations, which the VM runs
+//! with paranoid checks on to detect, are reported as crashes.
+//!
+//! The target is run from this directory, on a nightly toolchain, with
+//! `cargo fuzz run interpreter`.
+
+use arbitrary::{Arbitrary, Unstructured};
+use move_binary_format::{
+    file_format::{
+        AbilitySet, AddressIdentifierIndex, Bytecode, CodeOffset, CodeUnit, CompiledModule,
+        FieldDefinition, FieldHandle, FieldHandleIndex, FunctionDefinition, FunctionHandle,
+        FunctionHandleIndex, IdentifierIndex, LocalIndex, ModuleHandle, ModuleHandleIndex,
+        Signature, SignatureIndex, SignatureToken, StructDefinition, StructDefinitionIndex,
+        StructFieldInformation, StructHandle, StructHandleIndex, TypeSignature, Visibility,
+    },
+    file_format_common::VERSION_MAX,
+};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::{IdentStr, Identifier},
+    value::{MoveStruct, MoveValue},
+    vm_status::StatusType,
+};
+use move_vm_runtime::{config::VMConfig, move_vm::MoveVM};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+
+/// Maximum number of instructions executed by an input, so that loops terminate
+const MAX_INSTRUCTIONS: u64 = 10_000;
+
+const MODULE_NAME: &str = "M";
+const STRUCT_NAME: &str = "S";
+const FIELD_NAMES: [&str; 2] = ["a", "b"];
+const FUNCTION_NAME: &str = "f";
+
+/// The types of the parameters and locals of the fuzzed function, and of the elements of the
+/// vectors it operates on. `Struct` is the only struct of the module,
+/// `struct S has copy, drop, store, key { a: u64, b: vector<u8> }`.
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum FuzzType {
+    Bool,
+    U8,
+    U64,
+    U128,
+    Address,
+    Signer,
+    VectorU8,
+    VectorU64,
+    VectorVectorU8,
+    Struct,
+}
+
+impl FuzzType {
+    fn signature_token(self) -> SignatureToken {
+        use SignatureToken as S;
+
+        match self {
+            Self::Bool => S::Bool,
+            Self::U8 => S::U8,
+            Self::U64 => S::U64,
+            Self::U128 => S::U128,
+            Self::Address => S::Address,
+            Self::Signer => S::Signer,
+            Self::VectorU8 => S::Vector(Box::new(S::U8)),
+            Self::VectorU64 => S::Vector(Box::new(S::U64)),
+            Self::VectorVectorU8 => S::Vector(Box::new(S::Vector(Box::new(S::U8)))),
+            Self::Struct => S::Struct(StructHandleIndex(0)),
+        }
+    }
+
+    fn arbitrary_value(self, u: &mut Unstructured) -> arbitrary::Result<MoveValue> {
+        Ok(match self {
+            Self::Bool => MoveValue::Bool(u.arbitrary()?),
+            Self::U8 => MoveValue::U8(u.arbitrary()?),
+            Self::U64 => MoveValue::U64(u.arbitrary()?),
+            Self::U128 => MoveValue::U128(u.arbitrary()?),
+            Self::Address => MoveValue::Address(AccountAddress::new(u.arbitrary()?)),
+            Self::Signer => MoveValue::Signer(AccountAddress::new(u.arbitrary()?)),
+            Self::VectorU8 => MoveValue::vector_u8(u.arbitrary()?),
+            Self::VectorU64 => MoveValue::Vector(
+                u.arbitrary_iter::<u64>()?
+                    .map(|elem| elem.map(MoveValue::U64))
+                    .collect::<arbitrary::Result<_>>()?,
+            ),
+            Self::VectorVectorU8 => MoveValue::Vector(
+                u.arbitrary_iter::<Vec<u8>>()?
+                    .map(|elem| elem.map(MoveValue::vector_u8))
+                    .collect::<arbitrary::Result<_>>()?,
+            ),
+            Self::Struct => MoveValue::Struct(MoveStruct::new(vec![
+                MoveValue::U64(u.arbitrary()?),
+                MoveValue::vector_u8(u.arbitrary()?),
+            ])),
+        })
+    }
+}
+
+/// The instructions of the fuzzed function. Operands are left unchecked, the inputs with invalid
+/// ones being rejected by the bytecode verifier. Fields are `S.a` (`false`) and `S.b` (`true`),
+/// and `Call` calls the fuzzed function itself.
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum FuzzBytecode {
+    Pop,
+    Ret,
+    BrTrue(CodeOffset),
+    BrFalse(CodeOffset),
+    Branch(CodeOffset),
+    LdU8(u8),
+    LdU64(u64),
+    LdU128(u128),
+    CastU8,
+    CastU64,
+    CastU128,
+    LdTrue,
+    LdFalse,
+    CopyLoc(LocalIndex),
+    MoveLoc(LocalIndex),
+    StLoc(LocalIndex),
+    Call,
+    Pack,
+    Unpack,
+    ReadRef,
+    WriteRef,
+    FreezeRef,
+    MutBorrowLoc(LocalIndex),
+    ImmBorrowLoc(LocalIndex),
+    MutBorrowField(bool),
+    ImmBorrowField(bool),
+    MutBorrowGlobal,
+    ImmBorrowGlobal,
+    Add,
+    Sub,
+    Mul,
+    Mod,
+    Div,
+    BitOr,
+    BitAnd,
+    Xor,
+    Or,
+    And,
+    Not,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Abort,
+    Nop,
+    Exists,
+    MoveFrom,
+    MoveTo,
+    Shl,
+    Shr,
+    VecPack(FuzzType, u8),
+    VecLen(FuzzType),
+    VecImmBorrow(FuzzType),
+    VecMutBorrow(FuzzType),
+    VecPushBack(FuzzType),
+    VecPopBack(FuzzType),
+    VecUnpack(FuzzType, u8),
+    VecSwap(FuzzType),
+}
+
+/// A module with a single function, and the arguments to call it with
+#[derive(Debug)]
+pub struct FuzzInput {
+    parameters: Vec<FuzzType>,
+    args: Vec<MoveValue>,
+    locals: Vec<FuzzType>,
+    returns: Vec<FuzzType>,
+    acquires: bool,
+    code: Vec<FuzzBytecode>,
+}
+
+impl<'a> Arbitrary<'a> for FuzzInput {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let parameters: Vec<FuzzType> = u.arbitrary()?;
+        // the arguments are always of the types of the parameters, so that inputs are not wasted
+        // on failing to deserialize them
+        let args = parameters
+            .iter()
+            .map(|ty| ty.arbitrary_value(u))
+            .collect::<arbitrary::Result<_>>()?;
+        Ok(Self {
+            parameters,
+            args,
+            locals: u.arbitrary()?,
+            returns: u.arbitrary()?,
+            acquires: u.arbitrary()?,
+            code: u.arbitrary()?,
+        })
+    }
+}
+
+impl FuzzInput {
+    /// Builds the module, which may not pass the bytecode verifier
+    pub fn module(&self) -> CompiledModule {
+        // signatures must not be duplicated
+        let mut signatures = vec![];
+        let mut signature = |tys: Vec<SignatureToken>| {
+            let sig = Signature(tys);
+            let idx = match signatures.iter().position(|other| *other == sig) {
+                Some(idx) => idx,
+                None => {
+                    signatures.push(sig);
+                    signatures.len() - 1
+                }
+            };
+            SignatureIndex(idx as u16)
+        };
+        let tokens = |tys: &[FuzzType]| -> Vec<SignatureToken> {
+            tys.iter().map(|ty| ty.signature_token()).collect()
+        };
+        let parameters = signature(tokens(&self.parameters));
+        let returns = signature(tokens(&self.returns));
+        let locals = signature(tokens(&self.locals));
+        let code = self
+            .code
+            .iter()
+            .map(|instr| instr.to_bytecode(&mut |ty| signature(vec![ty.signature_token()])))
+            .collect();
+
+        let mut identifiers = vec![MODULE_NAME, STRUCT_NAME, FUNCTION_NAME];
+        identifiers.extend(FIELD_NAMES.iter().copied());
+        CompiledModule {
+            version: VERSION_MAX,
+            self_module_handle_idx: ModuleHandleIndex(0),
+            module_handles: vec![ModuleHandle {
+                address: AddressIdentifierIndex(0),
+                name: IdentifierIndex(0),
+            }],
+            struct_handles: vec![StructHandle {
+                module: ModuleHandleIndex(0),
+                name: IdentifierIndex(1),
+                abilities: AbilitySet::ALL,
+                type_parameters: vec![],
+            }],
+            function_handles: vec![FunctionHandle {
+                module: ModuleHandleIndex(0),
+                name: IdentifierIndex(2),
+                parameters,
+                return_: returns,
+                type_parameters: vec![],
+            }],
+            field_handles: (0..FIELD_NAMES.len())
+                .map(|field| FieldHandle {
+                    owner: StructDefinitionIndex(0),
+                    field: field as u16,
+                })
+                .collect(),
+            friend_decls: vec![],
+            struct_def_instantiations: vec![],
+            function_instantiations: vec![],
+            field_instantiations: vec![],
+            signatures,
+            identifiers: identifiers
+                .into_iter()
+                .map(|name| Identifier::new(name).unwrap())
+                .collect(),
+            address_identifiers: vec![AccountAddress::ONE],
+            constant_pool: vec![],
+            metadata: vec![],
+            struct_defs: vec![StructDefinition {
+                struct_handle: StructHandleIndex(0),
+                field_information: StructFieldInformation::Declared(vec![
+                    FieldDefinition {
+                        name: IdentifierIndex(3),
+                        signature: TypeSignature(SignatureToken::U64),
+                    },
+                    FieldDefinition {
+                        name: IdentifierIndex(4),
+                        signature: TypeSignature(FuzzType::VectorU8.signature_token()),
+                    },
+                ]),
+            }],
+            function_defs: vec![FunctionDefinition {
+                function: FunctionHandleIndex(0),
+                visibility: Visibility::Public,
+                is_entry: false,
+                acquires_global_resources: if self.acquires {
+                    vec![StructDefinitionIndex(0)]
+                } else {
+                    vec![]
+                },
+                code: Some(CodeUnit { locals, code }),
+            }],
+        }
+    }
+}
+
+impl FuzzBytecode {
+    fn to_bytecode(self, signature: &mut impl FnMut(FuzzType) -> SignatureIndex) -> Bytecode {
+        use Bytecode as B;
+
+        let field = |field: bool| FieldHandleIndex(field as u16);
+        let struct_def = StructDefinitionIndex(0);
+        match self {
+            Self::Pop => B::Pop,
+            Self::Ret => B::Ret,
+            Self::BrTrue(offset) => B::BrTrue(offset),
+            Self::BrFalse(offset) => B::BrFalse(offset),
+            Self::Branch(offset) => B::Branch(offset),
+            Self::LdU8(value) => B::LdU8(value),
+            Self::LdU64(value) => B::LdU64(value),
+            Self::LdU128(value) => B::LdU128(value),
+            Self::CastU8 => B::CastU8,
+            Self::CastU64 => B::CastU64,
+            Self::CastU128 => B::CastU128,
+            Self::LdTrue => B::LdTrue,
+            Self::LdFalse => B::LdFalse,
+            Self::CopyLoc(idx) => B::CopyLoc(idx),
+            Self::MoveLoc(idx) => B::MoveLoc(idx),
+            Self::StLoc(idx) => B::StLoc(idx),
+            Self::Call => B::Call(FunctionHandleIndex(0)),
+            Self::Pack => B::Pack(struct_def),
+            Self::Unpack => B::Unpack(struct_def),
+            Self::ReadRef => B::ReadRef,
+            Self::WriteRef => B::WriteRef,
+            Self::FreezeRef => B::FreezeRef,
+            Self::MutBorrowLoc(idx) => B::MutBorrowLoc(idx),
+            Self::ImmBorrowLoc(idx) => B::ImmBorrowLoc(idx),
+            Self::MutBorrowField(f) => B::MutBorrowField(field(f)),
+            Self::ImmBorrowField(f) => B::ImmBorrowField(field(f)),
+            Self::MutBorrowGlobal => B::MutBorrowGlobal(struct_def),
+            Self::ImmBorrowGlobal => B::ImmBorrowGlobal(struct_def),
+            Self::Add => B::Add,
+            Self::Sub => B::Sub,
+            Self::Mul => B::Mul,
+            Self::Mod => B::Mod,
+            Self::Div => B::Div,
+            Self::BitOr => B::BitOr,
+            Self::BitAnd => B::BitAnd,
+            Self::Xor => B::Xor,
+            Self::Or => B::Or,
+            Self::And => B::And,
+            Self::Not => B::Not,
+            Self::Eq => B::Eq,
+            Self::Neq => B::Neq,
+            Self::Lt => B::Lt,
+            Self::Gt => B::Gt,
+            Self::Le => B::Le,
+            Self::Ge => B::Ge,
+            Self::Abort => B::Abort,
+            Self::Nop => B::Nop,
+            Self::Exists => B::Exists(struct_def),
+            Self::MoveFrom => B::MoveFrom(struct_def),
+            Self::MoveTo => B::MoveTo(struct_def),
+            Self::Shl => B::Shl,
+            Self::Shr => B::Shr,
+            Self::VecPack(ty, len) => B::VecPack(signature(ty), len as u64),
+            Self::VecLen(ty) => B::VecLen(signature(ty)),
+            Self::VecImmBorrow(ty) => B::VecImmBorrow(signature(ty)),
+            Self::VecMutBorrow(ty) => B::VecMutBorrow(signature(ty)),
+            Self::VecPushBack(ty) => B::VecPushBack(signature(ty)),
+            Self::VecPopBack(ty) => B::VecPopBack(signature(ty)),
+            Self::VecUnpack(ty, len) => B::VecUnpack(signature(ty), len as u64),
+            Self::VecSwap(ty) => B::VecSwap(signature(ty)),
+        }
+    }
+}
+
+/// Runs the function of the input if its module passes the bytecode verifier, and panics if the
+/// VM fails with an invariant violation
+pub fn run(input: FuzzInput) {
+    let module = input.module();
+    // the VM relies on what the verifier checks, so only verified modules are of interest
+    if move_bytecode_verifier::verify_module(&module).is_err() {
+        return;
+    }
+    let mut blob = vec![];
+    module
+        .serialize(&mut blob)
+        .expect("verified module must serialize");
+    let module_id = module.self_id();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+    let args = input
+        .args
+        .iter()
+        .map(|arg| arg.simple_serialize().expect("argument must serialize"))
+        .collect::<Vec<_>>();
+
+    let vm_config = VMConfig {
+        paranoid_checks: true,
+        max_instructions: Some(MAX_INSTRUCTIONS),
+        ..VMConfig::default()
+    };
+    let vm = MoveVM::new_with_config(vec![], vm_config).unwrap();
+    let mut sess = vm.new_session(&storage);
+    let result = sess
+        .execute_function_bypass_visibility(
+            &module_id,
+            IdentStr::new(FUNCTION_NAME).unwrap(),
+            vec![],
+            args,
+            &mut GasStatus::new_unmetered(),
+        )
+        .and_then(|_| sess.finish().map(|_| ()));
+    if let Err(err) = result {
+        assert_ne!(
+            err.status_type(),
+            StatusType::InvariantViolation,
+            "verified code caused an invariant violation: {:?}",
+            err
+        );
+    }
+}
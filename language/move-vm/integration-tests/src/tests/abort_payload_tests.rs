@@ -0,0 +1,135 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::{Location, PartialVMResult};
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, InternalGasUnits},
+    identifier::Identifier,
+    language_storage::ModuleId,
+    value::{serialize_values, MoveValue},
+    vm_status::StatusCode,
+};
+use move_vm_runtime::{
+    move_vm::MoveVM,
+    native_functions::{make_native, NativeContext},
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{
+    gas_schedule::GasStatus, loaded_data::runtime_types::Type, natives::function::NativeResult,
+    values::Value,
+};
+use std::collections::VecDeque;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn native_fail(
+    _context: &mut NativeContext,
+    _ty_args: Vec<Type>,
+    _args: VecDeque<Value>,
+) -> PartialVMResult<NativeResult> {
+    Ok(NativeResult::err_with_payload(
+        InternalGasUnits::new(0),
+        42,
+        b"not enough coins".to_vec(),
+    ))
+}
+
+#[test]
+fn native_abort_payload() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            native fun fail();
+
+            fun call_fail() {{ fail() }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let module_id = m.self_id();
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+
+    let natives = vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("fail").unwrap(),
+        make_native(native_fail),
+    )];
+    let vm = MoveVM::new(natives).unwrap();
+    let mut sess = vm.new_session(&storage);
+    let err = sess
+        .execute_function_bypass_visibility(
+            &module_id,
+            &Identifier::new("call_fail").unwrap(),
+            vec![],
+            Vec::<Vec<u8>>::new(),
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::ABORTED);
+    assert_eq!(err.sub_status(), Some(42));
+    assert_eq!(err.abort_payload(), Some(&b"not enough coins"[..]));
+}
+
+#[test]
+fn move_abort_payload() {
+    let code = format!(
+        r#"
+        module 0x1::errors {{
+            native public fun abort_with_payload(code: u64, payload: vector<u8>);
+        }}
+
+        module 0x{}::M {{
+            use 0x1::errors;
+
+            fun withdraw(balance: u64, amount: u64): u64 {{
+                if (amount > balance) errors::abort_with_payload(7, b"not enough coins");
+                balance - amount
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut storage = InMemoryStorage::new();
+    for unit in compile_units(&code).unwrap() {
+        let m = as_module(unit);
+        let mut blob = vec![];
+        m.serialize(&mut blob).unwrap();
+        storage.publish_or_overwrite_module(m.self_id(), blob);
+    }
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+
+    let vm = MoveVM::new(move_stdlib::natives::all_natives(AccountAddress::ONE)).unwrap();
+    let withdraw = |amount: u64| {
+        vm.new_session(&storage)
+            .execute_function_bypass_visibility(
+                &module_id,
+                &Identifier::new("withdraw").unwrap(),
+                vec![],
+                serialize_values(&vec![MoveValue::U64(10), MoveValue::U64(amount)]),
+                &mut GasStatus::new_unmetered(),
+            )
+            .map(|_| ())
+    };
+
+    withdraw(5).unwrap();
+    let err = withdraw(15).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::ABORTED);
+    assert_eq!(err.sub_status(), Some(7));
+    assert_eq!(err.abort_payload(), Some(&b"not enough coins"[..]));
+    // as for other natives, the abort is located in the module of the native
+    assert_eq!(
+        err.location(),
+        &Location::Module(ModuleId::new(
+            AccountAddress::ONE,
+            Identifier::new("errors").unwrap()
+        ))
+    );
+}
@@ -0,0 +1,208 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use futures::{
+    executor::block_on,
+    future::{self, BoxFuture},
+};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, ResourceResolver},
+    value::{serialize_values, MoveStruct, MoveValue},
+};
+use move_vm_runtime::{
+    async_resolver::{AsyncMoveResolver, AsyncResolverAdapter, AsyncResolverError},
+    move_vm::MoveVM,
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::sync::Mutex;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+// Storage answering from memory, and recording the batches of keys requested
+struct BatchingStorage {
+    storage: InMemoryStorage,
+    module_batches: Mutex<Vec<Vec<ModuleId>>>,
+    resource_batches: Mutex<Vec<Vec<(AccountAddress, StructTag)>>>,
+}
+
+impl AsyncMoveResolver for BatchingStorage {
+    type Err = ();
+
+    fn get_modules(
+        &self,
+        ids: Vec<ModuleId>,
+    ) -> BoxFuture<'_, Result<Vec<Option<Vec<u8>>>, Self::Err>> {
+        let blobs = ids.iter().map(|id| self.storage.get_module(id)).collect();
+        self.module_batches.lock().unwrap().push(ids);
+        Box::pin(future::ready(blobs))
+    }
+
+    fn get_resources(
+        &self,
+        keys: Vec<(AccountAddress, StructTag)>,
+    ) -> BoxFuture<'_, Result<Vec<Option<Vec<u8>>>, Self::Err>> {
+        let blobs = keys
+            .iter()
+            .map(|(address, tag)| self.storage.get_resource(address, tag))
+            .collect();
+        self.resource_batches.lock().unwrap().push(keys);
+        Box::pin(future::ready(blobs))
+    }
+}
+
+fn struct_tag(name: &str) -> StructTag {
+    StructTag {
+        address: TEST_ADDR,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    }
+}
+
+#[test]
+fn async_resolver() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            struct R has key {{ v: u64 }}
+            struct S has key {{ v: u64 }}
+
+            public fun get_r(a: address): u64 acquires R {{ borrow_global<R>(a).v }}
+            public fun get_s(a: address): u64 acquires S {{ borrow_global<S>(a).v }}
+        }}
+
+        module 0x{0}::N {{
+            public fun get_r(a: address): u64 {{ 0x{0}::M::get_r(a) }}
+            public fun get_s(a: address): u64 {{ 0x{0}::M::get_s(a) }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let n = as_module(units.pop().unwrap());
+    let m = as_module(units.pop().unwrap());
+
+    let mut storage = BatchingStorage {
+        storage: InMemoryStorage::new(),
+        module_batches: Mutex::new(vec![]),
+        resource_batches: Mutex::new(vec![]),
+    };
+    for module in [&m, &n] {
+        let mut blob = vec![];
+        module.serialize(&mut blob).unwrap();
+        storage
+            .storage
+            .publish_or_overwrite_module(module.self_id(), blob);
+    }
+    for (name, v) in [("R", 1), ("S", 2)] {
+        let blob = MoveValue::Struct(MoveStruct::new(vec![MoveValue::U64(v)]))
+            .simple_serialize()
+            .unwrap();
+        storage
+            .storage
+            .publish_or_overwrite_resource(TEST_ADDR, struct_tag(name), blob);
+    }
+
+    let adapter = AsyncResolverAdapter::new(storage);
+    block_on(adapter.prefetch_modules_with_dependencies(vec![n.self_id()])).unwrap();
+    block_on(adapter.prefetch_resources(vec![(TEST_ADDR, struct_tag("R"))])).unwrap();
+    // one batch per level of dependencies
+    assert_eq!(
+        *adapter.resolver().module_batches.lock().unwrap(),
+        vec![vec![n.self_id()], vec![m.self_id()]]
+    );
+    assert_eq!(adapter.resolver().resource_batches.lock().unwrap().len(), 1);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    let call = |fun_name: &str| {
+        let mut sess = vm.new_session(&adapter);
+        let return_values = sess
+            .execute_function_bypass_visibility(
+                &n.self_id(),
+                &Identifier::new(fun_name).unwrap(),
+                vec![],
+                serialize_values(&vec![MoveValue::Address(TEST_ADDR)]),
+                &mut GasStatus::new_unmetered(),
+            )
+            .unwrap()
+            .return_values;
+        return_values[0].0.clone()
+    };
+
+    // everything read was prefetched
+    assert_eq!(call("get_r"), MoveValue::U64(1).simple_serialize().unwrap());
+    assert_eq!(adapter.resolver().module_batches.lock().unwrap().len(), 2);
+    assert_eq!(adapter.resolver().resource_batches.lock().unwrap().len(), 1);
+
+    // reads of data which was not prefetched block on storage
+    assert_eq!(call("get_s"), MoveValue::U64(2).simple_serialize().unwrap());
+    assert_eq!(adapter.resolver().module_batches.lock().unwrap().len(), 2);
+    assert_eq!(
+        adapter.resolver().resource_batches.lock().unwrap()[1],
+        vec![(TEST_ADDR, struct_tag("S"))]
+    );
+}
+
+// Storage which drops the last entry of each batch
+struct TruncatingStorage(InMemoryStorage);
+
+impl AsyncMoveResolver for TruncatingStorage {
+    type Err = ();
+
+    fn get_modules(
+        &self,
+        ids: Vec<ModuleId>,
+    ) -> BoxFuture<'_, Result<Vec<Option<Vec<u8>>>, Self::Err>> {
+        let mut blobs = ids
+            .iter()
+            .map(|id| self.0.get_module(id))
+            .collect::<Result<Vec<_>, _>>();
+        if let Ok(blobs) = &mut blobs {
+            blobs.pop();
+        }
+        Box::pin(future::ready(blobs))
+    }
+
+    fn get_resources(
+        &self,
+        keys: Vec<(AccountAddress, StructTag)>,
+    ) -> BoxFuture<'_, Result<Vec<Option<Vec<u8>>>, Self::Err>> {
+        let mut blobs = keys
+            .iter()
+            .map(|(address, tag)| self.0.get_resource(address, tag))
+            .collect::<Result<Vec<_>, _>>();
+        if let Ok(blobs) = &mut blobs {
+            blobs.pop();
+        }
+        Box::pin(future::ready(blobs))
+    }
+}
+
+#[test]
+fn async_resolver_batch_too_short() {
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_resource(TEST_ADDR, struct_tag("R"), vec![1]);
+    let adapter = AsyncResolverAdapter::new(TruncatingStorage(storage));
+
+    // the resource exists, it must not be read as absent
+    assert!(matches!(
+        adapter.get_resource(&TEST_ADDR, &struct_tag("R")),
+        Err(AsyncResolverError::BatchSize {
+            requested: 1,
+            returned: 0
+        })
+    ));
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    assert!(matches!(
+        adapter.get_module(&module_id),
+        Err(AsyncResolverError::BatchSize {
+            requested: 1,
+            returned: 0
+        })
+    ));
+}
@@ -0,0 +1,285 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress, effects::Op, identifier::Identifier,
+    language_storage::ModuleId, resolver::MoveResolver, value::MoveValue, vm_status::StatusCode,
+};
+use move_stdlib::natives::event::write_to_event_store;
+use move_vm_runtime::{move_vm::MoveVM, session::Session};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::sync::Arc;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn module_blob() -> (ModuleId, Vec<u8>) {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct R has key {{ v: u64 }}
+            struct E has drop, store {{ v: u64 }}
+
+            native fun emit<T: drop + store>(guid: vector<u8>, seq_num: u64, msg: T);
+
+            fun publish(s: signer, v: u64) {{
+                move_to(&s, R {{ v }});
+                emit(x"01", 0, E {{ v }});
+            }}
+
+            fun set(addr: address, v: u64) acquires R {{
+                borrow_global_mut<R>(addr).v = v;
+                emit(x"01", 1, E {{ v }});
+            }}
+
+            fun set_and_abort(addr: address, v: u64) acquires R {{
+                set(addr, v);
+                abort 1
+            }}
+
+            fun get(addr: address): u64 acquires R {{
+                borrow_global<R>(addr).v
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    (m.self_id(), blob)
+}
+
+fn new_vm() -> MoveVM {
+    MoveVM::new(vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("emit").unwrap(),
+        Arc::new(write_to_event_store) as _,
+    )])
+    .unwrap()
+}
+
+fn call<S: MoveResolver>(
+    sess: &mut Session<S>,
+    module_id: &ModuleId,
+    fun_name: &str,
+    args: Vec<MoveValue>,
+) -> Result<Option<MoveValue>, StatusCode> {
+    let args = args
+        .into_iter()
+        .map(|arg| arg.simple_serialize().unwrap())
+        .collect::<Vec<_>>();
+    let return_values = sess
+        .execute_function_bypass_visibility(
+            module_id,
+            &Identifier::new(fun_name).unwrap(),
+            vec![],
+            args,
+            &mut GasStatus::new_unmetered(),
+        )
+        .map_err(|err| err.major_status())?
+        .return_values;
+    Ok(return_values
+        .first()
+        .map(|(blob, layout)| MoveValue::simple_deserialize(blob, layout).unwrap()))
+}
+
+fn get<S: MoveResolver>(sess: &mut Session<S>, module_id: &ModuleId) -> Option<MoveValue> {
+    call(sess, module_id, "get", vec![MoveValue::Address(TEST_ADDR)]).unwrap()
+}
+
+#[test]
+fn rollback_resources_and_events() {
+    let (module_id, blob) = module_blob();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+    let vm = new_vm();
+    let mut sess = vm.new_session(&storage);
+
+    call(
+        &mut sess,
+        &module_id,
+        "publish",
+        vec![MoveValue::Signer(TEST_ADDR), MoveValue::U64(1)],
+    )
+    .unwrap();
+    let checkpoint = sess.checkpoint().unwrap();
+
+    // the changes of a call which aborted are kept until rolled back
+    let err = call(
+        &mut sess,
+        &module_id,
+        "set_and_abort",
+        vec![MoveValue::Address(TEST_ADDR), MoveValue::U64(2)],
+    )
+    .unwrap_err();
+    assert_eq!(err, StatusCode::ABORTED);
+    assert_eq!(get(&mut sess, &module_id), Some(MoveValue::U64(2)));
+    sess.rollback_to(checkpoint);
+    assert_eq!(get(&mut sess, &module_id), Some(MoveValue::U64(1)));
+
+    // the changes made after rolling back are kept
+    call(
+        &mut sess,
+        &module_id,
+        "set",
+        vec![MoveValue::Address(TEST_ADDR), MoveValue::U64(3)],
+    )
+    .unwrap();
+    let (changeset, events) = sess.finish().unwrap();
+
+    let resources = changeset.resources().collect::<Vec<_>>();
+    assert_eq!(resources.len(), 1);
+    assert_eq!(
+        resources[0].2,
+        Op::New(&MoveValue::U64(3).simple_serialize().unwrap()[..])
+    );
+    // the event emitted by the aborted call is dropped
+    assert_eq!(
+        events
+            .iter()
+            .map(|(_, seq_num, _, _)| *seq_num)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+}
+
+#[test]
+fn rollback_module_publishing() {
+    let code = format!(
+        r#"
+        module 0x{}::N {{
+            public fun one(): u64 {{ 1 }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let storage = InMemoryStorage::new();
+    let vm = new_vm();
+    let mut sess = vm.new_session(&storage);
+    let checkpoint = sess.checkpoint().unwrap();
+    sess.publish_module(blob, TEST_ADDR, &mut GasStatus::new_unmetered())
+        .unwrap();
+    sess.rollback_to(checkpoint);
+    let (changeset, _) = sess.finish().unwrap();
+    assert!(changeset.modules().next().is_none());
+}
+
+#[cfg(feature = "table-extension")]
+#[test]
+fn rollback_tables() {
+    use move_table_extension::{finish_session, table_natives, NativeTableContext};
+    use move_vm_runtime::native_extensions::NativeContextExtensions;
+
+    let code = format!(
+        r#"
+        module 0x2::Table {{
+            struct Table<phantom K: copy + drop, phantom V> has store {{ handle: u128 }}
+            struct Box<V> has key, drop, store {{ val: V }}
+
+            public fun new<K: copy + drop, V: store>(): Table<K, V> {{
+                Table {{ handle: new_table_handle() }}
+            }}
+
+            public fun add<K: copy + drop, V>(table: &mut Table<K, V>, key: K, val: V) {{
+                add_box<K, V, Box<V>>(table, key, Box {{ val }})
+            }}
+
+            public fun contains<K: copy + drop, V>(table: &Table<K, V>, key: K): bool {{
+                contains_box<K, V, Box<V>>(table, key)
+            }}
+
+            native fun new_table_handle(): u128;
+            native fun add_box<K: copy + drop, V, B>(table: &mut Table<K, V>, key: K, val: Box<V>);
+            native fun contains_box<K: copy + drop, V, B>(table: &Table<K, V>, key: K): bool;
+        }}
+
+        module 0x{}::T {{
+            use 0x2::Table::{{Self, Table}};
+
+            struct S has key {{ t: Table<u64, u64> }}
+
+            fun create(s: signer) {{
+                move_to(&s, S {{ t: Table::new() }})
+            }}
+
+            fun add(addr: address, k: u64) acquires S {{
+                Table::add(&mut borrow_global_mut<S>(addr).t, k, k)
+            }}
+
+            fun contains(addr: address, k: u64): bool acquires S {{
+                Table::contains(&borrow_global<S>(addr).t, k)
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut storage = InMemoryStorage::new();
+    for unit in compile_units(&code).unwrap() {
+        let m = as_module(unit);
+        let mut blob = vec![];
+        m.serialize(&mut blob).unwrap();
+        storage.publish_or_overwrite_module(m.self_id(), blob);
+    }
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("T").unwrap());
+
+    let vm = MoveVM::new(table_natives(
+        AccountAddress::from_hex_literal("0x2").unwrap(),
+    ))
+    .unwrap();
+    let mut extensions = NativeContextExtensions::default();
+    extensions.add_session_extension(NativeTableContext::new(0, &storage));
+    let mut sess = vm.new_session_with_extensions(&storage, extensions);
+    let contains = |sess: &mut Session<_>, k: u64| {
+        call(
+            sess,
+            &module_id,
+            "contains",
+            vec![MoveValue::Address(TEST_ADDR), MoveValue::U64(k)],
+        )
+        .unwrap()
+    };
+
+    call(
+        &mut sess,
+        &module_id,
+        "create",
+        vec![MoveValue::Signer(TEST_ADDR)],
+    )
+    .unwrap();
+    call(
+        &mut sess,
+        &module_id,
+        "add",
+        vec![MoveValue::Address(TEST_ADDR), MoveValue::U64(1)],
+    )
+    .unwrap();
+    let checkpoint = sess.checkpoint().unwrap();
+    call(
+        &mut sess,
+        &module_id,
+        "add",
+        vec![MoveValue::Address(TEST_ADDR), MoveValue::U64(2)],
+    )
+    .unwrap();
+    assert_eq!(contains(&mut sess, 2), Some(MoveValue::Bool(true)));
+
+    // the entries added to tables since the checkpoint are rolled back with the resources
+    sess.rollback_to(checkpoint);
+    assert_eq!(contains(&mut sess, 1), Some(MoveValue::Bool(true)));
+    assert_eq!(contains(&mut sess, 2), Some(MoveValue::Bool(false)));
+
+    let (_, _, table_change_set) = finish_session(sess).unwrap();
+    assert_eq!(table_change_set.new_tables.len(), 1);
+    let changes = table_change_set.changes.values().collect::<Vec<_>>();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].entries.len(), 1);
+}
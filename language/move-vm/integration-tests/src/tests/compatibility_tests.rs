@@ -0,0 +1,87 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::{compatibility::CompatibilityPolicy, errors::VMResult};
+use move_core_types::{account_address::AccountAddress, vm_status::StatusCode};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+const ORIGINAL: &str = r#"
+    struct S { x: u64 }
+    public fun f() {}
+"#;
+
+// only the layout of `S` changes
+const LAYOUT_CHANGE: &str = r#"
+    struct S { x: u64, y: u64 }
+    public fun f() {}
+"#;
+
+// only the public function `f` is removed
+const LINKING_CHANGE: &str = r#"
+    struct S { x: u64 }
+"#;
+
+fn compile(body: &str) -> Vec<u8> {
+    let code = format!("module 0x{}::M {{ {} }}", TEST_ADDR, body);
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    blob
+}
+
+// Publishes the original module, then upgrades it with the given policy
+fn upgrade(new_body: &str, policy: CompatibilityPolicy) -> VMResult<()> {
+    let mut storage = InMemoryStorage::new();
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut gas_status = GasStatus::new_unmetered();
+
+    let mut sess = vm.new_session(&storage);
+    sess.publish_module(compile(ORIGINAL), TEST_ADDR, &mut gas_status)
+        .unwrap();
+    let (changeset, _) = sess.finish().unwrap();
+    storage.apply(changeset).unwrap();
+
+    let mut sess = vm.new_session(&storage);
+    sess.publish_module_bundle_with_compat_policy(
+        vec![compile(new_body)],
+        TEST_ADDR,
+        &mut gas_status,
+        policy,
+    )
+}
+
+fn expect_incompatible(result: VMResult<()>) {
+    assert_eq!(
+        result.unwrap_err().major_status(),
+        StatusCode::BACKWARD_INCOMPATIBLE_MODULE_UPDATE
+    );
+}
+
+#[test]
+fn full_compatibility() {
+    upgrade(ORIGINAL, CompatibilityPolicy::Full).unwrap();
+    expect_incompatible(upgrade(LAYOUT_CHANGE, CompatibilityPolicy::Full));
+    expect_incompatible(upgrade(LINKING_CHANGE, CompatibilityPolicy::Full));
+}
+
+#[test]
+fn no_struct_layout_changes() {
+    upgrade(ORIGINAL, CompatibilityPolicy::NoStructLayoutChanges).unwrap();
+    expect_incompatible(upgrade(
+        LAYOUT_CHANGE,
+        CompatibilityPolicy::NoStructLayoutChanges,
+    ));
+    upgrade(LINKING_CHANGE, CompatibilityPolicy::NoStructLayoutChanges).unwrap();
+}
+
+#[test]
+fn arbitrary_upgrades() {
+    upgrade(LAYOUT_CHANGE, CompatibilityPolicy::Arbitrary).unwrap();
+    upgrade(LINKING_CHANGE, CompatibilityPolicy::Arbitrary).unwrap();
+}
@@ -0,0 +1,132 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, language_storage::ModuleId,
+    value::MoveValue,
+};
+use move_vm_runtime::{
+    debugger::{Breakpoint, Debugger, DebuggerFrontend, ResumeCommand, StopReason, StoppedState},
+    move_vm::MoveVM,
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::collections::{BTreeSet, VecDeque};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+/// Resumes with scripted commands, recording where execution stopped.
+struct ScriptedFrontend {
+    commands: VecDeque<ResumeCommand>,
+    // reason, function, depth and offset of each stop
+    stops: Vec<(StopReason, String, usize, u16)>,
+}
+
+impl DebuggerFrontend for ScriptedFrontend {
+    fn on_stop(
+        &mut self,
+        state: &StoppedState,
+        _breakpoints: &mut BTreeSet<Breakpoint>,
+    ) -> ResumeCommand {
+        self.stops.push((
+            state.reason,
+            state.frame.function_name.to_string(),
+            state.frame.depth,
+            state.pc,
+        ));
+        self.commands.pop_front().unwrap_or(ResumeCommand::Continue)
+    }
+}
+
+fn debug(
+    stop_on_entry: bool,
+    breakpoints: Vec<Breakpoint>,
+    commands: Vec<ResumeCommand>,
+) -> Vec<(StopReason, String, usize, u16)> {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            fun add_one(x: u64): u64 {{
+                x + 1
+            }}
+
+            fun foo(x: u64): u64 {{
+                let y = add_one(x);
+                y + 1
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    let frontend = ScriptedFrontend {
+        commands: commands.into(),
+        stops: vec![],
+    };
+    let mut debugger = Debugger::new(frontend, stop_on_entry);
+    for breakpoint in breakpoints {
+        debugger.add_breakpoint(breakpoint);
+    }
+    {
+        let mut sess = vm.new_session(&storage);
+        sess.set_tracer(Box::new(&mut debugger));
+        sess.execute_function_bypass_visibility(
+            &module_id,
+            &Identifier::new("foo").unwrap(),
+            vec![],
+            vec![MoveValue::U64(41).simple_serialize().unwrap()],
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap();
+    }
+    debugger.into_frontend().stops
+}
+
+#[test]
+fn function_breakpoint() {
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    let stops = debug(
+        false,
+        vec![Breakpoint::function(Some(module_id), "add_one")],
+        vec![],
+    );
+    assert_eq!(
+        stops,
+        vec![(StopReason::Breakpoint, "add_one".to_string(), 1, 0)]
+    );
+}
+
+#[test]
+fn stepping() {
+    use ResumeCommand::*;
+
+    // step to the call in `foo`, into `add_one`, then out of it
+    let stops = debug(true, vec![], vec![StepIn, StepIn, StepOut]);
+    let call_pc = stops[1].3;
+    assert_eq!(
+        stops,
+        vec![
+            (StopReason::Step, "foo".to_string(), 0, 0),
+            (StopReason::Step, "foo".to_string(), 0, call_pc),
+            (StopReason::Step, "add_one".to_string(), 1, 0),
+            // back in `foo`, right after the call
+            (StopReason::Step, "foo".to_string(), 0, call_pc + 1),
+        ]
+    );
+
+    // stepping over the call never stops in `add_one`
+    let stops = debug(true, vec![], vec![StepOver; 10]);
+    assert!(stops
+        .iter()
+        .all(|(_, function, depth, _)| function == "foo" && *depth == 0));
+}
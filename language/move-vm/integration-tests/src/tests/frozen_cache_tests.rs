@@ -0,0 +1,127 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::ModuleId,
+    value::{serialize_values, MoveValue},
+    vm_status::StatusCode,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::thread;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn call_double(vm: &MoveVM, storage: &InMemoryStorage, module_id: &ModuleId) -> StatusCode {
+    call(vm, storage, module_id, "double")
+}
+
+fn call(vm: &MoveVM, storage: &InMemoryStorage, module_id: &ModuleId, name: &str) -> StatusCode {
+    let mut sess = vm.new_session(storage);
+    match sess.execute_function_bypass_visibility(
+        module_id,
+        &Identifier::new(name).unwrap(),
+        vec![],
+        serialize_values(&vec![MoveValue::U64(21)]),
+        &mut GasStatus::new_unmetered(),
+    ) {
+        Ok(_) => StatusCode::EXECUTED,
+        Err(err) => err.major_status(),
+    }
+}
+
+#[test]
+fn share_frozen_cache() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            public fun double(x: u64): u64 {{ x + x }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let module_id = m.self_id();
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    vm.load_module(&module_id, &storage).unwrap();
+    let cache = vm.freeze_loader_cache();
+
+    // VMs created from the frozen cache do not need to load the module from storage
+    let empty_storage = InMemoryStorage::new();
+    assert_eq!(
+        call_double(&MoveVM::new(vec![]).unwrap(), &empty_storage, &module_id),
+        StatusCode::LINKER_ERROR
+    );
+    let handles = (0..4)
+        .map(|_| {
+            let cache = cache.clone();
+            let module_id = module_id.clone();
+            thread::spawn(move || {
+                let vm = MoveVM::new_from_frozen_cache(&cache);
+                call_double(&vm, &InMemoryStorage::new(), &module_id)
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), StatusCode::EXECUTED);
+    }
+}
+
+#[test]
+fn load_on_top_of_frozen_cache() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            struct S has drop {{ x: u64 }}
+            public fun make(x: u64): S {{ S {{ x }} }}
+            public fun get(s: S): u64 {{ let S {{ x }} = s; x }}
+        }}
+        module 0x{0}::N {{
+            use 0x{0}::M;
+            struct T has drop {{ s: M::S }}
+            public fun quadruple(x: u64): u64 {{
+                let t = T {{ s: M::make(x + x) }};
+                let T {{ s }} = t;
+                let y = M::get(s);
+                y + y
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let units = compile_units(&code).unwrap();
+    let mut storage = InMemoryStorage::new();
+    let mut ids = vec![];
+    for unit in units {
+        let m = as_module(unit);
+        let mut blob = vec![];
+        m.serialize(&mut blob).unwrap();
+        ids.push(m.self_id());
+        storage.publish_or_overwrite_module(m.self_id(), blob);
+    }
+    let (m_id, n_id) = (&ids[0], &ids[1]);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    vm.load_module(m_id, &storage).unwrap();
+    let cache = vm.freeze_loader_cache();
+
+    // N is loaded into the code cache of the new VM, on top of M in the frozen cache
+    let vm = MoveVM::new_from_frozen_cache(&cache);
+    assert_eq!(call(&vm, &storage, n_id, "quadruple"), StatusCode::EXECUTED);
+    // the frozen cache does not get N
+    let vm = MoveVM::new_from_frozen_cache(&cache);
+    assert_eq!(
+        call(&vm, &InMemoryStorage::new(), n_id, "quadruple"),
+        StatusCode::LINKER_ERROR
+    );
+}
@@ -0,0 +1,105 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    value::{MoveStruct, MoveValue},
+    vm_status::StatusCode,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn run(function_path: &str, ty_args: &[&str], args: Vec<MoveValue>) -> VMResult<Vec<u8>> {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct Foo has drop {{ x: u64 }}
+
+            fun add<T: drop>(_t: T, x: u64, y: &u64): u64 {{ x + *y }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut sess = vm.new_session(&storage);
+    let return_values = sess
+        .execute_function_by_path(
+            function_path,
+            ty_args,
+            args,
+            &mut GasStatus::new_unmetered(),
+        )?
+        .return_values;
+    Ok(return_values[0].0.clone())
+}
+
+fn foo(x: u64) -> MoveValue {
+    MoveValue::Struct(MoveStruct::new(vec![MoveValue::U64(x)]))
+}
+
+#[test]
+fn call_by_path() {
+    let path = format!("0x{}::M::add", TEST_ADDR);
+    let foo_type = format!("0x{}::M::Foo", TEST_ADDR);
+    let args = vec![foo(0), MoveValue::U64(1), MoveValue::U64(2)];
+    assert_eq!(
+        run(&path, &[&foo_type], args).unwrap(),
+        MoveValue::U64(3).simple_serialize().unwrap()
+    );
+    let args = vec![MoveValue::Bool(true), MoveValue::U64(1), MoveValue::U64(2)];
+    assert_eq!(
+        run(&path, &["bool"], args).unwrap(),
+        MoveValue::U64(3).simple_serialize().unwrap()
+    );
+}
+
+#[test]
+fn invalid_path_or_type_args() {
+    let args = || vec![foo(0), MoveValue::U64(1), MoveValue::U64(2)];
+    let foo_type = format!("0x{}::M::Foo", TEST_ADDR);
+    for path in ["M::add", "0x1::M::add::x", "0xZ::M::add"] {
+        assert_eq!(
+            run(path, &[&foo_type], args()).unwrap_err().major_status(),
+            StatusCode::FUNCTION_RESOLUTION_FAILURE
+        );
+    }
+    let path = format!("0x{}::M::add", TEST_ADDR);
+    assert_eq!(
+        run(&path, &["vector<"], args()).unwrap_err().major_status(),
+        StatusCode::TYPE_RESOLUTION_FAILURE
+    );
+}
+
+#[test]
+fn mismatched_args() {
+    let path = format!("0x{}::M::add", TEST_ADDR);
+    let foo_type = format!("0x{}::M::Foo", TEST_ADDR);
+    assert_eq!(
+        run(&path, &[&foo_type], vec![foo(0), MoveValue::U64(1)])
+            .unwrap_err()
+            .major_status(),
+        StatusCode::NUMBER_OF_ARGUMENTS_MISMATCH
+    );
+    for args in [
+        vec![MoveValue::U64(0), MoveValue::U64(1), MoveValue::U64(2)],
+        vec![foo(0), MoveValue::U8(1), MoveValue::U64(2)],
+        vec![foo(0), MoveValue::U64(1), MoveValue::Bool(true)],
+    ] {
+        assert_eq!(
+            run(&path, &[&foo_type], args).unwrap_err().major_status(),
+            StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT
+        );
+    }
+}
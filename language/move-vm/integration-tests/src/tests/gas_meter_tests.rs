@@ -0,0 +1,544 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::{
+    errors::{PartialVMError, PartialVMResult},
+    file_format::NUMBER_OF_NATIVE_FUNCTIONS,
+    file_format_common::Opcodes,
+};
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{
+        AbstractMemorySize, CostTable, GasAlgebra, GasCarrier, GasConstants, GasCost, GasUnits,
+        InternalGasUnits, NativeGasParameters, VersionedCostTables,
+    },
+    identifier::Identifier,
+    language_storage::ModuleId,
+    value::MoveValue,
+    vm_status::StatusCode,
+};
+use move_stdlib::natives::event::write_to_event_store;
+use move_vm_runtime::{
+    move_vm::MoveVM,
+    native_functions::{make_native, NativeContext, NativeFunction},
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{
+    gas_schedule::{zero_cost_schedule, GasMeter, GasStatus, NativeCostIndex},
+    loaded_data::runtime_types::Type,
+    natives::function::NativeResult,
+    pop_arg,
+    values::Value,
+};
+use smallvec::smallvec;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+/// Records what the VM charges for.
+struct RecordingGasMeter {
+    // instructions charged for, as opcodes
+    instructions: Vec<u8>,
+    // dependencies charged for, with their size
+    dependencies: Vec<(ModuleId, u64)>,
+    written_modules: Vec<ModuleId>,
+    // size of the events charged for
+    events: Vec<u64>,
+}
+
+impl RecordingGasMeter {
+    fn new() -> Self {
+        Self {
+            instructions: vec![],
+            dependencies: vec![],
+            written_modules: vec![],
+            events: vec![],
+        }
+    }
+}
+
+impl GasMeter for RecordingGasMeter {
+    fn charge_instr(&mut self, opcode: Opcodes) -> PartialVMResult<()> {
+        self.instructions.push(opcode as u8);
+        Ok(())
+    }
+
+    fn charge_instr_with_size(
+        &mut self,
+        opcode: Opcodes,
+        _size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        self.instructions.push(opcode as u8);
+        Ok(())
+    }
+
+    fn charge_native_function(
+        &mut self,
+        _amount: InternalGasUnits<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        Ok(())
+    }
+
+    fn charge_dependency(
+        &mut self,
+        module_id: &ModuleId,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        self.dependencies.push((module_id.clone(), size.get()));
+        Ok(())
+    }
+
+    fn charge_resource_group_load(
+        &mut self,
+        _size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        Ok(())
+    }
+
+    fn charge_emit_event(&mut self, size: AbstractMemorySize<GasCarrier>) -> PartialVMResult<()> {
+        self.events.push(size.get());
+        Ok(())
+    }
+
+    fn charge_write_module(
+        &mut self,
+        module_id: &ModuleId,
+        _size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        self.written_modules.push(module_id.clone());
+        Ok(())
+    }
+
+    fn native_cost(&self, _native_table_idx: u8, _size: usize) -> InternalGasUnits<GasCarrier> {
+        InternalGasUnits::new(0)
+    }
+
+    fn native_parameter(&self, _name: &str) -> Option<InternalGasUnits<GasCarrier>> {
+        None
+    }
+
+    fn gas_left(&self) -> InternalGasUnits<GasCarrier> {
+        InternalGasUnits::new(0)
+    }
+}
+
+#[test]
+fn custom_gas_meter() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            public fun one(): u64 {{ 1 }}
+        }}
+
+        module 0x{0}::N {{
+            fun two(): u64 {{ 0x{0}::M::one() + 1 }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let blobs: Vec<Vec<u8>> = compile_units(&code)
+        .unwrap()
+        .into_iter()
+        .map(|unit| {
+            let mut blob = vec![];
+            as_module(unit).serialize(&mut blob).unwrap();
+            blob
+        })
+        .collect();
+    let m = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    let n = ModuleId::new(TEST_ADDR, Identifier::new("N").unwrap());
+
+    let mut storage = InMemoryStorage::new();
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut gas_meter = RecordingGasMeter::new();
+
+    let mut sess = vm.new_session(&storage);
+    sess.publish_module_bundle(blobs.clone(), TEST_ADDR, &mut gas_meter)
+        .unwrap();
+    let (changeset, _) = sess.finish().unwrap();
+    storage.apply(changeset).unwrap();
+    assert_eq!(gas_meter.written_modules, vec![m.clone(), n.clone()]);
+
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &n,
+        &Identifier::new("two").unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut gas_meter,
+    )
+    .unwrap();
+    assert_eq!(
+        gas_meter.dependencies,
+        vec![(m, blobs[0].len() as u64), (n, blobs[1].len() as u64)]
+    );
+    assert!(gas_meter.instructions.contains(&(Opcodes::CALL as u8)));
+    assert!(gas_meter.instructions.contains(&(Opcodes::ADD as u8)));
+}
+
+#[test]
+fn charge_dependencies_once_per_session() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            public fun one(): u64 {{ 1 }}
+        }}
+
+        module 0x{0}::N {{
+            fun two(): u64 {{ 0x{0}::M::one() + 1 }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut storage = InMemoryStorage::new();
+    for unit in compile_units(&code).unwrap() {
+        let module = as_module(unit);
+        let mut blob = vec![];
+        module.serialize(&mut blob).unwrap();
+        storage.publish_or_overwrite_module(module.self_id(), blob);
+    }
+    let n = ModuleId::new(TEST_ADDR, Identifier::new("N").unwrap());
+    let two = Identifier::new("two").unwrap();
+
+    // calls `N::two` the given number of times in a new session, returning the dependencies charged
+    let call_two = |vm: &MoveVM, times: usize| {
+        let mut gas_meter = RecordingGasMeter::new();
+        let mut sess = vm.new_session(&storage);
+        for _ in 0..times {
+            sess.execute_function_bypass_visibility(
+                &n,
+                &two,
+                vec![],
+                Vec::<Vec<u8>>::new(),
+                &mut gas_meter,
+            )
+            .unwrap();
+        }
+        gas_meter.dependencies
+    };
+
+    // each module is charged once per session
+    let vm = MoveVM::new(vec![]).unwrap();
+    let charged = call_two(&vm, 2);
+    assert_eq!(charged.len(), 2);
+    // the modules are in the code cache now, but they are charged all the same
+    assert_eq!(call_two(&vm, 1), charged);
+}
+
+#[test]
+fn charge_dependencies_with_cost_table() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            fun one(): u64 {{ 1 }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    let call_one = |vm: &MoveVM, cost_table: &CostTable| {
+        let mut sess = vm.new_session(&storage);
+        sess.execute_function_bypass_visibility(
+            &m.self_id(),
+            &Identifier::new("one").unwrap(),
+            vec![],
+            Vec::<Vec<u8>>::new(),
+            &mut GasStatus::new(cost_table, GasUnits::new(0)),
+        )
+        .map(|_| ())
+    };
+
+    // instructions are free, but the module the code depends on is not, even once it is cached
+    let mut cost_table = zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS);
+    cost_table.gas_constants.dependency_per_byte_cost = InternalGasUnits::new(1);
+    let vm = MoveVM::new(vec![]).unwrap();
+    for _ in 0..2 {
+        let err = call_one(&vm, &cost_table).unwrap_err();
+        assert_eq!(err.major_status(), StatusCode::OUT_OF_GAS);
+    }
+
+    // dependencies are free with the zero cost schedule
+    call_one(&vm, &zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS)).unwrap();
+}
+
+#[test]
+fn charge_events_by_size() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct E has drop, store {{ data: vector<u8> }}
+
+            native fun emit<T: drop + store>(guid: vector<u8>, seq_num: u64, msg: T);
+
+            fun emit_two(data: vector<u8>) {{
+                emit(x"01", 0, E {{ data: copy data }});
+                emit(x"01", 1, E {{ data }});
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    let vm = MoveVM::new(vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("emit").unwrap(),
+        Arc::new(write_to_event_store) as _,
+    )])
+    .unwrap();
+
+    let mut gas_meter = RecordingGasMeter::new();
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &m.self_id(),
+        &Identifier::new("emit_two").unwrap(),
+        vec![],
+        vec![MoveValue::vector_u8(vec![0; 100])
+            .simple_serialize()
+            .unwrap()],
+        &mut gas_meter,
+    )
+    .unwrap();
+    let (_, events) = sess.finish().unwrap();
+
+    // each event is charged for its size once serialized
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        gas_meter.events,
+        events
+            .iter()
+            .map(|(_, _, _, blob)| blob.len() as u64)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(gas_meter.events[0], 101);
+
+    // the native charges a flat cost per event, so the size of the events is only charged once
+    let gas_used = |size: usize| {
+        let mut cost_table = zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS);
+        cost_table.native_table[NativeCostIndex::EMIT_EVENT as usize] = GasCost::new(52, 1);
+        let mut gas_status = GasStatus::new(&cost_table, GasUnits::new(1_000));
+        let gas_left = gas_status.gas_left();
+        let mut sess = vm.new_session(&storage);
+        sess.execute_function_bypass_visibility(
+            &m.self_id(),
+            &Identifier::new("emit_two").unwrap(),
+            vec![],
+            vec![MoveValue::vector_u8(vec![0; size])
+                .simple_serialize()
+                .unwrap()],
+            &mut gas_status,
+        )
+        .unwrap();
+        gas_left.sub(gas_status.gas_left()).get()
+    };
+    let per_byte = GasConstants::default()
+        .global_memory_per_byte_write_cost
+        .get();
+    assert_eq!(gas_used(100) - gas_used(0), 2 * 100 * per_byte);
+}
+
+#[test]
+fn versioned_cost_tables() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            fun add(a: u64, b: u64): u64 {{ a + b }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    let vm = MoveVM::new(vec![]).unwrap();
+
+    // version 2 charges for instructions, unlike version 1
+    let mut cost_tables = VersionedCostTables::new();
+    let free = zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS);
+    let mut priced = free.clone();
+    for cost in priced.instruction_table.iter_mut() {
+        *cost = GasCost::new(1000, 0);
+    }
+    cost_tables.insert(1, free);
+    cost_tables.insert(2, priced);
+    // the tables can be stored, e.g., as on-chain configuration
+    let cost_tables = VersionedCostTables::from_bytes(&cost_tables.to_bytes().unwrap()).unwrap();
+    assert_eq!(cost_tables.latest().unwrap().0, 2);
+
+    let gas_used = |version: u64| {
+        let mut gas_status =
+            GasStatus::new_versioned(&cost_tables, version, GasUnits::new(100)).unwrap();
+        let mut sess = vm.new_session(&storage);
+        sess.execute_function_bypass_visibility(
+            &m.self_id(),
+            &Identifier::new("add").unwrap(),
+            vec![],
+            vec![
+                MoveValue::U64(1).simple_serialize().unwrap(),
+                MoveValue::U64(2).simple_serialize().unwrap(),
+            ],
+            &mut gas_status,
+        )
+        .unwrap();
+        100 - gas_status.remaining_gas().get()
+    };
+    assert!(gas_used(1) < gas_used(2));
+
+    let err = GasStatus::new_versioned(&cost_tables, 3, GasUnits::new(100))
+        .err()
+        .unwrap();
+    assert_eq!(err.major_status(), StatusCode::UNKNOWN_GAS_SCHEDULE_VERSION);
+}
+
+fn native_charge_per_byte(
+    context: &mut NativeContext,
+    _ty_args: Vec<Type>,
+    mut args: VecDeque<Value>,
+) -> PartialVMResult<NativeResult> {
+    let bytes = pop_arg!(args, Vec<u8>);
+    let per_byte = context
+        .native_gas_parameter("test.charge.per_byte")
+        .unwrap_or_else(|| InternalGasUnits::new(0));
+    let cost = per_byte.mul(AbstractMemorySize::new(bytes.len() as GasCarrier));
+    Ok(NativeResult::ok(cost, smallvec![]))
+}
+
+#[test]
+fn native_gas_parameters() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            native fun charge(v: vector<u8>);
+
+            fun charge_five() {{ charge(x"0102030405") }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    let natives = vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("charge").unwrap(),
+        make_native(native_charge_per_byte),
+    )];
+    let vm = MoveVM::new(natives).unwrap();
+
+    let cost_table = zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS);
+    let mut native_parameters = NativeGasParameters::new();
+    native_parameters.register("test.charge.per_byte", InternalGasUnits::new(10));
+
+    let mut gas_status =
+        GasStatus::new(&cost_table, GasUnits::new(1)).with_native_parameters(&native_parameters);
+    let gas_left = gas_status.gas_left().get();
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &m.self_id(),
+        &Identifier::new("charge_five").unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut gas_status,
+    )
+    .unwrap();
+    // instructions are free, only the native charges
+    assert_eq!(gas_left - gas_status.gas_left().get(), 50);
+}
+
+#[test]
+fn native_gas_balance() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            native fun iterate(n: u64);
+
+            fun iterate_many(n: u64) {{ iterate(n) }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    // iterates `n` times at a cost of 10 per iteration, stopping once out of gas
+    let iterations = Arc::new(AtomicU64::new(0));
+    let native_iterations = iterations.clone();
+    let natives = vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("iterate").unwrap(),
+        Arc::new(
+            move |context: &mut NativeContext, _ty_args: Vec<Type>, mut args: VecDeque<Value>| {
+                let n = pop_arg!(args, u64);
+                let mut cost = InternalGasUnits::new(0);
+                for _ in 0..n {
+                    native_iterations.fetch_add(1, Ordering::Relaxed);
+                    cost = cost.add(InternalGasUnits::new(10));
+                    if matches!(context.gas_balance(), Some(balance) if cost.get() > balance.get())
+                    {
+                        return Err(PartialVMError::new(StatusCode::OUT_OF_GAS));
+                    }
+                }
+                Ok(NativeResult::ok(cost, smallvec![]))
+            },
+        ) as NativeFunction,
+    )];
+    let vm = MoveVM::new(natives).unwrap();
+    let run = |gas_status: &mut GasStatus, n: u64| {
+        iterations.store(0, Ordering::Relaxed);
+        let mut sess = vm.new_session(&storage);
+        sess.execute_function_bypass_visibility(
+            &m.self_id(),
+            &Identifier::new("iterate_many").unwrap(),
+            vec![],
+            vec![MoveValue::U64(n).simple_serialize().unwrap()],
+            gas_status,
+        )
+        .map(|_| ())
+    };
+
+    let mut cost_table = zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS);
+    cost_table.gas_constants.global_memory_per_byte_cost = InternalGasUnits::new(0);
+    let mut gas_status = GasStatus::new(&cost_table, GasUnits::new(100));
+    let balance = gas_status.gas_left().get();
+    let err = run(&mut gas_status, u64::MAX).unwrap_err();
+    // the native stops as soon as the work it did costs more than the balance
+    assert_eq!(err.major_status(), StatusCode::OUT_OF_GAS);
+    assert_eq!(iterations.load(Ordering::Relaxed), balance / 10 + 1);
+
+    // there is no balance without metering
+    run(&mut GasStatus::new_unmetered(), 1000).unwrap();
+    assert_eq!(iterations.load(Ordering::Relaxed), 1000);
+}
@@ -0,0 +1,82 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, as_script, compile_units};
+use move_binary_format::file_format_common::Opcodes;
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, GasUnits},
+    value::{serialize_values, MoveValue},
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::{GasMeter, GasStatus, INITIAL_COST_SCHEDULE};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+#[test]
+fn gas_profile() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            public fun double(x: u64): u64 {{ x + x }}
+        }}
+
+        script {{
+            fun main(x: u64) {{ 0x{0}::M::double(x); }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let script = as_script(units.pop().unwrap());
+    let module = as_module(units.pop().unwrap());
+    let mut script_blob = vec![];
+    script.serialize(&mut script_blob).unwrap();
+    let mut module_blob = vec![];
+    module.serialize(&mut module_blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module.self_id(), module_blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    let cost_table = &*INITIAL_COST_SCHEDULE;
+    let run_script = |profile: bool| {
+        let mut gas_status = GasStatus::new(cost_table, GasUnits::new(1_000_000));
+        let gas_before = gas_status.gas_left();
+        let mut sess = vm.new_session(&storage);
+        if profile {
+            sess.enable_gas_profiling();
+        }
+        sess.execute_script(
+            script_blob.clone(),
+            vec![],
+            serialize_values(&vec![MoveValue::U64(1)]),
+            &mut gas_status,
+        )
+        .unwrap();
+        let consumed = gas_before.get() - gas_status.gas_left().get();
+        let (_, _, gas_profile) = sess.finish_with_gas_profile().unwrap();
+        (gas_profile, consumed)
+    };
+
+    let (gas_profile, _) = run_script(false);
+    assert!(gas_profile.is_none());
+
+    let (gas_profile, consumed) = run_script(true);
+    let gas_profile = gas_profile.unwrap();
+    assert_eq!(
+        gas_profile.functions.keys().cloned().collect::<Vec<_>>(),
+        vec![
+            format!("0x{}::M::double", TEST_ADDR),
+            "Script::main".to_string()
+        ]
+    );
+    assert!(gas_profile.functions.values().all(|gas| *gas > 0));
+    assert!(gas_profile.instructions[&Opcodes::ADD] > 0);
+    assert!(gas_profile.instructions[&Opcodes::CALL] > 0);
+    assert!(gas_profile.natives.is_empty());
+    // dependencies are charged for before execution, and so are not part of the profile
+    assert!(gas_profile.total() > 0);
+    assert!(gas_profile.total() <= consumed);
+}
@@ -2,12 +2,13 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::compiler::compile_modules_in_file;
+use crate::compiler::{as_module, compile_modules_in_file, compile_units};
 use move_binary_format::CompiledModule;
 use move_core_types::{
     account_address::AccountAddress,
     identifier::{IdentStr, Identifier},
     language_storage::ModuleId,
+    value::MoveValue,
 };
 use move_vm_runtime::move_vm::MoveVM;
 use move_vm_test_utils::InMemoryStorage;
@@ -156,3 +157,64 @@ fn load_concurrent_many() {
     // makes 150 threads
     adapter.call_functions_async(30);
 }
+
+// Compiles module `M`, whose `value` function returns the given value, and module `N` calling it
+fn get_upgradable_modules(value: u64) -> Vec<CompiledModule> {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            public fun value(): u64 {{ {1} }}
+        }}
+
+        module 0x{0}::N {{
+            public fun value(): u64 {{ 0x{0}::M::value() }}
+        }}
+    "#,
+        WORKING_ACCOUNT, value
+    );
+    compile_units(&code)
+        .unwrap()
+        .into_iter()
+        .map(as_module)
+        .collect()
+}
+
+fn call_value(vm: &MoveVM, store: &InMemoryStorage, module_name: &str) -> u64 {
+    let mut session = vm.new_session(store);
+    let return_values = session
+        .execute_function_bypass_visibility(
+            &ModuleId::new(WORKING_ACCOUNT, Identifier::new(module_name).unwrap()),
+            &Identifier::new("value").unwrap(),
+            vec![],
+            Vec::<Vec<u8>>::new(),
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap()
+        .return_values;
+    match MoveValue::simple_deserialize(&return_values[0].0, &return_values[0].1).unwrap() {
+        MoveValue::U64(value) => value,
+        value => panic!("unexpected return value {:?}", value),
+    }
+}
+
+#[test]
+fn load_after_upgrade() {
+    let mut adapter = Adapter::new(InMemoryStorage::new());
+    adapter.publish_modules(get_upgradable_modules(1));
+    assert_eq!(call_value(&adapter.vm, &adapter.store, "N"), 1);
+
+    // only `M` is upgraded, `N` must be reloaded as it depends on it
+    let upgraded = get_upgradable_modules(2)
+        .into_iter()
+        .filter(|module| module.self_id().name().as_str() == "M")
+        .collect();
+    adapter.publish_modules(upgraded);
+    adapter.vm.flush_loader_cache_if_invalidated();
+    assert_eq!(call_value(&adapter.vm, &adapter.store, "M"), 2);
+    assert_eq!(call_value(&adapter.vm, &adapter.store, "N"), 2);
+
+    // flushing the whole cache reloads everything from storage
+    adapter.vm.mark_loader_cache_as_invalid();
+    adapter.vm.flush_loader_cache_if_invalidated();
+    assert_eq!(call_value(&adapter.vm, &adapter.store, "N"), 2);
+}
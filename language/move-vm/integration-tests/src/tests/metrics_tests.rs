@@ -0,0 +1,122 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, as_script, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    value::{serialize_values, MoveValue},
+};
+use move_vm_runtime::{config::VMConfig, metrics::VMMetrics, move_vm::MoveVM};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+#[derive(Default)]
+struct CountingMetrics {
+    module_cache_hits: AtomicU64,
+    module_cache_misses: AtomicU64,
+    scripts_loaded: AtomicU64,
+    verifications: AtomicU64,
+    instructions_executed: AtomicU64,
+}
+
+impl VMMetrics for CountingMetrics {
+    fn inc_module_cache_hits(&self) {
+        self.module_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn inc_module_cache_misses(&self) {
+        self.module_cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn inc_scripts_loaded(&self) {
+        self.scripts_loaded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn observe_verification_time(&self, _duration: Duration) {
+        self.verifications.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn inc_instructions_executed(&self, count: u64) {
+        self.instructions_executed
+            .fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn metrics() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            public fun double(x: u64): u64 {{ x + x }}
+        }}
+
+        script {{
+            fun main(x: u64) {{ 0x{0}::M::double(x); }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let script = as_script(units.pop().unwrap());
+    let module = as_module(units.pop().unwrap());
+    let mut script_blob = vec![];
+    script.serialize(&mut script_blob).unwrap();
+    let mut module_blob = vec![];
+    module.serialize(&mut module_blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module.self_id(), module_blob);
+
+    let metrics = Arc::new(CountingMetrics::default());
+    let vm = MoveVM::new_with_metrics(vec![], VMConfig::default(), metrics.clone()).unwrap();
+    let run_script = || {
+        let mut sess = vm.new_session(&storage);
+        sess.execute_script(
+            script_blob.clone(),
+            vec![],
+            serialize_values(&vec![MoveValue::U64(1)]),
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap();
+    };
+
+    run_script();
+    assert_eq!(metrics.scripts_loaded.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.module_cache_misses.load(Ordering::Relaxed), 1);
+    // the script and the module
+    assert_eq!(metrics.verifications.load(Ordering::Relaxed), 2);
+    let instructions = metrics.instructions_executed.load(Ordering::Relaxed);
+    assert!(instructions > 0);
+
+    // everything is cached now
+    run_script();
+    assert_eq!(metrics.scripts_loaded.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.module_cache_misses.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.verifications.load(Ordering::Relaxed), 2);
+    assert_eq!(
+        metrics.instructions_executed.load(Ordering::Relaxed),
+        2 * instructions
+    );
+
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &module.self_id(),
+        &Identifier::new("double").unwrap(),
+        vec![],
+        serialize_values(&vec![MoveValue::U64(1)]),
+        &mut GasStatus::new_unmetered(),
+    )
+    .unwrap();
+    assert!(metrics.module_cache_hits.load(Ordering::Relaxed) > 0);
+    assert_eq!(metrics.module_cache_misses.load(Ordering::Relaxed), 1);
+}
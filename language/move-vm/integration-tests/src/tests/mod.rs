@@ -2,10 +2,33 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+mod abort_payload_tests;
+mod async_resolver_tests;
 mod bad_entry_point_tests;
 mod bad_storage_tests;
+mod checkpoint_tests;
+mod compatibility_tests;
+mod debugger_tests;
 mod exec_func_effects_tests;
+mod frozen_cache_tests;
 mod function_arg_tests;
+mod function_path_tests;
+mod gas_meter_tests;
+mod gas_profile_tests;
 mod loader_tests;
+mod metrics_tests;
+mod module_removal_tests;
 mod mutated_accounts_tests;
+mod native_context_tests;
+mod prefetch_tests;
+mod read_write_set_tests;
+mod replay_tests;
+mod resource_group_tests;
 mod return_value_tests;
+mod script_cache_tests;
+mod session_permissions_tests;
+mod source_location_tests;
+mod tracer_tests;
+mod type_cache_tests;
+mod vm_config_tests;
+mod watchdog_tests;
@@ -0,0 +1,143 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::{ChangeSet, Op},
+    identifier::Identifier,
+    language_storage::ModuleId,
+    vm_status::StatusCode,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn module_blob() -> (ModuleId, Vec<u8>) {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            public fun one(): u64 {{ 1 }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let module = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    module.serialize(&mut blob).unwrap();
+    (module.self_id(), blob)
+}
+
+fn module_op(changeset: &ChangeSet) -> Option<Op<&[u8]>> {
+    changeset.modules().next().map(|(_, _, op)| op)
+}
+
+#[test]
+fn unpublish_module() {
+    let (module_id, blob) = module_blob();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob.clone());
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut gas_status = GasStatus::new_unmetered();
+    let one = Identifier::new("one").unwrap();
+
+    // removal must be allowed by the adapter
+    let mut sess = vm.new_session(&storage);
+    let err = sess.unpublish_module(&module_id, TEST_ADDR).unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::OPERATION_DISALLOWED_IN_SESSION
+    );
+    sess.allow_module_removal();
+    sess.disallow_module_publishing();
+    let err = sess.unpublish_module(&module_id, TEST_ADDR).unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::OPERATION_DISALLOWED_IN_SESSION
+    );
+    let (changeset, _) = sess.finish().unwrap();
+    assert_eq!(module_op(&changeset), None);
+
+    // load the module into the code cache
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &module_id,
+        &one,
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut gas_status,
+    )
+    .unwrap();
+    sess.allow_module_removal();
+    let err = sess
+        .unpublish_module(&module_id, AccountAddress::ONE)
+        .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::MODULE_ADDRESS_DOES_NOT_MATCH_SENDER
+    );
+    sess.unpublish_module(&module_id, TEST_ADDR).unwrap();
+    let err = sess.unpublish_module(&module_id, TEST_ADDR).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::LINKER_ERROR);
+    let (changeset, _) = sess.finish().unwrap();
+    assert_eq!(module_op(&changeset), Some(Op::Delete));
+    storage.apply(changeset.clone()).unwrap();
+
+    // the module stays in the code cache until the adapter invalidates it
+    vm.flush_loader_cache_if_invalidated();
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &module_id,
+        &one,
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut gas_status,
+    )
+    .unwrap();
+    drop(sess);
+    vm.mark_removed_modules_as_invalid(&changeset);
+
+    // the removed module is flushed from the code cache
+    vm.flush_loader_cache_if_invalidated();
+    let mut sess = vm.new_session(&storage);
+    let err = sess
+        .execute_function_bypass_visibility(
+            &module_id,
+            &one,
+            vec![],
+            Vec::<Vec<u8>>::new(),
+            &mut gas_status,
+        )
+        .unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::LINKER_ERROR);
+}
+
+#[test]
+fn unpublish_and_republish_module() {
+    let (module_id, blob) = module_blob();
+    let mut storage = InMemoryStorage::new();
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut gas_status = GasStatus::new_unmetered();
+
+    // a module published and removed in the same session leaves no change
+    let mut sess = vm.new_session(&storage);
+    sess.allow_module_removal();
+    sess.publish_module(blob.clone(), TEST_ADDR, &mut gas_status)
+        .unwrap();
+    sess.unpublish_module(&module_id, TEST_ADDR).unwrap();
+    let (changeset, _) = sess.finish().unwrap();
+    assert_eq!(module_op(&changeset), None);
+
+    // a module removed and published again in the same session is replaced
+    storage.publish_or_overwrite_module(module_id.clone(), blob.clone());
+    let mut sess = vm.new_session(&storage);
+    sess.allow_module_removal();
+    sess.unpublish_module(&module_id, TEST_ADDR).unwrap();
+    sess.publish_module(blob.clone(), TEST_ADDR, &mut gas_status)
+        .unwrap();
+    let (changeset, _) = sess.finish().unwrap();
+    assert_eq!(module_op(&changeset), Some(Op::Modify(&blob[..])));
+}
@@ -0,0 +1,117 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, InternalGasUnits},
+    identifier::{IdentStr, Identifier},
+    language_storage::{ModuleId, StructTag, TypeTag},
+};
+use move_vm_runtime::{
+    move_vm::MoveVM,
+    native_functions::{NativeContext, NativeFunction},
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{
+    gas_schedule::GasStatus, loaded_data::runtime_types::Type, natives::function::NativeResult,
+    values::Value,
+};
+use smallvec::smallvec;
+use std::{collections::VecDeque, sync::Arc};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn setup() -> InMemoryStorage {
+    let code = format!(
+        r#"
+        module 0x{0}::A {{
+            struct S has drop {{ x: u64 }}
+            struct G<T> has drop {{ x: T }}
+        }}
+
+        module 0x{0}::M {{
+            native fun load_plain();
+            native fun load_generic();
+
+            public fun plain() {{ load_plain() }}
+            public fun generic() {{ load_generic() }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut storage = InMemoryStorage::new();
+    for unit in compile_units(&code).unwrap() {
+        let m = as_module(unit);
+        let mut blob = vec![];
+        m.serialize(&mut blob).unwrap();
+        storage.publish_or_overwrite_module(m.self_id(), blob);
+    }
+    storage
+}
+
+// A native which loads `A::<name>` instantiated with `ty_args` and aborts if its type tag is not
+// the expected one.
+fn load_struct_native(name: &'static str, ty_args: Vec<Type>) -> NativeFunction {
+    Arc::new(
+        move |context: &mut NativeContext, _: Vec<Type>, _: VecDeque<Value>| {
+            let module = IdentStr::new("A").unwrap();
+            let ty = context.load_struct(
+                TEST_ADDR,
+                module,
+                IdentStr::new(name).unwrap(),
+                ty_args.clone(),
+            )?;
+            let expected = TypeTag::Struct(StructTag {
+                address: TEST_ADDR,
+                module: module.to_owned(),
+                name: Identifier::new(name).unwrap(),
+                type_params: ty_args
+                    .iter()
+                    .map(|ty| context.type_to_type_tag(ty))
+                    .collect::<Result<_, _>>()?,
+            });
+            if context.type_to_type_tag(&ty)? == expected {
+                Ok(NativeResult::ok(InternalGasUnits::new(0), smallvec![]))
+            } else {
+                Ok(NativeResult::err(InternalGasUnits::new(0), 1))
+            }
+        },
+    )
+}
+
+fn run(fun_name: &str) -> VMResult<()> {
+    let storage = setup();
+    let m = Identifier::new("M").unwrap();
+    let natives = vec![
+        (
+            TEST_ADDR,
+            m.clone(),
+            Identifier::new("load_plain").unwrap(),
+            load_struct_native("S", vec![]),
+        ),
+        (
+            TEST_ADDR,
+            m.clone(),
+            Identifier::new("load_generic").unwrap(),
+            load_struct_native("G", vec![Type::U64]),
+        ),
+    ];
+    let vm = MoveVM::new(natives).unwrap();
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &ModuleId::new(TEST_ADDR, m),
+        &Identifier::new(fun_name).unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut GasStatus::new_unmetered(),
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn load_struct() {
+    run("plain").unwrap();
+    run("generic").unwrap();
+}
@@ -0,0 +1,177 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, ResourceResolver},
+    value::MoveValue,
+};
+use move_vm_runtime::{config::VMConfig, move_vm::MoveVM};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::cell::RefCell;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+// Storage answering from memory, and recording the batches of resources prefetched
+struct PrefetchingStorage {
+    storage: InMemoryStorage,
+    batches: RefCell<Vec<Vec<(AccountAddress, StructTag)>>>,
+    // whether prefetching fails, after recording the batch
+    fail_prefetch: bool,
+}
+
+impl ModuleResolver for PrefetchingStorage {
+    type Error = ();
+
+    fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.storage.get_module(id)
+    }
+}
+
+impl ResourceResolver for PrefetchingStorage {
+    type Error = ();
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.storage.get_resource(address, tag)
+    }
+
+    fn prefetch_resources(&self, keys: &[(AccountAddress, StructTag)]) -> Result<(), Self::Error> {
+        self.batches.borrow_mut().push(keys.to_vec());
+        if self.fail_prefetch {
+            return Err(());
+        }
+        Ok(())
+    }
+}
+
+fn struct_tag(name: &str) -> StructTag {
+    StructTag {
+        address: TEST_ADDR,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    }
+}
+
+fn setup() -> (ModuleId, PrefetchingStorage) {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct A has key {{ v: u64 }}
+            struct B has key {{ v: u64 }}
+            struct C has key {{ v: u64 }}
+
+            fun sum(addr: address): u64 acquires A, B {{
+                borrow_global<A>(addr).v + borrow_global<B>(addr).v
+            }}
+
+            fun publish(s: &signer, v: u64) {{
+                move_to(s, C {{ v }})
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    for (name, v) in [("A", 1), ("B", 2)] {
+        storage.publish_or_overwrite_resource(
+            TEST_ADDR,
+            struct_tag(name),
+            MoveValue::U64(v).simple_serialize().unwrap(),
+        );
+    }
+    let storage = PrefetchingStorage {
+        storage,
+        batches: RefCell::new(vec![]),
+        fail_prefetch: false,
+    };
+    (m.self_id(), storage)
+}
+
+fn run(vm: &MoveVM, storage: &PrefetchingStorage, module_id: &ModuleId) {
+    let mut sess = vm.new_session(storage);
+    let mut gas_status = GasStatus::new_unmetered();
+    for _ in 0..2 {
+        sess.execute_function_bypass_visibility(
+            module_id,
+            &Identifier::new("sum").unwrap(),
+            vec![],
+            vec![MoveValue::Address(TEST_ADDR).simple_serialize().unwrap()],
+            &mut gas_status,
+        )
+        .unwrap();
+    }
+    sess.execute_function_bypass_visibility(
+        module_id,
+        &Identifier::new("publish").unwrap(),
+        vec![],
+        vec![
+            MoveValue::Signer(TEST_ADDR).simple_serialize().unwrap(),
+            MoveValue::U64(3).simple_serialize().unwrap(),
+        ],
+        &mut gas_status,
+    )
+    .unwrap();
+}
+
+#[test]
+fn prefetch_resources() {
+    let (module_id, storage) = setup();
+    let vm = MoveVM::new_with_config(
+        vec![],
+        VMConfig {
+            prefetch_resources: true,
+            ..VMConfig::default()
+        },
+    )
+    .unwrap();
+    run(&vm, &storage, &module_id);
+
+    // the resources read by a call are prefetched in one batch, unless already loaded
+    assert_eq!(
+        *storage.batches.borrow(),
+        vec![
+            vec![(TEST_ADDR, struct_tag("A")), (TEST_ADDR, struct_tag("B"))],
+            vec![(TEST_ADDR, struct_tag("C"))],
+        ]
+    );
+}
+
+#[test]
+fn no_prefetch_by_default() {
+    let (module_id, storage) = setup();
+    let vm = MoveVM::new(vec![]).unwrap();
+    run(&vm, &storage, &module_id);
+    assert!(storage.batches.borrow().is_empty());
+}
+
+#[test]
+fn prefetch_failures_are_ignored() {
+    let (module_id, mut storage) = setup();
+    storage.fail_prefetch = true;
+    let vm = MoveVM::new_with_config(
+        vec![],
+        VMConfig {
+            prefetch_resources: true,
+            ..VMConfig::default()
+        },
+    )
+    .unwrap();
+    // the calls succeed, reading the resources one by one
+    run(&vm, &storage, &module_id);
+    assert_eq!(storage.batches.borrow().len(), 2);
+}
@@ -0,0 +1,102 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    value::{serialize_values, MoveValue},
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::collections::BTreeSet;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn struct_tag(name: &str) -> (AccountAddress, StructTag) {
+    (
+        TEST_ADDR,
+        StructTag {
+            address: TEST_ADDR,
+            module: Identifier::new("M").unwrap(),
+            name: Identifier::new(name).unwrap(),
+            type_params: vec![],
+        },
+    )
+}
+
+#[test]
+fn read_write_set() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            struct R has key {{ v: u64 }}
+            struct S has key {{ v: u64 }}
+            struct T has key {{ v: u64 }}
+
+            public fun setup(account: signer) {{
+                move_to(&account, R {{ v: 1 }});
+                move_to(&account, T {{ v: 1 }});
+            }}
+
+            public fun run(account: signer) acquires R, T {{
+                let R {{ v }} = move_from<R>(@0x{0});
+                borrow_global_mut<T>(@0x{0}).v = v + 1;
+                move_to(&account, S {{ v }});
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let module_id = m.self_id();
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    let call = |storage: &InMemoryStorage, fun_name: &str| {
+        let mut sess = vm.new_session(storage);
+        sess.execute_function_bypass_visibility(
+            &module_id,
+            &Identifier::new(fun_name).unwrap(),
+            vec![],
+            serialize_values(&vec![MoveValue::Signer(TEST_ADDR)]),
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap();
+        sess.finish_with_read_write_set().unwrap()
+    };
+
+    let (change_set, _, read_write_set) = call(&storage, "setup");
+    assert_eq!(
+        read_write_set.resource_writes,
+        BTreeSet::from([struct_tag("R"), struct_tag("T")])
+    );
+    storage.apply(change_set).unwrap();
+
+    let (_, _, read_write_set) = call(&storage, "run");
+    assert_eq!(
+        read_write_set.resource_reads,
+        BTreeSet::from([struct_tag("R"), struct_tag("S"), struct_tag("T")])
+    );
+    assert_eq!(
+        read_write_set.resource_writes,
+        BTreeSet::from([struct_tag("S"), struct_tag("T")])
+    );
+    assert_eq!(
+        read_write_set.resource_deletes,
+        BTreeSet::from([struct_tag("R")])
+    );
+    // `M` is served from the code cache, as the previous session loaded it, but it is read all the
+    // same
+    assert_eq!(
+        read_write_set.module_reads,
+        BTreeSet::from([ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap())])
+    );
+    assert!(read_write_set.module_writes.is_empty());
+}
@@ -0,0 +1,157 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, GasUnits, InternalGasUnits},
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::MoveResolver,
+    value::{serialize_values, MoveStruct, MoveValue},
+};
+use move_vm_runtime::{
+    move_vm::MoveVM,
+    native_functions::{NativeContext, NativeFunction},
+    replay::{record, replay, Divergence, ExecutionTrace},
+    session::Session,
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{
+    gas_schedule::{GasStatus, INITIAL_COST_SCHEDULE},
+    loaded_data::runtime_types::Type,
+    natives::function::NativeResult,
+    values::Value,
+};
+use smallvec::smallvec;
+use std::{collections::VecDeque, sync::Arc};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+// A VM whose `M::now` native, which has an external input, returns `time`
+fn vm_at(time: u64) -> MoveVM {
+    let now: NativeFunction = Arc::new(
+        move |_: &mut NativeContext, _: Vec<Type>, _: VecDeque<Value>| {
+            Ok(NativeResult::ok(
+                InternalGasUnits::new(0),
+                smallvec![Value::u64(time)],
+            ))
+        },
+    );
+    MoveVM::new(vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("now").unwrap(),
+        now,
+    )])
+    .unwrap()
+}
+
+fn bump<S: MoveResolver>(sess: &mut Session<S>, gas_status: &mut GasStatus, module_id: &ModuleId) {
+    sess.execute_function_bypass_visibility(
+        module_id,
+        &Identifier::new("bump").unwrap(),
+        vec![],
+        serialize_values(&vec![MoveValue::Address(TEST_ADDR)]),
+        gas_status,
+    )
+    .unwrap();
+}
+
+#[test]
+fn record_and_replay() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct R has key {{ v: u64 }}
+
+            native fun now(): u64;
+
+            public fun bump(a: address) acquires R {{
+                let r = borrow_global_mut<R>(a);
+                r.v = r.v + now();
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let m = as_module(compile_units(&code).unwrap().pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let r_tag = StructTag {
+        address: TEST_ADDR,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new("R").unwrap(),
+        type_params: vec![],
+    };
+
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+    storage.publish_or_overwrite_resource(
+        TEST_ADDR,
+        r_tag.clone(),
+        MoveValue::Struct(MoveStruct::new(vec![MoveValue::U64(1)]))
+            .simple_serialize()
+            .unwrap(),
+    );
+
+    let native = format!("0x{}::M::now", TEST_ADDR);
+    let gas_status = || GasStatus::new(&INITIAL_COST_SCHEDULE, GasUnits::new(1_000_000));
+
+    // the VM recording has `M` in its code cache already, which is recorded nonetheless
+    let vm = vm_at(10);
+    bump(
+        &mut vm.new_session(&storage),
+        &mut gas_status(),
+        &m.self_id(),
+    );
+    let ((), trace) = record(
+        &vm,
+        &storage,
+        vec![native],
+        &mut gas_status(),
+        |sess, gas_status| bump(sess, gas_status, &m.self_id()),
+    )
+    .unwrap();
+    assert!(trace.modules.contains_key(&m.self_id()));
+    assert!(trace.resources.contains_key(&(TEST_ADDR, r_tag.clone())));
+    assert_eq!(trace.native_calls.len(), 1);
+    assert_eq!(trace.change_set.resources().count(), 1);
+    assert!(trace.gas_used > 0);
+
+    // the trace can be saved and loaded, and replaying it on a new VM needs neither storage nor
+    // the time
+    let trace = ExecutionTrace::from_bytes(&trace.to_bytes().unwrap()).unwrap();
+    let replay_bump = |trace: &ExecutionTrace| {
+        replay(&vm_at(20), trace, &mut gas_status(), |sess, gas_status| {
+            bump(sess, gas_status, &m.self_id())
+        })
+        .unwrap()
+        .1
+    };
+    assert!(replay_bump(&trace).is_empty());
+
+    // a different result of the native changes the resource written
+    let mut tampered = trace.clone();
+    tampered.native_calls[0].result = Ok(vec![MoveValue::U64(11).simple_serialize().unwrap()]);
+    assert_eq!(
+        replay_bump(&tampered),
+        vec![Divergence::Resource(TEST_ADDR, r_tag)]
+    );
+
+    // and a different cost of the native changes the gas used
+    let mut tampered = trace.clone();
+    tampered.native_calls[0].cost += 7;
+    assert_eq!(
+        replay_bump(&tampered),
+        vec![Divergence::GasUsed {
+            recorded: trace.gas_used,
+            replayed: trace.gas_used + 7,
+        }]
+    );
+
+    // not executing anything diverges as well
+    let ((), divergences) = replay(&vm_at(20), &trace, &mut gas_status(), |_, _| ()).unwrap();
+    assert_eq!(divergences.len(), 3);
+    assert!(divergences.contains(&Divergence::NativeCalls));
+}
@@ -0,0 +1,126 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::ResourceGroup,
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::ResourceResolver,
+    value::MoveValue,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn struct_tag(name: &str) -> StructTag {
+    StructTag {
+        address: TEST_ADDR,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    }
+}
+
+fn setup() -> InMemoryStorage {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct Group {{}}
+            struct A has key {{ x: u64 }}
+            struct B has key {{ y: u64 }}
+
+            fun publish(s: &signer) {{
+                move_to(s, A {{ x: 1 }});
+                move_to(s, B {{ y: 2 }});
+            }}
+
+            fun sum(addr: address): u64 acquires A, B {{
+                borrow_global<A>(addr).x + borrow_global<B>(addr).y
+            }}
+
+            fun remove_a(addr: address) acquires A {{
+                A {{ x: _ }} = move_from<A>(addr);
+            }}
+
+            fun remove_b(addr: address) acquires B {{
+                B {{ y: _ }} = move_from<B>(addr);
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    storage.publish_or_overwrite_module(module_id, blob);
+    storage.add_resource_group_member(struct_tag("A"), struct_tag("Group"));
+    storage.add_resource_group_member(struct_tag("B"), struct_tag("Group"));
+    storage
+}
+
+// Runs a function of `M` with the given argument and applies its effects to the storage
+fn run(storage: &mut InMemoryStorage, fun_name: &str, arg: MoveValue) -> Vec<u8> {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut sess = vm.new_session(storage);
+    let return_values = sess
+        .execute_function_bypass_visibility(
+            &ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap()),
+            &Identifier::new(fun_name).unwrap(),
+            vec![],
+            vec![arg.simple_serialize().unwrap()],
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap()
+        .return_values;
+    let (changeset, _) = sess.finish().unwrap();
+    storage.apply(changeset).unwrap();
+    return_values
+        .into_iter()
+        .next()
+        .map(|(blob, _)| blob)
+        .unwrap_or_default()
+}
+
+fn stored_group(storage: &InMemoryStorage) -> Option<ResourceGroup> {
+    storage
+        .get_resource(&TEST_ADDR, &struct_tag("Group"))
+        .unwrap()
+        .map(|blob| ResourceGroup::deserialize(&blob).unwrap())
+}
+
+#[test]
+fn resource_group_members() {
+    let mut storage = setup();
+
+    // members are stored together in the slot of the group
+    run(&mut storage, "publish", MoveValue::Signer(TEST_ADDR));
+    assert!(storage
+        .get_resource(&TEST_ADDR, &struct_tag("A"))
+        .unwrap()
+        .is_none());
+    let group = stored_group(&storage).unwrap();
+    assert_eq!(
+        group.members().keys().cloned().collect::<Vec<_>>(),
+        vec![struct_tag("A"), struct_tag("B")]
+    );
+
+    // members are read from the group
+    let sum = run(&mut storage, "sum", MoveValue::Address(TEST_ADDR));
+    assert_eq!(sum, MoveValue::U64(3).simple_serialize().unwrap());
+
+    // removing a member keeps the other ones, removing the last one deletes the group
+    run(&mut storage, "remove_a", MoveValue::Address(TEST_ADDR));
+    let group = stored_group(&storage).unwrap();
+    assert!(group.get(&struct_tag("A")).is_none());
+    assert!(group.get(&struct_tag("B")).is_some());
+    run(&mut storage, "remove_b", MoveValue::Address(TEST_ADDR));
+    assert!(stored_group(&storage).is_none());
+}
@@ -8,7 +8,7 @@ use move_core_types::{
     account_address::AccountAddress,
     identifier::Identifier,
     language_storage::{ModuleId, TypeTag},
-    value::{MoveTypeLayout, MoveValue},
+    value::{MoveStructLayout, MoveTypeLayout, MoveValue},
 };
 use move_vm_runtime::{move_vm::MoveVM, session::SerializedReturnValues};
 use move_vm_test_utils::InMemoryStorage;
@@ -22,7 +22,7 @@ fn run(
     fun_body: &str,
     ty_args: Vec<TypeTag>,
     args: Vec<MoveValue>,
-) -> VMResult<Vec<Vec<u8>>> {
+) -> VMResult<Vec<(Vec<u8>, MoveTypeLayout)>> {
     let structs = structs.to_vec().join("\n");
 
     let code = format!(
@@ -69,10 +69,7 @@ fn run(
         &mut gas_status,
     )?;
 
-    Ok(return_values
-        .into_iter()
-        .map(|(bytes, _layout)| bytes)
-        .collect())
+    Ok(return_values)
 }
 
 fn expect_success(
@@ -86,8 +83,12 @@ fn expect_success(
     let return_vals = run(structs, fun_sig, fun_body, ty_args, args).unwrap();
     assert!(return_vals.len() == expected_layouts.len());
 
-    for (blob, layout) in return_vals.iter().zip(expected_layouts.iter()) {
-        MoveValue::simple_deserialize(blob, layout).unwrap();
+    // values must be readable with both the expected layouts and the ones returned by the VM
+    for ((blob, layout), expected_layout) in return_vals.iter().zip(expected_layouts.iter()) {
+        assert_eq!(
+            MoveValue::simple_deserialize(blob, layout).unwrap(),
+            MoveValue::simple_deserialize(blob, expected_layout).unwrap()
+        );
     }
 }
 
@@ -124,3 +125,18 @@ fn return_signer_ref() {
         &[MoveTypeLayout::Signer],
     )
 }
+
+#[test]
+fn return_struct() {
+    expect_success(
+        &["struct S has drop { x: u64, b: bool }"],
+        "(): S",
+        "S { x: 42, b: true }",
+        vec![],
+        vec![],
+        &[MoveTypeLayout::Struct(MoveStructLayout::Runtime(vec![
+            MoveTypeLayout::U64,
+            MoveTypeLayout::Bool,
+        ]))],
+    )
+}
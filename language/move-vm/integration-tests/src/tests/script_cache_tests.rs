@@ -0,0 +1,66 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_script, compile_units};
+use move_core_types::value::{serialize_values, MoveValue};
+use move_vm_runtime::{config::VMConfig, metrics::VMMetrics, move_vm::MoveVM};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+#[derive(Default)]
+struct ScriptsLoaded(AtomicU64);
+
+impl VMMetrics for ScriptsLoaded {
+    fn inc_scripts_loaded(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn script(value: u64) -> Vec<u8> {
+    let code = format!(
+        "script {{ fun main(x: u64) {{ assert!(x != {}, 0); }} }}",
+        value
+    );
+    let mut units = compile_units(&code).unwrap();
+    let script = as_script(units.pop().unwrap());
+    let mut blob = vec![];
+    script.serialize(&mut blob).unwrap();
+    blob
+}
+
+#[test]
+fn script_cache() {
+    let scripts = [script(1), script(2), script(3)];
+    let storage = InMemoryStorage::new();
+    let metrics = Arc::new(ScriptsLoaded::default());
+    let vm_config = VMConfig {
+        max_script_cache_size: 2,
+        ..VMConfig::default()
+    };
+    let vm = MoveVM::new_with_metrics(vec![], vm_config, metrics.clone()).unwrap();
+    let run_scripts = |indices: &[usize]| {
+        for idx in indices {
+            // each script runs in its own session
+            let mut sess = vm.new_session(&storage);
+            sess.execute_script(
+                scripts[*idx].clone(),
+                vec![],
+                serialize_values(&vec![MoveValue::U64(0)]),
+                &mut GasStatus::new_unmetered(),
+            )
+            .unwrap();
+        }
+        metrics.0.load(Ordering::Relaxed)
+    };
+
+    // scripts are verified once, whichever session executes them
+    assert_eq!(run_scripts(&[0, 1, 0, 1]), 2);
+    // the cache is full, so the least recently used script (1) is evicted
+    assert_eq!(run_scripts(&[0, 2]), 3);
+    assert_eq!(run_scripts(&[0, 2]), 3);
+    assert_eq!(run_scripts(&[1]), 4);
+}
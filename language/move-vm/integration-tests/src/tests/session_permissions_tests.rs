@@ -0,0 +1,83 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, as_script, compile_units};
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, vm_status::StatusCode,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+#[test]
+fn disallow_publishing_and_scripts() {
+    let code = format!(
+        r#"
+        module 0x{0}::M {{
+            public fun one(): u64 {{ 1 }}
+        }}
+
+        script {{
+            fun main() {{ 0x{0}::M::one(); }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let script = as_script(units.pop().unwrap());
+    let module = as_module(units.pop().unwrap());
+    let mut script_blob = vec![];
+    script.serialize(&mut script_blob).unwrap();
+    let mut module_blob = vec![];
+    module.serialize(&mut module_blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut gas_status = GasStatus::new_unmetered();
+
+    let mut sess = vm.new_session(&storage);
+    sess.disallow_module_publishing();
+    let err = sess
+        .publish_module(module_blob.clone(), TEST_ADDR, &mut gas_status)
+        .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::OPERATION_DISALLOWED_IN_SESSION
+    );
+    let (changeset, _) = sess.finish().unwrap();
+    assert_eq!(changeset.accounts().len(), 0);
+
+    storage.publish_or_overwrite_module(module.self_id(), module_blob);
+
+    // functions can still be executed, but not scripts
+    let mut sess = vm.new_session(&storage);
+    sess.disallow_module_publishing();
+    sess.disallow_scripts();
+    sess.execute_function_bypass_visibility(
+        &module.self_id(),
+        &Identifier::new("one").unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut gas_status,
+    )
+    .unwrap();
+    let err = sess
+        .execute_script(
+            script_blob.clone(),
+            vec![],
+            Vec::<Vec<u8>>::new(),
+            &mut gas_status,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::OPERATION_DISALLOWED_IN_SESSION
+    );
+
+    // sessions allow both by default
+    let mut sess = vm.new_session(&storage);
+    sess.execute_script(script_blob, vec![], Vec::<Vec<u8>>::new(), &mut gas_status)
+        .unwrap();
+}
@@ -0,0 +1,125 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::compile_units;
+use move_binary_format::{
+    errors::{SourceLocation, VMError},
+    file_format::{CodeOffset, FunctionDefinitionIndex},
+};
+use move_bytecode_source_map::source_map::SourceMap;
+use move_compiler::compiled_unit::AnnotatedCompiledUnit;
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, vm_status::StatusCode,
+};
+use move_vm_runtime::{move_vm::MoveVM, source_locator::SourceLocator};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::sync::Arc;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+const FILE_NAME: &str = "M.move";
+
+struct SourceMapLocator {
+    source_map: SourceMap,
+    file_contents: String,
+}
+
+impl SourceLocator for SourceMapLocator {
+    fn source_location(
+        &self,
+        function: FunctionDefinitionIndex,
+        offset: CodeOffset,
+    ) -> Option<SourceLocation> {
+        self.source_map
+            .get_source_location(function, offset, FILE_NAME, &self.file_contents)
+    }
+}
+
+fn code() -> String {
+    format!(
+        r#"
+        module 0x{}::M {{
+            public fun fail_abort() {{
+                abort 7
+            }}
+
+            public fun fail_arithmetic(): u64 {{
+                let x = 0;
+                1 / x
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    )
+}
+
+// Line, starting at 1, of the first occurrence of `pattern` in `code`
+fn line_of(code: &str, pattern: &str) -> u32 {
+    let idx = code.find(pattern).unwrap();
+    code[..idx].matches('\n').count() as u32 + 1
+}
+
+// Publishes the module and runs `fun_name`, with the source map of the module registered if
+// `with_locator` is set
+fn run(fun_name: &str, with_locator: bool) -> VMError {
+    let code = code();
+    let (module, source_map) = match compile_units(&code).unwrap().pop().unwrap() {
+        AnnotatedCompiledUnit::Module(annot_module) => (
+            annot_module.named_module.module,
+            annot_module.named_module.source_map,
+        ),
+        AnnotatedCompiledUnit::Script(_) => panic!("expected module got script"),
+    };
+    let module_id = module.self_id();
+    let mut blob = vec![];
+    module.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    if with_locator {
+        vm.set_source_locator(
+            module_id.clone(),
+            Arc::new(SourceMapLocator {
+                source_map,
+                // the compiler reads the code from a file ending with a new line
+                file_contents: format!("{}\n", code),
+            }),
+        );
+    }
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &module_id,
+        &Identifier::new(fun_name).unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut GasStatus::new_unmetered(),
+    )
+    .unwrap_err()
+}
+
+#[test]
+fn abort_source_location() {
+    let err = run("fail_abort", true);
+    assert_eq!(err.major_status(), StatusCode::ABORTED);
+    assert_eq!(err.sub_status(), Some(7));
+    let source_location = err.source_location().unwrap();
+    assert_eq!(source_location.file, FILE_NAME);
+    assert_eq!(source_location.line, line_of(&code(), "abort 7"));
+}
+
+#[test]
+fn arithmetic_error_source_location() {
+    let err = run("fail_arithmetic", true);
+    assert_eq!(err.major_status(), StatusCode::ARITHMETIC_ERROR);
+    let source_location = err.source_location().unwrap();
+    assert_eq!(source_location.file, FILE_NAME);
+    assert_eq!(source_location.line, line_of(&code(), "1 / x"));
+}
+
+#[test]
+fn no_source_location_without_locator() {
+    let err = run("fail_abort", false);
+    assert_eq!(err.major_status(), StatusCode::ABORTED);
+    assert!(err.source_location().is_none());
+}
@@ -0,0 +1,97 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::file_format::Bytecode;
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, language_storage::ModuleId,
+    value::MoveValue,
+};
+use move_vm_runtime::{
+    move_vm::MoveVM,
+    tracer::{FrameInfo, Tracer},
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{gas_schedule::GasStatus, values::Value};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+/// Records the function, depth, offset, instruction and operand stack height of each instruction.
+#[derive(Default)]
+struct RecordingTracer {
+    steps: Vec<(String, usize, u16, Bytecode, usize)>,
+}
+
+impl Tracer for RecordingTracer {
+    fn on_instruction(
+        &mut self,
+        frame: &FrameInfo,
+        pc: u16,
+        instruction: &Bytecode,
+        operand_stack: &[Value],
+    ) {
+        self.steps.push((
+            frame.function_name.to_string(),
+            frame.depth,
+            pc,
+            instruction.clone(),
+            operand_stack.len(),
+        ));
+    }
+}
+
+#[test]
+fn trace_instructions() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            fun add_one(x: u64): u64 {{
+                x + 1
+            }}
+
+            fun foo(x: u64): u64 {{
+                add_one(x)
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut tracer = RecordingTracer::default();
+    {
+        let mut sess = vm.new_session(&storage);
+        sess.set_tracer(Box::new(&mut tracer));
+        sess.execute_function_bypass_visibility(
+            &module_id,
+            &Identifier::new("foo").unwrap(),
+            vec![],
+            vec![MoveValue::U64(41).simple_serialize().unwrap()],
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap();
+    }
+
+    let steps = tracer.steps;
+    // the first instruction of the called function is traced at the start of its frame
+    assert_eq!(steps[0].0, "foo");
+    assert_eq!((steps[0].1, steps[0].2, steps[0].4), (0, 0, 0));
+    let add = steps
+        .iter()
+        .find(|(_, _, _, instruction, _)| *instruction == Bytecode::Add)
+        .unwrap();
+    assert_eq!(add.0, "add_one");
+    assert_eq!(add.1, 1);
+    // both operands of the addition are on the stack
+    assert_eq!(add.4, 2);
+    let last = steps.last().unwrap();
+    assert_eq!((last.0.as_str(), &last.3), ("foo", &Bytecode::Ret));
+}
@@ -0,0 +1,89 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{StructTag, TypeTag},
+};
+use move_vm_runtime::{config::VMConfig, metrics::VMMetrics, move_vm::MoveVM};
+use move_vm_test_utils::InMemoryStorage;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+#[derive(Default)]
+struct TypeCacheMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl VMMetrics for TypeCacheMetrics {
+    fn inc_type_cache_hits(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn inc_type_cache_misses(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn inc_type_cache_evictions(&self, count: u64) {
+        self.evictions.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+fn s(ty_arg: TypeTag) -> TypeTag {
+    TypeTag::Struct(StructTag {
+        address: TEST_ADDR,
+        module: Identifier::new("M").unwrap(),
+        name: Identifier::new("S").unwrap(),
+        type_params: vec![ty_arg],
+    })
+}
+
+#[test]
+fn type_cache() {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            struct S<T> {{ x: T }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(m.self_id(), blob);
+
+    let metrics = Arc::new(TypeCacheMetrics::default());
+    let vm_config = VMConfig {
+        max_type_cache_size: 2,
+        ..VMConfig::default()
+    };
+    let vm = MoveVM::new_with_metrics(vec![], vm_config, metrics.clone()).unwrap();
+    let sess = vm.new_session(&storage);
+    let stats = |ty_arg: TypeTag| {
+        sess.get_type_layout(&s(ty_arg)).unwrap();
+        (
+            metrics.hits.load(Ordering::Relaxed),
+            metrics.misses.load(Ordering::Relaxed),
+            metrics.evictions.load(Ordering::Relaxed),
+        )
+    };
+
+    assert_eq!(stats(TypeTag::U8), (0, 1, 0));
+    assert_eq!(stats(TypeTag::U8), (1, 1, 0));
+    assert_eq!(stats(TypeTag::U64), (1, 2, 0));
+    // the cache is full, so the least recently used instantiation (`S<u8>`) is evicted
+    assert_eq!(stats(TypeTag::Bool), (1, 3, 1));
+    assert_eq!(stats(TypeTag::U64), (2, 3, 1));
+    assert_eq!(stats(TypeTag::U8), (2, 4, 2));
+}
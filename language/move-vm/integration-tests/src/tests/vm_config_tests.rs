@@ -0,0 +1,409 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, InternalGasUnits},
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag, TypeTag},
+    value::{serialize_values, MoveStruct, MoveValue},
+    vm_status::StatusCode,
+};
+use move_vm_runtime::{
+    config::VMConfig,
+    move_vm::MoveVM,
+    native_functions::{NativeContext, NativeFunction, NativeFunctionTable},
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{
+    gas_schedule::GasStatus, loaded_data::runtime_types::Type, natives::function::NativeResult,
+    values::Value,
+};
+use smallvec::smallvec;
+use std::{collections::VecDeque, sync::Arc};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn setup() -> InMemoryStorage {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            fun recurse(n: u64): u64 {{
+                if (n == 0) 0 else recurse(n - 1) + 1
+            }}
+
+            fun generic<T>() {{}}
+
+            struct Pair<T> has drop {{ a: T, b: T }}
+
+            struct R has key {{ v: vector<u8> }}
+
+            fun publish(s: signer, v: vector<u8>) {{
+                move_to(&s, R {{ v }})
+            }}
+
+            native fun noop();
+
+            fun call_native(n: u64) {{
+                while (n > 0) {{ noop(); n = n - 1 }}
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    storage.publish_or_overwrite_module(module_id, blob);
+    storage
+}
+
+fn natives() -> NativeFunctionTable {
+    vec![(
+        TEST_ADDR,
+        Identifier::new("M").unwrap(),
+        Identifier::new("noop").unwrap(),
+        Arc::new(|_: &mut NativeContext, _: Vec<Type>, _: VecDeque<Value>| {
+            Ok(NativeResult::ok(InternalGasUnits::new(0), smallvec![]))
+        }) as NativeFunction,
+    )]
+}
+
+fn run(
+    vm_config: VMConfig,
+    fun_name: &str,
+    ty_args: Vec<TypeTag>,
+    args: Vec<MoveValue>,
+) -> VMResult<()> {
+    let storage = setup();
+    let vm = MoveVM::new_with_config(natives(), vm_config).unwrap();
+    let mut sess = vm.new_session(&storage);
+    sess.execute_function_bypass_visibility(
+        &ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap()),
+        &Identifier::new(fun_name).unwrap(),
+        ty_args,
+        serialize_values(&args),
+        &mut GasStatus::new_unmetered(),
+    )?;
+    sess.finish().map(|_| ())
+}
+
+#[test]
+fn max_call_stack_size() {
+    let vm_config = VMConfig {
+        max_call_stack_size: 10,
+        ..VMConfig::default()
+    };
+    run(
+        vm_config.clone(),
+        "recurse",
+        vec![],
+        vec![MoveValue::U64(5)],
+    )
+    .unwrap();
+    let err = run(vm_config, "recurse", vec![], vec![MoveValue::U64(20)]).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::CALL_STACK_OVERFLOW);
+}
+
+#[test]
+fn max_type_instantiation_size() {
+    // a type of `depth + 1` nodes
+    fn nested_vector(depth: usize) -> TypeTag {
+        (0..depth).fold(TypeTag::U8, |ty, _| TypeTag::Vector(Box::new(ty)))
+    }
+
+    // no limit by default
+    run(
+        VMConfig::default(),
+        "generic",
+        vec![nested_vector(300)],
+        vec![],
+    )
+    .unwrap();
+
+    let vm_config = VMConfig {
+        max_type_instantiation_size: Some(8),
+        ..VMConfig::default()
+    };
+    run(vm_config.clone(), "generic", vec![nested_vector(7)], vec![]).unwrap();
+    let err = run(vm_config, "generic", vec![nested_vector(8)], vec![]).unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::VM_MAX_TYPE_INSTANTIATION_SIZE_REACHED
+    );
+}
+
+#[test]
+fn paranoid_checks() {
+    let vm_config = VMConfig {
+        paranoid_checks: true,
+        ..VMConfig::default()
+    };
+    run(vm_config, "recurse", vec![], vec![MoveValue::U64(20)]).unwrap();
+}
+
+#[test]
+fn max_instructions() {
+    let vm_config = VMConfig {
+        max_instructions: Some(100),
+        ..VMConfig::default()
+    };
+    run(
+        vm_config.clone(),
+        "recurse",
+        vec![],
+        vec![MoveValue::U64(5)],
+    )
+    .unwrap();
+    let err = run(vm_config, "recurse", vec![], vec![MoveValue::U64(50)]).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::EXECUTION_LIMIT_REACHED);
+}
+
+#[test]
+fn max_native_calls() {
+    let vm_config = VMConfig {
+        max_native_calls: Some(10),
+        ..VMConfig::default()
+    };
+    run(
+        vm_config.clone(),
+        "call_native",
+        vec![],
+        vec![MoveValue::U64(10)],
+    )
+    .unwrap();
+    let err = run(vm_config, "call_native", vec![], vec![MoveValue::U64(11)]).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::EXECUTION_LIMIT_REACHED);
+}
+
+#[test]
+fn max_type_layout_nodes() {
+    // a type whose layout has `2^(depth + 1) - 1` nodes
+    fn nested_pair(depth: usize) -> TypeTag {
+        (0..depth).fold(TypeTag::U8, |ty, _| {
+            TypeTag::Struct(StructTag {
+                address: TEST_ADDR,
+                module: Identifier::new("M").unwrap(),
+                name: Identifier::new("Pair").unwrap(),
+                type_params: vec![ty],
+            })
+        })
+    }
+
+    let storage = setup();
+    let layout = |vm: &MoveVM, depth: usize| {
+        vm.new_session(&storage)
+            .get_type_layout(&nested_pair(depth))
+            .map(|_| ())
+    };
+
+    // no limit by default
+    let vm = MoveVM::new_with_config(natives(), VMConfig::default()).unwrap();
+    layout(&vm, 14).unwrap();
+
+    let vm = MoveVM::new_with_config(
+        natives(),
+        VMConfig {
+            max_type_layout_nodes: Some(64),
+            ..VMConfig::default()
+        },
+    )
+    .unwrap();
+    layout(&vm, 5).unwrap();
+    // the layouts cached are accounted for as if they were converted
+    let err = layout(&vm, 6).unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::VM_MAX_TYPE_LAYOUT_NODES_REACHED
+    );
+
+    let vm = MoveVM::new_with_config(
+        natives(),
+        VMConfig {
+            max_value_nest_depth: 4,
+            ..VMConfig::default()
+        },
+    )
+    .unwrap();
+    layout(&vm, 3).unwrap();
+    let err = layout(&vm, 4).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::VM_MAX_VALUE_DEPTH_REACHED);
+}
+
+#[test]
+fn max_resource_size() {
+    // a resource of `len + 1` bytes
+    fn publish_args(len: usize) -> Vec<MoveValue> {
+        vec![
+            MoveValue::Signer(TEST_ADDR),
+            MoveValue::vector_u8(vec![0; len]),
+        ]
+    }
+
+    let vm_config = VMConfig {
+        max_resource_size: Some(33),
+        ..VMConfig::default()
+    };
+    run(vm_config.clone(), "publish", vec![], publish_args(32)).unwrap();
+    let err = run(vm_config, "publish", vec![], publish_args(33)).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::RESOURCE_SIZE_LIMIT_EXCEEDED);
+}
+
+#[test]
+fn check_entry_argument_types() {
+    let code = format!(
+        r#"
+        module 0x1::string {{
+            struct String has copy, drop {{ bytes: vector<u8> }}
+        }}
+
+        module 0x1::option {{
+            struct Option<T> has copy, drop {{ vec: vector<T> }}
+        }}
+
+        module 0x{0}::O {{
+            struct Other has copy, drop {{ v: u64 }}
+        }}
+
+        module 0x{0}::N {{
+            use 0x1::option::Option;
+            use 0x1::string::String;
+            use 0x{0}::O::Other;
+
+            struct Str has copy, drop {{ bytes: vector<u8> }}
+            struct Opt<T> has copy, drop {{ vec: vector<T> }}
+            struct R has key {{ v: u64 }}
+
+            public entry fun take_values(
+                _s: &signer,
+                _v: vector<vector<u64>>,
+                _str: Str,
+                _o: Opt<Str>,
+            ) {{}}
+
+            public entry fun take_std_values(_s: String, _o: Option<vector<String>>) {{}}
+
+            public entry fun take_other(_o: Other) {{}}
+
+            public entry fun take_resource(s: signer, r: R) {{
+                move_to(&s, r)
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut storage = setup();
+    for unit in compile_units(&code).unwrap() {
+        let m = as_module(unit);
+        let mut blob = vec![];
+        m.serialize(&mut blob).unwrap();
+        storage.publish_or_overwrite_module(m.self_id(), blob);
+    }
+    let n_id = ModuleId::new(TEST_ADDR, Identifier::new("N").unwrap());
+
+    let vm = MoveVM::new_with_config(
+        natives(),
+        VMConfig {
+            check_entry_argument_types: true,
+            ..VMConfig::default()
+        },
+    )
+    .unwrap();
+    let call = |fun_name: &str, args: Vec<MoveValue>| {
+        vm.new_session(&storage)
+            .execute_entry_function(
+                &n_id,
+                &Identifier::new(fun_name).unwrap(),
+                vec![],
+                serialize_values(&args),
+                &mut GasStatus::new_unmetered(),
+            )
+            .map(|_| ())
+    };
+    let str_value = |bytes: &[u8]| {
+        MoveValue::Struct(MoveStruct::new(vec![MoveValue::vector_u8(bytes.to_vec())]))
+    };
+    let opt_value =
+        |elems: Vec<MoveValue>| MoveValue::Struct(MoveStruct::new(vec![MoveValue::Vector(elems)]));
+
+    // nested vectors and structs of values of the module of the function can be passed
+    call(
+        "take_values",
+        vec![
+            MoveValue::Signer(TEST_ADDR),
+            MoveValue::Vector(vec![MoveValue::Vector(vec![MoveValue::U64(1)])]),
+            str_value(b"a"),
+            opt_value(vec![str_value(b"b")]),
+        ],
+    )
+    .unwrap();
+
+    // as well as strings and options of the standard library
+    let strings = MoveValue::Vector(vec![str_value(b"b"), str_value(b"c")]);
+    call(
+        "take_std_values",
+        vec![str_value(b"a"), opt_value(vec![strings.clone()])],
+    )
+    .unwrap();
+
+    // as long as strings are valid UTF-8
+    let err = call(
+        "take_std_values",
+        vec![
+            str_value(b"a"),
+            opt_value(vec![MoveValue::Vector(vec![str_value(b"\xff")])]),
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT
+    );
+    assert_eq!(
+        err.message().unwrap(),
+        "argument 1 contains a string which is not valid UTF-8"
+    );
+
+    // and options have at most one element
+    let err = call(
+        "take_std_values",
+        vec![str_value(b"a"), opt_value(vec![strings.clone(), strings])],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT
+    );
+
+    // but not structs of other modules
+    let err = call(
+        "take_other",
+        vec![MoveValue::Struct(MoveStruct::new(vec![MoveValue::U64(1)]))],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::INVALID_PARAM_TYPE_FOR_DESERIALIZATION
+    );
+
+    // nor resources
+    let err = call(
+        "take_resource",
+        vec![
+            MoveValue::Signer(TEST_ADDR),
+            MoveValue::Struct(MoveStruct::new(vec![MoveValue::U64(1)])),
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.major_status(),
+        StatusCode::INVALID_PARAM_TYPE_FOR_DESERIALIZATION
+    );
+    assert!(err.message().unwrap().starts_with("argument 1 of type"));
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, language_storage::ModuleId,
+    vm_status::StatusCode,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas_schedule::GasStatus;
+use std::cell::Cell;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+fn setup() -> (ModuleId, InMemoryStorage) {
+    let code = format!(
+        r#"
+        module 0x{}::M {{
+            fun spin() {{
+                loop {{}}
+            }}
+
+            fun one(): u64 {{
+                1
+            }}
+        }}
+    "#,
+        TEST_ADDR
+    );
+    let mut units = compile_units(&code).unwrap();
+    let m = as_module(units.pop().unwrap());
+    let mut blob = vec![];
+    m.serialize(&mut blob).unwrap();
+
+    let mut storage = InMemoryStorage::new();
+    let module_id = ModuleId::new(TEST_ADDR, Identifier::new("M").unwrap());
+    storage.publish_or_overwrite_module(module_id.clone(), blob);
+    (module_id, storage)
+}
+
+#[test]
+fn watchdog_interrupts_execution() {
+    let (module_id, storage) = setup();
+    let vm = MoveVM::new(vec![]).unwrap();
+    let calls = Cell::new(0);
+    let mut sess = vm.new_session(&storage);
+    sess.set_watchdog(
+        10,
+        Box::new(|| {
+            calls.set(calls.get() + 1);
+            calls.get() == 3
+        }),
+    );
+    let err = sess
+        .execute_function_bypass_visibility(
+            &module_id,
+            &Identifier::new("spin").unwrap(),
+            vec![],
+            Vec::<Vec<u8>>::new(),
+            &mut GasStatus::new_unmetered(),
+        )
+        .unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::EXECUTION_INTERRUPTED);
+    assert!(err.message().unwrap().contains("after 30 instructions"));
+    assert_eq!(calls.get(), 3);
+
+    // executions shorter than the interval never call the watchdog
+    sess.execute_function_bypass_visibility(
+        &module_id,
+        &Identifier::new("one").unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut GasStatus::new_unmetered(),
+    )
+    .unwrap();
+    assert_eq!(calls.get(), 3);
+
+    // the removed watchdog is handed back
+    let mut watchdog = sess.remove_watchdog().unwrap();
+    assert!(!watchdog.should_interrupt());
+    assert_eq!(calls.get(), 4);
+}
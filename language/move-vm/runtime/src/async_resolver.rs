@@ -0,0 +1,248 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adapter plugging storage with an async interface, e.g., backed by RPCs or an async database,
+//! into the VM, which reads storage synchronously.
+//!
+//! An `AsyncResolverAdapter` wraps an `AsyncMoveResolver` and implements `MoveResolver`, so that a
+//! session can be created on it. The data a transaction is expected to read should be prefetched
+//! (in batches, without blocking) before executing it, with `prefetch_modules`,
+//! `prefetch_modules_with_dependencies` and `prefetch_resources`. Reads of data which was not
+//! prefetched block the current thread until storage answers, so sessions on an adapter should
+//! run where blocking is allowed, e.g., in `tokio::task::spawn_blocking` rather than on an async
+//! executor thread.
+
+use futures::{executor::block_on, future::BoxFuture};
+use move_binary_format::{access::ModuleAccess, CompiledModule};
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, ResourceResolver},
+};
+use parking_lot::RwLock;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+/// A storage backend resolving modules and resources asynchronously, in batches.
+///
+/// Results are returned in the order of the keys requested, one per key (the adapter fails
+/// otherwise), following the conventions of
+/// `ModuleResolver` and `ResourceResolver`: `None` if the data does not exist, and an error only
+/// when something really wrong happens.
+pub trait AsyncMoveResolver: Send + Sync {
+    type Err: Debug + Send;
+
+    fn get_modules(
+        &self,
+        ids: Vec<ModuleId>,
+    ) -> BoxFuture<'_, Result<Vec<Option<Vec<u8>>>, Self::Err>>;
+
+    fn get_resources(
+        &self,
+        keys: Vec<(AccountAddress, StructTag)>,
+    ) -> BoxFuture<'_, Result<Vec<Option<Vec<u8>>>, Self::Err>>;
+
+    /// Returns the tag of the resource group the given resource type is a member of, if any (see
+    /// `ResourceResolver::get_resource_group`).
+    fn get_resource_group(
+        &self,
+        _typ: StructTag,
+    ) -> BoxFuture<'_, Result<Option<StructTag>, Self::Err>> {
+        Box::pin(futures::future::ready(Ok(None)))
+    }
+}
+
+/// An error reading storage through an `AsyncResolverAdapter`
+#[derive(Debug)]
+pub enum AsyncResolverError<E> {
+    /// The backend failed to read storage
+    Backend(E),
+    /// The backend returned a batch which does not have one entry per key requested, so that the
+    /// entries cannot be matched with the keys
+    BatchSize { requested: usize, returned: usize },
+}
+
+// Checks that the backend returned one entry per key requested
+fn check_batch_size<E>(
+    requested: usize,
+    blobs: &[Option<Vec<u8>>],
+) -> Result<(), AsyncResolverError<E>> {
+    if blobs.len() != requested {
+        return Err(AsyncResolverError::BatchSize {
+            requested,
+            returned: blobs.len(),
+        });
+    }
+    Ok(())
+}
+
+/// A `MoveResolver` over an `AsyncMoveResolver`, serving reads from the data prefetched, and
+/// blocking on storage for the rest.
+///
+/// Everything read is cached, including data which does not exist, so an adapter should live no
+/// longer than the state of storage it reads, e.g., be created for each block of transactions.
+pub struct AsyncResolverAdapter<R> {
+    resolver: R,
+    modules: RwLock<BTreeMap<ModuleId, Option<Vec<u8>>>>,
+    resources: RwLock<BTreeMap<(AccountAddress, StructTag), Option<Vec<u8>>>>,
+    resource_groups: RwLock<BTreeMap<StructTag, Option<StructTag>>>,
+}
+
+impl<R: AsyncMoveResolver> AsyncResolverAdapter<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            modules: RwLock::new(BTreeMap::new()),
+            resources: RwLock::new(BTreeMap::new()),
+            resource_groups: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Fetches the given modules which are not cached yet, in one batch.
+    pub async fn prefetch_modules(
+        &self,
+        ids: impl IntoIterator<Item = ModuleId>,
+    ) -> Result<(), AsyncResolverError<R::Err>> {
+        self.fetch_modules(ids).await.map(|_| ())
+    }
+
+    /// Fetches the given modules along with their transitive dependencies, which are not cached
+    /// yet, in one batch per level of dependencies.
+    ///
+    /// Modules which fail to deserialize are cached as they are, and their dependencies are not
+    /// fetched: the VM reports the error when loading them.
+    pub async fn prefetch_modules_with_dependencies(
+        &self,
+        ids: impl IntoIterator<Item = ModuleId>,
+    ) -> Result<(), AsyncResolverError<R::Err>> {
+        let mut visited = BTreeSet::new();
+        let mut next = ids.into_iter().collect::<BTreeSet<_>>();
+        while !next.is_empty() {
+            visited.extend(next.iter().cloned());
+            let cached = {
+                let modules = self.modules.read();
+                next.iter()
+                    .filter_map(|id| modules.get(id).cloned().flatten())
+                    .collect::<Vec<_>>()
+            };
+            let fetched = self.fetch_modules(next).await?;
+            next = cached
+                .iter()
+                .chain(fetched.iter())
+                .filter_map(|blob| CompiledModule::deserialize(blob).ok())
+                .flat_map(|module| module.immediate_dependencies())
+                .filter(|dep| !visited.contains(dep))
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Fetches the given resources which are not cached yet, in one batch.
+    pub async fn prefetch_resources(
+        &self,
+        keys: impl IntoIterator<Item = (AccountAddress, StructTag)>,
+    ) -> Result<(), AsyncResolverError<R::Err>> {
+        let missing = {
+            let resources = self.resources.read();
+            keys.into_iter()
+                .filter(|key| !resources.contains_key(key))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let blobs = self
+            .resolver
+            .get_resources(missing.clone())
+            .await
+            .map_err(AsyncResolverError::Backend)?;
+        check_batch_size(missing.len(), &blobs)?;
+        self.resources
+            .write()
+            .extend(missing.into_iter().zip(blobs));
+        Ok(())
+    }
+
+    // Fetches the modules which are not cached yet, and returns the ones which exist
+    async fn fetch_modules(
+        &self,
+        ids: impl IntoIterator<Item = ModuleId>,
+    ) -> Result<Vec<Vec<u8>>, AsyncResolverError<R::Err>> {
+        let missing = {
+            let modules = self.modules.read();
+            ids.into_iter()
+                .filter(|id| !modules.contains_key(id))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        if missing.is_empty() {
+            return Ok(vec![]);
+        }
+        let blobs = self
+            .resolver
+            .get_modules(missing.clone())
+            .await
+            .map_err(AsyncResolverError::Backend)?;
+        check_batch_size(missing.len(), &blobs)?;
+        let fetched = blobs.iter().flatten().cloned().collect();
+        self.modules.write().extend(missing.into_iter().zip(blobs));
+        Ok(fetched)
+    }
+}
+
+impl<R: AsyncMoveResolver> ModuleResolver for AsyncResolverAdapter<R> {
+    type Error = AsyncResolverError<R::Err>;
+
+    fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        if let Some(blob) = self.modules.read().get(id) {
+            return Ok(blob.clone());
+        }
+        block_on(self.prefetch_modules(vec![id.clone()]))?;
+        Ok(self.modules.read().get(id).cloned().flatten())
+    }
+}
+
+impl<R: AsyncMoveResolver> ResourceResolver for AsyncResolverAdapter<R> {
+    type Error = AsyncResolverError<R::Err>;
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        typ: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        let key = (*address, typ.clone());
+        if let Some(blob) = self.resources.read().get(&key) {
+            return Ok(blob.clone());
+        }
+        block_on(self.prefetch_resources(vec![key.clone()]))?;
+        Ok(self.resources.read().get(&key).cloned().flatten())
+    }
+
+    fn get_resource_group(&self, typ: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        if let Some(group) = self.resource_groups.read().get(typ) {
+            return Ok(group.clone());
+        }
+        let group = block_on(self.resolver.get_resource_group(typ.clone()))
+            .map_err(AsyncResolverError::Backend)?;
+        self.resource_groups
+            .write()
+            .insert(typ.clone(), group.clone());
+        Ok(group)
+    }
+
+    fn prefetch_resources(&self, keys: &[(AccountAddress, StructTag)]) -> Result<(), Self::Error> {
+        block_on(AsyncResolverAdapter::prefetch_resources(
+            self,
+            keys.iter().cloned(),
+        ))
+    }
+}
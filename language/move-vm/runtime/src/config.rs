@@ -0,0 +1,71 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Configuration of the limits and checks the VM enforces at runtime.
+
+/// Limits and checks of a `MoveVM` (see `MoveVM::new_with_config`). The default configuration
+/// is the one of `MoveVM::new`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VMConfig {
+    /// Maximum nesting depth of a value, when computing the layout of its type
+    pub max_value_nest_depth: usize,
+    /// Maximum number of nodes in the layout of a type, which may be exponential in the size of
+    /// the type for nested generic structs, or `None` for no limit
+    pub max_type_layout_nodes: Option<usize>,
+    /// Maximum number of active function calls
+    pub max_call_stack_size: usize,
+    /// Maximum number of values on the operand stack
+    pub max_operand_stack_size: usize,
+    /// Maximum number of nodes in the type arguments of a generic function or struct
+    /// instantiated at runtime, or `None` for no limit
+    pub max_type_instantiation_size: Option<usize>,
+    /// Whether to check at runtime invariants the bytecode verifier already guarantees, e.g.
+    /// that functions leave exactly their return values on the operand stack
+    pub paranoid_checks: bool,
+    /// Maximum number of verified scripts kept in the code cache, shared by all the sessions of
+    /// the VM. The least recently used scripts are evicted first.
+    pub max_script_cache_size: usize,
+    /// Maximum number of struct instantiations whose type tag and layout are kept in the type
+    /// cache. The least recently used ones are evicted first.
+    pub max_type_cache_size: usize,
+    /// Maximum number of instructions executed by a call into the VM, independently of gas, e.g.,
+    /// to bound unmetered executions such as simulations or view functions (`None` for no limit)
+    pub max_instructions: Option<u64>,
+    /// Maximum number of native functions called by a call into the VM, independently of gas
+    /// (`None` for no limit)
+    pub max_native_calls: Option<u64>,
+    /// Maximum size of a resource written by a session, once serialized, checked when the session
+    /// finishes (`None` for no limit)
+    pub max_resource_size: Option<usize>,
+    /// Whether to check the arguments of scripts and entry functions (unless visibility is
+    /// bypassed) are transaction arguments: signers and `&signer`, primitive values, vectors of
+    /// them, `std::string::String` (which must be valid UTF-8), `std::option::Option` of them, and
+    /// the structs with copy and drop of the module of the function whose fields are all such
+    /// values. Other values, e.g., resources or structs of other modules, whose invariants only
+    /// their module can maintain, must not be created from bytes supplied by users.
+    pub check_entry_argument_types: bool,
+    /// Whether to ask storage to prefetch, in one batch, the resources a function is expected to
+    /// read when it is called: those of the types its code accesses in global storage, at the
+    /// addresses and signers passed to it (see `ResourceResolver::prefetch_resources`)
+    pub prefetch_resources: bool,
+}
+
+impl Default for VMConfig {
+    fn default() -> Self {
+        Self {
+            max_value_nest_depth: 128,
+            max_type_layout_nodes: None,
+            max_call_stack_size: 1024,
+            max_operand_stack_size: 1024,
+            max_type_instantiation_size: None,
+            paranoid_checks: false,
+            max_script_cache_size: 1024,
+            max_type_cache_size: 16 * 1024,
+            max_instructions: None,
+            max_native_calls: None,
+            max_resource_size: None,
+            check_entry_argument_types: false,
+            prefetch_resources: false,
+        }
+    }
+}
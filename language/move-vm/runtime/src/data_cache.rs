@@ -7,9 +7,10 @@ use crate::loader::Loader;
 use move_binary_format::errors::*;
 use move_core_types::{
     account_address::AccountAddress,
-    effects::{AccountChangeSet, ChangeSet, Event},
+    effects::{AccountChangeSet, ChangeSet, Event, Op, ReadWriteSet, ResourceGroup},
+    gas_schedule::{AbstractMemorySize, GasAlgebra, GasCarrier},
     identifier::Identifier,
-    language_storage::{ModuleId, TypeTag},
+    language_storage::{ModuleId, StructTag, TypeTag},
     resolver::MoveResolver,
     value::MoveTypeLayout,
     vm_status::StatusCode,
@@ -17,13 +18,24 @@ use move_core_types::{
 use move_vm_types::{
     data_store::DataStore,
     loaded_data::runtime_types::Type,
-    values::{GlobalValue, GlobalValueEffect, Value},
+    values::{GlobalValue, Value},
+};
+use std::{
+    cell::RefCell,
+    collections::{
+        btree_map::{self, BTreeMap},
+        BTreeSet,
+    },
+    fmt::Debug,
 };
-use std::collections::btree_map::BTreeMap;
 
 pub struct AccountDataCache {
-    data_map: BTreeMap<Type, (MoveTypeLayout, GlobalValue)>,
-    module_map: BTreeMap<Identifier, Vec<u8>>,
+    // resources along with the group they are a member of, if any
+    data_map: BTreeMap<Type, (MoveTypeLayout, GlobalValue, Option<StructTag>)>,
+    // modules published or removed, as the operations to apply to storage
+    module_map: BTreeMap<Identifier, Op<Vec<u8>>>,
+    // resource groups as loaded from remote storage (`None` if they do not exist)
+    group_map: BTreeMap<StructTag, Option<ResourceGroup>>,
 }
 
 impl AccountDataCache {
@@ -31,8 +43,36 @@ impl AccountDataCache {
         Self {
             data_map: BTreeMap::new(),
             module_map: BTreeMap::new(),
+            group_map: BTreeMap::new(),
         }
     }
+
+    fn copy_value(&self) -> PartialVMResult<Self> {
+        let data_map = self
+            .data_map
+            .iter()
+            .map(|(ty, (layout, gv, group))| {
+                Ok((
+                    ty.clone(),
+                    (layout.clone(), gv.copy_value()?, group.clone()),
+                ))
+            })
+            .collect::<PartialVMResult<_>>()?;
+        Ok(Self {
+            data_map,
+            module_map: self.module_map.clone(),
+            group_map: self.group_map.clone(),
+        })
+    }
+}
+
+/// The resources, modules and events of a `TransactionDataCache` at some point, which it can be
+/// restored to. What was read from remote storage is not part of it, since restoring the cache
+/// does not undo reads.
+pub(crate) struct DataCacheSnapshot {
+    account_map: BTreeMap<AccountAddress, AccountDataCache>,
+    // events are only ever appended, so the number emitted is enough to restore them
+    num_events: usize,
 }
 
 /// Transaction data cache. Keep updates within a transaction so they can all be published at
@@ -53,6 +93,18 @@ pub(crate) struct TransactionDataCache<'r, 'l, S> {
     loader: &'l Loader,
     account_map: BTreeMap<AccountAddress, AccountDataCache>,
     event_data: Vec<(Vec<u8>, u64, Type, MoveTypeLayout, Value)>,
+    // events serialized when emitted, in the order of `event_data`
+    event_blobs: Vec<Vec<u8>>,
+    // layouts of the types of the events emitted, computed on the first event of each type
+    event_layouts: BTreeMap<Type, MoveTypeLayout>,
+    // resources loaded from remote storage
+    resource_reads: BTreeSet<(AccountAddress, StructTag)>,
+    // modules loaded from remote storage. Modules are loaded through a shared reference, hence the
+    // `RefCell`.
+    module_reads: RefCell<BTreeSet<ModuleId>>,
+    // modules the code executed depends on, which have been charged for. They are read as well,
+    // even when the loader serves them from the code cache.
+    used_modules: BTreeSet<ModuleId>,
 }
 
 impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
@@ -64,6 +116,11 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
             loader,
             account_map: BTreeMap::new(),
             event_data: vec![],
+            event_blobs: vec![],
+            event_layouts: BTreeMap::new(),
+            resource_reads: BTreeSet::new(),
+            module_reads: RefCell::new(BTreeSet::new()),
+            used_modules: BTreeSet::new(),
         }
     }
 
@@ -72,59 +129,190 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
     ///
     /// Gives all proper guarantees on lifetime of global data as well.
     pub(crate) fn into_effects(self) -> PartialVMResult<(ChangeSet, Vec<Event>)> {
+        let (change_set, events, _) = self.into_effects_with_read_write_set()?;
+        Ok((change_set, events))
+    }
+
+    /// Same as `into_effects`, but also returns the resources and modules read and written.
+    pub(crate) fn into_effects_with_read_write_set(
+        self,
+    ) -> PartialVMResult<(ChangeSet, Vec<Event>, ReadWriteSet)> {
+        let mut module_reads = self.module_reads.into_inner();
+        module_reads.extend(self.used_modules);
+        let mut read_write_set = ReadWriteSet {
+            resource_reads: self.resource_reads,
+            module_reads,
+            ..ReadWriteSet::default()
+        };
+        let max_resource_size = self.loader.vm_config().max_resource_size;
         let mut change_set = ChangeSet::new();
         for (addr, account_data_cache) in self.account_map.into_iter() {
             let mut modules = BTreeMap::new();
-            for (module_name, module_blob) in account_data_cache.module_map {
-                modules.insert(module_name, Some(module_blob));
+            for (module_name, op) in account_data_cache.module_map {
+                read_write_set
+                    .module_writes
+                    .insert(ModuleId::new(addr, module_name.clone()));
+                modules.insert(module_name, op);
             }
 
             let mut resources = BTreeMap::new();
-            for (ty, (layout, gv)) in account_data_cache.data_map {
-                match gv.into_effect()? {
-                    GlobalValueEffect::None => (),
-                    GlobalValueEffect::Deleted => {
-                        let struct_tag = match self.loader.type_to_type_tag(&ty)? {
-                            TypeTag::Struct(struct_tag) => struct_tag,
-                            _ => return Err(PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR)),
-                        };
-                        resources.insert(struct_tag, None);
+            let mut group_changes = BTreeMap::new();
+            for (ty, (layout, gv, group)) in account_data_cache.data_map {
+                let serialize = |val: Value| {
+                    let blob = val
+                        .simple_serialize(&layout)
+                        .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
+                    match max_resource_size {
+                        Some(max_size) if blob.len() > max_size => Err(PartialVMError::new(
+                            StatusCode::RESOURCE_SIZE_LIMIT_EXCEEDED,
+                        )
+                        .with_message(format!(
+                            "resource of {} bytes written at {}, while the limit is {} bytes",
+                            blob.len(),
+                            addr,
+                            max_size
+                        ))),
+                        _ => Ok(blob),
                     }
-                    GlobalValueEffect::Changed(val) => {
-                        let struct_tag = match self.loader.type_to_type_tag(&ty)? {
-                            TypeTag::Struct(struct_tag) => struct_tag,
-                            _ => return Err(PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR)),
-                        };
-                        let resource_blob = val
-                            .simple_serialize(&layout)
-                            .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
-                        resources.insert(struct_tag, Some(resource_blob));
+                };
+                let op = match gv.into_effect()? {
+                    None => continue,
+                    Some(Op::New(val)) => Op::New(serialize(val)?),
+                    Some(Op::Modify(val)) => Op::Modify(serialize(val)?),
+                    Some(Op::Delete) => Op::Delete,
+                };
+                let struct_tag = match self.loader.type_to_type_tag(&ty)? {
+                    TypeTag::Struct(struct_tag) => struct_tag,
+                    _ => return Err(PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR)),
+                };
+                match op {
+                    Op::Delete => &mut read_write_set.resource_deletes,
+                    Op::New(_) | Op::Modify(_) => &mut read_write_set.resource_writes,
+                }
+                .insert((addr, struct_tag.clone()));
+                match group {
+                    Some(group_tag) => {
+                        group_changes
+                            .entry(group_tag)
+                            .or_insert_with(BTreeMap::new)
+                            .insert(struct_tag, op.ok());
+                    }
+                    None => {
+                        resources.insert(struct_tag, op);
                     }
                 }
             }
-            change_set.publish_or_overwrite_account_change_set(
-                addr,
-                AccountChangeSet::from_modules_resources(modules, resources),
-            );
+
+            let mut account_change_set =
+                AccountChangeSet::from_modules_resources(modules, resources);
+            let mut group_map = account_data_cache.group_map;
+            for (group_tag, changes) in group_changes {
+                let stored = group_map.remove(&group_tag).flatten();
+                account_change_set
+                    .merge_resource_group(group_tag, stored, changes)
+                    .map_err(|err| {
+                        PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
+                            .with_message(format!("Failed to merge resource group: {}", err))
+                    })?;
+            }
+            change_set.publish_or_overwrite_account_change_set(addr, account_change_set);
         }
 
         let mut events = vec![];
-        for (guid, seq_num, ty, ty_layout, val) in self.event_data {
-            let ty_tag = self.loader.type_to_type_tag(&ty)?;
-            let blob = val
-                .simple_serialize(&ty_layout)
-                .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
-            events.push((guid, seq_num, ty_tag, blob))
+        let mut ty_tags = BTreeMap::new();
+        for ((guid, seq_num, ty, _, _), blob) in self.event_data.into_iter().zip(self.event_blobs) {
+            if !ty_tags.contains_key(&ty) {
+                let ty_tag = self.loader.type_to_type_tag(&ty)?;
+                ty_tags.insert(ty.clone(), ty_tag);
+            }
+            events.push((guid, seq_num, ty_tags[&ty].clone(), blob))
         }
 
-        Ok((change_set, events))
+        Ok((change_set, events, read_write_set))
+    }
+
+    /// Takes a snapshot of the resources, modules and events of the cache. There must not be
+    /// references to global values outstanding, i.e., no function must be executing.
+    pub(crate) fn snapshot(&self) -> PartialVMResult<DataCacheSnapshot> {
+        let account_map = self
+            .account_map
+            .iter()
+            .map(|(addr, account_cache)| Ok((*addr, account_cache.copy_value()?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(DataCacheSnapshot {
+            account_map,
+            num_events: self.event_data.len(),
+        })
+    }
+
+    /// Restores the resources, modules and events of the cache to the given snapshot, taken from
+    /// this cache. Returns the modules published or removed since the snapshot was taken.
+    pub(crate) fn restore(&mut self, snapshot: DataCacheSnapshot) -> Vec<ModuleId> {
+        let mut changed_modules = BTreeSet::new();
+        for (addr, account_cache) in self.account_map.iter() {
+            for (name, op) in account_cache.module_map.iter() {
+                let before = snapshot
+                    .account_map
+                    .get(addr)
+                    .and_then(|account_cache| account_cache.module_map.get(name));
+                if before != Some(op) {
+                    changed_modules.insert(ModuleId::new(*addr, name.clone()));
+                }
+            }
+        }
+        // modules published before the snapshot and removed since then are no longer in the map
+        for (addr, account_cache) in snapshot.account_map.iter() {
+            for name in account_cache.module_map.keys() {
+                let now = self
+                    .account_map
+                    .get(addr)
+                    .and_then(|account_cache| account_cache.module_map.get(name));
+                if now.is_none() {
+                    changed_modules.insert(ModuleId::new(*addr, name.clone()));
+                }
+            }
+        }
+
+        self.account_map = snapshot.account_map;
+        self.event_data.truncate(snapshot.num_events);
+        self.event_blobs.truncate(snapshot.num_events);
+        changed_modules.into_iter().collect()
+    }
+
+    fn load_module_impl(&self, module_id: &ModuleId) -> VMResult<Vec<u8>> {
+        if let Some(account_cache) = self.account_map.get(module_id.address()) {
+            match account_cache.module_map.get(module_id.name()) {
+                Some(Op::New(blob) | Op::Modify(blob)) => return Ok(blob.clone()),
+                Some(Op::Delete) => {
+                    return Err(PartialVMError::new(StatusCode::LINKER_ERROR)
+                        .with_message(format!("{:?} was removed in this session", module_id))
+                        .finish(Location::Undefined))
+                }
+                None => (),
+            }
+        }
+        self.module_reads.borrow_mut().insert(module_id.clone());
+        match self.remote.get_module(module_id) {
+            Ok(Some(bytes)) => Ok(bytes),
+            Ok(None) => Err(PartialVMError::new(StatusCode::LINKER_ERROR)
+                .with_message(format!("Cannot find {:?} in data cache", module_id))
+                .finish(Location::Undefined)),
+            Err(err) => {
+                let msg = format!("Unexpected storage error: {:?}", err);
+                Err(
+                    PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
+                        .with_message(msg)
+                        .finish(Location::Undefined),
+                )
+            }
+        }
     }
 
     pub(crate) fn num_mutated_accounts(&self, sender: &AccountAddress) -> u64 {
         // The sender's account will always be mutated.
         let mut total_mutated_accounts: u64 = 1;
         for (addr, entry) in self.account_map.iter() {
-            if addr != sender && entry.data_map.values().any(|(_, v)| v.is_mutated()) {
+            if addr != sender && entry.data_map.values().any(|(_, v, _)| v.is_mutated()) {
                 total_mutated_accounts += 1;
             }
         }
@@ -144,6 +332,11 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
     }
 }
 
+fn storage_error(err: impl Debug) -> PartialVMError {
+    let msg = format!("Unexpected storage error: {:?}", err);
+    PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR).with_message(msg)
+}
+
 // `DataStore` implementation for the `TransactionDataCache`
 impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
     // Retrieve data from the local cache or loads it from the remote cache into the local cache.
@@ -153,11 +346,12 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
         &mut self,
         addr: AccountAddress,
         ty: &Type,
-    ) -> PartialVMResult<&mut GlobalValue> {
+    ) -> PartialVMResult<(&mut GlobalValue, Option<AbstractMemorySize<GasCarrier>>)> {
         let account_cache = Self::get_mut_or_insert_with(&mut self.account_map, &addr, || {
             (addr, AccountDataCache::new())
         });
 
+        let mut loaded_group_size = None;
         if !account_cache.data_map.contains_key(ty) {
             let ty_tag = match self.loader.type_to_type_tag(ty)? {
                 TypeTag::Struct(s_tag) => s_tag,
@@ -168,8 +362,51 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
                 }
             };
             let ty_layout = self.loader.type_to_type_layout(ty)?;
+            self.resource_reads.insert((addr, ty_tag.clone()));
 
-            let gv = match self.remote.get_resource(&addr, &ty_tag) {
+            let group = self
+                .remote
+                .get_resource_group(&ty_tag)
+                .map_err(storage_error)?;
+            let blob_opt = match &group {
+                None => self.remote.get_resource(&addr, &ty_tag),
+                Some(group_tag) => {
+                    if !account_cache.group_map.contains_key(group_tag) {
+                        // the whole group is loaded along with its first member accessed
+                        let group_blob = self
+                            .remote
+                            .get_resource(&addr, group_tag)
+                            .map_err(storage_error)?;
+                        loaded_group_size = Some(AbstractMemorySize::new(
+                            group_blob
+                                .as_ref()
+                                .map_or(0, |blob| blob.len() as GasCarrier),
+                        ));
+                        let resource_group = match group_blob {
+                            Some(blob) => {
+                                Some(ResourceGroup::deserialize(&blob).map_err(|_| {
+                                    let msg = format!(
+                                        "Failed to deserialize resource group {} at {}!",
+                                        group_tag, addr
+                                    );
+                                    PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_RESOURCE)
+                                        .with_message(msg)
+                                })?)
+                            }
+                            None => None,
+                        };
+                        account_cache
+                            .group_map
+                            .insert(group_tag.clone(), resource_group);
+                    }
+                    Ok(account_cache.group_map[group_tag]
+                        .as_ref()
+                        .and_then(|resource_group| resource_group.get(&ty_tag))
+                        .map(|blob| blob.to_vec()))
+                }
+            };
+
+            let gv = match blob_opt {
                 Ok(Some(blob)) => {
                     let val = match Value::simple_deserialize(&blob, &ty_layout) {
                         Some(val) => val,
@@ -186,64 +423,121 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
                     GlobalValue::cached(val)?
                 }
                 Ok(None) => GlobalValue::none(),
-                Err(err) => {
-                    let msg = format!("Unexpected storage error: {:?}", err);
-                    return Err(
-                        PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
-                            .with_message(msg),
-                    );
-                }
+                Err(err) => return Err(storage_error(err)),
             };
 
-            account_cache.data_map.insert(ty.clone(), (ty_layout, gv));
+            account_cache
+                .data_map
+                .insert(ty.clone(), (ty_layout, gv, group));
         }
 
-        Ok(account_cache
+        let gv = account_cache
             .data_map
             .get_mut(ty)
-            .map(|(_ty_layout, gv)| gv)
-            .expect("global value must exist"))
+            .map(|(_ty_layout, gv, _group)| gv)
+            .expect("global value must exist");
+        Ok((gv, loaded_group_size))
     }
 
-    fn load_module(&self, module_id: &ModuleId) -> VMResult<Vec<u8>> {
-        if let Some(account_cache) = self.account_map.get(module_id.address()) {
-            if let Some(blob) = account_cache.module_map.get(module_id.name()) {
-                return Ok(blob.clone());
+    fn prefetch_resources(&mut self, resources: &[(AccountAddress, Type)]) -> PartialVMResult<()> {
+        let mut keys = vec![];
+        for (addr, ty) in resources {
+            // resources already in the cache are not read from remote storage again
+            let cached = self.account_map.get(addr).map_or(false, |account_cache| {
+                account_cache.data_map.contains_key(ty)
+            });
+            if cached {
+                continue;
+            }
+            match self.loader.type_to_type_tag(ty)? {
+                TypeTag::Struct(struct_tag) => keys.push((*addr, struct_tag)),
+                _ => return Err(PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR)),
             }
         }
-        match self.remote.get_module(module_id) {
-            Ok(Some(bytes)) => Ok(bytes),
-            Ok(None) => Err(PartialVMError::new(StatusCode::LINKER_ERROR)
-                .with_message(format!("Cannot find {:?} in data cache", module_id))
-                .finish(Location::Undefined)),
-            Err(err) => {
-                let msg = format!("Unexpected storage error: {:?}", err);
-                Err(
-                    PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
-                        .with_message(msg)
-                        .finish(Location::Undefined),
-                )
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.remote.prefetch_resources(&keys).map_err(storage_error)
+    }
+
+    fn load_module(&self, module_id: &ModuleId) -> VMResult<Vec<u8>> {
+        self.load_module_impl(module_id)
+    }
+
+    fn is_module_used(&self, module_id: &ModuleId) -> bool {
+        self.used_modules.contains(module_id)
+    }
+
+    fn record_module_use(&mut self, module_id: &ModuleId) {
+        self.used_modules.insert(module_id.clone());
+    }
+
+    fn publish_module(
+        &mut self,
+        module_id: &ModuleId,
+        blob: Vec<u8>,
+        is_republishing: bool,
+    ) -> VMResult<()> {
+        let account_cache =
+            Self::get_mut_or_insert_with(&mut self.account_map, module_id.address(), || {
+                (*module_id.address(), AccountDataCache::new())
+            });
+
+        // a module published earlier in this session is still new when published again, and a
+        // module removed earlier in this session still exists in storage
+        match account_cache.module_map.entry(module_id.name().to_owned()) {
+            btree_map::Entry::Occupied(mut entry) => {
+                let op = match entry.get() {
+                    Op::New(_) => Op::New(blob),
+                    Op::Modify(_) | Op::Delete => Op::Modify(blob),
+                };
+                entry.insert(op);
+            }
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(if is_republishing {
+                    Op::Modify(blob)
+                } else {
+                    Op::New(blob)
+                });
             }
         }
+
+        Ok(())
     }
 
-    fn publish_module(&mut self, module_id: &ModuleId, blob: Vec<u8>) -> VMResult<()> {
+    fn unpublish_module(&mut self, module_id: &ModuleId) -> VMResult<()> {
         let account_cache =
             Self::get_mut_or_insert_with(&mut self.account_map, module_id.address(), || {
                 (*module_id.address(), AccountDataCache::new())
             });
 
-        account_cache
-            .module_map
-            .insert(module_id.name().to_owned(), blob);
+        // a module published earlier in this session is not in storage, so it is just forgotten
+        match account_cache.module_map.entry(module_id.name().to_owned()) {
+            btree_map::Entry::Occupied(mut entry) => {
+                match std::mem::replace(entry.get_mut(), Op::Delete) {
+                    Op::New(_) => {
+                        entry.remove();
+                    }
+                    Op::Modify(_) => (),
+                    Op::Delete => {
+                        return Err(PartialVMError::new(StatusCode::LINKER_ERROR)
+                            .with_message(format!("{:?} was removed in this session", module_id))
+                            .finish(Location::Undefined))
+                    }
+                }
+            }
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(Op::Delete);
+            }
+        }
 
         Ok(())
     }
 
     fn exists_module(&self, module_id: &ModuleId) -> VMResult<bool> {
         if let Some(account_cache) = self.account_map.get(module_id.address()) {
-            if account_cache.module_map.contains_key(module_id.name()) {
-                return Ok(true);
+            if let Some(op) = account_cache.module_map.get(module_id.name()) {
+                return Ok(!matches!(op, Op::Delete));
             }
         }
         Ok(self
@@ -261,9 +555,22 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
         seq_num: u64,
         ty: Type,
         val: Value,
-    ) -> PartialVMResult<()> {
-        let ty_layout = self.loader.type_to_type_layout(&ty)?;
-        Ok(self.event_data.push((guid, seq_num, ty, ty_layout, val)))
+    ) -> PartialVMResult<u64> {
+        let ty_layout = match self.event_layouts.get(&ty) {
+            Some(ty_layout) => ty_layout.clone(),
+            None => {
+                let ty_layout = self.loader.type_to_type_layout(&ty)?;
+                self.event_layouts.insert(ty.clone(), ty_layout.clone());
+                ty_layout
+            }
+        };
+        let blob = val
+            .simple_serialize(&ty_layout)
+            .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
+        let size = blob.len() as u64;
+        self.event_data.push((guid, seq_num, ty, ty_layout, val));
+        self.event_blobs.push(blob);
+        Ok(size)
     }
 
     fn events(&self) -> &Vec<(Vec<u8>, u64, Type, MoveTypeLayout, Value)> {
@@ -0,0 +1,180 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A debugger built on top of the `Tracer` hook, for front ends such as a DAP server or a command
+//! line to drive.
+//!
+//! A `Debugger` is installed on a `Session` as its tracer. It stops execution before instructions
+//! with a breakpoint, or after stepping, and hands over to its `DebuggerFrontend`, which can
+//! inspect the frame of the function stopped in, change breakpoints, and tell how to resume.
+
+use crate::tracer::{FrameInfo, Tracer};
+use move_binary_format::file_format::Bytecode;
+use move_core_types::language_storage::ModuleId;
+use move_vm_types::values::Value;
+use std::collections::BTreeSet;
+
+/// An instruction execution stops at
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Breakpoint {
+    /// Module declaring the function (`None` for a script)
+    pub module_id: Option<ModuleId>,
+    /// Name of the function
+    pub function_name: String,
+    /// Offset of the instruction in the function
+    pub pc: u16,
+}
+
+impl Breakpoint {
+    /// A breakpoint at the first instruction of a function, i.e., when the function is called
+    pub fn function(module_id: Option<ModuleId>, function_name: impl Into<String>) -> Self {
+        Self::instruction(module_id, function_name, 0)
+    }
+
+    /// A breakpoint at the instruction at the given offset of a function
+    pub fn instruction(
+        module_id: Option<ModuleId>,
+        function_name: impl Into<String>,
+        pc: u16,
+    ) -> Self {
+        Self {
+            module_id,
+            function_name: function_name.into(),
+            pc,
+        }
+    }
+
+    fn is_at(&self, frame: &FrameInfo, pc: u16) -> bool {
+        self.pc == pc
+            && self.function_name == frame.function_name
+            && self.module_id.as_ref() == frame.module_id
+    }
+}
+
+/// Why execution stopped
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StopReason {
+    /// The instruction has a breakpoint
+    Breakpoint,
+    /// A step requested by the front end completed
+    Step,
+}
+
+/// How to resume execution once stopped
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResumeCommand {
+    /// Run until the next breakpoint
+    Continue,
+    /// Stop at the next instruction, which may be in a function called by the current one
+    StepIn,
+    /// Stop at the next instruction of the current function, or of its caller if it returns
+    StepOver,
+    /// Stop at the next instruction of the caller of the current function
+    StepOut,
+}
+
+/// The state of execution when stopped, before the instruction is executed
+pub struct StoppedState<'a> {
+    pub reason: StopReason,
+    /// Frame of the function stopped in, including its locals
+    pub frame: &'a FrameInfo<'a>,
+    /// Offset of the instruction in the function
+    pub pc: u16,
+    pub instruction: &'a Bytecode,
+    /// Operand stack, whose top is the last value
+    pub operand_stack: &'a [Value],
+}
+
+/// The front end driving a `Debugger`
+pub trait DebuggerFrontend {
+    /// Called each time execution stops. The front end may inspect the state and change the
+    /// breakpoints, and returns how to resume. Execution does not resume until this returns, so
+    /// a front end may block, e.g., waiting for the next request of a client.
+    fn on_stop(
+        &mut self,
+        state: &StoppedState,
+        breakpoints: &mut BTreeSet<Breakpoint>,
+    ) -> ResumeCommand;
+}
+
+/// A `Tracer` stopping execution at breakpoints and after steps, for a `DebuggerFrontend` to
+/// inspect it
+pub struct Debugger<F> {
+    frontend: F,
+    breakpoints: BTreeSet<Breakpoint>,
+    // the step in progress, along with the depth of the frame it started from
+    step: Option<(ResumeCommand, usize)>,
+}
+
+impl<F: DebuggerFrontend> Debugger<F> {
+    /// Create a debugger. If `stop_on_entry` is set, execution stops at the first instruction,
+    /// otherwise at the first breakpoint.
+    pub fn new(frontend: F, stop_on_entry: bool) -> Self {
+        Self {
+            frontend,
+            breakpoints: BTreeSet::new(),
+            step: if stop_on_entry {
+                Some((ResumeCommand::StepIn, 0))
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.insert(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) {
+        self.breakpoints.remove(breakpoint);
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<Breakpoint> {
+        &self.breakpoints
+    }
+
+    pub fn frontend(&self) -> &F {
+        &self.frontend
+    }
+
+    pub fn into_frontend(self) -> F {
+        self.frontend
+    }
+}
+
+impl<F: DebuggerFrontend> Tracer for Debugger<F> {
+    fn on_instruction(
+        &mut self,
+        frame: &FrameInfo,
+        pc: u16,
+        instruction: &Bytecode,
+        operand_stack: &[Value],
+    ) {
+        let reason = if self.breakpoints.iter().any(|bp| bp.is_at(frame, pc)) {
+            StopReason::Breakpoint
+        } else {
+            let step_completed = match self.step {
+                None | Some((ResumeCommand::Continue, _)) => false,
+                Some((ResumeCommand::StepIn, _)) => true,
+                Some((ResumeCommand::StepOver, depth)) => frame.depth <= depth,
+                Some((ResumeCommand::StepOut, depth)) => frame.depth < depth,
+            };
+            if !step_completed {
+                return;
+            }
+            StopReason::Step
+        };
+
+        let state = StoppedState {
+            reason,
+            frame,
+            pc,
+            instruction,
+            operand_stack,
+        };
+        self.step = match self.frontend.on_stop(&state, &mut self.breakpoints) {
+            ResumeCommand::Continue => None,
+            command => Some((command, frame.depth)),
+        };
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Profiling of where gas is consumed during execution.
+//!
+//! Profiling is enabled on a `Session` (see `Session::enable_gas_profiling`), and the resulting
+//! `GasProfile` is returned along with the effects of the session. Gas is measured with
+//! `GasMeter::gas_left`, so nothing is recorded by a meter which does not charge.
+
+use crate::loader::Function;
+use move_binary_format::file_format_common::Opcodes;
+use move_core_types::gas_schedule::{GasAlgebra, GasCarrier, InternalGasUnits};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The gas consumed during execution, in internal gas units, broken down in several ways
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GasProfile {
+    /// Gas consumed by the instructions of each function (e.g., `0x1::M::f`), not including the
+    /// functions and natives it calls
+    pub functions: BTreeMap<String, GasCarrier>,
+    /// Gas consumed by each kind of instruction, over all functions
+    pub instructions: BTreeMap<Opcodes, GasCarrier>,
+    /// Gas consumed by each native function
+    pub natives: BTreeMap<String, GasCarrier>,
+}
+
+impl GasProfile {
+    /// Total gas consumed by the execution profiled
+    pub fn total(&self) -> GasCarrier {
+        self.functions.values().sum::<GasCarrier>() + self.natives.values().sum::<GasCarrier>()
+    }
+}
+
+// What the gas consumed is attributed to
+pub(crate) enum Charge {
+    Instruction(Arc<Function>, Opcodes),
+    Native(Arc<Function>),
+}
+
+// Records the gas left each time the interpreter starts an instruction or calls a native, and
+// attributes the gas consumed since the previous record to the previous instruction or native.
+pub(crate) struct GasProfiler {
+    profile: GasProfile,
+    current: Option<Charge>,
+    gas_left: InternalGasUnits<GasCarrier>,
+}
+
+impl GasProfiler {
+    pub(crate) fn new(gas_left: InternalGasUnits<GasCarrier>) -> Self {
+        Self {
+            profile: GasProfile::default(),
+            current: None,
+            gas_left,
+        }
+    }
+
+    // Attributes the gas consumed so far, and starts measuring the gas consumed by `next`
+    pub(crate) fn record(&mut self, gas_left: InternalGasUnits<GasCarrier>, next: Charge) {
+        self.attribute(gas_left);
+        self.current = Some(next);
+    }
+
+    // Attributes the gas consumed so far, and adds the profile to `profile`
+    pub(crate) fn finish(
+        mut self,
+        gas_left: InternalGasUnits<GasCarrier>,
+        profile: &mut GasProfile,
+    ) {
+        self.attribute(gas_left);
+        for (function, gas) in self.profile.functions {
+            *profile.functions.entry(function).or_default() += gas;
+        }
+        for (opcode, gas) in self.profile.instructions {
+            *profile.instructions.entry(opcode).or_default() += gas;
+        }
+        for (native, gas) in self.profile.natives {
+            *profile.natives.entry(native).or_default() += gas;
+        }
+    }
+
+    fn attribute(&mut self, gas_left: InternalGasUnits<GasCarrier>) {
+        let consumed = self.gas_left.get().saturating_sub(gas_left.get());
+        self.gas_left = gas_left;
+        match &self.current {
+            None => (),
+            Some(Charge::Instruction(function, opcode)) => {
+                *self
+                    .profile
+                    .functions
+                    .entry(function.pretty_string())
+                    .or_default() += consumed;
+                *self.profile.instructions.entry(*opcode).or_default() += consumed;
+            }
+            Some(Charge::Native(function)) => {
+                *self
+                    .profile
+                    .natives
+                    .entry(function.pretty_string())
+                    .or_default() += consumed;
+            }
+        }
+    }
+}
@@ -3,15 +3,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    loader::{Function, Loader, Resolver},
+    config::VMConfig,
+    gas_profile::{Charge, GasProfile, GasProfiler},
+    loader::{Function, Loader, Resolver, ResourceAccess},
     native_functions::NativeContext,
+    replay::NativeRecording,
     trace,
+    tracer::{FrameInfo, Tracer},
+    watchdog::ScheduledWatchdog,
 };
 use fail::fail_point;
 use move_binary_format::{
     errors::*,
-    file_format::{Bytecode, FunctionHandleIndex, FunctionInstantiationIndex},
-    file_format_common::Opcodes,
+    file_format::{Bytecode, FunctionHandleIndex, FunctionInstantiationIndex, SignatureToken},
+    file_format_common::{instruction_opcode, Opcodes},
 };
 use move_core_types::{
     account_address::AccountAddress,
@@ -20,17 +25,23 @@ use move_core_types::{
 };
 use move_vm_types::{
     data_store::DataStore,
-    gas_schedule::GasStatus,
+    gas_schedule::GasMeter,
     loaded_data::runtime_types::Type,
     values::{
-        self, GlobalValue, IntegerValue, Locals, Reference, Struct, StructRef, VMValueCast, Value,
-        Vector, VectorRef,
+        self, GlobalValue, IntegerValue, Locals, LocalsPool, Reference, SignerRef, Struct,
+        StructRef, VMValueCast, Value, Vector, VectorRef,
     },
 };
 
 use crate::native_extensions::NativeContextExtensions;
-use std::{cmp::min, collections::VecDeque, fmt::Write, mem, sync::Arc};
-use tracing::error;
+use std::{
+    cmp::min,
+    collections::{BTreeSet, VecDeque},
+    fmt::Write,
+    mem,
+    sync::Arc,
+};
+use tracing::{error, warn};
 
 macro_rules! debug_write {
     ($($toks: tt)*) => {
@@ -66,6 +77,20 @@ pub(crate) struct Interpreter {
     operand_stack: Stack,
     /// The stack of active functions.
     call_stack: CallStack,
+    /// Number of instructions executed so far, reported to the VM metrics.
+    instructions_executed: u64,
+    /// Limit on the number of instructions executed, independent of gas.
+    max_instructions: Option<u64>,
+    /// Number of native functions called so far.
+    native_calls: u64,
+    /// Limit on the number of native functions called, independent of gas.
+    max_native_calls: Option<u64>,
+    /// Where gas is consumed, if profiling is enabled.
+    gas_profiler: Option<GasProfiler>,
+    /// The natives whose calls are recorded or replayed, if any.
+    native_recording: Option<NativeRecording>,
+    /// The allocations of the locals of the frames which returned, reused by the next calls.
+    locals_pool: LocalsPool,
 }
 
 impl Interpreter {
@@ -76,24 +101,51 @@ impl Interpreter {
         ty_args: Vec<Type>,
         args: Vec<Value>,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
+        tracer: Option<&mut dyn Tracer>,
+        watchdog: Option<&mut ScheduledWatchdog>,
+        gas_profile: Option<&mut GasProfile>,
+        mut native_recording: Option<&mut NativeRecording>,
         loader: &Loader,
     ) -> VMResult<Vec<Value>> {
         // We count the intrinsic cost of the transaction here, since that needs to also cover the
         // setup of the function.
-        let mut interp = Self::new();
-        interp.execute(
-            loader, data_store, gas_status, extensions, function, ty_args, args,
-        )
+        let mut interp = Self::new(loader.vm_config());
+        if gas_profile.is_some() {
+            interp.gas_profiler = Some(GasProfiler::new(gas_meter.gas_left()));
+        }
+        interp.native_recording = native_recording.as_deref_mut().map(mem::take);
+        let result = interp.execute(
+            loader, data_store, gas_meter, extensions, tracer, watchdog, function, ty_args, args,
+        );
+        if let (Some(recording), Some(native_recording)) =
+            (interp.native_recording.take(), native_recording)
+        {
+            *native_recording = recording;
+        }
+        loader
+            .metrics()
+            .inc_instructions_executed(interp.instructions_executed);
+        if let (Some(profiler), Some(profile)) = (interp.gas_profiler.take(), gas_profile) {
+            profiler.finish(gas_meter.gas_left(), profile);
+        }
+        result
     }
 
     /// Create a new instance of an `Interpreter` in the context of a transaction with a
     /// given module cache and gas schedule.
-    fn new() -> Self {
+    fn new(vm_config: &VMConfig) -> Self {
         Interpreter {
-            operand_stack: Stack::new(),
-            call_stack: CallStack::new(),
+            operand_stack: Stack::new(vm_config.max_operand_stack_size),
+            call_stack: CallStack::new(vm_config.max_call_stack_size),
+            instructions_executed: 0,
+            max_instructions: vm_config.max_instructions,
+            native_calls: 0,
+            max_native_calls: vm_config.max_native_calls,
+            gas_profiler: None,
+            native_recording: None,
+            locals_pool: LocalsPool::new(),
         }
     }
 
@@ -102,8 +154,10 @@ impl Interpreter {
         &mut self,
         loader: &Loader,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
+        tracer: Option<&mut dyn Tracer>,
+        watchdog: Option<&mut ScheduledWatchdog>,
         function: Arc<Function>,
         ty_args: Vec<Type>,
         args: Vec<Value>,
@@ -111,7 +165,7 @@ impl Interpreter {
         // No unwinding of the call stack and value stack need to be done here -- the context will
         // take care of that.
         self.execute_main(
-            loader, data_store, gas_status, extensions, function, ty_args, args,
+            loader, data_store, gas_meter, extensions, tracer, watchdog, function, ty_args, args,
         )
     }
 
@@ -127,40 +181,58 @@ impl Interpreter {
         &mut self,
         loader: &Loader,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
+        mut tracer: Option<&mut dyn Tracer>,
+        mut watchdog: Option<&mut ScheduledWatchdog>,
         function: Arc<Function>,
         ty_args: Vec<Type>,
         args: Vec<Value>,
     ) -> VMResult<Vec<Value>> {
-        let mut locals = Locals::new(function.local_count());
+        let mut locals = self.locals_pool.new_locals(function.local_count());
         for (i, value) in args.into_iter().enumerate() {
             locals
                 .store_loc(i, value)
                 .map_err(|e| self.set_location(e))?;
         }
 
-        let mut current_frame = Frame::new(function, ty_args, locals);
+        let mut current_frame = Frame::new(function, ty_args, locals, 0);
+        if loader.vm_config().prefetch_resources {
+            current_frame.prefetch_resources(&current_frame.resolver(loader), data_store);
+        }
         loop {
             let resolver = current_frame.resolver(loader);
             let exit_code = current_frame //self
-                .execute_code(&resolver, self, data_store, gas_status)
+                .execute_code(
+                    &resolver,
+                    self,
+                    data_store,
+                    gas_meter,
+                    tracer.as_mut().map(|t| &mut **t as &mut dyn Tracer),
+                    watchdog.as_deref_mut(),
+                )
                 .map_err(|err| self.maybe_core_dump(err, &current_frame))?;
             match exit_code {
                 ExitCode::Return => {
+                    if loader.vm_config().paranoid_checks {
+                        current_frame
+                            .check_stack_balance(&self.operand_stack)
+                            .map_err(|e| set_err_info!(current_frame, e))?;
+                    }
                     if let Some(frame) = self.call_stack.pop() {
-                        current_frame = frame;
+                        let returned = mem::replace(&mut current_frame, frame);
+                        self.locals_pool.release(returned.locals);
                         current_frame.pc += 1; // advance past the Call instruction in the caller
                     } else {
-                        return Ok(mem::take(&mut self.operand_stack.0));
+                        return Ok(mem::take(&mut self.operand_stack.values));
                     }
                 }
                 ExitCode::Call(fh_idx) => {
-                    gas_status
+                    gas_meter
                         .charge_instr_with_size(Opcodes::CALL, AbstractMemorySize::new(1))
                         .map_err(|e| set_err_info!(current_frame, e))?;
                     let func = resolver.function_from_handle(fh_idx);
-                    gas_status
+                    gas_meter
                         .charge_instr_with_size(
                             Opcodes::CALL,
                             AbstractMemorySize::new(func.arg_count() as GasCarrier),
@@ -170,7 +242,7 @@ impl Interpreter {
                         self.call_native(
                             &resolver,
                             data_store,
-                            gas_status,
+                            gas_meter,
                             extensions,
                             func,
                             vec![],
@@ -179,7 +251,7 @@ impl Interpreter {
                         continue;
                     }
                     let frame = self
-                        .make_call_frame(func, vec![])
+                        .make_call_frame(loader, data_store, func, vec![])
                         .map_err(|err| self.maybe_core_dump(err, &current_frame))?;
                     self.call_stack.push(current_frame).map_err(|frame| {
                         let err = PartialVMError::new(StatusCode::CALL_STACK_OVERFLOW);
@@ -190,7 +262,7 @@ impl Interpreter {
                 }
                 ExitCode::CallGeneric(idx) => {
                     let arity = resolver.type_params_count(idx);
-                    gas_status
+                    gas_meter
                         .charge_instr_with_size(
                             Opcodes::CALL_GENERIC,
                             AbstractMemorySize::new((arity + 1) as GasCarrier),
@@ -200,7 +272,7 @@ impl Interpreter {
                         .instantiate_generic_function(idx, current_frame.ty_args())
                         .map_err(|e| set_err_info!(current_frame, e))?;
                     let func = resolver.function_from_instantiation(idx);
-                    gas_status
+                    gas_meter
                         .charge_instr_with_size(
                             Opcodes::CALL_GENERIC,
                             AbstractMemorySize::new(func.arg_count() as GasCarrier),
@@ -208,13 +280,13 @@ impl Interpreter {
                         .map_err(|e| set_err_info!(current_frame, e))?;
                     if func.is_native() {
                         self.call_native(
-                            &resolver, data_store, gas_status, extensions, func, ty_args,
+                            &resolver, data_store, gas_meter, extensions, func, ty_args,
                         )?;
                         current_frame.pc += 1; // advance past the Call instruction in the caller
                         continue;
                     }
                     let frame = self
-                        .make_call_frame(func, ty_args)
+                        .make_call_frame(loader, data_store, func, ty_args)
                         .map_err(|err| self.maybe_core_dump(err, &current_frame))?;
                     self.call_stack.push(current_frame).map_err(|frame| {
                         let err = PartialVMError::new(StatusCode::CALL_STACK_OVERFLOW);
@@ -232,8 +304,14 @@ impl Interpreter {
     ///
     /// Native functions do not push a frame at the moment and as such errors from a native
     /// function are incorrectly attributed to the caller.
-    fn make_call_frame(&mut self, func: Arc<Function>, ty_args: Vec<Type>) -> VMResult<Frame> {
-        let mut locals = Locals::new(func.local_count());
+    fn make_call_frame(
+        &mut self,
+        loader: &Loader,
+        data_store: &mut impl DataStore,
+        func: Arc<Function>,
+        ty_args: Vec<Type>,
+    ) -> VMResult<Frame> {
+        let mut locals = self.locals_pool.new_locals(func.local_count());
         let arg_count = func.arg_count();
         for i in 0..arg_count {
             locals
@@ -243,7 +321,11 @@ impl Interpreter {
                 )
                 .map_err(|e| self.set_location(e))?;
         }
-        Ok(Frame::new(func, ty_args, locals))
+        let frame = Frame::new(func, ty_args, locals, self.operand_stack.values.len());
+        if loader.vm_config().prefetch_resources {
+            frame.prefetch_resources(&frame.resolver(loader), data_store);
+        }
+        Ok(frame)
     }
 
     /// Call a native functions.
@@ -251,7 +333,7 @@ impl Interpreter {
         &mut self,
         resolver: &Resolver,
         data_store: &mut dyn DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
         function: Arc<Function>,
         ty_args: Vec<Type>,
@@ -260,7 +342,7 @@ impl Interpreter {
         self.call_native_impl(
             resolver,
             data_store,
-            gas_status,
+            gas_meter,
             extensions,
             function.clone(),
             ty_args,
@@ -281,7 +363,7 @@ impl Interpreter {
         &mut self,
         resolver: &Resolver,
         data_store: &mut dyn DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
         function: Arc<Function>,
         ty_args: Vec<Type>,
@@ -291,14 +373,61 @@ impl Interpreter {
         for _ in 0..expected_args {
             arguments.push_front(self.operand_stack.pop()?);
         }
-        let mut native_context =
-            NativeContext::new(self, data_store, gas_status, resolver, extensions);
-        let native_function = function.get_native()?;
-        let result = native_function(&mut native_context, ty_args, arguments)?;
-        gas_status.deduct_gas(result.cost)?;
-        let return_values = result
-            .result
-            .map_err(|code| PartialVMError::new(StatusCode::ABORTED).with_sub_status(code))?;
+        self.native_calls += 1;
+        if let Some(max_native_calls) = self.max_native_calls {
+            if self.native_calls > max_native_calls {
+                return Err(PartialVMError::new(StatusCode::EXECUTION_LIMIT_REACHED)
+                    .with_message(format!("more than {} native calls", max_native_calls)));
+            }
+        }
+        if let Some(profiler) = &mut self.gas_profiler {
+            profiler.record(gas_meter.gas_left(), Charge::Native(function.clone()));
+        }
+        // layouts of the values returned, if calls to the native are recorded or replayed
+        let layouts = match &self.native_recording {
+            Some(recording) if recording.covers(&function) => Some(
+                resolver
+                    .loader()
+                    .function_return_types(&function, &ty_args)?
+                    .iter()
+                    .map(|ty| resolver.type_to_type_layout(ty))
+                    .collect::<PartialVMResult<Vec<_>>>()?,
+            ),
+            _ => None,
+        };
+        let replayed = match (&mut self.native_recording, &layouts) {
+            (Some(recording), Some(layouts)) if recording.is_replaying() => {
+                Some(recording.replay_call(&function, layouts)?)
+            }
+            _ => None,
+        };
+        let (result, event_bytes) = match replayed {
+            // replayed natives are not called, but the events they emitted are charged as recorded
+            Some(replayed) => replayed,
+            None => {
+                let mut native_context =
+                    NativeContext::new(self, data_store, gas_meter, resolver, extensions);
+                let native_function = function.get_native()?;
+                let result = native_function(&mut native_context, ty_args, arguments)?;
+                let event_bytes = native_context.event_bytes();
+                if let (Some(recording), Some(layouts)) = (&mut self.native_recording, &layouts) {
+                    recording.record_call(&function, layouts, &result, event_bytes)?;
+                }
+                (result, event_bytes)
+            }
+        };
+        gas_meter.charge_native_function(result.cost)?;
+        if event_bytes > 0 {
+            gas_meter.charge_emit_event(AbstractMemorySize::new(event_bytes))?;
+        }
+        let abort_payload = result.abort_payload;
+        let return_values = result.result.map_err(|code| {
+            let err = PartialVMError::new(StatusCode::ABORTED).with_sub_status(code);
+            match abort_payload {
+                Some(payload) => err.with_abort_payload(payload),
+                None => err,
+            }
+        })?;
         for value in return_values {
             self.operand_stack.push(value)?;
         }
@@ -340,14 +469,21 @@ impl Interpreter {
         self.binop(|lhs, rhs| Ok(Value::bool(f(lhs, rhs)?)))
     }
 
-    /// Load a resource from the data store.
+    /// Load a resource from the data store, charging for the resource group loaded along with it,
+    /// if any.
     fn load_resource<'b>(
+        gas_meter: &mut impl GasMeter,
         data_store: &'b mut impl DataStore,
         addr: AccountAddress,
         ty: &Type,
     ) -> PartialVMResult<&'b mut GlobalValue> {
         match data_store.load_resource(addr, ty) {
-            Ok(gv) => Ok(gv),
+            Ok((gv, loaded_group_size)) => {
+                if let Some(size) = loaded_group_size {
+                    gas_meter.charge_resource_group_load(size)?;
+                }
+                Ok(gv)
+            }
             Err(e) => {
                 error!(
                     "[VM] error loading resource at ({}, {:?}): {:?} from data store",
@@ -361,11 +497,12 @@ impl Interpreter {
     /// BorrowGlobal (mutable and not) opcode.
     fn borrow_global(
         &mut self,
+        gas_meter: &mut impl GasMeter,
         data_store: &mut impl DataStore,
         addr: AccountAddress,
         ty: &Type,
     ) -> PartialVMResult<AbstractMemorySize<GasCarrier>> {
-        let g = Self::load_resource(data_store, addr, ty)?.borrow_global()?;
+        let g = Self::load_resource(gas_meter, data_store, addr, ty)?.borrow_global()?;
         let size = g.size();
         self.operand_stack.push(g)?;
         Ok(size)
@@ -374,11 +511,12 @@ impl Interpreter {
     /// Exists opcode.
     fn exists(
         &mut self,
+        gas_meter: &mut impl GasMeter,
         data_store: &mut impl DataStore,
         addr: AccountAddress,
         ty: &Type,
     ) -> PartialVMResult<AbstractMemorySize<GasCarrier>> {
-        let gv = Self::load_resource(data_store, addr, ty)?;
+        let gv = Self::load_resource(gas_meter, data_store, addr, ty)?;
         let mem_size = gv.size();
         let exists = gv.exists()?;
         self.operand_stack.push(Value::bool(exists))?;
@@ -388,11 +526,12 @@ impl Interpreter {
     /// MoveFrom opcode.
     fn move_from(
         &mut self,
+        gas_meter: &mut impl GasMeter,
         data_store: &mut impl DataStore,
         addr: AccountAddress,
         ty: &Type,
     ) -> PartialVMResult<AbstractMemorySize<GasCarrier>> {
-        let resource = Self::load_resource(data_store, addr, ty)?.move_from()?;
+        let resource = Self::load_resource(gas_meter, data_store, addr, ty)?.move_from()?;
         let size = resource.size();
         self.operand_stack.push(resource)?;
         Ok(size)
@@ -401,13 +540,14 @@ impl Interpreter {
     /// MoveTo opcode.
     fn move_to(
         &mut self,
+        gas_meter: &mut impl GasMeter,
         data_store: &mut impl DataStore,
         addr: AccountAddress,
         ty: &Type,
         resource: Value,
     ) -> PartialVMResult<AbstractMemorySize<GasCarrier>> {
         let size = resource.size();
-        Self::load_resource(data_store, addr, ty)?.move_to(resource)?;
+        Self::load_resource(gas_meter, data_store, addr, ty)?.move_to(resource)?;
         Ok(size)
     }
 
@@ -509,11 +649,11 @@ impl Interpreter {
         loader: &Loader,
     ) -> PartialVMResult<()> {
         debug_writeln!(buf, "Call Stack:")?;
-        for (i, frame) in self.call_stack.0.iter().enumerate() {
+        for (i, frame) in self.call_stack.frames.iter().enumerate() {
             self.debug_print_frame(buf, loader, i, frame)?;
         }
         debug_writeln!(buf, "Operand Stack:")?;
-        for (idx, val) in self.operand_stack.0.iter().enumerate() {
+        for (idx, val) in self.operand_stack.values.iter().enumerate() {
             // TODO: Currently we do not know the types of the values on the operand stack.
             // Revisit.
             debug_write!(buf, "    [{}] ", idx)?;
@@ -531,7 +671,7 @@ impl Interpreter {
     /// of an execution.
     fn internal_state_str(&self, current_frame: &Frame) -> String {
         let mut internal_state = "Call stack:\n".to_string();
-        for (i, frame) in self.call_stack.0.iter().enumerate() {
+        for (i, frame) in self.call_stack.frames.iter().enumerate() {
             internal_state.push_str(
                 format!(
                     " frame #{}: {} [pc = {}]\n",
@@ -545,7 +685,7 @@ impl Interpreter {
         internal_state.push_str(
             format!(
                 "*frame #{}: {} [pc = {}]:\n",
-                self.call_stack.0.len(),
+                self.call_stack.frames.len(),
                 current_frame.function.pretty_string(),
                 current_frame.pc,
             )
@@ -563,7 +703,7 @@ impl Interpreter {
         }
         internal_state.push_str(format!("Locals:\n{}\n", current_frame.locals).as_str());
         internal_state.push_str("Operand Stack:\n");
-        for value in &self.operand_stack.0 {
+        for value in &self.operand_stack.values {
             internal_state.push_str(format!("{}\n", value).as_str());
         }
         internal_state
@@ -579,7 +719,7 @@ impl Interpreter {
         // is the last one)
         let stack_trace = self
             .call_stack
-            .0
+            .frames
             .iter()
             .rev()
             .map(|frame| {
@@ -594,24 +734,26 @@ impl Interpreter {
     }
 }
 
-// TODO Determine stack size limits based on gas limit
-const OPERAND_STACK_SIZE_LIMIT: usize = 1024;
-const CALL_STACK_SIZE_LIMIT: usize = 1024;
-
 /// The operand stack.
-struct Stack(Vec<Value>);
+struct Stack {
+    values: Vec<Value>,
+    max_size: usize,
+}
 
 impl Stack {
-    /// Create a new empty operand stack.
-    fn new() -> Self {
-        Stack(vec![])
+    /// Create a new empty operand stack holding at most `max_size` values.
+    fn new(max_size: usize) -> Self {
+        Stack {
+            values: vec![],
+            max_size,
+        }
     }
 
     /// Push a `Value` on the stack if the max stack size has not been reached. Abort execution
     /// otherwise.
     fn push(&mut self, value: Value) -> PartialVMResult<()> {
-        if self.0.len() < OPERAND_STACK_SIZE_LIMIT {
-            self.0.push(value);
+        if self.values.len() < self.max_size {
+            self.values.push(value);
             Ok(())
         } else {
             Err(PartialVMError::new(StatusCode::EXECUTION_STACK_OVERFLOW))
@@ -620,7 +762,7 @@ impl Stack {
 
     /// Pop a `Value` off the stack or abort execution if the stack is empty.
     fn pop(&mut self) -> PartialVMResult<Value> {
-        self.0
+        self.values
             .pop()
             .ok_or_else(|| PartialVMError::new(StatusCode::EMPTY_VALUE_STACK))
     }
@@ -637,29 +779,35 @@ impl Stack {
     /// Pop n values off the stack.
     fn popn(&mut self, n: u16) -> PartialVMResult<Vec<Value>> {
         let remaining_stack_size = self
-            .0
+            .values
             .len()
             .checked_sub(n as usize)
             .ok_or_else(|| PartialVMError::new(StatusCode::EMPTY_VALUE_STACK))?;
-        let args = self.0.split_off(remaining_stack_size);
+        let args = self.values.split_off(remaining_stack_size);
         Ok(args)
     }
 }
 
 /// A call stack.
 // #[derive(Debug)]
-struct CallStack(Vec<Frame>);
+struct CallStack {
+    frames: Vec<Frame>,
+    max_size: usize,
+}
 
 impl CallStack {
-    /// Create a new empty call stack.
-    fn new() -> Self {
-        CallStack(vec![])
+    /// Create a new empty call stack holding at most `max_size` frames.
+    fn new(max_size: usize) -> Self {
+        CallStack {
+            frames: vec![],
+            max_size,
+        }
     }
 
     /// Push a `Frame` on the call stack.
     fn push(&mut self, frame: Frame) -> ::std::result::Result<(), Frame> {
-        if self.0.len() < CALL_STACK_SIZE_LIMIT {
-            self.0.push(frame);
+        if self.frames.len() < self.max_size {
+            self.frames.push(frame);
             Ok(())
         } else {
             Err(frame)
@@ -668,11 +816,11 @@ impl CallStack {
 
     /// Pop a `Frame` off the call stack.
     fn pop(&mut self) -> Option<Frame> {
-        self.0.pop()
+        self.frames.pop()
     }
 
     fn current_location(&self) -> Location {
-        let location_opt = self.0.last().map(|frame| frame.location());
+        let location_opt = self.frames.last().map(|frame| frame.location());
         location_opt.unwrap_or(Location::Undefined)
     }
 }
@@ -685,6 +833,8 @@ struct Frame {
     locals: Locals,
     function: Arc<Function>,
     ty_args: Vec<Type>,
+    // Size of the operand stack when the function was called, arguments popped
+    stack_height: usize,
 }
 
 /// An `ExitCode` from `execute_code_unit`.
@@ -699,28 +849,61 @@ impl Frame {
     /// Create a new `Frame` given a `Function` and the function `Locals`.
     ///
     /// The locals must be loaded before calling this.
-    fn new(function: Arc<Function>, ty_args: Vec<Type>, locals: Locals) -> Self {
+    fn new(
+        function: Arc<Function>,
+        ty_args: Vec<Type>,
+        locals: Locals,
+        stack_height: usize,
+    ) -> Self {
         Frame {
             pc: 0,
             locals,
             function,
             ty_args,
+            stack_height,
         }
     }
 
+    /// Check the function returning left exactly its return values on the operand stack, as
+    /// guaranteed by the bytecode verifier.
+    fn check_stack_balance(&self, operand_stack: &Stack) -> PartialVMResult<()> {
+        let expected = self.stack_height + self.function.return_count();
+        if operand_stack.values.len() != expected {
+            return Err(
+                PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR).with_message(
+                    format!(
+                        "operand stack of size {} on return, expected {}",
+                        operand_stack.values.len(),
+                        expected
+                    ),
+                ),
+            );
+        }
+        Ok(())
+    }
+
     /// Execute a Move function until a return or a call opcode is found.
     fn execute_code(
         &mut self,
         resolver: &Resolver,
         interpreter: &mut Interpreter,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
+        tracer: Option<&mut dyn Tracer>,
+        watchdog: Option<&mut ScheduledWatchdog>,
     ) -> VMResult<ExitCode> {
-        self.execute_code_impl(resolver, interpreter, data_store, gas_status)
-            .map_err(|e| {
-                e.at_code_offset(self.function.index(), self.pc)
-                    .finish(self.location())
-            })
+        self.execute_code_impl(
+            resolver,
+            interpreter,
+            data_store,
+            gas_meter,
+            tracer,
+            watchdog,
+        )
+        .map_err(|e| {
+            e.at_code_offset(self.function.index(), self.pc)
+                .finish(self.location())
+        })
     }
 
     fn execute_code_impl(
@@ -728,7 +911,9 @@ impl Frame {
         resolver: &Resolver,
         interpreter: &mut Interpreter,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
+        mut tracer: Option<&mut dyn Tracer>,
+        mut watchdog: Option<&mut ScheduledWatchdog>,
     ) -> PartialVMResult<ExitCode> {
         let code = self.function.code();
         loop {
@@ -742,6 +927,43 @@ impl Frame {
                     interpreter
                 );
 
+                if let Some(tracer) = tracer.as_deref_mut() {
+                    let frame = FrameInfo {
+                        module_id: self.function.module_id(),
+                        function_name: self.function.name(),
+                        function_index: self.function.index(),
+                        ty_args: &self.ty_args,
+                        locals: &self.locals,
+                        depth: interpreter.call_stack.frames.len(),
+                    };
+                    tracer.on_instruction(
+                        &frame,
+                        self.pc,
+                        instruction,
+                        &interpreter.operand_stack.values,
+                    );
+                }
+
+                interpreter.instructions_executed += 1;
+                if let Some(max_instructions) = interpreter.max_instructions {
+                    if interpreter.instructions_executed > max_instructions {
+                        return Err(PartialVMError::new(StatusCode::EXECUTION_LIMIT_REACHED)
+                            .with_message(format!(
+                                "more than {} instructions executed",
+                                max_instructions
+                            )));
+                    }
+                }
+                if let Some(watchdog) = watchdog.as_deref_mut() {
+                    watchdog.check(interpreter.instructions_executed)?;
+                }
+                if let Some(profiler) = &mut interpreter.gas_profiler {
+                    profiler.record(
+                        gas_meter.gas_left(),
+                        Charge::Instruction(self.function.clone(), instruction_opcode(instruction)),
+                    );
+                }
+
                 fail_point!("move_vm::interpreter_loop", |_| {
                     Err(
                         PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION).with_message(
@@ -752,47 +974,47 @@ impl Frame {
 
                 match instruction {
                     Bytecode::Pop => {
-                        gas_status.charge_instr(Opcodes::POP)?;
+                        gas_meter.charge_instr(Opcodes::POP)?;
                         interpreter.operand_stack.pop()?;
                     }
                     Bytecode::Ret => {
-                        gas_status.charge_instr(Opcodes::RET)?;
+                        gas_meter.charge_instr(Opcodes::RET)?;
                         return Ok(ExitCode::Return);
                     }
                     Bytecode::BrTrue(offset) => {
-                        gas_status.charge_instr(Opcodes::BR_TRUE)?;
+                        gas_meter.charge_instr(Opcodes::BR_TRUE)?;
                         if interpreter.operand_stack.pop_as::<bool>()? {
                             self.pc = *offset;
                             break;
                         }
                     }
                     Bytecode::BrFalse(offset) => {
-                        gas_status.charge_instr(Opcodes::BR_FALSE)?;
+                        gas_meter.charge_instr(Opcodes::BR_FALSE)?;
                         if !interpreter.operand_stack.pop_as::<bool>()? {
                             self.pc = *offset;
                             break;
                         }
                     }
                     Bytecode::Branch(offset) => {
-                        gas_status.charge_instr(Opcodes::BRANCH)?;
+                        gas_meter.charge_instr(Opcodes::BRANCH)?;
                         self.pc = *offset;
                         break;
                     }
                     Bytecode::LdU8(int_const) => {
-                        gas_status.charge_instr(Opcodes::LD_U8)?;
+                        gas_meter.charge_instr(Opcodes::LD_U8)?;
                         interpreter.operand_stack.push(Value::u8(*int_const))?;
                     }
                     Bytecode::LdU64(int_const) => {
-                        gas_status.charge_instr(Opcodes::LD_U64)?;
+                        gas_meter.charge_instr(Opcodes::LD_U64)?;
                         interpreter.operand_stack.push(Value::u64(*int_const))?;
                     }
                     Bytecode::LdU128(int_const) => {
-                        gas_status.charge_instr(Opcodes::LD_U128)?;
+                        gas_meter.charge_instr(Opcodes::LD_U128)?;
                         interpreter.operand_stack.push(Value::u128(*int_const))?;
                     }
                     Bytecode::LdConst(idx) => {
                         let constant = resolver.constant_at(*idx);
-                        gas_status.charge_instr_with_size(
+                        gas_meter.charge_instr_with_size(
                             Opcodes::LD_CONST,
                             AbstractMemorySize::new(constant.data.len() as GasCarrier),
                         )?;
@@ -807,28 +1029,27 @@ impl Frame {
                         )?
                     }
                     Bytecode::LdTrue => {
-                        gas_status.charge_instr(Opcodes::LD_TRUE)?;
+                        gas_meter.charge_instr(Opcodes::LD_TRUE)?;
                         interpreter.operand_stack.push(Value::bool(true))?;
                     }
                     Bytecode::LdFalse => {
-                        gas_status.charge_instr(Opcodes::LD_FALSE)?;
+                        gas_meter.charge_instr(Opcodes::LD_FALSE)?;
                         interpreter.operand_stack.push(Value::bool(false))?;
                     }
                     Bytecode::CopyLoc(idx) => {
                         let local = self.locals.copy_loc(*idx as usize)?;
-                        gas_status.charge_instr_with_size(Opcodes::COPY_LOC, local.size())?;
+                        gas_meter.charge_instr_with_size(Opcodes::COPY_LOC, local.size())?;
                         interpreter.operand_stack.push(local)?;
                     }
                     Bytecode::MoveLoc(idx) => {
                         let local = self.locals.move_loc(*idx as usize)?;
-                        gas_status.charge_instr_with_size(Opcodes::MOVE_LOC, local.size())?;
+                        gas_meter.charge_instr_with_size(Opcodes::MOVE_LOC, local.size())?;
 
                         interpreter.operand_stack.push(local)?;
                     }
                     Bytecode::StLoc(idx) => {
                         let value_to_store = interpreter.operand_stack.pop()?;
-                        gas_status
-                            .charge_instr_with_size(Opcodes::ST_LOC, value_to_store.size())?;
+                        gas_meter.charge_instr_with_size(Opcodes::ST_LOC, value_to_store.size())?;
                         self.locals.store_loc(*idx as usize, value_to_store)?;
                     }
                     Bytecode::Call(idx) => {
@@ -842,7 +1063,7 @@ impl Frame {
                             Bytecode::MutBorrowLoc(_) => Opcodes::MUT_BORROW_LOC,
                             _ => Opcodes::IMM_BORROW_LOC,
                         };
-                        gas_status.charge_instr(opcode)?;
+                        gas_meter.charge_instr(opcode)?;
                         interpreter
                             .operand_stack
                             .push(self.locals.borrow_loc(*idx as usize)?)?;
@@ -852,7 +1073,7 @@ impl Frame {
                             Bytecode::MutBorrowField(_) => Opcodes::MUT_BORROW_FIELD,
                             _ => Opcodes::IMM_BORROW_FIELD,
                         };
-                        gas_status.charge_instr(opcode)?;
+                        gas_meter.charge_instr(opcode)?;
 
                         let reference = interpreter.operand_stack.pop_as::<StructRef>()?;
                         let offset = resolver.field_offset(*fh_idx);
@@ -865,7 +1086,7 @@ impl Frame {
                             Bytecode::MutBorrowField(_) => Opcodes::MUT_BORROW_FIELD_GENERIC,
                             _ => Opcodes::IMM_BORROW_FIELD_GENERIC,
                         };
-                        gas_status.charge_instr(opcode)?;
+                        gas_meter.charge_instr(opcode)?;
 
                         let reference = interpreter.operand_stack.pop_as::<StructRef>()?;
                         let offset = resolver.field_instantiation_offset(*fi_idx);
@@ -879,7 +1100,7 @@ impl Frame {
                             AbstractMemorySize::new(GasCarrier::from(field_count)),
                             |acc, v| acc.add(v.size()),
                         );
-                        gas_status.charge_instr_with_size(Opcodes::PACK, size)?;
+                        gas_meter.charge_instr_with_size(Opcodes::PACK, size)?;
                         interpreter
                             .operand_stack
                             .push(Value::struct_(Struct::pack(args)))?;
@@ -891,7 +1112,7 @@ impl Frame {
                             AbstractMemorySize::new(GasCarrier::from(field_count)),
                             |acc, v| acc.add(v.size()),
                         );
-                        gas_status.charge_instr_with_size(Opcodes::PACK_GENERIC, size)?;
+                        gas_meter.charge_instr_with_size(Opcodes::PACK_GENERIC, size)?;
                         interpreter
                             .operand_stack
                             .push(Value::struct_(Struct::pack(args)))?;
@@ -899,7 +1120,7 @@ impl Frame {
                     Bytecode::Unpack(sd_idx) => {
                         let field_count = resolver.field_count(*sd_idx);
                         let struct_ = interpreter.operand_stack.pop_as::<Struct>()?;
-                        gas_status.charge_instr_with_size(
+                        gas_meter.charge_instr_with_size(
                             Opcodes::UNPACK,
                             AbstractMemorySize::new(GasCarrier::from(field_count)),
                         )?;
@@ -907,14 +1128,14 @@ impl Frame {
                         // questionable.  However, if we don't have it in the loop we could wind up
                         // doing a fair bit of work before charging for it.
                         for value in struct_.unpack()? {
-                            gas_status.charge_instr_with_size(Opcodes::UNPACK, value.size())?;
+                            gas_meter.charge_instr_with_size(Opcodes::UNPACK, value.size())?;
                             interpreter.operand_stack.push(value)?;
                         }
                     }
                     Bytecode::UnpackGeneric(si_idx) => {
                         let field_count = resolver.field_instantiation_count(*si_idx);
                         let struct_ = interpreter.operand_stack.pop_as::<Struct>()?;
-                        gas_status.charge_instr_with_size(
+                        gas_meter.charge_instr_with_size(
                             Opcodes::UNPACK_GENERIC,
                             AbstractMemorySize::new(GasCarrier::from(field_count)),
                         )?;
@@ -922,7 +1143,7 @@ impl Frame {
                         // questionable.  However, if we don't have it in the loop we could wind up
                         // doing a fair bit of work before charging for it.
                         for value in struct_.unpack()? {
-                            gas_status
+                            gas_meter
                                 .charge_instr_with_size(Opcodes::UNPACK_GENERIC, value.size())?;
                             interpreter.operand_stack.push(value)?;
                         }
@@ -930,31 +1151,31 @@ impl Frame {
                     Bytecode::ReadRef => {
                         let reference = interpreter.operand_stack.pop_as::<Reference>()?;
                         let value = reference.read_ref()?;
-                        gas_status.charge_instr_with_size(Opcodes::READ_REF, value.size())?;
+                        gas_meter.charge_instr_with_size(Opcodes::READ_REF, value.size())?;
                         interpreter.operand_stack.push(value)?;
                     }
                     Bytecode::WriteRef => {
                         let reference = interpreter.operand_stack.pop_as::<Reference>()?;
                         let value = interpreter.operand_stack.pop()?;
-                        gas_status.charge_instr_with_size(Opcodes::WRITE_REF, value.size())?;
+                        gas_meter.charge_instr_with_size(Opcodes::WRITE_REF, value.size())?;
                         reference.write_ref(value)?;
                     }
                     Bytecode::CastU8 => {
-                        gas_status.charge_instr(Opcodes::CAST_U8)?;
+                        gas_meter.charge_instr(Opcodes::CAST_U8)?;
                         let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
                         interpreter
                             .operand_stack
                             .push(Value::u8(integer_value.cast_u8()?))?;
                     }
                     Bytecode::CastU64 => {
-                        gas_status.charge_instr(Opcodes::CAST_U64)?;
+                        gas_meter.charge_instr(Opcodes::CAST_U64)?;
                         let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
                         interpreter
                             .operand_stack
                             .push(Value::u64(integer_value.cast_u64()?))?;
                     }
                     Bytecode::CastU128 => {
-                        gas_status.charge_instr(Opcodes::CAST_U128)?;
+                        gas_meter.charge_instr(Opcodes::CAST_U128)?;
                         let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
                         interpreter
                             .operand_stack
@@ -962,39 +1183,39 @@ impl Frame {
                     }
                     // Arithmetic Operations
                     Bytecode::Add => {
-                        gas_status.charge_instr(Opcodes::ADD)?;
+                        gas_meter.charge_instr(Opcodes::ADD)?;
                         interpreter.binop_int(IntegerValue::add_checked)?
                     }
                     Bytecode::Sub => {
-                        gas_status.charge_instr(Opcodes::SUB)?;
+                        gas_meter.charge_instr(Opcodes::SUB)?;
                         interpreter.binop_int(IntegerValue::sub_checked)?
                     }
                     Bytecode::Mul => {
-                        gas_status.charge_instr(Opcodes::MUL)?;
+                        gas_meter.charge_instr(Opcodes::MUL)?;
                         interpreter.binop_int(IntegerValue::mul_checked)?
                     }
                     Bytecode::Mod => {
-                        gas_status.charge_instr(Opcodes::MOD)?;
+                        gas_meter.charge_instr(Opcodes::MOD)?;
                         interpreter.binop_int(IntegerValue::rem_checked)?
                     }
                     Bytecode::Div => {
-                        gas_status.charge_instr(Opcodes::DIV)?;
+                        gas_meter.charge_instr(Opcodes::DIV)?;
                         interpreter.binop_int(IntegerValue::div_checked)?
                     }
                     Bytecode::BitOr => {
-                        gas_status.charge_instr(Opcodes::BIT_OR)?;
+                        gas_meter.charge_instr(Opcodes::BIT_OR)?;
                         interpreter.binop_int(IntegerValue::bit_or)?
                     }
                     Bytecode::BitAnd => {
-                        gas_status.charge_instr(Opcodes::BIT_AND)?;
+                        gas_meter.charge_instr(Opcodes::BIT_AND)?;
                         interpreter.binop_int(IntegerValue::bit_and)?
                     }
                     Bytecode::Xor => {
-                        gas_status.charge_instr(Opcodes::XOR)?;
+                        gas_meter.charge_instr(Opcodes::XOR)?;
                         interpreter.binop_int(IntegerValue::bit_xor)?
                     }
                     Bytecode::Shl => {
-                        gas_status.charge_instr(Opcodes::SHL)?;
+                        gas_meter.charge_instr(Opcodes::SHL)?;
                         let rhs = interpreter.operand_stack.pop_as::<u8>()?;
                         let lhs = interpreter.operand_stack.pop_as::<IntegerValue>()?;
                         interpreter
@@ -1002,7 +1223,7 @@ impl Frame {
                             .push(lhs.shl_checked(rhs)?.into_value())?;
                     }
                     Bytecode::Shr => {
-                        gas_status.charge_instr(Opcodes::SHR)?;
+                        gas_meter.charge_instr(Opcodes::SHR)?;
                         let rhs = interpreter.operand_stack.pop_as::<u8>()?;
                         let lhs = interpreter.operand_stack.pop_as::<IntegerValue>()?;
                         interpreter
@@ -1010,31 +1231,31 @@ impl Frame {
                             .push(lhs.shr_checked(rhs)?.into_value())?;
                     }
                     Bytecode::Or => {
-                        gas_status.charge_instr(Opcodes::OR)?;
+                        gas_meter.charge_instr(Opcodes::OR)?;
                         interpreter.binop_bool(|l, r| Ok(l || r))?
                     }
                     Bytecode::And => {
-                        gas_status.charge_instr(Opcodes::AND)?;
+                        gas_meter.charge_instr(Opcodes::AND)?;
                         interpreter.binop_bool(|l, r| Ok(l && r))?
                     }
                     Bytecode::Lt => {
-                        gas_status.charge_instr(Opcodes::LT)?;
+                        gas_meter.charge_instr(Opcodes::LT)?;
                         interpreter.binop_bool(IntegerValue::lt)?
                     }
                     Bytecode::Gt => {
-                        gas_status.charge_instr(Opcodes::GT)?;
+                        gas_meter.charge_instr(Opcodes::GT)?;
                         interpreter.binop_bool(IntegerValue::gt)?
                     }
                     Bytecode::Le => {
-                        gas_status.charge_instr(Opcodes::LE)?;
+                        gas_meter.charge_instr(Opcodes::LE)?;
                         interpreter.binop_bool(IntegerValue::le)?
                     }
                     Bytecode::Ge => {
-                        gas_status.charge_instr(Opcodes::GE)?;
+                        gas_meter.charge_instr(Opcodes::GE)?;
                         interpreter.binop_bool(IntegerValue::ge)?
                     }
                     Bytecode::Abort => {
-                        gas_status.charge_instr(Opcodes::ABORT)?;
+                        gas_meter.charge_instr(Opcodes::ABORT)?;
                         let error_code = interpreter.operand_stack.pop_as::<u64>()?;
                         let error = PartialVMError::new(StatusCode::ABORTED)
                             .with_sub_status(error_code)
@@ -1052,7 +1273,7 @@ impl Frame {
                     Bytecode::Eq => {
                         let lhs = interpreter.operand_stack.pop()?;
                         let rhs = interpreter.operand_stack.pop()?;
-                        gas_status
+                        gas_meter
                             .charge_instr_with_size(Opcodes::EQ, lhs.size().add(rhs.size()))?;
                         interpreter
                             .operand_stack
@@ -1061,7 +1282,7 @@ impl Frame {
                     Bytecode::Neq => {
                         let lhs = interpreter.operand_stack.pop()?;
                         let rhs = interpreter.operand_stack.pop()?;
-                        gas_status
+                        gas_meter
                             .charge_instr_with_size(Opcodes::NEQ, lhs.size().add(rhs.size()))?;
                         interpreter
                             .operand_stack
@@ -1070,44 +1291,44 @@ impl Frame {
                     Bytecode::MutBorrowGlobal(sd_idx) | Bytecode::ImmBorrowGlobal(sd_idx) => {
                         let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
                         let ty = resolver.get_struct_type(*sd_idx);
-                        let size = interpreter.borrow_global(data_store, addr, &ty)?;
-                        gas_status.charge_instr_with_size(Opcodes::MUT_BORROW_GLOBAL, size)?;
+                        let size = interpreter.borrow_global(gas_meter, data_store, addr, &ty)?;
+                        gas_meter.charge_instr_with_size(Opcodes::MUT_BORROW_GLOBAL, size)?;
                     }
                     Bytecode::MutBorrowGlobalGeneric(si_idx)
                     | Bytecode::ImmBorrowGlobalGeneric(si_idx) => {
                         let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
                         let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        let size = interpreter.borrow_global(data_store, addr, &ty)?;
-                        gas_status
+                        let size = interpreter.borrow_global(gas_meter, data_store, addr, &ty)?;
+                        gas_meter
                             .charge_instr_with_size(Opcodes::MUT_BORROW_GLOBAL_GENERIC, size)?;
                     }
                     Bytecode::Exists(sd_idx) => {
                         let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
                         let ty = resolver.get_struct_type(*sd_idx);
-                        let size = interpreter.exists(data_store, addr, &ty)?;
-                        gas_status.charge_instr_with_size(Opcodes::EXISTS, size)?;
+                        let size = interpreter.exists(gas_meter, data_store, addr, &ty)?;
+                        gas_meter.charge_instr_with_size(Opcodes::EXISTS, size)?;
                     }
                     Bytecode::ExistsGeneric(si_idx) => {
                         let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
                         let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        let size = interpreter.exists(data_store, addr, &ty)?;
-                        gas_status.charge_instr_with_size(Opcodes::EXISTS_GENERIC, size)?;
+                        let size = interpreter.exists(gas_meter, data_store, addr, &ty)?;
+                        gas_meter.charge_instr_with_size(Opcodes::EXISTS_GENERIC, size)?;
                     }
                     Bytecode::MoveFrom(sd_idx) => {
                         let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
                         let ty = resolver.get_struct_type(*sd_idx);
-                        let size = interpreter.move_from(data_store, addr, &ty)?;
+                        let size = interpreter.move_from(gas_meter, data_store, addr, &ty)?;
                         // TODO: Have this calculate before pulling in the data based upon
                         // the size of the data that we are about to read in.
-                        gas_status.charge_instr_with_size(Opcodes::MOVE_FROM, size)?;
+                        gas_meter.charge_instr_with_size(Opcodes::MOVE_FROM, size)?;
                     }
                     Bytecode::MoveFromGeneric(si_idx) => {
                         let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
                         let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        let size = interpreter.move_from(data_store, addr, &ty)?;
+                        let size = interpreter.move_from(gas_meter, data_store, addr, &ty)?;
                         // TODO: Have this calculate before pulling in the data based upon
                         // the size of the data that we are about to read in.
-                        gas_status.charge_instr_with_size(Opcodes::MOVE_FROM_GENERIC, size)?;
+                        gas_meter.charge_instr_with_size(Opcodes::MOVE_FROM_GENERIC, size)?;
                     }
                     Bytecode::MoveTo(sd_idx) => {
                         let resource = interpreter.operand_stack.pop()?;
@@ -1119,8 +1340,9 @@ impl Frame {
                             .value_as::<AccountAddress>()?;
                         let ty = resolver.get_struct_type(*sd_idx);
                         // REVIEW: Can we simplify Interpreter::move_to?
-                        let size = interpreter.move_to(data_store, addr, &ty, resource)?;
-                        gas_status.charge_instr_with_size(Opcodes::MOVE_TO, size)?;
+                        let size =
+                            interpreter.move_to(gas_meter, data_store, addr, &ty, resource)?;
+                        gas_meter.charge_instr_with_size(Opcodes::MOVE_TO, size)?;
                     }
                     Bytecode::MoveToGeneric(si_idx) => {
                         let resource = interpreter.operand_stack.pop()?;
@@ -1131,26 +1353,27 @@ impl Frame {
                             .read_ref()?
                             .value_as::<AccountAddress>()?;
                         let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        let size = interpreter.move_to(data_store, addr, &ty, resource)?;
-                        gas_status.charge_instr_with_size(Opcodes::MOVE_TO_GENERIC, size)?;
+                        let size =
+                            interpreter.move_to(gas_meter, data_store, addr, &ty, resource)?;
+                        gas_meter.charge_instr_with_size(Opcodes::MOVE_TO_GENERIC, size)?;
                     }
                     Bytecode::FreezeRef => {
-                        gas_status.charge_instr(Opcodes::FREEZE_REF)?;
+                        gas_meter.charge_instr(Opcodes::FREEZE_REF)?;
                         // FreezeRef should just be a null op as we don't distinguish between mut
                         // and immut ref at runtime.
                     }
                     Bytecode::Not => {
-                        gas_status.charge_instr(Opcodes::NOT)?;
+                        gas_meter.charge_instr(Opcodes::NOT)?;
                         let value = !interpreter.operand_stack.pop_as::<bool>()?;
                         interpreter.operand_stack.push(Value::bool(value))?;
                     }
                     Bytecode::Nop => {
-                        gas_status.charge_instr(Opcodes::NOP)?;
+                        gas_meter.charge_instr(Opcodes::NOP)?;
                     }
                     Bytecode::VecPack(si, num) => {
                         let elements = interpreter.operand_stack.popn(*num as u16)?;
                         let size = AbstractMemorySize::new(*num);
-                        gas_status.charge_instr_with_size(Opcodes::VEC_PACK, size)?;
+                        gas_meter.charge_instr_with_size(Opcodes::VEC_PACK, size)?;
                         let value = Vector::pack(
                             &resolver.instantiate_single_type(*si, self.ty_args())?,
                             elements,
@@ -1159,7 +1382,7 @@ impl Frame {
                     }
                     Bytecode::VecLen(si) => {
                         let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        gas_status.charge_instr(Opcodes::VEC_LEN)?;
+                        gas_meter.charge_instr(Opcodes::VEC_LEN)?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         let value = vec_ref.len(vec_ty_arg)?;
                         interpreter.operand_stack.push(value)?;
@@ -1167,7 +1390,7 @@ impl Frame {
                     Bytecode::VecImmBorrow(si) => {
                         let idx = interpreter.operand_stack.pop_as::<u64>()? as usize;
                         let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        gas_status.charge_instr(Opcodes::VEC_IMM_BORROW)?;
+                        gas_meter.charge_instr(Opcodes::VEC_IMM_BORROW)?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         let value = vec_ref.borrow_elem(idx, vec_ty_arg)?;
                         interpreter.operand_stack.push(value)?;
@@ -1175,7 +1398,7 @@ impl Frame {
                     Bytecode::VecMutBorrow(si) => {
                         let idx = interpreter.operand_stack.pop_as::<u64>()? as usize;
                         let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        gas_status.charge_instr(Opcodes::VEC_MUT_BORROW)?;
+                        gas_meter.charge_instr(Opcodes::VEC_MUT_BORROW)?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         let value = vec_ref.borrow_elem(idx, vec_ty_arg)?;
                         interpreter.operand_stack.push(value)?;
@@ -1183,13 +1406,13 @@ impl Frame {
                     Bytecode::VecPushBack(si) => {
                         let elem = interpreter.operand_stack.pop()?;
                         let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        gas_status.charge_instr_with_size(Opcodes::VEC_PUSH_BACK, elem.size())?;
+                        gas_meter.charge_instr_with_size(Opcodes::VEC_PUSH_BACK, elem.size())?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         vec_ref.push_back(elem, vec_ty_arg)?;
                     }
                     Bytecode::VecPopBack(si) => {
                         let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        gas_status.charge_instr(Opcodes::VEC_POP_BACK)?;
+                        gas_meter.charge_instr(Opcodes::VEC_POP_BACK)?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         let value = vec_ref.pop(vec_ty_arg)?;
                         interpreter.operand_stack.push(value)?;
@@ -1197,7 +1420,7 @@ impl Frame {
                     Bytecode::VecUnpack(si, num) => {
                         let vec_val = interpreter.operand_stack.pop_as::<Vector>()?;
                         let size = AbstractMemorySize::new(*num);
-                        gas_status.charge_instr_with_size(Opcodes::VEC_UNPACK, size)?;
+                        gas_meter.charge_instr_with_size(Opcodes::VEC_UNPACK, size)?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         let elements = vec_val.unpack(vec_ty_arg, *num)?;
                         for value in elements {
@@ -1208,7 +1431,7 @@ impl Frame {
                         let idx2 = interpreter.operand_stack.pop_as::<u64>()? as usize;
                         let idx1 = interpreter.operand_stack.pop_as::<u64>()? as usize;
                         let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        gas_status.charge_instr(Opcodes::VEC_SWAP)?;
+                        gas_meter.charge_instr(Opcodes::VEC_SWAP)?;
                         let vec_ty_arg = &resolver.instantiate_single_type(*si, self.ty_args())?;
                         vec_ref.swap(idx1, idx2, vec_ty_arg)?;
                     }
@@ -1240,6 +1463,67 @@ impl Frame {
         self.function.get_resolver(loader)
     }
 
+    // Asks the data store to prefetch the resources this frame is expected to read: those of the
+    // types its code accesses in global storage, at the addresses and signers passed to it.
+    // Prefetching is only a hint to storage, so failing to prefetch does not fail the call.
+    fn prefetch_resources(&self, resolver: &Resolver, data_store: &mut impl DataStore) {
+        if let Err(err) = self.prefetch_resources_impl(resolver, data_store) {
+            warn!("[VM] failed to prefetch resources: {:?}", err);
+        }
+    }
+
+    fn prefetch_resources_impl(
+        &self,
+        resolver: &Resolver,
+        data_store: &mut impl DataStore,
+    ) -> PartialVMResult<()> {
+        if self.function.resource_accesses().is_empty() {
+            return Ok(());
+        }
+
+        let mut addresses = BTreeSet::new();
+        for (idx, token) in self.function.parameters().0.iter().enumerate() {
+            let signer_ref = match token {
+                SignatureToken::Address => {
+                    addresses.insert(self.locals.copy_loc(idx)?.value_as::<AccountAddress>()?);
+                    continue;
+                }
+                SignatureToken::Signer => self.locals.borrow_loc(idx)?,
+                SignatureToken::Reference(inner) if **inner == SignatureToken::Signer => {
+                    self.locals.copy_loc(idx)?
+                }
+                _ => continue,
+            };
+            let addr = signer_ref
+                .value_as::<SignerRef>()?
+                .borrow_signer()?
+                .value_as::<Reference>()?
+                .read_ref()?
+                .value_as::<AccountAddress>()?;
+            addresses.insert(addr);
+        }
+        if addresses.is_empty() {
+            return Ok(());
+        }
+
+        let types = self
+            .function
+            .resource_accesses()
+            .iter()
+            .map(|access| match access {
+                ResourceAccess::Struct(sd_idx) => Ok(resolver.get_struct_type(*sd_idx)),
+                ResourceAccess::Generic(si_idx) => {
+                    resolver.instantiate_generic_type(*si_idx, self.ty_args())
+                }
+            })
+            .collect::<PartialVMResult<BTreeSet<_>>>()?;
+        let resources = addresses
+            .iter()
+            .flat_map(|addr| types.iter().map(move |ty| (*addr, ty.clone())))
+            .collect::<Vec<_>>();
+        data_store.prefetch_resources(&resources)
+    }
+
     fn location(&self) -> Location {
         match self.function.module_id() {
             None => Location::Script,
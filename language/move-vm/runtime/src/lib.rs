@@ -10,17 +10,27 @@
 //! other blockchains can use it as well. The VM isn't there yet, but hopefully will be there
 //! soon.
 
+#[cfg(feature = "async-resolver")]
+pub mod async_resolver;
+pub mod config;
 pub mod data_cache;
+pub mod debugger;
+pub mod gas_profile;
 mod interpreter;
 mod loader;
 pub mod logging;
+pub mod metrics;
 pub mod move_vm;
 pub mod native_extensions;
 pub mod native_functions;
+pub mod replay;
 mod runtime;
 pub mod session;
+pub mod source_locator;
+pub mod tracer;
 #[macro_use]
 mod tracing;
+pub mod watchdog;
 
 // Only include debugging functionality in debug builds
 #[cfg(any(debug_assertions, feature = "debugging"))]
@@ -3,14 +3,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    config::VMConfig,
     logging::expect_no_verification_errors,
+    metrics::VMMetrics,
     native_functions::{NativeFunction, NativeFunctions},
     session::LoadedFunctionInstantiation,
+    source_locator::SourceLocator,
 };
 use move_binary_format::{
     access::{ModuleAccess, ScriptAccess},
     binary_views::BinaryIndexedView,
-    errors::{verification_error, Location, PartialVMError, PartialVMResult, VMResult},
+    errors::{verification_error, Location, PartialVMError, PartialVMResult, VMError, VMResult},
     file_format::{
         AbilitySet, Bytecode, CompiledModule, CompiledScript, Constant, ConstantPoolIndex,
         FieldHandleIndex, FieldInstantiationIndex, FunctionDefinition, FunctionDefinitionIndex,
@@ -34,13 +37,21 @@ use move_vm_types::{
 use parking_lot::RwLock;
 use sha3::{Digest, Sha3_256};
 use std::{
+    cmp::max,
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt::Debug,
     hash::Hash,
-    sync::Arc,
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 use tracing::error;
 
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
 type ScriptHash = [u8; 32];
 
 // A simple cache that offers both a HashMap and a Vector lookup.
@@ -51,6 +62,16 @@ struct BinaryCache<K, V> {
     binaries: Vec<Arc<V>>,
 }
 
+// Cloning a cache only clones the `Arc`s of the binaries, so the binaries are shared, not copied
+impl<K: Clone, V> Clone for BinaryCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            id_map: self.id_map.clone(),
+            binaries: self.binaries.clone(),
+        }
+    }
+}
+
 impl<K, V> BinaryCache<K, V>
 where
     K: Eq + Hash,
@@ -74,24 +95,41 @@ where
     fn get(&self, key: &K) -> Option<&Arc<V>> {
         self.id_map.get(key).and_then(|idx| self.binaries.get(*idx))
     }
+
+    // Makes the binary unreachable by key. The binary itself stays in `binaries`, so that indices
+    // of other binaries remain valid.
+    fn remove(&mut self, key: &K) {
+        self.id_map.remove(key);
+    }
 }
 
 // A script cache is a map from the hash value of a script and the `Script` itself.
 // Script are added in the cache once verified and so getting a script out the cache
 // does not require further verification (except for parameters and type parameters)
+//
+// The cache holds at most `max_size` scripts: when full, the least recently used script is
+// evicted. Scripts being executed are never evicted, as their functions refer to them by hash.
 struct ScriptCache {
-    scripts: BinaryCache<ScriptHash, Script>,
+    // scripts along with the tick they were last used at
+    scripts: HashMap<ScriptHash, (Arc<Script>, u64)>,
+    tick: u64,
+    max_size: usize,
 }
 
 impl ScriptCache {
-    fn new() -> Self {
+    fn new(max_size: usize) -> Self {
         Self {
-            scripts: BinaryCache::new(),
+            scripts: HashMap::new(),
+            tick: 0,
+            max_size,
         }
     }
 
-    fn get(&self, hash: &ScriptHash) -> Option<(Arc<Function>, Vec<Type>, Vec<Type>)> {
-        self.scripts.get(hash).map(|script| {
+    fn get(&mut self, hash: &ScriptHash) -> Option<(Arc<Function>, Vec<Type>, Vec<Type>)> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.scripts.get_mut(hash).map(|(script, last_used)| {
+            *last_used = tick;
             (
                 script.entry_point(),
                 script.parameter_tys.clone(),
@@ -100,6 +138,10 @@ impl ScriptCache {
         })
     }
 
+    fn get_script(&self, hash: &ScriptHash) -> Option<&Arc<Script>> {
+        self.scripts.get(hash).map(|(script, _)| script)
+    }
+
     fn insert(
         &mut self,
         hash: ScriptHash,
@@ -108,12 +150,33 @@ impl ScriptCache {
         match self.get(&hash) {
             Some(cached) => cached,
             None => {
-                let script = self.scripts.insert(hash, script);
-                (
+                let entry = (
                     script.entry_point(),
                     script.parameter_tys.clone(),
                     script.return_tys.clone(),
-                )
+                );
+                self.scripts.insert(hash, (Arc::new(script), self.tick));
+                self.evict();
+                entry
+            }
+        }
+    }
+
+    // Evicts the least recently used scripts which are not being executed, i.e., whose entry
+    // point is only referred to by the script, until the cache is within its size.
+    fn evict(&mut self) {
+        while self.scripts.len() > self.max_size {
+            let lru = self
+                .scripts
+                .iter()
+                .filter(|(_, (script, _))| Arc::strong_count(&script.main) == 1)
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(hash, _)| *hash);
+            match lru {
+                Some(hash) => {
+                    self.scripts.remove(&hash);
+                }
+                None => break,
             }
         }
     }
@@ -123,7 +186,14 @@ impl ScriptCache {
 // It holds all Modules, Types and Functions loaded.
 // Types and Functions are pushed globally to the ModuleCache.
 // All accesses to the ModuleCache are under lock (exclusive).
+// A ModuleCache may sit on top of a frozen cache shared, read-only, with other loaders. Lookups go
+// to the frozen cache first, and the types and functions of this cache are indexed after the ones
+// of the frozen cache.
+#[derive(Clone)]
 pub struct ModuleCache {
+    frozen: Option<Arc<ModuleCache>>,
+    struct_offset: usize,
+    function_offset: usize,
     modules: BinaryCache<ModuleId, Module>,
     structs: Vec<Arc<StructType>>,
     functions: Vec<Arc<Function>>,
@@ -132,6 +202,20 @@ pub struct ModuleCache {
 impl ModuleCache {
     fn new() -> Self {
         Self {
+            frozen: None,
+            struct_offset: 0,
+            function_offset: 0,
+            modules: BinaryCache::new(),
+            structs: vec![],
+            functions: vec![],
+        }
+    }
+
+    fn new_on_top_of(frozen: Arc<ModuleCache>) -> Self {
+        Self {
+            struct_offset: frozen.struct_offset + frozen.structs.len(),
+            function_offset: frozen.function_offset + frozen.functions.len(),
+            frozen: Some(frozen),
             modules: BinaryCache::new(),
             structs: vec![],
             functions: vec![],
@@ -145,17 +229,38 @@ impl ModuleCache {
     // Retrieve a module by `ModuleId`. The module may have not been loaded yet in which
     // case `None` is returned
     fn module_at(&self, id: &ModuleId) -> Option<Arc<Module>> {
-        self.modules.get(id).map(Arc::clone)
+        self.get_module(id).map(Arc::clone)
+    }
+
+    fn get_module(&self, id: &ModuleId) -> Option<&Arc<Module>> {
+        self.frozen
+            .as_ref()
+            .and_then(|frozen| frozen.get_module(id))
+            .or_else(|| self.modules.get(id))
     }
 
     // Retrieve a function by index
     fn function_at(&self, idx: usize) -> Arc<Function> {
-        Arc::clone(&self.functions[idx])
+        match &self.frozen {
+            Some(frozen) if idx < self.function_offset => frozen.function_at(idx),
+            _ => Arc::clone(&self.functions[idx - self.function_offset]),
+        }
     }
 
     // Retrieve a struct by index
     fn struct_at(&self, idx: CachedStructIndex) -> Arc<StructType> {
-        Arc::clone(&self.structs[idx.0])
+        match &self.frozen {
+            Some(frozen) if idx.0 < self.struct_offset => frozen.struct_at(idx),
+            _ => Arc::clone(&self.structs[idx.0 - self.struct_offset]),
+        }
+    }
+
+    // Retrieve a struct by index, returning `None` if the index is out of bounds
+    fn get_struct(&self, idx: CachedStructIndex) -> Option<&Arc<StructType>> {
+        match &self.frozen {
+            Some(frozen) if idx.0 < self.struct_offset => frozen.get_struct(idx),
+            _ => self.structs.get(idx.0 - self.struct_offset),
+        }
     }
 
     //
@@ -168,6 +273,7 @@ impl ModuleCache {
         natives: &NativeFunctions,
         id: ModuleId,
         module: CompiledModule,
+        size: usize,
     ) -> VMResult<Arc<Module>> {
         if let Some(cached) = self.module_at(&id) {
             return Ok(cached);
@@ -176,7 +282,7 @@ impl ModuleCache {
         // we need this operation to be transactional, if an error occurs we must
         // leave a clean state
         self.add_module(natives, &module)?;
-        match Module::new(module, self) {
+        match Module::new(module, size, self) {
             Ok(module) => Ok(Arc::clone(self.modules.insert(id, module))),
             Err((err, module)) => {
                 // remove all structs and functions that have been pushed
@@ -299,7 +405,7 @@ impl ModuleCache {
                             break;
                         }
                         if struct_type.name.as_ident_str() == struct_name {
-                            return Ok(CachedStructIndex(idx));
+                            return Ok(CachedStructIndex(self.struct_offset + idx));
                         }
                     }
                     Err(
@@ -378,9 +484,50 @@ impl ModuleCache {
         Ok(res)
     }
 
+    // Removes the given modules, along with all the modules (transitively) depending on them, so
+    // that they are loaded again from storage when next used.
+    // Their types and functions stay in the cache, as they are referred to by index, until the
+    // whole cache is flushed.
+    fn remove_with_dependents(&mut self, ids: &BTreeSet<ModuleId>) {
+        let mut removed = ids.clone();
+        loop {
+            let dependents: Vec<_> = self
+                .modules
+                .id_map
+                .keys()
+                .filter(|id| !removed.contains(*id))
+                .filter(|id| {
+                    self.modules.get(id).map_or(false, |module| {
+                        module
+                            .module()
+                            .immediate_dependencies()
+                            .iter()
+                            .any(|dep| removed.contains(dep))
+                    })
+                })
+                .cloned()
+                .collect();
+            if dependents.is_empty() {
+                break;
+            }
+            removed.extend(dependents);
+        }
+        for id in &removed {
+            self.modules.remove(id);
+        }
+    }
+
     // Given a module id, returns whether the module cache has the module or not
     fn has_module(&self, module_id: &ModuleId) -> bool {
-        self.modules.id_map.contains_key(module_id)
+        self.has_frozen_module(module_id) || self.modules.id_map.contains_key(module_id)
+    }
+
+    // Given a module id, returns whether the module is in the frozen cache, in which case it
+    // cannot be removed
+    fn has_frozen_module(&self, module_id: &ModuleId) -> bool {
+        self.frozen
+            .as_ref()
+            .map_or(false, |frozen| frozen.has_module(module_id))
     }
 
     // Given a ModuleId::struct_name, retrieve the `StructType` and the index associated.
@@ -391,11 +538,10 @@ impl ModuleCache {
         module_id: &ModuleId,
     ) -> PartialVMResult<(CachedStructIndex, Arc<StructType>)> {
         match self
-            .modules
-            .get(module_id)
+            .get_module(module_id)
             .and_then(|module| module.struct_map.get(struct_name))
         {
-            Some(struct_idx) => Ok((*struct_idx, Arc::clone(&self.structs[struct_idx.0]))),
+            Some(struct_idx) => Ok((*struct_idx, self.struct_at(*struct_idx))),
             None => Err(
                 PartialVMError::new(StatusCode::TYPE_RESOLUTION_FAILURE).with_message(format!(
                     "Cannot find {:?}::{:?} in cache",
@@ -413,8 +559,7 @@ impl ModuleCache {
         module_id: &ModuleId,
     ) -> PartialVMResult<usize> {
         match self
-            .modules
-            .get(module_id)
+            .get_module(module_id)
             .and_then(|module| module.function_map.get(func_name))
         {
             Some(func_idx) => Ok(*func_idx),
@@ -441,18 +586,252 @@ pub(crate) struct Loader {
     module_cache: RwLock<ModuleCache>,
     type_cache: RwLock<TypeCache>,
     natives: NativeFunctions,
+    vm_config: VMConfig,
+    metrics: Arc<dyn VMMetrics>,
+    // Whether the whole cache must be flushed (e.g., because modules were upgraded in storage)
+    invalidated: RwLock<bool>,
+    // Modules which, along with the modules depending on them, must be removed from the cache
+    invalidated_modules: RwLock<BTreeSet<ModuleId>>,
+    // Resolve the code offsets of modules into positions in their source code
+    source_locators: RwLock<BTreeMap<ModuleId, Arc<dyn SourceLocator>>>,
 }
 
 impl Loader {
-    pub(crate) fn new(natives: NativeFunctions) -> Self {
+    pub(crate) fn new(
+        natives: NativeFunctions,
+        vm_config: VMConfig,
+        metrics: Arc<dyn VMMetrics>,
+    ) -> Self {
         Self {
-            scripts: RwLock::new(ScriptCache::new()),
+            scripts: RwLock::new(ScriptCache::new(vm_config.max_script_cache_size)),
             module_cache: RwLock::new(ModuleCache::new()),
-            type_cache: RwLock::new(TypeCache::new()),
+            type_cache: RwLock::new(TypeCache::new(vm_config.max_type_cache_size)),
             natives,
+            vm_config,
+            metrics,
+            invalidated: RwLock::new(false),
+            invalidated_modules: RwLock::new(BTreeSet::new()),
+            source_locators: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    // Freezes the module and type caches of this loader, so that they can be shared by other
+    // loaders.
+    pub(crate) fn freeze(&self) -> FrozenCaches {
+        FrozenCaches {
+            module_cache: Arc::new(self.module_cache.read().clone()),
+            type_cache: Arc::new(self.type_cache.read().clone()),
+            natives: self.natives.clone(),
+            vm_config: self.vm_config.clone(),
+            metrics: Arc::clone(&self.metrics),
+            source_locators: self.source_locators.read().clone(),
         }
     }
 
+    // Returns a new loader on top of the given frozen caches. The frozen caches are shared, not
+    // copied, and looked up before the caches of the new loader, which only hold what is loaded
+    // afterwards.
+    pub(crate) fn new_from_frozen(frozen: &FrozenCaches) -> Self {
+        Self {
+            scripts: RwLock::new(ScriptCache::new(frozen.vm_config.max_script_cache_size)),
+            module_cache: RwLock::new(ModuleCache::new_on_top_of(Arc::clone(&frozen.module_cache))),
+            type_cache: RwLock::new(TypeCache::new_on_top_of(
+                Arc::clone(&frozen.type_cache),
+                frozen.vm_config.max_type_cache_size,
+            )),
+            natives: frozen.natives.clone(),
+            vm_config: frozen.vm_config.clone(),
+            metrics: Arc::clone(&frozen.metrics),
+            invalidated: RwLock::new(false),
+            invalidated_modules: RwLock::new(BTreeSet::new()),
+            source_locators: RwLock::new(frozen.source_locators.clone()),
+        }
+    }
+
+    // Returns a new loader with the natives, configuration and metrics of this loader, but nothing
+    // loaded yet
+    pub(crate) fn new_with_empty_cache(&self) -> Self {
+        let loader = Self::new(
+            self.natives.clone(),
+            self.vm_config.clone(),
+            Arc::clone(&self.metrics),
+        );
+        *loader.source_locators.write() = self.source_locators.read().clone();
+        loader
+    }
+
+    pub(crate) fn vm_config(&self) -> &VMConfig {
+        &self.vm_config
+    }
+
+    pub(crate) fn metrics(&self) -> &dyn VMMetrics {
+        &*self.metrics
+    }
+
+    // Runs a bytecode verifier pass, recording the time it takes
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn timed_verification<T>(&self, verify: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = verify();
+        self.metrics.observe_verification_time(start.elapsed());
+        result
+    }
+
+    // There is no clock on wasm32-unknown-unknown (`Instant::now` panics), so verification is not
+    // timed there
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    fn timed_verification<T>(&self, verify: impl FnOnce() -> T) -> T {
+        verify()
+    }
+
+    //
+    // Source locations
+    //
+
+    // Registers how to resolve the code offsets of the given module into source locations,
+    // replacing the locator previously registered for it, if any.
+    pub(crate) fn set_source_locator(&self, module_id: ModuleId, locator: Arc<dyn SourceLocator>) {
+        self.source_locators.write().insert(module_id, locator);
+    }
+
+    // Attaches the source location of the failing instruction to aborts and arithmetic errors
+    // raised in a module with a source locator. Other errors are returned unchanged.
+    pub(crate) fn attach_source_location(&self, err: VMError) -> VMError {
+        if !matches!(
+            err.major_status(),
+            StatusCode::ABORTED | StatusCode::ARITHMETIC_ERROR
+        ) {
+            return err;
+        }
+        let module_id = match err.location() {
+            Location::Module(module_id) => module_id,
+            _ => return err,
+        };
+        let (function, offset) = match err.offsets().first() {
+            Some(function_and_offset) => *function_and_offset,
+            None => return err,
+        };
+        let source_location = self
+            .source_locators
+            .read()
+            .get(module_id)
+            .and_then(|locator| locator.source_location(function, offset));
+        match source_location {
+            Some(source_location) => err.with_source_location(source_location),
+            None => err,
+        }
+    }
+
+    //
+    // Cache invalidation
+    //
+
+    // Marks the whole cache as invalid. It is flushed on the next call to `flush_if_invalidated`.
+    pub(crate) fn mark_as_invalid(&self) {
+        *self.invalidated.write() = true;
+    }
+
+    // Marks a module as invalid. On the next call to `flush_if_invalidated` it is removed from the
+    // cache, along with the modules depending on it and all the scripts.
+    pub(crate) fn mark_module_as_invalid(&self, module_id: ModuleId) {
+        self.invalidated_modules.write().insert(module_id);
+    }
+
+    // Flushes the parts of the cache which have been marked as invalid.
+    // Entities are referred to by index while executing, so this must not be called while
+    // sessions using this loader are alive.
+    pub(crate) fn flush_if_invalidated(&self) {
+        let mut invalidated = self.invalidated.write();
+        let mut invalidated_modules = self.invalidated_modules.write();
+        // modules of a frozen cache cannot be removed from it, so the frozen cache is dropped
+        // along with the whole cache
+        let frozen_invalidated = {
+            let module_cache = self.module_cache.read();
+            invalidated_modules
+                .iter()
+                .any(|id| module_cache.has_frozen_module(id))
+        };
+        if *invalidated || frozen_invalidated {
+            *self.scripts.write() = ScriptCache::new(self.vm_config.max_script_cache_size);
+            *self.module_cache.write() = ModuleCache::new();
+            *self.type_cache.write() = TypeCache::new(self.vm_config.max_type_cache_size);
+            *invalidated = false;
+        } else if !invalidated_modules.is_empty() {
+            // scripts refer to the types and functions of the modules they use, and cached type
+            // tags and layouts may refer to the types of the modules removed, so they are all
+            // dropped
+            *self.scripts.write() = ScriptCache::new(self.vm_config.max_script_cache_size);
+            *self.type_cache.write() = TypeCache::new(self.vm_config.max_type_cache_size);
+            self.module_cache
+                .write()
+                .remove_with_dependents(&invalidated_modules);
+        }
+        invalidated_modules.clear();
+    }
+
+    // Returns the types of the values returned by the given module function, instantiated with
+    // `ty_args`. The function must have been loaded.
+    pub(crate) fn function_return_types(
+        &self,
+        func: &Function,
+        ty_args: &[Type],
+    ) -> PartialVMResult<Vec<Type>> {
+        let module_cache = self.module_cache.read();
+        let module = func
+            .module_id()
+            .and_then(|module_id| module_cache.module_at(module_id))
+            .ok_or_else(|| {
+                PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
+                    .with_message(format!("module of {} not loaded", func.pretty_string()))
+            })?;
+        func.return_
+            .0
+            .iter()
+            .map(|tok| {
+                module_cache
+                    .make_type(BinaryIndexedView::Module(module.module()), tok)?
+                    .subst(ty_args)
+            })
+            .collect()
+    }
+
+    // Returns the modules the given function (transitively) depends on, including its own module
+    // if it is not a script, along with the size of each of them. The modules for which `skip`
+    // returns true are left out, along with their own dependencies. The function must have been
+    // loaded, so that all those modules are in the cache.
+    pub(crate) fn function_dependencies(
+        &self,
+        function: &Function,
+        skip: impl Fn(&ModuleId) -> bool,
+    ) -> PartialVMResult<Vec<(ModuleId, usize)>> {
+        let mut stack = match &function.scope {
+            Scope::Module(module_id) => vec![module_id.clone()],
+            Scope::Script(hash) => self
+                .scripts
+                .read()
+                .get_script(hash)
+                .map(|script| script.script.immediate_dependencies())
+                .ok_or_else(|| {
+                    PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
+                        .with_message("script must be in the cache".to_string())
+                })?,
+        };
+        let module_cache = self.module_cache.read();
+        let mut dependencies = BTreeMap::new();
+        while let Some(module_id) = stack.pop() {
+            if dependencies.contains_key(&module_id) || skip(&module_id) {
+                continue;
+            }
+            let module = module_cache.module_at(&module_id).ok_or_else(|| {
+                PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
+                    .with_message(format!("module {} must be in the cache", module_id))
+            })?;
+            stack.extend(module.module().immediate_dependencies());
+            dependencies.insert(module_id, module.size());
+        }
+        Ok(dependencies.into_iter().collect())
+    }
+
     //
     // Script verification and loading
     //
@@ -469,7 +848,7 @@ impl Loader {
         &self,
         script_blob: &[u8],
         ty_args: &[TypeTag],
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<(Arc<Function>, LoadedFunctionInstantiation)> {
         // retrieve or load the script
         let mut sha3_256 = Sha3_256::new();
@@ -482,6 +861,7 @@ impl Loader {
             None => {
                 let ver_script = self.deserialize_and_verify_script(script_blob, data_store)?;
                 let script = Script::new(ver_script, &hash_value, &self.module_cache.read())?;
+                self.metrics.inc_scripts_loaded();
                 scripts.insert(hash_value, script)
             }
         };
@@ -508,7 +888,7 @@ impl Loader {
     fn deserialize_and_verify_script(
         &self,
         script: &[u8],
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<CompiledScript> {
         let script = match CompiledScript::deserialize(script) {
             Ok(script) => script,
@@ -545,7 +925,7 @@ impl Loader {
     // Script verification steps.
     // See `verify_module()` for module verification steps.
     fn verify_script(&self, script: &CompiledScript) -> VMResult<()> {
-        move_bytecode_verifier::verify_script(script)
+        self.timed_verification(|| move_bytecode_verifier::verify_script(script))
     }
 
     fn verify_script_dependencies(
@@ -572,7 +952,7 @@ impl Loader {
         module_id: &ModuleId,
         function_name: &IdentStr,
         ty_args: &[TypeTag],
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<(Arc<Module>, Arc<Function>, LoadedFunctionInstantiation)> {
         let module = self.load_module(module_id, data_store)?;
         let idx = self
@@ -631,12 +1011,28 @@ impl Loader {
         modules: &[CompiledModule],
         data_store: &mut impl DataStore,
     ) -> VMResult<()> {
+        // The bytecode of each module is verified independently of the other modules, so this is
+        // done upfront (in parallel with the `parallel-verification` feature), and the results are
+        // checked in the order of the bundle, to report the same error as a sequential
+        // verification.
+        let verify = |module: &CompiledModule| {
+            self.timed_verification(|| move_bytecode_verifier::verify_module(module))
+        };
+        #[cfg(feature = "parallel-verification")]
+        let bytecode_results: Vec<_> = {
+            use rayon::prelude::*;
+            modules.par_iter().map(verify).collect()
+        };
+        #[cfg(not(feature = "parallel-verification"))]
+        let bytecode_results: Vec<_> = modules.iter().map(verify).collect();
+
         let mut bundle_unverified: BTreeSet<_> = modules.iter().map(|m| m.self_id()).collect();
         let mut bundle_verified = BTreeMap::new();
-        for module in modules {
+        for (module, bytecode_result) in modules.iter().zip(bytecode_results) {
             let module_id = module.self_id();
             bundle_unverified.remove(&module_id);
 
+            bytecode_result?;
             self.verify_module_for_publication(
                 module,
                 &bundle_verified,
@@ -650,7 +1046,8 @@ impl Loader {
 
     // A module to be published must be loadable.
     //
-    // This step performs all verification steps to load the module without loading it.
+    // This step performs all verification steps to load the module without loading it, except
+    // for the bytecode verification, which the caller does beforehand.
     // The module is not added to the code cache. It is simply published to the data cache.
     // See `verify_script()` for script verification steps.
     //
@@ -664,13 +1061,12 @@ impl Loader {
         module: &CompiledModule,
         bundle_verified: &BTreeMap<ModuleId, CompiledModule>,
         bundle_unverified: &BTreeSet<ModuleId>,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<()> {
         // Performs all verification steps to load the module without loading it, i.e., the new
         // module will NOT show up in `module_cache`. In the module republishing case, it means
         // that the old module is still in the `module_cache`, unless a new Loader is created,
         // which means that a new MoveVM instance needs to be created.
-        move_bytecode_verifier::verify_module(module)?;
         self.check_natives(module)?;
 
         let mut visited = BTreeSet::new();
@@ -717,7 +1113,7 @@ impl Loader {
             |module_id| {
                 bundle_verified
                     .get(module_id)
-                    .or_else(|| module_cache.modules.get(module_id).map(|m| m.module()))
+                    .or_else(|| module_cache.get_module(module_id).map(|m| m.module()))
                     .map(|m| m.immediate_dependencies())
                     .ok_or_else(|| PartialVMError::new(StatusCode::MISSING_DEPENDENCY))
             },
@@ -732,7 +1128,7 @@ impl Loader {
                     // creates a cyclic relation.
                     bundle_verified
                         .get(module_id)
-                        .or_else(|| module_cache.modules.get(module_id).map(|m| m.module()))
+                        .or_else(|| module_cache.get_module(module_id).map(|m| m.module()))
                         .map(|m| m.immediate_friends())
                         .ok_or_else(|| PartialVMError::new(StatusCode::MISSING_DEPENDENCY))
                 }
@@ -789,7 +1185,7 @@ impl Loader {
     pub(crate) fn load_type(
         &self,
         type_tag: &TypeTag,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<Type> {
         Ok(match type_tag {
             TypeTag::Bool => Type::Bool,
@@ -823,12 +1219,37 @@ impl Loader {
         })
     }
 
+    // Loads the module declaring a struct and returns the type of the struct, instantiated with the
+    // given type arguments if it is generic.
+    pub(crate) fn load_struct_type(
+        &self,
+        module_id: &ModuleId,
+        name: &IdentStr,
+        ty_args: Vec<Type>,
+        data_store: &(impl DataStore + ?Sized),
+    ) -> VMResult<Type> {
+        self.load_module(module_id, data_store)?;
+        let (idx, struct_type) = self
+            .module_cache
+            .read()
+            // GOOD module was loaded above
+            .resolve_struct_by_name(name, module_id)
+            .map_err(|e| e.finish(Location::Undefined))?;
+        if struct_type.type_parameters.is_empty() && ty_args.is_empty() {
+            Ok(Type::Struct(idx))
+        } else {
+            self.verify_ty_args(struct_type.type_param_constraints(), &ty_args)
+                .map_err(|e| e.finish(Location::Undefined))?;
+            Ok(Type::StructInstantiation(idx, ty_args))
+        }
+    }
+
     // The interface for module loading. Aligned with `load_type` and `load_function`, this function
     // verifies that the module is OK instead of expect it.
     pub(crate) fn load_module(
         &self,
         id: &ModuleId,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<Arc<Module>> {
         self.load_module_internal(id, &BTreeMap::new(), &BTreeSet::new(), data_store)
     }
@@ -840,12 +1261,14 @@ impl Loader {
         id: &ModuleId,
         bundle_verified: &BTreeMap<ModuleId, CompiledModule>,
         bundle_unverified: &BTreeSet<ModuleId>,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
     ) -> VMResult<Arc<Module>> {
         // if the module is already in the code cache, load the cached version
         if let Some(cached) = self.module_cache.read().module_at(id) {
+            self.metrics.inc_module_cache_hits();
             return Ok(cached);
         }
+        self.metrics.inc_module_cache_misses();
 
         // otherwise, load the transitive closure of the target module
         let module_ref = self.load_and_verify_module_and_dependencies_and_friends(
@@ -866,13 +1289,14 @@ impl Loader {
         Ok(module_ref)
     }
 
-    // Load, deserialize, and check the module with the bytecode verifier, without linking
+    // Load, deserialize, and check the module with the bytecode verifier, without linking.
+    // Returns the module along with the size of its serialized form.
     fn load_and_verify_module(
         &self,
         id: &ModuleId,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
         allow_loading_failure: bool,
-    ) -> VMResult<CompiledModule> {
+    ) -> VMResult<(CompiledModule, usize)> {
         // bytes fetching, allow loading to fail if the flag is set
         let bytes = match data_store.load_module(id) {
             Ok(bytes) => bytes,
@@ -895,10 +1319,11 @@ impl Loader {
             .map_err(expect_no_verification_errors)?;
 
         // bytecode verifier checks that can be performed with the module itself
-        move_bytecode_verifier::verify_module(&module).map_err(expect_no_verification_errors)?;
+        self.timed_verification(|| move_bytecode_verifier::verify_module(&module))
+            .map_err(expect_no_verification_errors)?;
         self.check_natives(&module)
             .map_err(expect_no_verification_errors)?;
-        Ok(module)
+        Ok((module, bytes.len()))
     }
 
     // Everything in `load_and_verify_module` and also recursively load and verify all the
@@ -907,7 +1332,7 @@ impl Loader {
         &self,
         id: &ModuleId,
         bundle_verified: &BTreeMap<ModuleId, CompiledModule>,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
         visited: &mut BTreeSet<ModuleId>,
         friends_discovered: &mut BTreeSet<ModuleId>,
         allow_module_loading_failure: bool,
@@ -919,7 +1344,8 @@ impl Loader {
         }
 
         // module self-check
-        let module = self.load_and_verify_module(id, data_store, allow_module_loading_failure)?;
+        let (module, size) =
+            self.load_and_verify_module(id, data_store, allow_module_loading_failure)?;
         visited.insert(id.clone());
         friends_discovered.extend(module.immediate_friends());
 
@@ -936,7 +1362,7 @@ impl Loader {
 
         // if linking goes well, insert the module to the code cache
         let mut locked_cache = self.module_cache.write();
-        let module_ref = locked_cache.insert(&self.natives, id.clone(), module)?;
+        let module_ref = locked_cache.insert(&self.natives, id.clone(), module, size)?;
         drop(locked_cache); // explicit unlock
 
         Ok(module_ref)
@@ -947,7 +1373,7 @@ impl Loader {
         &self,
         module: &CompiledModule,
         bundle_verified: &BTreeMap<ModuleId, CompiledModule>,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
         visited: &mut BTreeSet<ModuleId>,
         friends_discovered: &mut BTreeSet<ModuleId>,
         allow_dependency_loading_failure: bool,
@@ -1003,7 +1429,7 @@ impl Loader {
         id: &ModuleId,
         bundle_verified: &BTreeMap<ModuleId, CompiledModule>,
         bundle_unverified: &BTreeSet<ModuleId>,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
         allow_module_loading_failure: bool,
     ) -> VMResult<Arc<Module>> {
         // load the closure of the module in terms of dependency relation
@@ -1037,7 +1463,7 @@ impl Loader {
         friends_discovered: BTreeSet<ModuleId>,
         bundle_verified: &BTreeMap<ModuleId, CompiledModule>,
         bundle_unverified: &BTreeSet<ModuleId>,
-        data_store: &impl DataStore,
+        data_store: &(impl DataStore + ?Sized),
         allow_friend_loading_failure: bool,
     ) -> VMResult<()> {
         // for each new module discovered in the frontier, load them fully and expand the frontier.
@@ -1096,6 +1522,42 @@ impl Loader {
                 return Err(PartialVMError::new(StatusCode::CONSTRAINT_NOT_SATISFIED));
             }
         }
+        self.check_type_instantiation_size(ty_args)
+    }
+
+    // Check the number of nodes in the types of an instantiation is within the configured limit
+    fn check_type_instantiation_size(&self, ty_args: &[Type]) -> PartialVMResult<()> {
+        fn size(ty: &Type) -> usize {
+            match ty {
+                Type::Bool
+                | Type::U8
+                | Type::U64
+                | Type::U128
+                | Type::Address
+                | Type::Signer
+                | Type::Struct(_)
+                | Type::TyParam(_) => 1,
+                Type::Vector(ty) | Type::Reference(ty) | Type::MutableReference(ty) => 1 + size(ty),
+                Type::StructInstantiation(_, ty_args) => {
+                    1 + ty_args.iter().map(size).sum::<usize>()
+                }
+            }
+        }
+
+        let max_size = match self.vm_config.max_type_instantiation_size {
+            Some(max_size) => max_size,
+            None => return Ok(()),
+        };
+        let instantiation_size: usize = ty_args.iter().map(size).sum();
+        if instantiation_size > max_size {
+            return Err(
+                PartialVMError::new(StatusCode::VM_MAX_TYPE_INSTANTIATION_SIZE_REACHED)
+                    .with_message(format!(
+                        "type instantiation of size {} exceeds the limit of {}",
+                        instantiation_size, max_size
+                    )),
+            );
+        }
         Ok(())
     }
 
@@ -1111,8 +1573,7 @@ impl Loader {
         Arc::clone(
             self.module_cache
                 .read()
-                .modules
-                .get(idx)
+                .get_module(idx)
                 .expect("ModuleId on Function must exist"),
         )
     }
@@ -1121,14 +1582,13 @@ impl Loader {
         Arc::clone(
             self.scripts
                 .read()
-                .scripts
-                .get(hash)
+                .get_script(hash)
                 .expect("Script hash on Function must exist"),
         )
     }
 
     pub(crate) fn get_struct_type(&self, idx: CachedStructIndex) -> Option<Arc<StructType>> {
-        self.module_cache.read().structs.get(idx.0).map(Arc::clone)
+        self.module_cache.read().get_struct(idx).map(Arc::clone)
     }
 
     pub(crate) fn abilities(&self, ty: &Type) -> PartialVMResult<AbilitySet> {
@@ -1247,6 +1707,7 @@ impl<'a> Resolver<'a> {
         for ty in &func_inst.instantiation {
             instantiation.push(ty.subst(type_params)?);
         }
+        self.loader.check_type_instantiation_size(&instantiation)?;
         Ok(instantiation)
     }
 
@@ -1279,14 +1740,13 @@ impl<'a> Resolver<'a> {
             BinaryType::Module(module) => module.struct_instantiation_at(idx.0),
             BinaryType::Script(_) => unreachable!("Scripts cannot have type instructions"),
         };
-        Ok(Type::StructInstantiation(
-            struct_inst.def,
-            struct_inst
-                .instantiation
-                .iter()
-                .map(|ty| ty.subst(ty_args))
-                .collect::<PartialVMResult<_>>()?,
-        ))
+        let instantiation = struct_inst
+            .instantiation
+            .iter()
+            .map(|ty| ty.subst(ty_args))
+            .collect::<PartialVMResult<Vec<_>>>()?;
+        self.loader.check_type_instantiation_size(&instantiation)?;
+        Ok(Type::StructInstantiation(struct_inst.def, instantiation))
     }
 
     fn single_type_at(&self, idx: SignatureIndex) -> &Type {
@@ -1357,6 +1817,8 @@ pub(crate) struct Module {
     id: ModuleId,
     // primitive pools
     module: Arc<CompiledModule>,
+    // size of the serialized module, in bytes
+    size: usize,
 
     //
     // types as indexes into the Loader type list
@@ -1403,6 +1865,7 @@ pub(crate) struct Module {
 impl Module {
     fn new(
         module: CompiledModule,
+        size: usize,
         cache: &ModuleCache,
     ) -> Result<Self, (PartialVMError, CompiledModule)> {
         let id = module.self_id();
@@ -1438,7 +1901,7 @@ impl Module {
                                 )));
                         }
                         if struct_type.name.as_ident_str() == struct_name {
-                            struct_refs.push(CachedStructIndex(idx));
+                            struct_refs.push(CachedStructIndex(cache.struct_offset + idx));
                             break;
                         }
                     }
@@ -1449,7 +1912,7 @@ impl Module {
 
             for struct_def in module.struct_defs() {
                 let idx = struct_refs[struct_def.struct_handle.0 as usize];
-                let field_count = cache.structs[idx.0].fields.len() as u16;
+                let field_count = cache.struct_at(idx).fields.len() as u16;
                 structs.push(StructDef { field_count, idx });
                 let name =
                     module.identifier_at(module.struct_handle_at(struct_def.struct_handle).name);
@@ -1488,7 +1951,7 @@ impl Module {
                             )));
                         }
                         if function.name.as_ident_str() == func_name {
-                            function_refs.push(idx);
+                            function_refs.push(cache.function_offset + idx);
                             break;
                         }
                     }
@@ -1570,6 +2033,7 @@ impl Module {
             Ok(_) => Ok(Self {
                 id,
                 module: Arc::new(module),
+                size,
                 struct_refs,
                 structs,
                 struct_instantiations,
@@ -1613,6 +2077,10 @@ impl Module {
         &self.module
     }
 
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
     pub(crate) fn arc_module(&self) -> Arc<CompiledModule> {
         self.module.clone()
     }
@@ -1756,6 +2224,8 @@ impl Script {
             native,
             scope,
             name,
+            // scripts cannot access global storage
+            resource_accesses: vec![],
         });
 
         let mut single_signature_token_map = BTreeMap::new();
@@ -1847,6 +2317,38 @@ pub(crate) struct Function {
     native: Option<NativeFunction>,
     scope: Scope,
     name: Identifier,
+    // the structs the code accesses in global storage, e.g., to prefetch them
+    resource_accesses: Vec<ResourceAccess>,
+}
+
+// A struct accessed in global storage by the code of a function
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum ResourceAccess {
+    Struct(StructDefinitionIndex),
+    // instantiated with the type arguments of the function
+    Generic(StructDefInstantiationIndex),
+}
+
+impl ResourceAccess {
+    fn in_code(code: &[Bytecode]) -> Vec<Self> {
+        code.iter()
+            .filter_map(|instruction| match instruction {
+                Bytecode::MutBorrowGlobal(sd_idx)
+                | Bytecode::ImmBorrowGlobal(sd_idx)
+                | Bytecode::Exists(sd_idx)
+                | Bytecode::MoveFrom(sd_idx)
+                | Bytecode::MoveTo(sd_idx) => Some(Self::Struct(*sd_idx)),
+                Bytecode::MutBorrowGlobalGeneric(si_idx)
+                | Bytecode::ImmBorrowGlobalGeneric(si_idx)
+                | Bytecode::ExistsGeneric(si_idx)
+                | Bytecode::MoveFromGeneric(si_idx)
+                | Bytecode::MoveToGeneric(si_idx) => Some(Self::Generic(*si_idx)),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
 }
 
 impl Function {
@@ -1887,6 +2389,7 @@ impl Function {
         };
         let return_ = module.signature_at(handle.return_).clone();
         let type_parameters = handle.type_parameters.clone();
+        let resource_accesses = ResourceAccess::in_code(&code);
         Self {
             file_format_version: module.version(),
             index,
@@ -1898,6 +2401,7 @@ impl Function {
             native,
             scope,
             name,
+            resource_accesses,
         }
     }
 
@@ -1938,6 +2442,10 @@ impl Function {
         self.parameters.len()
     }
 
+    pub(crate) fn return_count(&self) -> usize {
+        self.return_.len()
+    }
+
     pub(crate) fn name(&self) -> &str {
         self.name.as_str()
     }
@@ -1946,6 +2454,10 @@ impl Function {
         &self.code
     }
 
+    pub(crate) fn resource_accesses(&self) -> &[ResourceAccess] {
+        &self.resource_accesses
+    }
+
     pub(crate) fn type_parameters(&self) -> &[AbilitySet] {
         &self.type_parameters
     }
@@ -1971,8 +2483,8 @@ impl Function {
         self.native.is_some()
     }
 
-    pub(crate) fn get_native(&self) -> PartialVMResult<NativeFunction> {
-        self.native.ok_or_else(|| {
+    pub(crate) fn get_native(&self) -> PartialVMResult<&NativeFunction> {
+        self.native.as_ref().ok_or_else(|| {
             PartialVMError::new(StatusCode::UNREACHABLE)
                 .with_message("Missing Native Function".to_string())
         })
@@ -2035,7 +2547,10 @@ struct FieldInstantiation {
 
 struct StructInfo {
     struct_tag: Option<StructTag>,
-    struct_layout: Option<MoveStructLayout>,
+    // the layout, along with its number of nodes (not counting the struct itself) and its height
+    struct_layout: Option<(MoveStructLayout, usize, usize)>,
+    // tick of the type cache the entry was last used at
+    last_used: AtomicU64,
 }
 
 impl StructInfo {
@@ -2043,23 +2558,158 @@ impl StructInfo {
         Self {
             struct_tag: None,
             struct_layout: None,
+            last_used: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Clone for StructInfo {
+    fn clone(&self) -> Self {
+        Self {
+            struct_tag: self.struct_tag.clone(),
+            struct_layout: self.struct_layout.clone(),
+            last_used: AtomicU64::new(self.last_used.load(Ordering::Relaxed)),
         }
     }
 }
 
+// The type tags and layouts of struct instantiations.
+// The cache holds at most `max_size` instantiations: when full, the least recently used ones are
+// evicted, a tenth of the cache at a time so that the cost of evicting is amortized. Entries are
+// used under the read lock, so the recency of entries is tracked with atomics.
+// Like the ModuleCache, a TypeCache may sit on top of a frozen cache, which is looked up first and
+// never evicted from.
 pub(crate) struct TypeCache {
+    frozen: Option<Arc<TypeCache>>,
     structs: HashMap<CachedStructIndex, HashMap<Vec<Type>, StructInfo>>,
+    len: usize,
+    max_size: usize,
+    tick: AtomicU64,
+}
+
+impl Clone for TypeCache {
+    fn clone(&self) -> Self {
+        Self {
+            frozen: self.frozen.clone(),
+            structs: self.structs.clone(),
+            len: self.len,
+            max_size: self.max_size,
+            tick: AtomicU64::new(self.tick.load(Ordering::Relaxed)),
+        }
+    }
 }
 
 impl TypeCache {
-    fn new() -> Self {
+    fn new(max_size: usize) -> Self {
         Self {
+            frozen: None,
             structs: HashMap::new(),
+            len: 0,
+            max_size,
+            tick: AtomicU64::new(0),
+        }
+    }
+
+    fn new_on_top_of(frozen: Arc<TypeCache>, max_size: usize) -> Self {
+        Self {
+            frozen: Some(frozen),
+            ..Self::new(max_size)
+        }
+    }
+
+    // Looks up the information selected by `get` for the given struct instantiation, in the
+    // frozen cache first
+    fn find<T>(
+        &self,
+        gidx: CachedStructIndex,
+        ty_args: &[Type],
+        get: &impl Fn(&StructInfo) -> Option<&T>,
+    ) -> Option<&T> {
+        self.frozen
+            .as_ref()
+            .and_then(|frozen| frozen.find(gidx, ty_args, get))
+            .or_else(|| self.get(gidx, ty_args).and_then(get))
+    }
+
+    // Returns the entry of the given instantiation, marking it as used
+    fn get(&self, gidx: CachedStructIndex, ty_args: &[Type]) -> Option<&StructInfo> {
+        let info = self.structs.get(&gidx)?.get(ty_args)?;
+        let tick = self.tick.fetch_add(1, Ordering::Relaxed) + 1;
+        info.last_used.store(tick, Ordering::Relaxed);
+        Some(info)
+    }
+
+    // Returns the entry of the given instantiation, inserting it if needed, along with the number
+    // of entries evicted to make room for it
+    fn get_or_insert(
+        &mut self,
+        gidx: CachedStructIndex,
+        ty_args: &[Type],
+    ) -> (&mut StructInfo, usize) {
+        let cached = self
+            .structs
+            .get(&gidx)
+            .map_or(false, |struct_map| struct_map.contains_key(ty_args));
+        let mut evicted = 0;
+        if !cached {
+            if self.len >= self.max_size {
+                evicted = self.evict();
+            }
+            self.len += 1;
+        }
+        let tick = *self.tick.get_mut() + 1;
+        *self.tick.get_mut() = tick;
+        let info = self
+            .structs
+            .entry(gidx)
+            .or_insert_with(HashMap::new)
+            .entry(ty_args.to_vec())
+            .or_insert_with(StructInfo::new);
+        *info.last_used.get_mut() = tick;
+        (info, evicted)
+    }
+
+    // Evicts the least recently used tenth of the cache (at least one entry), and returns the
+    // number of entries evicted
+    fn evict(&mut self) -> usize {
+        let mut last_used = self
+            .structs
+            .values_mut()
+            .flat_map(|struct_map| struct_map.values_mut())
+            .map(|info| *info.last_used.get_mut())
+            .collect::<Vec<_>>();
+        if last_used.is_empty() {
+            return 0;
+        }
+        let count = std::cmp::max(self.max_size / 10, 1).min(last_used.len());
+        let (_, threshold, _) = last_used.select_nth_unstable(count - 1);
+        let threshold = *threshold;
+
+        let mut evicted = 0;
+        for struct_map in self.structs.values_mut() {
+            struct_map.retain(|_, info| {
+                let evict = evicted < count && *info.last_used.get_mut() <= threshold;
+                if evict {
+                    evicted += 1;
+                }
+                !evict
+            });
         }
+        self.structs.retain(|_, struct_map| !struct_map.is_empty());
+        self.len -= evicted;
+        evicted
     }
 }
 
-const VALUE_DEPTH_MAX: usize = 128;
+// The module and type caches of a loader, frozen to be shared with other loaders
+pub(crate) struct FrozenCaches {
+    module_cache: Arc<ModuleCache>,
+    type_cache: Arc<TypeCache>,
+    natives: NativeFunctions,
+    vm_config: VMConfig,
+    metrics: Arc<dyn VMMetrics>,
+    source_locators: BTreeMap<ModuleId, Arc<dyn SourceLocator>>,
+}
 
 impl Loader {
     fn struct_gidx_to_type_tag(
@@ -2067,13 +2717,15 @@ impl Loader {
         gidx: CachedStructIndex,
         ty_args: &[Type],
     ) -> PartialVMResult<StructTag> {
-        if let Some(struct_map) = self.type_cache.read().structs.get(&gidx) {
-            if let Some(struct_info) = struct_map.get(ty_args) {
-                if let Some(struct_tag) = &struct_info.struct_tag {
-                    return Ok(struct_tag.clone());
-                }
-            }
+        if let Some(struct_tag) = self
+            .type_cache
+            .read()
+            .find(gidx, ty_args, &|info| info.struct_tag.as_ref())
+        {
+            self.metrics.inc_type_cache_hits();
+            return Ok(struct_tag.clone());
         }
+        self.metrics.inc_type_cache_misses();
 
         let ty_arg_tags = ty_args
             .iter()
@@ -2087,14 +2739,12 @@ impl Loader {
             type_params: ty_arg_tags,
         };
 
-        self.type_cache
-            .write()
-            .structs
-            .entry(gidx)
-            .or_insert_with(HashMap::new)
-            .entry(ty_args.to_vec())
-            .or_insert_with(StructInfo::new)
-            .struct_tag = Some(struct_tag.clone());
+        let mut type_cache = self.type_cache.write();
+        let (struct_info, evicted) = type_cache.get_or_insert(gidx, ty_args);
+        struct_info.struct_tag = Some(struct_tag.clone());
+        if evicted > 0 {
+            self.metrics.inc_type_cache_evictions(evicted as u64);
+        }
 
         Ok(struct_tag)
     }
@@ -2126,14 +2776,32 @@ impl Loader {
         gidx: CachedStructIndex,
         ty_args: &[Type],
         depth: usize,
+        budget: &mut LayoutBudget,
     ) -> PartialVMResult<MoveStructLayout> {
-        if let Some(struct_map) = self.type_cache.read().structs.get(&gidx) {
-            if let Some(struct_info) = struct_map.get(ty_args) {
-                if let Some(layout) = &struct_info.struct_layout {
-                    return Ok(layout.clone());
-                }
-            }
+        if let Some((layout, nodes, height)) = self
+            .type_cache
+            .read()
+            .find(gidx, ty_args, &|info| info.struct_layout.as_ref())
+        {
+            self.metrics.inc_type_cache_hits();
+            // charge the layout as if it was converted, so that limits do not depend on what is
+            // cached
+            budget.visit(depth + height, *nodes, &self.vm_config)?;
+            return Ok(layout.clone());
         }
+        self.metrics.inc_type_cache_misses();
+
+        if budget
+            .path
+            .iter()
+            .any(|(idx, args)| *idx == gidx && args.as_slice() == ty_args)
+        {
+            return Err(PartialVMError::new(StatusCode::CYCLIC_TYPE_LAYOUT)
+                .with_message("cyclic struct instantiation in type layout".to_string()));
+        }
+        budget.path.push((gidx, ty_args.to_vec()));
+        let nodes_before = budget.nodes;
+        let max_depth_before = mem::replace(&mut budget.max_depth, depth);
 
         let struct_type = self.module_cache.read().struct_at(gidx);
         let field_tys = struct_type
@@ -2143,26 +2811,33 @@ impl Loader {
             .collect::<PartialVMResult<Vec<_>>>()?;
         let field_layouts = field_tys
             .iter()
-            .map(|ty| self.type_to_type_layout_impl(ty, depth + 1))
+            .map(|ty| self.type_to_type_layout_impl(ty, depth + 1, budget))
             .collect::<PartialVMResult<Vec<_>>>()?;
         let struct_layout = MoveStructLayout::new(field_layouts);
 
-        self.type_cache
-            .write()
-            .structs
-            .entry(gidx)
-            .or_insert_with(HashMap::new)
-            .entry(ty_args.to_vec())
-            .or_insert_with(StructInfo::new)
-            .struct_layout = Some(struct_layout.clone());
+        budget.path.pop();
+        // the struct node itself is counted by the caller
+        let nodes = budget.nodes - nodes_before;
+        let height = budget.max_depth - depth;
+        budget.max_depth = max(budget.max_depth, max_depth_before);
+
+        let mut type_cache = self.type_cache.write();
+        let (struct_info, evicted) = type_cache.get_or_insert(gidx, ty_args);
+        struct_info.struct_layout = Some((struct_layout.clone(), nodes, height));
+        if evicted > 0 {
+            self.metrics.inc_type_cache_evictions(evicted as u64);
+        }
 
         Ok(struct_layout)
     }
 
-    fn type_to_type_layout_impl(&self, ty: &Type, depth: usize) -> PartialVMResult<MoveTypeLayout> {
-        if depth > VALUE_DEPTH_MAX {
-            return Err(PartialVMError::new(StatusCode::VM_MAX_VALUE_DEPTH_REACHED));
-        }
+    fn type_to_type_layout_impl(
+        &self,
+        ty: &Type,
+        depth: usize,
+        budget: &mut LayoutBudget,
+    ) -> PartialVMResult<MoveTypeLayout> {
+        budget.visit(depth, 1, &self.vm_config)?;
         Ok(match ty {
             Type::Bool => MoveTypeLayout::Bool,
             Type::U8 => MoveTypeLayout::U8,
@@ -2170,15 +2845,20 @@ impl Loader {
             Type::U128 => MoveTypeLayout::U128,
             Type::Address => MoveTypeLayout::Address,
             Type::Signer => MoveTypeLayout::Signer,
-            Type::Vector(ty) => {
-                MoveTypeLayout::Vector(Box::new(self.type_to_type_layout_impl(ty, depth + 1)?))
-            }
-            Type::Struct(gidx) => {
-                MoveTypeLayout::Struct(self.struct_gidx_to_type_layout(*gidx, &[], depth)?)
-            }
-            Type::StructInstantiation(gidx, ty_args) => {
-                MoveTypeLayout::Struct(self.struct_gidx_to_type_layout(*gidx, ty_args, depth)?)
-            }
+            Type::Vector(ty) => MoveTypeLayout::Vector(Box::new(self.type_to_type_layout_impl(
+                ty,
+                depth + 1,
+                budget,
+            )?)),
+            Type::Struct(gidx) => MoveTypeLayout::Struct(self.struct_gidx_to_type_layout(
+                *gidx,
+                &[],
+                depth,
+                budget,
+            )?),
+            Type::StructInstantiation(gidx, ty_args) => MoveTypeLayout::Struct(
+                self.struct_gidx_to_type_layout(*gidx, ty_args, depth, budget)?,
+            ),
             Type::Reference(_) | Type::MutableReference(_) | Type::TyParam(_) => {
                 return Err(
                     PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
@@ -2192,7 +2872,36 @@ impl Loader {
         self.type_to_type_tag_impl(ty)
     }
     pub(crate) fn type_to_type_layout(&self, ty: &Type) -> PartialVMResult<MoveTypeLayout> {
-        self.type_to_type_layout_impl(ty, 1)
+        self.type_to_type_layout_impl(ty, 1, &mut LayoutBudget::default())
+    }
+}
+
+// Bounds the conversion of a type to a layout, whose size may be exponential in the size of the
+// type, e.g., for `S<S<S<u8>>>` with `struct S<T> { a: T, b: T }`.
+#[derive(Default)]
+struct LayoutBudget {
+    // nodes of the layout converted so far
+    nodes: usize,
+    // depth of the deepest node converted so far
+    max_depth: usize,
+    // struct instantiations being converted, from the outermost one, to detect cycles
+    path: Vec<(CachedStructIndex, Vec<Type>)>,
+}
+
+impl LayoutBudget {
+    // Accounts for `nodes` nodes of the layout, the deepest one at `depth`
+    fn visit(&mut self, depth: usize, nodes: usize, vm_config: &VMConfig) -> PartialVMResult<()> {
+        if depth > vm_config.max_value_nest_depth {
+            return Err(PartialVMError::new(StatusCode::VM_MAX_VALUE_DEPTH_REACHED));
+        }
+        self.max_depth = max(self.max_depth, depth);
+        self.nodes += nodes;
+        if matches!(vm_config.max_type_layout_nodes, Some(max_nodes) if self.nodes > max_nodes) {
+            return Err(PartialVMError::new(
+                StatusCode::VM_MAX_TYPE_LAYOUT_NODES_REACHED,
+            ));
+        }
+        Ok(())
     }
 }
 
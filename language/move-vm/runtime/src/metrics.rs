@@ -0,0 +1,47 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hook for collecting metrics about the work done by the VM, e.g., to export them to a
+//! monitoring system such as Prometheus.
+//!
+//! `VMMetrics` is installed on a `MoveVM` (see `MoveVM::new_with_metrics`) and shared by all the
+//! sessions created from it, possibly from several threads. All its methods default to doing
+//! nothing, so that an implementation only overrides the ones it exports.
+
+use std::time::Duration;
+
+/// Counters and histograms updated by the VM
+pub trait VMMetrics: Send + Sync {
+    /// Called when a module is found in the code cache
+    fn inc_module_cache_hits(&self) {}
+
+    /// Called when a module is not found in the code cache, and so gets loaded from storage along
+    /// with its dependencies
+    fn inc_module_cache_misses(&self) {}
+
+    /// Called when a script which is not in the code cache gets loaded
+    fn inc_scripts_loaded(&self) {}
+
+    /// Called with the time spent running the bytecode verifier on a module or a script (except
+    /// on wasm32-unknown-unknown, which has no clock)
+    fn observe_verification_time(&self, _duration: Duration) {}
+
+    /// Called with the number of instructions executed by a call to the interpreter, whether it
+    /// succeeded or not
+    fn inc_instructions_executed(&self, _count: u64) {}
+
+    /// Called when the type tag or the layout of a struct instantiation is found in the type cache
+    fn inc_type_cache_hits(&self) {}
+
+    /// Called when the type tag or the layout of a struct instantiation is not found in the type
+    /// cache, and so gets computed
+    fn inc_type_cache_misses(&self) {}
+
+    /// Called with the number of struct instantiations evicted from the type cache when it is full
+    fn inc_type_cache_evictions(&self, _count: u64) {}
+}
+
+/// Metrics which are not collected, the default of a `MoveVM`
+pub struct NoMetrics;
+
+impl VMMetrics for NoMetrics {}
@@ -5,15 +5,25 @@
 use std::sync::Arc;
 
 use crate::{
-    data_cache::TransactionDataCache, native_extensions::NativeContextExtensions,
-    native_functions::NativeFunction, runtime::VMRuntime, session::Session,
+    config::VMConfig,
+    data_cache::TransactionDataCache,
+    loader::{FrozenCaches, Loader},
+    metrics::{NoMetrics, VMMetrics},
+    native_extensions::NativeContextExtensions,
+    native_functions::NativeFunction,
+    runtime::VMRuntime,
+    session::Session,
+    source_locator::SourceLocator,
 };
 use move_binary_format::{
     errors::{Location, VMResult},
     CompiledModule,
 };
 use move_core_types::{
-    account_address::AccountAddress, identifier::Identifier, language_storage::ModuleId,
+    account_address::AccountAddress,
+    effects::{ChangeSet, Op},
+    identifier::Identifier,
+    language_storage::ModuleId,
     resolver::MoveResolver,
 };
 
@@ -21,12 +31,68 @@ pub struct MoveVM {
     runtime: VMRuntime,
 }
 
+/// A frozen copy of the code cache of a `MoveVM` (see `MoveVM::freeze_loader_cache`), e.g., with
+/// the framework modules loaded. New VMs created from it with `MoveVM::new_from_frozen_cache` start
+/// with those modules loaded instead of deserializing and verifying them again. It can be shared
+/// between threads through an `Arc`.
+pub struct FrozenLoaderCache {
+    caches: FrozenCaches,
+}
+
 impl MoveVM {
     pub fn new(
         natives: impl IntoIterator<Item = (AccountAddress, Identifier, Identifier, NativeFunction)>,
+    ) -> VMResult<Self> {
+        Self::new_with_config(natives, VMConfig::default())
+    }
+
+    /// Create a new Move VM enforcing the limits and checks of the given configuration.
+    pub fn new_with_config(
+        natives: impl IntoIterator<Item = (AccountAddress, Identifier, Identifier, NativeFunction)>,
+        vm_config: VMConfig,
+    ) -> VMResult<Self> {
+        Self::new_with_metrics(natives, vm_config, Arc::new(NoMetrics))
+    }
+
+    /// Create a new Move VM, as in `new_with_config`, reporting what it does to the given metrics.
+    pub fn new_with_metrics(
+        natives: impl IntoIterator<Item = (AccountAddress, Identifier, Identifier, NativeFunction)>,
+        vm_config: VMConfig,
+        metrics: Arc<dyn VMMetrics>,
     ) -> VMResult<Self> {
         Ok(Self {
-            runtime: VMRuntime::new(natives).map_err(|err| err.finish(Location::Undefined))?,
+            runtime: VMRuntime::new(natives, vm_config, metrics)
+                .map_err(|err| err.finish(Location::Undefined))?,
+        })
+    }
+
+    /// Create a new Move VM whose code cache starts as the given frozen cache. The VM has the
+    /// natives, configuration and metrics of the VM the cache was frozen from.
+    ///
+    /// The frozen cache is shared with the new VM rather than copied, and looked up before the
+    /// VM's own code cache. Loading more modules only adds them to the code cache of the new VM,
+    /// so the frozen cache can keep being used to create other VMs.
+    pub fn new_from_frozen_cache(cache: &FrozenLoaderCache) -> Self {
+        Self {
+            runtime: VMRuntime::new_with_loader(Loader::new_from_frozen(&cache.caches)),
+        }
+    }
+
+    // Create a new Move VM with the natives and configuration of this VM, but an empty code cache,
+    // so that every module it uses is read from storage
+    pub(crate) fn new_with_empty_cache(&self) -> Self {
+        Self {
+            runtime: VMRuntime::new_with_loader(self.runtime.loader().new_with_empty_cache()),
+        }
+    }
+
+    /// Freeze a copy of the code cache of this VM, to create other VMs from it. The modules and
+    /// types loaded are shared with those VMs rather than copied.
+    ///
+    /// Modules loaded into this VM afterwards are not added to the frozen cache.
+    pub fn freeze_loader_cache(&self) -> Arc<FrozenLoaderCache> {
+        Arc::new(FrozenLoaderCache {
+            caches: self.runtime.loader().freeze(),
         })
     }
 
@@ -44,6 +110,9 @@ impl MoveVM {
     ///     cases where this may not be necessary, with the most notable one being the common module
     ///     publishing flow: you can keep using the same Move VM if you publish some modules in a Session
     ///     and apply the effects to the storage when the Session ends.
+    ///   - Instead of creating a new Move VM, the stale parts of the code cache can be invalidated
+    ///     with `mark_loader_cache_as_invalid` or `mark_module_as_invalid`, and flushed with
+    ///     `flush_loader_cache_if_invalidated` once no Session is alive.
     pub fn new_session<'r, S: MoveResolver>(&self, remote: &'r S) -> Session<'r, '_, S> {
         self.runtime.new_session(remote)
     }
@@ -57,6 +126,56 @@ impl MoveVM {
         self.runtime.new_session_with_extensions(remote, extensions)
     }
 
+    /// Mark the whole code cache of the VM as invalid, e.g., after the storage has been modified by
+    /// an outer environment. The cache is flushed by the next call to
+    /// `flush_loader_cache_if_invalidated`.
+    pub fn mark_loader_cache_as_invalid(&self) {
+        self.runtime.loader().mark_as_invalid()
+    }
+
+    /// Mark a module as invalid in the code cache of the VM, e.g., after it has been upgraded in
+    /// storage. On the next call to `flush_loader_cache_if_invalidated`, the module is removed from
+    /// the cache, along with the modules depending on it, and loaded again when next used.
+    ///
+    /// Modules upgraded in a Session are marked automatically.
+    pub fn mark_module_as_invalid(&self, module_id: &ModuleId) {
+        self.runtime
+            .loader()
+            .mark_module_as_invalid(module_id.clone())
+    }
+
+    /// Mark the modules removed by the given change set as invalid in the code cache of the VM.
+    /// This is to be called once the change set has been applied to the storage, so that the
+    /// removed modules, along with the modules depending on them, are dropped by the next call to
+    /// `flush_loader_cache_if_invalidated`.
+    pub fn mark_removed_modules_as_invalid(&self, change_set: &ChangeSet) {
+        for (addr, name, op) in change_set.modules() {
+            if let Op::Delete = op {
+                self.runtime
+                    .loader()
+                    .mark_module_as_invalid(ModuleId::new(addr, name.clone()))
+            }
+        }
+    }
+
+    /// Flush the parts of the code cache marked as invalid, so that a long-lived VM keeps in sync
+    /// with the storage when modules get upgraded.
+    ///
+    /// This must only be called when there is no outstanding Session created from this VM, e.g.,
+    /// after the effects of a Session upgrading modules have been applied to the storage.
+    pub fn flush_loader_cache_if_invalidated(&self) {
+        self.runtime.loader().flush_if_invalidated()
+    }
+
+    /// Resolve aborts and arithmetic errors raised in the given module to positions in its source
+    /// code with the given locator, e.g., built from the source map of the module. The position is
+    /// attached to the error (see `VMError::source_location`).
+    ///
+    /// This replaces the locator previously set for the module, if any.
+    pub fn set_source_locator(&self, module_id: ModuleId, locator: Arc<dyn SourceLocator>) {
+        self.runtime.loader().set_source_locator(module_id, locator)
+    }
+
     /// Load a module into VM's code cache
     pub fn load_module<'r, S: MoveResolver>(
         &self,
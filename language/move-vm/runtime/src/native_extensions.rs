@@ -3,7 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use better_any::{Tid, TidAble, TidExt};
-use std::{any::TypeId, collections::HashMap};
+use move_binary_format::errors::PartialVMResult;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
 
 /// A data type to represent a heterogeneous collection of extensions which are available to
 /// native functions. A value to this is passed into the session function execution.
@@ -13,11 +17,59 @@ use std::{any::TypeId, collections::HashMap};
 /// avoids that extensions need to have `'static` lifetime, which `Any` requires. In order to make a
 /// struct suitable to be a 'Tid', use `#[derive(Tid)]` in the struct declaration. (See also
 /// tests at the end of this module.)
+///
+/// Extensions live as long as this value, which can be carried from one session to the next: it
+/// is returned by `Session::finish_with_extensions` and can be passed to the next session created
+/// with `new_session_with_extensions`.
 #[derive(Default)]
 pub struct NativeContextExtensions<'a> {
     map: HashMap<TypeId, Box<dyn Tid<'a>>>,
+    // the hooks of the session extensions, in the order they were added
+    hooks: Vec<(TypeId, SessionHooks<'a>)>,
+}
+
+/// An extension taking part in the lifecycle of the sessions it is attached to, and producing an
+/// output which the adapter carries into storage at the end, e.g., the changes to tables.
+///
+/// Session extensions are added with `NativeContextExtensions::add_session_extension`, and their
+/// output is extracted with `NativeContextExtensions::finish`.
+pub trait SessionExtension<'a>: TidAble<'a> {
+    type Output;
+
+    /// Called when a session is created with the extension, including sessions the extension is
+    /// carried over to from a previous session.
+    fn on_session_start(&mut self) {}
+
+    /// Called when a session the extension is attached to finishes with
+    /// `Session::finish_with_extensions`.
+    fn on_session_finish(&mut self) -> PartialVMResult<()> {
+        Ok(())
+    }
+
+    /// Called by `Session::checkpoint`, returns what `rollback_to` needs to restore the current
+    /// state of the extension, e.g., a copy of the changes it accumulated so far.
+    fn checkpoint(&self) -> PartialVMResult<Box<dyn Any>> {
+        Ok(Box::new(()))
+    }
+
+    /// Called by `Session::rollback_to` with what `checkpoint` returned, to undo what happened to
+    /// the extension since.
+    fn rollback_to(&mut self, _checkpoint: Box<dyn Any>) {}
+
+    /// Consumes the extension and returns what it accumulated.
+    fn finish(self) -> PartialVMResult<Self::Output>;
+}
+
+struct SessionHooks<'a> {
+    on_start: fn(&mut NativeContextExtensions<'a>),
+    on_finish: fn(&mut NativeContextExtensions<'a>) -> PartialVMResult<()>,
+    checkpoint: fn(&NativeContextExtensions<'a>) -> PartialVMResult<Box<dyn Any>>,
+    rollback_to: fn(&mut NativeContextExtensions<'a>, Box<dyn Any>),
 }
 
+/// The state of the session extensions at some point, by extension
+pub(crate) struct ExtensionsCheckpoint(Vec<(TypeId, Box<dyn Any>)>);
+
 impl<'a> NativeContextExtensions<'a> {
     pub fn add<T: TidAble<'a>>(&mut self, ext: T) {
         assert!(
@@ -26,6 +78,58 @@ impl<'a> NativeContextExtensions<'a> {
         )
     }
 
+    /// Adds an extension whose hooks get called over the lifecycle of the sessions it is attached
+    /// to.
+    pub fn add_session_extension<T: SessionExtension<'a>>(&mut self, ext: T) {
+        self.add(ext);
+        self.hooks.push((
+            T::id(),
+            SessionHooks {
+                on_start: |exts| exts.get_mut::<T>().on_session_start(),
+                on_finish: |exts| exts.get_mut::<T>().on_session_finish(),
+                checkpoint: |exts| exts.get::<T>().checkpoint(),
+                rollback_to: |exts, checkpoint| exts.get_mut::<T>().rollback_to(checkpoint),
+            },
+        ));
+    }
+
+    /// Removes an extension and returns its output.
+    pub fn finish<T: SessionExtension<'a>>(&mut self) -> PartialVMResult<T::Output> {
+        self.remove::<T>().finish()
+    }
+
+    pub(crate) fn on_session_start(&mut self) {
+        for i in 0..self.hooks.len() {
+            (self.hooks[i].1.on_start)(self)
+        }
+    }
+
+    pub(crate) fn on_session_finish(&mut self) -> PartialVMResult<()> {
+        for i in 0..self.hooks.len() {
+            (self.hooks[i].1.on_finish)(self)?
+        }
+        Ok(())
+    }
+
+    pub(crate) fn checkpoint(&self) -> PartialVMResult<ExtensionsCheckpoint> {
+        let checkpoints = self
+            .hooks
+            .iter()
+            .map(|(id, hooks)| Ok((*id, (hooks.checkpoint)(self)?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(ExtensionsCheckpoint(checkpoints))
+    }
+
+    // Rolls back the extensions which are still there, extensions added since the checkpoint are
+    // left as they are
+    pub(crate) fn rollback_to(&mut self, checkpoint: ExtensionsCheckpoint) {
+        for (id, checkpoint) in checkpoint.0 {
+            if let Some(i) = self.hooks.iter().position(|(hook_id, _)| *hook_id == id) {
+                (self.hooks[i].1.rollback_to)(self, checkpoint)
+            }
+        }
+    }
+
     pub fn get<T: TidAble<'a>>(&self) -> &T {
         self.map
             .get(&T::id())
@@ -45,6 +149,7 @@ impl<'a> NativeContextExtensions<'a> {
     }
 
     pub fn remove<T: TidAble<'a>>(&mut self) -> T {
+        self.hooks.retain(|(id, _)| *id != T::id());
         // can't use expect below because it requires `T: Debug`.
         match self
             .map
@@ -60,8 +165,10 @@ impl<'a> NativeContextExtensions<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::native_extensions::NativeContextExtensions;
+    use crate::native_extensions::{NativeContextExtensions, SessionExtension};
     use better_any::{Tid, TidAble};
+    use move_binary_format::errors::PartialVMResult;
+    use std::any::Any;
 
     #[derive(Tid)]
     struct Ext<'a> {
@@ -80,4 +187,57 @@ mod tests {
         let e1 = exts.remove::<Ext>();
         assert_eq!(*e1.a, 25)
     }
+
+    #[derive(Tid)]
+    struct Counter<'a> {
+        sessions: &'a mut u64,
+        finished: u64,
+    }
+
+    impl<'a> SessionExtension<'a> for Counter<'a> {
+        type Output = (u64, u64);
+
+        fn on_session_start(&mut self) {
+            *self.sessions += 1;
+        }
+
+        fn on_session_finish(&mut self) -> PartialVMResult<()> {
+            self.finished += 1;
+            Ok(())
+        }
+
+        fn checkpoint(&self) -> PartialVMResult<Box<dyn Any>> {
+            Ok(Box::new(self.finished))
+        }
+
+        fn rollback_to(&mut self, checkpoint: Box<dyn Any>) {
+            self.finished = *checkpoint.downcast::<u64>().unwrap();
+        }
+
+        fn finish(self) -> PartialVMResult<(u64, u64)> {
+            Ok((*self.sessions, self.finished))
+        }
+    }
+
+    #[test]
+    fn session_ext() {
+        let mut v: u64 = 0;
+        let mut exts = NativeContextExtensions::default();
+        exts.add_session_extension(Counter {
+            sessions: &mut v,
+            finished: 0,
+        });
+        for _ in 0..2 {
+            exts.on_session_start();
+            exts.on_session_finish().unwrap();
+        }
+        let checkpoint = exts.checkpoint().unwrap();
+        exts.on_session_finish().unwrap();
+        exts.rollback_to(checkpoint);
+        exts.on_session_start();
+        assert_eq!(exts.finish::<Counter>().unwrap(), (3, 2));
+        assert!(exts.hooks.is_empty());
+        // hooks are not called on removed extensions
+        exts.on_session_start();
+    }
 }
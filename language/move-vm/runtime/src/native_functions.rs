@@ -8,31 +8,52 @@ use crate::{
 use move_binary_format::errors::{PartialVMError, PartialVMResult};
 use move_core_types::{
     account_address::AccountAddress,
-    gas_schedule::CostTable,
-    identifier::Identifier,
-    language_storage::TypeTag,
-    value::MoveTypeLayout,
+    gas_schedule::{GasCarrier, InternalGasUnits},
+    identifier::{IdentStr, Identifier},
+    language_storage::{ModuleId, TypeTag},
+    value::{MoveTypeLayout, MoveValue},
     vm_status::{StatusCode, StatusType},
 };
 use move_vm_types::{
-    data_store::DataStore, gas_schedule::GasStatus, loaded_data::runtime_types::Type,
+    data_store::DataStore, gas_schedule::GasMeter, loaded_data::runtime_types::Type,
     natives::function::NativeResult, values::Value,
 };
 use std::{
     collections::{HashMap, VecDeque},
     fmt::Write,
+    sync::Arc,
 };
 
-pub type NativeFunction =
+/// A native function, which may capture state (e.g., configuration of the adapter such as feature
+/// flags or the chain id) in addition to what it gets from the `NativeContext`.
+pub type NativeFunction = Arc<
+    dyn Fn(&mut NativeContext, Vec<Type>, VecDeque<Value>) -> PartialVMResult<NativeResult>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// A native function that does not capture any state, so that it can be listed in constant tables.
+pub type StatelessNativeFunction =
     fn(&mut NativeContext, Vec<Type>, VecDeque<Value>) -> PartialVMResult<NativeResult>;
 
 pub type NativeFunctionTable = Vec<(AccountAddress, Identifier, Identifier, NativeFunction)>;
 
 pub fn make_table(
     addr: AccountAddress,
-    elems: &[(&str, &str, NativeFunction)],
+    elems: &[(&str, &str, StatelessNativeFunction)],
 ) -> NativeFunctionTable {
-    make_table_from_iter(addr, elems.iter().cloned())
+    make_table_from_iter(
+        addr,
+        elems
+            .iter()
+            .map(|(module_name, func_name, func)| (*module_name, *func_name, make_native(*func))),
+    )
+}
+
+/// Turns a function that does not capture any state into a `NativeFunction`.
+pub fn make_native(func: StatelessNativeFunction) -> NativeFunction {
+    Arc::new(func)
 }
 
 pub fn make_table_from_iter<S: Into<Box<str>>>(
@@ -52,6 +73,7 @@ pub fn make_table_from_iter<S: Into<Box<str>>>(
         .collect()
 }
 
+#[derive(Clone)]
 pub(crate) struct NativeFunctions(
     HashMap<AccountAddress, HashMap<String, HashMap<String, NativeFunction>>>,
 );
@@ -88,27 +110,34 @@ impl NativeFunctions {
 pub struct NativeContext<'a, 'b> {
     interpreter: &'a mut Interpreter,
     data_store: &'a mut dyn DataStore,
-    gas_status: &'a GasStatus<'a>,
+    gas_meter: &'a dyn GasMeter,
     resolver: &'a Resolver<'a>,
     extensions: &'a mut NativeContextExtensions<'b>,
+    // size of the events saved, which the interpreter charges for once the native returns
+    event_bytes: u64,
 }
 
 impl<'a, 'b> NativeContext<'a, 'b> {
     pub(crate) fn new(
         interpreter: &'a mut Interpreter,
         data_store: &'a mut dyn DataStore,
-        gas_status: &'a mut GasStatus,
+        gas_meter: &'a dyn GasMeter,
         resolver: &'a Resolver<'a>,
         extensions: &'a mut NativeContextExtensions<'b>,
     ) -> Self {
         Self {
             interpreter,
             data_store,
-            gas_status,
+            gas_meter,
             resolver,
             extensions,
+            event_bytes: 0,
         }
     }
+
+    pub(crate) fn event_bytes(&self) -> u64 {
+        self.event_bytes
+    }
 }
 
 impl<'a, 'b> NativeContext<'a, 'b> {
@@ -117,8 +146,30 @@ impl<'a, 'b> NativeContext<'a, 'b> {
             .debug_print_stack_trace(buf, self.resolver.loader())
     }
 
-    pub fn cost_table(&self) -> &CostTable {
-        self.gas_status.cost_table()
+    /// Return the cost of the native operation with the given index in the native cost table over
+    /// data of the given size, as priced by the gas meter of the execution.
+    pub fn native_gas(
+        &self,
+        native_table_idx: impl Into<u8>,
+        size: usize,
+    ) -> InternalGasUnits<GasCarrier> {
+        self.gas_meter.native_cost(native_table_idx.into(), size)
+    }
+
+    /// Returns the value of a gas parameter registered by the embedder (see
+    /// `NativeGasParameters` and `GasStatus::with_native_parameters`), or `None` if there is no
+    /// such parameter, e.g., because the gas meter is unmetered.
+    pub fn native_gas_parameter(&self, name: &str) -> Option<InternalGasUnits<GasCarrier>> {
+        self.gas_meter.native_parameter(name)
+    }
+
+    /// Returns the gas left when the native was called, or `None` if gas is not metered. The cost
+    /// returned by the native is charged once it returns, so a native whose work depends on its
+    /// inputs, e.g., batch verification, can check the cost of the work done so far against the
+    /// balance and fail with `OUT_OF_GAS` as soon as it exceeds it, rather than finishing all the
+    /// work first.
+    pub fn gas_balance(&self) -> Option<InternalGasUnits<GasCarrier>> {
+        self.gas_meter.gas_balance()
     }
 
     pub fn save_event(
@@ -129,7 +180,10 @@ impl<'a, 'b> NativeContext<'a, 'b> {
         val: Value,
     ) -> PartialVMResult<bool> {
         match self.data_store.emit_event(guid, seq_num, ty, val) {
-            Ok(()) => Ok(true),
+            Ok(size) => {
+                self.event_bytes += size;
+                Ok(true)
+            }
             Err(e) if e.major_status().status_type() == StatusType::InvariantViolation => Err(e),
             Err(_) => Ok(false),
         }
@@ -139,6 +193,23 @@ impl<'a, 'b> NativeContext<'a, 'b> {
         self.data_store.events()
     }
 
+    /// Returns the type of the struct `address::module::name` instantiated with `ty_args`, loading
+    /// the module declaring it if needed. Natives can use it to get the type of well-known structs
+    /// (e.g., `Option<T>`) they construct values of.
+    pub fn load_struct(
+        &self,
+        address: AccountAddress,
+        module: &IdentStr,
+        name: &IdentStr,
+        ty_args: Vec<Type>,
+    ) -> PartialVMResult<Type> {
+        let module_id = ModuleId::new(address, module.to_owned());
+        self.resolver
+            .loader()
+            .load_struct_type(&module_id, name, ty_args, &*self.data_store)
+            .map_err(|e| e.to_partial())
+    }
+
     pub fn type_to_type_tag(&self, ty: &Type) -> PartialVMResult<TypeTag> {
         self.resolver.loader().type_to_type_tag(ty)
     }
@@ -151,6 +222,19 @@ impl<'a, 'b> NativeContext<'a, 'b> {
         }
     }
 
+    /// Converts a value of type `ty` into a `MoveValue`, e.g., to inspect the fields of a struct
+    /// passed to the native.
+    pub fn value_to_move_value(&self, val: &Value, ty: &Type) -> PartialVMResult<MoveValue> {
+        let layout = self.resolver.type_to_type_layout(ty)?;
+        val.to_move_value(&layout)
+    }
+
+    /// Creates a value of type `ty` from a `MoveValue`, e.g., to return a struct from the native.
+    pub fn move_value_to_value(&self, val: &MoveValue, ty: &Type) -> PartialVMResult<Value> {
+        let layout = self.resolver.type_to_type_layout(ty)?;
+        Value::from_move_value(val, &layout)
+    }
+
     pub fn extensions(&self) -> &NativeContextExtensions<'b> {
         self.extensions
     }
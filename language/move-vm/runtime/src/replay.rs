@@ -0,0 +1,449 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deterministic record and replay of the execution of a session, e.g., to debug executions
+//! diverging across versions of the VM.
+//!
+//! `record` executes a session over a `RecordingResolver`, which captures everything read from
+//! storage, records the results of the natives with external inputs (e.g., the time or
+//! randomness) given, and returns an `ExecutionTrace` along with the effects of the session and
+//! the gas it used. The trace can be saved to a file and loaded elsewhere, to be re-executed with
+//! `replay`, which serves storage reads and the calls to the recorded natives from the trace, and
+//! reports how the effects and the gas used differ from the recorded ones.
+
+use crate::{loader::Function, move_vm::MoveVM, session::Session};
+use move_binary_format::errors::*;
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::{ChangeSet, Event, Op},
+    gas_schedule::{GasAlgebra, GasCarrier, InternalGasUnits},
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, MoveResolver, ResourceResolver},
+    value::MoveTypeLayout,
+    vm_status::StatusCode,
+};
+use move_vm_types::{gas_schedule::GasMeter, natives::function::NativeResult, values::Value};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs, io, mem,
+    path::Path,
+};
+
+/// A call to a native function, as recorded
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NativeCallRecord {
+    /// The native called, e.g., `0x1::M::f`
+    pub function: String,
+    /// Gas units charged by the native
+    pub cost: GasCarrier,
+    /// Values returned, serialized, or abort code
+    pub result: Result<Vec<Vec<u8>>, u64>,
+    pub abort_payload: Option<Vec<u8>>,
+    /// Size of the events the native emitted, once serialized, which the VM charges for
+    pub event_bytes: u64,
+}
+
+/// Everything a session read from storage, the calls to the natives with external inputs it
+/// made, and its effects.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    /// Modules read (`None` if they do not exist)
+    pub modules: BTreeMap<ModuleId, Option<Vec<u8>>>,
+    /// Resources read (`None` if they do not exist)
+    pub resources: BTreeMap<(AccountAddress, StructTag), Option<Vec<u8>>>,
+    /// Resource groups the resources read are members of
+    pub resource_groups: BTreeMap<StructTag, Option<StructTag>>,
+    /// Natives whose calls are recorded, e.g., `0x1::M::f`
+    pub natives: BTreeSet<String>,
+    /// Calls to the natives recorded, in the order they were made
+    pub native_calls: Vec<NativeCallRecord>,
+    pub change_set: ChangeSet,
+    pub events: Vec<Event>,
+    /// Internal gas units used by the execution, as charged by the gas meter it was given
+    pub gas_used: GasCarrier,
+}
+
+impl ExecutionTrace {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bcs::Error> {
+        bcs::to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bcs::Error> {
+        bcs::from_bytes(bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let bytes = self
+            .to_bytes()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// How the effects of a replayed execution differ from the recorded ones
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Divergence {
+    /// The module was changed differently
+    Module(ModuleId),
+    /// The resource (or resource group) was changed differently
+    Resource(AccountAddress, StructTag),
+    /// Different events were emitted
+    Events,
+    /// Fewer calls to the recorded natives were made. Other differences in the calls fail the
+    /// replayed execution with `NATIVE_REPLAY_ERROR`.
+    NativeCalls,
+    /// A different amount of gas was used
+    GasUsed {
+        recorded: GasCarrier,
+        replayed: GasCarrier,
+    },
+}
+
+/// A `MoveResolver` recording everything read from the resolver it wraps
+pub struct RecordingResolver<'a, S> {
+    remote: &'a S,
+    modules: RwLock<BTreeMap<ModuleId, Option<Vec<u8>>>>,
+    resources: RwLock<BTreeMap<(AccountAddress, StructTag), Option<Vec<u8>>>>,
+    resource_groups: RwLock<BTreeMap<StructTag, Option<StructTag>>>,
+}
+
+impl<'a, S: MoveResolver> RecordingResolver<'a, S> {
+    pub fn new(remote: &'a S) -> Self {
+        Self {
+            remote,
+            modules: RwLock::new(BTreeMap::new()),
+            resources: RwLock::new(BTreeMap::new()),
+            resource_groups: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl<'a, S: MoveResolver> ModuleResolver for RecordingResolver<'a, S> {
+    type Error = S::Err;
+
+    fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        let blob = self.remote.get_module(id)?;
+        self.modules
+            .write()
+            .entry(id.clone())
+            .or_insert_with(|| blob.clone());
+        Ok(blob)
+    }
+}
+
+impl<'a, S: MoveResolver> ResourceResolver for RecordingResolver<'a, S> {
+    type Error = S::Err;
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        typ: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        let blob = self.remote.get_resource(address, typ)?;
+        self.resources
+            .write()
+            .entry((*address, typ.clone()))
+            .or_insert_with(|| blob.clone());
+        Ok(blob)
+    }
+
+    fn get_resource_group(&self, typ: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        let group = self.remote.get_resource_group(typ)?;
+        self.resource_groups
+            .write()
+            .entry(typ.clone())
+            .or_insert_with(|| group.clone());
+        Ok(group)
+    }
+
+    fn prefetch_resources(&self, keys: &[(AccountAddress, StructTag)]) -> Result<(), Self::Error> {
+        self.remote.prefetch_resources(keys)
+    }
+}
+
+/// An error of a `ReplayResolver`, when data which was not recorded is read
+#[derive(Clone, Debug)]
+pub struct UnrecordedRead(pub String);
+
+/// A `MoveResolver` serving the reads recorded in a trace
+pub struct ReplayResolver<'a> {
+    trace: &'a ExecutionTrace,
+}
+
+impl<'a> ReplayResolver<'a> {
+    pub fn new(trace: &'a ExecutionTrace) -> Self {
+        Self { trace }
+    }
+}
+
+impl<'a> ModuleResolver for ReplayResolver<'a> {
+    type Error = UnrecordedRead;
+
+    fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.trace
+            .modules
+            .get(id)
+            .cloned()
+            .ok_or_else(|| UnrecordedRead(format!("module {}", id)))
+    }
+}
+
+impl<'a> ResourceResolver for ReplayResolver<'a> {
+    type Error = UnrecordedRead;
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        typ: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.trace
+            .resources
+            .get(&(*address, typ.clone()))
+            .cloned()
+            .ok_or_else(|| UnrecordedRead(format!("resource {} at {}", typ, address)))
+    }
+
+    fn get_resource_group(&self, typ: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        self.trace
+            .resource_groups
+            .get(typ)
+            .cloned()
+            .ok_or_else(|| UnrecordedRead(format!("resource group of {}", typ)))
+    }
+}
+
+/// Executes a session with `execute`, recording what it reads from `remote` and the calls to the
+/// given natives it makes, e.g., `0x1::M::f`. Returns what `execute` returned, along with the
+/// trace of the session.
+///
+/// The session runs on a VM with the natives and configuration of `vm` but an empty code cache,
+/// so that all the modules it uses are read from `remote` and recorded, even those `vm` has
+/// loaded already. `execute` is given `gas_meter` to charge the calls it makes with, and the gas
+/// used is recorded.
+///
+/// The effects of the session are recorded whether `execute` succeeded or not, so that failed
+/// executions can be replayed as well.
+pub fn record<S: MoveResolver, G: GasMeter, T>(
+    vm: &MoveVM,
+    remote: &S,
+    natives: impl IntoIterator<Item = String>,
+    gas_meter: &mut G,
+    execute: impl FnOnce(&mut Session<RecordingResolver<S>>, &mut G) -> T,
+) -> VMResult<(T, ExecutionTrace)> {
+    let natives = natives.into_iter().collect::<BTreeSet<_>>();
+    let resolver = RecordingResolver::new(remote);
+    let vm = vm.new_with_empty_cache();
+    let mut sess = vm.new_session(&resolver);
+    sess.record_natives(natives.clone());
+    let gas_left = gas_meter.gas_left().get();
+    let result = execute(&mut sess, gas_meter);
+    let gas_used = gas_left - gas_meter.gas_left().get();
+    let native_calls = sess.take_native_calls();
+    let (change_set, events) = sess.finish()?;
+    let trace = ExecutionTrace {
+        modules: resolver.modules.into_inner(),
+        resources: resolver.resources.into_inner(),
+        resource_groups: resolver.resource_groups.into_inner(),
+        natives,
+        native_calls,
+        change_set,
+        events,
+        gas_used,
+    };
+    Ok((result, trace))
+}
+
+/// Re-executes a recorded session with `execute`, which should make the same calls, charged with
+/// `gas_meter`, as the recorded execution did. Storage reads and calls to the recorded natives
+/// are served from the trace. Returns what `execute` returned, along with how the effects and the
+/// gas used differ from the recorded ones.
+pub fn replay<G: GasMeter, T>(
+    vm: &MoveVM,
+    trace: &ExecutionTrace,
+    gas_meter: &mut G,
+    execute: impl FnOnce(&mut Session<ReplayResolver>, &mut G) -> T,
+) -> VMResult<(T, Vec<Divergence>)> {
+    let resolver = ReplayResolver::new(trace);
+    let mut sess = vm.new_session(&resolver);
+    sess.replay_natives(trace.natives.clone(), trace.native_calls.clone());
+    let gas_left = gas_meter.gas_left().get();
+    let result = execute(&mut sess, gas_meter);
+    let gas_used = gas_left - gas_meter.gas_left().get();
+    let native_calls_left = sess.take_native_calls();
+    let (change_set, events) = sess.finish()?;
+
+    let mut divergences = vec![];
+    let recorded_modules = modules_changed(&trace.change_set);
+    let modules = modules_changed(&change_set);
+    for (address, name) in recorded_modules
+        .keys()
+        .chain(modules.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let key = (*address, name.clone());
+        if recorded_modules.get(&key) != modules.get(&key) {
+            divergences.push(Divergence::Module(ModuleId::new(key.0, key.1)));
+        }
+    }
+    let recorded_resources = resources_changed(&trace.change_set);
+    let resources = resources_changed(&change_set);
+    for key in recorded_resources
+        .keys()
+        .chain(resources.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        if recorded_resources.get(key) != resources.get(key) {
+            divergences.push(Divergence::Resource(key.0, key.1.clone()));
+        }
+    }
+    if events != trace.events {
+        divergences.push(Divergence::Events);
+    }
+    if !native_calls_left.is_empty() {
+        divergences.push(Divergence::NativeCalls);
+    }
+    if gas_used != trace.gas_used {
+        divergences.push(Divergence::GasUsed {
+            recorded: trace.gas_used,
+            replayed: gas_used,
+        });
+    }
+    Ok((result, divergences))
+}
+
+fn modules_changed(change_set: &ChangeSet) -> BTreeMap<(AccountAddress, Identifier), Op<&[u8]>> {
+    change_set
+        .modules()
+        .map(|(address, name, op)| ((address, name.clone()), op))
+        .collect()
+}
+
+fn resources_changed(change_set: &ChangeSet) -> BTreeMap<(AccountAddress, StructTag), Op<&[u8]>> {
+    change_set
+        .resources()
+        .map(|(address, struct_tag, op)| ((address, struct_tag.clone()), op))
+        .collect()
+}
+
+// The natives whose calls are recorded, or replayed, in a session
+#[derive(Default)]
+pub(crate) struct NativeRecording {
+    natives: BTreeSet<String>,
+    // whether calls are served from `calls` rather than recorded in it
+    replaying: bool,
+    calls: VecDeque<NativeCallRecord>,
+}
+
+impl NativeRecording {
+    pub(crate) fn record(natives: BTreeSet<String>) -> Self {
+        Self {
+            natives,
+            replaying: false,
+            calls: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn replay(natives: BTreeSet<String>, calls: Vec<NativeCallRecord>) -> Self {
+        Self {
+            natives,
+            replaying: true,
+            calls: calls.into(),
+        }
+    }
+
+    // Whether calls to the function are recorded or replayed
+    pub(crate) fn covers(&self, function: &Function) -> bool {
+        !self.natives.is_empty() && self.natives.contains(&function.pretty_string())
+    }
+
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    // Returns the calls recorded, or the calls left to replay
+    pub(crate) fn take_calls(&mut self) -> Vec<NativeCallRecord> {
+        mem::take(&mut self.calls).into()
+    }
+
+    // Records a call to `function` which returned `result`, with values of the given layouts, and
+    // emitted events of `event_bytes` bytes
+    pub(crate) fn record_call(
+        &mut self,
+        function: &Function,
+        layouts: &[MoveTypeLayout],
+        result: &NativeResult,
+        event_bytes: u64,
+    ) -> PartialVMResult<()> {
+        let values = match &result.result {
+            Ok(values) => Ok(values
+                .iter()
+                .zip(layouts)
+                .map(|(value, layout)| {
+                    value.simple_serialize(layout).ok_or_else(|| {
+                        PartialVMError::new(StatusCode::NATIVE_REPLAY_ERROR).with_message(format!(
+                            "cannot record the values returned by {}",
+                            function.pretty_string()
+                        ))
+                    })
+                })
+                .collect::<PartialVMResult<_>>()?),
+            Err(code) => Err(*code),
+        };
+        self.calls.push_back(NativeCallRecord {
+            function: function.pretty_string(),
+            cost: result.cost.get(),
+            result: values,
+            abort_payload: result.abort_payload.clone(),
+            event_bytes,
+        });
+        Ok(())
+    }
+
+    // Replays the next call recorded, which must be to `function`, returning values of the given
+    // layouts along with the size of the events the call emitted
+    pub(crate) fn replay_call(
+        &mut self,
+        function: &Function,
+        layouts: &[MoveTypeLayout],
+    ) -> PartialVMResult<(NativeResult, u64)> {
+        let name = function.pretty_string();
+        let call = match self.calls.pop_front() {
+            Some(call) if call.function == name => call,
+            _ => {
+                return Err(PartialVMError::new(StatusCode::NATIVE_REPLAY_ERROR)
+                    .with_message(format!("call to {} was not recorded next", name)))
+            }
+        };
+        let result = match call.result {
+            Ok(blobs) if blobs.len() == layouts.len() => Ok(blobs
+                .iter()
+                .zip(layouts)
+                .map(|(blob, layout)| {
+                    Value::simple_deserialize(blob, layout).ok_or_else(|| {
+                        PartialVMError::new(StatusCode::NATIVE_REPLAY_ERROR)
+                            .with_message(format!("cannot replay the values returned by {}", name))
+                    })
+                })
+                .collect::<PartialVMResult<_>>()?),
+            Ok(_) => {
+                return Err(PartialVMError::new(StatusCode::NATIVE_REPLAY_ERROR)
+                    .with_message(format!("wrong number of values recorded for {}", name)))
+            }
+            Err(code) => Err(code),
+        };
+        let result = NativeResult {
+            cost: InternalGasUnits::new(call.cost),
+            result,
+            abort_payload: call.abort_payload,
+        };
+        Ok((result, call.event_bytes))
+    }
+}
@@ -3,16 +3,22 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    config::VMConfig,
     data_cache::TransactionDataCache,
+    gas_profile::GasProfile,
     interpreter::Interpreter,
     loader::{Function, Loader},
+    metrics::VMMetrics,
     native_extensions::NativeContextExtensions,
     native_functions::{NativeFunction, NativeFunctions},
+    replay::NativeRecording,
     session::{LoadedFunctionInstantiation, SerializedReturnValues, Session},
+    tracer::Tracer,
+    watchdog::ScheduledWatchdog,
 };
 use move_binary_format::{
     access::ModuleAccess,
-    compatibility::Compatibility,
+    compatibility::{Compatibility, CompatibilityPolicy},
     errors::{verification_error, Location, PartialVMError, PartialVMResult, VMResult},
     file_format::LocalIndex,
     normalized, CompiledModule, IndexKind,
@@ -20,16 +26,17 @@ use move_binary_format::{
 use move_bytecode_verifier::script_signature;
 use move_core_types::{
     account_address::AccountAddress,
+    gas_schedule::{AbstractMemorySize, GasAlgebra, GasCarrier},
     identifier::{IdentStr, Identifier},
-    language_storage::{ModuleId, TypeTag},
+    language_storage::{ModuleId, TypeTag, CORE_CODE_ADDRESS},
     resolver::MoveResolver,
-    value::MoveTypeLayout,
+    value::{MoveTypeLayout, MoveValue},
     vm_status::StatusCode,
 };
 use move_vm_types::{
     data_store::DataStore,
-    gas_schedule::GasStatus,
-    loaded_data::runtime_types::Type,
+    gas_schedule::GasMeter,
+    loaded_data::runtime_types::{CachedStructIndex, StructType, Type},
     values::{Locals, Reference, VMValueCast, Value},
 };
 use std::{borrow::Borrow, collections::BTreeSet, sync::Arc};
@@ -43,29 +50,50 @@ pub(crate) struct VMRuntime {
 impl VMRuntime {
     pub(crate) fn new(
         natives: impl IntoIterator<Item = (AccountAddress, Identifier, Identifier, NativeFunction)>,
+        vm_config: VMConfig,
+        metrics: Arc<dyn VMMetrics>,
     ) -> PartialVMResult<Self> {
         Ok(VMRuntime {
-            loader: Loader::new(NativeFunctions::new(natives)?),
+            loader: Loader::new(NativeFunctions::new(natives)?, vm_config, metrics),
         })
     }
 
+    pub(crate) fn new_with_loader(loader: Loader) -> Self {
+        VMRuntime { loader }
+    }
+
     pub fn new_session<'r, S: MoveResolver>(&self, remote: &'r S) -> Session<'r, '_, S> {
         Session {
             runtime: self,
             data_cache: TransactionDataCache::new(remote, &self.loader),
             native_extensions: NativeContextExtensions::default(),
+            tracer: None,
+            watchdog: None,
+            gas_profile: None,
+            allow_module_publishing: true,
+            allow_module_removal: false,
+            allow_scripts: true,
+            native_recording: None,
         }
     }
 
     pub fn new_session_with_extensions<'r, S: MoveResolver>(
         &self,
         remote: &'r S,
-        native_extensions: NativeContextExtensions<'r>,
+        mut native_extensions: NativeContextExtensions<'r>,
     ) -> Session<'r, '_, S> {
+        native_extensions.on_session_start();
         Session {
             runtime: self,
             data_cache: TransactionDataCache::new(remote, &self.loader),
             native_extensions,
+            tracer: None,
+            watchdog: None,
+            gas_profile: None,
+            allow_module_publishing: true,
+            allow_module_removal: false,
+            allow_scripts: true,
+            native_recording: None,
         }
     }
 
@@ -74,7 +102,8 @@ impl VMRuntime {
         modules: Vec<Vec<u8>>,
         sender: AccountAddress,
         data_store: &mut impl DataStore,
-        _gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
+        compat_policy: CompatibilityPolicy,
     ) -> VMResult<()> {
         // deserialize the modules. Perform bounds check. After this indexes can be
         // used with the `[]` operator
@@ -108,19 +137,21 @@ impl VMRuntime {
         let mut bundle_unverified = BTreeSet::new();
 
         // For now, we assume that all modules can be republished, as long as the new module is
-        // backward compatible with the old module.
+        // compatible with the old module according to the given policy.
         //
         // TODO: in the future, we may want to add restrictions on module republishing, possibly by
         // changing the bytecode format to include an `is_upgradable` flag in the CompiledModule.
         for module in &compiled_modules {
             let module_id = module.self_id();
-            if data_store.exists_module(&module_id)? {
+            if compat_policy != CompatibilityPolicy::Arbitrary
+                && data_store.exists_module(&module_id)?
+            {
                 let old_module_ref = self.loader.load_module(&module_id, data_store)?;
                 let old_module = old_module_ref.module();
                 let old_m = normalized::Module::new(old_module);
                 let new_m = normalized::Module::new(module);
                 let compat = Compatibility::check(&old_m, &new_m);
-                if !compat.is_fully_compatible() {
+                if !compat.is_allowed_by(compat_policy) {
                     return Err(PartialVMError::new(
                         StatusCode::BACKWARD_INCOMPATIBLE_MODULE_UPDATE,
                     )
@@ -192,19 +223,65 @@ impl VMRuntime {
 
         // All modules verified, publish them to data cache
         for (module, blob) in compiled_modules.into_iter().zip(modules.into_iter()) {
-            data_store.publish_module(&module.self_id(), blob)?;
+            let module_id = module.self_id();
+            // Upgraded modules are stale in the code cache, mark them so they can be flushed once
+            // the session ends (see `MoveVM::flush_loader_cache_if_invalidated`)
+            let is_republishing = data_store.exists_module(&module_id)?;
+            if is_republishing {
+                self.loader.mark_module_as_invalid(module_id.clone());
+            }
+            gas_meter
+                .charge_write_module(
+                    &module_id,
+                    AbstractMemorySize::new(blob.len() as GasCarrier),
+                )
+                .map_err(|err| err.finish(Location::Undefined))?;
+            data_store.publish_module(&module_id, blob, is_republishing)?;
         }
         Ok(())
     }
 
-    fn deserialize_value(&self, ty: &Type, arg: impl Borrow<[u8]>) -> PartialVMResult<Value> {
+    pub(crate) fn unpublish_module(
+        &self,
+        module_id: &ModuleId,
+        sender: AccountAddress,
+        data_store: &mut impl DataStore,
+    ) -> VMResult<()> {
+        // As for publishing, modules can only be removed from the account of the sender
+        if module_id.address() != &sender {
+            return Err(
+                PartialVMError::new(StatusCode::MODULE_ADDRESS_DOES_NOT_MATCH_SENDER)
+                    .finish(Location::Undefined),
+            );
+        }
+        if !data_store.exists_module(module_id)? {
+            return Err(PartialVMError::new(StatusCode::LINKER_ERROR)
+                .with_message(format!("Cannot find {:?} to remove", module_id))
+                .finish(Location::Undefined));
+        }
+        // The module stays valid in the code cache until its deletion is applied to the storage
+        // (see `MoveVM::mark_removed_modules_as_invalid`), as other sessions may still use it
+        data_store.unpublish_module(module_id)
+    }
+
+    fn deserialize_value(
+        &self,
+        idx: usize,
+        ty: &Type,
+        arg: impl Borrow<[u8]>,
+    ) -> PartialVMResult<Value> {
         let layout = match self.loader.type_to_type_layout(ty) {
             Ok(layout) => layout,
-            Err(_err) => {
+            Err(err) => {
                 warn!("[VM] failed to get layout from type");
                 return Err(PartialVMError::new(
                     StatusCode::INVALID_PARAM_TYPE_FOR_DESERIALIZATION,
-                ));
+                )
+                .with_message(format!(
+                    "argument {} cannot be deserialized: {:?}",
+                    idx,
+                    err.major_status()
+                )));
             }
         };
 
@@ -212,13 +289,195 @@ impl VMRuntime {
             Some(val) => Ok(val),
             None => {
                 warn!("[VM] failed to deserialize argument");
-                Err(PartialVMError::new(
-                    StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT,
-                ))
+                Err(
+                    PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT).with_message(
+                        format!(
+                            "argument {} is not a serialized value of its type {}",
+                            idx,
+                            self.type_name(ty)
+                        ),
+                    ),
+                )
             }
         }
     }
 
+    fn type_name(&self, ty: &Type) -> String {
+        match self.loader.type_to_type_tag(ty) {
+            Ok(tag) => tag.to_string(),
+            Err(_) => format!("{:?}", ty),
+        }
+    }
+
+    // Checks an argument of an entry function or script is a transaction argument (see
+    // `VMConfig::check_entry_argument_types`), given the module of the function, if any
+    fn check_entry_argument_type(
+        &self,
+        idx: usize,
+        ty: &Type,
+        module_id: Option<&ModuleId>,
+    ) -> PartialVMResult<()> {
+        match ty {
+            Type::Signer => Ok(()),
+            Type::Reference(inner) if matches!(&**inner, Type::Signer) => Ok(()),
+            _ => self.check_entry_argument_value_type(idx, ty, module_id, 1),
+        }
+    }
+
+    fn check_entry_argument_value_type(
+        &self,
+        idx: usize,
+        ty: &Type,
+        module_id: Option<&ModuleId>,
+        depth: usize,
+    ) -> PartialVMResult<()> {
+        let invalid = |reason: &str| {
+            PartialVMError::new(StatusCode::INVALID_PARAM_TYPE_FOR_DESERIALIZATION).with_message(
+                format!("argument {} of type {} {}", idx, self.type_name(ty), reason),
+            )
+        };
+        if depth > self.loader.vm_config().max_value_nest_depth {
+            return Err(invalid("is nested too deeply"));
+        }
+        match ty {
+            Type::Bool | Type::U8 | Type::U64 | Type::U128 | Type::Address => Ok(()),
+            Type::Vector(inner) => {
+                self.check_entry_argument_value_type(idx, inner, module_id, depth + 1)
+            }
+            Type::Struct(gidx) | Type::StructInstantiation(gidx, _) => {
+                let struct_type = self.struct_type(*gidx)?;
+                if is_std_struct(&struct_type, "string", "String") {
+                    return Ok(());
+                }
+                if is_std_struct(&struct_type, "option", "Option") {
+                    return match ty {
+                        Type::StructInstantiation(_, ty_args) if ty_args.len() == 1 => self
+                            .check_entry_argument_value_type(
+                                idx,
+                                &ty_args[0],
+                                module_id,
+                                depth + 1,
+                            ),
+                        _ => Err(PartialVMError::new(
+                            StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR,
+                        )
+                        .with_message("Option without a type argument".to_string())),
+                    };
+                }
+                if module_id != Some(&struct_type.module) {
+                    return Err(invalid(
+                        "is a struct of another module, which cannot be created from bytes",
+                    ));
+                }
+                let abilities = self.loader.abilities(ty)?;
+                if !abilities.has_copy() || !abilities.has_drop() {
+                    return Err(invalid(
+                        "is not a struct with copy and drop, which cannot be created from bytes",
+                    ));
+                }
+                for field_ty in &struct_type.fields {
+                    let field_ty = match ty {
+                        Type::StructInstantiation(_, ty_args) => field_ty.subst(ty_args)?,
+                        _ => field_ty.clone(),
+                    };
+                    self.check_entry_argument_value_type(idx, &field_ty, module_id, depth + 1)?;
+                }
+                Ok(())
+            }
+            Type::Signer => Err(invalid("contains a signer")),
+            Type::Reference(_) | Type::MutableReference(_) => {
+                Err(invalid("is a reference which is not &signer"))
+            }
+            Type::TyParam(_) => Err(PartialVMError::new(
+                StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR,
+            )
+            .with_message("type parameter in an argument type".to_string())),
+        }
+    }
+
+    // Checks the value of an argument of an entry function or script, whose type is checked
+    // already, maintains the invariants of the standard library structs in it: strings are valid
+    // UTF-8 and options have at most one element
+    fn check_entry_argument_value(&self, idx: usize, ty: &Type, arg: &[u8]) -> PartialVMResult<()> {
+        if matches!(ty, Type::Signer | Type::Reference(_)) {
+            return Ok(());
+        }
+        let value = self
+            .loader
+            .type_to_type_layout(ty)
+            .ok()
+            .and_then(|layout| MoveValue::simple_deserialize(arg, &layout).ok())
+            .ok_or_else(|| {
+                PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT).with_message(
+                    format!(
+                        "argument {} is not a serialized value of its type {}",
+                        idx,
+                        self.type_name(ty)
+                    ),
+                )
+            })?;
+        self.check_entry_argument_value_impl(idx, ty, &value)
+    }
+
+    fn check_entry_argument_value_impl(
+        &self,
+        idx: usize,
+        ty: &Type,
+        value: &MoveValue,
+    ) -> PartialVMResult<()> {
+        let invalid = |reason: &str| {
+            PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT)
+                .with_message(format!("argument {} {}", idx, reason))
+        };
+        match (ty, value) {
+            (Type::Vector(inner), MoveValue::Vector(elems)) => elems
+                .iter()
+                .try_for_each(|elem| self.check_entry_argument_value_impl(idx, inner, elem)),
+            (Type::Struct(gidx), MoveValue::Struct(fields))
+            | (Type::StructInstantiation(gidx, _), MoveValue::Struct(fields)) => {
+                let struct_type = self.struct_type(*gidx)?;
+                let fields = fields.fields();
+                if is_std_struct(&struct_type, "string", "String") {
+                    let bytes = match fields {
+                        [MoveValue::Vector(bytes)] => bytes
+                            .iter()
+                            .map(|byte| match byte {
+                                MoveValue::U8(byte) => Some(*byte),
+                                _ => None,
+                            })
+                            .collect::<Option<Vec<_>>>(),
+                        _ => None,
+                    };
+                    return match bytes {
+                        Some(bytes) if std::str::from_utf8(&bytes).is_ok() => Ok(()),
+                        _ => Err(invalid("contains a string which is not valid UTF-8")),
+                    };
+                }
+                if is_std_struct(&struct_type, "option", "Option")
+                    && !matches!(fields, [MoveValue::Vector(elems)] if elems.len() <= 1)
+                {
+                    return Err(invalid("contains an option with more than one element"));
+                }
+                for (field_ty, field) in struct_type.fields.iter().zip(fields) {
+                    let field_ty = match ty {
+                        Type::StructInstantiation(_, ty_args) => field_ty.subst(ty_args)?,
+                        _ => field_ty.clone(),
+                    };
+                    self.check_entry_argument_value_impl(idx, &field_ty, field)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn struct_type(&self, gidx: CachedStructIndex) -> PartialVMResult<Arc<StructType>> {
+        self.loader.get_struct_type(gidx).ok_or_else(|| {
+            PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR)
+                .with_message("struct type not found".to_string())
+        })
+    }
+
     fn deserialize_args(
         &self,
         arg_tys: Vec<Type>,
@@ -246,10 +505,11 @@ impl VMRuntime {
             .enumerate()
             .map(|(idx, (arg_ty, arg_bytes))| match &arg_ty {
                 Type::MutableReference(inner_t) | Type::Reference(inner_t) => {
-                    dummy_locals.store_loc(idx, self.deserialize_value(inner_t, arg_bytes)?)?;
+                    dummy_locals
+                        .store_loc(idx, self.deserialize_value(idx, inner_t, arg_bytes)?)?;
                     dummy_locals.borrow_loc(idx)
                 }
-                _ => self.deserialize_value(&arg_ty, arg_bytes),
+                _ => self.deserialize_value(idx, &arg_ty, arg_bytes),
             })
             .collect::<PartialVMResult<Vec<_>>>()?;
         Ok((dummy_locals, deserialized_args))
@@ -317,14 +577,44 @@ impl VMRuntime {
         return_types: Vec<Type>,
         serialized_args: Vec<impl Borrow<[u8]>>,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
+        tracer: Option<&mut dyn Tracer>,
+        watchdog: Option<&mut ScheduledWatchdog>,
+        gas_profile: Option<&mut GasProfile>,
+        native_recording: Option<&mut NativeRecording>,
+        check_arg_types: bool,
     ) -> VMResult<SerializedReturnValues> {
+        // charge for each module the code depends on the first time it is used in the session,
+        // whether or not it is in the code cache already. The dependencies of a used module are
+        // used as well, so they are not visited again.
+        let new_dependencies = self
+            .loader
+            .function_dependencies(&func, |module_id| data_store.is_module_used(module_id))
+            .map_err(|err| err.finish(Location::Undefined))?;
+        for (module_id, size) in &new_dependencies {
+            gas_meter
+                .charge_dependency(module_id, AbstractMemorySize::new(*size as GasCarrier))
+                .map_err(|err| err.finish(Location::Undefined))?;
+        }
+        // modules are recorded once they are all charged, so that a module is only recorded along
+        // with its dependencies
+        for (module_id, _) in &new_dependencies {
+            data_store.record_module_use(module_id);
+        }
+
         let arg_types = param_types
             .into_iter()
             .map(|ty| ty.subst(&ty_args))
             .collect::<PartialVMResult<Vec<_>>>()
             .map_err(|err| err.finish(Location::Undefined))?;
+        if check_arg_types {
+            for (idx, (ty, arg)) in arg_types.iter().zip(&serialized_args).enumerate() {
+                self.check_entry_argument_type(idx, ty, func.module_id())
+                    .and_then(|()| self.check_entry_argument_value(idx, ty, arg.borrow()))
+                    .map_err(|err| err.finish(Location::Undefined))?;
+            }
+        }
         let mut_ref_args = arg_types
             .iter()
             .enumerate()
@@ -347,10 +637,15 @@ impl VMRuntime {
             ty_args,
             deserialized_args,
             data_store,
-            gas_status,
+            gas_meter,
             extensions,
+            tracer,
+            watchdog,
+            gas_profile,
+            native_recording,
             &self.loader,
-        )?;
+        )
+        .map_err(|err| self.loader.attach_source_location(err))?;
 
         let serialized_return_values = self
             .serialize_return_values(&return_types, return_values)
@@ -382,8 +677,12 @@ impl VMRuntime {
         ty_args: Vec<TypeTag>,
         serialized_args: Vec<impl Borrow<[u8]>>,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
+        tracer: Option<&mut dyn Tracer>,
+        watchdog: Option<&mut ScheduledWatchdog>,
+        gas_profile: Option<&mut GasProfile>,
+        native_recording: Option<&mut NativeRecording>,
         bypass_declared_entry_check: bool,
     ) -> VMResult<SerializedReturnValues> {
         use move_binary_format::{binary_views::BinaryIndexedView, file_format::SignatureIndex};
@@ -434,8 +733,13 @@ impl VMRuntime {
             return_,
             serialized_args,
             data_store,
-            gas_status,
+            gas_meter,
             extensions,
+            tracer,
+            watchdog,
+            gas_profile,
+            native_recording,
+            self.loader.vm_config().check_entry_argument_types && !bypass_declared_entry_check,
         )
     }
 
@@ -446,8 +750,12 @@ impl VMRuntime {
         ty_args: Vec<TypeTag>,
         serialized_args: Vec<impl Borrow<[u8]>>,
         data_store: &mut impl DataStore,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
         extensions: &mut NativeContextExtensions,
+        tracer: Option<&mut dyn Tracer>,
+        watchdog: Option<&mut ScheduledWatchdog>,
+        gas_profile: Option<&mut GasProfile>,
+        native_recording: Option<&mut NativeRecording>,
     ) -> VMResult<SerializedReturnValues> {
         // load the script, perform verification
         let (
@@ -468,8 +776,13 @@ impl VMRuntime {
             return_,
             serialized_args,
             data_store,
-            gas_status,
+            gas_meter,
             extensions,
+            tracer,
+            watchdog,
+            gas_profile,
+            native_recording,
+            self.loader.vm_config().check_entry_argument_types,
         )
     }
 
@@ -477,3 +790,11 @@ impl VMRuntime {
         &self.loader
     }
 }
+
+// Whether the struct is the given struct of the standard library, whose values are created from
+// bytes by checking its invariants, rather than by its module
+fn is_std_struct(struct_type: &StructType, module_name: &str, struct_name: &str) -> bool {
+    struct_type.module.address() == &CORE_CODE_ADDRESS
+        && struct_type.module.name().as_str() == module_name
+        && struct_type.name.as_str() == struct_name
+}
@@ -3,24 +3,32 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    data_cache::TransactionDataCache, native_extensions::NativeContextExtensions,
+    data_cache::{DataCacheSnapshot, TransactionDataCache},
+    gas_profile::GasProfile,
+    native_extensions::{ExtensionsCheckpoint, NativeContextExtensions},
+    replay::{NativeCallRecord, NativeRecording},
     runtime::VMRuntime,
+    tracer::Tracer,
+    watchdog::{ScheduledWatchdog, Watchdog},
 };
 use move_binary_format::{
+    compatibility::CompatibilityPolicy,
     errors::*,
     file_format::{AbilitySet, LocalIndex},
 };
 use move_core_types::{
     account_address::AccountAddress,
-    effects::{ChangeSet, Event},
-    identifier::IdentStr,
+    effects::{ChangeSet, Event, ReadWriteSet},
+    identifier::{IdentStr, Identifier},
     language_storage::{ModuleId, TypeTag},
+    parser::parse_type_tag,
     resolver::MoveResolver,
-    value::MoveTypeLayout,
+    value::{MoveStruct, MoveTypeLayout, MoveValue},
+    vm_status::StatusCode,
 };
 use move_vm_types::{
     data_store::DataStore,
-    gas_schedule::GasStatus,
+    gas_schedule::GasMeter,
     loaded_data::runtime_types::{CachedStructIndex, StructType, Type},
 };
 use std::{borrow::Borrow, sync::Arc};
@@ -29,6 +37,13 @@ pub struct Session<'r, 'l, S> {
     pub(crate) runtime: &'l VMRuntime,
     pub(crate) data_cache: TransactionDataCache<'r, 'l, S>,
     pub(crate) native_extensions: NativeContextExtensions<'r>,
+    pub(crate) tracer: Option<Box<dyn Tracer + 'r>>,
+    pub(crate) watchdog: Option<ScheduledWatchdog<'r>>,
+    pub(crate) gas_profile: Option<GasProfile>,
+    pub(crate) allow_module_publishing: bool,
+    pub(crate) allow_module_removal: bool,
+    pub(crate) allow_scripts: bool,
+    pub(crate) native_recording: Option<NativeRecording>,
 }
 
 /// Serialized return values from function/script execution
@@ -42,6 +57,13 @@ pub struct SerializedReturnValues {
     pub return_values: Vec<(Vec<u8>, MoveTypeLayout)>,
 }
 
+/// The state of a session at some point, which it can be rolled back to with
+/// `Session::rollback_to`
+pub struct SessionCheckpoint {
+    data_cache: DataCacheSnapshot,
+    extensions: ExtensionsCheckpoint,
+}
+
 impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
     /// Execute a Move function with the given arguments. This is mainly designed for an external
     /// environment to invoke system logic written in Move.
@@ -74,7 +96,7 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         function_name: &IdentStr,
         ty_args: Vec<TypeTag>,
         args: Vec<impl Borrow<[u8]>>,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
     ) -> VMResult<SerializedReturnValues> {
         let bypass_declared_entry_check = false;
         self.runtime.execute_function(
@@ -83,20 +105,27 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
             ty_args,
             args,
             &mut self.data_cache,
-            gas_status,
+            gas_meter,
             &mut self.native_extensions,
+            self.tracer.as_deref_mut().map(|t| t as &mut dyn Tracer),
+            self.watchdog.as_mut(),
+            self.gas_profile.as_mut(),
+            self.native_recording.as_mut(),
             bypass_declared_entry_check,
         )
     }
 
     /// Similar to execute_entry_function, but it bypasses visibility checks
+    ///
+    /// Return values are serialized along with their type layouts, so that, e.g., off-chain view
+    /// functions can be evaluated and their results decoded without knowing their signatures.
     pub fn execute_function_bypass_visibility(
         &mut self,
         module: &ModuleId,
         function_name: &IdentStr,
         ty_args: Vec<TypeTag>,
         args: Vec<impl Borrow<[u8]>>,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
     ) -> VMResult<SerializedReturnValues> {
         let bypass_declared_entry_check = true;
         self.runtime.execute_function(
@@ -105,12 +134,101 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
             ty_args,
             args,
             &mut self.data_cache,
-            gas_status,
+            gas_meter,
             &mut self.native_extensions,
+            self.tracer.as_deref_mut().map(|t| t as &mut dyn Tracer),
+            self.watchdog.as_mut(),
+            self.gas_profile.as_mut(),
+            self.native_recording.as_mut(),
             bypass_declared_entry_check,
         )
     }
 
+    /// Similar to `execute_function_bypass_visibility`, but the function is given by its path
+    /// (e.g. `0x1::coin::transfer`), its type arguments as strings (e.g. `0x1::coin::Coin<u64>`),
+    /// and its arguments as `MoveValue`s, which are checked against the types of the parameters
+    /// of the function and serialized here. Arguments of reference types are given as the values
+    /// they refer to.
+    pub fn execute_function_by_path(
+        &mut self,
+        function_path: &str,
+        ty_args: &[&str],
+        args: Vec<MoveValue>,
+        gas_meter: &mut impl GasMeter,
+    ) -> VMResult<SerializedReturnValues> {
+        let (module_id, function_name) =
+            parse_function_path(function_path).map_err(|e| e.finish(Location::Undefined))?;
+        let ty_args = ty_args
+            .iter()
+            .map(|ty_arg| {
+                parse_type_tag(ty_arg).map_err(|err| {
+                    PartialVMError::new(StatusCode::TYPE_RESOLUTION_FAILURE)
+                        .with_message(format!("invalid type argument `{}`: {}", ty_arg, err))
+                        .finish(Location::Undefined)
+                })
+            })
+            .collect::<VMResult<Vec<_>>>()?;
+        let serialized_args = self.serialize_args(&module_id, &function_name, &ty_args, args)?;
+        self.execute_function_bypass_visibility(
+            &module_id,
+            &function_name,
+            ty_args,
+            serialized_args,
+            gas_meter,
+        )
+    }
+
+    fn serialize_args(
+        &self,
+        module_id: &ModuleId,
+        function_name: &IdentStr,
+        ty_args: &[TypeTag],
+        args: Vec<MoveValue>,
+    ) -> VMResult<Vec<Vec<u8>>> {
+        let LoadedFunctionInstantiation {
+            type_arguments,
+            parameters,
+            ..
+        } = self.load_function(module_id, function_name, ty_args)?;
+        if parameters.len() != args.len() {
+            return Err(
+                PartialVMError::new(StatusCode::NUMBER_OF_ARGUMENTS_MISMATCH)
+                    .with_message(format!(
+                        "argument length mismatch: expected {} got {}",
+                        parameters.len(),
+                        args.len()
+                    ))
+                    .finish(Location::Undefined),
+            );
+        }
+        parameters
+            .iter()
+            .zip(args)
+            .enumerate()
+            .map(|(idx, (ty, arg))| {
+                let ty = match ty.subst(&type_arguments)? {
+                    Type::Reference(inner) | Type::MutableReference(inner) => *inner,
+                    ty => ty,
+                };
+                let layout = self.runtime.loader().type_to_type_layout(&ty)?;
+                if !value_has_layout(&arg, &layout) {
+                    return Err(
+                        PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT)
+                            .with_message(format!(
+                                "argument {} is not a value of type {}",
+                                idx, layout
+                            )),
+                    );
+                }
+                arg.simple_serialize().ok_or_else(|| {
+                    PartialVMError::new(StatusCode::FAILED_TO_DESERIALIZE_ARGUMENT)
+                        .with_message(format!("failed to serialize argument {}", idx))
+                })
+            })
+            .collect::<PartialVMResult<_>>()
+            .map_err(|e| e.finish(Location::Undefined))
+    }
+
     /// Execute a transaction script.
     ///
     /// The Move VM MUST return a user error (in other words, an error that's not an invariant
@@ -132,15 +250,26 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         script: impl Borrow<[u8]>,
         ty_args: Vec<TypeTag>,
         args: Vec<impl Borrow<[u8]>>,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
     ) -> VMResult<SerializedReturnValues> {
+        if !self.allow_scripts {
+            return Err(
+                PartialVMError::new(StatusCode::OPERATION_DISALLOWED_IN_SESSION)
+                    .with_message("scripts cannot be executed in this session".to_string())
+                    .finish(Location::Script),
+            );
+        }
         self.runtime.execute_script(
             script,
             ty_args,
             args,
             &mut self.data_cache,
-            gas_status,
+            gas_meter,
             &mut self.native_extensions,
+            self.tracer.as_deref_mut().map(|t| t as &mut dyn Tracer),
+            self.watchdog.as_mut(),
+            self.gas_profile.as_mut(),
+            self.native_recording.as_mut(),
         )
     }
 
@@ -149,7 +278,8 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
     /// The Move VM MUST return a user error, i.e., an error that's not an invariant violation, if
     ///   - The module fails to deserialize or verify.
     ///   - The sender address does not match that of the module.
-    ///   - (Republishing-only) the module to be updated is not backward compatible with the old module.
+    ///   - (Republishing-only) the module to be updated is not fully backward compatible with the
+    ///     old module (see `publish_module_bundle_with_compat_policy` for other policies).
     ///   - (Republishing-only) the module to be updated introduces cyclic dependencies.
     ///
     /// The Move VM should not be able to produce other user errors.
@@ -161,9 +291,9 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         &mut self,
         module: Vec<u8>,
         sender: AccountAddress,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
     ) -> VMResult<()> {
-        self.publish_module_bundle(vec![module], sender, gas_status)
+        self.publish_module_bundle(vec![module], sender, gas_meter)
     }
 
     /// Publish a series of modules.
@@ -182,19 +312,118 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         &mut self,
         modules: Vec<Vec<u8>>,
         sender: AccountAddress,
-        gas_status: &mut GasStatus,
+        gas_meter: &mut impl GasMeter,
+    ) -> VMResult<()> {
+        self.publish_module_bundle_with_compat_policy(
+            modules,
+            sender,
+            gas_meter,
+            CompatibilityPolicy::Full,
+        )
+    }
+
+    /// Publish a series of modules, as in `publish_module_bundle`, but allow republished modules
+    /// to be upgraded as long as they are compatible with the old modules according to the given
+    /// policy, rather than fully backward compatible.
+    ///
+    /// Note that, e.g., with `CompatibilityPolicy::Arbitrary`, modules depending on an upgraded
+    /// module may fail to link, and values previously published under its structs may not be
+    /// readable anymore. It is the responsibility of the caller (e.g. adapter) to only use such a
+    /// policy when this is acceptable.
+    pub fn publish_module_bundle_with_compat_policy(
+        &mut self,
+        modules: Vec<Vec<u8>>,
+        sender: AccountAddress,
+        gas_meter: &mut impl GasMeter,
+        compat_policy: CompatibilityPolicy,
+    ) -> VMResult<()> {
+        if !self.allow_module_publishing {
+            return Err(
+                PartialVMError::new(StatusCode::OPERATION_DISALLOWED_IN_SESSION)
+                    .with_message("modules cannot be published in this session".to_string())
+                    .finish(Location::Undefined),
+            );
+        }
+        self.runtime.publish_module_bundle(
+            modules,
+            sender,
+            &mut self.data_cache,
+            gas_meter,
+            compat_policy,
+        )
+    }
+
+    /// Remove a module, recording its deletion in the change set of the session.
+    ///
+    /// Removal is rejected with `OPERATION_DISALLOWED_IN_SESSION` unless the adapter allowed it
+    /// with `allow_module_removal`, and module publishing is not disallowed. Otherwise, the Move VM
+    /// returns a user error if
+    ///   - The sender address does not match that of the module.
+    ///   - The module does not exist, e.g., because it was already removed.
+    ///
+    /// The module remains loaded, and so usable by the code executed in this session, until the
+    /// deletion is applied to the storage and the adapter invalidates it in the code cache (see
+    /// `MoveVM::mark_removed_modules_as_invalid`). Modules depending on it are not checked, and
+    /// fail to link once it is flushed: it is the responsibility of the adapter to only remove
+    /// modules when this is acceptable. A
+    /// module with the same name can be published again afterwards, replacing the removed one
+    /// without any compatibility check.
+    pub fn unpublish_module(
+        &mut self,
+        module_id: &ModuleId,
+        sender: AccountAddress,
     ) -> VMResult<()> {
+        if !self.allow_module_publishing || !self.allow_module_removal {
+            return Err(
+                PartialVMError::new(StatusCode::OPERATION_DISALLOWED_IN_SESSION)
+                    .with_message("modules cannot be removed in this session".to_string())
+                    .finish(Location::Undefined),
+            );
+        }
         self.runtime
-            .publish_module_bundle(modules, sender, &mut self.data_cache, gas_status)
+            .unpublish_module(module_id, sender, &mut self.data_cache)
     }
 
     pub fn num_mutated_accounts(&self, sender: &AccountAddress) -> u64 {
         self.data_cache.num_mutated_accounts(sender)
     }
 
+    /// Takes a checkpoint of the resources, modules and events changed so far in this session, so
+    /// that, e.g., an adapter can execute a call speculatively and roll its changes back if it
+    /// fails. The session extensions are part of the checkpoint (see
+    /// `SessionExtension::checkpoint`), other native context extensions are not.
+    pub fn checkpoint(&self) -> VMResult<SessionCheckpoint> {
+        Ok(SessionCheckpoint {
+            data_cache: self
+                .data_cache
+                .snapshot()
+                .map_err(|e| e.finish(Location::Undefined))?,
+            extensions: self
+                .native_extensions
+                .checkpoint()
+                .map_err(|e| e.finish(Location::Undefined))?,
+        })
+    }
+
+    /// Rolls back the resources, modules, events and session extensions changed in this session
+    /// since the given checkpoint, which must have been taken from this session. Reads of remote
+    /// storage are still reported by `finish_with_read_write_set`.
+    ///
+    /// Modules published or removed since the checkpoint are stale in the code cache, like the
+    /// modules published in a discarded session, and are flushed by
+    /// `MoveVM::flush_loader_cache_if_invalidated`.
+    pub fn rollback_to(&mut self, checkpoint: SessionCheckpoint) {
+        for module_id in self.data_cache.restore(checkpoint.data_cache) {
+            self.runtime.loader().mark_module_as_invalid(module_id);
+        }
+        self.native_extensions.rollback_to(checkpoint.extensions);
+    }
+
     /// Finish up the session and produce the side effects.
     ///
-    /// This function should always succeed with no user errors returned, barring invariant violations.
+    /// This function should always succeed with no user errors returned, barring invariant violations
+    /// and resources larger than `VMConfig::max_resource_size`, which fail with
+    /// `RESOURCE_SIZE_LIMIT_EXCEEDED`.
     ///
     /// This MUST NOT be called if there is a previous invocation that failed with an invariant violation.
     pub fn finish(self) -> VMResult<(ChangeSet, Vec<Event>)> {
@@ -203,21 +432,137 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
             .map_err(|e| e.finish(Location::Undefined))
     }
 
-    /// Same like `finish`, but also extracts the native context extensions from the session.
+    /// Same like `finish`, but also returns the resources and modules read and written in the
+    /// session.
+    pub fn finish_with_read_write_set(self) -> VMResult<(ChangeSet, Vec<Event>, ReadWriteSet)> {
+        self.data_cache
+            .into_effects_with_read_write_set()
+            .map_err(|e| e.finish(Location::Undefined))
+    }
+
+    /// Same like `finish`, but also returns where gas was consumed in the session, if gas profiling
+    /// was enabled with `enable_gas_profiling`.
+    pub fn finish_with_gas_profile(self) -> VMResult<(ChangeSet, Vec<Event>, Option<GasProfile>)> {
+        let Session {
+            data_cache,
+            gas_profile,
+            ..
+        } = self;
+        let (change_set, events) = data_cache
+            .into_effects()
+            .map_err(|e| e.finish(Location::Undefined))?;
+        Ok((change_set, events, gas_profile))
+    }
+
+    /// Same like `finish`, but also extracts the native context extensions from the session, after
+    /// calling the `on_session_finish` hooks of its session extensions. The extensions can then be
+    /// finished, or carried over to another session.
     pub fn finish_with_extensions(
         self,
     ) -> VMResult<(ChangeSet, Vec<Event>, NativeContextExtensions<'r>)> {
         let Session {
             data_cache,
-            native_extensions,
+            mut native_extensions,
             ..
         } = self;
         let (change_set, events) = data_cache
             .into_effects()
             .map_err(|e| e.finish(Location::Undefined))?;
+        native_extensions
+            .on_session_finish()
+            .map_err(|e| e.finish(Location::Undefined))?;
         Ok((change_set, events, native_extensions))
     }
 
+    /// Installs a tracer which gets called before each instruction executed in this session
+    /// (replacing any previously installed tracer).
+    pub fn set_tracer(&mut self, tracer: Box<dyn Tracer + 'r>) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Removes the tracer installed in this session, if any.
+    pub fn remove_tracer(&mut self) -> Option<Box<dyn Tracer + 'r>> {
+        self.tracer.take()
+    }
+
+    /// Installs a watchdog which gets called every `interval` instructions executed by each call
+    /// into this session (replacing any previously installed watchdog). Execution fails with
+    /// `EXECUTION_INTERRUPTED` as soon as the watchdog asks for it.
+    pub fn set_watchdog(&mut self, interval: u64, watchdog: Box<dyn Watchdog + 'r>) {
+        self.watchdog = Some(ScheduledWatchdog::new(interval, watchdog));
+    }
+
+    /// Removes the watchdog installed in this session, if any.
+    pub fn remove_watchdog(&mut self) -> Option<Box<dyn Watchdog + 'r>> {
+        self.watchdog.take().map(ScheduledWatchdog::into_inner)
+    }
+
+    /// Rejects the modules published from now on in this session, e.g., so that a session
+    /// evaluating view functions or simulating transactions cannot change code. Publishing, and
+    /// removing modules, fail with `OPERATION_DISALLOWED_IN_SESSION`.
+    pub fn disallow_module_publishing(&mut self) {
+        self.allow_module_publishing = false;
+    }
+
+    /// Accepts the modules removed from now on in this session with `unpublish_module`, which is
+    /// rejected by default.
+    pub fn allow_module_removal(&mut self) {
+        self.allow_module_removal = true;
+    }
+
+    /// Rejects the scripts executed from now on in this session, which fail with
+    /// `OPERATION_DISALLOWED_IN_SESSION`. Functions can still be executed.
+    pub fn disallow_scripts(&mut self) {
+        self.allow_scripts = false;
+    }
+
+    /// Records where gas is consumed by the functions and scripts executed from now on in this
+    /// session. The profile is measured from the gas left in the gas meters passed in, so nothing
+    /// is recorded with an unmetered gas meter.
+    pub fn enable_gas_profiling(&mut self) {
+        if self.gas_profile.is_none() {
+            self.gas_profile = Some(GasProfile::default());
+        }
+    }
+
+    /// Where gas was consumed so far in this session, if gas profiling is enabled.
+    pub fn gas_profile(&self) -> Option<&GasProfile> {
+        self.gas_profile.as_ref()
+    }
+
+    /// Records the calls made from now on in this session to the given natives, e.g.,
+    /// `0x1::M::f`, so that they can be replayed with `replay_natives`. Natives with external
+    /// inputs, e.g., reading the time, should be recorded for executions to be replayed
+    /// deterministically. Recording a call fails with `NATIVE_REPLAY_ERROR` if the native returns
+    /// references.
+    ///
+    /// See also `replay::record`, which records everything needed to replay a session.
+    pub fn record_natives(&mut self, natives: impl IntoIterator<Item = String>) {
+        self.native_recording = Some(NativeRecording::record(natives.into_iter().collect()));
+    }
+
+    /// Serves the calls made from now on in this session to the given natives from the calls
+    /// recorded, in order, rather than calling the natives. Calls which do not match the next
+    /// call recorded fail with `NATIVE_REPLAY_ERROR`.
+    pub fn replay_natives(
+        &mut self,
+        natives: impl IntoIterator<Item = String>,
+        calls: Vec<NativeCallRecord>,
+    ) {
+        self.native_recording = Some(NativeRecording::replay(
+            natives.into_iter().collect(),
+            calls,
+        ));
+    }
+
+    /// Returns the calls to natives recorded so far, or the calls recorded left to replay.
+    pub fn take_native_calls(&mut self) -> Vec<NativeCallRecord> {
+        self.native_recording
+            .as_mut()
+            .map(|recording| recording.take_calls())
+            .unwrap_or_default()
+    }
+
     /// Load a script and all of its types into cache
     pub fn load_script(
         &self,
@@ -281,3 +626,49 @@ pub struct LoadedFunctionInstantiation {
     pub parameters: Vec<Type>,
     pub return_: Vec<Type>,
 }
+
+// Splits a path `<address>::<module>::<function>` into the module and the function name
+fn parse_function_path(function_path: &str) -> PartialVMResult<(ModuleId, Identifier)> {
+    let invalid_path = || {
+        PartialVMError::new(StatusCode::FUNCTION_RESOLUTION_FAILURE)
+            .with_message(format!("invalid function path `{}`", function_path))
+    };
+    let parts = function_path.split("::").collect::<Vec<_>>();
+    if parts.len() != 3 {
+        return Err(invalid_path());
+    }
+    let address = AccountAddress::from_hex_literal(parts[0]).map_err(|_| invalid_path())?;
+    let module_name = Identifier::new(parts[1]).map_err(|_| invalid_path())?;
+    let function_name = Identifier::new(parts[2]).map_err(|_| invalid_path())?;
+    Ok((ModuleId::new(address, module_name), function_name))
+}
+
+// Whether the value is of the type with the given layout
+fn value_has_layout(value: &MoveValue, layout: &MoveTypeLayout) -> bool {
+    match (value, layout) {
+        (MoveValue::Bool(_), MoveTypeLayout::Bool)
+        | (MoveValue::U8(_), MoveTypeLayout::U8)
+        | (MoveValue::U64(_), MoveTypeLayout::U64)
+        | (MoveValue::U128(_), MoveTypeLayout::U128)
+        | (MoveValue::Address(_), MoveTypeLayout::Address)
+        | (MoveValue::Signer(_), MoveTypeLayout::Signer) => true,
+        (MoveValue::Vector(values), MoveTypeLayout::Vector(layout)) => {
+            values.iter().all(|value| value_has_layout(value, layout))
+        }
+        (MoveValue::Struct(value), MoveTypeLayout::Struct(layout)) => {
+            let fields = match value {
+                MoveStruct::Runtime(fields) => fields.iter().collect::<Vec<_>>(),
+                MoveStruct::WithFields(fields) | MoveStruct::WithTypes { fields, .. } => {
+                    fields.iter().map(|(_, value)| value).collect()
+                }
+            };
+            let field_layouts = layout.fields();
+            fields.len() == field_layouts.len()
+                && fields
+                    .iter()
+                    .zip(field_layouts)
+                    .all(|(value, layout)| value_has_layout(value, layout))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,27 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hook for resolving the position in the source code of errors raised while executing a module.
+//!
+//! A `SourceLocator` is registered for a module with `MoveVM::set_source_locator`, e.g., by
+//! wrapping the source map the compiler emitted for it (see
+//! `move_bytecode_source_map::source_map::SourceMap::get_source_location`). When an abort or an
+//! arithmetic error is raised in the module, the VM asks the locator for the position of the
+//! instruction raising it, and attaches it to the `VMError` (see `VMError::source_location`), so
+//! that tests and tools can report the failing line rather than a bytecode offset.
+
+use move_binary_format::{
+    errors::SourceLocation,
+    file_format::{CodeOffset, FunctionDefinitionIndex},
+};
+
+/// Resolves the code offsets of the functions of a module into positions in its source code
+pub trait SourceLocator: Send + Sync {
+    /// Returns the position in the source code of the instruction at `offset` in the function
+    /// defined at `function`, or `None` if it is unknown
+    fn source_location(
+        &self,
+        function: FunctionDefinitionIndex,
+        offset: CodeOffset,
+    ) -> Option<SourceLocation>;
+}
@@ -0,0 +1,58 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hook for observing the execution of each instruction by the interpreter, e.g., for external
+//! profilers, debuggers or coverage tools.
+//!
+//! A `Tracer` is installed on a `Session` (see `Session::set_tracer`) and gets called before each
+//! instruction executed in that session. It only observes execution and cannot change it.
+
+use move_binary_format::file_format::{Bytecode, FunctionDefinitionIndex};
+use move_core_types::language_storage::ModuleId;
+use move_vm_types::{
+    loaded_data::runtime_types::Type,
+    values::{Locals, Value},
+};
+
+/// Information about the frame of the function executing an instruction
+pub struct FrameInfo<'a> {
+    /// Module declaring the function (`None` for a script)
+    pub module_id: Option<&'a ModuleId>,
+    /// Name of the function
+    pub function_name: &'a str,
+    /// Index of the function's definition in its module (or script)
+    pub function_index: FunctionDefinitionIndex,
+    /// Type arguments the function is instantiated with
+    pub ty_args: &'a [Type],
+    /// Locals of the function
+    pub locals: &'a Locals,
+    /// Number of frames below this one in the call stack (0 for the function called by the
+    /// session)
+    pub depth: usize,
+}
+
+/// A hook called by the interpreter before executing each instruction
+pub trait Tracer {
+    /// Called before the instruction at the given offset of the function of the given frame is
+    /// executed, with the operand stack as it is before the instruction (whose top is the last
+    /// value)
+    fn on_instruction(
+        &mut self,
+        frame: &FrameInfo,
+        pc: u16,
+        instruction: &Bytecode,
+        operand_stack: &[Value],
+    );
+}
+
+impl<T: Tracer + ?Sized> Tracer for &mut T {
+    fn on_instruction(
+        &mut self,
+        frame: &FrameInfo,
+        pc: u16,
+        instruction: &Bytecode,
+        operand_stack: &[Value],
+    ) {
+        (**self).on_instruction(frame, pc, instruction, operand_stack)
+    }
+}
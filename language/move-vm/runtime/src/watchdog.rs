@@ -0,0 +1,59 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hook for interrupting executions, e.g., once a wall-clock deadline is exceeded, so that
+//! services simulating transactions or evaluating view functions can bound their latency even
+//! with generous gas limits.
+//!
+//! A `Watchdog` is installed on a `Session` (see `Session::set_watchdog`) and gets called every
+//! given number of instructions executed by each call into the session. Execution is interrupted
+//! with `EXECUTION_INTERRUPTED` as soon as it asks for it.
+
+use move_binary_format::errors::{PartialVMError, PartialVMResult};
+use move_core_types::vm_status::StatusCode;
+
+/// A hook called by the interpreter periodically, which can interrupt execution
+pub trait Watchdog {
+    /// Returns whether execution must be interrupted
+    fn should_interrupt(&mut self) -> bool;
+}
+
+impl<F: FnMut() -> bool> Watchdog for F {
+    fn should_interrupt(&mut self) -> bool {
+        self()
+    }
+}
+
+/// A watchdog along with how often it is called
+pub(crate) struct ScheduledWatchdog<'a> {
+    interval: u64,
+    watchdog: Box<dyn Watchdog + 'a>,
+}
+
+impl<'a> ScheduledWatchdog<'a> {
+    pub(crate) fn new(interval: u64, watchdog: Box<dyn Watchdog + 'a>) -> Self {
+        Self {
+            // an interval of 0 would never be reached
+            interval: interval.max(1),
+            watchdog,
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> Box<dyn Watchdog + 'a> {
+        self.watchdog
+    }
+
+    // Calls the watchdog if `instructions_executed` is a multiple of the interval, and fails with
+    // `EXECUTION_INTERRUPTED` if it asks for it
+    pub(crate) fn check(&mut self, instructions_executed: u64) -> PartialVMResult<()> {
+        if instructions_executed % self.interval == 0 && self.watchdog.should_interrupt() {
+            return Err(
+                PartialVMError::new(StatusCode::EXECUTION_INTERRUPTED).with_message(format!(
+                    "execution interrupted by the watchdog after {} instructions",
+                    instructions_executed
+                )),
+            );
+        }
+        Ok(())
+    }
+}
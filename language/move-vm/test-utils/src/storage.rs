@@ -3,17 +3,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 #[allow(unused)]
-use anyhow::{anyhow, format_err, Error, Result};
+use anyhow::{anyhow, bail, format_err, Error, Result};
 #[allow(unused)]
 use move_core_types::gas_schedule::{GasAlgebra, GasCarrier, InternalGasUnits};
 use move_core_types::{
     account_address::AccountAddress,
-    effects::{AccountChangeSet, ChangeSet},
+    effects::{AccountChangeSet, ChangeSet, Op},
     identifier::Identifier,
     language_storage::{ModuleId, StructTag},
     resolver::{ModuleResolver, MoveResolver, ResourceResolver},
 };
-use std::collections::{btree_map, BTreeMap};
+use std::{
+    collections::{btree_map, BTreeMap},
+    fmt::Debug,
+};
 
 #[cfg(feature = "table-extension")]
 use move_table_extension::{TableChangeSet, TableHandle, TableOperation, TableResolver};
@@ -81,8 +84,8 @@ impl<'a, 'b, S: ModuleResolver> ModuleResolver for DeltaStorage<'a, 'b, S> {
 
     fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
         if let Some(account_storage) = self.delta.accounts().get(module_id.address()) {
-            if let Some(blob_opt) = account_storage.modules().get(module_id.name()) {
-                return Ok(blob_opt.clone());
+            if let Some(op) = account_storage.modules().get(module_id.name()) {
+                return Ok(op.clone().ok());
             }
         }
 
@@ -99,13 +102,21 @@ impl<'a, 'b, S: ResourceResolver> ResourceResolver for DeltaStorage<'a, 'b, S> {
         tag: &StructTag,
     ) -> Result<Option<Vec<u8>>, S::Error> {
         if let Some(account_storage) = self.delta.accounts().get(address) {
-            if let Some(blob_opt) = account_storage.resources().get(tag) {
-                return Ok(blob_opt.clone());
+            if let Some(op) = account_storage.resources().get(tag) {
+                return Ok(op.clone().ok());
             }
         }
 
         self.base.get_resource(address, tag)
     }
+
+    fn get_resource_group(&self, tag: &StructTag) -> Result<Option<StructTag>, S::Error> {
+        self.base.get_resource_group(tag)
+    }
+
+    fn prefetch_resources(&self, keys: &[(AccountAddress, StructTag)]) -> Result<(), S::Error> {
+        self.base.prefetch_resources(keys)
+    }
 }
 
 #[cfg(feature = "table-extension")]
@@ -147,53 +158,64 @@ struct InMemoryAccountStorage {
 #[derive(Debug, Clone)]
 pub struct InMemoryStorage {
     accounts: BTreeMap<AccountAddress, InMemoryAccountStorage>,
+    // resource types stored in a group, along with the tag of their group
+    resource_groups: BTreeMap<StructTag, StructTag>,
     #[cfg(feature = "table-extension")]
     tables: BTreeMap<TableHandle, BTreeMap<Vec<u8>, Vec<u8>>>,
 }
 
-fn apply_changes<K, V, F, E>(
+fn apply_changes<K, V>(
     tree: &mut BTreeMap<K, V>,
-    changes: impl IntoIterator<Item = (K, Option<V>)>,
-    make_err: F,
-) -> std::result::Result<(), E>
+    changes: impl IntoIterator<Item = (K, Op<V>)>,
+) -> Result<()>
 where
-    K: Ord,
-    F: FnOnce(K) -> E,
+    K: Ord + Debug,
 {
-    for (k, v_opt) in changes.into_iter() {
-        match (tree.entry(k), v_opt) {
-            (btree_map::Entry::Vacant(entry), None) => return Err(make_err(entry.into_key())),
-            (btree_map::Entry::Vacant(entry), Some(v)) => {
-                entry.insert(v);
+    use btree_map::Entry::*;
+    use Op::*;
+
+    for (k, op) in changes.into_iter() {
+        match (tree.entry(k), op) {
+            (Occupied(entry), New(_)) => {
+                bail!(
+                    "Failed to apply changes -- key {:?} already exists",
+                    entry.key()
+                )
             }
-            (btree_map::Entry::Occupied(entry), None) => {
+            (Occupied(entry), Delete) => {
                 entry.remove();
             }
-            (btree_map::Entry::Occupied(entry), Some(v)) => {
-                *entry.into_mut() = v;
+            (Occupied(entry), Modify(val)) => {
+                *entry.into_mut() = val;
+            }
+            (Vacant(entry), New(val)) => {
+                entry.insert(val);
             }
+            (Vacant(entry), Delete | Modify(_)) => bail!(
+                "Failed to apply changes -- key {:?} does not exist",
+                entry.key()
+            ),
         }
     }
     Ok(())
 }
 
+fn get_or_insert<K, V, F>(map: &mut BTreeMap<K, V>, key: K, make_default: F) -> &mut V
+where
+    K: Ord,
+    F: FnOnce() -> V,
+{
+    match map.entry(key) {
+        btree_map::Entry::Occupied(entry) => entry.into_mut(),
+        btree_map::Entry::Vacant(entry) => entry.insert(make_default()),
+    }
+}
+
 impl InMemoryAccountStorage {
     fn apply(&mut self, account_changeset: AccountChangeSet) -> Result<()> {
         let (modules, resources) = account_changeset.into_inner();
-        apply_changes(&mut self.modules, modules, |module_name| {
-            format_err!(
-                "Failed to delete module {}: module does not exist.",
-                module_name
-            )
-        })?;
-
-        apply_changes(&mut self.resources, resources, |struct_tag| {
-            format_err!(
-                "Failed to delete resource {}: resource does not exist.",
-                struct_tag
-            )
-        })?;
-
+        apply_changes(&mut self.modules, modules)?;
+        apply_changes(&mut self.resources, resources)?;
         Ok(())
     }
 
@@ -267,20 +289,24 @@ impl InMemoryStorage {
     pub fn new() -> Self {
         Self {
             accounts: BTreeMap::new(),
+            resource_groups: BTreeMap::new(),
             #[cfg(feature = "table-extension")]
             tables: BTreeMap::new(),
         }
     }
 
+    /// Stores resources of the given type in the given resource group.
+    pub fn add_resource_group_member(&mut self, member: StructTag, group: StructTag) {
+        self.resource_groups.insert(member, group);
+    }
+
     pub fn publish_or_overwrite_module(&mut self, module_id: ModuleId, blob: Vec<u8>) {
-        let mut delta = ChangeSet::new();
-        delta.publish_module(module_id, blob).unwrap();
-        self.apply_extended(
-            delta,
-            #[cfg(feature = "table-extension")]
-            TableChangeSet::default(),
-        )
-        .unwrap();
+        let account = get_or_insert(
+            &mut self.accounts,
+            *module_id.address(),
+            InMemoryAccountStorage::new,
+        );
+        account.modules.insert(module_id.name().to_owned(), blob);
     }
 
     pub fn publish_or_overwrite_resource(
@@ -289,14 +315,8 @@ impl InMemoryStorage {
         struct_tag: StructTag,
         blob: Vec<u8>,
     ) {
-        let mut delta = ChangeSet::new();
-        delta.publish_resource(addr, struct_tag, blob).unwrap();
-        self.apply_extended(
-            delta,
-            #[cfg(feature = "table-extension")]
-            TableChangeSet::default(),
-        )
-        .unwrap();
+        let account = get_or_insert(&mut self.accounts, addr, InMemoryAccountStorage::new);
+        account.resources.insert(struct_tag, blob);
     }
 }
 
@@ -324,6 +344,10 @@ impl ResourceResolver for InMemoryStorage {
         }
         Ok(None)
     }
+
+    fn get_resource_group(&self, tag: &StructTag) -> Result<Option<StructTag>, Self::Error> {
+        Ok(self.resource_groups.get(tag).cloned())
+    }
 }
 
 #[cfg(feature = "table-extension")]
@@ -8,7 +8,10 @@ use crate::{
 };
 use move_binary_format::errors::{PartialVMResult, VMResult};
 use move_core_types::{
-    account_address::AccountAddress, language_storage::ModuleId, value::MoveTypeLayout,
+    account_address::AccountAddress,
+    gas_schedule::{AbstractMemorySize, GasCarrier},
+    language_storage::ModuleId,
+    value::MoveTypeLayout,
 };
 
 /// Provide an implementation for bytecodes related to data with a given data store.
@@ -24,17 +27,44 @@ pub trait DataStore {
 
     /// Try to load a resource from remote storage and create a corresponding GlobalValue
     /// that is owned by the data store.
+    ///
+    /// If the resource is a member of a resource group which had to be loaded from remote storage,
+    /// the size of the group is returned as well.
     fn load_resource(
         &mut self,
         addr: AccountAddress,
         ty: &Type,
-    ) -> PartialVMResult<&mut GlobalValue>;
+    ) -> PartialVMResult<(&mut GlobalValue, Option<AbstractMemorySize<GasCarrier>>)>;
+
+    /// Hint that the given resources are likely to be loaded soon, so that the ones not loaded yet
+    /// can be fetched from remote storage in one batch. Called by the interpreter when it can
+    /// predict reads, if `VMConfig::prefetch_resources` is set.
+    fn prefetch_resources(&mut self, _resources: &[(AccountAddress, Type)]) -> PartialVMResult<()> {
+        Ok(())
+    }
 
     /// Get the serialized format of a `CompiledModule` given a `ModuleId`.
     fn load_module(&self, module_id: &ModuleId) -> VMResult<Vec<u8>>;
 
-    /// Publish a module.
-    fn publish_module(&mut self, module_id: &ModuleId, blob: Vec<u8>) -> VMResult<()>;
+    /// Check whether the code executed in this session used a module, see `record_module_use`.
+    fn is_module_used(&self, module_id: &ModuleId) -> bool;
+
+    /// Record that the code being executed uses a module, i.e., the module of the function called
+    /// by the client or one of its (transitive) dependencies, whether it was loaded from this data
+    /// store or served from the code cache.
+    fn record_module_use(&mut self, module_id: &ModuleId);
+
+    /// Publish a module. `is_republishing` tells whether the module already exists, i.e., is
+    /// upgraded rather than newly published.
+    fn publish_module(
+        &mut self,
+        module_id: &ModuleId,
+        blob: Vec<u8>,
+        is_republishing: bool,
+    ) -> VMResult<()>;
+
+    /// Remove a module, which must exist.
+    fn unpublish_module(&mut self, module_id: &ModuleId) -> VMResult<()>;
 
     /// Check if this module exists.
     fn exists_module(&self, module_id: &ModuleId) -> VMResult<bool>;
@@ -43,14 +73,15 @@ pub trait DataStore {
     // EventStore operations
     // ---
 
-    /// Emit an event to the EventStore
+    /// Emit an event to the EventStore, and return the size of the event once serialized, which
+    /// the VM charges for.
     fn emit_event(
         &mut self,
         guid: Vec<u8>,
         seq_num: u64,
         ty: Type,
         val: Value,
-    ) -> PartialVMResult<()>;
+    ) -> PartialVMResult<u64>;
 
     fn events(&self) -> &Vec<(Vec<u8>, u64, Type, MoveTypeLayout, Value)>;
 }
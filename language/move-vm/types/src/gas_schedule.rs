@@ -7,6 +7,7 @@
 //! It is important to note that the cost schedule defined in this file does not track hashing
 //! operations or other native operations; the cost of each native operation will be returned by the
 //! native function itself.
+use crate::natives::function::native_gas;
 use move_binary_format::{
     errors::{Location, PartialVMError, PartialVMResult, VMResult},
     file_format::{
@@ -19,8 +20,9 @@ use move_binary_format::{
 use move_core_types::{
     gas_schedule::{
         AbstractMemorySize, CostTable, GasAlgebra, GasCarrier, GasConstants, GasCost, GasUnits,
-        InternalGasUnits,
+        InternalGasUnits, NativeGasParameters, VersionedCostTables,
     },
+    language_storage::ModuleId,
     vm_status::StatusCode,
 };
 use once_cell::sync::Lazy;
@@ -29,14 +31,89 @@ use std::cmp::max;
 static ZERO_COST_SCHEDULE: Lazy<CostTable> =
     Lazy::new(|| zero_cost_schedule(NUMBER_OF_NATIVE_FUNCTIONS));
 
+/// A gas meter, charging for the operations performed by the Move VM.
+///
+/// The interpreter is generic over the gas meter, so that clients can implement their own pricing
+/// models. `GasStatus` is the default implementation, pricing operations with a `CostTable`.
+pub trait GasMeter {
+    /// Charge an instruction and fail if not enough gas units are left.
+    fn charge_instr(&mut self, opcode: Opcodes) -> PartialVMResult<()>;
+
+    /// Charge an instruction over data with a given size and fail if not enough gas units are left.
+    fn charge_instr_with_size(
+        &mut self,
+        opcode: Opcodes,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()>;
+
+    /// Charge the cost returned by a native function and fail if not enough gas units are left.
+    fn charge_native_function(
+        &mut self,
+        amount: InternalGasUnits<GasCarrier>,
+    ) -> PartialVMResult<()>;
+
+    /// Charge for a module of the given size the code being executed depends on, i.e., the module
+    /// of the function called by the client or one of its (transitive) dependencies, and fail if
+    /// not enough gas units are left.
+    ///
+    /// Each module is charged before the execution starts, the first time it is used in the
+    /// session, whether or not the code cache holds it already, so that the gas used does not
+    /// depend on the state of the cache.
+    fn charge_dependency(
+        &mut self,
+        module_id: &ModuleId,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()>;
+
+    /// Charge for reading a resource group of the given size from storage and fail if not enough
+    /// gas units are left.
+    fn charge_resource_group_load(
+        &mut self,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()>;
+
+    /// Charge for events of the given size, once serialized, emitted by a native function and fail
+    /// if not enough gas units are left. Natives emitting events do not charge for their size
+    /// themselves.
+    fn charge_emit_event(&mut self, size: AbstractMemorySize<GasCarrier>) -> PartialVMResult<()>;
+
+    /// Charge for writing a published module of the given size to storage and fail if not enough
+    /// gas units are left.
+    fn charge_write_module(
+        &mut self,
+        module_id: &ModuleId,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()>;
+
+    /// Return the cost of the native operation with the given index over data of the given size.
+    /// Native functions price themselves with this, the interpreter then charges the cost they
+    /// return with `charge_native_function`.
+    fn native_cost(&self, native_table_idx: u8, size: usize) -> InternalGasUnits<GasCarrier>;
+
+    /// Return the value of a named gas parameter for natives registered by the embedder, or `None`
+    /// if there is no such parameter.
+    fn native_parameter(&self, name: &str) -> Option<InternalGasUnits<GasCarrier>>;
+
+    /// Return the gas left, e.g., to profile how much gas is consumed where.
+    fn gas_left(&self) -> InternalGasUnits<GasCarrier>;
+
+    /// Return the gas left for native functions to bound the work they do (see
+    /// `NativeContext::gas_balance`), or `None` if gas is not metered.
+    fn gas_balance(&self) -> Option<InternalGasUnits<GasCarrier>> {
+        Some(self.gas_left())
+    }
+}
+
 /// The Move VM implementation of state for gas metering.
 ///
 /// Initialize with a `CostTable` and the gas provided to the transaction.
 /// Provide all the proper guarantees about gas metering in the Move VM.
 ///
-/// Every client must use an instance of this type to interact with the Move VM.
+/// Clients which do not implement their own `GasMeter` must use an instance of this type to
+/// interact with the Move VM.
 pub struct GasStatus<'a> {
     cost_table: &'a CostTable,
+    native_parameters: Option<&'a NativeGasParameters>,
     gas_left: InternalGasUnits<GasCarrier>,
     charge: bool,
 }
@@ -50,10 +127,25 @@ impl<'a> GasStatus<'a> {
         Self {
             gas_left: cost_table.gas_constants.to_internal_units(gas_left),
             cost_table,
+            native_parameters: None,
             charge: true,
         }
     }
 
+    /// Initialize the gas state with metering enabled, pricing operations with the given version
+    /// of the cost table. Fails with `UNKNOWN_GAS_SCHEDULE_VERSION` if there is no such version.
+    pub fn new_versioned(
+        cost_tables: &'a VersionedCostTables,
+        version: u64,
+        gas_left: GasUnits<GasCarrier>,
+    ) -> PartialVMResult<Self> {
+        let cost_table = cost_tables.get(version).ok_or_else(|| {
+            PartialVMError::new(StatusCode::UNKNOWN_GAS_SCHEDULE_VERSION)
+                .with_message(format!("no gas schedule of version {}", version))
+        })?;
+        Ok(Self::new(cost_table, gas_left))
+    }
+
     /// Initialize the gas state with metering disabled.
     ///
     /// It should be used by clients in very specific cases and when executing system
@@ -62,10 +154,18 @@ impl<'a> GasStatus<'a> {
         Self {
             gas_left: InternalGasUnits::new(0),
             cost_table: &ZERO_COST_SCHEDULE,
+            native_parameters: None,
             charge: false,
         }
     }
 
+    /// Provide the gas parameters of the natives of the embedder (see
+    /// `NativeContext::native_gas_parameter`).
+    pub fn with_native_parameters(mut self, native_parameters: &'a NativeGasParameters) -> Self {
+        self.native_parameters = Some(native_parameters);
+        self
+    }
+
     /// Return the `CostTable` behind this `GasStatus`.
     pub fn cost_table(&self) -> &CostTable {
         self.cost_table
@@ -134,6 +234,90 @@ impl<'a> GasStatus<'a> {
     }
 }
 
+impl<'a> GasMeter for GasStatus<'a> {
+    fn charge_instr(&mut self, opcode: Opcodes) -> PartialVMResult<()> {
+        GasStatus::charge_instr(self, opcode)
+    }
+
+    fn charge_instr_with_size(
+        &mut self,
+        opcode: Opcodes,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        GasStatus::charge_instr_with_size(self, opcode, size)
+    }
+
+    fn charge_native_function(
+        &mut self,
+        amount: InternalGasUnits<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        self.deduct_gas(amount)
+    }
+
+    fn charge_dependency(
+        &mut self,
+        _module_id: &ModuleId,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        self.deduct_gas(
+            self.cost_table
+                .gas_constants
+                .dependency_per_byte_cost
+                .mul(size),
+        )
+    }
+
+    fn charge_resource_group_load(
+        &mut self,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        self.deduct_gas(
+            self.cost_table
+                .gas_constants
+                .global_memory_per_byte_cost
+                .mul(size),
+        )
+    }
+
+    fn charge_emit_event(&mut self, size: AbstractMemorySize<GasCarrier>) -> PartialVMResult<()> {
+        self.deduct_gas(
+            self.cost_table
+                .gas_constants
+                .global_memory_per_byte_write_cost
+                .mul(size),
+        )
+    }
+
+    // Publishing modules is not priced by the cost table
+    fn charge_write_module(
+        &mut self,
+        _module_id: &ModuleId,
+        _size: AbstractMemorySize<GasCarrier>,
+    ) -> PartialVMResult<()> {
+        Ok(())
+    }
+
+    fn native_cost(&self, native_table_idx: u8, size: usize) -> InternalGasUnits<GasCarrier> {
+        native_gas(self.cost_table, native_table_idx, size)
+    }
+
+    fn native_parameter(&self, name: &str) -> Option<InternalGasUnits<GasCarrier>> {
+        self.native_parameters?.get(name)
+    }
+
+    fn gas_left(&self) -> InternalGasUnits<GasCarrier> {
+        self.gas_left
+    }
+
+    fn gas_balance(&self) -> Option<InternalGasUnits<GasCarrier>> {
+        if self.charge {
+            Some(self.gas_left)
+        } else {
+            None
+        }
+    }
+}
+
 pub fn new_from_instructions(
     mut instrs: Vec<(Bytecode, GasCost)>,
     native_table: Vec<GasCost>,
@@ -292,7 +476,10 @@ pub fn zero_cost_schedule(num_of_native_funcs: usize) -> CostTable {
     let native_table = (0..num_of_native_funcs)
         .map(|_| GasCost::new(0, 0))
         .collect::<Vec<GasCost>>();
-    new_from_instructions(instrs, native_table)
+    let mut table = new_from_instructions(instrs, native_table);
+    // modules the code depends on are free as well
+    table.gas_constants.dependency_per_byte_cost = InternalGasUnits::new(0);
+    table
 }
 
 pub fn bytecode_instruction_costs() -> Vec<(Bytecode, GasCost)> {
@@ -40,6 +40,9 @@ pub struct NativeResult {
     pub cost: InternalGasUnits<GasCarrier>,
     /// Result of execution. This is either the return values or the error to report.
     pub result: Result<SmallVec<[Value; 1]>, u64>,
+    /// Payload of the abort, if the execution failed and the function reported more than its
+    /// abort code, e.g., a human-readable reason for the failure.
+    pub abort_payload: Option<Vec<u8>>,
 }
 
 impl NativeResult {
@@ -48,17 +51,45 @@ impl NativeResult {
         NativeResult {
             cost,
             result: Ok(values),
+            abort_payload: None,
         }
     }
 
     /// Failed execution. The failure is a runtime failure in the function and not an invariant
     /// failure of the VM which would raise a `PartialVMError` error directly.
     /// The only thing the funciton can specify is its abort code, as if it had invoked the `Abort`
-    /// bytecode instruction
+    /// bytecode instruction (see `err_with_payload` to report more)
     pub fn err(cost: InternalGasUnits<GasCarrier>, abort_code: u64) -> Self {
         NativeResult {
             cost,
             result: Err(abort_code),
+            abort_payload: None,
+        }
+    }
+
+    /// Failed execution, as in `err`, which also reports a payload along with the abort code,
+    /// e.g., a human-readable reason for the failure. The payload ends up in the `VMError` of the
+    /// abort.
+    pub fn err_with_payload(
+        cost: InternalGasUnits<GasCarrier>,
+        abort_code: u64,
+        abort_payload: Vec<u8>,
+    ) -> Self {
+        NativeResult {
+            cost,
+            result: Err(abort_code),
+            abort_payload: Some(abort_payload),
+        }
+    }
+
+    // Turns an `ABORTED` error into a failed execution, keeping its payload
+    fn from_abort(cost: InternalGasUnits<GasCarrier>, err: PartialVMError) -> Self {
+        let abort_payload = err.abort_payload().map(|payload| payload.to_vec());
+        let (_, abort_code, _, _, _, _) = err.all_data();
+        NativeResult {
+            cost,
+            result: Err(abort_code.unwrap_or(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR as u64)),
+            abort_payload,
         }
     }
 
@@ -70,11 +101,7 @@ impl NativeResult {
         let result = match res {
             Ok(_) => NativeResult::ok(cost, smallvec![]),
             Err(err) if err.major_status() == StatusCode::ABORTED => {
-                let (_, abort_code, _, _, _, _) = err.all_data();
-                NativeResult::err(
-                    cost,
-                    abort_code.unwrap_or(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR as u64),
-                )
+                NativeResult::from_abort(cost, err)
             }
             Err(err) => {
                 return Err(err);
@@ -91,11 +118,7 @@ impl NativeResult {
         let result = match res {
             Ok(val) => NativeResult::ok(cost, smallvec![val]),
             Err(err) if err.major_status() == StatusCode::ABORTED => {
-                let (_, abort_code, _, _, _, _) = err.all_data();
-                NativeResult::err(
-                    cost,
-                    abort_code.unwrap_or(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR as u64),
-                )
+                NativeResult::from_abort(cost, err)
             }
             Err(err) => {
                 return Err(err);
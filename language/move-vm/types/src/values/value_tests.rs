@@ -4,6 +4,10 @@
 
 use crate::values::*;
 use move_binary_format::errors::*;
+use move_core_types::{
+    account_address::AccountAddress,
+    value::{MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
+};
 
 #[test]
 fn locals() -> PartialVMResult<()> {
@@ -32,6 +36,31 @@ fn locals() -> PartialVMResult<()> {
     Ok(())
 }
 
+#[test]
+fn locals_pool() -> PartialVMResult<()> {
+    let mut pool = LocalsPool::new();
+    let mut locals = pool.new_locals(2);
+    locals.store_loc(0, Value::u64(42))?;
+    pool.release(locals);
+
+    // reused locals start invalid, with the number of locals requested
+    let mut locals = pool.new_locals(3);
+    for i in 0..3 {
+        assert!(locals.copy_loc(i).is_err());
+    }
+    assert!(locals.copy_loc(3).is_err());
+    locals.store_loc(2, Value::u64(1))?;
+
+    // locals still referenced are left alone
+    let r = locals.borrow_loc(2)?.value_as::<Reference>()?;
+    pool.release(locals);
+    let locals = pool.new_locals(1);
+    assert!(locals.copy_loc(0).is_err());
+    assert!(r.read_ref()?.equals(&Value::u64(1))?);
+
+    Ok(())
+}
+
 #[test]
 fn struct_pack_and_unpack() -> PartialVMResult<()> {
     let vals = vec![Value::u8(10), Value::u64(20), Value::u128(30)];
@@ -121,3 +150,130 @@ fn global_value_non_struct() -> PartialVMResult<()> {
 
     Ok(())
 }
+
+// Records what is visited, as strings prefixed by the depth
+#[derive(Default)]
+struct RecordingVisitor(Vec<String>);
+
+impl ValueVisitor for RecordingVisitor {
+    fn visit_u8(&mut self, depth: usize, val: u8) {
+        self.0.push(format!("{} u8 {}", depth, val));
+    }
+
+    fn visit_u64(&mut self, depth: usize, val: u64) {
+        self.0.push(format!("{} u64 {}", depth, val));
+    }
+
+    fn visit_u128(&mut self, depth: usize, val: u128) {
+        self.0.push(format!("{} u128 {}", depth, val));
+    }
+
+    fn visit_bool(&mut self, depth: usize, val: bool) {
+        self.0.push(format!("{} bool {}", depth, val));
+    }
+
+    fn visit_address(&mut self, depth: usize, val: AccountAddress) {
+        self.0
+            .push(format!("{} address {}", depth, val.short_str_lossless()));
+    }
+
+    fn visit_struct(&mut self, depth: usize, len: usize) -> bool {
+        self.0.push(format!("{} struct {}", depth, len));
+        true
+    }
+
+    fn visit_vec(&mut self, depth: usize, len: usize) -> bool {
+        self.0.push(format!("{} vec {}", depth, len));
+        true
+    }
+
+    fn visit_ref(&mut self, depth: usize, is_global: bool) -> bool {
+        self.0.push(format!("{} ref {}", depth, is_global));
+        // do not follow references to global storage
+        !is_global
+    }
+}
+
+#[test]
+fn visit_values() -> PartialVMResult<()> {
+    let val = Value::struct_(Struct::pack(vec![
+        Value::bool(true),
+        Value::vector_u8(vec![1, 2]),
+        Value::signer(AccountAddress::ONE),
+    ]));
+    let mut visitor = RecordingVisitor::default();
+    val.visit(&mut visitor);
+    assert_eq!(
+        visitor.0,
+        vec![
+            "0 struct 3",
+            "1 bool true",
+            "1 vec 2",
+            "2 u8 1",
+            "2 u8 2",
+            "1 struct 1",
+            "2 address 1",
+        ]
+    );
+
+    let mut locals = Locals::new(1);
+    locals.store_loc(0, Value::u128(7))?;
+    let r: Reference = locals.borrow_loc(0)?.value_as()?;
+    let mut visitor = RecordingVisitor::default();
+    r.visit(&mut visitor);
+    assert_eq!(visitor.0, vec!["0 ref false", "1 u128 7"]);
+
+    let gv = GlobalValue::cached(Value::struct_(Struct::pack(vec![Value::u64(3)])))?;
+    let r: Reference = gv.borrow_global()?.value_as()?;
+    let mut visitor = RecordingVisitor::default();
+    r.visit(&mut visitor);
+    assert_eq!(visitor.0, vec!["0 ref true"]);
+
+    let mut visitor = RecordingVisitor::default();
+    gv.visit(&mut visitor);
+    assert_eq!(visitor.0, vec!["0 struct 1", "1 u64 3"]);
+
+    Ok(())
+}
+
+#[test]
+fn move_value_conversion() -> PartialVMResult<()> {
+    use MoveTypeLayout as L;
+
+    let layout = L::Struct(MoveStructLayout::new(vec![
+        L::Signer,
+        L::Vector(Box::new(L::U8)),
+        L::Vector(Box::new(L::Struct(MoveStructLayout::new(vec![
+            L::U64,
+            L::Bool,
+        ])))),
+    ]));
+    let move_val = MoveValue::Struct(MoveStruct::new(vec![
+        MoveValue::Signer(AccountAddress::ONE),
+        MoveValue::vector_u8(vec![1, 2]),
+        MoveValue::Vector(vec![MoveValue::Struct(MoveStruct::new(vec![
+            MoveValue::U64(3),
+            MoveValue::Bool(true),
+        ]))]),
+    ]));
+
+    let val = Value::from_move_value(&move_val, &layout)?;
+    assert_eq!(val.simple_serialize(&layout), move_val.simple_serialize());
+    assert_eq!(val.to_move_value(&layout)?, move_val);
+
+    // values not matching the layout
+    assert!(Value::from_move_value(&MoveValue::U8(1), &L::U64).is_err());
+    assert!(Value::u64(1)
+        .to_move_value(&L::Vector(Box::new(L::U64)))
+        .is_err());
+    assert!(Value::vector_u8(vec![1])
+        .to_move_value(&L::Vector(Box::new(L::U64)))
+        .is_err());
+
+    // references cannot be converted
+    let mut locals = Locals::new(1);
+    locals.store_loc(0, Value::u64(1))?;
+    assert!(locals.borrow_loc(0)?.to_move_value(&L::U64).is_err());
+
+    Ok(())
+}
@@ -9,11 +9,12 @@ use move_binary_format::{
 };
 use move_core_types::{
     account_address::AccountAddress,
+    effects::Op,
     gas_schedule::{
         AbstractMemorySize, GasAlgebra, GasCarrier, CONST_SIZE, MIN_EXISTS_DATA_SIZE,
         REFERENCE_SIZE, STRUCT_SIZE,
     },
-    value::{MoveStructLayout, MoveTypeLayout},
+    value::{MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
     vm_status::{sub_status::NFE_VECTOR_ERROR_BASE, StatusCode},
 };
 use std::{
@@ -162,6 +163,13 @@ pub struct Value(ValueImpl);
 #[derive(Debug)]
 pub struct Locals(Rc<RefCell<Vec<ValueImpl>>>);
 
+/// A pool of the allocations of the locals of frames which returned, reused for the locals of
+/// the frames called next, so that calls in loops or recursions do not hit the allocator.
+#[derive(Debug, Default)]
+pub struct LocalsPool {
+    free: Vec<Rc<RefCell<Vec<ValueImpl>>>>,
+}
+
 /// An integer value in Move.
 #[derive(Debug)]
 pub enum IntegerValue {
@@ -200,16 +208,6 @@ enum GlobalValueImpl {
 #[derive(Debug)]
 pub struct GlobalValue(GlobalValueImpl);
 
-/// Simple enum for the change state of a GlobalValue, used by `into_effect`.
-pub enum GlobalValueEffect<T> {
-    /// There was no value, or the value was not changed
-    None,
-    /// The value was removed
-    Deleted,
-    /// Updated with a new value
-    Changed(T),
-}
-
 /***************************************************************************************
  *
  * Misc
@@ -953,6 +951,33 @@ impl Locals {
     }
 }
 
+impl LocalsPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `n` invalid locals, reusing an allocation from the pool if there is one.
+    pub fn new_locals(&mut self, n: usize) -> Locals {
+        match self.free.pop() {
+            Some(r) => {
+                r.borrow_mut()
+                    .extend(iter::repeat_with(|| ValueImpl::Invalid).take(n));
+                Locals(r)
+            }
+            None => Locals::new(n),
+        }
+    }
+
+    /// Drops the values of locals no longer in use, and keeps their allocation for the next
+    /// locals created. Locals still referenced are not reused.
+    pub fn release(&mut self, locals: Locals) {
+        if Rc::strong_count(&locals.0) == 1 {
+            locals.0.borrow_mut().clear();
+            self.free.push(locals.0);
+        }
+    }
+}
+
 /***************************************************************************************
  *
  * Public Value Constructors
@@ -1890,6 +1915,235 @@ impl GlobalValue {
     }
 }
 
+/***************************************************************************************
+ *
+ * Traversal
+ *
+ *   Visiting the contents of VM values, e.g., to compute sizes or hashes in natives,
+ *   without relying on their internal representation.
+ *
+ **************************************************************************************/
+
+/// A visitor over the contents of a value, called by `ValueView::visit` in depth-first order.
+///
+/// `depth` is the depth of the value visited, the root value being at depth 0, and the elements
+/// of a vector, the fields of a struct or the value a reference points to one level deeper. The
+/// visitor of a vector, a struct or a reference returns whether to visit its contents.
+///
+/// The visitors of vectors of primitives default to visiting the vector and then each element,
+/// and can be overridden to process the elements at once.
+pub trait ValueVisitor {
+    fn visit_u8(&mut self, depth: usize, val: u8);
+    fn visit_u64(&mut self, depth: usize, val: u64);
+    fn visit_u128(&mut self, depth: usize, val: u128);
+    fn visit_bool(&mut self, depth: usize, val: bool);
+    fn visit_address(&mut self, depth: usize, val: AccountAddress);
+
+    /// Visit a struct with `len` fields. A signer is visited as a struct with one address field.
+    fn visit_struct(&mut self, depth: usize, len: usize) -> bool;
+    /// Visit a vector with `len` elements.
+    fn visit_vec(&mut self, depth: usize, len: usize) -> bool;
+    /// Visit a reference, to a value in global storage if `is_global` is set.
+    fn visit_ref(&mut self, depth: usize, is_global: bool) -> bool;
+
+    fn visit_vec_u8(&mut self, depth: usize, vals: &[u8]) {
+        if self.visit_vec(depth, vals.len()) {
+            for val in vals {
+                self.visit_u8(depth + 1, *val);
+            }
+        }
+    }
+
+    fn visit_vec_u64(&mut self, depth: usize, vals: &[u64]) {
+        if self.visit_vec(depth, vals.len()) {
+            for val in vals {
+                self.visit_u64(depth + 1, *val);
+            }
+        }
+    }
+
+    fn visit_vec_u128(&mut self, depth: usize, vals: &[u128]) {
+        if self.visit_vec(depth, vals.len()) {
+            for val in vals {
+                self.visit_u128(depth + 1, *val);
+            }
+        }
+    }
+
+    fn visit_vec_bool(&mut self, depth: usize, vals: &[bool]) {
+        if self.visit_vec(depth, vals.len()) {
+            for val in vals {
+                self.visit_bool(depth + 1, *val);
+            }
+        }
+    }
+
+    fn visit_vec_address(&mut self, depth: usize, vals: &[AccountAddress]) {
+        if self.visit_vec(depth, vals.len()) {
+            for val in vals {
+                self.visit_address(depth + 1, *val);
+            }
+        }
+    }
+}
+
+/// A value whose contents can be visited with a `ValueVisitor`.
+pub trait ValueView {
+    fn visit(&self, visitor: &mut impl ValueVisitor);
+}
+
+impl ValueImpl {
+    fn visit_impl(&self, visitor: &mut impl ValueVisitor, depth: usize) {
+        use ValueImpl::*;
+
+        match self {
+            // invalid values only live in the slots of locals which were moved from, and cannot
+            // be reached from a valid value
+            Invalid => (),
+
+            U8(val) => visitor.visit_u8(depth, *val),
+            U64(val) => visitor.visit_u64(depth, *val),
+            U128(val) => visitor.visit_u128(depth, *val),
+            Bool(val) => visitor.visit_bool(depth, *val),
+            Address(val) => visitor.visit_address(depth, *val),
+
+            Container(c) => c.visit_impl(visitor, depth),
+
+            ContainerRef(r) => r.visit_impl(visitor, depth),
+            IndexedRef(r) => r.visit_impl(visitor, depth),
+        }
+    }
+}
+
+impl Container {
+    fn visit_impl(&self, visitor: &mut impl ValueVisitor, depth: usize) {
+        match self {
+            // locals are only reached through references to one of them, see `visit_indexed`
+            Self::Locals(_) => (),
+            Self::Vec(r) => {
+                let r = r.borrow();
+                if visitor.visit_vec(depth, r.len()) {
+                    for val in r.iter() {
+                        val.visit_impl(visitor, depth + 1);
+                    }
+                }
+            }
+            Self::Struct(r) => {
+                let r = r.borrow();
+                if visitor.visit_struct(depth, r.len()) {
+                    for val in r.iter() {
+                        val.visit_impl(visitor, depth + 1);
+                    }
+                }
+            }
+            Self::VecU8(r) => visitor.visit_vec_u8(depth, &*r.borrow()),
+            Self::VecU64(r) => visitor.visit_vec_u64(depth, &*r.borrow()),
+            Self::VecU128(r) => visitor.visit_vec_u128(depth, &*r.borrow()),
+            Self::VecBool(r) => visitor.visit_vec_bool(depth, &*r.borrow()),
+            Self::VecAddress(r) => visitor.visit_vec_address(depth, &*r.borrow()),
+        }
+    }
+
+    // Visits the element at `idx`, as the value pointed to by a reference at `depth`
+    fn visit_indexed(&self, visitor: &mut impl ValueVisitor, depth: usize, idx: usize) {
+        match self {
+            Self::Locals(r) | Self::Vec(r) | Self::Struct(r) => {
+                if let Some(val) = r.borrow().get(idx) {
+                    val.visit_impl(visitor, depth + 1)
+                }
+            }
+            Self::VecU8(r) => {
+                if let Some(val) = r.borrow().get(idx) {
+                    visitor.visit_u8(depth + 1, *val)
+                }
+            }
+            Self::VecU64(r) => {
+                if let Some(val) = r.borrow().get(idx) {
+                    visitor.visit_u64(depth + 1, *val)
+                }
+            }
+            Self::VecU128(r) => {
+                if let Some(val) = r.borrow().get(idx) {
+                    visitor.visit_u128(depth + 1, *val)
+                }
+            }
+            Self::VecBool(r) => {
+                if let Some(val) = r.borrow().get(idx) {
+                    visitor.visit_bool(depth + 1, *val)
+                }
+            }
+            Self::VecAddress(r) => {
+                if let Some(val) = r.borrow().get(idx) {
+                    visitor.visit_address(depth + 1, *val)
+                }
+            }
+        }
+    }
+}
+
+impl ContainerRef {
+    fn visit_impl(&self, visitor: &mut impl ValueVisitor, depth: usize) {
+        let is_global = matches!(self, Self::Global { .. });
+        if visitor.visit_ref(depth, is_global) {
+            self.container().visit_impl(visitor, depth + 1);
+        }
+    }
+}
+
+impl IndexedRef {
+    fn visit_impl(&self, visitor: &mut impl ValueVisitor, depth: usize) {
+        let is_global = matches!(self.container_ref, ContainerRef::Global { .. });
+        if visitor.visit_ref(depth, is_global) {
+            self.container_ref
+                .container()
+                .visit_indexed(visitor, depth, self.idx);
+        }
+    }
+}
+
+impl ValueView for Value {
+    fn visit(&self, visitor: &mut impl ValueVisitor) {
+        self.0.visit_impl(visitor, 0)
+    }
+}
+
+impl ValueView for Struct {
+    fn visit(&self, visitor: &mut impl ValueVisitor) {
+        if visitor.visit_struct(0, self.fields.len()) {
+            for val in self.fields.iter() {
+                val.visit_impl(visitor, 1);
+            }
+        }
+    }
+}
+
+impl ValueView for Reference {
+    fn visit(&self, visitor: &mut impl ValueVisitor) {
+        match &self.0 {
+            ReferenceImpl::ContainerRef(r) => r.visit_impl(visitor, 0),
+            ReferenceImpl::IndexedRef(r) => r.visit_impl(visitor, 0),
+        }
+    }
+}
+
+impl ValueView for GlobalValue {
+    /// Visit the resource in the slot, if any, as a struct. Nothing is visited if the slot is
+    /// empty.
+    fn visit(&self, visitor: &mut impl ValueVisitor) {
+        match &self.0 {
+            GlobalValueImpl::None | GlobalValueImpl::Deleted => (),
+            GlobalValueImpl::Fresh { fields } | GlobalValueImpl::Cached { fields, .. } => {
+                let fields = fields.borrow();
+                if visitor.visit_struct(0, fields.len()) {
+                    for val in fields.iter() {
+                        val.visit_impl(visitor, 1);
+                    }
+                }
+            }
+        }
+    }
+}
+
 /***************************************************************************************
  *
  * Struct Operations
@@ -1997,18 +2251,42 @@ impl GlobalValueImpl {
         }
     }
 
-    fn into_effect(self) -> PartialVMResult<GlobalValueEffect<ValueImpl>> {
-        Ok(match self {
-            Self::None => GlobalValueEffect::None,
-            Self::Deleted => GlobalValueEffect::Deleted,
+    fn into_effect(self) -> Option<Op<ValueImpl>> {
+        match self {
+            Self::None => None,
+            Self::Deleted => Some(Op::Delete),
             Self::Fresh { fields } => {
-                GlobalValueEffect::Changed(ValueImpl::Container(Container::Struct(fields)))
+                Some(Op::New(ValueImpl::Container(Container::Struct(fields))))
             }
             Self::Cached { fields, status } => match &*status.borrow() {
                 GlobalDataStatus::Dirty => {
-                    GlobalValueEffect::Changed(ValueImpl::Container(Container::Struct(fields)))
+                    Some(Op::Modify(ValueImpl::Container(Container::Struct(fields))))
                 }
-                GlobalDataStatus::Clean => GlobalValueEffect::None,
+                GlobalDataStatus::Clean => None,
+            },
+        }
+    }
+
+    fn copy_value(&self) -> PartialVMResult<Self> {
+        let copy_fields = |fields: &Rc<RefCell<Vec<ValueImpl>>>| {
+            Ok(Rc::new(RefCell::new(
+                fields
+                    .borrow()
+                    .iter()
+                    .map(|v| v.copy_value())
+                    .collect::<PartialVMResult<_>>()?,
+            )))
+        };
+
+        Ok(match self {
+            Self::None => Self::None,
+            Self::Deleted => Self::Deleted,
+            Self::Fresh { fields } => Self::Fresh {
+                fields: copy_fields(fields)?,
+            },
+            Self::Cached { fields, status } => Self::Cached {
+                fields: copy_fields(fields)?,
+                status: Rc::new(RefCell::new(*status.borrow())),
             },
         })
     }
@@ -2054,17 +2332,21 @@ impl GlobalValue {
         self.0.exists()
     }
 
-    pub fn into_effect(self) -> PartialVMResult<GlobalValueEffect<Value>> {
-        Ok(match self.0.into_effect()? {
-            GlobalValueEffect::None => GlobalValueEffect::None,
-            GlobalValueEffect::Deleted => GlobalValueEffect::Deleted,
-            GlobalValueEffect::Changed(v) => GlobalValueEffect::Changed(Value(v)),
-        })
+    /// Returns the operation to perform on storage to reflect the changes to this slot, or `None`
+    /// if it is unchanged.
+    pub fn into_effect(self) -> PartialVMResult<Option<Op<Value>>> {
+        Ok(self.0.into_effect().map(|op| op.map(Value)))
     }
 
     pub fn is_mutated(&self) -> bool {
         self.0.is_mutated()
     }
+
+    /// Makes a deep copy of this slot, including whether its resource has been altered. The copy
+    /// does not share any data with this slot, so changes to one are not seen by the other.
+    pub fn copy_value(&self) -> PartialVMResult<Self> {
+        Ok(Self(self.0.copy_value()?))
+    }
 }
 
 /***************************************************************************************
@@ -2628,6 +2910,159 @@ impl Value {
     }
 }
 
+/***************************************************************************************
+*
+* Move Values
+*
+*   Conversion of runtime values to and from `MoveValue`s, e.g., for natives to inspect or
+*   build structs without a serialization round trip. As for serialization, the layout of
+*   the value is needed, since it cannot be reconstructed from the value.
+*
+**************************************************************************************/
+
+fn move_value_conversion_error(val: impl Debug, layout: &MoveTypeLayout) -> PartialVMError {
+    PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR)
+        .with_message(format!("cannot convert {:?} with layout {:?}", val, layout))
+}
+
+impl ValueImpl {
+    fn to_move_value(&self, layout: &MoveTypeLayout) -> PartialVMResult<MoveValue> {
+        use MoveTypeLayout as L;
+
+        Ok(match (layout, self) {
+            (L::U8, ValueImpl::U8(x)) => MoveValue::U8(*x),
+            (L::U64, ValueImpl::U64(x)) => MoveValue::U64(*x),
+            (L::U128, ValueImpl::U128(x)) => MoveValue::U128(*x),
+            (L::Bool, ValueImpl::Bool(x)) => MoveValue::Bool(*x),
+            (L::Address, ValueImpl::Address(x)) => MoveValue::Address(*x),
+
+            (L::Signer, ValueImpl::Container(Container::Struct(r))) => {
+                match r.borrow().as_slice() {
+                    [ValueImpl::Address(x)] => MoveValue::Signer(*x),
+                    _ => return Err(move_value_conversion_error(self, layout)),
+                }
+            }
+
+            (L::Struct(struct_layout), ValueImpl::Container(Container::Struct(r))) => {
+                let fields = r.borrow();
+                if fields.len() != struct_layout.fields().len() {
+                    return Err(move_value_conversion_error(self, layout));
+                }
+                MoveValue::Struct(MoveStruct::new(
+                    fields
+                        .iter()
+                        .zip(struct_layout.fields())
+                        .map(|(field, field_layout)| field.to_move_value(field_layout))
+                        .collect::<PartialVMResult<_>>()?,
+                ))
+            }
+
+            (L::Vector(elem_layout), ValueImpl::Container(c)) => {
+                MoveValue::Vector(match (elem_layout.as_ref(), c) {
+                    (L::U8, Container::VecU8(r)) => {
+                        r.borrow().iter().map(|x| MoveValue::U8(*x)).collect()
+                    }
+                    (L::U64, Container::VecU64(r)) => {
+                        r.borrow().iter().map(|x| MoveValue::U64(*x)).collect()
+                    }
+                    (L::U128, Container::VecU128(r)) => {
+                        r.borrow().iter().map(|x| MoveValue::U128(*x)).collect()
+                    }
+                    (L::Bool, Container::VecBool(r)) => {
+                        r.borrow().iter().map(|x| MoveValue::Bool(*x)).collect()
+                    }
+                    (L::Address, Container::VecAddress(r)) => {
+                        r.borrow().iter().map(|x| MoveValue::Address(*x)).collect()
+                    }
+                    (_, Container::Vec(r)) => r
+                        .borrow()
+                        .iter()
+                        .map(|elem| elem.to_move_value(elem_layout))
+                        .collect::<PartialVMResult<_>>()?,
+                    _ => return Err(move_value_conversion_error(self, layout)),
+                })
+            }
+
+            _ => return Err(move_value_conversion_error(self, layout)),
+        })
+    }
+
+    fn from_move_value(val: &MoveValue, layout: &MoveTypeLayout) -> PartialVMResult<Self> {
+        use MoveTypeLayout as L;
+
+        Ok(match (layout, val) {
+            (L::U8, MoveValue::U8(x)) => ValueImpl::U8(*x),
+            (L::U64, MoveValue::U64(x)) => ValueImpl::U64(*x),
+            (L::U128, MoveValue::U128(x)) => ValueImpl::U128(*x),
+            (L::Bool, MoveValue::Bool(x)) => ValueImpl::Bool(*x),
+            (L::Address, MoveValue::Address(x)) => ValueImpl::Address(*x),
+            (L::Signer, MoveValue::Signer(x)) => ValueImpl::Container(Container::signer(*x)),
+
+            (L::Struct(struct_layout), MoveValue::Struct(s)) => {
+                let fields = s.fields();
+                if fields.len() != struct_layout.fields().len() {
+                    return Err(move_value_conversion_error(val, layout));
+                }
+                ValueImpl::Container(Container::Struct(Rc::new(RefCell::new(
+                    fields
+                        .iter()
+                        .zip(struct_layout.fields())
+                        .map(|(field, field_layout)| Self::from_move_value(field, field_layout))
+                        .collect::<PartialVMResult<_>>()?,
+                ))))
+            }
+
+            (L::Vector(elem_layout), MoveValue::Vector(elems)) => {
+                macro_rules! specialized_vec {
+                    ($container:ident, $variant:ident) => {
+                        Container::$container(Rc::new(RefCell::new(
+                            elems
+                                .iter()
+                                .map(|elem| match elem {
+                                    MoveValue::$variant(x) => Ok(*x),
+                                    _ => Err(move_value_conversion_error(val, layout)),
+                                })
+                                .collect::<PartialVMResult<_>>()?,
+                        )))
+                    };
+                }
+
+                ValueImpl::Container(match elem_layout.as_ref() {
+                    L::U8 => specialized_vec!(VecU8, U8),
+                    L::U64 => specialized_vec!(VecU64, U64),
+                    L::U128 => specialized_vec!(VecU128, U128),
+                    L::Bool => specialized_vec!(VecBool, Bool),
+                    L::Address => specialized_vec!(VecAddress, Address),
+                    L::Signer | L::Vector(_) | L::Struct(_) => {
+                        Container::Vec(Rc::new(RefCell::new(
+                            elems
+                                .iter()
+                                .map(|elem| Self::from_move_value(elem, elem_layout))
+                                .collect::<PartialVMResult<_>>()?,
+                        )))
+                    }
+                })
+            }
+
+            _ => return Err(move_value_conversion_error(val, layout)),
+        })
+    }
+}
+
+impl Value {
+    /// Converts the value into a `MoveValue` with the given layout. Fails with
+    /// `INTERNAL_TYPE_ERROR` if the value does not have the layout, e.g., for references.
+    pub fn to_move_value(&self, layout: &MoveTypeLayout) -> PartialVMResult<MoveValue> {
+        self.0.to_move_value(layout)
+    }
+
+    /// Creates a value from a `MoveValue` with the given layout. Fails with `INTERNAL_TYPE_ERROR`
+    /// if the `MoveValue` does not have the layout.
+    pub fn from_move_value(val: &MoveValue, layout: &MoveTypeLayout) -> PartialVMResult<Value> {
+        Ok(Value(ValueImpl::from_move_value(val, layout)?))
+    }
+}
+
 /***************************************************************************************
  *
  * Prop Testing
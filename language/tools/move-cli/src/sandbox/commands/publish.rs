@@ -125,7 +125,7 @@ pub fn publish(
             }
             let modules: Vec<_> = changeset
                 .into_modules()
-                .map(|(module_id, blob_opt)| (module_id, blob_opt.expect("must be non-deletion")))
+                .map(|(module_id, op)| (module_id, op.ok().expect("must be non-deletion")))
                 .collect();
             state.save_modules(&modules)?;
         }
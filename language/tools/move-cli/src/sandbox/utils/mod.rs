@@ -21,7 +21,7 @@ use move_compiler::{
 };
 use move_core_types::{
     account_address::AccountAddress,
-    effects::{ChangeSet, Event},
+    effects::{ChangeSet, Event, Op},
     errmap::ErrorMapping,
     gas_schedule::{GasAlgebra, GasUnits},
     language_storage::{ModuleId, TypeTag},
@@ -74,8 +74,8 @@ pub(crate) fn explain_publish_changeset(changeset: &ChangeSet, state: &OnDiskSta
     assert!(changeset.resources().next().is_none());
     // total bytes written across all accounts
     let mut total_bytes_written = 0;
-    for (addr, name, blob_opt) in changeset.modules() {
-        if let Some(module_bytes) = blob_opt {
+    for (addr, name, op) in changeset.modules() {
+        if let Some(module_bytes) = op.ok() {
             let bytes_written = addr.len() + name.len() + module_bytes.len();
             total_bytes_written += bytes_written;
             let module_id = ModuleId::new(addr, name.clone());
@@ -181,11 +181,11 @@ pub(crate) fn explain_execution_effects(
             account.resources().len(),
             addr
         );
-        for (struct_tag, write_opt) in account.resources() {
+        for (struct_tag, op) in account.resources() {
             print!("    ");
             let mut bytes_to_write = struct_tag.access_vector().len();
-            match write_opt {
-                Some(blob) => {
+            match op {
+                Op::New(blob) | Op::Modify(blob) => {
                     bytes_to_write += blob.len();
                     if state
                         .get_resource_bytes(*addr, struct_tag.clone())?
@@ -216,7 +216,7 @@ pub(crate) fn explain_execution_effects(
                         print_struct_with_indent(&resource, 6)
                     }
                 }
-                None => {
+                Op::Delete => {
                     println!(
                         "Deleted type {} (wrote {:?} bytes)",
                         struct_tag, bytes_to_write
@@ -254,10 +254,12 @@ pub(crate) fn maybe_commit_effects(
     // shouldn't contain modules
     if commit {
         for (addr, account) in changeset.into_inner() {
-            for (struct_tag, blob_opt) in account.into_resources() {
-                match blob_opt {
-                    Some(blob) => state.save_resource(addr, struct_tag, &blob)?,
-                    None => state.delete_resource(addr, struct_tag)?,
+            for (struct_tag, op) in account.into_resources() {
+                match op {
+                    Op::New(blob) | Op::Modify(blob) => {
+                        state.save_resource(addr, struct_tag, &blob)?
+                    }
+                    Op::Delete => state.delete_resource(addr, struct_tag)?,
                 }
             }
         }
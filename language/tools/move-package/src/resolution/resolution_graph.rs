@@ -6,7 +6,7 @@ use crate::{
     resolution::digest::compute_digest,
     source_package::{
         layout::SourcePackageLayout,
-        manifest_parser::{parse_move_manifest_string, parse_source_manifest},
+        manifest_parser::{parse_address_spans, parse_move_manifest_string, parse_source_manifest},
         parsed_manifest::{
             Dependency, FileName, NamedAddress, PackageDigest, PackageName, SourceManifest,
             SubstOrRename,
@@ -24,6 +24,7 @@ use std::{
     cell::RefCell,
     collections::{BTreeMap, BTreeSet},
     fs,
+    ops::Range,
     path::{Path, PathBuf},
     process::Command,
     rc::Rc,
@@ -42,6 +43,15 @@ type ResolvingTable = ResolutionTable<ResolvingNamedAddress>;
 type ResolvingGraph = ResolutionGraph<ResolvingNamedAddress>;
 type ResolvingPackage = ResolutionPackage<ResolvingNamedAddress>;
 
+/// Location of the declaration of a named address in the `[addresses]` section of a package
+/// manifest
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AddressDeclaration {
+    pub manifest_path: PathBuf,
+    /// Byte offsets of the address name in the manifest
+    pub span: Range<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ResolvingNamedAddress {
     value: Rc<RefCell<Option<AccountAddress>>>,
@@ -84,6 +94,9 @@ pub struct ResolutionPackage<T> {
     pub renaming: Renaming,
     /// The mapping of addresses for this package (and that are in scope for it)
     pub resolution_table: ResolutionTable<T>,
+    /// Declarations of addresses in scope for this package, either in its own manifest or in the
+    /// manifest of the dependency that introduced them (under their original names if renamed)
+    pub address_declarations: BTreeMap<NamedAddress, AddressDeclaration>,
     /// The digest of the contents of all source files and manifest under the package root
     pub source_digest: PackageDigest,
 }
@@ -138,6 +151,7 @@ impl ResolvingGraph {
                     package_path,
                     renaming,
                     resolution_table,
+                    address_declarations,
                     source_digest,
                 } = package;
 
@@ -162,6 +176,7 @@ impl ResolvingGraph {
                     package_path,
                     renaming,
                     resolution_table: resolved_table,
+                    address_declarations,
                     source_digest,
                 };
                 (name, resolved_pkg)
@@ -210,6 +225,7 @@ impl ResolvingGraph {
         };
 
         let mut renaming = BTreeMap::new();
+        let mut address_declarations = BTreeMap::new();
         let mut resolution_table = self
             .build_options
             .additional_named_addresses
@@ -244,7 +260,7 @@ impl ResolvingGraph {
             })?;
             self.graph.add_edge(package_node_id, dep_node_id, ());
 
-            let (dep_renaming, dep_resolution_table) = self
+            let (dep_renaming, dep_resolution_table, dep_address_declarations) = self
                 .process_dependency(dep_name, dep, package_path.clone())
                 .with_context(|| {
                     format!(
@@ -273,10 +289,31 @@ impl ResolvingGraph {
                     dep_name, package_name
                 )
             })?;
+
+            for (name, declaration) in dep_address_declarations {
+                address_declarations.entry(name).or_insert(declaration);
+            }
         }
 
         self.unify_addresses_in_package(&package, &mut resolution_table, is_root_package)?;
 
+        // addresses declared in the package's own manifest take precedence over those declared in
+        // its dependencies (locations of declarations are only informative, so a manifest that
+        // cannot be read again does not fail the resolution)
+        let manifest_path = package_path.join(SourcePackageLayout::Manifest.path());
+        if let Ok(spans) = fs::read_to_string(&manifest_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| parse_address_spans(&contents))
+        {
+            for (name, span) in spans {
+                let declaration = AddressDeclaration {
+                    manifest_path: manifest_path.clone(),
+                    span,
+                };
+                address_declarations.insert(name, declaration);
+            }
+        }
+
         let source_digest =
             ResolvingPackage::get_package_digest_for_config(&package_path, &self.build_options)?;
 
@@ -286,6 +323,7 @@ impl ResolvingGraph {
             package_path,
             renaming,
             resolution_table,
+            address_declarations,
             source_digest,
         };
 
@@ -391,7 +429,11 @@ impl ResolvingGraph {
         dep_name_in_pkg: PackageName,
         dep: Dependency,
         root_path: PathBuf,
-    ) -> Result<(Renaming, ResolvingTable)> {
+    ) -> Result<(
+        Renaming,
+        ResolvingTable,
+        BTreeMap<NamedAddress, AddressDeclaration>,
+    )> {
         Self::download_and_update_if_repo(dep_name_in_pkg, &dep)?;
         let (dep_package, dep_package_dir) =
             Self::parse_package_manifest(&dep, &dep_name_in_pkg, root_path)
@@ -429,6 +471,7 @@ impl ResolvingGraph {
         let resolving_dep = &self.package_table[&dep_name_in_pkg];
         let mut renaming = BTreeMap::new();
         let mut resolution_table = resolving_dep.resolution_table.clone();
+        let mut address_declarations = resolving_dep.address_declarations.clone();
 
         // check that address being renamed exists in the dep that is being renamed/imported
         if let Some(dep_subst) = dep.subst {
@@ -449,6 +492,9 @@ impl ResolvingGraph {
                         if let Some(other_val) = resolution_table.remove(&ident) {
                             resolution_table.insert(name, other_val);
                         }
+                        if let Some(declaration) = address_declarations.remove(&ident) {
+                            address_declarations.insert(name, declaration);
+                        }
 
                         if renaming.insert(name, (dep_name_in_pkg, ident)).is_some() {
                             bail!("Duplicate renaming of named address '{0}' found for dependency {1}",
@@ -473,7 +519,7 @@ impl ResolvingGraph {
             }
         }
 
-        Ok((renaming, resolution_table))
+        Ok((renaming, resolution_table, address_declarations))
     }
 
     fn get_or_add_node(&mut self, package_name: PackageName) -> Result<GraphIndex> {
@@ -6,11 +6,13 @@ use crate::{source_package::parsed_manifest as PM, Architecture};
 use anyhow::{bail, format_err, Context, Result};
 use move_core_types::account_address::{AccountAddress, AccountAddressParseError};
 use move_symbol_pool::symbol::Symbol;
+use serde::Deserialize;
 use std::{
     collections::{BTreeMap, BTreeSet},
+    ops::Range,
     path::{Path, PathBuf},
 };
-use toml::Value as TV;
+use toml::{Spanned, Value as TV};
 
 use super::layout::SourcePackageLayout;
 
@@ -34,6 +36,20 @@ const KNOWN_NAMES: &[&str] = &[
 
 const REQUIRED_FIELDS: &[&str] = &[PACKAGE_NAME];
 
+/// Returns the directory where git dependencies are downloaded to: MOVE_HOME if it has been set,
+/// otherwise `.move` in the user's home directory
+pub fn move_home() -> String {
+    std::env::var("MOVE_HOME").unwrap_or_else(|_| {
+        format!(
+            "{}/.move",
+            dirs_next::home_dir()
+                .expect("user's home directory not found")
+                .to_str()
+                .unwrap()
+        )
+    })
+}
+
 pub fn parse_move_manifest_from_file(path: &Path) -> Result<PM::SourceManifest> {
     let file_contents = if path.is_file() {
         std::fs::read_to_string(path)?
@@ -179,6 +195,37 @@ pub fn parse_dependencies(tval: TV) -> Result<PM::Dependencies> {
     }
 }
 
+/// Returns spans (byte offsets in the manifest) of the `local` paths or `git` URLs of dependencies
+/// and dev-dependencies declared in a manifest. The sections themselves are validated by
+/// `parse_dependencies`.
+pub fn parse_dependency_spans(
+    manifest_string: &str,
+) -> Result<Vec<(PM::PackageName, Range<usize>)>> {
+    #[derive(Deserialize)]
+    struct DependencySpan {
+        local: Option<Spanned<String>>,
+        git: Option<Spanned<String>>,
+    }
+    #[derive(Deserialize)]
+    struct DependencySpans {
+        #[serde(default)]
+        dependencies: BTreeMap<String, DependencySpan>,
+        #[serde(default, rename = "dev-dependencies")]
+        dev_dependencies: BTreeMap<String, DependencySpan>,
+    }
+    let spans = toml::from_str::<DependencySpans>(manifest_string)
+        .context("Unable to parse Move package manifest")?;
+    Ok(spans
+        .dependencies
+        .into_iter()
+        .chain(spans.dev_dependencies)
+        .filter_map(|(dep_name, dep)| {
+            let path = dep.local.or(dep.git)?;
+            Some((PM::PackageName::from(dep_name), path.start()..path.end()))
+        })
+        .collect())
+}
+
 pub fn parse_build_info(tval: TV) -> Result<PM::BuildInfo> {
     match tval {
         TV::Table(mut table) => {
@@ -241,6 +288,28 @@ pub fn parse_addresses(tval: TV) -> Result<PM::AddressDeclarations> {
     }
 }
 
+/// Returns spans (byte offsets in the manifest) of names of addresses declared in the
+/// `[addresses]` section of a manifest. The section itself is validated by `parse_addresses`.
+pub fn parse_address_spans(
+    manifest_string: &str,
+) -> Result<BTreeMap<PM::NamedAddress, Range<usize>>> {
+    #[derive(Deserialize)]
+    struct AddressSpans {
+        #[serde(default)]
+        addresses: BTreeMap<Spanned<String>, TV>,
+    }
+    let spans = toml::from_str::<AddressSpans>(manifest_string)
+        .context("Unable to parse Move package manifest")?;
+    Ok(spans
+        .addresses
+        .into_keys()
+        .map(|name| {
+            let span = name.start()..name.end();
+            (PM::NamedAddress::from(name.into_inner()), span)
+        })
+        .collect())
+}
+
 pub fn parse_dev_addresses(tval: TV) -> Result<PM::DevAddressDeclarations> {
     match tval {
         TV::Table(table) => {
@@ -327,16 +396,7 @@ fn parse_dependency(tval: TV) -> Result<PM::Dependency> {
                     })
                 }
                 (None, Some(git)) => {
-                    // Look to see if a MOVE_HOME has been set. Otherwise default to $HOME
-                    let move_home = std::env::var("MOVE_HOME").unwrap_or_else(|_| {
-                        format!(
-                            "{}/.move",
-                            dirs_next::home_dir()
-                                .expect("user's home directory not found")
-                                .to_str()
-                                .unwrap()
-                        )
-                    });
+                    let move_home = move_home();
                     let rev_name = match table.remove("rev") {
                         None => bail!("Git revision not supplied for dependency"),
                         Some(r) => Symbol::from(
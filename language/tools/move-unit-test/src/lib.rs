@@ -7,7 +7,7 @@ mod extensions;
 pub mod test_reporter;
 pub mod test_runner;
 
-use crate::test_runner::TestRunner;
+use crate::{test_reporter::TestStatistics, test_runner::TestRunner};
 use clap::*;
 use move_command_line_common::files::verify_and_create_named_address_mapping;
 use move_compiler::{
@@ -207,6 +207,22 @@ impl UnitTestingConfig {
         native_function_table: Option<NativeFunctionTable>,
         writer: W,
     ) -> Result<(W, bool)> {
+        let (writer, all_tests_passed, _) = self.run_and_report_unit_tests_with_statistics(
+            test_plan,
+            native_function_table,
+            writer,
+        )?;
+        Ok((writer, all_tests_passed))
+    }
+
+    /// Same as `run_and_report_unit_tests`, but additionally returns the statistics of each test
+    /// run (which are empty if tests are only listed).
+    pub fn run_and_report_unit_tests_with_statistics<W: Write + Send>(
+        &self,
+        test_plan: TestPlan,
+        native_function_table: Option<NativeFunctionTable>,
+        writer: W,
+    ) -> Result<(W, bool, TestStatistics)> {
         let shared_writer = Mutex::new(writer);
 
         if self.list {
@@ -220,7 +236,11 @@ impl UnitTestingConfig {
                     )?;
                 }
             }
-            return Ok((shared_writer.into_inner().unwrap(), true));
+            return Ok((
+                shared_writer.into_inner().unwrap(),
+                true,
+                TestStatistics::new(),
+            ));
         }
 
         writeln!(shared_writer.lock().unwrap(), "Running Move unit tests")?;
@@ -248,9 +268,10 @@ impl UnitTestingConfig {
             test_results.report_statistics(&shared_writer)?;
         }
 
+        let statistics = test_results.statistics().clone();
         let all_tests_passed = test_results.summarize(&shared_writer)?;
 
         let writer = shared_writer.into_inner().unwrap();
-        Ok((writer, all_tests_passed))
+        Ok((writer, all_tests_passed, statistics))
     }
 }
@@ -386,6 +386,16 @@ impl TestStatistics {
             .insert(test_info);
     }
 
+    /// Returns information about runs of tests that passed, keyed by their modules
+    pub fn passed(&self) -> &BTreeMap<ModuleId, BTreeSet<TestRunInfo>> {
+        &self.passed
+    }
+
+    /// Returns failures of tests, keyed by their modules
+    pub fn failed(&self) -> &BTreeMap<ModuleId, BTreeSet<TestFailure>> {
+        &self.failed
+    }
+
     pub fn combine(mut self, other: Self) -> Self {
         for (module_id, test_result) in other.passed {
             let entry = self.passed.entry(module_id).or_default();
@@ -407,6 +417,10 @@ impl TestResults {
         }
     }
 
+    pub fn statistics(&self) -> &TestStatistics {
+        &self.final_statistics
+    }
+
     pub fn report_statistics<W: Write>(&self, writer: &Mutex<W>) -> Result<()> {
         writeln!(writer.lock().unwrap(), "\nTest Statistics:\n")?;
 